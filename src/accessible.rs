@@ -0,0 +1,235 @@
+// Screen reader friendly output for --accessible / accessible = true.
+// Plain lines instead of boxes: section names as headers, no bars or icons, units and symbols
+// written out ("15 of 32 gigabytes"), tree lines numbered ("Display 1 of 2"). Works on the
+// finished sections, so the regular rendering doesn't know this exists.
+
+use crate::renderer::{Section, strip_escapes};
+
+// Glyphs that carry meaning, said as words. Any other icon is dropped.
+const GLYPH_WORDS: &[(char, &str)] = &[
+    ('󰂐', "charging"),
+    ('󰂍', "discharging"),
+    ('󰂃', "battery low"),
+    ('󰋊', "disk"),
+    ('󰈀', "network"),
+    ('↓', "down"),
+    ('↑', "up"),
+    ('·', ","),
+    ('→', "to"),
+    ('⚠', "warning"),
+];
+
+// Unit suffixes after a number, singular and plural
+const UNITS: &[(&str, &str, &str)] = &[
+    ("MB/s", "megabyte per second", "megabytes per second"),
+    ("KB", "kilobyte", "kilobytes"),
+    ("MB", "megabyte", "megabytes"),
+    ("GB", "gigabyte", "gigabytes"),
+    ("TB", "terabyte", "terabytes"),
+    ("PB", "petabyte", "petabytes"),
+    ("KiB", "kibibyte", "kibibytes"),
+    ("MiB", "mebibyte", "mebibytes"),
+    ("GiB", "gibibyte", "gibibytes"),
+    ("W", "watt", "watts"),
+    ("GHz", "gigahertz", "gigahertz"),
+    ("MHz", "megahertz", "megahertz"),
+    ("Hz", "hertz", "hertz"),
+    ("%", "percent", "percent"),
+    ("d", "day", "days"),
+    ("h", "hour", "hours"),
+    ("m", "minute", "minutes"),
+    ("s", "second", "seconds"),
+];
+
+// Lay out the sections as plain text, the greeting first and the footer last
+pub fn render(sections: &[Section], greeting: Option<&str>, footer: Option<&str>) -> String {
+    let mut output = String::new();
+    if let Some(greeting) = greeting {
+        output.push_str(&speak(&strip_escapes(greeting)));
+        output.push_str("\n\n");
+    }
+    for section in sections {
+        output.push_str(&section.title);
+        output.push('\n');
+        for line in section_lines(section) {
+            output.push_str(&line);
+            output.push('\n');
+        }
+        output.push('\n');
+    }
+    if let Some(footer) = footer {
+        output.push_str(&speak(footer));
+        output.push('\n');
+    }
+    output
+}
+
+// "Key: value" per line. Keys that show up more than once and tree lines get "i of n".
+fn section_lines(section: &Section) -> Vec<String> {
+    let lines = &section.lines;
+    let is_tree = |key: &str| key.starts_with('├') || key.starts_with('╰');
+    let mut result = Vec::new();
+    let mut i = 0;
+    while i < lines.len() {
+        let (key, value) = &lines[i];
+        let key = strip_escapes(key);
+        let children = lines[i + 1..].iter().take_while(|(key, _)| is_tree(key)).count();
+        let count = lines.iter().filter(|(other, _)| strip_escapes(other) == key).count();
+        let nth = lines[..i].iter().filter(|(other, _)| strip_escapes(other) == key).count() + 1;
+        let value = strip_escapes(value);
+
+        // A header line ("Displays") just introduces its children, they're named after it
+        let (name, children_name) = if value.trim().is_empty() && children > 0 {
+            (None, key.strip_suffix('s').unwrap_or(&key).to_string())
+        } else if count > 1 {
+            (Some(format!("{} {} of {}", key, nth, count)), format!("{} detail", key))
+        } else {
+            (Some(key.clone()), format!("{} detail", key))
+        };
+        if let Some(name) = name {
+            result.push(format!("{}: {}", name, phrase(&key, &value)));
+        }
+        for (n, (_, child)) in lines[i + 1..i + 1 + children].iter().enumerate() {
+            let child = speak(&strip_escapes(child));
+            result.push(format!("{} {} of {}: {}", children_name, n + 1, children, child));
+        }
+        i += 1 + children;
+    }
+    result
+}
+
+// A module's value as a sentence. Memory and storage lead with how full they are.
+fn phrase(key: &str, value: &str) -> String {
+    let spoken = speak(value);
+    if !matches!(key, "Memory" | "Storage") {
+        return spoken;
+    }
+    match percent_used(value) {
+        Some(percent) => format!("{} percent used, {}", percent, spoken),
+        None => spoken,
+    }
+}
+
+// How full "15.2GB/32GB" is, from the first used/total pair in the value
+fn percent_used(value: &str) -> Option<u64> {
+    value.split_whitespace().find_map(|word| {
+        let (used, total) = word.split_once('/')?;
+        let (used, total) = (bytes(quantity(used)?)?, bytes(quantity(total)?)?);
+        (total > 0.0).then(|| (used / total * 100.0).round() as u64)
+    })
+}
+
+// A quantity in one of the byte units, in bytes
+fn bytes((number, unit): (&str, &str)) -> Option<f64> {
+    let scale = match unit {
+        "KB" => 1e3,
+        "MB" => 1e6,
+        "GB" => 1e9,
+        "TB" => 1e12,
+        "PB" => 1e15,
+        "KiB" => 1024.0,
+        "MiB" => 1024.0 * 1024.0,
+        "GiB" => 1024.0 * 1024.0 * 1024.0,
+        _ => return None,
+    };
+    Some(number.parse::<f64>().ok()? * scale)
+}
+
+// Text with bars and icons taken out and units, fractions and symbols written out
+pub fn speak(text: &str) -> String {
+    let mut words: Vec<String> = Vec::new();
+    for word in without_bars(text).split_whitespace() {
+        let word: String = word
+            .chars()
+            .filter(|&c| !is_icon(c) || GLYPH_WORDS.iter().any(|(glyph, _)| *glyph == c))
+            // Block bars, "████░░░░░░"
+            .filter(|&c| !matches!(c, '█' | '░'))
+            .collect();
+        if word.is_empty() {
+            continue;
+        }
+        if let Some(spoken) = GLYPH_WORDS.iter().find(|(glyph, _)| word == glyph.to_string()) {
+            words.push(spoken.1.to_string());
+            continue;
+        }
+        words.push(speak_word(&word));
+    }
+    // "disk 12 megabytes per second , network" -> "disk 12 megabytes per second, network"
+    words.join(" ").replace(" ,", ",")
+}
+
+// One word: "15GB/32GB" -> "15 of 32 gigabytes", "+1.2GB" -> "up 1.2 gigabytes", "41m" ->
+// "41 minutes". Anything else stays as it is.
+fn speak_word(word: &str) -> String {
+    // Brackets and trailing punctuation go around the spoken form, "~" is "about"
+    let start = word.find(|c: char| !matches!(c, '(' | '[' | '~')).unwrap_or(word.len());
+    let end = word
+        .char_indices()
+        .rev()
+        .find(|&(_, c)| !matches!(c, ')' | ']' | ',' | ':'))
+        .map_or(start, |(i, c)| (i + c.len_utf8()).max(start));
+    let (open, core, close) = (&word[..start], &word[start..end], &word[end..]);
+
+    let spoken = if let Some((used, total)) = core.split_once('/')
+        && let (Some((used, used_unit)), Some((total, total_unit))) =
+            (quantity(used), quantity(total))
+    {
+        if used_unit == total_unit {
+            format!("{} of {} {}", used, total, unit_name(total_unit, total))
+        } else {
+            let used = format!("{} {}", used, unit_name(used_unit, used));
+            format!("{} of {} {}", used, total, unit_name(total_unit, total))
+        }
+    } else if let Some((number, unit)) = quantity(core.trim_start_matches(['+', '-'])) {
+        let sign = match core.chars().next() {
+            Some('+') => "up ",
+            Some('-') => "down ",
+            _ => "",
+        };
+        format!("{}{} {}", sign, number, unit_name(unit, number))
+    } else {
+        core.to_string()
+    };
+    format!("{}{}{}", open.replace('~', "about "), spoken, close)
+}
+
+// "32GB" -> ("32", "GB"), only for units in UNITS
+fn quantity(text: &str) -> Option<(&str, &str)> {
+    let split = text.find(|c: char| !c.is_ascii_digit() && c != '.')?;
+    let (number, unit) = text.split_at(split);
+    let known = UNITS.iter().any(|(suffix, ..)| *suffix == unit);
+    (!number.is_empty() && known).then_some((number, unit))
+}
+
+fn unit_name(unit: &str, number: &str) -> &'static str {
+    let Some(&(_, singular, plural)) = UNITS.iter().find(|(suffix, ..)| *suffix == unit) else {
+        return "";
+    };
+    if number == "1" { singular } else { plural }
+}
+
+// Take out the plain bars ("[====      ]"), nerd font bars are icons and go with them
+fn without_bars(text: &str) -> String {
+    let mut result = String::with_capacity(text.len());
+    let mut rest = text;
+    while let Some(start) = rest.find('[') {
+        let after = &rest[start + 1..];
+        match after.find(']') {
+            Some(end) if end > 0 && after[..end].chars().all(|c| c == '=' || c == ' ') => {
+                result.push_str(&rest[..start]);
+                rest = &after[end + 1..];
+            }
+            _ => {
+                result.push_str(&rest[..start + 1]);
+                rest = after;
+            }
+        }
+    }
+    result.push_str(rest);
+    result
+}
+
+// Nerd font icons live in the private use areas
+fn is_icon(c: char) -> bool {
+    matches!(c, '\u{E000}'..='\u{F8FF}' | '\u{F0000}'..='\u{FFFFD}' | '\u{100000}'..='\u{10FFFD}')
+}
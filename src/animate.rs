@@ -0,0 +1,73 @@
+// --animate: the art's colors cycle for a few seconds after the fetch is printed, then it
+// settles back into the normal colors. A key press (Ctrl-C included) stops it early.
+// Only the art's own cells are redrawn: the cursor is saved below the layout and every art
+// line is written from there, moved up and over to where the layout put it. The sections
+// box is never touched, so the values stay selectable and nothing flickers.
+
+use std::io::{Read, Write};
+use std::time::Duration;
+
+use crate::colorcontrol::RESET;
+use crate::modules::asciimodule::ArtSet;
+use crate::renderer::ArtPlacement;
+use crate::terminalsize::get_terminal_size;
+use crate::termquery::RawMode;
+
+const FRAME: Duration = Duration::from_millis(100);
+const FRAMES: usize = 30;
+
+// Gives the cursor back however the animation ends
+struct HiddenCursor;
+
+impl HiddenCursor {
+    fn hide() -> HiddenCursor {
+        print!("\x1b[?25l");
+        HiddenCursor
+    }
+}
+
+impl Drop for HiddenCursor {
+    fn drop(&mut self) {
+        print!("\x1b[?25h");
+        let _ = std::io::stdout().flush();
+    }
+}
+
+// Animate the art of a layout that was just printed, `height` rows with the cursor right
+// under it. Does nothing without a terminal to take keys from, or when the top of the layout
+// has scrolled out of view (there's no moving the cursor up to it).
+pub fn run(art: &ArtSet, placement: ArtPlacement, height: usize) {
+    let fits = get_terminal_size().is_some_and(|(_, rows)| height < usize::from(rows));
+    if !fits {
+        return;
+    }
+    let Some(mut raw) = RawMode::enter(true) else {
+        return;
+    };
+    let _cursor = HiddenCursor::hide();
+    print!("\x1b7");
+
+    for frame in 1..FRAMES {
+        draw_frame(&art.shifted(placement.size, frame), placement, height);
+        if raw.wait_for_input(FRAME) {
+            // Swallow the key so it doesn't end up on the prompt
+            let _ = raw.tty().read(&mut [0u8; 64]);
+            break;
+        }
+    }
+    // Shifted by nothing is the art as it was printed
+    draw_frame(&art.shifted(placement.size, 0), placement, height);
+}
+
+// Write the art lines where the layout has them and go back below the layout
+fn draw_frame(lines: &[String], placement: ArtPlacement, height: usize) {
+    let mut frame = String::new();
+    for (i, line) in lines.iter().enumerate() {
+        let up = height - (placement.row + i);
+        let column = placement.column + 1;
+        frame.push_str(&format!("\x1b8\x1b[{}A\x1b[{}G{}{}", up, column, line, RESET));
+    }
+    frame.push_str("\x1b8");
+    print!("{}", frame);
+    let _ = std::io::stdout().flush();
+}
@@ -0,0 +1,45 @@
+// Terminal background detection for the contrast check.
+// Asks the terminal for its background color (OSC 11), falling back to COLORFGBG.
+
+use std::time::Duration;
+
+use crate::termquery;
+
+// Terminals answer in a few ms, over ssh it's a round trip. Past this we go without.
+const QUERY_TIMEOUT: Duration = Duration::from_millis(150);
+
+// The terminal's background color, None when it can't be found out
+pub fn detect() -> Option<(u8, u8, u8)> {
+    query_background().or_else(background_from_colorfgbg)
+}
+
+// Ask with OSC 11, terminals that don't know it just answer the DA1 behind it
+fn query_background() -> Option<(u8, u8, u8)> {
+    parse_osc11(&termquery::query(b"\x1b]11;?\x1b\\", QUERY_TIMEOUT)?)
+}
+
+// "\x1b]11;rgb:1e1e/1e1e/2e2e\x1b\\" (or BEL terminated) -> (0x1e, 0x1e, 0x2e).
+// Components have 1-4 hex digits, scaled down to 8 bits.
+fn parse_osc11(reply: &[u8]) -> Option<(u8, u8, u8)> {
+    let start = memchr::memmem::find(reply, b"]11;rgb:")? + b"]11;rgb:".len();
+    let rest = std::str::from_utf8(&reply[start..]).ok()?;
+    let end = rest.find(['\x1b', '\x07'])?;
+
+    let mut channels = rest[..end].split('/').map(|hex| {
+        let value = u32::from_str_radix(hex, 16).ok()?;
+        let max = (1u32 << (4 * hex.len().clamp(1, 4))) - 1;
+        Some((value * 255 / max) as u8)
+    });
+    Some((channels.next()??, channels.next()??, channels.next()??))
+}
+
+// COLORFGBG="15;0" (foreground;background as ANSI indexes), set by rxvt and Konsole.
+// Only says dark or light, so it maps to black or white.
+fn background_from_colorfgbg() -> Option<(u8, u8, u8)> {
+    let colorfgbg = std::env::var("COLORFGBG").ok()?;
+    let background: u8 = colorfgbg.rsplit(';').next()?.parse().ok()?;
+    match background {
+        7 | 15 => Some((255, 255, 255)),
+        _ => Some((0, 0, 0)),
+    }
+}
@@ -0,0 +1,121 @@
+// Section balancing for Slowfetch.
+// With lots of modules on, Hardware ends up much taller than Core and Userspace.
+// balance_sections splits a section like that into two boxes of about equal height,
+// after all modules are collected and before anything is drawn.
+
+use crate::renderer::Section;
+
+// Sections shorter than this are never split, two tiny boxes look worse than one
+const MIN_SPLIT_LINES: usize = 8;
+
+// Split the tallest section in two when it's over half again as tall as the next tallest.
+// The second box is titled `second_title`, or "<title> (cont.)" without one.
+pub fn balance_sections(sections: &mut Vec<Section>, second_title: Option<&str>) {
+    let Some(tallest) = (0..sections.len()).max_by_key(|&i| sections[i].lines.len()) else {
+        return;
+    };
+    let height = sections[tallest].lines.len();
+    let others = sections
+        .iter()
+        .enumerate()
+        .filter(|&(i, _)| i != tallest)
+        .map(|(_, section)| section.lines.len())
+        .max()
+        .unwrap_or(0);
+    if height < MIN_SPLIT_LINES || height * 2 <= others * 3 {
+        return;
+    }
+
+    let Some(at) = split_point(&sections[tallest].lines) else {
+        return;
+    };
+    let section = &mut sections[tallest];
+    let rest = section.lines.split_off(at);
+    let title = match second_title {
+        Some(title) => title.to_string(),
+        None => format!("{} (cont.)", section.title),
+    };
+    sections.insert(tallest + 1, Section::new(&title, rest));
+}
+
+// Where to cut the lines closest to the middle without leaving ├─/╰─ children
+// apart from their parent. None when the whole section is one group.
+fn split_point(lines: &[(String, String)]) -> Option<usize> {
+    let middle = lines.len() / 2;
+    (1..lines.len())
+        .filter(|&i| !is_tree_child(&lines[i].0))
+        .min_by_key(|&i| i.abs_diff(middle))
+}
+
+fn is_tree_child(key: &str) -> bool {
+    key.starts_with('├') || key.starts_with('╰')
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    // A section of `keys`, every value "x"
+    fn section(title: &str, keys: &[&str]) -> Section {
+        Section::new(title, keys.iter().map(|key| (key.to_string(), "x".to_string())).collect())
+    }
+
+    // A plain section of `count` lines
+    fn lines(title: &str, count: usize) -> Section {
+        let keys: Vec<String> = (0..count).map(|i| format!("{}{}", title, i)).collect();
+        section(title, &keys.iter().map(String::as_str).collect::<Vec<_>>())
+    }
+
+    fn shape(sections: &[Section]) -> Vec<(&str, usize)> {
+        sections.iter().map(|section| (section.title.as_str(), section.lines.len())).collect()
+    }
+
+    #[test]
+    fn tall_sections_split_in_half() {
+        let mut sections = vec![lines("Core", 4), lines("Hardware", 14), lines("Userspace", 6)];
+        balance_sections(&mut sections, None);
+        assert_eq!(
+            shape(&sections),
+            [("Core", 4), ("Hardware", 7), ("Hardware (cont.)", 7), ("Userspace", 6)]
+        );
+        // Nothing lost or reordered
+        assert_eq!(sections[2].lines[0].0, "Hardware7");
+
+        let mut sections = vec![lines("Core", 3), lines("Hardware", 9)];
+        balance_sections(&mut sections, Some("Sensors"));
+        assert_eq!(shape(&sections), [("Core", 3), ("Hardware", 4), ("Sensors", 5)]);
+    }
+
+    #[test]
+    fn balanced_or_small_layouts_stay() {
+        // Not half again as tall as the next one
+        let mut sections = vec![lines("Core", 8), lines("Hardware", 12), lines("Userspace", 5)];
+        balance_sections(&mut sections, None);
+        assert_eq!(shape(&sections), [("Core", 8), ("Hardware", 12), ("Userspace", 5)]);
+        // Tall next to the others but too short to be worth two boxes
+        let mut sections = vec![lines("Core", 1), lines("Hardware", 7)];
+        balance_sections(&mut sections, None);
+        assert_eq!(shape(&sections), [("Core", 1), ("Hardware", 7)]);
+
+        let mut sections = Vec::new();
+        balance_sections(&mut sections, None);
+        assert!(sections.is_empty());
+    }
+
+    #[test]
+    fn tree_groups_stay_together() {
+        // The middle falls inside the Displays tree, the cut moves to the nearest group edge
+        let keys = ["CPU", "GPU", "Memory", "Displays", "├─", "├─", "├─", "╰─", "Battery", "Audio"];
+        let mut sections = vec![lines("Core", 3), section("Hardware", &keys)];
+        balance_sections(&mut sections, None);
+        assert_eq!(shape(&sections), [("Core", 3), ("Hardware", 3), ("Hardware (cont.)", 7)]);
+        assert_eq!(sections[2].lines[0].0, "Displays");
+        assert_eq!(sections[2].lines[4].0, "╰─");
+
+        // One parent with all its children can't be cut anywhere
+        let keys = ["Displays", "├─", "├─", "├─", "├─", "├─", "├─", "├─", "╰─"];
+        let mut sections = vec![lines("Core", 2), section("Hardware", &keys)];
+        balance_sections(&mut sections, None);
+        assert_eq!(shape(&sections), [("Core", 2), ("Hardware", 9)]);
+    }
+}
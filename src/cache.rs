@@ -1,8 +1,12 @@
 // Persistent cache for slow-to-fetch OS/GPU values.
 
-use std::fs;
-use std::path::PathBuf;
+use std::collections::HashMap;
+use std::fs::{self, File};
+use std::os::fd::AsRawFd;
+use std::path::{Path, PathBuf};
 use std::sync::atomic::{AtomicBool, Ordering};
+use std::sync::{Mutex, OnceLock};
+use std::time::{Duration, Instant, SystemTime, UNIX_EPOCH};
 
 // Global flag to force cache refresh
 static FORCE_REFRESH: AtomicBool = AtomicBool::new(false);
@@ -15,6 +19,135 @@ pub fn should_refresh() -> bool {
     FORCE_REFRESH.load(Ordering::Relaxed)
 }
 
+// --refresh images: only the converted images are made again, the cached values stay
+static REFRESH_IMAGES: AtomicBool = AtomicBool::new(false);
+
+pub fn set_refresh_images(value: bool) {
+    REFRESH_IMAGES.store(value, Ordering::Relaxed);
+}
+
+// A plain --refresh converts the images again too
+#[cfg(feature = "image")]
+pub fn should_refresh_images() -> bool {
+    should_refresh() || REFRESH_IMAGES.load(Ordering::Relaxed)
+}
+
+// How long a cached value stays good before it's fetched again
+#[derive(Clone, Copy, PartialEq, Debug)]
+pub enum CacheTtl {
+    // Cached once, kept until --refresh
+    Never,
+    // Never read from the cache
+    Always,
+    After(Duration),
+}
+
+const DAY: Duration = Duration::from_secs(24 * 60 * 60);
+
+// Cached modules: config name, cache file, default TTL.
+// Hardware names don't change, the OS name does on a distro upgrade.
+// The nerd font check is redone anyway when the font changes, the foreign package count when
+// pacman's databases do, the shell and terminal versions when their binary does.
+pub const CACHED_MODULES: &[(&str, &str, CacheTtl)] = &[
+    ("os", "os", CacheTtl::After(DAY)),
+    ("windows", "windows", CacheTtl::After(DAY)),
+    ("cpu", "cpu_model", CacheTtl::Never),
+    ("gpu", "gpu", CacheTtl::Never),
+    ("nerd_font", "nerd_font", CacheTtl::Never),
+    ("packages", "pacman_foreign", CacheTtl::Never),
+    ("shell", "shell", CacheTtl::Never),
+    ("terminal", "terminal_version", CacheTtl::Never),
+];
+
+// TTLs from the [cache] config section (config name -> TTL), set once in main
+static TTL_OVERRIDES: OnceLock<HashMap<String, CacheTtl>> = OnceLock::new();
+
+// Every cached value lives in this one file in the cache dir. After the header, each entry is
+// a "<file>\t<unix milliseconds written>\t<line count>" line followed by the value's lines
+// (the GPUs and the histories take a few).
+const STORE_FILE: &str = "values";
+const STORE_HEADER: &str = "# slowfetch cache";
+
+// Cached values by file name, with when they were written (unix milliseconds)
+type Entries = HashMap<String, (u64, String)>;
+
+// A cache dir, its values as read on first use, and the values cached this run
+// (file -> value) that flush() merges in and writes back once the output is on screen.
+// A later value for the same file replaces the earlier one. slowfetch has the one in STORE,
+// tests make their own in a temp dir.
+struct Store {
+    // None is ~/.cache/slowfetch
+    dir: Option<PathBuf>,
+    entries: OnceLock<Entries>,
+    pending: Mutex<Vec<(&'static str, String)>>,
+}
+
+static STORE: Store =
+    Store { dir: None, entries: OnceLock::new(), pending: Mutex::new(Vec::new()) };
+
+// What read_cache did per module this run, for --verbose
+static STATUS: Mutex<Vec<(&'static str, String)>> = Mutex::new(Vec::new());
+
+pub fn set_ttl_overrides(overrides: HashMap<String, CacheTtl>) {
+    let _ = TTL_OVERRIDES.set(overrides);
+}
+
+// "never", "always", or a number with an s/m/h/d unit ("30m", "7d")
+pub fn parse_ttl(value: &str) -> Option<CacheTtl> {
+    let value = value.trim();
+    match value {
+        "never" => return Some(CacheTtl::Never),
+        "always" => return Some(CacheTtl::Always),
+        _ => {}
+    }
+
+    let unit = value.chars().last()?;
+    let amount: u64 = value[..value.len() - unit.len_utf8()].parse().ok()?;
+    let seconds = match unit {
+        's' => 1,
+        'm' => 60,
+        'h' => 60 * 60,
+        'd' => 24 * 60 * 60,
+        _ => return None,
+    };
+    Some(CacheTtl::After(Duration::from_secs(amount.checked_mul(seconds)?)))
+}
+
+// TTL for a cache file: the [cache] override for its module, else the default
+fn ttl_for(key: &str) -> (&'static str, CacheTtl) {
+    ttl_with(key, TTL_OVERRIDES.get())
+}
+
+fn ttl_with(key: &str, overrides: Option<&HashMap<String, CacheTtl>>) -> (&'static str, CacheTtl) {
+    let Some(&(name, _, default)) = CACHED_MODULES.iter().find(|(_, file, _)| *file == key) else {
+        return ("unknown", CacheTtl::Never);
+    };
+    let ttl = overrides.and_then(|overrides| overrides.get(name)).copied();
+    (name, ttl.unwrap_or(default))
+}
+
+// "40s", "12m", "5h", "3d"
+pub fn format_age(age: Duration) -> String {
+    let seconds = age.as_secs();
+    match seconds {
+        0..60 => format!("{}s", seconds),
+        60..3600 => format!("{}m", seconds / 60),
+        3600..86400 => format!("{}h", seconds / 3600),
+        _ => format!("{}d", seconds / 86400),
+    }
+}
+
+fn record_status(name: &'static str, status: String) {
+    if let Ok(mut statuses) = STATUS.lock() {
+        statuses.push((name, status));
+    }
+}
+
+// Cache hits and misses so far, e.g. ("gpu", "cache hit (age 3d)")
+pub fn status() -> Vec<(&'static str, String)> {
+    STATUS.lock().map(|statuses| statuses.clone()).unwrap_or_default()
+}
+
 fn get_cache_dir() -> Option<PathBuf> {
     let home = std::env::var("HOME").ok()?;
     let cache_dir = PathBuf::from(home).join(".cache").join("slowfetch");
@@ -27,34 +160,295 @@ fn get_cache_dir() -> Option<PathBuf> {
     Some(cache_dir)
 }
 
-fn get_cache_path(key: &str) -> Option<PathBuf> {
-    Some(get_cache_dir()?.join(key))
+// ~/.cache/slowfetch/images, where image mode keeps the images it converted
+#[cfg(feature = "image")]
+pub fn images_dir() -> Option<PathBuf> {
+    let dir = get_cache_dir()?.join("images");
+    fs::create_dir_all(&dir).ok()?;
+    Some(dir)
+}
+
+// Whether values can be cached at all, by writing and removing a scratch file. For doctor.
+pub fn check_writable() -> Result<PathBuf, String> {
+    let dir = get_cache_dir().ok_or("no $HOME, or ~/.cache/slowfetch can't be created")?;
+    let probe = dir.join(".doctor");
+    fs::write(&probe, b"ok")
+        .and_then(|_| fs::remove_file(&probe))
+        .map_err(|error| format!("{}: {}", dir.display(), error))?;
+    Ok(dir)
+}
+
+// Whether a cache file is read at all, a module set to "always" in [cache] isn't
+pub fn is_cached(key: &str) -> bool {
+    ttl_for(key).1 != CacheTtl::Always
 }
 
-// Read a cached value. Returns None if cache doesn't exist or refresh is being forced.
+// Read a cached value. Returns None if cache doesn't exist, is older than the module's TTL,
+// or refresh is being forced.
 pub fn read_cache(key: &str) -> Option<String> {
-    if should_refresh() {
-        return None;
+    STORE.read(key)
+}
+
+// How long a flush waits for another slowfetch's flush, and how often it tries meanwhile
+const LOCK_WAIT: Duration = Duration::from_millis(100);
+const LOCK_RETRY: Duration = Duration::from_millis(5);
+
+// Run `f` while holding an advisory lock on the cache dir.
+// Another slowfetch flushing at the same time (tmux restoring a few panes) usually writes a
+// few small files and lets go quickly, so the lock is retried for up to LOCK_WAIT. Past that
+// the writes are skipped (None) rather than holding up the exit, the other run is caching the
+// same modules and whatever this one found gets cached next time.
+fn with_cache_lock<T>(dir: &Path, f: impl FnOnce() -> Option<T>) -> Option<T> {
+    let _lock = lock(dir, LOCK_WAIT)?;
+    f()
+}
+
+// The cache dir's lock, if nobody has it now. Image mode converts an image under it, and
+// doesn't wait for it since another run holding it is most likely converting the same image.
+#[cfg(feature = "image")]
+pub fn try_lock(dir: &Path) -> Option<File> {
+    lock(dir, Duration::ZERO)
+}
+
+// Take the lock on `dir`, trying for up to `wait`. It's released when the file is dropped.
+fn lock(dir: &Path, wait: Duration) -> Option<File> {
+    let lock_file = File::create(dir.join(".lock")).ok()?;
+    let started = Instant::now();
+    // flock on an fd we own, nothing unsafe about it really
+    while unsafe { libc::flock(lock_file.as_raw_fd(), libc::LOCK_EX | libc::LOCK_NB) } != 0 {
+        let held = std::io::Error::last_os_error().kind() == std::io::ErrorKind::WouldBlock;
+        if !held || started.elapsed() >= wait {
+            return None;
+        }
+        std::thread::sleep(LOCK_RETRY);
+    }
+    Some(lock_file)
+}
+
+// Queue a value for the cache. 10,000IQ
+// Nothing touches the disk until flush(), so a slow disk never holds up the fetch itself.
+pub fn write_cache(key: &'static str, value: &str) {
+    STORE.queue(key, value);
+}
+
+// What flush() did, for --timings
+pub struct FlushStats {
+    pub written: usize,
+    pub changed: usize,
+    pub elapsed: Duration,
+}
+
+impl std::fmt::Display for FlushStats {
+    fn fmt(&self, f: &mut std::fmt::Formatter) -> std::fmt::Result {
+        let plural = if self.written == 1 { "" } else { "s" };
+        write!(
+            f,
+            "{} write{}, {:.1}ms, {} entries changed",
+            self.written,
+            plural,
+            self.elapsed.as_secs_f64() * 1000.0,
+            self.changed
+        )
+    }
+}
+
+// Write everything queued this run in one go, under the lock. The store file is written to a
+// temp file that's renamed into place, so concurrent readers never see half of it. A fully
+// cached run has nothing queued and doesn't touch the disk at all.
+pub fn flush() -> FlushStats {
+    STORE.flush()
+}
+
+impl Store {
+    fn dir(&self) -> Option<PathBuf> {
+        self.dir.clone().or_else(get_cache_dir)
+    }
+
+    // Read the store file the first time it's needed, the rest of the run reads from memory
+    fn entries(&self) -> &Entries {
+        self.entries.get_or_init(|| self.dir().map(|dir| read_entries(&dir)).unwrap_or_default())
+    }
+
+    fn read(&self, key: &str) -> Option<String> {
+        let (name, ttl) = ttl_for(key);
+        if should_refresh() {
+            record_status(name, "refreshed".into());
+            return None;
+        }
+        if ttl == CacheTtl::Always {
+            record_status(name, "cache off".into());
+            return None;
+        }
+
+        let Some((written, value)) = self.entries().get(key) else {
+            record_status(name, "cache miss".into());
+            return None;
+        };
+
+        // A clock that went backwards counts as a fresh cache
+        let age = Duration::from_millis(now_millis().saturating_sub(*written));
+        if let CacheTtl::After(max_age) = ttl
+            && age > max_age
+        {
+            record_status(name, format!("cache expired (age {})", format_age(age)));
+            return None;
+        }
+
+        record_status(name, format!("cache hit (age {})", format_age(age)));
+        Some(value.clone())
+    }
+
+    fn queue(&self, key: &'static str, value: &str) {
+        if let Ok(mut pending) = self.pending.lock() {
+            pending.retain(|(queued, _)| *queued != key);
+            pending.push((key, value.to_string()));
+        }
+    }
+
+    fn flush(&self) -> FlushStats {
+        let started = Instant::now();
+        let pending = self.pending.lock().map(|mut pending| std::mem::take(&mut *pending));
+        let pending = pending.unwrap_or_default();
+        let mut stats = FlushStats { written: 0, changed: 0, elapsed: Duration::ZERO };
+        if pending.is_empty() {
+            return stats;
+        }
+
+        if let Some(dir) = self.dir() {
+            write_values(&dir, &pending, &mut stats);
+        }
+        stats.elapsed = started.elapsed();
+        stats
+    }
+}
+
+// Milliseconds since the epoch, what entries are stamped with
+fn now_millis() -> u64 {
+    SystemTime::now().duration_since(UNIX_EPOCH).map_or(0, |since| since.as_millis() as u64)
+}
+
+// The entries of the store file in `dir`, none if it's missing or from something else.
+// An entry cut short ends the read, the ones before it are kept.
+fn read_entries(dir: &Path) -> Entries {
+    let Ok(content) = fs::read_to_string(dir.join(STORE_FILE)) else {
+        return Entries::new();
+    };
+    let mut entries = Entries::new();
+    let mut lines = content.split('\n');
+    if lines.next() != Some(STORE_HEADER) {
+        return entries;
     }
+    while let Some(header) = lines.next() {
+        let mut fields = header.split('\t');
+        let (Some(key), Some(written), Some(count)) = (
+            fields.next(),
+            fields.next().and_then(|written| written.parse().ok()),
+            fields.next().and_then(|count| count.parse::<usize>().ok()),
+        ) else {
+            break;
+        };
+        let value: Vec<&str> = lines.by_ref().take(count).collect();
+        if value.len() < count {
+            break;
+        }
+        entries.insert(key.to_string(), (written, value.join("\n")));
+    }
+    entries
+}
+
+// The store file's content, entries sorted by file name so it diffs well
+fn format_entries(entries: &Entries) -> String {
+    let mut keys: Vec<&String> = entries.keys().collect();
+    keys.sort();
+    let mut content = format!("{}\n", STORE_HEADER);
+    for key in keys {
+        let (written, value) = &entries[key];
+        let count = value.split('\n').count();
+        content.push_str(&format!("{}\t{}\t{}\n{}\n", key, written, count, value));
+    }
+    content
+}
 
-    let path = get_cache_path(key)?;
-    fs::read_to_string(path).ok()
+// Merge (file, value) pairs into the store file in `dir` under the lock, counting the write
+// into `stats`. The file is read again under the lock, so values another slowfetch flushed
+// since this one started are kept.
+fn write_values(dir: &Path, values: &[(&str, String)], stats: &mut FlushStats) {
+    with_cache_lock(dir, || {
+        let path = dir.join(STORE_FILE);
+        let first = !path.exists();
+        let mut entries = read_entries(dir);
+        let now = now_millis();
+        for (key, value) in values {
+            // Same value still gets written, that renews its age for the TTL
+            if entries.get(*key).map(|(_, cached)| cached) != Some(value) {
+                stats.changed += 1;
+            }
+            entries.insert(key.to_string(), (now, value.clone()));
+        }
+        let tmp_path = path.with_extension(format!("tmp.{}", std::process::id()));
+        if fs::write(&tmp_path, format_entries(&entries)).is_err()
+            || fs::rename(&tmp_path, &path).is_err()
+        {
+            let _ = fs::remove_file(&tmp_path);
+            return None;
+        }
+        stats.written += 1;
+        // Older versions kept a file per value, they're read no more
+        if first {
+            for (key, _) in values {
+                let _ = fs::remove_file(dir.join(key));
+            }
+        }
+        Some(())
+    });
 }
 
-// Write a value to cache. 10,000IQ
-pub fn write_cache(key: &str, value: &str) -> Option<()> {
-    let path = get_cache_path(key)?;
-    fs::write(path, value).ok()
+// Timestamped samples for usage_deltas, oldest first, as (unix seconds, value).
+// Stored one per line as "<seconds>\t<value>". A history has no TTL, it's read straight from
+// the store, --refresh included.
+pub fn read_history(key: &str) -> Vec<(u64, u64)> {
+    let Some((_, content)) = STORE.entries().get(key) else {
+        return Vec::new();
+    };
+    content
+        .lines()
+        .filter_map(|line| {
+            let (time, value) = line.split_once('\t')?;
+            Some((time.parse().ok()?, value.parse().ok()?))
+        })
+        .collect()
 }
 
-// Read cached GPU value, or return None to trigger the freshest of fetches.
-pub fn get_cached_gpu() -> Option<String> {
-    read_cache("gpu")
+// Queue a history for the cache like any other value
+pub fn write_history(key: &'static str, samples: &[(u64, u64)]) {
+    let lines: Vec<String> =
+        samples.iter().map(|(time, value)| format!("{}\t{}", time, value)).collect();
+    write_cache(key, &lines.join("\n"));
 }
 
-// Cache the GPU value
-pub fn cache_gpu(value: &str) {
-    let _ = write_cache("gpu", value);
+// Read the cached GPUs as (name, vendor, iGPU/dGPU label), or return None to trigger the
+// freshest of fetches. Stored one GPU per line as "<vendor>\t<label>\t<name>", caches from
+// before that format count as a miss.
+pub fn get_cached_gpus() -> Option<Vec<(String, String, String)>> {
+    let cached = read_cache("gpu")?;
+    cached
+        .lines()
+        .map(|line| {
+            let mut fields = line.splitn(3, '\t');
+            let (vendor, label, name) = (fields.next()?, fields.next()?, fields.next()?);
+            Some((name.to_string(), vendor.to_string(), label.to_string()))
+        })
+        .collect::<Option<Vec<_>>>()
+        .filter(|gpus| !gpus.is_empty())
+}
+
+// Cache the GPUs as (name, vendor, label)
+pub fn cache_gpus(gpus: &[(&str, &str, &str)]) {
+    let lines: Vec<String> = gpus
+        .iter()
+        .map(|(name, vendor, label)| format!("{}\t{}\t{}", vendor, label, name))
+        .collect();
+    write_cache("gpu", &lines.join("\n"));
 }
 
 // Read cached OS value, or return None to trigger a fresh fetch.
@@ -64,15 +458,373 @@ pub fn get_cached_os() -> Option<String> {
 
 // Cache the OS value (arch btw)
 pub fn cache_os(value: &str) {
-    let _ = write_cache("os", value);
+    write_cache("os", value);
+}
+
+// Read the cached Windows version under WSL ("10.0.22631.3880")
+pub fn get_cached_windows() -> Option<String> {
+    read_cache("windows")
+}
+
+// Cache the Windows version, asking cmd.exe takes a while
+pub fn cache_windows(value: &str) {
+    write_cache("windows", value);
+}
+
+// Read cached CPU model and vendor, or return None to trigger a fresh fetch.
+// Stored under "cpu_model" since older versions cached the clock suffix under "cpu",
+// as "<model>\n<vendor>" like the GPU.
+pub fn get_cached_cpu() -> Option<(String, String)> {
+    let cached = read_cache("cpu_model")?;
+    let (model, vendor) = cached.split_once('\n')?;
+    Some((model.to_string(), vendor.trim().to_string()))
+}
+
+// Cache the CPU model (without clock, that's read fresh every run) and vendor
+pub fn cache_cpu(value: &str, vendor: &str) {
+    write_cache("cpu_model", &format!("{}\n{}", value, vendor));
+}
+
+// Read the cached nerd font check, only valid for the font it was made for.
+// Stored as "<font>\n<true|false>" so switching fonts re-runs the fc queries.
+pub fn get_cached_nerd_font(font: &str) -> Option<bool> {
+    let cached = read_cache("nerd_font")?;
+    let (cached_font, is_nerd) = cached.split_once('\n')?;
+    (cached_font == font).then(|| is_nerd.trim() == "true")
+}
+
+// Cache the nerd font check for this font
+pub fn cache_nerd_font(font: &str, is_nerd: bool) {
+    write_cache("nerd_font", &format!("{}\n{}", font, is_nerd));
 }
 
-// Read cached CPU value, or return None to trigger a fresh fetch.
-pub fn get_cached_cpu() -> Option<String> {
-    read_cache("cpu")
+// Read a value cached as "<stamp>\n<value>", only valid for that stamp
+fn read_stamped(key: &str, stamp: &str) -> Option<String> {
+    let cached = read_cache(key)?;
+    let (cached_stamp, value) = cached.split_once('\n')?;
+    (cached_stamp == stamp).then(|| value.to_string())
 }
 
-// Cache the CPU value
-pub fn cache_cpu(value: &str) {
-    let _ = write_cache("cpu", value);
+// Read the cached foreign package count, only valid while pacman's databases are unchanged.
+// Stored as "<stamp>\n<count>", the stamp being their modification times.
+pub fn get_cached_foreign(stamp: &str) -> Option<usize> {
+    read_stamped("pacman_foreign", stamp)?.trim().parse().ok()
+}
+
+// Cache the foreign package count for these databases
+pub fn cache_foreign(stamp: &str, count: usize) {
+    write_cache("pacman_foreign", &format!("{}\n{}", stamp, count));
+}
+
+// Read the cached shell line ("Zsh 5.9"), only valid for the binary it was asked from.
+// The stamp is the binary's path and modification time, see helpers::file_stamp.
+pub fn get_cached_shell(stamp: &str) -> Option<String> {
+    read_stamped("shell", stamp)
+}
+
+pub fn cache_shell(stamp: &str, value: &str) {
+    write_cache("shell", &format!("{}\n{}", stamp, value));
+}
+
+// Read the cached terminal version, only valid for the binary it was asked from
+pub fn get_cached_terminal_version(stamp: &str) -> Option<String> {
+    read_stamped("terminal_version", stamp)
+}
+
+pub fn cache_terminal_version(stamp: &str, version: &str) {
+    write_cache("terminal_version", &format!("{}\n{}", stamp, version));
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::testutil::TempDir;
+
+    fn after(seconds: u64) -> Option<CacheTtl> {
+        Some(CacheTtl::After(Duration::from_secs(seconds)))
+    }
+
+    #[test]
+    fn ttl_units() {
+        assert_eq!(parse_ttl("45s"), after(45));
+        assert_eq!(parse_ttl("30m"), after(30 * 60));
+        assert_eq!(parse_ttl("12h"), after(12 * 60 * 60));
+        assert_eq!(parse_ttl(" 7d "), after(7 * 24 * 60 * 60));
+        assert_eq!(parse_ttl("never"), Some(CacheTtl::Never));
+        assert_eq!(parse_ttl("always"), Some(CacheTtl::Always));
+        // Zero is allowed, it's stale as soon as it's written, same as "always" in practice
+        assert_eq!(parse_ttl("0s"), after(0));
+        assert_eq!(parse_ttl("0d"), after(0));
+    }
+
+    #[test]
+    fn ttl_garbage() {
+        // A bare number could be seconds or days, it needs a unit
+        assert_eq!(parse_ttl("30"), None);
+        assert_eq!(parse_ttl("0"), None);
+        for value in ["", "m", "d7", "7 d", "-5m", "1.5h", "7w", "7D", "soon", "Never", "5é"] {
+            assert_eq!(parse_ttl(value), None, "{:?}", value);
+        }
+        // Would overflow u64 seconds
+        assert_eq!(parse_ttl("999999999999999999d"), None);
+    }
+
+    #[test]
+    fn ttl_overrides_by_config_name() {
+        // Defaults when nothing is configured
+        assert_eq!(ttl_with("os", None), ("os", CacheTtl::After(DAY)));
+        assert_eq!(ttl_with("cpu_model", None), ("cpu", CacheTtl::Never));
+
+        // Keys in [cache] are the module names, the lookup is by cache file
+        let overrides: HashMap<String, CacheTtl> = [
+            ("cpu".to_string(), CacheTtl::After(Duration::from_secs(60))),
+            ("packages".to_string(), CacheTtl::Always),
+            ("pacman_foreign".to_string(), CacheTtl::Never),
+        ]
+        .into_iter()
+        .collect();
+        let with = |key| ttl_with(key, Some(&overrides));
+        assert_eq!(with("cpu_model"), ("cpu", CacheTtl::After(Duration::from_secs(60))));
+        assert_eq!(with("pacman_foreign"), ("packages", CacheTtl::Always));
+        // Not overridden, still the default
+        assert_eq!(with("gpu"), ("gpu", CacheTtl::Never));
+        assert_eq!(with("os"), ("os", CacheTtl::After(DAY)));
+        // Files that aren't in the table are kept until --refresh
+        assert_eq!(with("weather"), ("unknown", CacheTtl::Never));
+    }
+
+    const WRITERS: usize = 12;
+    const ROUNDS: usize = 25;
+
+    // Big enough that a half written value would show, and says which round wrote it
+    fn value(writer: usize, round: usize) -> String {
+        format!("{} {}\n{}\nend\n", writer, round, "x".repeat(8192))
+    }
+
+    // One writer: its own key over and over, checking the others' along the way
+    fn write_rounds(cache: &Path, keys: &[String], writer: usize) {
+        for round in 0..ROUNDS {
+            // A write that gave up on the lock wrote nothing, try again so the last round
+            // is what's left in the end
+            loop {
+                let mut stats = FlushStats { written: 0, changed: 0, elapsed: Duration::ZERO };
+                write_values(cache, &[(keys[writer].as_str(), value(writer, round))], &mut stats);
+                if stats.written == 1 {
+                    break;
+                }
+                assert_eq!(stats.changed, 0);
+            }
+            // Whatever the other writers are in the middle of is either there in full or
+            // not there yet
+            let entries = read_entries(cache);
+            for other in keys {
+                if let Some((_, read)) = entries.get(other) {
+                    assert!(read.ends_with("end\n"), "partial read of {}", other);
+                }
+            }
+        }
+    }
+
+    #[cfg(feature = "image")]
+    const CONVERTERS: usize = 4;
+
+    // Image mode converting one image into the images dir under the same lock, --refresh so
+    // every round makes it again. Whatever it gets to send is a whole PNG, the one in the
+    // cache or one made on the side.
+    #[cfg(feature = "image")]
+    fn convert_rounds(cache: &Path, source: &Path) {
+        let placement = crate::image::Placement {
+            columns: 40,
+            rows: 20,
+            offset: (0, 0),
+            pixels: None,
+            resample: false,
+        };
+        for _ in 0..ROUNDS {
+            let converted =
+                crate::image::convert_in(&cache.join("images"), source, &placement, true).unwrap();
+            assert_eq!(::image::open(&converted.path).unwrap().width(), 48);
+            if converted.medium == kitty_image::Medium::TemporaryFile {
+                // Kitty deletes it once it's read it
+                fs::remove_file(&converted.path).unwrap();
+            }
+        }
+    }
+
+    // Temp files left in `dir`
+    fn leftovers(dir: &Path) -> Vec<String> {
+        fs::read_dir(dir)
+            .unwrap()
+            .map(|entry| entry.unwrap().file_name().to_string_lossy().into_owned())
+            .filter(|name| name.contains(".tmp"))
+            .collect()
+    }
+
+    #[test]
+    fn concurrent_writers_keep_every_key() {
+        let cache = TempDir::new();
+        let keys: Vec<String> = (0..WRITERS).map(|writer| format!("module_{}", writer)).collect();
+        #[cfg(feature = "image")]
+        let source = {
+            fs::create_dir(cache.join("images")).unwrap();
+            let path = cache.join("photo.jpg");
+            let pixels =
+                ::image::RgbImage::from_fn(48, 32, |x, y| ::image::Rgb([x as u8, y as u8, 90]));
+            pixels.save(&path).unwrap();
+            path
+        };
+        std::thread::scope(|scope| {
+            for writer in 0..WRITERS {
+                let (cache, keys) = (cache.path(), &keys);
+                scope.spawn(move || write_rounds(cache, keys, writer));
+            }
+            #[cfg(feature = "image")]
+            for _ in 0..CONVERTERS {
+                let (cache, source) = (cache.path(), &source);
+                scope.spawn(move || convert_rounds(cache, source));
+            }
+        });
+
+        let entries = read_entries(cache.path());
+        for (writer, key) in keys.iter().enumerate() {
+            assert_eq!(entries[key].1, value(writer, ROUNDS - 1));
+        }
+        assert_eq!(leftovers(cache.path()), Vec::<String>::new());
+        #[cfg(feature = "image")]
+        assert_eq!(leftovers(&cache.join("images")), Vec::<String>::new());
+    }
+
+    // A run's cache pointed at `dir`, nothing queued yet
+    fn store(dir: &Path) -> Store {
+        let pending = Mutex::new(Vec::new());
+        Store { dir: Some(dir.to_path_buf()), entries: OnceLock::new(), pending }
+    }
+
+    // What a cached module does: the cached value, or the fresh one queued for next time
+    fn cached(store: &Store, key: &'static str, fresh: &str) -> String {
+        store.read(key).unwrap_or_else(|| {
+            store.queue(key, fresh);
+            fresh.to_string()
+        })
+    }
+
+    // Every file in the cache dir and when it was last written
+    fn files(dir: &Path) -> Vec<(String, SystemTime)> {
+        let mut files: Vec<_> = fs::read_dir(dir)
+            .unwrap()
+            .map(|entry| entry.unwrap())
+            .map(|entry| {
+                let modified = entry.metadata().unwrap().modified().unwrap();
+                (entry.file_name().to_string_lossy().into_owned(), modified)
+            })
+            .collect();
+        files.sort();
+        files
+    }
+
+    #[test]
+    fn nothing_is_written_on_a_cached_run() {
+        let cache = TempDir::new();
+        // Kept until --refresh, so the second run can't find them expired
+        let keys = ["gpu", "cpu_model", "nerd_font"];
+
+        // First run: every module misses and queues what it found
+        let first = store(cache.path());
+        for key in keys {
+            assert_eq!(cached(&first, key, "fresh"), "fresh");
+        }
+        // One write for all three, and only the store file and the lock in the dir
+        let stats = first.flush();
+        assert_eq!((stats.written, stats.changed), (1, 3));
+        let before = files(cache.path());
+        let names: Vec<&str> = before.iter().map(|(name, _)| name.as_str()).collect();
+        assert_eq!(names, [".lock", STORE_FILE]);
+
+        // Second run: all hits, nothing queued and the dir isn't touched, lock file included
+        let second = store(cache.path());
+        for key in keys {
+            assert_eq!(cached(&second, key, "newer"), "fresh");
+        }
+        let stats = second.flush();
+        assert_eq!((stats.written, stats.changed), (0, 0));
+        assert_eq!(files(cache.path()), before);
+
+        // A module queueing the value it already had renews it without changing it
+        let third = store(cache.path());
+        third.queue("gpu", "fresh");
+        third.queue("gpu", "fresh");
+        third.queue("os", "Arch Linux");
+        let stats = third.flush();
+        assert_eq!((stats.written, stats.changed), (1, 1));
+        let entries = read_entries(cache.path());
+        assert_eq!(entries.len(), 4);
+        assert_eq!(entries["os"].1, "Arch Linux");
+        // Flushed once, the queue is empty again
+        assert_eq!(third.flush().written, 0);
+    }
+
+    // Another slowfetch holding the lock past LOCK_WAIT, the write is left to it
+    #[test]
+    fn a_held_lock_skips_the_write() {
+        let cache = TempDir::new();
+        let held = File::create(cache.join(".lock")).unwrap();
+        assert_eq!(unsafe { libc::flock(held.as_raw_fd(), libc::LOCK_EX) }, 0);
+
+        let started = Instant::now();
+        let mut stats = FlushStats { written: 0, changed: 0, elapsed: Duration::ZERO };
+        write_values(cache.path(), &[("gpu", "RTX 4070".into())], &mut stats);
+        assert!(started.elapsed() >= LOCK_WAIT);
+        assert_eq!(stats.written, 0);
+        assert!(!cache.join(STORE_FILE).exists());
+
+        // Let go, and the next flush gets it straight away
+        drop(held);
+        write_values(cache.path(), &[("gpu", "RTX 4070".into())], &mut stats);
+        assert_eq!(stats.written, 1);
+        assert_eq!(read_entries(cache.path())["gpu"].1, "RTX 4070");
+    }
+
+    #[test]
+    fn store_file_round_trip() {
+        let cache = TempDir::new();
+        // One line, several (the GPUs), a trailing newline, and empty
+        let values = [
+            ("os", "Arch Linux".to_string()),
+            ("gpu", "AMD\tdGPU\tRX 7800 XT\nIntel\tiGPU\tArc".to_string()),
+            ("shell", "/bin/zsh 1700000000\nZsh 5.9\n".to_string()),
+            ("windows", String::new()),
+        ];
+        let mut stats = FlushStats { written: 0, changed: 0, elapsed: Duration::ZERO };
+        write_values(cache.path(), &values, &mut stats);
+        let entries = read_entries(cache.path());
+        assert_eq!(entries.len(), values.len());
+        for (key, value) in &values {
+            assert_eq!(&entries[*key].1, value);
+        }
+
+        // Cut short, the entries before the cut are kept
+        let content = fs::read_to_string(cache.join(STORE_FILE)).unwrap();
+        let cut = content.find("Zsh 5.9").unwrap();
+        fs::write(cache.join(STORE_FILE), &content[..cut]).unwrap();
+        let entries = read_entries(cache.path());
+        let mut keys: Vec<&str> = entries.keys().map(String::as_str).collect();
+        keys.sort();
+        assert_eq!(keys, ["gpu", "os"]);
+
+        // Something else entirely is no entries
+        fs::write(cache.join(STORE_FILE), "gpu\t0\t1\nRTX 4070\n").unwrap();
+        assert!(read_entries(cache.path()).is_empty());
+    }
+
+    #[test]
+    fn per_value_files_go_on_the_first_write() {
+        let cache = TempDir::new();
+        fs::write(cache.join("gpu"), "RTX 3060").unwrap();
+        fs::write(cache.join("os"), "Arch Linux").unwrap();
+        let mut stats = FlushStats { written: 0, changed: 0, elapsed: Duration::ZERO };
+        write_values(cache.path(), &[("gpu", "RTX 4070".into())], &mut stats);
+        assert!(!cache.join("gpu").exists());
+        // Only what was written is cleaned up, the rest isn't read anyway
+        assert!(cache.join("os").exists());
+    }
 }
@@ -0,0 +1,1336 @@
+// Module collection for Slowfetch.
+// Modules are split into static ones (collected once) and dynamic ones (re-read every
+// --watch tick), then both are assembled into the three sections.
+
+use std::any::Any;
+use std::collections::HashMap;
+use std::time::{Duration, Instant};
+
+use crate::balance;
+use crate::colorcontrol;
+use crate::compact;
+use crate::configloader::Config;
+use crate::deltas;
+use crate::helpers::{self, Precision, hyperlink};
+use crate::modules::{coremodules, fontmodule, hardwaremodules, microarch, userspacemodules};
+use crate::modules::hardwaremodules::{Gpu, Vendor};
+use crate::pool;
+use crate::renderer::{Section, ordered_lines};
+use crate::template::{Fields, format_module};
+use crate::terminalsize;
+
+// Default line order for each section, also the module names accepted in [order]
+const CORE_ORDER: &[&str] =
+    &["os", "host_os", "kernel", "uptime", "load", "boot", "bootloader", "today"];
+const HARDWARE_ORDER: &[&str] = &[
+    "host", "cpu", "gpu", "memory", "storage", "battery", "power", "ups", "audio", "activity",
+    "network", "displays",
+];
+const USERSPACE_ORDER: &[&str] = &[
+    "packages", "terminal", "shell", "prompt", "wm", "mode", "session", "login", "ui", "desktop",
+    "editor", "font",
+];
+
+// Modules that only get a line when they found something (no battery, no editor set),
+// unless [modules] says "show" for them
+const HIDDEN_WHEN_UNKNOWN: &[&str] = &[
+    "host_os", "load", "battery", "power", "ups", "session", "login", "prompt", "desktop",
+    "editor", "audio", "activity", "network", "mode", "today",
+];
+
+// What a module came back with
+pub enum ValueState<T = String> {
+    Ok(T),
+    // The module ran but couldn't find out, shown as "unknown"
+    Unknown,
+    // The module panicked or missed the fetch deadline, and why. Shown dimmed.
+    Failed(String),
+    // Nothing to show: turned off in the config, or doesn't apply here (no battery)
+    Disabled,
+}
+
+impl ValueState {
+    // Regular module output, modules say "unknown" when they can't tell
+    fn from_text(value: String) -> Self {
+        match value.as_str() {
+            "unknown" => Self::Unknown,
+            "" => Self::Disabled,
+            _ => Self::Ok(value),
+        }
+    }
+}
+
+impl<T> From<Missed> for ValueState<T> {
+    fn from(missed: Missed) -> Self {
+        match missed {
+            Missed::TimedOut => Self::Failed(TIMED_OUT.to_string()),
+            Missed::Failed(reason) => Self::Failed(reason),
+        }
+    }
+}
+
+// Values that don't change while slowfetch is running
+pub struct StaticInfo {
+    // Raw OS name for art detection, the displayed one may be templated
+    #[cfg_attr(not(feature = "art"), allow(dead_code))]
+    pub os_name: String,
+    // OS name plus deployment info on image based distros
+    os: ValueState,
+    // NixOS generation, flakes and store size, only read with nixos_detail
+    nixos_detail: Vec<String>,
+    // Distro homepage for the OS hyperlink
+    os_url: Option<String>,
+    // WSL 1 or 2, None outside WSL
+    wsl: Option<u8>,
+    // "Windows 11 23H2" for the OS line under WSL
+    windows: Option<String>,
+    // Windows version under WSL, Disabled outside it
+    host_os: ValueState,
+    kernel: ValueState,
+    // Newer kernel installed than the running one, only checked with kernel_check
+    kernel_pending: Option<String>,
+    // Make and model, only read with show_host (or on handhelds with it on auto)
+    host: ValueState,
+    cpu: ValueState,
+    // Labeled iGPU/dGPU when there's more than one
+    gpus: ValueState<Vec<Gpu>>,
+    // For the vendor icon
+    cpu_vendor: Vendor,
+    // Cgroup CPU quota in cores, only read with respect_cgroups
+    cpu_limit: Option<f64>,
+    // Microarchitecture names, only read with arch_names. One per GPU when they line up
+    cpu_arch: Option<&'static str>,
+    gpu_archs: Vec<Option<&'static str>>,
+    packages: ValueState,
+    terminal: ValueState,
+    shell: ValueState,
+    prompt: ValueState,
+    wm: ValueState,
+    // KWin/Mutter backend after the WM name, only read with wm_detail
+    wm_backend: Option<String>,
+    // SteamOS Gaming/Desktop Mode
+    mode: ValueState,
+    ui: ValueState,
+    desktop: ValueState,
+    editor: ValueState,
+    // Display manager, only read with show_login
+    login: ValueState,
+    font: ValueState,
+    // Font, opacity and padding lines, only read with terminal_detail (font is Disabled then)
+    terminal_detail: ValueState<Vec<(String, String)>>,
+    audio: ValueState,
+    boot: ValueState<Vec<(String, String)>>,
+    // Bootloader and Secure Boot, only read with show_bootloader
+    bootloader: ValueState,
+    // Root filesystem is on LUKS, only checked with show_encryption
+    encrypted: bool,
+    displays: ValueState<Vec<(String, String)>>,
+    // Modules that missed the fetch deadline
+    timed_out: Vec<&'static str>,
+    // Modules that failed and why, for --verbose
+    failed: Vec<(&'static str, String)>,
+    // How long each probe ran, for --timings
+    timings: Vec<(&'static str, Duration)>,
+}
+
+// Values --watch re-reads every tick
+pub struct DynamicInfo {
+    uptime: ValueState,
+    // Only read with show_load
+    load: ValueState,
+    memory: ValueState,
+    storage: ValueState,
+    battery: ValueState,
+    // Power profile and CPU governor, only with power_detail
+    power: ValueState,
+    ups: ValueState,
+    session: ValueState,
+    activity: ValueState,
+    // Default route interface, link speed or Wi-Fi network, addresses with show_ip
+    network: ValueState,
+    // "+1.2GB since last run" after the memory and storage bars, only with usage_deltas
+    memory_delta: Option<String>,
+    storage_delta: Option<String>,
+    // Date and [[event]] countdown, only with events configured. Dynamic for --watch at midnight
+    today: ValueState,
+    // Modules that missed the fetch deadline
+    timed_out: Vec<&'static str>,
+    // Modules that failed and why, for --verbose
+    failed: Vec<(&'static str, String)>,
+    // How long each probe ran, for --timings
+    timings: Vec<(&'static str, Duration)>,
+    // The raw numbers behind the lines, for --json
+    numbers: Vec<(&'static str, f64)>,
+}
+
+impl StaticInfo {
+    pub fn failures(&self) -> &[(&'static str, String)] {
+        &self.failed
+    }
+
+    pub fn timings(&self) -> &[(&'static str, Duration)] {
+        &self.timings
+    }
+
+    pub fn timed_out(&self) -> bool {
+        !self.timed_out.is_empty()
+    }
+}
+
+impl DynamicInfo {
+    // collect_dynamic itself panicked, every dynamic module is unavailable
+    pub fn failed(reason: String) -> Self {
+        let failed = || ValueState::Failed(reason.clone());
+        Self {
+            uptime: failed(),
+            load: ValueState::Disabled,
+            memory: failed(),
+            storage: failed(),
+            battery: ValueState::Disabled,
+            power: ValueState::Disabled,
+            ups: ValueState::Disabled,
+            session: ValueState::Disabled,
+            activity: ValueState::Disabled,
+            network: ValueState::Disabled,
+            memory_delta: None,
+            storage_delta: None,
+            today: ValueState::Disabled,
+            timed_out: Vec::new(),
+            failed: vec![("dynamic modules", reason.clone())],
+            timings: Vec::new(),
+            numbers: Vec::new(),
+        }
+    }
+
+    pub fn failures(&self) -> &[(&'static str, String)] {
+        &self.failed
+    }
+
+    pub fn timings(&self) -> &[(&'static str, Duration)] {
+        &self.timings
+    }
+
+    pub fn timed_out(&self) -> bool {
+        !self.timed_out.is_empty()
+    }
+
+    // The raw numbers behind the humanized lines, for --json. See json_numbers for the names.
+    pub fn numbers(&self) -> &[(&'static str, f64)] {
+        &self.numbers
+    }
+}
+
+// Can `name` go in the [order] list for `section`? Anything else takes any module.
+pub fn known_module(section: &str, name: &str) -> bool {
+    match section {
+        "core" => CORE_ORDER.contains(&name),
+        "hardware" => HARDWARE_ORDER.contains(&name),
+        "userspace" => USERSPACE_ORDER.contains(&name),
+        _ => module_names().contains(&name),
+    }
+}
+
+// Every module, in section order
+pub fn module_names() -> Vec<&'static str> {
+    [CORE_ORDER, HARDWARE_ORDER, USERSPACE_ORDER].concat()
+}
+
+// Names in [order] and [modules] that aren't modules. The config loader warns about the
+// [order] ones, this is for the exit code.
+pub fn unknown_modules(config: &Config) -> Vec<String> {
+    let ordered = config
+        .order
+        .iter()
+        .flat_map(|(section, names)| names.iter().map(move |name| (section.as_str(), name)));
+    let overridden = config.unknown_lines.keys().map(|name| ("", name));
+    ordered
+        .chain(overridden)
+        .filter(|&(section, name)| !known_module(section, name))
+        .map(|(_, name)| name.clone())
+        .collect()
+}
+
+// Every module in section order with what it came back with, as plain text, for doctor
+pub fn module_states(
+    statics: &StaticInfo,
+    dynamic: &DynamicInfo,
+) -> Vec<(&'static str, ValueState)> {
+    let text = |state: &ValueState| describe(state, String::clone);
+    let lines = |state: &ValueState<Vec<(String, String)>>| match state {
+        ValueState::Ok(lines) if lines.is_empty() => ValueState::Disabled,
+        state => describe(state, |lines| {
+            let lines: Vec<String> =
+                lines.iter().map(|(key, value)| format!("{}: {}", key, value)).collect();
+            lines.join(", ")
+        }),
+    };
+    let gpus = |gpus: &Vec<Gpu>| {
+        gpus.iter().map(|gpu| gpu.name.as_str()).collect::<Vec<_>>().join(", ")
+    };
+
+    module_names()
+        .into_iter()
+        .map(|id| {
+            let state = match id {
+                "os" => text(&statics.os),
+                "host_os" => text(&statics.host_os),
+                "kernel" => text(&statics.kernel),
+                "uptime" => text(&dynamic.uptime),
+                "load" => text(&dynamic.load),
+                "boot" => lines(&statics.boot),
+                "bootloader" => text(&statics.bootloader),
+                "host" => text(&statics.host),
+                "cpu" => text(&statics.cpu),
+                "gpu" => describe(&statics.gpus, gpus),
+                "memory" => text(&dynamic.memory),
+                "storage" => text(&dynamic.storage),
+                "battery" => text(&dynamic.battery),
+                "power" => text(&dynamic.power),
+                "ups" => text(&dynamic.ups),
+                "audio" => text(&statics.audio),
+                "activity" => text(&dynamic.activity),
+                "network" => text(&dynamic.network),
+                "displays" => lines(&statics.displays),
+                "packages" => text(&statics.packages),
+                "terminal" => text(&statics.terminal),
+                "shell" => text(&statics.shell),
+                "prompt" => text(&statics.prompt),
+                "wm" => text(&statics.wm),
+                "mode" => text(&statics.mode),
+                "session" => text(&dynamic.session),
+                "ui" => text(&statics.ui),
+                "desktop" => text(&statics.desktop),
+                "editor" => text(&statics.editor),
+                "login" => text(&statics.login),
+                "today" => text(&dynamic.today),
+                // terminal_detail reads the font in place of the font module
+                "font" => match &statics.terminal_detail {
+                    ValueState::Disabled => text(&statics.font),
+                    detail => lines(detail),
+                },
+                _ => ValueState::Unknown,
+            };
+            // These only say unknown when there's nothing to find (no battery)
+            let state = match state {
+                ValueState::Unknown if HIDDEN_WHEN_UNKNOWN.contains(&id) => ValueState::Disabled,
+                state => state,
+            };
+            (id, state)
+        })
+        .collect()
+}
+
+// A module's state with its value turned into text
+fn describe<T>(state: &ValueState<T>, text: impl Fn(&T) -> String) -> ValueState {
+    match state {
+        ValueState::Ok(value) => ValueState::Ok(text(value)),
+        ValueState::Unknown => ValueState::Unknown,
+        ValueState::Failed(reason) => ValueState::Failed(reason.clone()),
+        ValueState::Disabled => ValueState::Disabled,
+    }
+}
+
+// Who made slowfetch, "Role: Names" per line, shown by --credits
+const CREDITS: &str = include_str!("assets/credits.txt");
+
+// What a module that missed the fetch deadline shows
+const TIMED_OUT: &str = "timed out";
+
+// A module that panics shows as unavailable, which needs the panic to unwind. With
+// panic = "abort" the first one takes the whole fetch down with it.
+#[cfg(not(panic = "unwind"))]
+compile_error!("slowfetch needs panic = \"unwind\" to show panicking modules as unavailable");
+
+// The message a panic was raised with
+pub fn panic_reason(payload: &(dyn Any + Send)) -> String {
+    if let Some(message) = payload.downcast_ref::<&str>() {
+        format!("panicked: {}", message)
+    } else if let Some(message) = payload.downcast_ref::<String>() {
+        format!("panicked: {}", message)
+    } else {
+        "panicked".to_string()
+    }
+}
+
+// A slow module running on the worker pool, its result comes back over a channel.
+// Waiting is bounded by the deadline, a probe that misses it is abandoned and left running
+// without holding up the pool or the process (main returning exits even with workers busy).
+// A panic in the module comes back as its reason.
+struct Probe<T> {
+    task: pool::Task<T>,
+}
+
+impl<T: Send + 'static> Probe<T> {
+    fn spawn(module: impl FnOnce() -> T + Send + 'static) -> Self {
+        Self { task: pool::spawn(module) }
+    }
+}
+
+// Why a probe didn't deliver
+enum Missed {
+    TimedOut,
+    // The module panicked (or its worker died some other way), and why
+    Failed(String),
+}
+
+// The shared fetch deadline (max_fetch_ms) probes are collected against.
+// Remembers which modules missed it so build_sections can hide them if asked,
+// and why modules failed for --verbose, and how long each ran for --timings.
+struct Deadline {
+    at: Option<Instant>,
+    timed_out: Vec<&'static str>,
+    failed: Vec<(&'static str, String)>,
+    timings: Vec<(&'static str, Duration)>,
+}
+
+impl Deadline {
+    fn new(config: &Config) -> Self {
+        Self {
+            at: (config.max_fetch_ms > 0)
+                .then(|| Instant::now() + Duration::from_millis(config.max_fetch_ms)),
+            timed_out: Vec::new(),
+            failed: Vec::new(),
+            timings: Vec::new(),
+        }
+    }
+
+    // Wait for a probe, recording it if it missed the deadline or failed
+    fn collect<T>(&mut self, id: &'static str, probe: Probe<T>) -> Result<T, Missed> {
+        let result = match probe.task.wait(self.at) {
+            Ok(finished) => {
+                self.timings.push((id, finished.took));
+                finished.result.map_err(Missed::Failed)
+            }
+            Err(pool::Missed::TimedOut) => Err(Missed::TimedOut),
+            Err(pool::Missed::Lost) => Err(Missed::Failed("no result".into())),
+        };
+        match &result {
+            Err(Missed::TimedOut) => {
+                self.timed_out.push(id);
+                self.failed.push((id, TIMED_OUT.to_string()));
+            }
+            Err(Missed::Failed(reason)) => self.failed.push((id, reason.clone())),
+            Ok(_) => {}
+        }
+        result
+    }
+
+    // A module with a single value
+    fn text(&mut self, id: &'static str, probe: Probe<String>) -> ValueState {
+        match self.collect(id, probe) {
+            Ok(value) => ValueState::from_text(value),
+            Err(missed) => missed.into(),
+        }
+    }
+
+    // A module with any number of lines (or values)
+    fn lines<T>(&mut self, id: &'static str, probe: Probe<Vec<T>>) -> ValueState<Vec<T>> {
+        match self.collect(id, probe) {
+            Ok(lines) => ValueState::Ok(lines),
+            Err(missed) => missed.into(),
+        }
+    }
+}
+
+// Collect the static modules.
+// Only spawn threads for slow I/O operations (subprocesses)
+// These may run external commands like vulkaninfo, shell --version, etc.
+pub fn collect_static(config: &Config) -> StaticInfo {
+    let mut deadline = Deadline::new(config);
+    let gpu_probe = Probe::spawn(hardwaremodules::gpus);
+    let packages_detail = config.packages_detail;
+    let packages_probe = Probe::spawn(move || userspacemodules::packages(packages_detail));
+    let shell_probe = Probe::spawn(userspacemodules::shell);
+    // terminal_detail reads the whole terminal config in place of just the font
+    let (font_probe, terminal_probe) = if config.terminal_detail {
+        (None, Some(Probe::spawn(fontmodule::terminal_detail)))
+    } else {
+        (Some(Probe::spawn(|| helpers::cached_font().to_string())), None)
+    };
+    // The terminal's version, asked with --version when the environment doesn't say
+    let terminal = userspacemodules::terminal();
+    let terminal_version_probe = {
+        let terminal = terminal.clone();
+        Probe::spawn(move || userspacemodules::terminal_version(&terminal))
+    };
+    let display_detail = config.display_detail;
+    let screen_probe = Probe::spawn(move || hardwaremodules::screen(display_detail));
+    // Optional: may fall back to running efibootmgr
+    let boot_probe = config
+        .show_boot_entries
+        .then(|| Probe::spawn(coremodules::boot_entries));
+    // Optional: asks the sound server via wpctl/pactl
+    let audio_probe = config
+        .show_audio
+        .then(|| Probe::spawn(hardwaremodules::audio));
+    // Only under WSL: asks cmd.exe for the Windows version
+    let wsl = helpers::wsl_version();
+    let host_os_probe = wsl.map(|_| Probe::spawn(coremodules::windows_version));
+    // Optional: may run gsettings on GNOME
+    let desktop_probe = config
+        .show_desktop
+        .then(|| Probe::spawn(userspacemodules::desktop_details));
+
+    // Fast operations - just file reads or env var checks, no benefit from threading
+    let os_name = coremodules::os();
+    // Only on image based distros: may ask rpm-ostree for the booted deployment
+    // nixos_detail has the generation in its own tree, the OS line goes without it
+    let nixos_detail = config.nixos_detail && coremodules::is_nixos();
+    let os_probe = (coremodules::is_immutable() && !nixos_detail).then(|| {
+        let os_name = os_name.clone();
+        Probe::spawn(move || coremodules::immutable_os(os_name))
+    });
+    let kernel = coremodules::kernel();
+    let (cpu, cpu_vendor) = hardwaremodules::cpu_with_vendor(config.cpu_clock);
+    let wm = userspacemodules::wm();
+    let ui = userspacemodules::ui();
+    let editor = userspacemodules::editor();
+    let prompt = if config.show_prompt {
+        userspacemodules::prompt()
+    } else {
+        String::new()
+    };
+    let mode = userspacemodules::steamos_mode(&os_name, &wm);
+    // No GPU found comes back as a single "unknown" one
+    let gpus = match deadline.lines("gpu", gpu_probe) {
+        ValueState::Ok(gpus) if gpus.iter().all(|gpu| gpu.name == "unknown") => ValueState::Unknown,
+        state => state,
+    };
+    let host_os =
+        host_os_probe.map_or(ValueState::Disabled, |probe| deadline.text("host_os", probe));
+
+    StaticInfo {
+        os_url: coremodules::os_url(&os_name),
+        // Deployment info is a bonus, a slow rpm-ostree just means the plain OS name
+        os: ValueState::from_text(
+            os_probe
+                .and_then(|probe| probe.task.wait(deadline.at).ok()?.result.ok())
+                .unwrap_or_else(|| os_name.clone()),
+        ),
+        os_name,
+        wsl,
+        windows: match &host_os {
+            ValueState::Ok(version) => Some(coremodules::windows_name(version)),
+            _ => None,
+        },
+        // "Windows 11 23H2 (10.0.22631.3880)"
+        host_os: match host_os {
+            ValueState::Ok(version) => {
+                ValueState::Ok(format!("{} ({})", coremodules::windows_name(&version), version))
+            }
+            state => state,
+        },
+        nixos_detail: if nixos_detail { coremodules::nixos_detail() } else { Vec::new() },
+        // Just a couple of directory listings, a container's kernel is the host's
+        kernel_pending: (config.kernel_check && !helpers::in_container())
+            .then(|| coremodules::pending_kernel(&kernel))
+            .flatten(),
+        kernel: ValueState::from_text(kernel),
+        // Just DMI reads
+        host: match config.show_host {
+            Some(true) => ValueState::from_text(hardwaremodules::host()),
+            Some(false) => ValueState::Disabled,
+            None => hardwaremodules::handheld()
+                .map_or(ValueState::Disabled, |name| ValueState::Ok(name.to_string())),
+        },
+        cpu: ValueState::from_text(cpu),
+        gpus,
+        cpu_vendor,
+        cpu_limit: hardwaremodules::cgroup_cpu_limit(),
+        cpu_arch: config.arch_names.then(microarch::cpu_arch).flatten(),
+        gpu_archs: if config.arch_names { microarch::gpu_archs() } else { Vec::new() },
+        packages: deadline.text("packages", packages_probe),
+        // Without its version the terminal's name still goes on the line
+        terminal: ValueState::from_text(
+            match deadline.collect("terminal", terminal_version_probe) {
+                Ok(Some(version)) => format!("{} {}", terminal, version),
+                _ => terminal,
+            },
+        ),
+        shell: deadline.text("shell", shell_probe),
+        prompt: ValueState::from_text(prompt),
+        wm_backend: config.wm_detail.then(|| userspacemodules::wm_backend(&wm)).flatten(),
+        mode: ValueState::from_text(mode),
+        wm: ValueState::from_text(wm),
+        ui: ValueState::from_text(ui),
+        desktop: desktop_probe
+            .map_or(ValueState::Disabled, |probe| deadline.text("desktop", probe)),
+        editor: ValueState::from_text(editor),
+        // A symlink and a config file read, or the process scan wm() already did
+        login: if config.show_login {
+            ValueState::from_text(userspacemodules::login_manager())
+        } else {
+            ValueState::Disabled
+        },
+        font: font_probe.map_or(ValueState::Disabled, |probe| deadline.text("font", probe)),
+        terminal_detail: terminal_probe
+            .map_or(ValueState::Disabled, |probe| deadline.lines("font", probe)),
+        audio: audio_probe.map_or(ValueState::Disabled, |probe| deadline.text("audio", probe)),
+        boot: boot_probe.map_or(ValueState::Disabled, |probe| deadline.lines("boot", probe)),
+        // Just efivarfs and /boot reads
+        bootloader: if config.show_bootloader {
+            ValueState::from_text(coremodules::bootloader())
+        } else {
+            ValueState::Disabled
+        },
+        // Just sysfs reads
+        encrypted: config.show_encryption && hardwaremodules::root_is_encrypted(),
+        displays: deadline.lines("displays", screen_probe),
+        timed_out: deadline.timed_out,
+        failed: deadline.failed,
+        timings: deadline.timings,
+    }
+}
+
+// Collect the dynamic modules
+pub fn collect_dynamic(config: &Config) -> DynamicInfo {
+    let mut deadline = Deadline::new(config);
+    // Also hands back the used and total bytes for usage_deltas and --json
+    let storage_probe = Probe::spawn(hardwaremodules::storage);
+    // Optional: sleeps between two /proc samples, overlaps with the other slow probes
+    let activity_probe = config
+        .show_activity
+        .then(|| Probe::spawn(hardwaremodules::activity));
+    // Optional: asks iw for the Wi-Fi network
+    let (show_ipv4, show_ipv6) = (config.show_ipv4, config.show_ipv6);
+    let network_probe = config
+        .show_network
+        .then(|| Probe::spawn(move || hardwaremodules::network(show_ipv4, show_ipv6)));
+    // Samples the battery's power draw twice
+    let (thresholds, battery_power) = (config.battery_thresholds, config.battery_power);
+    let battery_probe =
+        Probe::spawn(move || hardwaremodules::laptop_battery(thresholds, battery_power));
+    // Optional: may ask powerprofilesctl
+    let power_probe = config.power_detail.then(|| Probe::spawn(hardwaremodules::power));
+
+    let session = if config.show_session {
+        userspacemodules::session()
+    } else {
+        String::new()
+    };
+    let (load, load_averages) =
+        if config.show_load { coremodules::load() } else { (String::new(), None) };
+    let uptime_precision = match config.uptime_seconds {
+        Some(true) => Precision::Seconds,
+        _ => Precision::Minutes,
+    };
+    let (storage, storage_bytes) = match deadline.collect("storage", storage_probe) {
+        Ok((line, bytes)) => (ValueState::from_text(line), bytes),
+        Err(missed) => (missed.into(), None),
+    };
+    // "unknown" here means there's no battery/UPS
+    let (battery, battery_percent) = match deadline.collect("battery", battery_probe) {
+        Ok((line, percent)) => (ValueState::from_text(line), percent),
+        Err(missed) => (missed.into(), None),
+    };
+    let (uptime, uptime_seconds) = coremodules::uptime(uptime_precision);
+    let (memory, memory_kb) = hardwaremodules::memory();
+    let numbers = json_numbers(Measured {
+        uptime_seconds,
+        load_averages,
+        memory_kb,
+        storage_bytes,
+        battery_percent,
+    });
+
+    DynamicInfo {
+        uptime: ValueState::from_text(uptime),
+        load: ValueState::from_text(load),
+        memory: ValueState::from_text(memory),
+        storage,
+        battery,
+        power: power_probe.map_or(ValueState::Disabled, |probe| deadline.text("power", probe)),
+        ups: ValueState::from_text(hardwaremodules::ups(config.battery_thresholds)),
+        session: ValueState::from_text(session),
+        activity: activity_probe
+            .map_or(ValueState::Disabled, |probe| deadline.text("activity", probe)),
+        network: network_probe
+            .map_or(ValueState::Disabled, |probe| deadline.text("network", probe)),
+        memory_delta: usage_delta(config.usage_deltas, &deltas::MEMORY, || {
+            memory_kb.map(|(used, _)| used * 1000)
+        }),
+        storage_delta: usage_delta(config.usage_deltas, &deltas::STORAGE, || {
+            storage_bytes.map(|(used, _)| used)
+        }),
+        today: ValueState::from_text(coremodules::today(&config.events)),
+        timed_out: deadline.timed_out,
+        failed: deadline.failed,
+        timings: deadline.timings,
+        numbers,
+    }
+}
+
+// What the dynamic modules measured on the way to their lines, None where they couldn't
+struct Measured {
+    uptime_seconds: Option<f64>,
+    // 1, 5 and 15 minutes, only with show_load
+    load_averages: Option<[f64; 3]>,
+    // (used, total) in the KB /proc/meminfo counts in
+    memory_kb: Option<(u64, u64)>,
+    // (used, total)
+    storage_bytes: Option<(u64, u64)>,
+    battery_percent: Option<u8>,
+}
+
+// The measured values as --json fields. A name is the module, what's measured when there's
+// more than one value (used, total) and the unit: memory_used_kb, storage_total_bytes,
+// uptime_seconds. Load averages have no unit, theirs is the window: load_1m, load_5m,
+// load_15m. What wasn't measured is left out rather than written as null.
+fn json_numbers(measured: Measured) -> Vec<(&'static str, f64)> {
+    let mut numbers = Vec::new();
+    if let Some(seconds) = measured.uptime_seconds {
+        numbers.push(("uptime_seconds", seconds));
+    }
+    if let Some([one, five, fifteen]) = measured.load_averages {
+        numbers.extend([("load_1m", one), ("load_5m", five), ("load_15m", fifteen)]);
+    }
+    if let Some((used, total)) = measured.memory_kb {
+        numbers.extend([("memory_used_kb", used as f64), ("memory_total_kb", total as f64)]);
+    }
+    if let Some((used, total)) = measured.storage_bytes {
+        let (used, total) = (used as f64, total as f64);
+        numbers.extend([("storage_used_bytes", used), ("storage_total_bytes", total)]);
+    }
+    if let Some(percent) = measured.battery_percent {
+        numbers.push(("battery_percent", f64::from(percent)));
+    }
+    numbers
+}
+
+// How `metric` changed over the usage_deltas `window`, measured with `bytes` only when
+// usage_deltas is on. Each delta also leaves this run's sample in the cache.
+fn usage_delta(
+    window: Option<Duration>,
+    metric: &deltas::Metric,
+    bytes: impl FnOnce() -> Option<u64>,
+) -> Option<String> {
+    let window = window?;
+    deltas::annotate(metric, bytes()?, window)
+}
+
+// The --credits box
+pub fn credits() -> Section {
+    let lines = CREDITS
+        .lines()
+        .filter_map(|line| line.split_once(": "))
+        .map(|(role, names)| (role.to_string(), names.to_string()))
+        .collect();
+    Section::new("Credits", lines)
+}
+
+// Optional greeting line above everything, in the title color.
+// Recomputed every --watch tick since it depends on the time of day.
+pub fn greeting(config: &Config) -> Option<String> {
+    config
+        .greeting
+        .then(|| userspacemodules::greeting(config.date_format.as_deref()))
+        .map(|greeting| colorcontrol::color_title(&greeting))
+}
+
+// Section lines per module id, what ordered_lines puts in order
+type ModuleLines = HashMap<&'static str, Vec<(String, String)>>;
+
+// Add a module's line, unless it has nothing to show
+fn insert_line(results: &mut ModuleLines, id: &'static str, key: &str, value: Option<String>) {
+    if let Some(value) = value {
+        results.insert(id, vec![(key.to_string(), value)]);
+    }
+}
+
+// A failed module's value, dimmed. Timeouts keep saying so, anything else is just unavailable.
+fn unavailable(reason: &str) -> String {
+    colorcontrol::color_unavailable(if reason == TIMED_OUT { TIMED_OUT } else { "unavailable" })
+}
+
+// Lines of a multi line module, or one dimmed line under `key` when it failed
+fn module_lines(key: &str, state: &ValueState<Vec<(String, String)>>) -> Vec<(String, String)> {
+    match state {
+        ValueState::Ok(lines) => lines.clone(),
+        ValueState::Failed(reason) => vec![(key.to_string(), unavailable(reason))],
+        ValueState::Unknown | ValueState::Disabled => Vec::new(),
+    }
+}
+
+// Build the Core/Hardware/Userspace sections.
+// Applies [format] templates, [order], compact mode and balance_sections.
+pub fn build_sections(
+    config: &Config,
+    statics: &StaticInfo,
+    dynamic: DynamicInfo,
+) -> Vec<Section> {
+    let formats = &config.formats;
+    let no_fields = |_: &str| vec![];
+    let format =
+        |module: &str, value: &str| format_module(formats, module, value.to_string(), no_fields);
+
+    // Which lines with nothing to show are left out: a [modules] override first, then
+    // hide_unknown. Unknown lines of HIDDEN_WHEN_UNKNOWN modules go by default, failed ones
+    // stay under --verbose so there's still a way to see what went wrong.
+    let hide_override = |module: &str| config.unknown_lines.get(module).copied();
+    let hide_unknown = |module: &str| {
+        hide_override(module)
+            .unwrap_or(config.hide_unknown || HIDDEN_WHEN_UNKNOWN.contains(&module))
+    };
+    let hide_failed =
+        |module: &str| !config.verbose && hide_override(module).unwrap_or(config.hide_unknown);
+
+    // What a module's line shows, None leaves the line out. `fields` feeds its [format] template.
+    let shown_with = |module: &str, state: &ValueState, fields: fn(&str) -> Fields| match state {
+        ValueState::Ok(value) => Some(format_module(formats, module, value.clone(), fields)),
+        ValueState::Unknown if hide_unknown(module) => None,
+        ValueState::Unknown => Some("unknown".to_string()),
+        ValueState::Failed(_) if hide_failed(module) => None,
+        ValueState::Failed(reason) => Some(unavailable(reason)),
+        ValueState::Disabled => None,
+    };
+    let shown = |module: &str, state: &ValueState| shown_with(module, state, |_| vec![]);
+    // Same for the multi line modules
+    let lines_of = |module: &str, key: &str, state: &ValueState<Vec<(String, String)>>| {
+        match state {
+            ValueState::Failed(_) if hide_failed(module) => Vec::new(),
+            state => module_lines(key, state),
+        }
+    };
+
+    // Modules that missed the deadline, dropped instead of showing "timed out" if configured
+    let hidden: Vec<&str> = if config.hide_timed_out {
+        statics.timed_out.iter().chain(&dynamic.timed_out).copied().collect()
+    } else {
+        Vec::new()
+    };
+
+    // Clickable values, hyperlink() leaves them alone when links are off or unsupported
+    let link = |value: String, url: Option<&str>| match url {
+        Some(url) => hyperlink(&value, url),
+        None => value,
+    };
+
+    // Each module maps to its lines, modules with nothing to show are left out of the map.
+    let mut core_results = ModuleLines::new();
+    let mut os = shown("os", &statics.os).map(|os| link(os, statics.os_url.as_deref()));
+    // "Ubuntu 24.04 (WSL2 on Windows 11 23H2)"
+    if let (Some(os), ValueState::Ok(_), Some(wsl)) = (&mut os, &statics.os, statics.wsl) {
+        match &statics.windows {
+            Some(windows) => os.push_str(&format!(" (WSL{} on {})", wsl, windows)),
+            None => os.push_str(&format!(" (WSL{})", wsl)),
+        }
+    }
+    insert_line(&mut core_results, "os", "OS", os);
+    insert_line(&mut core_results, "host_os", "Host OS", shown("host_os", &statics.host_os));
+    // nixos_detail hangs under the OS line as a tree
+    if let Some(lines) = core_results.get_mut("os") {
+        let last = statics.nixos_detail.len().saturating_sub(1);
+        for (i, detail) in statics.nixos_detail.iter().enumerate() {
+            let branch = if i == last { "╰─" } else { "├─" };
+            lines.push((branch.to_string(), detail.clone()));
+        }
+    }
+    let mut kernel = shown("kernel", &statics.kernel);
+    if let (Some(kernel), ValueState::Ok(_), Some(installed)) =
+        (&mut kernel, &statics.kernel, &statics.kernel_pending)
+    {
+        let pending = format!("⚠ {} installed — reboot pending", installed);
+        kernel.push_str(&format!(" {}", colorcontrol::color_warning(&pending)));
+    }
+    insert_line(&mut core_results, "kernel", "Kernel", kernel);
+    insert_line(&mut core_results, "uptime", "Uptime", shown("uptime", &dynamic.uptime));
+    insert_line(&mut core_results, "load", "Load", shown("load", &dynamic.load));
+    core_results.insert("boot", lines_of("boot", "Boot entries", &statics.boot));
+    let bootloader = shown("bootloader", &statics.bootloader);
+    insert_line(&mut core_results, "bootloader", "Boot", bootloader);
+    insert_line(&mut core_results, "today", "Today", shown("today", &dynamic.today));
+
+    core_results.retain(|id, _| !hidden.contains(id));
+    let core_lines = ordered_lines(CORE_ORDER, config.order.get("core"), core_results);
+    let core = Section::new("Core", core_lines);
+
+    // Vendor icon in front of the CPU and GPU, plain "[AMD]" without a nerd font
+    let icon = |vendor: Vendor, glyph: &str| {
+        if config.vendor_icons { vendor.icon(glyph) } else { String::new() }
+    };
+    let mut cpu = shown_with("cpu", &statics.cpu, hardwaremodules::cpu_fields);
+    if let (Some(cpu), ValueState::Ok(_)) = (&mut cpu, &statics.cpu) {
+        cpu.insert_str(0, &icon(statics.cpu_vendor, hardwaremodules::CPU_GLYPH));
+        if let Some(arch) = statics.cpu_arch {
+            cpu.push_str(&format!(" ({})", arch));
+        }
+        if let Some(cores) = statics.cpu_limit {
+            cpu.push_str(&format!(" ({:.1} cores limit)", cores));
+        }
+    }
+    // "GPU (dGPU): ..." / "GPU (iGPU): ..." on machines with more than one
+    // Architectures are per DRM card, only used when there's one for every listed GPU
+    let gpu_lines = match &statics.gpus {
+        ValueState::Ok(gpus) => gpus
+            .iter()
+            .enumerate()
+            .map(|(i, gpu)| {
+                let key = gpu.kind.map_or("GPU".into(), |kind| format!("GPU ({})", kind.label()));
+                let mut value =
+                    icon(gpu.vendor, hardwaremodules::GPU_GLYPH) + &format("gpu", &gpu.name);
+                if statics.gpu_archs.len() == gpus.len()
+                    && let Some(arch) = statics.gpu_archs[i]
+                {
+                    value.push_str(&format!(" ({})", arch));
+                }
+                (key, value)
+            })
+            .collect(),
+        ValueState::Failed(_) if hide_failed("gpu") => Vec::new(),
+        ValueState::Failed(reason) => vec![("GPU".to_string(), unavailable(reason))],
+        ValueState::Unknown | ValueState::Disabled if hide_unknown("gpu") => Vec::new(),
+        ValueState::Unknown | ValueState::Disabled => vec![("GPU".into(), "unknown".into())],
+    };
+    let mut storage = shown_with("storage", &dynamic.storage, hardwaremodules::storage_fields);
+    if let (Some(storage), ValueState::Ok(_), true) =
+        (&mut storage, &dynamic.storage, statics.encrypted)
+    {
+        storage.push_str(if helpers::get_cached_is_nerd_font() { " 󰌾 LUKS" } else { " LUKS" });
+    }
+    if let (Some(storage), ValueState::Ok(_), Some(delta)) =
+        (&mut storage, &dynamic.storage, &dynamic.storage_delta)
+    {
+        storage.push_str(&format!(" ({})", delta));
+    }
+
+    let mut hardware_results = ModuleLines::new();
+    insert_line(&mut hardware_results, "host", "Host", shown("host", &statics.host));
+    insert_line(&mut hardware_results, "cpu", "CPU", cpu);
+    hardware_results.insert("gpu", gpu_lines);
+    let mut memory = shown_with("memory", &dynamic.memory, hardwaremodules::memory_fields);
+    if let (Some(memory), ValueState::Ok(_), Some(delta)) =
+        (&mut memory, &dynamic.memory, &dynamic.memory_delta)
+    {
+        memory.push_str(&format!(" ({})", delta));
+    }
+    insert_line(&mut hardware_results, "memory", "Memory", memory);
+    insert_line(&mut hardware_results, "storage", "Storage", storage);
+    hardware_results.insert("displays", lines_of("displays", "Display", &statics.displays));
+    insert_line(&mut hardware_results, "battery", "Battery", shown("battery", &dynamic.battery));
+    insert_line(&mut hardware_results, "power", "Power", shown("power", &dynamic.power));
+    insert_line(&mut hardware_results, "ups", "UPS", shown("ups", &dynamic.ups));
+    insert_line(&mut hardware_results, "audio", "Audio", shown("audio", &statics.audio));
+    let activity = shown("activity", &dynamic.activity);
+    insert_line(&mut hardware_results, "activity", "Activity", activity);
+    let network = shown("network", &dynamic.network);
+    insert_line(&mut hardware_results, "network", "Network", network);
+
+    hardware_results.retain(|id, _| !hidden.contains(id));
+    let hardware_lines = ordered_lines(
+        HARDWARE_ORDER,
+        config.order.get("hardware"),
+        hardware_results,
+    );
+    let hardware = Section::new("Hardware", hardware_lines);
+
+    let mut userspace_results = ModuleLines::new();
+    let packages = shown("packages", &statics.packages);
+    insert_line(&mut userspace_results, "packages", "Packages", packages);
+    let terminal = shown("terminal", &statics.terminal);
+    insert_line(&mut userspace_results, "terminal", "Terminal", terminal);
+    // terminal_detail: the config findings hang under the Terminal line as a tree
+    if let ValueState::Ok(details) = &statics.terminal_detail
+        && let Some(lines) = userspace_results.get_mut("terminal")
+    {
+        let last = details.len().saturating_sub(1);
+        for (i, (key, value)) in details.iter().enumerate() {
+            let branch = if i == last { "╰─" } else { "├─" };
+            lines.push((branch.to_string(), format!("{}: {}", key, value)));
+        }
+    }
+    let shell = shown_with("shell", &statics.shell, userspacemodules::shell_fields);
+    insert_line(&mut userspace_results, "shell", "Shell", shell);
+    insert_line(&mut userspace_results, "prompt", "Prompt", shown("prompt", &statics.prompt));
+    let wm_url = match &statics.wm {
+        ValueState::Ok(name) => userspacemodules::wm_url(name),
+        _ => None,
+    };
+    let backend = statics.wm_backend.as_ref().filter(|_| config.wm_detail);
+    let wm = shown("wm", &statics.wm).map(|wm| match backend {
+        Some(backend) => format!("{} ({})", link(wm, wm_url), backend),
+        None => link(wm, wm_url),
+    });
+    insert_line(&mut userspace_results, "wm", "WM", wm);
+    insert_line(&mut userspace_results, "mode", "Mode", shown("mode", &statics.mode));
+    let session = shown("session", &dynamic.session);
+    insert_line(&mut userspace_results, "session", "Session", session);
+    insert_line(&mut userspace_results, "login", "Login", shown("login", &statics.login));
+    insert_line(&mut userspace_results, "ui", "UI", shown("ui", &statics.ui));
+    let desktop = shown("desktop", &statics.desktop);
+    insert_line(&mut userspace_results, "desktop", "Desktop", desktop);
+    insert_line(&mut userspace_results, "editor", "Editor", shown("editor", &statics.editor));
+    insert_line(&mut userspace_results, "font", "Terminal Font", shown("font", &statics.font));
+    // A failed terminal_detail read still gets its dimmed line
+    if let ValueState::Failed(_) = &statics.terminal_detail {
+        let lines = lines_of("font", "Terminal Font", &statics.terminal_detail);
+        userspace_results.insert("font", lines);
+    }
+
+    userspace_results.retain(|id, _| !hidden.contains(id));
+    let userspace_lines = ordered_lines(
+        USERSPACE_ORDER,
+        config.order.get("userspace"),
+        userspace_results,
+    );
+    let userspace = Section::new("Userspace", userspace_lines);
+    let mut sections = vec![core, hardware, userspace];
+
+    // Compact mode: forced in config, or automatic on narrow terminals
+    let terminal_width = terminalsize::get_terminal_size().map(|(cols, _)| cols as usize);
+    if config.compact || terminal_width.is_some_and(|width| width < config.compact_width) {
+        compact::compact_sections(&mut sections);
+    }
+    if config.balance_sections {
+        balance::balance_sections(&mut sections, config.balance_title.as_deref());
+    }
+
+    sections
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::configloader::Margins;
+    use crate::renderer::{draw_layout_sized, strip_escapes, visible_len};
+
+    fn ok(value: &str) -> ValueState {
+        ValueState::Ok(value.to_string())
+    }
+
+    // Everything found, nothing optional turned on
+    fn statics() -> StaticInfo {
+        // The vendor icons ask about the nerd font, and the cache is shared by every test.
+        // Pin it to what the golden tests draw with before anything detects it.
+        helpers::init_font_cache(None, Some(true));
+        StaticInfo {
+            os_name: "Arch Linux".into(),
+            os: ok("Arch Linux"),
+            nixos_detail: Vec::new(),
+            os_url: None,
+            wsl: None,
+            windows: None,
+            host_os: ValueState::Disabled,
+            kernel: ok("6.12.10-arch1-1"),
+            kernel_pending: None,
+            host: ValueState::Disabled,
+            cpu: ok("AMD Ryzen 7 7840U"),
+            gpus: ValueState::Ok(vec![Gpu {
+                name: "AMD Radeon 780M".into(),
+                vendor: Vendor::Amd,
+                kind: None,
+            }]),
+            cpu_vendor: Vendor::Amd,
+            cpu_limit: None,
+            cpu_arch: None,
+            gpu_archs: Vec::new(),
+            packages: ok("1204 (pacman)"),
+            terminal: ok("kitty"),
+            shell: ok("zsh 5.9"),
+            prompt: ValueState::Disabled,
+            wm: ok("Hyprland"),
+            wm_backend: None,
+            mode: ValueState::Disabled,
+            ui: ok("Adwaita"),
+            desktop: ValueState::Disabled,
+            editor: ValueState::Disabled,
+            login: ValueState::Disabled,
+            font: ok("JetBrainsMono Nerd Font"),
+            terminal_detail: ValueState::Disabled,
+            audio: ValueState::Disabled,
+            boot: ValueState::Disabled,
+            bootloader: ValueState::Disabled,
+            encrypted: false,
+            displays: ValueState::Ok(vec![("Display".into(), "eDP-1 2880x1800 @ 120Hz".into())]),
+            timed_out: Vec::new(),
+            failed: Vec::new(),
+            timings: Vec::new(),
+        }
+    }
+
+    // The value of the line with `key`, escapes stripped
+    fn value_of(sections: &[Section], key: &str) -> Option<String> {
+        let mut lines = sections.iter().flat_map(|section| &section.lines);
+        lines.find(|(k, _)| k == key).map(|(_, value)| strip_escapes(value))
+    }
+
+    // doctor lists what module_states gives it, so that has to be every module once, in order.
+    // Nothing in the fixtures is unknown, a module without its own arm there would be.
+    #[test]
+    fn module_states_cover_every_module() {
+        let dynamic = DynamicInfo::failed("panicked: statvfs".into());
+        let states = module_states(&statics(), &dynamic);
+        let ids: Vec<&str> = states.iter().map(|(id, _)| *id).collect();
+        assert_eq!(ids, module_names());
+        for (id, state) in &states {
+            assert!(!matches!(state, ValueState::Unknown), "{}", id);
+        }
+        let (id, uptime) = &states[3];
+        assert_eq!(*id, "uptime");
+        assert!(matches!(uptime, ValueState::Failed(reason) if reason == "panicked: statvfs"));
+    }
+
+    #[test]
+    fn panics_become_failed_with_the_message() {
+        let mut deadline = Deadline::new(&Config::default());
+        let font = deadline.text("font", Probe::spawn(|| -> String { panic!("no fc-match") }));
+        let gpus = deadline.lines("gpu", Probe::spawn(|| -> Vec<Gpu> { panic!("{} cards", 3) }));
+        assert!(matches!(&font, ValueState::Failed(reason) if reason == "panicked: no fc-match"));
+        assert!(matches!(&gpus, ValueState::Failed(reason) if reason == "panicked: 3 cards"));
+        assert_eq!(
+            deadline.failed,
+            [("font", "panicked: no fc-match".into()), ("gpu", "panicked: 3 cards".into())]
+        );
+        // A module that finished is still fine next to them
+        let shell = deadline.text("shell", Probe::spawn(|| "zsh 5.9".to_string()));
+        assert!(matches!(shell, ValueState::Ok(shell) if shell == "zsh 5.9"));
+    }
+
+    #[test]
+    fn panicking_modules_still_give_a_whole_layout() {
+        let mut deadline = Deadline::new(&Config::default());
+        let mut statics = statics();
+        statics.font = deadline.text("font", Probe::spawn(|| -> String { panic!("no fc-match") }));
+        statics.gpus = deadline.lines("gpu", Probe::spawn(|| -> Vec<Gpu> { panic!("no drm") }));
+        statics.cpu = ValueState::Unknown;
+        // collect_dynamic panicking takes every dynamic module with it
+        let dynamic = DynamicInfo::failed("panicked: statvfs".into());
+        let sections = build_sections(&Config::default(), &statics, dynamic);
+
+        let titles: Vec<&str> = sections.iter().map(|section| section.title.as_str()).collect();
+        assert_eq!(titles, ["Core", "Hardware", "Userspace"]);
+        for key in ["Terminal Font", "GPU", "Uptime", "Memory", "Storage"] {
+            assert_eq!(value_of(&sections, key).as_deref(), Some("unavailable"), "{}", key);
+        }
+        // Unknown still reads as before, and the modules that worked are all there
+        assert_eq!(value_of(&sections, "CPU").as_deref(), Some("unknown"));
+        assert_eq!(value_of(&sections, "Shell").as_deref(), Some("zsh 5.9"));
+        assert_eq!(value_of(&sections, "OS").as_deref(), Some("Arch Linux"));
+
+        // Every box closes and every row is as wide as its border
+        let (output, _) =
+            draw_layout_sized(None, &sections, None, None, Margins::default(), (120, 35));
+        let output = strip_escapes(&output);
+        let rows: Vec<&str> = output.lines().collect();
+        assert_eq!(rows.iter().filter(|row| row.starts_with('╭')).count(), 3);
+        assert_eq!(rows.iter().filter(|row| row.starts_with('╰')).count(), 3);
+        let width = visible_len(rows[0]);
+        assert!(rows.iter().all(|row| visible_len(row) == width), "{}", output);
+    }
+
+    #[test]
+    fn failed_lines_hide_like_unknown_ones() {
+        let mut statics = statics();
+        statics.font = ValueState::Failed("panicked: no fc-match".into());
+        let config = Config { hide_unknown: true, ..Config::default() };
+        let dynamic = DynamicInfo::failed("panicked: statvfs".into());
+        let sections = build_sections(&config, &statics, dynamic);
+        assert_eq!(value_of(&sections, "Terminal Font"), None);
+        assert_eq!(value_of(&sections, "Memory"), None);
+
+        // --verbose keeps them so there's a way to see what went wrong
+        let config = Config { hide_unknown: true, verbose: true, ..Config::default() };
+        let dynamic = DynamicInfo::failed("panicked: statvfs".into());
+        let sections = build_sections(&config, &statics, dynamic);
+        assert_eq!(value_of(&sections, "Terminal Font").as_deref(), Some("unavailable"));
+    }
+
+    // What each combination of hide_unknown, a [modules] override and --verbose does to an
+    // unknown or failed line, for a plain module (cpu), one hidden when unknown by default
+    // (editor), a dynamic one (battery) and the GPU list. Columns go hide_unknown off then on,
+    // each with no override, "hide-if-unknown" and "show".
+    #[test]
+    fn hide_combinations() {
+        const UNKNOWN_SHOWN: [[bool; 6]; 4] = [
+            [true, false, true, false, false, true],  // cpu
+            [false, false, true, false, false, true], // editor
+            [false, false, true, false, false, true], // battery
+            [true, false, true, false, false, true],  // gpu
+        ];
+        // Failed lines don't care about the default, --verbose always shows them
+        const FAILED_SHOWN: [bool; 6] = [true, false, true, false, false, true];
+        const MODULES: [(&str, &str); 4] =
+            [("cpu", "CPU"), ("editor", "Editor"), ("battery", "Battery"), ("gpu", "GPU")];
+
+        let overrides = [None, Some(true), Some(false)];
+        let columns = [false, true]
+            .into_iter()
+            .flat_map(|hide_unknown| overrides.map(|hide| (hide_unknown, hide)));
+        for (column, (hide_unknown, hide)) in columns.enumerate() {
+            for (failed, verbose) in [(false, false), (false, true), (true, false), (true, true)] {
+                let state = || match failed {
+                    true => ValueState::Failed("panicked: oops".into()),
+                    false => ValueState::Unknown,
+                };
+                let mut statics = statics();
+                statics.cpu = state();
+                statics.editor = state();
+                statics.gpus = match state() {
+                    ValueState::Failed(reason) => ValueState::Failed(reason),
+                    _ => ValueState::Unknown,
+                };
+                let mut dynamic = DynamicInfo::failed("panicked: statvfs".into());
+                dynamic.battery = state();
+                let unknown_lines =
+                    MODULES.iter().filter_map(|(id, _)| Some((id.to_string(), hide?))).collect();
+                let config = Config { hide_unknown, unknown_lines, verbose, ..Config::default() };
+                let sections = build_sections(&config, &statics, dynamic);
+
+                for (row, (id, key)) in MODULES.iter().enumerate() {
+                    let expected = match failed {
+                        true => verbose || FAILED_SHOWN[column],
+                        false => UNKNOWN_SHOWN[row][column],
+                    };
+                    let case = (id, failed, hide_unknown, hide, verbose);
+                    let text = if failed { "unavailable" } else { "unknown" };
+                    let value = value_of(&sections, key);
+                    assert_eq!(value.as_deref(), expected.then_some(text), "{:?}", case);
+                }
+            }
+        }
+    }
+
+    // A hidden line is as good as one that was never there, it doesn't widen the box either
+    #[test]
+    fn hidden_lines_leave_no_trace() {
+        let draw = |statics: &StaticInfo| {
+            let config = Config { hide_unknown: true, ..Config::default() };
+            let dynamic = DynamicInfo::failed("panicked: statvfs".into());
+            let sections = build_sections(&config, statics, dynamic);
+            draw_layout_sized(None, &sections, None, None, Margins::default(), (200, 60)).0
+        };
+        let mut unknown = statics();
+        unknown.font = ValueState::Unknown;
+        let mut disabled = statics();
+        disabled.font = ValueState::Disabled;
+        assert_eq!(draw(&unknown), draw(&disabled));
+        // Not that there was nothing to hide
+        assert_ne!(draw(&statics()), draw(&disabled));
+    }
+
+    // A stuck module is cut off at max_fetch_ms, the rest of the fetch goes on without it
+    #[test]
+    fn slow_modules_miss_the_deadline() {
+        let slow = |millis: u64, value: &str| {
+            let value = value.to_string();
+            Probe::spawn(move || {
+                std::thread::sleep(Duration::from_millis(millis));
+                value
+            })
+        };
+        let started = Instant::now();
+        let mut deadline = Deadline::new(&Config { max_fetch_ms: 150, ..Config::default() });
+        let packages = slow(1000, "1204 (pacman)");
+        let shell = slow(0, "zsh 5.9");
+        let font = slow(600, "JetBrainsMono Nerd Font");
+        let packages = deadline.text("packages", packages);
+        // Past the deadline a finished probe is still collected, a running one gets no more time
+        let shell = deadline.text("shell", shell);
+        let font = deadline.text("font", font);
+        let waited = started.elapsed();
+        assert!(waited < Duration::from_millis(500), "{:?}", waited);
+
+        assert!(matches!(&packages, ValueState::Failed(reason) if reason == TIMED_OUT));
+        assert!(matches!(&shell, ValueState::Ok(shell) if shell == "zsh 5.9"));
+        assert!(matches!(&font, ValueState::Failed(reason) if reason == TIMED_OUT));
+        assert_eq!(deadline.timed_out, ["packages", "font"]);
+
+        // 0 turns the deadline off and waits as long as it takes
+        let mut deadline = Deadline::new(&Config { max_fetch_ms: 0, ..Config::default() });
+        let font = deadline.text("font", slow(200, "JetBrainsMono Nerd Font"));
+        assert!(matches!(&font, ValueState::Ok(font) if font == "JetBrainsMono Nerd Font"));
+        assert!(deadline.timed_out.is_empty());
+    }
+
+    // "KWin (wayland, card1)" with wm_detail, just the name without
+    #[test]
+    fn wm_backend_after_the_name() {
+        let mut statics = statics();
+        statics.wm = ok("KWin");
+        statics.wm_backend = Some("wayland, card1".into());
+        let draw = |config: &Config| {
+            let dynamic = DynamicInfo::failed("panicked: statvfs".into());
+            value_of(&build_sections(config, &statics, dynamic), "WM")
+        };
+        let detail = Config { wm_detail: true, ..Config::default() };
+        assert_eq!(draw(&detail).as_deref(), Some("KWin (wayland, card1)"));
+        assert_eq!(draw(&Config::default()).as_deref(), Some("KWin"));
+    }
+
+    // usage_deltas is off unless set, and then nothing is measured for it
+    #[test]
+    fn deltas_off_measure_nothing() {
+        let measured = std::cell::Cell::new(0);
+        let bytes = || {
+            measured.set(measured.get() + 1);
+            Some(5_000_000_000)
+        };
+        let window = Config::default().usage_deltas;
+        assert_eq!(window, None);
+        assert_eq!(usage_delta(window, &deltas::MEMORY, bytes), None);
+        assert_eq!(usage_delta(window, &deltas::STORAGE, bytes), None);
+        assert_eq!(measured.get(), 0);
+    }
+
+    // Every measured value comes back out of --json as the number it went in as
+    #[test]
+    fn json_numbers_round_trip() {
+        let measured = Measured {
+            uptime_seconds: Some(273_845.12),
+            load_averages: Some([0.52, 0.48, 0.45]),
+            memory_kb: Some((6_815_744, 32_768_000)),
+            storage_bytes: Some((271_000_000_000, 1_000_204_886_016)),
+            battery_percent: Some(64),
+        };
+        let json = crate::export::to_json(&[], &json_numbers(measured));
+        let number = |key| crate::helpers::json_number(&json, key);
+        assert_eq!(number("uptime_seconds"), Some(273_845.12));
+        assert_eq!(number("load_1m"), Some(0.52));
+        assert_eq!(number("load_5m"), Some(0.48));
+        assert_eq!(number("load_15m"), Some(0.45));
+        assert_eq!(number("memory_used_kb"), Some(6_815_744.0));
+        assert_eq!(number("memory_total_kb"), Some(32_768_000.0));
+        assert_eq!(number("storage_used_bytes"), Some(271_000_000_000.0));
+        assert_eq!(number("storage_total_bytes"), Some(1_000_204_886_016.0));
+        assert_eq!(number("battery_percent"), Some(64.0));
+
+        // Nothing measured, no battery: left out, not null
+        let unmeasured = Measured {
+            uptime_seconds: None,
+            load_averages: None,
+            memory_kb: None,
+            storage_bytes: None,
+            battery_percent: None,
+        };
+        assert!(json_numbers(unmeasured).is_empty());
+        assert_eq!(DynamicInfo::failed("panicked".into()).numbers(), &[]);
+    }
+
+    #[test]
+    fn timed_out_lines_show_or_hide() {
+        let mut statics = statics();
+        statics.packages = ValueState::Failed(TIMED_OUT.into());
+        statics.timed_out = vec!["packages"];
+        let draw = |config: &Config| {
+            let dynamic = DynamicInfo::failed("panicked: statvfs".into());
+            value_of(&build_sections(config, &statics, dynamic), "Packages")
+        };
+        assert_eq!(draw(&Config::default()).as_deref(), Some(TIMED_OUT));
+        assert_eq!(draw(&Config { hide_timed_out: true, ..Config::default() }), None);
+    }
+
+    #[test]
+    fn vendor_icons_go_in_front() {
+        let value = |config: &Config, statics: &StaticInfo, key: &str| {
+            let dynamic = DynamicInfo::failed("panicked: statvfs".into());
+            value_of(&build_sections(config, statics, dynamic), key).unwrap()
+        };
+        let config = Config { vendor_icons: true, ..Config::default() };
+        assert_eq!(value(&config, &statics(), "CPU"), "󰻠 AMD Ryzen 7 7840U");
+        assert_eq!(value(&config, &statics(), "GPU"), "󰢮 AMD Radeon 780M");
+        assert_eq!(value(&Config::default(), &statics(), "CPU"), "AMD Ryzen 7 7840U");
+
+        // Nothing for a vendor that isn't known
+        let unknown = StaticInfo { cpu_vendor: Vendor::Unknown, ..statics() };
+        assert_eq!(value(&config, &unknown, "CPU"), "AMD Ryzen 7 7840U");
+    }
+}
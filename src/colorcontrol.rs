@@ -4,54 +4,654 @@
 
 use crate::configloader::ColorConfig;
 use std::sync::OnceLock;
-use tintify::{DynColors, TintColorize};
+#[cfg(feature = "art")]
+use tintify::{AnsiColors, DynColors, XtermColors};
 
 // Global color config, initialized once from config file
 static COLORS: OnceLock<ColorConfig> = OnceLock::new();
 
+// What the terminal can show, set once at startup
+static COLOR_MODE: OnceLock<ColorMode> = OnceLock::new();
+
 // Initialize colors from config - call this once at startup
-pub fn init_colors(colors: ColorConfig) {
+pub fn init_colors(colors: ColorConfig, mode: ColorMode) {
+    let _ = COLOR_MODE.set(mode);
+    let _ = PALETTE.set(Palette::new(&colors));
     let _ = COLORS.set(colors);
 }
 
+// How many colors the terminal does. Configured colors are RGB, on terminals without
+// truecolor they're swapped for the nearest color the terminal has.
+#[derive(Clone, Copy, PartialEq, Debug)]
+pub enum ColorMode {
+    Truecolor,
+    Ansi256,
+    Ansi16,
+}
+
+impl ColorMode {
+    // "truecolor", "256" or "16", as color_mode takes them
+    pub fn parse(value: &str) -> Option<Self> {
+        match value {
+            "truecolor" => Some(ColorMode::Truecolor),
+            "256" => Some(ColorMode::Ansi256),
+            "16" => Some(ColorMode::Ansi16),
+            _ => None,
+        }
+    }
+
+    pub fn name(self) -> &'static str {
+        match self {
+            ColorMode::Truecolor => "truecolor",
+            ColorMode::Ansi256 => "256",
+            ColorMode::Ansi16 => "16",
+        }
+    }
+
+    // Guess from the environment. COLORTERM is the only real truecolor signal,
+    // TERM narrows it down otherwise. Unknown terminals keep truecolor like before.
+    pub fn detect() -> Self {
+        let colorterm = std::env::var("COLORTERM").unwrap_or_default();
+        let term = std::env::var("TERM").unwrap_or_default();
+        Self::from_env(&colorterm, &term)
+    }
+
+    // detect over the COLORTERM and TERM values
+    fn from_env(colorterm: &str, term: &str) -> Self {
+        if colorterm == "truecolor" || colorterm == "24bit" {
+            return ColorMode::Truecolor;
+        }
+
+        let truecolor_terms = ["xterm-kitty", "xterm-ghostty", "alacritty", "foot", "wezterm"];
+        if term.contains("direct") || truecolor_terms.iter().any(|name| term.starts_with(name)) {
+            ColorMode::Truecolor
+        } else if term == "linux" || term == "ansi" || term == "cons25" || term.starts_with("vt") {
+            ColorMode::Ansi16
+        } else if term.contains("256color") {
+            ColorMode::Ansi256
+        } else {
+            ColorMode::Truecolor
+        }
+    }
+}
+
+pub fn color_mode() -> ColorMode {
+    *COLOR_MODE.get_or_init(|| ColorMode::Truecolor)
+}
+
+// Foreground SGR for an RGB color in the current color mode
+pub fn fg_sgr(rgb: (u8, u8, u8)) -> String {
+    match color_mode() {
+        ColorMode::Truecolor => format!("\x1b[38;2;{};{};{}m", rgb.0, rgb.1, rgb.2),
+        ColorMode::Ansi256 => format!("\x1b[38;5;{}m", rgb_to_256(rgb)),
+        ColorMode::Ansi16 => match rgb_to_16(rgb) {
+            index @ 0..8 => format!("\x1b[{}m", 30 + index),
+            index => format!("\x1b[{}m", 90 + index - 8),
+        },
+    }
+}
+
+// Squared distance, good enough to pick the closest palette entry
+fn distance(a: (u8, u8, u8), b: (u8, u8, u8)) -> u32 {
+    let channel = |x: u8, y: u8| (x as i32 - y as i32).pow(2) as u32;
+    channel(a.0, b.0) + channel(a.1, b.1) + channel(a.2, b.2)
+}
+
+// Nearest xterm-256 color: the 6x6x6 cube (16-231) or the grayscale ramp (232-255),
+// whichever lands closer. The 16 base colors are skipped, terminals theme those.
+pub fn rgb_to_256(rgb: (u8, u8, u8)) -> u8 {
+    const CUBE_LEVELS: [u8; 6] = [0, 95, 135, 175, 215, 255];
+    let cube_index = |v: u8| match v {
+        0..48 => 0,
+        48..115 => 1,
+        _ => (v - 35) / 40,
+    };
+    let (r, g, b) = (cube_index(rgb.0), cube_index(rgb.1), cube_index(rgb.2));
+    let cube_color = (CUBE_LEVELS[r as usize], CUBE_LEVELS[g as usize], CUBE_LEVELS[b as usize]);
+
+    // Ramp runs 8, 18, ... 238
+    let average = (rgb.0 as u32 + rgb.1 as u32 + rgb.2 as u32) / 3;
+    let gray_index = (average.saturating_sub(3) / 10).min(23) as u8;
+    let gray_level = 8 + 10 * gray_index;
+
+    if distance(rgb, (gray_level, gray_level, gray_level)) < distance(rgb, cube_color) {
+        232 + gray_index
+    } else {
+        16 + 36 * r + 6 * g + b
+    }
+}
+
+// Nearest of the 16 ANSI colors (0-7 normal, 8-15 bright), going by xterm's default palette
+pub fn rgb_to_16(rgb: (u8, u8, u8)) -> u8 {
+    const ANSI_PALETTE: [(u8, u8, u8); 16] = [
+        (0, 0, 0),
+        (205, 0, 0),
+        (0, 205, 0),
+        (205, 205, 0),
+        (0, 0, 238),
+        (205, 0, 205),
+        (0, 205, 205),
+        (229, 229, 229),
+        (127, 127, 127),
+        (255, 0, 0),
+        (0, 255, 0),
+        (255, 255, 0),
+        (92, 92, 255),
+        (255, 0, 255),
+        (0, 255, 255),
+        (255, 255, 255),
+    ];
+    (0..16u8)
+        .min_by_key(|&index| distance(rgb, ANSI_PALETTE[index as usize]))
+        .unwrap_or(7)
+}
+
+// An art color for inkline in the current color mode
+#[cfg(feature = "art")]
+pub fn art_color(rgb: (u8, u8, u8)) -> DynColors {
+    const ANSI_COLORS: [AnsiColors; 16] = [
+        AnsiColors::Black,
+        AnsiColors::Red,
+        AnsiColors::Green,
+        AnsiColors::Yellow,
+        AnsiColors::Blue,
+        AnsiColors::Magenta,
+        AnsiColors::Cyan,
+        AnsiColors::White,
+        AnsiColors::BrightBlack,
+        AnsiColors::BrightRed,
+        AnsiColors::BrightGreen,
+        AnsiColors::BrightYellow,
+        AnsiColors::BrightBlue,
+        AnsiColors::BrightMagenta,
+        AnsiColors::BrightCyan,
+        AnsiColors::BrightWhite,
+    ];
+    match color_mode() {
+        ColorMode::Truecolor => DynColors::Rgb(rgb.0, rgb.1, rgb.2),
+        ColorMode::Ansi256 => DynColors::Xterm(XtermColors::from(rgb_to_256(rgb))),
+        ColorMode::Ansi16 => DynColors::Ansi(ANSI_COLORS[rgb_to_16(rgb) as usize]),
+    }
+}
+
 // Get the current color config
 fn colors() -> &'static ColorConfig {
     COLORS.get_or_init(ColorConfig::default)
 }
 
 // Get ASCII art colors as DynColors array for inkline
+#[cfg(feature = "art")]
 pub fn get_art_colors() -> Vec<DynColors> {
     let c = colors();
-    vec![
-        DynColors::Rgb(c.art_1.0, c.art_1.1, c.art_1.2),
-        DynColors::Rgb(c.art_2.0, c.art_2.1, c.art_2.2),
-        DynColors::Rgb(c.art_3.0, c.art_3.1, c.art_3.2),
-        DynColors::Rgb(c.art_4.0, c.art_4.1, c.art_4.2),
-        DynColors::Rgb(c.art_5.0, c.art_5.1, c.art_5.2),
-        DynColors::Rgb(c.art_6.0, c.art_6.1, c.art_6.2),
-        DynColors::Rgb(c.art_7.0, c.art_7.1, c.art_7.2),
-        DynColors::Rgb(c.art_8.0, c.art_8.1, c.art_8.2),
-        DynColors::Rgb(c.art_9.0, c.art_9.1, c.art_9.2),
-    ]
+    [c.art_1, c.art_2, c.art_3, c.art_4, c.art_5, c.art_6, c.art_7, c.art_8, c.art_9]
+        .into_iter()
+        .map(art_color)
+        .collect()
+}
+
+// Colors of a desktop theme in Material You roles, what colors_from reads.
+// Any role can be missing, the matching slowfetch colors keep their value then.
+#[derive(Debug, Default, PartialEq)]
+pub struct ThemePalette {
+    pub primary: Option<(u8, u8, u8)>,
+    pub secondary: Option<(u8, u8, u8)>,
+    pub surface_text: Option<(u8, u8, u8)>,
+    pub warning: Option<(u8, u8, u8)>,
+    pub error: Option<(u8, u8, u8)>,
+}
+
+// Point between two colors, t = 0 is `from`, t = 1 is `to`
+fn mix(from: (u8, u8, u8), to: (u8, u8, u8), t: f64) -> (u8, u8, u8) {
+    let channel = |a: u8, b: u8| (a as f64 + (b as f64 - a as f64) * t).round() as u8;
+    (channel(from.0, to.0), channel(from.1, to.1), channel(from.2, to.2))
+}
+
+// Derive the theme colors from a palette: border and keys in primary, titles in secondary,
+// values in the surface text color, and the art as a primary -> secondary gradient.
+// Colors named in `keep` (set in [colors]) stay as they are.
+pub fn apply_theme_palette(colors: &mut ColorConfig, palette: &ThemePalette, keep: &[String]) {
+    let primary = palette.primary;
+    let secondary = palette.secondary.or(primary);
+    let mut art = [None; 9];
+    if let (Some(from), Some(to)) = (primary, secondary) {
+        for (slot, color) in art.iter_mut().enumerate() {
+            *color = Some(mix(from, to, slot as f64 / 8.0));
+        }
+    }
+
+    let [art_1, art_2, art_3, art_4, art_5, art_6, art_7, art_8, art_9] = art;
+    let targets = [
+        ("border", &mut colors.border, primary),
+        ("title", &mut colors.title, secondary),
+        ("key", &mut colors.key, primary),
+        ("value", &mut colors.value, palette.surface_text),
+        ("warning", &mut colors.warning, palette.warning),
+        ("critical", &mut colors.critical, palette.error),
+        ("art_1", &mut colors.art_1, art_1),
+        ("art_2", &mut colors.art_2, art_2),
+        ("art_3", &mut colors.art_3, art_3),
+        ("art_4", &mut colors.art_4, art_4),
+        ("art_5", &mut colors.art_5, art_5),
+        ("art_6", &mut colors.art_6, art_6),
+        ("art_7", &mut colors.art_7, art_7),
+        ("art_8", &mut colors.art_8, art_8),
+        ("art_9", &mut colors.art_9, art_9),
+    ];
+    for (name, target, color) in targets {
+        if let Some(color) = color
+            && !keep.iter().any(|kept| kept == name)
+        {
+            *target = color;
+        }
+    }
+}
+
+// WCAG's minimum contrast for large text and UI parts, box text is about that
+const MIN_CONTRAST: f64 = 3.0;
+
+// Relative luminance (WCAG): 0 for black, 1 for white
+fn relative_luminance((r, g, b): (u8, u8, u8)) -> f64 {
+    let linear = |channel: u8| {
+        let c = channel as f64 / 255.0;
+        if c <= 0.04045 { c / 12.92 } else { ((c + 0.055) / 1.055).powf(2.4) }
+    };
+    0.2126 * linear(r) + 0.7152 * linear(g) + 0.0722 * linear(b)
+}
+
+// Contrast ratio between two colors, 1:1 (same) up to 21:1 (black on white)
+fn contrast_ratio(a: (u8, u8, u8), b: (u8, u8, u8)) -> f64 {
+    let (la, lb) = (relative_luminance(a), relative_luminance(b));
+    (la.max(lb) + 0.05) / (la.min(lb) + 0.05)
+}
+
+// Below this luminance white text reads better than black, i.e. a dark background
+fn is_dark(background: (u8, u8, u8)) -> bool {
+    relative_luminance(background) < 0.179
+}
+
+fn rgb_to_hsl((r, g, b): (u8, u8, u8)) -> (f64, f64, f64) {
+    let (r, g, b) = (r as f64 / 255.0, g as f64 / 255.0, b as f64 / 255.0);
+    let max = r.max(g).max(b);
+    let min = r.min(g).min(b);
+    let lightness = (max + min) / 2.0;
+    let delta = max - min;
+    if delta == 0.0 {
+        return (0.0, 0.0, lightness);
+    }
+
+    let saturation = delta / (1.0 - (2.0 * lightness - 1.0).abs());
+    let hue = if max == r {
+        ((g - b) / delta).rem_euclid(6.0)
+    } else if max == g {
+        (b - r) / delta + 2.0
+    } else {
+        (r - g) / delta + 4.0
+    };
+    (hue * 60.0, saturation, lightness)
+}
+
+fn hsl_to_rgb((hue, saturation, lightness): (f64, f64, f64)) -> (u8, u8, u8) {
+    let chroma = (1.0 - (2.0 * lightness - 1.0).abs()) * saturation;
+    let x = chroma * (1.0 - ((hue / 60.0).rem_euclid(2.0) - 1.0).abs());
+    let (r, g, b) = match hue as u32 {
+        0..60 => (chroma, x, 0.0),
+        60..120 => (x, chroma, 0.0),
+        120..180 => (0.0, chroma, x),
+        180..240 => (0.0, x, chroma),
+        240..300 => (x, 0.0, chroma),
+        _ => (chroma, 0.0, x),
+    };
+    let offset = lightness - chroma / 2.0;
+    let channel = |c: f64| ((c + offset) * 255.0).round().clamp(0.0, 255.0) as u8;
+    (channel(r), channel(g), channel(b))
+}
+
+// Lighten (dark background) or darken (light background) a color in HSL until it has
+// enough contrast, keeping its hue. Ends at white/black if that's what it takes.
+fn fit_contrast(color: (u8, u8, u8), background: (u8, u8, u8)) -> (u8, u8, u8) {
+    let (hue, saturation, mut lightness) = rgb_to_hsl(color);
+    let step = if is_dark(background) { 0.02 } else { -0.02 };
+    let mut adjusted = color;
+    // 50 steps walk the whole lightness range
+    for _ in 0..50 {
+        if contrast_ratio(adjusted, background) >= MIN_CONTRAST {
+            break;
+        }
+        lightness = (lightness + step).clamp(0.0, 1.0);
+        adjusted = hsl_to_rgb((hue, saturation, lightness));
+    }
+    adjusted
+}
+
+// Check the theme colors against the background. With `adjust` the ones that are hard to
+// read get fixed up, otherwise they're returned as hints for stderr.
+pub fn check_contrast(
+    colors: &mut ColorConfig,
+    background: (u8, u8, u8),
+    adjust: bool,
+) -> Vec<String> {
+    let shade = if is_dark(background) { "dark" } else { "light" };
+    let mut hints = Vec::new();
+    for (name, color) in [
+        ("border", &mut colors.border),
+        ("title", &mut colors.title),
+        ("key", &mut colors.key),
+        ("value", &mut colors.value),
+    ] {
+        let ratio = contrast_ratio(*color, background);
+        if ratio >= MIN_CONTRAST {
+            continue;
+        }
+        if adjust {
+            *color = fit_contrast(*color, background);
+        } else {
+            hints.push(format!(
+                "{} color #{:02X}{:02X}{:02X} is hard to read on a {} background ({:.1}:1), \
+                 set auto_contrast = true to adjust it",
+                name, color.0, color.1, color.2, shade, ratio
+            ));
+        }
+    }
+    hints
+}
+
+// Foreground reset, ends every colored span
+pub const RESET: &str = "\x1b[39m";
+
+// SGR prefixes for the theme colors, built once so coloring text is just
+// string concatenation instead of a formatter round trip per fragment
+pub struct Palette {
+    pub border: String,
+    pub title: String,
+    pub key: String,
+    pub value: String,
+    pub warning: String,
+    pub critical: String,
+    pub unavailable: String,
+}
+
+impl Palette {
+    fn new(colors: &ColorConfig) -> Self {
+        Self {
+            border: fg_sgr(colors.border),
+            title: fg_sgr(colors.title),
+            key: fg_sgr(colors.key),
+            value: fg_sgr(colors.value),
+            warning: fg_sgr(colors.warning),
+            critical: fg_sgr(colors.critical),
+            unavailable: fg_sgr(colors.unavailable),
+        }
+    }
+}
+
+static PALETTE: OnceLock<Palette> = OnceLock::new();
+
+// Get the precomputed prefixes
+pub fn palette() -> &'static Palette {
+    PALETTE.get_or_init(|| Palette::new(colors()))
+}
+
+// prefix + text + reset in a single allocation
+fn paint(prefix: &str, text: &str) -> String {
+    let mut painted = String::with_capacity(prefix.len() + text.len() + RESET.len());
+    painted.push_str(prefix);
+    painted.push_str(text);
+    painted.push_str(RESET);
+    painted
 }
 
 // Color application functions
 pub fn color_border(text: &str) -> String {
-    let c = colors().border;
-    text.truecolor(c.0, c.1, c.2).to_string()
+    paint(&palette().border, text)
 }
 
 pub fn color_title(text: &str) -> String {
-    let c = colors().title;
-    text.truecolor(c.0, c.1, c.2).to_string()
+    paint(&palette().title, text)
 }
 
 pub fn color_key(text: &str) -> String {
-    let c = colors().key;
-    text.truecolor(c.0, c.1, c.2).to_string()
+    paint(&palette().key, text)
 }
 
 pub fn color_value(text: &str) -> String {
-    let c = colors().value;
-    text.truecolor(c.0, c.1, c.2).to_string()
+    paint(&palette().value, text)
+}
+
+pub fn color_warning(text: &str) -> String {
+    paint(&palette().warning, text)
+}
+
+pub fn color_critical(text: &str) -> String {
+    paint(&palette().critical, text)
+}
+
+pub fn color_unavailable(text: &str) -> String {
+    paint(&palette().unavailable, text)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn cube_colors_map_to_themselves() {
+        assert_eq!(rgb_to_256((0, 0, 0)), 16);
+        assert_eq!(rgb_to_256((255, 0, 0)), 196);
+        assert_eq!(rgb_to_256((95, 135, 175)), 67);
+        assert_eq!(rgb_to_256((255, 255, 255)), 231);
+        // Every cube entry comes back as itself
+        const LEVELS: [u8; 6] = [0, 95, 135, 175, 215, 255];
+        for (r, &red) in LEVELS.iter().enumerate() {
+            for (g, &green) in LEVELS.iter().enumerate() {
+                for (b, &blue) in LEVELS.iter().enumerate() {
+                    let index = 16 + 36 * r + 6 * g + b;
+                    assert_eq!(rgb_to_256((red, green, blue)) as usize, index);
+                }
+            }
+        }
+    }
+
+    #[test]
+    fn nearest_256_color() {
+        // Dracula purple, the default key color
+        assert_eq!(rgb_to_256((189, 147, 249)), 141);
+        // Just off a cube color, and on the boundaries between cube levels
+        assert_eq!(rgb_to_256((250, 10, 10)), 196);
+        assert_eq!(rgb_to_256((47, 0, 255)), 21);
+        assert_eq!(rgb_to_256((48, 0, 255)), 57);
+        assert_eq!(rgb_to_256((114, 0, 255)), 57);
+        assert_eq!(rgb_to_256((115, 0, 255)), 93);
+        // Dark red is nearer the dark end of the gray ramp than cube black
+        assert_eq!(rgb_to_256((47, 0, 0)), 233);
+    }
+
+    #[test]
+    fn grays_use_the_ramp() {
+        assert_eq!(rgb_to_256((8, 8, 8)), 232);
+        assert_eq!(rgb_to_256((128, 128, 128)), 244);
+        assert_eq!(rgb_to_256((238, 238, 238)), 255);
+        // Close enough to gray that the ramp beats the cube
+        assert_eq!(rgb_to_256((40, 42, 54)), 236);
+        // Cube black and white are nearer than the ends of the ramp
+        assert_eq!(rgb_to_256((2, 2, 2)), 16);
+        assert_eq!(rgb_to_256((253, 253, 253)), 231);
+    }
+
+    #[test]
+    fn nearest_16_color() {
+        assert_eq!(rgb_to_16((10, 10, 10)), 0);
+        assert_eq!(rgb_to_16((205, 0, 0)), 1);
+        assert_eq!(rgb_to_16((255, 85, 85)), 9);
+        assert_eq!(rgb_to_16((128, 128, 128)), 8);
+        assert_eq!(rgb_to_16((80, 250, 80)), 10);
+        assert_eq!(rgb_to_16((250, 250, 250)), 15);
+    }
+
+    #[test]
+    fn mode_from_the_environment() {
+        let cases = [
+            ("truecolor", "xterm-256color", ColorMode::Truecolor),
+            ("24bit", "linux", ColorMode::Truecolor),
+            ("", "xterm-kitty", ColorMode::Truecolor),
+            ("", "xterm-direct", ColorMode::Truecolor),
+            ("", "xterm-256color", ColorMode::Ansi256),
+            ("", "screen-256color", ColorMode::Ansi256),
+            ("", "linux", ColorMode::Ansi16),
+            ("", "vt100", ColorMode::Ansi16),
+            // Nothing to go by, keeps truecolor
+            ("", "", ColorMode::Truecolor),
+        ];
+        for (colorterm, term, mode) in cases {
+            assert_eq!(ColorMode::from_env(colorterm, term), mode, "{:?} {:?}", colorterm, term);
+        }
+        for mode in [ColorMode::Truecolor, ColorMode::Ansi256, ColorMode::Ansi16] {
+            assert_eq!(ColorMode::parse(mode.name()), Some(mode));
+        }
+        assert_eq!(ColorMode::parse("8"), None);
+    }
+
+    fn close(a: f64, b: f64) -> bool {
+        (a - b).abs() < 0.005
+    }
+
+    #[test]
+    fn luminance_and_contrast() {
+        assert_eq!(relative_luminance((0, 0, 0)), 0.0);
+        assert!(close(relative_luminance((255, 255, 255)), 1.0));
+        assert!(close(relative_luminance((128, 128, 128)), 0.216));
+        // Green counts for most of it
+        assert!(relative_luminance((0, 255, 0)) > relative_luminance((255, 0, 255)));
+
+        assert!(close(contrast_ratio((0, 0, 0), (255, 255, 255)), 21.0));
+        assert!(close(contrast_ratio((255, 255, 255), (0, 0, 0)), 21.0));
+        assert!(close(contrast_ratio((0x77, 0x77, 0x77), (255, 255, 255)), 4.48));
+        assert_eq!(contrast_ratio((40, 42, 54), (40, 42, 54)), 1.0);
+    }
+
+    #[test]
+    fn hsl_round_trip() {
+        assert_eq!(rgb_to_hsl((255, 0, 0)), (0.0, 1.0, 0.5));
+        assert_eq!(rgb_to_hsl((0, 255, 255)), (180.0, 1.0, 0.5));
+        let (hue, saturation, lightness) = rgb_to_hsl((0xBD, 0x93, 0xF9));
+        assert!((hue - 264.7).abs() < 0.1, "{}", hue);
+        assert!(close(saturation, 0.89) && close(lightness, 0.776));
+        // Grays have no hue or saturation
+        assert_eq!(rgb_to_hsl((128, 128, 128)).1, 0.0);
+
+        for color in [
+            (0, 0, 0),
+            (255, 255, 255),
+            (0xFF, 0x79, 0xC6),
+            (0xBD, 0x93, 0xF9),
+            (0x8B, 0xE9, 0xFD),
+            (0x62, 0x72, 0xA4),
+            (12, 200, 7),
+            (128, 128, 128),
+        ] {
+            assert_eq!(hsl_to_rgb(rgb_to_hsl(color)), color);
+        }
+    }
+
+    #[test]
+    fn fitting_keeps_the_hue() {
+        let black = (0, 0, 0);
+        let white = (255, 255, 255);
+
+        // Too dark on black gets lighter, too light on white darker
+        let fixed = fit_contrast((0x22, 0x22, 0x44), black);
+        assert!(contrast_ratio(fixed, black) >= MIN_CONTRAST);
+        assert!(relative_luminance(fixed) > relative_luminance((0x22, 0x22, 0x44)));
+        assert!((rgb_to_hsl(fixed).0 - 240.0).abs() < 2.0, "{:?}", fixed);
+
+        let fixed = fit_contrast((0xF1, 0xFA, 0x8C), white);
+        assert!(contrast_ratio(fixed, white) >= MIN_CONTRAST);
+        assert!((rgb_to_hsl(fixed).0 - rgb_to_hsl((0xF1, 0xFA, 0x8C)).0).abs() < 2.0);
+
+        // Readable already, left alone
+        assert_eq!(fit_contrast((0x8B, 0xE9, 0xFD), black), (0x8B, 0xE9, 0xFD));
+        // Invisible on the background itself: just over the middle counts as light, so darker
+        let gray = (119, 119, 119);
+        let fixed = fit_contrast(gray, gray);
+        assert!(contrast_ratio(fixed, gray) >= MIN_CONTRAST);
+        assert!(fixed.0 < 119 && fixed.0 == fixed.1 && fixed.1 == fixed.2, "{:?}", fixed);
+    }
+
+    #[test]
+    fn hints_without_auto_contrast() {
+        let mut colors = ColorConfig {
+            value: (0x22, 0x22, 0x22),
+            ..ColorConfig::default()
+        };
+        let hints = check_contrast(&mut colors, (0, 0, 0), false);
+        assert_eq!(
+            hints,
+            ["value color #222222 is hard to read on a dark background (1.3:1), \
+              set auto_contrast = true to adjust it"]
+        );
+        // Nothing changed without auto_contrast, explicit background or not
+        assert_eq!(colors.value, (0x22, 0x22, 0x22));
+
+        let hints = check_contrast(&mut colors, (0, 0, 0), true);
+        assert!(hints.is_empty());
+        assert!(contrast_ratio(colors.value, (0, 0, 0)) >= MIN_CONTRAST);
+        // The defaults are fine on a dark background
+        assert_eq!(colors.key, ColorConfig::default().key);
+    }
+
+    #[test]
+    fn full_theme_palette() {
+        let palette = ThemePalette {
+            primary: Some((0, 0, 200)),
+            secondary: Some((200, 0, 0)),
+            surface_text: Some((220, 220, 220)),
+            warning: Some((250, 180, 0)),
+            error: Some((240, 40, 40)),
+        };
+        let mut colors = ColorConfig::default();
+        apply_theme_palette(&mut colors, &palette, &[]);
+        assert_eq!((colors.border, colors.key), ((0, 0, 200), (0, 0, 200)));
+        assert_eq!(colors.title, (200, 0, 0));
+        assert_eq!(colors.value, (220, 220, 220));
+        assert_eq!((colors.warning, colors.critical), ((250, 180, 0), (240, 40, 40)));
+        // The art runs from primary to secondary
+        assert_eq!(colors.art_1, (0, 0, 200));
+        assert_eq!(colors.art_5, (100, 0, 100));
+        assert_eq!(colors.art_9, (200, 0, 0));
+    }
+
+    #[test]
+    fn partial_palettes_fill_from_defaults() {
+        let defaults = ColorConfig::default();
+        let palette = ThemePalette { primary: Some((0, 0, 200)), ..ThemePalette::default() };
+        let mut colors = ColorConfig::default();
+        apply_theme_palette(&mut colors, &palette, &[]);
+        // No secondary: titles and the whole art take the primary
+        assert_eq!(colors.title, (0, 0, 200));
+        assert_eq!((colors.art_1, colors.art_9), ((0, 0, 200), (0, 0, 200)));
+        // The roles it doesn't have keep the defaults
+        assert_eq!(colors.value, defaults.value);
+        assert_eq!((colors.warning, colors.critical), (defaults.warning, defaults.critical));
+
+        // Without a primary there's nothing to build the art from
+        let palette = ThemePalette { warning: Some((1, 2, 3)), ..ThemePalette::default() };
+        let mut colors = ColorConfig::default();
+        apply_theme_palette(&mut colors, &palette, &[]);
+        assert_eq!(colors.warning, (1, 2, 3));
+        assert_eq!((colors.border, colors.art_1), (defaults.border, defaults.art_1));
+    }
+
+    #[test]
+    fn colors_set_in_the_config_stay() {
+        let palette = ThemePalette {
+            primary: Some((0, 0, 200)),
+            secondary: Some((200, 0, 0)),
+            ..ThemePalette::default()
+        };
+        let mut colors =
+            ColorConfig { border: (9, 9, 9), art_9: (8, 8, 8), ..ColorConfig::default() };
+        let keep = ["border".to_string(), "art_9".to_string()];
+        apply_theme_palette(&mut colors, &palette, &keep);
+        assert_eq!((colors.border, colors.art_9), ((9, 9, 9), (8, 8, 8)));
+        assert_eq!((colors.key, colors.art_8), ((0, 0, 200), (175, 0, 25)));
+    }
 }
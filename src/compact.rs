@@ -0,0 +1,140 @@
+// Compact mode for Slowfetch.
+// Shortens keys and values after all modules are collected, so narrow terminals
+// don't wrap even in the sections-only layout.
+
+use crate::renderer::Section;
+
+// Long keys and their compact versions
+const KEY_ABBREVIATIONS: &[(&str, &str)] = &[
+    ("Memory", "Mem"),
+    ("Storage", "Disk"),
+    ("Terminal Font", "Font"),
+    ("Terminal", "Term"),
+    ("Packages", "Pkgs"),
+];
+
+// Marketing words that don't help tell CPUs/GPUs apart
+const HARDWARE_NOISE: &[&str] = &[
+    "(TM)", "(tm)", "(R)", "(r)", "Radeon", "GeForce", "Core", "Processor", "CPU",
+];
+
+// Shorten every section in place
+pub fn compact_sections(sections: &mut [Section]) {
+    for section in sections {
+        for (key, value) in &mut section.lines {
+            *value = match key.as_str() {
+                // "GPU (iGPU)" / "GPU (dGPU)" with more than one
+                key if key == "CPU" || key.starts_with("GPU") => abbreviate_hardware(value),
+                "Memory" | "Storage" => drop_usage_bar(value),
+                _ => std::mem::take(value),
+            };
+            if let Some((_, short)) = KEY_ABBREVIATIONS.iter().find(|(long, _)| long == key) {
+                *key = short.to_string();
+            }
+        }
+    }
+}
+
+// "Intel(R) Core(TM) i7-12700K @ 5.00GHz" -> "Intel i7-12700K @ 5.00GHz"
+// "AMD Ryzen 9 7950X3D 16-Core Processor" -> "AMD Ryzen 9 7950X3D"
+// "NVIDIA GeForce RTX 4090" -> "NVIDIA RTX 4090"
+fn abbreviate_hardware(name: &str) -> String {
+    let mut name = name.to_string();
+    for noise in HARDWARE_NOISE.iter().filter(|noise| noise.starts_with('(')) {
+        name = name.replace(noise, "");
+    }
+
+    name.split_whitespace()
+        .filter(|word| !HARDWARE_NOISE.contains(word) && !is_core_count(word))
+        .collect::<Vec<_>>()
+        .join(" ")
+}
+
+// "16-Core", "8-core", "96-Cores"
+fn is_core_count(word: &str) -> bool {
+    let word = word.to_lowercase();
+    let count = word.strip_suffix("-core").or_else(|| word.strip_suffix("-cores"));
+    count.is_some_and(|count| !count.is_empty() && count.chars().all(|c| c.is_ascii_digit()))
+}
+
+// "[=====     ] 15GB/32GB" -> "15/32GB". Values that don't end in used/total are left alone.
+fn drop_usage_bar(value: &str) -> String {
+    let Some(usage) = value.split_whitespace().last() else {
+        return value.to_string();
+    };
+    let Some((used, total)) = usage.split_once('/') else {
+        return value.to_string();
+    };
+
+    let unit_start = |s: &str| s.find(|c: char| c.is_ascii_alphabetic());
+    match (unit_start(used), unit_start(total)) {
+        // Same unit on both sides, only keep it once
+        (Some(u), Some(t)) if used[u..] == total[t..] => format!("{}/{}", &used[..u], total),
+        (Some(_), Some(_)) => usage.to_string(),
+        _ => value.to_string(),
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn real_cpu_and_gpu_names() {
+        let names = [
+            ("Intel(R) Core(TM) i7-12700K @ 5.00GHz", "Intel i7-12700K @ 5.00GHz"),
+            ("Intel(R) Core(TM) Ultra 7 155H", "Intel Ultra 7 155H"),
+            (
+                "11th Gen Intel(R) Core(TM) i5-1135G7 @ 2.40GHz",
+                "11th Gen Intel i5-1135G7 @ 2.40GHz",
+            ),
+            ("Intel(R) Xeon(R) CPU E5-2680 v4 @ 2.40GHz", "Intel Xeon E5-2680 v4 @ 2.40GHz"),
+            ("AMD Ryzen 9 7950X3D 16-Core Processor", "AMD Ryzen 9 7950X3D"),
+            ("AMD Ryzen 7 5800X 8-Core Processor @ 4.85GHz", "AMD Ryzen 7 5800X @ 4.85GHz"),
+            ("AMD Ryzen Threadripper PRO 7995WX 96-Cores", "AMD Ryzen Threadripper PRO 7995WX"),
+            ("Apple M2 Pro", "Apple M2 Pro"),
+            ("NVIDIA GeForce RTX 4090", "NVIDIA RTX 4090"),
+            ("NVIDIA GeForce GTX 1660 SUPER", "NVIDIA GTX 1660 SUPER"),
+            ("AMD Radeon RX 7900 XTX", "AMD RX 7900 XTX"),
+            ("AMD Radeon(TM) Graphics", "AMD Graphics"),
+            ("Intel(R) UHD Graphics 770", "Intel UHD Graphics 770"),
+            ("Intel Arc A770", "Intel Arc A770"),
+        ];
+        for (name, short) in names {
+            assert_eq!(abbreviate_hardware(name), short);
+        }
+    }
+
+    #[test]
+    fn usage_bars_are_dropped() {
+        assert_eq!(drop_usage_bar("[=====     ] 15GB/32GB"), "15/32GB");
+        assert_eq!(drop_usage_bar("󰄰󰄰󰄰󰄰 412GB/1TB"), "412GB/1TB");
+        assert_eq!(drop_usage_bar("[==        ] 2"), "[==        ] 2");
+        assert_eq!(drop_usage_bar(""), "");
+    }
+
+    #[test]
+    fn sections_get_short_keys() {
+        let lines = vec![
+            ("CPU".to_string(), "AMD Ryzen 9 7950X3D 16-Core Processor".to_string()),
+            ("GPU (dGPU)".to_string(), "NVIDIA GeForce RTX 4090".to_string()),
+            ("Memory".to_string(), "[====      ] 15GB/32GB".to_string()),
+            ("Terminal Font".to_string(), "JetBrainsMono Nerd Font".to_string()),
+            ("Shell".to_string(), "zsh 5.9".to_string()),
+        ];
+        let mut sections = [Section::new("Hardware", lines)];
+        compact_sections(&mut sections);
+        let lines: Vec<(&str, &str)> =
+            sections[0].lines.iter().map(|(key, value)| (key.as_str(), value.as_str())).collect();
+        assert_eq!(
+            lines,
+            [
+                ("CPU", "AMD Ryzen 9 7950X3D"),
+                ("GPU (dGPU)", "NVIDIA RTX 4090"),
+                ("Mem", "15/32GB"),
+                ("Font", "JetBrainsMono Nerd Font"),
+                ("Shell", "zsh 5.9"),
+            ]
+        );
+    }
+}
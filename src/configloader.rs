@@ -1,20 +1,138 @@
 // Configuration loader for Slowfetch
 // Loads settings from config.toml
 
+use std::borrow::Cow;
+use std::collections::HashMap;
 use std::fs;
 use std::path::PathBuf;
+use std::time::Duration;
+
+use crate::cache::{CACHED_MODULES, CacheTtl, parse_ttl};
+use crate::collect;
+use crate::colorcontrol::{self, ColorMode};
+use crate::dateformat::{self, DEFAULT_DATE_FORMAT, Date};
+use crate::helpers;
 
 // Embed the default config file at compile time
 const DEFAULT_CONFIG: &str = include_str!("config.toml");
 
+// Comment an installed config gets under its first line, with the version that wrote it
+const VERSION_STAMP: &str = "## Written for slowfetch ";
+
 // OS art setting - can be disabled, auto-detect, or specific OS
 #[derive(Debug, Clone)]
+#[cfg_attr(not(feature = "art"), allow(dead_code))]
 pub enum OsArtSetting {
     Disabled,
     Auto,
     Specific(String),
 }
 
+// Which clock to show after the CPU model
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub enum CpuClock {
+    Boost,
+    Base,
+    Current,
+    None,
+}
+
+// Metrics drawn with a bar, the <metric> in [bars.<metric>]
+pub const BAR_METRICS: &[&str] = &["memory", "storage", "battery", "ups"];
+
+// Glyphs a bar is drawn with. Auto is nerd font bars with a nerd font, ascii without.
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub enum BarGlyphs {
+    Auto,
+    Nerd,
+    Ascii,
+    // █ and ░
+    Blocks,
+}
+
+// How the filled part of a bar is colored: the value color, or cell by cell along a gradient
+// from gradient_start (the first cell) to gradient_end (the last)
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub enum BarFill {
+    Solid,
+    Gradient,
+}
+
+// One [bars] or [bars.<metric>] section, None where it doesn't say
+#[derive(Debug, Clone, Copy, Default)]
+pub struct BarSettings {
+    pub style: Option<BarGlyphs>,
+    pub fill: Option<BarFill>,
+    pub gradient_start: Option<(u8, u8, u8)>,
+    pub gradient_end: Option<(u8, u8, u8)>,
+}
+
+// A metric's bar with everything filled in
+#[derive(Debug, Clone, Copy)]
+pub struct BarStyle {
+    pub glyphs: BarGlyphs,
+    pub fill: BarFill,
+    pub gradient: ((u8, u8, u8), (u8, u8, u8)),
+}
+
+// [bars] for every bar, [bars.<metric>] on top of it for one
+#[derive(Debug, Clone, Default)]
+pub struct BarStyles {
+    pub all: BarSettings,
+    pub metrics: HashMap<String, BarSettings>,
+}
+
+impl BarStyles {
+    // The bar for a metric: its own section, then [bars], then the defaults (green to red)
+    pub fn resolve(&self, metric: &str) -> BarStyle {
+        let own = self.metrics.get(metric).copied().unwrap_or_default();
+        let all = self.all;
+        BarStyle {
+            glyphs: own.style.or(all.style).unwrap_or(BarGlyphs::Auto),
+            fill: own.fill.or(all.fill).unwrap_or(BarFill::Solid),
+            gradient: (
+                own.gradient_start.or(all.gradient_start).unwrap_or((0x50, 0xFA, 0x7B)),
+                own.gradient_end.or(all.gradient_end).unwrap_or((0xFF, 0x55, 0x55)),
+            ),
+        }
+    }
+}
+
+// Battery/UPS charge levels (percent) where the line turns warning/critical colored
+#[derive(Debug, Clone, Copy)]
+pub struct BatteryThresholds {
+    pub low: u8,
+    pub critical: u8,
+}
+
+impl Default for BatteryThresholds {
+    fn default() -> Self {
+        Self {
+            low: 20,
+            critical: 10,
+        }
+    }
+}
+
+// Blank space around the whole output, in columns (left) and lines (top/bottom)
+#[derive(Debug, Clone, Copy, Default)]
+pub struct Margins {
+    pub left: usize,
+    pub top: usize,
+    pub bottom: usize,
+}
+
+impl Margins {
+    // Same margin on every side (used by --margin)
+    pub fn uniform(size: usize) -> Self {
+        Self {
+            left: size,
+            top: size,
+            bottom: size,
+        }
+    }
+}
+
 // Color configuration - all colors stored as RGB tuples
 #[derive(Debug, Clone)]
 pub struct ColorConfig {
@@ -23,6 +141,11 @@ pub struct ColorConfig {
     pub title: (u8, u8, u8),
     pub key: (u8, u8, u8),
     pub value: (u8, u8, u8),
+    // Severity colors for values running low (battery)
+    pub warning: (u8, u8, u8),
+    pub critical: (u8, u8, u8),
+    // Values of modules that failed ("unavailable", "timed out")
+    pub unavailable: (u8, u8, u8),
     // ASCII art colors (1-9)
     pub art_1: (u8, u8, u8),
     pub art_2: (u8, u8, u8),
@@ -35,6 +158,56 @@ pub struct ColorConfig {
     pub art_9: (u8, u8, u8),
 }
 
+impl ColorConfig {
+    // The color a [colors] key sets
+    fn slot(&mut self, key: &str) -> Option<&mut (u8, u8, u8)> {
+        Some(match key {
+            "border" => &mut self.border,
+            "title" => &mut self.title,
+            "key" => &mut self.key,
+            "value" => &mut self.value,
+            "warning" => &mut self.warning,
+            "critical" => &mut self.critical,
+            "unavailable" => &mut self.unavailable,
+            "art_1" => &mut self.art_1,
+            "art_2" => &mut self.art_2,
+            "art_3" => &mut self.art_3,
+            "art_4" => &mut self.art_4,
+            "art_5" => &mut self.art_5,
+            "art_6" => &mut self.art_6,
+            "art_7" => &mut self.art_7,
+            "art_8" => &mut self.art_8,
+            "art_9" => &mut self.art_9,
+            _ => return None,
+        })
+    }
+
+    // Apply --color key=hex overrides on top of everything from the config and environment.
+    // Errors on the first one that doesn't name a color or doesn't parse.
+    pub fn apply_overrides(&mut self, overrides: &[String]) -> Result<(), String> {
+        for entry in overrides {
+            let Some((key, hex)) = entry.split_once('=') else {
+                return Err(format!("--color {} should look like key=#RRGGBB", entry));
+            };
+            let key = key.trim();
+            let color = parse_hex_color(hex);
+            let Some(slot) = self.slot(key) else {
+                let keys: Vec<&str> = CONFIG_KEYS
+                    .iter()
+                    .filter(|(section, _)| *section == "colors")
+                    .flat_map(|(_, keys)| keys.iter().map(|(name, _)| *name))
+                    .collect();
+                return Err(format!("Unknown color '{}' (colors: {})", key, keys.join(", ")));
+            };
+            let Some(color) = color else {
+                return Err(format!("Bad color for {}: {} (expected #RRGGBB)", key, hex.trim()));
+            };
+            *slot = color;
+        }
+        Ok(())
+    }
+}
+
 impl Default for ColorConfig {
     fn default() -> Self {
         Self {
@@ -43,6 +216,9 @@ impl Default for ColorConfig {
             title: (0xFF, 0x79, 0xC6),  // #FF79C6 - magenta/pink
             key: (0xBD, 0x93, 0xF9),    // #BD93F9 - purple
             value: (0x8B, 0xE9, 0xFD),  // #8BE9FD - cyan
+            warning: (0xF1, 0xFA, 0x8C), // #F1FA8C - yellow
+            critical: (0xFF, 0x55, 0x55), // #FF5555 - red
+            unavailable: (0x62, 0x72, 0xA4), // #6272A4 - comment grey
             // Default art colors (rainbow spectrum)
             art_1: (0xFF, 0x00, 0x00), // #FF0000 - Red
             art_2: (0xFF, 0x80, 0x00), // #FF8000 - Orange
@@ -57,13 +233,148 @@ impl Default for ColorConfig {
     }
 }
 
+// A countdown from an [[event]] table, "18 days until Vacation" on the Today line
+#[derive(Debug)]
+pub struct Event {
+    pub name: String,
+    pub date: Date,
+}
+
 #[derive(Debug)]
 pub struct Config {
     pub os_art: OsArtSetting,
     pub colors: ColorConfig,
     pub custom_art: Option<String>,
+    // Art picked by name from the registry, or "random"
+    pub art: Option<String>,
+    // Slowfetch logo in the distro's colors when there's no OS art
+    pub logo_accent: bool,
+    // Directory of extra *.txt art files for art/--art/--random-art
+    pub art_dir: Option<String>,
+    // Columns between tab stops in art files
+    pub tab_width: usize,
     pub image: bool,
     pub image_path: Option<String>,
+    // Wait for Kitty to confirm it loaded the image, reporting when it didn't
+    pub verify_image: bool,
+    // image_resample = "auto": fit images by their real size instead of stretching them
+    pub image_resample: bool,
+    // Widest the image box gets in columns, whatever the sections height
+    pub image_max_width: usize,
+    pub show_boot_entries: bool,
+    // "Boot: systemd-boot · Secure Boot off"
+    pub show_bootloader: bool,
+    // "⚠ 6.12.4 installed — reboot pending" after the kernel when a newer one is installed
+    pub kernel_check: bool,
+    // Host line with the machine's make and model, None = only on known handhelds
+    pub show_host: Option<bool>,
+    // Seconds on the uptime line, None is auto (only in --watch, main fills it in)
+    pub uptime_seconds: Option<bool>,
+    // Generation, flakes and store size as a tree under the OS line on NixOS
+    pub nixos_detail: bool,
+    pub show_session: bool,
+    pub show_prompt: bool,
+    pub show_desktop: bool,
+    // Display manager line ("Login: SDDM")
+    pub show_login: bool,
+    pub show_audio: bool,
+    pub show_activity: bool,
+    // Default route interface with its link speed or Wi-Fi network
+    pub show_network: bool,
+    // Addresses on the network line, from show_ip, both off by default for screenshots
+    pub show_ipv4: bool,
+    pub show_ipv6: bool,
+    // Power profile and CPU governor line
+    pub power_detail: bool,
+    pub show_load: bool,
+    pub greeting: bool,
+    // Line of text centered below the boxes
+    pub footer: Option<String>,
+    // strftime style format for dates (%Y-%m-%d), None keeps each line's own
+    pub date_format: Option<String>,
+    // Exit non-zero on config warnings instead of carrying on
+    pub strict_config: bool,
+    // Clickable OS/WM values on terminals that support OSC 8
+    pub hyperlinks: bool,
+    // Screen reader friendly plain lines instead of boxes, same as --accessible
+    pub accessible: bool,
+    // Version from the config's "Written for slowfetch" stamp, configs from before it have none
+    pub written_for: Option<String>,
+    // Notices for old key names and module ids the config still uses, not read from the file
+    pub deprecated: Vec<String>,
+    // Extra display info (VRR) on the Display lines
+    pub display_detail: bool,
+    // Compositor backend after KWin/Mutter, "KWin (wayland, card1)"
+    pub wm_detail: bool,
+    // Terminal tree with font, opacity and padding instead of the Terminal Font line
+    pub terminal_detail: bool,
+    // Pacman count split into repo and foreign (AUR) packages, "1390 + 66 AUR"
+    pub packages_detail: bool,
+    // Vendor icon in front of the CPU and GPU
+    pub vendor_icons: bool,
+    // One border around the art and the sections, split by a divider
+    pub unified_box: bool,
+    // Cycle the art's colors for a few seconds before settling, like --animate
+    pub animate: bool,
+    // Microarchitecture after the CPU and GPU names, "(Zen 4)", "(RDNA 3)"
+    pub arch_names: bool,
+    // "LUKS" after the storage bar when / is encrypted
+    pub show_encryption: bool,
+    // Count NFS/SMB/sshfs shares and WSL's Windows drives in the storage line
+    pub include_network_fs: bool,
+    // Memory/storage change against a sample this old (zero = the last run), None = off
+    pub usage_deltas: Option<Duration>,
+    // Give up on slow modules after this long (0 = wait forever)
+    pub max_fetch_ms: u64,
+    // Leave out modules that missed max_fetch_ms instead of showing "timed out"
+    pub hide_timed_out: bool,
+    // Worker threads the slow modules share, 0 = auto (2 to 4)
+    pub threads: usize,
+    // Leave out lines that say "unknown" (and failed ones, unless --verbose)
+    pub hide_unknown: bool,
+    // Keys --diff compares even though they change all the time (uptime, memory...)
+    pub diff_include: Vec<String>,
+    // Per-module hide_unknown overrides from the [modules] section (module id -> hide)
+    pub unknown_lines: HashMap<String, bool>,
+    // [[event]] countdowns, the Today line only shows with at least one
+    pub events: Vec<Event>,
+    // --verbose, not read from the file. Keeps failed lines around under hide_unknown.
+    pub verbose: bool,
+    // Force nerd font glyphs on/off, None = guess from the terminal font
+    pub nerd_font: Option<bool>,
+    // Declared terminal and font, they skip detection where it can't work
+    pub terminal: Option<String>,
+    pub terminal_font: Option<String>,
+    // Use the container's cgroup memory/CPU limits, None = only inside a container
+    pub respect_cgroups: Option<bool>,
+    // Force the color mode, None = guess from COLORTERM/TERM
+    pub color_mode: Option<ColorMode>,
+    // Terminal background for the contrast check, None = ask the terminal
+    pub background: Option<(u8, u8, u8)>,
+    // Fix up theme colors that are hard to read on the background instead of hinting
+    pub auto_contrast: bool,
+    pub cpu_clock: CpuClock,
+    pub battery_thresholds: BatteryThresholds,
+    // Power draw or charge rate and the time left on the Battery line
+    pub battery_power: bool,
+    pub compact: bool,
+    // Compact mode turns on by itself below this many columns (0 = never)
+    pub compact_width: usize,
+    // Split a section much taller than the rest in two
+    pub balance_sections: bool,
+    // Title of the second half, None = "<title> (cont.)"
+    pub balance_title: Option<String>,
+    pub margins: Margins,
+    // Per-module value templates from the [format] section (module id -> template)
+    pub formats: HashMap<String, String>,
+    // Module order per section from the [order] section (core/hardware/userspace -> module ids)
+    pub order: HashMap<String, Vec<String>>,
+    // Per-module cache TTLs from the [cache] section (module -> TTL)
+    pub cache_ttl: HashMap<String, CacheTtl>,
+    // Art color slot remaps from the [art_remap] section (slot -> slot whose color it uses)
+    pub art_remap: HashMap<usize, usize>,
+    // Bar glyphs and fill from [bars] and [bars.<metric>]
+    pub bars: BarStyles,
 }
 
 impl Default for Config {
@@ -72,15 +383,86 @@ impl Default for Config {
             os_art: OsArtSetting::Disabled,
             colors: ColorConfig::default(),
             custom_art: None,
+            art: None,
+            logo_accent: false,
+            art_dir: None,
+            tab_width: 8,
             image: false,
             image_path: None,
+            verify_image: false,
+            image_resample: true,
+            image_max_width: 40,
+            show_boot_entries: false,
+            show_bootloader: false,
+            kernel_check: true,
+            show_host: None,
+            uptime_seconds: None,
+            nixos_detail: false,
+            show_session: false,
+            show_prompt: true,
+            show_desktop: false,
+            show_login: false,
+            show_audio: false,
+            show_activity: false,
+            show_network: false,
+            power_detail: false,
+            show_ipv4: false,
+            show_ipv6: false,
+            show_load: false,
+            greeting: false,
+            footer: None,
+            date_format: None,
+            strict_config: false,
+            hyperlinks: true,
+            accessible: false,
+            written_for: None,
+            deprecated: Vec::new(),
+            display_detail: false,
+            wm_detail: false,
+            terminal_detail: false,
+            packages_detail: false,
+            vendor_icons: false,
+            unified_box: false,
+            animate: false,
+            arch_names: false,
+            show_encryption: false,
+            include_network_fs: false,
+            usage_deltas: None,
+            max_fetch_ms: 3000,
+            hide_timed_out: false,
+            threads: 0,
+            hide_unknown: false,
+            unknown_lines: HashMap::new(),
+            events: Vec::new(),
+            diff_include: Vec::new(),
+            verbose: false,
+            nerd_font: None,
+            terminal: None,
+            terminal_font: None,
+            respect_cgroups: None,
+            color_mode: None,
+            background: None,
+            auto_contrast: false,
+            cpu_clock: CpuClock::Boost,
+            battery_thresholds: BatteryThresholds::default(),
+            battery_power: true,
+            compact: false,
+            compact_width: 72,
+            balance_sections: false,
+            balance_title: None,
+            margins: Margins::default(),
+            formats: HashMap::new(),
+            order: HashMap::new(),
+            cache_ttl: HashMap::new(),
+            art_remap: HashMap::new(),
+            bars: BarStyles::default(),
         }
     }
 }
 
 // Parse a hex color string like "#FF79C6" or "FF79C6" into RGB tuple
-fn parse_hex_color(hex: &str) -> Option<(u8, u8, u8)> {
-    let hex = hex.trim().trim_matches('"');
+pub fn parse_hex_color(hex: &str) -> Option<(u8, u8, u8)> {
+    let hex = unquote(hex);
     let hex = hex.strip_prefix('#').unwrap_or(hex);
 
     if hex.len() != 6 {
@@ -94,6 +476,437 @@ fn parse_hex_color(hex: &str) -> Option<(u8, u8, u8)> {
     Some((r, g, b))
 }
 
+// Parse a one-line string list like ["cpu", "displays", "memory"]
+fn parse_string_list(value: &str) -> Option<Vec<String>> {
+    let inner = value.trim().strip_prefix('[')?.strip_suffix(']')?;
+    Some(
+        inner
+            .split(',')
+            .map(|item| unquote(item).to_string())
+            .filter(|item| !item.is_empty())
+            .collect(),
+    )
+}
+
+// What a config key accepts, used to flag bad values
+#[derive(Clone, Copy)]
+enum ValueKind {
+    Bool,
+    Number,
+    // Quoted string
+    Text,
+    // true/false or a quoted string (os_art)
+    BoolOrText,
+    Color,
+    // One-line list of quoted strings, ["uptime", "memory"]
+    List,
+    Choice(&'static [&'static str]),
+}
+
+// Every key the parser understands, by section. Used to catch typos and to map SLOWFETCH_*
+// environment variables back to their section. [format], [order] and [cache] take module/section
+// names as keys, so they aren't listed here.
+const CONFIG_KEYS: &[(&str, &[(&str, ValueKind)])] = &[
+    (
+        "display",
+        &[
+            ("os_art", ValueKind::BoolOrText),
+            ("logo_accent", ValueKind::Choice(&["auto", "off"])),
+            ("custom_art", ValueKind::Text),
+            ("art", ValueKind::Text),
+            ("art_dir", ValueKind::Text),
+            ("tab_width", ValueKind::Number),
+            ("image", ValueKind::Bool),
+            ("image_path", ValueKind::Text),
+            ("verify_image", ValueKind::Bool),
+            ("image_resample", ValueKind::Choice(&["auto", "off"])),
+            ("image_max_width", ValueKind::Number),
+            ("show_boot_entries", ValueKind::Bool),
+            ("show_bootloader", ValueKind::Bool),
+            ("kernel_check", ValueKind::Bool),
+            ("show_host", ValueKind::Choice(&["auto", "true", "false"])),
+            ("uptime_seconds", ValueKind::Choice(&["auto", "true", "false"])),
+            ("nixos_detail", ValueKind::Bool),
+            ("nerd_font", ValueKind::Choice(&["auto", "true", "false"])),
+            ("terminal", ValueKind::Text),
+            ("terminal_font", ValueKind::Text),
+            ("respect_cgroups", ValueKind::Choice(&["auto", "true", "false"])),
+            ("color_mode", ValueKind::Choice(&["auto", "truecolor", "256", "16"])),
+            ("background", ValueKind::Text),
+            ("auto_contrast", ValueKind::Bool),
+            ("battery_low", ValueKind::Number),
+            ("battery_critical", ValueKind::Number),
+            ("battery_power", ValueKind::Bool),
+            ("colors_from", ValueKind::Choice(&["none", "dms"])),
+            ("greeting", ValueKind::Bool),
+            ("footer", ValueKind::Text),
+            ("date_format", ValueKind::Text),
+            ("cpu_clock", ValueKind::Choice(&["boost", "base", "current", "none"])),
+            ("show_session", ValueKind::Bool),
+            ("show_prompt", ValueKind::Bool),
+            ("show_desktop", ValueKind::Bool),
+            ("show_login", ValueKind::Bool),
+            ("show_audio", ValueKind::Bool),
+            ("show_activity", ValueKind::Bool),
+            ("show_network", ValueKind::Bool),
+            ("show_ip", ValueKind::Choice(&["off", "ipv4", "ipv6", "both"])),
+            ("power_detail", ValueKind::Bool),
+            ("show_load", ValueKind::Bool),
+            ("strict_config", ValueKind::Bool),
+            ("hyperlinks", ValueKind::Bool),
+            ("accessible", ValueKind::Bool),
+            ("display_detail", ValueKind::Bool),
+            ("wm_detail", ValueKind::Bool),
+            ("terminal_detail", ValueKind::Bool),
+            ("packages_detail", ValueKind::Bool),
+            ("vendor_icons", ValueKind::Bool),
+            ("arch_names", ValueKind::Bool),
+            ("show_encryption", ValueKind::Bool),
+            ("include_network_fs", ValueKind::Bool),
+            ("usage_deltas", ValueKind::Text),
+            ("max_fetch_ms", ValueKind::Number),
+            ("hide_timed_out", ValueKind::Bool),
+            ("threads", ValueKind::Number),
+            ("hide_unknown", ValueKind::Bool),
+            ("diff_include", ValueKind::List),
+        ],
+    ),
+    (
+        "layout",
+        &[
+            ("margin_left", ValueKind::Number),
+            ("margin_top", ValueKind::Number),
+            ("margin_bottom", ValueKind::Number),
+            ("compact", ValueKind::Bool),
+            ("compact_width", ValueKind::Number),
+            ("balance_sections", ValueKind::Bool),
+            ("unified_box", ValueKind::Bool),
+            ("animate", ValueKind::Bool),
+            ("balance_title", ValueKind::Text),
+        ],
+    ),
+    (
+        "colors",
+        &[
+            ("border", ValueKind::Color),
+            ("title", ValueKind::Color),
+            ("key", ValueKind::Color),
+            ("value", ValueKind::Color),
+            ("warning", ValueKind::Color),
+            ("critical", ValueKind::Color),
+            ("unavailable", ValueKind::Color),
+            ("art_1", ValueKind::Color),
+            ("art_2", ValueKind::Color),
+            ("art_3", ValueKind::Color),
+            ("art_4", ValueKind::Color),
+            ("art_5", ValueKind::Color),
+            ("art_6", ValueKind::Color),
+            ("art_7", ValueKind::Color),
+            ("art_8", ValueKind::Color),
+            ("art_9", ValueKind::Color),
+        ],
+    ),
+    (
+        "bars",
+        &[
+            ("style", ValueKind::Choice(&["auto", "nerd", "ascii", "blocks"])),
+            ("fill", ValueKind::Choice(&["solid", "gradient"])),
+            ("gradient_start", ValueKind::Color),
+            ("gradient_end", ValueKind::Color),
+        ],
+    ),
+];
+
+// Sections whose keys aren't in CONFIG_KEYS
+const FREEFORM_SECTIONS: &[&str] =
+    &["format", "order", "cache", "art_remap", "modules", "event"];
+
+// Look up a key. [display] and [layout] keys work in either section (or before any header),
+// [colors] keys only in [colors] and [bars] keys only in [bars] and [bars.<metric>].
+fn find_key(section: &str, key: &str) -> Option<ValueKind> {
+    let section = if section.starts_with("bars.") { "bars" } else { section };
+    let scoped = |name: &str| matches!(name, "colors" | "bars");
+    CONFIG_KEYS
+        .iter()
+        .filter(|(name, _)| if scoped(name) { *name == section } else { !scoped(section) })
+        .flat_map(|(_, keys)| keys.iter())
+        .find(|(name, _)| *name == key)
+        .map(|(_, kind)| *kind)
+}
+
+// Where a renamed name is used
+#[derive(Clone, Copy, PartialEq)]
+pub enum Renamed {
+    // A key of a CONFIG_KEYS section, "display" covering [layout] too (same as find_key).
+    // No key has been renamed yet, only the tests make these for now.
+    #[cfg_attr(not(test), allow(dead_code))]
+    Key(&'static str),
+    // A module id: [order] list entries and [format], [modules] and [cache] keys
+    Module,
+}
+
+pub struct Rename {
+    pub renamed: Renamed,
+    pub old: &'static str,
+    pub new: &'static str,
+}
+
+// Keys and module ids that got a new name. Module ids and keys are stable otherwise: renaming
+// one means an entry here, so configs using the old name keep working (with a notice) and
+// --migrate-config can update them.
+const RENAMES: &[Rename] = &[];
+
+// The rename a key (or an [order] list entry) in this section is using, if any.
+// Profile tables count as the section they override.
+fn find_rename<'a>(
+    renames: &'a [Rename],
+    section: &str,
+    name: &str,
+    list_entry: bool,
+) -> Option<&'a Rename> {
+    let section = section.strip_prefix("profile.").map_or(section, |rest| {
+        rest.split_once('.').map_or("display", |(_, section)| section)
+    });
+    let section = if section.starts_with("bars.") { "bars" } else { section };
+    let scoped = |name: &str| matches!(name, "colors" | "bars");
+    renames.iter().find(|rename| {
+        let applies = match rename.renamed {
+            _ if list_entry => rename.renamed == Renamed::Module && section == "order",
+            Renamed::Key(scope) if scoped(scope) => scope == section,
+            Renamed::Key(_) => !scoped(section) && !FREEFORM_SECTIONS.contains(&section),
+            Renamed::Module => matches!(section, "format" | "modules" | "cache"),
+        };
+        applies && rename.old == name
+    })
+}
+
+// Rewrite old names to the current ones. Only the names change, line by line, so comments,
+// spacing and the order of everything stay as they were. Returns the new content and the line
+// number (from 1) of every rename made.
+pub fn rename_keys<'a>(
+    content: &str,
+    renames: &'a [Rename],
+) -> (String, Vec<(usize, &'a Rename)>) {
+    let mut output = String::with_capacity(content.len());
+    let mut changes = Vec::new();
+    let mut section = String::new();
+
+    for (index, line) in content.split_inclusive('\n').enumerate() {
+        let (text, ending) = match line.strip_suffix('\n') {
+            Some(text) => (text, "\n"),
+            None => (line, ""),
+        };
+        let code = strip_inline_comment(text);
+        let trimmed = code.trim().trim_start_matches('\u{FEFF}');
+        if trimmed.starts_with('[') {
+            section = trimmed.trim_matches(['[', ']']).trim().to_string();
+        } else if let Some((renamed_code, renamed)) = rename_line(code, &section, renames) {
+            changes.extend(renamed.into_iter().map(|rename| (index + 1, rename)));
+            output.push_str(&renamed_code);
+            // The trailing comment, if there is one
+            output.push_str(&text[code.len()..]);
+            output.push_str(ending);
+            continue;
+        }
+        output.push_str(line);
+    }
+    (output, changes)
+}
+
+// The line with its key or [order] entries renamed, None when there's nothing to rename
+fn rename_line<'a>(
+    code: &str,
+    section: &str,
+    renames: &'a [Rename],
+) -> Option<(String, Vec<&'a Rename>)> {
+    let (key_part, value) = code.split_once('=')?;
+    let key = key_part.trim().trim_start_matches('\u{FEFF}');
+    if let Some(rename) = find_rename(renames, section, key, false) {
+        // Only the name is swapped, the indent and spacing around it stay
+        let key_part = key_part.replacen(key, rename.new, 1);
+        return Some((format!("{}={}", key_part, value), vec![rename]));
+    }
+
+    let (before, rest) = value.split_once('[')?;
+    let (list, after) = rest.rsplit_once(']')?;
+    let mut renamed = Vec::new();
+    let entries: Vec<String> = list
+        .split(',')
+        .map(|entry| match find_rename(renames, section, unquote(entry), true) {
+            Some(rename) => {
+                renamed.push(rename);
+                entry.replacen(rename.old, rename.new, 1)
+            }
+            None => entry.to_string(),
+        })
+        .collect();
+    let line = format!("{}={}[{}]{}", key_part, before, entries.join(","), after);
+    (!renamed.is_empty()).then_some((line, renamed))
+}
+
+// One notice per old name, however many times the config uses it
+pub fn deprecation_notices(changes: &[(usize, &Rename)]) -> Vec<String> {
+    let mut notices: Vec<String> = Vec::new();
+    for (_, rename) in changes {
+        let what = if rename.renamed == Renamed::Module { "Module" } else { "Config key" };
+        let notice = format!(
+            "{} '{}' is now '{}', the old name still works for now \
+             (slowfetch --migrate-config updates the config)",
+            what, rename.old, rename.new
+        );
+        if !notices.contains(&notice) {
+            notices.push(notice);
+        }
+    }
+    notices
+}
+
+// Does the value fit what the key accepts?
+fn value_matches(kind: ValueKind, value: &str) -> bool {
+    let is_bool = value == "true" || value == "false";
+    let is_quoted = value.len() >= 2 && value.starts_with('"') && value.ends_with('"');
+    match kind {
+        ValueKind::Bool => is_bool,
+        ValueKind::Number => value.parse::<usize>().is_ok(),
+        ValueKind::Text => is_quoted,
+        ValueKind::BoolOrText => is_bool || is_quoted,
+        ValueKind::Color => parse_hex_color(value).is_some(),
+        ValueKind::List => parse_string_list(value).is_some(),
+        ValueKind::Choice(options) => options.contains(&unquote(value)),
+    }
+}
+
+// Drop a trailing "# comment", leaving # inside quotes (colors) alone
+fn strip_inline_comment(line: &str) -> &str {
+    let mut in_quotes = false;
+    let mut escaped = false;
+    for (index, c) in line.char_indices() {
+        match c {
+            _ if escaped => escaped = false,
+            '\\' if in_quotes => escaped = true,
+            '"' => in_quotes = !in_quotes,
+            '#' if !in_quotes => return line[..index].trim_end(),
+            _ => {}
+        }
+    }
+    line
+}
+
+// Undo \" and \\ inside quoted strings, the quotes around them stay for the value checks
+fn unescape_quoted(line: &str) -> Cow<'_, str> {
+    if !line.contains('\\') {
+        return Cow::Borrowed(line);
+    }
+    let mut unescaped = String::with_capacity(line.len());
+    let mut in_quotes = false;
+    let mut chars = line.chars().peekable();
+    while let Some(c) = chars.next() {
+        match c {
+            '"' => in_quotes = !in_quotes,
+            '\\' if in_quotes => {
+                if let Some(&next) = chars.peek()
+                    && matches!(next, '"' | '\\')
+                {
+                    chars.next();
+                    unescaped.push(next);
+                    continue;
+                }
+            }
+            _ => {}
+        }
+        unescaped.push(c);
+    }
+    Cow::Owned(unescaped)
+}
+
+// The value without the quotes around it, quotes inside the text are kept
+fn unquote(value: &str) -> &str {
+    let value = value.trim();
+    let value = value.strip_prefix('"').unwrap_or(value);
+    value.strip_suffix('"').unwrap_or(value)
+}
+
+// SLOWFETCH_* variables that aren't config keys: PROFILE picks the profile (handled in main),
+// BLESS rewrites the golden files under cargo test
+const NOT_SETTINGS: &[&str] = &["profile", "bless"];
+
+// Turn SLOWFETCH_* environment variables into config lines, parsed after the file so they win.
+// SLOWFETCH_OS_ART=arch -> os_art = "arch", SLOWFETCH_COLORS__BORDER=#FF0000 -> [colors] border,
+// the double underscore separates section and key (a single one works when it's unambiguous).
+fn env_overrides(
+    vars: impl Iterator<Item = (String, String)>,
+    warnings: &mut Vec<String>,
+) -> String {
+    let mut overrides = String::new();
+
+    for (name, value) in vars {
+        let Some(setting) = name.strip_prefix("SLOWFETCH_") else {
+            continue;
+        };
+        let setting = setting.to_lowercase();
+        if NOT_SETTINGS.contains(&setting.as_str()) {
+            continue;
+        }
+
+        let Some((section, key)) = env_setting(&setting) else {
+            warnings.push(format!("Unknown environment override {}", name));
+            continue;
+        };
+
+        // Quote bare strings so they parse like they would in the file
+        let value = value.trim();
+        let bare = value == "true"
+            || value == "false"
+            || value.parse::<usize>().is_ok()
+            || value.starts_with('"')
+            || value.starts_with('[');
+        let value = if bare {
+            value.to_string()
+        } else {
+            format!("\"{}\"", value.replace('\\', "\\\\").replace('"', "\\\""))
+        };
+
+        overrides.push_str(&format!("[{}]\n{} = {}\n", section, key, value));
+    }
+
+    overrides
+}
+
+// "colors__border" / "colors_border" -> ("colors", "border"), "os_art" -> ("display", "os_art")
+fn env_setting(setting: &str) -> Option<(String, String)> {
+    if let Some((section, key)) = setting.split_once("__") {
+        return Some((section.to_string(), key.to_string()));
+    }
+
+    // Renamed keys still map back, the rename happens with the rest of the config
+    let renamed = RENAMES.iter().filter_map(|rename| match rename.renamed {
+        Renamed::Key(section) => Some((section, rename.old)),
+        Renamed::Module => None,
+    });
+    let keys = CONFIG_KEYS
+        .iter()
+        .flat_map(|(section, keys)| keys.iter().map(move |(key, _)| (*section, *key)));
+    for (section, key) in keys.chain(renamed) {
+        let nested = format!("{}_{}", section, key);
+        if setting == key && !matches!(section, "colors" | "bars") || setting == nested {
+            return Some((section.to_string(), key.to_string()));
+        }
+    }
+
+    FREEFORM_SECTIONS.iter().find_map(|section| {
+        let key = setting.strip_prefix(section)?.strip_prefix('_')?;
+        Some((section.to_string(), key.to_string()))
+    })
+}
+
+// Expand a leading ~/ to the home directory
+pub fn expand_home(path: &str) -> String {
+    match (path.strip_prefix("~/"), std::env::var("HOME")) {
+        (Some(rest), Ok(home)) => format!("{}/{}", home, rest),
+        _ => path.to_string(),
+    }
+}
+
 // Get the config directory path
 fn get_config_dir() -> Option<PathBuf> {
     // Prefer XDG_CONFIG_HOME if set
@@ -110,7 +923,7 @@ fn get_config_dir() -> Option<PathBuf> {
 }
 
 // Get the config file path, checking common locations
-fn get_config_path() -> Option<PathBuf> {
+pub fn get_config_path() -> Option<PathBuf> {
     // Check XDG_CONFIG_HOME/slowfetch/config.toml first
     if let Ok(xdg_config) = std::env::var("XDG_CONFIG_HOME") {
         let path = PathBuf::from(xdg_config).join("slowfetch/config.toml");
@@ -152,8 +965,9 @@ fn install_default_config() -> Option<PathBuf> {
         }
     }
 
-    // Write the default config file
-    if fs::write(&config_path, DEFAULT_CONFIG).is_err() {
+    // Write the default config file, stamped with this version under the title
+    let stamp = format!("\n{}{}\n", VERSION_STAMP, env!("CARGO_PKG_VERSION"));
+    if fs::write(&config_path, DEFAULT_CONFIG.replacen('\n', &stamp, 1)).is_err() {
         eprintln!("Warning: Could not write config file: {:?}", config_path);
         return None;
     }
@@ -162,73 +976,387 @@ fn install_default_config() -> Option<PathBuf> {
     Some(config_path)
 }
 
-// Load configuration from file
-pub fn load_config() -> Config {
-    // Try to find an existing config file
-    let path = match get_config_path() {
-        Some(p) => p,
-        None => {
-            // No config found, install the default one
-            match install_default_config() {
-                Some(p) => p,
-                None => return Config::default(),
+// Load configuration from file, then apply SLOWFETCH_* environment overrides.
+// Returns the config along with any warnings (unknown keys, bad values) for the caller to report.
+// Load the config with the named profile applied on top.
+// Errors when the profile isn't defined in the config.
+pub fn load_config(profile: Option<&str>) -> Result<(Config, Vec<String>), String> {
+    // Try to find an existing config file, no config found installs the default one
+    let content = get_config_path()
+        .or_else(install_default_config)
+        .and_then(|path| fs::read_to_string(path).ok())
+        .unwrap_or_default();
+    config_from(&content, profile, std::env::vars())
+}
+
+// The file content with the profile and then the environment overrides layered on top
+fn config_from(
+    content: &str,
+    profile: Option<&str>,
+    vars: impl Iterator<Item = (String, String)>,
+) -> Result<(Config, Vec<String>), String> {
+    // Editors on Windows like to start the file with a BOM, it would stick to the first key
+    let content = select_profile(content.trim_start_matches('\u{FEFF}'), profile)?;
+
+    let mut warnings = Vec::new();
+    let overrides = env_overrides(vars, &mut warnings);
+    // Old names are read as the new ones, with a notice
+    let (content, renamed) = rename_keys(&format!("{}\n{}", content, overrides), RENAMES);
+    let mut config = parse_config(&content, &mut warnings);
+    config.deprecated = deprecation_notices(&renamed);
+
+    // Keys this binary doesn't know in a config from a newer slowfetch are most likely options
+    // it doesn't have yet, usually an old binary earlier on PATH
+    if let Some(written_for) = &config.written_for
+        && is_newer_version(written_for, env!("CARGO_PKG_VERSION"))
+        && warnings.iter().any(|warning| warning.starts_with("Unknown key"))
+    {
+        warnings.push(format!(
+            "The config is from slowfetch {}, this is {}: the unknown keys are probably newer \
+             options (slowfetch doctor lists every slowfetch on PATH)",
+            written_for,
+            env!("CARGO_PKG_VERSION")
+        ));
+    }
+    Ok((config, warnings))
+}
+
+// --migrate-config: rewrite old key names and module ids in the config file to the current
+// ones, keeping a copy of the old file next to it as config.toml.bak. Returns what was done.
+pub fn migrate_config() -> Result<String, String> {
+    let path = get_config_path().ok_or("There's no config file to migrate")?;
+    let content = fs::read_to_string(&path)
+        .map_err(|err| format!("Can't read {}: {}", path.display(), err))?;
+    let (migrated, changes) = rename_keys(&content, RENAMES);
+    if changes.is_empty() {
+        return Ok(format!("{} already uses the current names\n", path.display()));
+    }
+
+    let mut backup = path.clone().into_os_string();
+    backup.push(".bak");
+    let backup = PathBuf::from(backup);
+    fs::copy(&path, &backup)
+        .map_err(|err| format!("Can't back up the config to {}: {}", backup.display(), err))?;
+    fs::write(&path, migrated)
+        .map_err(|err| format!("Can't write {}: {}", path.display(), err))?;
+
+    let mut report = String::new();
+    for (line, rename) in &changes {
+        report.push_str(&format!("line {}: {} -> {}\n", line, rename.old, rename.new));
+    }
+    report.push_str(&format!(
+        "Updated {}, the old one is saved as {}\n",
+        path.display(),
+        backup.display()
+    ));
+    Ok(report)
+}
+
+// Dotted versions compared number by number, "0.10.0" is newer than "0.9.3"
+pub fn is_newer_version(version: &str, than: &str) -> bool {
+    let numbers = |version: &str| -> Vec<u64> {
+        version.split('.').map_while(|part| part.trim().parse().ok()).collect()
+    };
+    numbers(version) > numbers(than)
+}
+
+// Pull the [profile.<name>] tables out of the config. Everything else is the base, the
+// selected profile's lines go after it so they win, key by key. [profile.<name>] takes
+// display/layout keys, [profile.<name>.colors], [profile.<name>.order] etc. override that
+// section. An [order] list in a profile replaces that section's list, it isn't merged.
+fn select_profile(content: &str, profile: Option<&str>) -> Result<String, String> {
+    let mut base = String::new();
+    let mut selected = String::new();
+    let mut names: Vec<&str> = Vec::new();
+    // Profile the current table belongs to, None for regular sections
+    let mut current: Option<&str> = None;
+
+    for line in content.lines() {
+        let trimmed = strip_inline_comment(line.trim());
+        if trimmed.starts_with('[') {
+            let header = trimmed.trim_matches(['[', ']']).trim();
+            current = None;
+            if let Some(rest) = header.strip_prefix("profile.") {
+                let (name, section) = rest.split_once('.').unwrap_or((rest, "display"));
+                if !names.contains(&name) {
+                    names.push(name);
+                }
+                current = Some(name);
+                if profile == Some(name) {
+                    selected.push_str(&format!("[{}]\n", section));
+                }
+                continue;
             }
         }
-    };
 
-    let content = match fs::read_to_string(&path) {
-        Ok(c) => c,
-        Err(_) => return Config::default(),
-    };
+        let target = match current {
+            None => &mut base,
+            Some(name) if profile == Some(name) => &mut selected,
+            Some(_) => continue,
+        };
+        target.push_str(line);
+        target.push('\n');
+    }
+
+    if let Some(profile) = profile
+        && !names.contains(&profile)
+    {
+        return Err(if names.is_empty() {
+            format!("Unknown profile '{}', the config doesn't define any", profile)
+        } else {
+            format!("Unknown profile '{}' (available: {})", profile, names.join(", "))
+        });
+    }
 
-    parse_config(&content)
+    Ok(format!("{}\n{}", base, selected))
 }
 
 // Parse the TOML config content
-fn parse_config(content: &str) -> Config {
+fn parse_config(content: &str, warnings: &mut Vec<String>) -> Config {
     let mut config = Config::default();
+    let mut section = String::new();
     let mut in_colors_section = false;
+    let mut in_format_section = false;
+    let mut in_order_section = false;
+    let mut in_cache_section = false;
+    let mut in_art_remap_section = false;
+    let mut in_modules_section = false;
+    // [[event]] tables as (name, date) while they're filled in, each header starts a new one
+    let mut events: Vec<(Option<String>, Option<Date>)> = Vec::new();
+    // Colors set in [colors], a colors_from theme doesn't touch them
+    let mut explicit_colors: Vec<String> = Vec::new();
+    let mut colors_from_dms = false;
 
     for line in content.lines() {
-        let line = line.trim();
+        if let Some(version) = line.trim().strip_prefix(VERSION_STAMP) {
+            config.written_for = Some(version.trim().to_string());
+        }
+        let line = unescape_quoted(strip_inline_comment(line.trim()));
+        let line = line.as_ref();
 
         // Skip comments and empty lines
-        if line.is_empty() || line.starts_with('#') {
+        if line.is_empty() {
             continue;
         }
 
         // Track which section we're in
         if line.starts_with('[') {
-            in_colors_section = line == "[colors]";
+            section = line.trim_matches(['[', ']']).trim().to_string();
+            let bar_metric = section.strip_prefix("bars.");
+            if bar_metric.is_some_and(|metric| !BAR_METRICS.contains(&metric)) {
+                warnings.push(format!(
+                    "Unknown bar [{}] (bars are {})",
+                    section,
+                    BAR_METRICS.join(", ")
+                ));
+            } else if bar_metric.is_none()
+                && !FREEFORM_SECTIONS.contains(&section.as_str())
+                && !CONFIG_KEYS.iter().any(|(name, _)| *name == section)
+            {
+                warnings.push(format!("Unknown section [{}]", section));
+            }
+            in_colors_section = section == "colors";
+            in_format_section = section == "format";
+            in_order_section = section == "order";
+            in_cache_section = section == "cache";
+            in_art_remap_section = section == "art_remap";
+            in_modules_section = section == "modules";
+            if section == "event" {
+                events.push((None, None));
+            }
             continue;
         }
 
+        // Flag typos and bad values, the parsing below quietly skips them
+        if !FREEFORM_SECTIONS.contains(&section.as_str()) {
+            match line.split_once('=') {
+                Some((key, value)) => match find_key(&section, key.trim()) {
+                    Some(kind) if !value_matches(kind, value.trim()) => warnings.push(format!(
+                        "Bad value for {}: {}",
+                        key.trim(),
+                        value.trim()
+                    )),
+                    Some(_) => {}
+                    None => warnings.push(format!("Unknown key '{}'", key.trim())),
+                },
+                None => warnings.push(format!("Can't parse line '{}'", line)),
+            }
+        }
+
         // Parse color settings
         if in_colors_section {
             if let Some((key, value)) = line.split_once('=') {
                 let key = key.trim();
-                if let Some(color) = parse_hex_color(value) {
-                    match key {
-                        "border" => config.colors.border = color,
-                        "title" => config.colors.title = color,
-                        "key" => config.colors.key = color,
-                        "value" => config.colors.value = color,
-                        "art_1" => config.colors.art_1 = color,
-                        "art_2" => config.colors.art_2 = color,
-                        "art_3" => config.colors.art_3 = color,
-                        "art_4" => config.colors.art_4 = color,
-                        "art_5" => config.colors.art_5 = color,
-                        "art_6" => config.colors.art_6 = color,
-                        "art_7" => config.colors.art_7 = color,
-                        "art_8" => config.colors.art_8 = color,
-                        "art_9" => config.colors.art_9 = color,
-                        _ => {}
+                if let Some(color) = parse_hex_color(value)
+                    && let Some(slot) = config.colors.slot(key)
+                {
+                    explicit_colors.push(key.to_string());
+                    *slot = color;
+                }
+            }
+            continue;
+        }
+
+        // Parse bar settings, [bars] for all of them and [bars.memory] etc. for one
+        if section == "bars" || section.starts_with("bars.") {
+            let settings = match section.strip_prefix("bars.") {
+                Some(metric) => config.bars.metrics.entry(metric.to_string()).or_default(),
+                None => &mut config.bars.all,
+            };
+            if let Some((key, value)) = line.split_once('=') {
+                let value = value.trim();
+                match key.trim() {
+                    "style" => {
+                        settings.style = match unquote(value) {
+                            "auto" => Some(BarGlyphs::Auto),
+                            "nerd" => Some(BarGlyphs::Nerd),
+                            "ascii" => Some(BarGlyphs::Ascii),
+                            "blocks" => Some(BarGlyphs::Blocks),
+                            _ => settings.style,
+                        }
+                    }
+                    "fill" => {
+                        settings.fill = match unquote(value) {
+                            "solid" => Some(BarFill::Solid),
+                            "gradient" => Some(BarFill::Gradient),
+                            _ => settings.fill,
+                        }
+                    }
+                    "gradient_start" => {
+                        settings.gradient_start = parse_hex_color(value).or(settings.gradient_start)
+                    }
+                    "gradient_end" => {
+                        settings.gradient_end = parse_hex_color(value).or(settings.gradient_end)
+                    }
+                    _ => {}
+                }
+            }
+            continue;
+        }
+
+        // Parse per-module format templates, e.g. kernel = "{value|split:-|first}"
+        if in_format_section {
+            if let Some((key, value)) = line.split_once('=') {
+                let value = value.trim();
+                if value.len() >= 2 && value.starts_with('"') && value.ends_with('"') {
+                    let template = value[1..value.len() - 1].to_string();
+                    config.formats.insert(key.trim().to_string(), template);
+                } else {
+                    warnings.push(format!("Format for {} must be a quoted string", key.trim()));
+                }
+            }
+            continue;
+        }
+
+        // Parse per-section module order, e.g. hardware = ["cpu", "gpu", "displays", "memory"]
+        if in_order_section {
+            if let Some((key, value)) = line.split_once('=') {
+                match parse_string_list(value) {
+                    Some(names) => {
+                        // Checked here rather than when the lines are ordered, so it's said
+                        // once and not on every --watch tick
+                        let section = key.trim();
+                        for name in &names {
+                            if !collect::known_module(section, name) {
+                                warnings.push(format!(
+                                    "Unknown module '{}' in {} order",
+                                    name, section
+                                ));
+                            }
+                        }
+                        config.order.insert(section.to_string(), names);
+                    }
+                    None => warnings.push(format!("Order for {} must be a list", key.trim())),
+                }
+            }
+            continue;
+        }
+
+        // Parse per-module cache TTLs, e.g. gpu = "never", os = "7d"
+        if in_cache_section {
+            if let Some((key, value)) = line.split_once('=') {
+                let key = key.trim();
+                if !CACHED_MODULES.iter().any(|(name, ..)| *name == key) {
+                    warnings.push(format!("Unknown cache key '{}'", key));
+                } else if let Some(ttl) = parse_ttl(unquote(value)) {
+                    config.cache_ttl.insert(key.to_string(), ttl);
+                } else {
+                    warnings.push(format!(
+                        "Bad cache TTL for {}: {} (expected never, always or e.g. \"30m\", \"7d\")",
+                        key,
+                        value.trim()
+                    ));
+                }
+            }
+            continue;
+        }
+
+        // Parse art color slot remaps, e.g. "1" = 4 draws slot 1 in the art_4 color
+        if in_art_remap_section {
+            if let Some((key, value)) = line.split_once('=') {
+                let slot = |text: &str| {
+                    unquote(text).parse().ok().filter(|slot| (1..=9).contains(slot))
+                };
+                match (slot(key), slot(value)) {
+                    (Some(from), Some(to)) => {
+                        config.art_remap.insert(from, to);
                     }
+                    _ => warnings.push(format!(
+                        "Bad art remap {} = {} (slots go from 1 to 9)",
+                        key.trim(),
+                        value.trim()
+                    )),
                 }
             }
             continue;
         }
 
+        // Parse per-module unknown handling, e.g. gpu = "hide-if-unknown"
+        if in_modules_section {
+            if let Some((key, value)) = line.split_once('=') {
+                let key = key.trim().to_string();
+                match unquote(value) {
+                    "hide-if-unknown" => {
+                        config.unknown_lines.insert(key, true);
+                    }
+                    "show" => {
+                        config.unknown_lines.insert(key, false);
+                    }
+                    other => warnings.push(format!(
+                        "Bad value for module {}: {} (expected \"hide-if-unknown\" or \"show\")",
+                        key, other
+                    )),
+                }
+            }
+            continue;
+        }
+
+        // Parse a countdown, name = "Vacation" and date = "2025-03-02"
+        if section == "event" {
+            let (Some((key, value)), Some(event)) = (line.split_once('='), events.last_mut()) else {
+                continue;
+            };
+            let value = unquote(value);
+            match key.trim() {
+                "name" => event.0 = Some(value.to_string()),
+                "date" => match dateformat::parse_date(value) {
+                    Some(date) => event.1 = Some(date),
+                    None => {
+                        warnings.push(format!("Bad event date {} (expected YYYY-MM-DD)", value))
+                    }
+                },
+                other => warnings.push(format!("Unknown event key '{}'", other)),
+            }
+            continue;
+        }
+
+        // Parse logo accent setting
+        if line.starts_with("logo_accent")
+            && let Some(value) = line.split('=').nth(1)
+        {
+            config.logo_accent = unquote(value) == "auto";
+        }
+
         // Parse os_art setting
         if line.starts_with("os_art") {
             if let Some(value) = line.split('=').nth(1) {
@@ -240,7 +1368,7 @@ fn parse_config(content: &str) -> Config {
                     config.os_art = OsArtSetting::Disabled;
                 } else if value.starts_with('"') && value.ends_with('"') {
                     // Extract string value between quotes
-                    let os_name = value.trim_matches('"').to_string();
+                    let os_name = unquote(value).to_string();
                     if !os_name.is_empty() {
                         config.os_art = OsArtSetting::Specific(os_name);
                     }
@@ -250,10 +1378,10 @@ fn parse_config(content: &str) -> Config {
 
         // Parse custom_art setting
         if line.starts_with("custom_art") {
-            if let Some(value) = line.split('=').nth(1) {
+            if let Some((_, value)) = line.split_once('=') {
                 let value = value.trim();
                 if value.starts_with('"') && value.ends_with('"') {
-                    let path = value.trim_matches('"').to_string();
+                    let path = unquote(value).to_string();
                     if !path.is_empty() {
                         // Expand ~ to home directory
                         let expanded_path = if path.starts_with("~/") {
@@ -271,20 +1399,499 @@ fn parse_config(content: &str) -> Config {
             }
         }
 
-        // Parse image toggle
-        if line.starts_with("image") && !line.starts_with("image_path") {
-            if let Some(value) = line.split('=').nth(1) {
-                let value = value.trim();
-                config.image = value == "true";
+        // Parse art setting, a registry name or "random"
+        if line.starts_with("art")
+            && !line.starts_with("art_dir")
+            && let Some(value) = line.split('=').nth(1)
+        {
+            let name = unquote(value);
+            if !name.is_empty() {
+                config.art = Some(name.to_string());
+            }
+        }
+
+        // Parse art_dir setting
+        if line.starts_with("art_dir")
+            && let Some((_, value)) = line.split_once('=')
+        {
+            let path = unquote(value);
+            if !path.is_empty() {
+                config.art_dir = Some(expand_home(path));
+            }
+        }
+
+        // Parse tab_width setting, a tab needs at least one column to go to
+        if line.starts_with("tab_width")
+            && let Some(value) = line.split('=').nth(1)
+            && let Ok(width) = value.trim().parse::<usize>()
+        {
+            if width > 0 {
+                config.tab_width = width;
+            } else {
+                warnings.push("tab_width must be at least 1, keeping 8".to_string());
+            }
+        }
+
+        // Parse boot entries toggle
+        if line.starts_with("show_boot_entries")
+            && let Some(value) = line.split('=').nth(1)
+        {
+            config.show_boot_entries = value.trim() == "true";
+        }
+
+        // Parse bootloader toggle
+        if line.starts_with("show_bootloader")
+            && let Some(value) = line.split('=').nth(1)
+        {
+            config.show_bootloader = value.trim() == "true";
+        }
+
+        // Parse pending kernel update check
+        if line.starts_with("kernel_check")
+            && let Some(value) = line.split('=').nth(1)
+        {
+            config.kernel_check = value.trim() == "true";
+        }
+
+        // Parse NixOS detail toggle
+        if line.starts_with("nixos_detail")
+            && let Some(value) = line.split('=').nth(1)
+        {
+            config.nixos_detail = value.trim() == "true";
+        }
+
+        // Parse show_host override
+        if line.starts_with("show_host")
+            && let Some(value) = line.split('=').nth(1)
+        {
+            match unquote(value) {
+                "true" => config.show_host = Some(true),
+                "false" => config.show_host = Some(false),
+                "auto" => config.show_host = None,
+                _ => {}
+            }
+        }
+
+        // Parse uptime seconds override
+        if line.starts_with("uptime_seconds")
+            && let Some(value) = line.split('=').nth(1)
+        {
+            match unquote(value) {
+                "true" => config.uptime_seconds = Some(true),
+                "false" => config.uptime_seconds = Some(false),
+                "auto" => config.uptime_seconds = None,
+                _ => {}
+            }
+        }
+
+        // Parse session length toggle
+        if line.starts_with("show_session")
+            && let Some(value) = line.split('=').nth(1)
+        {
+            config.show_session = value.trim() == "true";
+        }
+
+        // Parse prompt detection toggle
+        if line.starts_with("show_prompt")
+            && let Some(value) = line.split('=').nth(1)
+        {
+            config.show_prompt = value.trim() == "true";
+        }
+
+        // Parse desktop details toggle
+        if line.starts_with("show_desktop")
+            && let Some(value) = line.split('=').nth(1)
+        {
+            config.show_desktop = value.trim() == "true";
+        }
+
+        // Parse display manager toggle
+        if line.starts_with("show_login")
+            && let Some(value) = line.split('=').nth(1)
+        {
+            config.show_login = value.trim() == "true";
+        }
+
+        // Parse audio device toggle
+        if line.starts_with("show_audio")
+            && let Some(value) = line.split('=').nth(1)
+        {
+            config.show_audio = value.trim() == "true";
+        }
+
+        // Parse disk/network activity toggle
+        if line.starts_with("show_activity")
+            && let Some(value) = line.split('=').nth(1)
+        {
+            config.show_activity = value.trim() == "true";
+        }
+
+        // Parse network toggle and which addresses it shows
+        if line.starts_with("show_network")
+            && let Some(value) = line.split('=').nth(1)
+        {
+            config.show_network = value.trim() == "true";
+        }
+        if line.starts_with("show_ip")
+            && let Some(value) = line.split('=').nth(1)
+        {
+            let value = unquote(value);
+            config.show_ipv4 = matches!(value, "ipv4" | "both");
+            config.show_ipv6 = matches!(value, "ipv6" | "both");
+        }
+
+        // Parse power profile toggle
+        if line.starts_with("power_detail")
+            && let Some(value) = line.split('=').nth(1)
+        {
+            config.power_detail = value.trim() == "true";
+        }
+
+        // Parse load average toggle
+        if line.starts_with("show_load")
+            && let Some(value) = line.split('=').nth(1)
+        {
+            config.show_load = value.trim() == "true";
+        }
+
+        // Parse theme colors source
+        if line.starts_with("colors_from")
+            && let Some(value) = line.split('=').nth(1)
+        {
+            colors_from_dms = unquote(value) == "dms";
+        }
+
+        // Parse greeting toggle
+        if line.starts_with("greeting")
+            && let Some(value) = line.split('=').nth(1)
+        {
+            config.greeting = value.trim() == "true";
+        }
+
+        // Parse footer text, split_once keeps any '=' in it (URLs with a query string)
+        if line.starts_with("footer")
+            && let Some((_, value)) = line.split_once('=')
+        {
+            let text = unquote(value);
+            config.footer = (!text.is_empty()).then(|| text.to_string());
+        }
+
+        // Parse date format, a bad one falls back to the ISO style default
+        if line.starts_with("date_format")
+            && let Some((_, value)) = line.split_once('=')
+        {
+            let format = unquote(value);
+            config.date_format = match dateformat::check_format(format) {
+                _ if format.is_empty() => None,
+                Ok(()) => Some(format.to_string()),
+                Err(error) => {
+                    warnings.push(format!("{}, using \"{}\"", error, DEFAULT_DATE_FORMAT));
+                    Some(DEFAULT_DATE_FORMAT.to_string())
+                }
+            };
+        }
+
+        // Parse strict mode toggle
+        if line.starts_with("strict_config")
+            && let Some(value) = line.split('=').nth(1)
+        {
+            config.strict_config = value.trim() == "true";
+        }
+
+        // Parse accessible output toggle
+        if line.starts_with("accessible")
+            && let Some(value) = line.split('=').nth(1)
+        {
+            config.accessible = value.trim() == "true";
+        }
+
+        // Parse hyperlinks toggle
+        if line.starts_with("hyperlinks")
+            && let Some(value) = line.split('=').nth(1)
+        {
+            config.hyperlinks = value.trim() == "true";
+        }
+
+        // Parse display detail toggle
+        if line.starts_with("display_detail")
+            && let Some(value) = line.split('=').nth(1)
+        {
+            config.display_detail = value.trim() == "true";
+        }
+
+        // Parse WM detail toggle
+        if line.starts_with("wm_detail")
+            && let Some(value) = line.split('=').nth(1)
+        {
+            config.wm_detail = value.trim() == "true";
+        }
+
+        // Parse terminal detail toggle
+        if line.starts_with("terminal_detail")
+            && let Some(value) = line.split('=').nth(1)
+        {
+            config.terminal_detail = value.trim() == "true";
+        }
+
+        // Parse package detail toggle
+        if line.starts_with("packages_detail")
+            && let Some(value) = line.split('=').nth(1)
+        {
+            config.packages_detail = value.trim() == "true";
+        }
+
+        // Parse vendor icons toggle
+        if line.starts_with("vendor_icons")
+            && let Some(value) = line.split('=').nth(1)
+        {
+            config.vendor_icons = value.trim() == "true";
+        }
+
+        // Parse unified box toggle
+        if line.starts_with("unified_box")
+            && let Some(value) = line.split('=').nth(1)
+        {
+            config.unified_box = value.trim() == "true";
+        }
+
+        // Parse art animation toggle
+        if line.starts_with("animate")
+            && let Some(value) = line.split('=').nth(1)
+        {
+            config.animate = value.trim() == "true";
+        }
+
+        // Parse microarchitecture names toggle
+        if line.starts_with("arch_names")
+            && let Some(value) = line.split('=').nth(1)
+        {
+            config.arch_names = value.trim() == "true";
+        }
+
+        // Parse encryption toggle
+        if line.starts_with("show_encryption")
+            && let Some(value) = line.split('=').nth(1)
+        {
+            config.show_encryption = value.trim() == "true";
+        }
+
+        // Parse network filesystem toggle
+        if line.starts_with("include_network_fs")
+            && let Some(value) = line.split('=').nth(1)
+        {
+            config.include_network_fs = value.trim() == "true";
+        }
+
+        // Parse usage deltas: "off", "run" or a window like "7d"
+        if line.starts_with("usage_deltas")
+            && let Some(value) = line.split('=').nth(1)
+        {
+            config.usage_deltas = match unquote(value) {
+                "off" => None,
+                "run" => Some(Duration::ZERO),
+                window => match parse_ttl(window) {
+                    Some(CacheTtl::After(window)) => Some(window),
+                    _ => {
+                        warnings.push(format!(
+                            "Bad usage_deltas {} (expected \"off\", \"run\" or e.g. \"7d\")",
+                            window
+                        ));
+                        None
+                    }
+                },
+            };
+        }
+
+        // Parse fetch deadline settings
+        if line.starts_with("max_fetch_ms")
+            && let Some(value) = line.split('=').nth(1)
+            && let Ok(ms) = value.trim().parse::<u64>()
+        {
+            config.max_fetch_ms = ms;
+        }
+        if line.starts_with("hide_timed_out")
+            && let Some(value) = line.split('=').nth(1)
+        {
+            config.hide_timed_out = value.trim() == "true";
+        }
+        if line.starts_with("threads")
+            && let Some(value) = line.split('=').nth(1)
+            && let Ok(threads) = value.trim().parse::<usize>()
+        {
+            config.threads = threads;
+        }
+
+        // Parse the keys --diff shouldn't skip
+        if line.starts_with("diff_include")
+            && let Some((_, value)) = line.split_once('=')
+            && let Some(keys) = parse_string_list(value)
+        {
+            config.diff_include = keys;
+        }
+
+        // Parse hide_unknown toggle
+        if line.starts_with("hide_unknown")
+            && let Some(value) = line.split('=').nth(1)
+        {
+            config.hide_unknown = value.trim() == "true";
+        }
+
+        // Parse nerd_font override
+        if line.starts_with("nerd_font")
+            && let Some(value) = line.split('=').nth(1)
+        {
+            match unquote(value) {
+                "true" => config.nerd_font = Some(true),
+                "false" => config.nerd_font = Some(false),
+                "auto" => config.nerd_font = None,
+                // Anything else was already flagged as a bad value
+                _ => {}
+            }
+        }
+
+        // Parse terminal and terminal_font overrides, empty is auto
+        if line.starts_with("terminal")
+            && !line.starts_with("terminal_detail")
+            && let Some((key, value)) = line.split_once('=')
+        {
+            let value = unquote(value).trim();
+            let value = (!value.is_empty()).then(|| value.to_string());
+            match key.trim() {
+                "terminal" => config.terminal = value,
+                "terminal_font" => config.terminal_font = value,
+                _ => {}
             }
         }
 
+        // Parse respect_cgroups override
+        if line.starts_with("respect_cgroups")
+            && let Some(value) = line.split('=').nth(1)
+        {
+            match unquote(value) {
+                "true" => config.respect_cgroups = Some(true),
+                "false" => config.respect_cgroups = Some(false),
+                "auto" => config.respect_cgroups = None,
+                _ => {}
+            }
+        }
+
+        // Parse color_mode setting
+        if line.starts_with("color_mode")
+            && let Some(value) = line.split('=').nth(1)
+        {
+            // "auto" and anything unknown leave it to detection
+            config.color_mode = ColorMode::parse(unquote(value));
+        }
+
+        // Parse background setting: "auto", "dark", "light" or a hex color
+        if line.starts_with("background")
+            && let Some(value) = line.split('=').nth(1)
+        {
+            config.background = match unquote(value) {
+                "auto" => None,
+                "dark" => Some((0, 0, 0)),
+                "light" => Some((255, 255, 255)),
+                color => parse_hex_color(color).or_else(|| {
+                    warnings.push(format!("Bad value for background: {}", value.trim()));
+                    None
+                }),
+            };
+        }
+
+        // Parse auto_contrast toggle
+        if line.starts_with("auto_contrast")
+            && let Some(value) = line.split('=').nth(1)
+        {
+            config.auto_contrast = value.trim() == "true";
+        }
+
+        // Parse cpu_clock setting
+        if line.starts_with("cpu_clock")
+            && let Some(value) = line.split('=').nth(1)
+        {
+            match unquote(value) {
+                "boost" => config.cpu_clock = CpuClock::Boost,
+                "base" => config.cpu_clock = CpuClock::Base,
+                "current" => config.cpu_clock = CpuClock::Current,
+                "none" => config.cpu_clock = CpuClock::None,
+                _ => {}
+            }
+        }
+
+        // Parse battery thresholds
+        if line.starts_with("battery_")
+            && let Some((key, value)) = line.split_once('=')
+            && let Ok(percent) = value.trim().parse::<u8>()
+        {
+            match key.trim() {
+                "battery_low" => config.battery_thresholds.low = percent,
+                "battery_critical" => config.battery_thresholds.critical = percent,
+                _ => {}
+            }
+        }
+
+        if line.starts_with("battery_power")
+            && let Some(value) = line.split('=').nth(1)
+        {
+            config.battery_power = value.trim() == "true";
+        }
+
+        // Parse section balancing settings
+        if line.starts_with("balance_sections")
+            && let Some(value) = line.split('=').nth(1)
+        {
+            config.balance_sections = value.trim() == "true";
+        }
+        if line.starts_with("balance_title")
+            && let Some((_, value)) = line.split_once('=')
+        {
+            let title = unquote(value);
+            config.balance_title = (!title.is_empty()).then(|| title.to_string());
+        }
+
+        // Parse compact mode settings
+        if line.starts_with("compact_width") {
+            if let Some(value) = line.split('=').nth(1)
+                && let Ok(width) = value.trim().parse::<usize>()
+            {
+                config.compact_width = width;
+            }
+        } else if line.starts_with("compact")
+            && let Some(value) = line.split('=').nth(1)
+        {
+            config.compact = value.trim() == "true";
+        }
+
+        // Parse [layout] margins
+        if line.starts_with("margin_")
+            && let Some((key, value)) = line.split_once('=')
+            && let Ok(size) = value.trim().parse::<usize>()
+        {
+            match key.trim() {
+                "margin_left" => config.margins.left = size,
+                "margin_top" => config.margins.top = size,
+                "margin_bottom" => config.margins.bottom = size,
+                _ => {}
+            }
+        }
+
+        // Parse image toggle
+        if line.starts_with("image")
+            && !line.starts_with("image_path")
+            && !line.starts_with("image_resample")
+            && !line.starts_with("image_max_width")
+            && let Some(value) = line.split('=').nth(1)
+        {
+            let value = value.trim();
+            config.image = value == "true";
+        }
+
         // Parse image_path setting
         if line.starts_with("image_path") {
-            if let Some(value) = line.split('=').nth(1) {
+            if let Some((_, value)) = line.split_once('=') {
                 let value = value.trim();
                 if value.starts_with('"') && value.ends_with('"') {
-                    let path = value.trim_matches('"').to_string();
+                    let path = unquote(value).to_string();
                     if !path.is_empty() {
                         // Expand ~ to home directory
                         let expanded_path = if path.starts_with("~/") {
@@ -301,7 +1908,327 @@ fn parse_config(content: &str) -> Config {
                 }
             }
         }
+
+        if line.starts_with("verify_image")
+            && let Some(value) = line.split('=').nth(1)
+        {
+            config.verify_image = value.trim() == "true";
+        }
+
+        if line.starts_with("image_resample")
+            && let Some(value) = line.split('=').nth(1)
+        {
+            config.image_resample = unquote(value) != "off";
+        }
+
+        if line.starts_with("image_max_width")
+            && let Some(value) = line.split('=').nth(1)
+            && let Ok(width) = value.trim().parse::<usize>()
+        {
+            config.image_max_width = width;
+        }
+    }
+
+    for event in events {
+        match event {
+            (Some(name), Some(date)) => config.events.push(Event { name, date }),
+            (name, _) => warnings.push(format!(
+                "[[event]] {}needs both a name and a date",
+                name.map(|name| format!("\"{}\" ", name)).unwrap_or_default()
+            )),
+        }
     }
 
+    // Theme colors go under anything set in [colors]
+    if colors_from_dms {
+        match helpers::get_dms_palette() {
+            Some(palette) => {
+                colorcontrol::apply_theme_palette(&mut config.colors, &palette, &explicit_colors)
+            }
+            None => warnings.push(
+                "colors_from = \"dms\" but no DankMaterialShell theme colors were found".into(),
+            ),
+        }
+    }
     config
 }
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn unknown_order_names_warn_at_load() {
+        let mut warnings = Vec::new();
+        let config = parse_config(
+            "[order]\nhardware = [\"displays\", \"cpuu\", \"memory\"]\ncore = [\"os\"]\n",
+            &mut warnings,
+        );
+        assert_eq!(warnings, ["Unknown module 'cpuu' in hardware order"]);
+        // Kept as written, the unknown name is skipped when the lines are put in order
+        assert_eq!(config.order["hardware"], ["displays", "cpuu", "memory"]);
+    }
+
+    const LAYERED: &str = "[display]\nfooter = \"from the file\"\n\n\
+                           [colors]\nborder = \"#111111\"\n\n\
+                           [profile.work]\nfooter = \"from the profile\"\n\n\
+                           [profile.work.colors]\nborder = \"#222222\"\n";
+
+    fn vars(pairs: &[(&str, &str)]) -> std::vec::IntoIter<(String, String)> {
+        let pairs: Vec<_> = pairs.iter().map(|(k, v)| (k.to_string(), v.to_string())).collect();
+        pairs.into_iter()
+    }
+
+    #[test]
+    fn env_beats_profile_beats_file() {
+        let (config, warnings) = config_from(LAYERED, None, vars(&[])).unwrap();
+        assert_eq!(config.footer.as_deref(), Some("from the file"));
+        assert_eq!(config.colors.border, (0x11, 0x11, 0x11));
+        assert!(warnings.is_empty(), "{:?}", warnings);
+
+        let (config, _) = config_from(LAYERED, Some("work"), vars(&[])).unwrap();
+        assert_eq!(config.footer.as_deref(), Some("from the profile"));
+        assert_eq!(config.colors.border, (0x22, 0x22, 0x22));
+
+        let env = [("SLOWFETCH_FOOTER", "from the env"), ("SLOWFETCH_COLORS__BORDER", "#333333")];
+        let (config, warnings) = config_from(LAYERED, Some("work"), vars(&env)).unwrap();
+        assert_eq!(config.footer.as_deref(), Some("from the env"));
+        assert_eq!(config.colors.border, (0x33, 0x33, 0x33));
+        assert!(warnings.is_empty(), "{:?}", warnings);
+
+        // --color goes on top of all of them
+        let mut colors = config.colors;
+        colors.apply_overrides(&["border=#444444".to_string()]).unwrap();
+        assert_eq!(colors.border, (0x44, 0x44, 0x44));
+    }
+
+    // Every layer sets one color fewer than the one below it, so each color shows which
+    // layer won: defaults < [colors] < profile < environment < --color
+    #[test]
+    fn color_precedence() {
+        let file = "[colors]\nborder = \"#111111\"\ntitle = \"#111111\"\nkey = \"#111111\"\n\
+                    value = \"#111111\"\n\n\
+                    [profile.shot.colors]\ntitle = \"#222222\"\nkey = \"#222222\"\n\
+                    value = \"#222222\"\n";
+        let env = [("SLOWFETCH_COLORS__KEY", "#333333"), ("SLOWFETCH_COLORS__VALUE", "#333333")];
+        let (config, warnings) = config_from(file, Some("shot"), vars(&env)).unwrap();
+        assert!(warnings.is_empty(), "{:?}", warnings);
+        let mut colors = config.colors;
+        colors.apply_overrides(&["value=444444".to_string()]).unwrap();
+
+        let grey = |v: u8| (v, v, v);
+        assert_eq!(colors.warning, ColorConfig::default().warning);
+        assert_eq!(colors.border, grey(0x11));
+        assert_eq!(colors.title, grey(0x22));
+        assert_eq!(colors.key, grey(0x33));
+        assert_eq!(colors.value, grey(0x44));
+    }
+
+    #[test]
+    fn bad_color_overrides() {
+        let mut colors = ColorConfig::default();
+        // The last one for a key wins, with or without the #
+        let overrides = ["art_3=#FFB86C", " key = BD93F9 ", "art_3=#50FA7B"].map(String::from);
+        colors.apply_overrides(&overrides).unwrap();
+        assert_eq!((colors.art_3, colors.key), ((0x50, 0xFA, 0x7B), (0xBD, 0x93, 0xF9)));
+
+        let error = |entry: &str| colors.clone().apply_overrides(&[entry.to_string()]).unwrap_err();
+        assert_eq!(error("border"), "--color border should look like key=#RRGGBB");
+        assert_eq!(error("border=#12345"), "Bad color for border: #12345 (expected #RRGGBB)");
+        assert_eq!(error("border=red"), "Bad color for border: red (expected #RRGGBB)");
+        let unknown = error("frame=#50FA7B");
+        let listed = "Unknown color 'frame' (colors: border, title, ";
+        assert!(unknown.starts_with(listed), "{}", unknown);
+        assert!(unknown.contains("art_9"), "{}", unknown);
+    }
+
+    // A config a Windows editor saved: BOM, CRLF endings, and tabs around '='
+    #[test]
+    fn bom_crlf_and_tabs() {
+        let unix = "[display]\nshow_network = true\nfooter = \"hello\"\n\
+                    custom_art = \"/home/me/art/a=b.txt\"\nimage_path = \"/home/me/x=1.png\"\n\
+                    art_dir = \"/home/me/art=dir\"\n\n[colors]\nborder = \"#50FA7B\"\n";
+        let windows = format!("\u{FEFF}{}", unix.replace(" = ", "\t=\t").replace('\n', "\r\n"));
+        for content in [unix, windows.as_str()] {
+            let (config, warnings) = config_from(content, None, vars(&[])).unwrap();
+            assert!(warnings.is_empty(), "{:?}", warnings);
+            assert!(config.show_network);
+            assert_eq!(config.footer.as_deref(), Some("hello"));
+            assert_eq!(config.custom_art.as_deref(), Some("/home/me/art/a=b.txt"));
+            assert_eq!(config.image_path.as_deref(), Some("/home/me/x=1.png"));
+            assert_eq!(config.art_dir.as_deref(), Some("/home/me/art=dir"));
+            assert_eq!(config.colors.border, (0x50, 0xFA, 0x7B));
+        }
+
+        // The BOM would otherwise stick to the first key, a [colors] header right at the start
+        let (config, warnings) =
+            config_from("\u{FEFF}[colors]\r\nborder = \"#111111\"\r\n", None, vars(&[])).unwrap();
+        assert!(warnings.is_empty(), "{:?}", warnings);
+        assert_eq!(config.colors.border, (0x11, 0x11, 0x11));
+    }
+
+    #[test]
+    fn env_values_with_quotes_survive() {
+        let env = [("SLOWFETCH_FOOTER", r#"say "hi" # not a comment \o/"#)];
+        let (config, warnings) = config_from("", None, vars(&env)).unwrap();
+        assert_eq!(config.footer.as_deref(), Some(r#"say "hi" # not a comment \o/"#));
+        assert!(warnings.is_empty(), "{:?}", warnings);
+
+        // The same escapes work in the file
+        let file = "[display]\nfooter = \"a \\\"quoted\\\" word\" # comment\n";
+        let (config, _) = config_from(file, None, vars(&[])).unwrap();
+        assert_eq!(config.footer.as_deref(), Some("a \"quoted\" word"));
+    }
+
+    #[test]
+    fn only_config_vars_are_overrides() {
+        let env = [
+            ("SLOWFETCH_PROFILE", "work"),
+            ("SLOWFETCH_BLESS", "1"),
+            ("SLOWFETCH_NOT_A_KEY", "x"),
+            ("HOME", "/home/someone"),
+        ];
+        let (_, warnings) = config_from("", None, vars(&env)).unwrap();
+        assert_eq!(warnings, ["Unknown environment override SLOWFETCH_NOT_A_KEY"]);
+    }
+
+    // Profiles above and below the base sections, they win either way
+    const PROFILES: &str = "\
+[profile.full.colors]
+title = \"#BBBBBB\"
+border = \"#CCCCCC\"
+
+[display]
+show_network = true
+
+[colors]
+border = \"#111111\"
+title = \"#222222\"
+
+[order]
+core = [\"os\", \"kernel\"]
+hardware = [\"cpu\", \"gpu\"]
+
+[profile.minimal]
+show_network = false
+
+[profile.minimal.colors]
+border = \"#AAAAAA\"
+
+[profile.minimal.order]
+core = [\"os\"]
+";
+
+    #[test]
+    fn profiles_override_key_by_key() {
+        let grey = |v: u8| (v, v, v);
+        // profile, border, title, core order, hardware order, show_network
+        let cases = [
+            (None, grey(0x11), grey(0x22), vec!["os", "kernel"], vec!["cpu", "gpu"], true),
+            // Border only, the title stays; the core list is replaced, hardware inherited
+            (Some("minimal"), grey(0xAA), grey(0x22), vec!["os"], vec!["cpu", "gpu"], false),
+            (Some("full"), grey(0xCC), grey(0xBB), vec!["os", "kernel"], vec!["cpu", "gpu"], true),
+        ];
+        for (profile, border, title, core, hardware, show_network) in cases {
+            let (config, warnings) = config_from(PROFILES, profile, vars(&[])).unwrap();
+            assert!(warnings.is_empty(), "{:?}: {:?}", profile, warnings);
+            assert_eq!(config.colors.border, border, "{:?}", profile);
+            assert_eq!(config.colors.title, title, "{:?}", profile);
+            assert_eq!(config.order["core"], core, "{:?}", profile);
+            assert_eq!(config.order["hardware"], hardware, "{:?}", profile);
+            assert_eq!(config.show_network, show_network, "{:?}", profile);
+        }
+    }
+
+    #[test]
+    fn unknown_profiles_list_the_real_ones() {
+        let error = config_from(PROFILES, Some("work"), vars(&[])).err().unwrap();
+        assert_eq!(error, "Unknown profile 'work' (available: full, minimal)");
+        let error = config_from("[display]\n", Some("work"), vars(&[])).err().unwrap();
+        assert_eq!(error, "Unknown profile 'work', the config doesn't define any");
+    }
+
+    #[test]
+    fn bad_date_formats_fall_back() {
+        let mut warnings = Vec::new();
+        let config = parse_config("[display]\ndate_format = \"%d.%m.%Y %T\"\n", &mut warnings);
+        assert_eq!(config.date_format.as_deref(), Some(DEFAULT_DATE_FORMAT));
+        assert_eq!(warnings, ["unsupported %T in date_format, using \"%Y-%m-%d %H:%M\""]);
+
+        let mut warnings = Vec::new();
+        let config = parse_config("[display]\ndate_format = \"%d.%m.%Y\"\n", &mut warnings);
+        assert_eq!(config.date_format.as_deref(), Some("%d.%m.%Y"));
+        assert!(warnings.is_empty());
+    }
+
+    #[test]
+    fn modules_section_overrides() {
+        let mut warnings = Vec::new();
+        let content = "hide_unknown = true\n[modules]\n\
+                       gpu = \"hide-if-unknown\"\nbattery = \"show\"\neditor = \"maybe\"\n";
+        let config = parse_config(content, &mut warnings);
+        assert!(config.hide_unknown);
+        assert_eq!(config.unknown_lines.get("gpu"), Some(&true));
+        assert_eq!(config.unknown_lines.get("battery"), Some(&false));
+        assert_eq!(config.unknown_lines.get("editor"), None);
+        assert_eq!(
+            warnings,
+            ["Bad value for module editor: maybe (expected \"hide-if-unknown\" or \"show\")"]
+        );
+    }
+
+    // Made up renames, one of each kind, RENAMES has nothing to test with yet
+    const FIXTURE_RENAMES: &[Rename] = &[
+        Rename { renamed: Renamed::Key("display"), old: "os_logo", new: "os_art" },
+        Rename { renamed: Renamed::Key("colors"), old: "frame", new: "border" },
+        Rename { renamed: Renamed::Module, old: "disk", new: "storage" },
+    ];
+
+    fn fixture(name: &str) -> String {
+        let path = PathBuf::from(env!("CARGO_MANIFEST_DIR")).join("tests/fixtures").join(name);
+        fs::read_to_string(path).unwrap()
+    }
+
+    // Old names all over a config, in profiles too, next to comments and lookalikes that must
+    // stay: tests/fixtures/migrate_config.toml rewritten is migrate_config_expected.toml
+    #[test]
+    fn renames_keep_everything_else() {
+        let input = fixture("migrate_config.toml");
+        let (output, changes) = rename_keys(&input, FIXTURE_RENAMES);
+        assert_eq!(output, fixture("migrate_config_expected.toml"));
+
+        let lines: Vec<usize> = changes.iter().map(|(line, _)| *line).collect();
+        assert_eq!(lines, [6, 11, 18, 22, 26, 36, 40, 43]);
+        // Nothing left to rename the second time
+        assert!(rename_keys(&output, FIXTURE_RENAMES).1.is_empty());
+        // One notice per old name, however often it's used
+        assert_eq!(deprecation_notices(&changes).len(), 3);
+    }
+
+    // Nothing has been renamed yet, every config comes out of the real table as it went in
+    #[test]
+    fn current_renames_leave_configs_alone() {
+        for content in [fixture("migrate_config.toml"), DEFAULT_CONFIG.to_string()] {
+            let (output, changes) = rename_keys(&content, RENAMES);
+            assert!(changes.is_empty());
+            assert_eq!(output, content);
+        }
+    }
+
+    #[test]
+    fn nerd_font_override() {
+        let nerd_font = |value: &str| {
+            let mut warnings = Vec::new();
+            let content = format!("[display]\nnerd_font = {}\n", value);
+            let config = parse_config(&content, &mut warnings);
+            (config.nerd_font, warnings.len())
+        };
+        assert_eq!(nerd_font("true"), (Some(true), 0));
+        assert_eq!(nerd_font("false"), (Some(false), 0));
+        assert_eq!(nerd_font("\"true\""), (Some(true), 0));
+        assert_eq!(nerd_font("\"auto\""), (None, 0));
+        // A bad value warns and stays on auto
+        assert_eq!(nerd_font("\"yes\""), (None, 1));
+        assert_eq!(parse_config("", &mut Vec::new()).nerd_font, None);
+    }
+}
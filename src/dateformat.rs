@@ -0,0 +1,256 @@
+// Date formatting for date_format, a small strftime subset without pulling in chrono.
+// Supports %Y %m %d %H %M %b %B %A and %% for a literal percent sign.
+// Also reads the YYYY-MM-DD dates of [[event]] and counts the days between them.
+
+use crate::helpers::LocalTime;
+
+// What a date_format that doesn't parse is replaced with
+pub const DEFAULT_DATE_FORMAT: &str = "%Y-%m-%d %H:%M";
+
+// Month and weekday names for one language. English only for now, another language is
+// just another table.
+struct DateNames {
+    // January first
+    months: [&'static str; 12],
+    // What %b shows, not always the first three letters of the full name
+    short_months: [&'static str; 12],
+    // Sunday first, like tm_wday
+    weekdays: [&'static str; 7],
+}
+
+const ENGLISH: DateNames = DateNames {
+    months: [
+        "January", "February", "March", "April", "May", "June", "July", "August", "September",
+        "October", "November", "December",
+    ],
+    short_months: [
+        "Jan", "Feb", "Mar", "Apr", "May", "Jun", "Jul", "Aug", "Sep", "Oct", "Nov", "Dec",
+    ],
+    weekdays: ["Sunday", "Monday", "Tuesday", "Wednesday", "Thursday", "Friday", "Saturday"],
+};
+
+// The names dates are written with
+fn names() -> &'static DateNames {
+    &ENGLISH
+}
+
+// Check a date_format before using it, Err says what's wrong with it
+pub fn check_format(format: &str) -> Result<(), String> {
+    let mut chars = format.chars();
+    while let Some(c) = chars.next() {
+        if c != '%' {
+            continue;
+        }
+        match chars.next() {
+            Some('Y' | 'm' | 'd' | 'H' | 'M' | 'b' | 'B' | 'A' | '%') => {}
+            Some(other) => return Err(format!("unsupported %{} in date_format", other)),
+            None => return Err("date_format ends in a lone %".to_string()),
+        }
+    }
+    Ok(())
+}
+
+// Write a date with a checked format. Numbers are zero padded except the year.
+pub fn format_date(time: &LocalTime, format: &str) -> String {
+    let names = names();
+    let mut result = String::with_capacity(format.len() + 8);
+    let mut chars = format.chars();
+    while let Some(c) = chars.next() {
+        if c != '%' {
+            result.push(c);
+            continue;
+        }
+        match chars.next() {
+            Some('Y') => result.push_str(&time.year.to_string()),
+            Some('m') => result.push_str(&format!("{:02}", time.month)),
+            Some('d') => result.push_str(&format!("{:02}", time.day)),
+            Some('H') => result.push_str(&format!("{:02}", time.hour)),
+            Some('M') => result.push_str(&format!("{:02}", time.minute)),
+            Some('b') => {
+                let month = names.short_months.get(time.month.wrapping_sub(1) as usize);
+                result.push_str(month.copied().unwrap_or("?"));
+            }
+            Some('B') => {
+                let month = names.months.get(time.month.wrapping_sub(1) as usize);
+                result.push_str(month.copied().unwrap_or("?"));
+            }
+            Some('A') => {
+                let weekday = names.weekdays.get(time.weekday as usize);
+                result.push_str(weekday.copied().unwrap_or("?"));
+            }
+            Some('%') => result.push('%'),
+            // check_format keeps these out, written as is just in case
+            Some(other) => {
+                result.push('%');
+                result.push(other);
+            }
+            None => result.push('%'),
+        }
+    }
+    result
+}
+
+fn is_leap_year(year: i32) -> bool {
+    (year % 4 == 0 && year % 100 != 0) || year % 400 == 0
+}
+
+fn days_in_month(year: i32, month: u32) -> u32 {
+    match month {
+        2 if is_leap_year(year) => 29,
+        2 => 28,
+        4 | 6 | 9 | 11 => 30,
+        _ => 31,
+    }
+}
+
+// (year, month, day), January is 1
+pub type Date = (i32, u32, u32);
+
+// A "YYYY-MM-DD" date. None unless the day exists, Feb 29 only in leap years.
+pub fn parse_date(text: &str) -> Option<Date> {
+    let mut parts = text.split('-');
+    let (year, month, day) = (parts.next()?, parts.next()?, parts.next()?);
+    let digits =
+        |part: &str, len: usize| part.len() == len && part.bytes().all(|b| b.is_ascii_digit());
+    if parts.next().is_some() || !digits(year, 4) || !digits(month, 2) || !digits(day, 2) {
+        return None;
+    }
+    let (year, month, day) = (year.parse().ok()?, month.parse().ok()?, day.parse().ok()?);
+    ((1..=12).contains(&month) && (1..=days_in_month(year, month)).contains(&day))
+        .then_some((year, month, day))
+}
+
+// Days since 1970-01-01 (negative before it), so two dates can be subtracted.
+// Howard Hinnant's days_from_civil: counts in 400 year eras with years starting in March,
+// which puts the leap day last and keeps the month lengths a simple formula.
+pub fn days_from_civil(year: i32, month: u32, day: u32) -> i64 {
+    let year = i64::from(year) - i64::from(month <= 2);
+    let era = year.div_euclid(400);
+    let year_of_era = year - era * 400;
+    let month = i64::from(month);
+    let day_of_year = (153 * (if month > 2 { month - 3 } else { month + 9 }) + 2) / 5
+        + i64::from(day)
+        - 1;
+    let day_of_era = year_of_era * 365 + year_of_era / 4 - year_of_era / 100 + day_of_year;
+    era * 146097 + day_of_era - 719468
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn at(year: i32, month: u32, day: u32, hour: u32, minute: u32, weekday: u32) -> LocalTime {
+        LocalTime { year, month, day, hour, minute, weekday }
+    }
+
+    #[test]
+    fn edge_dates() {
+        let new_year = at(2025, 1, 1, 0, 0, 3);
+        assert_eq!(format_date(&new_year, DEFAULT_DATE_FORMAT), "2025-01-01 00:00");
+        assert_eq!(format_date(&new_year, "%A %d %B"), "Wednesday 01 January");
+        let new_years_eve = at(2024, 12, 31, 23, 59, 2);
+        assert_eq!(format_date(&new_years_eve, DEFAULT_DATE_FORMAT), "2024-12-31 23:59");
+        assert_eq!(format_date(&new_years_eve, "%b %d, %H:%M"), "Dec 31, 23:59");
+        let leap_day = at(2024, 2, 29, 9, 5, 4);
+        assert_eq!(format_date(&leap_day, "%d/%m/%Y %A"), "29/02/2024 Thursday");
+        // Every weekday and month has its name
+        assert_eq!(format_date(&at(2025, 9, 7, 0, 0, 0), "%A %b %B"), "Sunday Sep September");
+        assert_eq!(format_date(&at(2025, 5, 10, 0, 0, 6), "%A %b %B"), "Saturday May May");
+    }
+
+    #[test]
+    fn padding_and_literals() {
+        let time = at(987, 3, 4, 5, 6, 1);
+        // The year isn't padded, everything else is two digits
+        assert_eq!(format_date(&time, "%Y-%m-%d %H:%M"), "987-03-04 05:06");
+        assert_eq!(format_date(&time, "100%% at %H:%M%%"), "100% at 05:06%");
+        assert_eq!(format_date(&time, "no fields"), "no fields");
+        assert_eq!(format_date(&time, ""), "");
+        // Out of range fields don't panic
+        assert_eq!(format_date(&at(2025, 13, 1, 0, 0, 9), "%b %A"), "? ?");
+    }
+
+    #[test]
+    fn only_supported_specifiers_pass() {
+        for format in [DEFAULT_DATE_FORMAT, "%A, %d %B %Y", "%b %d", "50%%", "plain", ""] {
+            assert_eq!(check_format(format), Ok(()), "{}", format);
+        }
+        assert_eq!(check_format("%Y-%j"), Err("unsupported %j in date_format".to_string()));
+        assert_eq!(check_format("%e.%m."), Err("unsupported %e in date_format".to_string()));
+        assert_eq!(check_format("%H:%M %"), Err("date_format ends in a lone %".to_string()));
+        assert_eq!(check_format("%%%"), Err("date_format ends in a lone %".to_string()));
+    }
+
+    #[test]
+    fn leap_years() {
+        for year in [2024, 2000, 1600, 2400, 1996] {
+            assert!(is_leap_year(year), "{}", year);
+        }
+        // Divisible by 100 but not by 400
+        for year in [2023, 2100, 1900, 2200, 1800, 2025] {
+            assert!(!is_leap_year(year), "{}", year);
+        }
+        assert_eq!(parse_date("2024-02-29"), Some((2024, 2, 29)));
+        assert_eq!(parse_date("2000-02-29"), Some((2000, 2, 29)));
+        assert_eq!(parse_date("2023-02-29"), None);
+        assert_eq!(parse_date("2100-02-29"), None);
+        assert_eq!(parse_date("1900-02-29"), None);
+        assert_eq!(parse_date("2024-02-30"), None);
+    }
+
+    #[test]
+    fn day_counts() {
+        assert_eq!(days_from_civil(1970, 1, 1), 0);
+        assert_eq!(days_from_civil(1969, 12, 31), -1);
+        assert_eq!(days_from_civil(2000, 1, 1), 10957);
+        // Leap days in and out of the century rules
+        assert_eq!(days_from_civil(2024, 3, 1) - days_from_civil(2024, 2, 28), 2);
+        assert_eq!(days_from_civil(2000, 3, 1) - days_from_civil(2000, 2, 28), 2);
+        assert_eq!(days_from_civil(2100, 3, 1) - days_from_civil(2100, 2, 28), 1);
+        assert_eq!(days_from_civil(2025, 1, 1) - days_from_civil(2024, 1, 1), 366);
+        assert_eq!(days_from_civil(2101, 1, 1) - days_from_civil(2100, 1, 1), 365);
+
+        // Against counting one day at a time, across both century rules and the epoch
+        let mut expected = days_from_civil(1890, 1, 1);
+        for year in 1890..2110 {
+            for month in 1..=12 {
+                for day in 1..=days_in_month(year, month) {
+                    let days = days_from_civil(year, month, day);
+                    assert_eq!(days, expected, "{}-{}-{}", year, month, day);
+                    expected += 1;
+                }
+            }
+        }
+    }
+
+    #[test]
+    fn malformed_dates() {
+        for text in [
+            "",
+            "2025",
+            "2025-01",
+            "2025-1-01",
+            "2025-01-1",
+            "25-01-01",
+            "02025-01-01",
+            "2025-00-10",
+            "2025-13-01",
+            "2025-01-00",
+            "2025-01-32",
+            "2025-04-31",
+            "2025-01-01-",
+            "2025-01-01-01",
+            "2025/01/01",
+            " 2025-01-01",
+            "2025-01-01 ",
+            "+025-01-01",
+            "2025-0a-01",
+            "２０２５-01-01",
+            "-2025-01-01",
+        ] {
+            assert_eq!(parse_date(text), None, "{:?}", text);
+        }
+        assert_eq!(parse_date("0001-01-01"), Some((1, 1, 1)));
+        assert_eq!(parse_date("2025-12-31"), Some((2025, 12, 31)));
+    }
+}
@@ -0,0 +1,162 @@
+// Memory and storage growth for usage_deltas, "(+1.2GB since last run)" after the bar.
+// Every run leaves a timestamped sample per metric in the cache, the delta is taken against the
+// newest sample that's at least the configured window old. Small changes are noise and
+// don't get an annotation.
+
+use std::time::{Duration, SystemTime, UNIX_EPOCH};
+
+use crate::cache;
+
+// Samples kept per metric, the oldest goes first
+const HISTORY_LEN: usize = 10;
+
+// A metric with a history: its cache file and the smallest change worth showing, in bytes
+pub struct Metric {
+    key: &'static str,
+    threshold: u64,
+}
+
+pub const MEMORY: Metric = Metric { key: "memory_history", threshold: 100_000_000 };
+pub const STORAGE: Metric = Metric { key: "storage_history", threshold: 1_000_000_000 };
+
+// Record this run's value and describe how it changed, None when it's within the noise or
+// there's no sample old enough yet. A zero window compares against the last run.
+pub fn annotate(metric: &Metric, bytes: u64, window: Duration) -> Option<String> {
+    let now = SystemTime::now().duration_since(UNIX_EPOCH).ok()?.as_secs();
+    let mut history = cache::read_history(metric.key);
+    let annotation = describe(metric, &history, now, bytes, window);
+    if record(&mut history, now, bytes, window) {
+        cache::write_history(metric.key, &history);
+    }
+    annotation
+}
+
+// The annotation for `bytes` now against the history so far
+fn describe(
+    metric: &Metric,
+    history: &[(u64, u64)],
+    now: u64,
+    bytes: u64,
+    window: Duration,
+) -> Option<String> {
+    let delta = delta(history, now, bytes, window.as_secs(), metric.threshold)?;
+    let since = match window.as_secs() {
+        0 => "since last run".to_string(),
+        86400 => "since yesterday".to_string(),
+        604800 => "this week".to_string(),
+        _ => format!("in the last {}", cache::format_age(window)),
+    };
+    Some(format!("{} {}", format_delta(delta), since))
+}
+
+// Add this run's sample to the history, false when it's too soon after the last one to keep.
+// Samples stay at least a tenth of the window apart, so the history reaches back a whole
+// window however often slowfetch runs.
+fn record(history: &mut Vec<(u64, u64)>, now: u64, bytes: u64, window: Duration) -> bool {
+    let spacing = window.as_secs() / HISTORY_LEN as u64;
+    if history.last().is_some_and(|&(time, _)| now.saturating_sub(time) < spacing.max(1)) {
+        return false;
+    }
+    history.push((now, bytes));
+    let excess = history.len().saturating_sub(HISTORY_LEN);
+    history.drain(..excess);
+    true
+}
+
+// Change against the newest sample at least `window` seconds old, None without one or when
+// the change is under `threshold`
+fn delta(history: &[(u64, u64)], now: u64, bytes: u64, window: u64, threshold: u64) -> Option<i64> {
+    let &(_, then) = history.iter().rev().find(|&&(time, _)| now.saturating_sub(time) >= window)?;
+    let delta = bytes as i64 - then as i64;
+    (delta.unsigned_abs() >= threshold).then_some(delta)
+}
+
+// "+1.2GB", "-350MB", decimal units like the storage line
+fn format_delta(delta: i64) -> String {
+    let sign = if delta < 0 { '-' } else { '+' };
+    let bytes = delta.unsigned_abs() as f64;
+    if bytes >= 1e12 {
+        format!("{}{:.1}TB", sign, bytes / 1e12)
+    } else if bytes >= 1e9 {
+        format!("{}{:.1}GB", sign, bytes / 1e9)
+    } else {
+        format!("{}{:.0}MB", sign, bytes / 1e6)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    const DAY: u64 = 86400;
+    const WEEK: Duration = Duration::from_secs(7 * DAY);
+    const NOW: u64 = 1_750_000_000;
+    const GB: u64 = 1_000_000_000;
+
+    #[test]
+    fn nothing_to_compare_with() {
+        assert_eq!(describe(&STORAGE, &[], NOW, 500 * GB, WEEK), None);
+        assert_eq!(describe(&MEMORY, &[], NOW, 8 * GB, Duration::ZERO), None);
+    }
+
+    #[test]
+    fn single_sample() {
+        // Old enough for the window, or not yet
+        let history = [(NOW - 8 * DAY, 486 * GB)];
+        assert_eq!(describe(&STORAGE, &history, NOW, 500 * GB, WEEK).unwrap(), "+14.0GB this week");
+        assert_eq!(describe(&STORAGE, &history, NOW, 500 * GB, Duration::from_secs(9 * DAY)), None);
+        // The last run, however recent
+        let history = [(NOW - 5, 6_500_000_000)];
+        let annotation = describe(&MEMORY, &history, NOW, 7_700_000_000, Duration::ZERO);
+        assert_eq!(annotation.unwrap(), "+1.2GB since last run");
+        let annotation = describe(&MEMORY, &history, NOW, 6_150_000_000, Duration::from_secs(DAY));
+        assert_eq!(annotation, None);
+    }
+
+    #[test]
+    fn newest_sample_old_enough() {
+        let history = [(NOW - 9 * DAY, 400 * GB), (NOW - 7 * DAY, 480 * GB), (NOW - DAY, 499 * GB)];
+        assert_eq!(describe(&STORAGE, &history, NOW, 500 * GB, WEEK).unwrap(), "+20.0GB this week");
+        let since = describe(&STORAGE, &history, NOW, 497 * GB, Duration::from_secs(DAY));
+        assert_eq!(since.unwrap(), "-2.0GB since yesterday");
+        let three_days = describe(&STORAGE, &history, NOW, 400 * GB, Duration::from_secs(3 * DAY));
+        assert_eq!(three_days.unwrap(), "-80.0GB in the last 3d");
+    }
+
+    #[test]
+    fn changes_under_the_threshold_are_noise() {
+        let history = [(NOW - 60, 8 * GB)];
+        let memory = |bytes| describe(&MEMORY, &history, NOW, bytes, Duration::ZERO);
+        assert_eq!(memory(8 * GB + 99_999_999), None);
+        assert_eq!(memory(8 * GB - 99_999_999), None);
+        assert_eq!(memory(8 * GB), None);
+        assert_eq!(memory(8 * GB + 100_000_000).unwrap(), "+100MB since last run");
+        assert_eq!(memory(8 * GB - 350_000_000).unwrap(), "-350MB since last run");
+        // A change that's plenty for memory is nothing on a disk
+        let storage = |bytes| describe(&STORAGE, &history, NOW, bytes, Duration::ZERO);
+        assert_eq!(storage(8 * GB + 900_000_000), None);
+        assert_eq!(storage(8 * GB + GB).unwrap(), "+1.0GB since last run");
+        assert_eq!(storage(2500 * GB).unwrap(), "+2.5TB since last run");
+    }
+
+    #[test]
+    fn history_is_a_bounded_ring() {
+        let mut history = Vec::new();
+        assert!(record(&mut history, NOW, 1, Duration::ZERO));
+        // Every run counts for "run", just not twice in the same second
+        assert!(!record(&mut history, NOW, 2, Duration::ZERO));
+        for i in 1..20 {
+            assert!(record(&mut history, NOW + i, i, Duration::ZERO));
+        }
+        assert_eq!(history.len(), HISTORY_LEN);
+        assert_eq!(history.first(), Some(&(NOW + 10, 10)));
+        assert_eq!(history.last(), Some(&(NOW + 19, 19)));
+
+        // A week's samples are kept at least a tenth of a week apart
+        let mut history = vec![(NOW, 1)];
+        let spacing = 7 * DAY / 10;
+        assert!(!record(&mut history, NOW + spacing - 1, 2, WEEK));
+        assert!(record(&mut history, NOW + spacing, 3, WEEK));
+        assert_eq!(history, [(NOW, 1), (NOW + spacing, 3)]);
+    }
+}
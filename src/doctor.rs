@@ -0,0 +1,454 @@
+// `slowfetch doctor`: runs every check and collector fresh and prints what it found as a plain
+// ✓/✗ checklist, with a hint under anything that can be fixed. For bug reports and for
+// figuring out why a line says "unknown".
+
+use std::fmt::Display;
+use std::fs;
+use std::os::unix::fs::PermissionsExt;
+use std::path::{Path, PathBuf};
+use std::process::Command;
+use std::thread;
+use std::time::{Duration, Instant};
+
+use crate::cache;
+use crate::collect::{self, ValueState};
+use crate::configloader::{self, Config};
+use crate::helpers::{self, command_output_limited};
+#[cfg(feature = "image")]
+use crate::image;
+use crate::modules::{fontmodule, hardwaremodules, userspacemodules};
+use crate::renderer::strip_escapes;
+
+const VERSION: &str = env!("CARGO_PKG_VERSION");
+
+// What to install when a GPU probe has nothing, by probe name
+const GPU_HINTS: &[(&str, &str)] = &[
+    ("vulkaninfo", "install vulkan-tools, it's the fastest way to name the GPU"),
+    ("glxinfo", "install mesa-utils (mesa-demos on Arch/Fedora)"),
+    ("sysfs + pci.ids", "needs /sys/class/drm and pci.ids (the hwdata package)"),
+    ("lspci", "install pciutils"),
+];
+
+// The checklist as it's written, counting the ✗ lines
+struct Report {
+    text: String,
+    problems: usize,
+}
+
+impl Report {
+    fn heading(&mut self, title: &str) {
+        self.text.push_str(&format!("\n{}\n", title));
+    }
+
+    // A check that's a problem when it fails
+    fn check(&mut self, ok: bool, line: impl Display) {
+        self.problems += usize::from(!ok);
+        self.text.push_str(&format!("  {} {}\n", if ok { "✓" } else { "✗" }, line));
+    }
+
+    // Not there, but nothing's wrong (no battery, no image mode)
+    fn skip(&mut self, line: impl Display) {
+        self.text.push_str(&format!("  – {}\n", line));
+    }
+
+    // Evidence under the line above
+    fn detail(&mut self, ok: bool, line: impl Display) {
+        self.text.push_str(&format!("      {} {}\n", if ok { "✓" } else { "✗" }, line));
+    }
+
+    fn hint(&mut self, line: impl Display) {
+        self.text.push_str(&format!("    → {}\n", line));
+    }
+}
+
+// Build the report. `warnings` are the config warnings main already printed.
+// Every optional module is turned on, so doctor covers the ones the config leaves off too.
+pub fn report(mut config: Config, warnings: &[String]) -> String {
+    let mut report = Report { text: "Slowfetch doctor\n".to_string(), problems: 0 };
+    // Fresh values, not last week's cache
+    cache::set_force_refresh(true);
+
+    report.heading("Install");
+    install(&mut report);
+
+    report.heading("Config");
+    match configloader::get_config_path() {
+        Some(path) => report.check(true, format!("config file: {}", path.display())),
+        None => {
+            report.check(false, "no config file, running on the defaults");
+            report.hint("run slowfetch once and it installs one to ~/.config/slowfetch");
+        }
+    }
+    if warnings.is_empty() {
+        report.check(true, "no unknown keys or bad values");
+    } else {
+        let line = format!("{} config problem(s), see the warnings above", warnings.len());
+        report.check(false, line);
+    }
+    if let Some(written_for) = &config.written_for
+        && configloader::is_newer_version(written_for, VERSION)
+    {
+        let line = format!("config is from slowfetch {}, this is {}", written_for, VERSION);
+        report.check(false, line);
+        report.hint("options added since show up as unknown keys, update this install");
+    }
+
+    report.heading("Cache");
+    match cache::check_writable() {
+        Ok(dir) => report.check(true, format!("cache directory is writable: {}", dir.display())),
+        Err(error) => {
+            report.check(false, format!("can't write the cache: {}", error));
+            report.hint("slow values (GPU, OS, nerd font check) are looked up on every run");
+        }
+    }
+
+    report.heading("GPU");
+    #[cfg(feature = "pci-ids")]
+    match helpers::pci_ids_path() {
+        Some(path) => report.check(true, format!("pci.ids: {}", path)),
+        None => {
+            report.check(false, "pci.ids: not in /usr/share/hwdata or /usr/share/misc");
+            report.hint("install hwdata (pciutils ships it on Debian)");
+        }
+    }
+    #[cfg(not(feature = "pci-ids"))]
+    report.skip("pci.ids: built without pci-ids, GPUs are named by the tools and lspci");
+    // Only the first probe that works is used, the ones after it failing doesn't matter
+    let mut found = false;
+    for (name, took, gpu) in hardwaremodules::gpu_probes() {
+        let took = format!("{} ({:.1}ms)", name, took.as_secs_f64() * 1000.0);
+        match gpu {
+            Some(gpu) => {
+                report.check(true, format!("{}: {}", took, gpu));
+                found = true;
+            }
+            None if found => report.skip(format!("{}: nothing (not needed)", took)),
+            None => {
+                report.check(false, format!("{}: nothing", took));
+                if let Some((_, hint)) = GPU_HINTS.iter().find(|(probe, _)| *probe == name) {
+                    report.hint(hint);
+                }
+            }
+        }
+    }
+
+    report.heading("Terminal");
+    let terminal = userspacemodules::terminal();
+    if config.terminal.is_some() {
+        report.check(true, format!("terminal: {} (terminal in the config)", terminal));
+    } else {
+        report.check(terminal != "Unknown", format!("terminal: {}", terminal));
+    }
+    match fontmodule::config_file(&terminal) {
+        Some(file) if Path::new(&file).is_file() => {
+            report.check(true, format!("terminal config: {}", file))
+        }
+        Some(file) => {
+            report.check(false, format!("terminal config: {} doesn't exist", file));
+            report.hint("the font, opacity and padding are read from there");
+        }
+        None => report.skip(format!("terminal config: not read for {}", terminal)),
+    }
+    let font = helpers::cached_font();
+    if config.terminal_font.is_some() {
+        report.check(true, format!("font: {} (terminal_font in the config)", font));
+    } else {
+        report.check(font != "unknown", format!("font: {}", font));
+    }
+    graphics(&mut report, &config);
+    match config.nerd_font {
+        Some(forced) => {
+            report.check(true, format!("nerd font: {} (nerd_font in the config)", forced))
+        }
+        None => {
+            let evidence = fontmodule::nerd_font_evidence(font);
+            if evidence.iter().any(|(ok, _)| *ok) {
+                report.check(true, "nerd font: yes");
+            } else {
+                report.skip("nerd font: no, bars and icons use plain characters");
+            }
+            for (ok, line) in evidence {
+                report.detail(ok, line);
+            }
+        }
+    }
+
+    // Every collector, with the optional ones turned on
+    config.verbose = true;
+    config.show_boot_entries = true;
+    config.show_bootloader = true;
+    config.show_host = Some(true);
+    config.show_session = true;
+    config.show_prompt = true;
+    config.show_desktop = true;
+    config.show_login = true;
+    config.show_audio = true;
+    config.show_activity = true;
+    // show_ip stays as configured, doctor output ends up in bug reports
+    config.show_network = true;
+    config.power_detail = true;
+    config.show_load = true;
+    config.nixos_detail = true;
+    config.packages_detail = true;
+    let started = Instant::now();
+    let (statics, dynamic) = thread::scope(|scope| {
+        let dynamic_handler = scope.spawn(|| collect::collect_dynamic(&config));
+        let statics = collect::collect_static(&config);
+        let dynamic = dynamic_handler.join().unwrap_or_else(|payload| {
+            collect::DynamicInfo::failed(collect::panic_reason(&*payload))
+        });
+        (statics, dynamic)
+    });
+    let took = started.elapsed().as_secs_f64() * 1000.0;
+
+    report.heading(&format!("Modules (collected in {:.0}ms)", took));
+    modules(&mut report, collect::module_states(&statics, &dynamic));
+
+    let summary = match report.problems {
+        0 => "\nEverything checks out\n".to_string(),
+        problems => format!("\n{} problem(s) found\n", problems),
+    };
+    report.text.push_str(&summary);
+    report.text
+}
+
+// A line for each module with what it came back with
+fn modules(report: &mut Report, states: Vec<(&str, ValueState)>) {
+    for (module, state) in states {
+        match state {
+            // Bars and hyperlinks are escapes, the report is plain text
+            ValueState::Ok(value) => {
+                report.check(true, format!("{}: {}", module, strip_escapes(&value)))
+            }
+            ValueState::Unknown => report.check(false, format!("{}: unknown", module)),
+            ValueState::Failed(reason) => {
+                report.check(false, format!("{}: {}", module, reason));
+                if reason == "timed out" {
+                    report.hint("raise max_fetch_ms, or set it to 0 to wait as long as it takes");
+                }
+            }
+            ValueState::Disabled => report.skip(format!("{}: doesn't apply here", module)),
+        }
+    }
+}
+
+// The running binary and any other slowfetch on PATH. Two installs of different versions
+// (cargo install and a distro package) make config options look like they don't work.
+fn install(report: &mut Report) {
+    let path = std::env::var("PATH").unwrap_or_default();
+    install_from(report, std::env::current_exe().ok(), &path);
+}
+
+// install() for a given running binary and PATH
+fn install_from(report: &mut Report, running: Option<PathBuf>, path: &str) {
+    match &running {
+        Some(path) => report.check(true, format!("running: {} ({})", path.display(), VERSION)),
+        None => report.check(false, format!("running: can't read /proc/self/exe ({})", VERSION)),
+    }
+    let running = running.and_then(|path| path.canonicalize().ok());
+    let found = path_binaries(path);
+    let position = found.iter().position(|(_, real)| Some(real) == running.as_ref());
+
+    let mut others = 0;
+    for (i, (path, _)) in found.iter().enumerate().filter(|(i, _)| Some(*i) != position) {
+        others += 1;
+        let (order, hint) = match position {
+            Some(position) if i < position => (
+                "comes first on PATH",
+                "typing slowfetch runs that one, remove the install you don't use",
+            ),
+            Some(_) => {
+                ("comes later on PATH", "harmless for now, but remove the one you don't use")
+            }
+            None => ("is on PATH, this one isn't", "typing slowfetch runs that one"),
+        };
+        match binary_version(path) {
+            Some(version) if version == VERSION => {
+                report.skip(format!("{}: same version, {}", path.display(), order))
+            }
+            version => {
+                let version = version.unwrap_or_else(|| "unknown version".to_string());
+                report.check(false, format!("{} ({}) {}", path.display(), version, order));
+                report.hint(hint);
+            }
+        }
+    }
+    match (others, position) {
+        (0, Some(_)) => report.check(true, "no other slowfetch on PATH"),
+        (0, None) => report.skip("not on PATH"),
+        _ => {}
+    }
+}
+
+// Executables named slowfetch in PATH order, as (path, resolved path). A file reached through
+// two PATH entries or a symlink counts once.
+fn path_binaries(path: &str) -> Vec<(PathBuf, PathBuf)> {
+    let mut found: Vec<(PathBuf, PathBuf)> = Vec::new();
+    for dir in path.split(':').filter(|dir| !dir.is_empty()) {
+        let candidate = Path::new(dir).join("slowfetch");
+        let executable = fs::metadata(&candidate)
+            .is_ok_and(|metadata| metadata.is_file() && metadata.permissions().mode() & 0o111 != 0);
+        if !executable {
+            continue;
+        }
+        if let Ok(real) = candidate.canonicalize()
+            && !found.iter().any(|(_, seen)| *seen == real)
+        {
+            found.push((candidate, real));
+        }
+    }
+    found
+}
+
+// "0.2.5" from `slowfetch --version`
+fn binary_version(path: &Path) -> Option<String> {
+    let output =
+        command_output_limited(Command::new(path).arg("--version"), 256, Duration::from_secs(2))?;
+    let output = String::from_utf8_lossy(&output);
+    Some(output.trim().strip_prefix("slowfetch ")?.to_string())
+}
+
+// Whether image mode can draw: what the environment says, and what the terminal answers
+#[cfg(feature = "image")]
+fn graphics(report: &mut Report, config: &Config) {
+    let from_env = image::supports_kitty_graphics();
+    let line = match image::query_kitty_graphics() {
+        Some(true) => (true, "kitty graphics: the terminal answered the query".to_string()),
+        Some(false) => (false, "kitty graphics: the terminal didn't answer the query".into()),
+        None => (from_env, "kitty graphics: no terminal to ask, going by the environment".into()),
+    };
+    match line {
+        (true, line) => report.check(true, line),
+        // Only a problem when image mode is on
+        (false, line) if config.image => {
+            report.check(false, line);
+            report.hint("image mode needs kitty, ghostty or another kitty graphics terminal");
+        }
+        (false, line) => report.skip(line),
+    }
+}
+
+#[cfg(not(feature = "image"))]
+fn graphics(report: &mut Report, _config: &Config) {
+    report.skip("kitty graphics: built without image support");
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::testutil::TempDir;
+
+    // The module lines with every module coming back with state()
+    fn every_module(state: impl Fn() -> ValueState) -> Report {
+        let mut report = Report { text: String::new(), problems: 0 };
+        let states = collect::module_names().into_iter().map(|name| (name, state())).collect();
+        modules(&mut report, states);
+        report
+    }
+
+    // Every registered module gets a line whatever it came back with. Fed straight to
+    // modules(), report() would turn on force refresh for the cache tests running next to it.
+    #[test]
+    fn an_entry_for_every_module() {
+        let names = collect::module_names();
+        let lines_for = |report: Report, marker: &str, value: &str| {
+            let expected: Vec<String> =
+                names.iter().map(|name| format!("  {} {}: {}", marker, name, value)).collect();
+            assert_eq!(report.text.lines().collect::<Vec<_>>(), expected);
+            assert_eq!(report.problems, if marker == "✗" { names.len() } else { 0 });
+        };
+        let ok = every_module(|| ValueState::Ok("\x1b[32m■■■\x1b[39m 40%".into()));
+        lines_for(ok, "✓", "■■■ 40%");
+        lines_for(every_module(|| ValueState::Unknown), "✗", "unknown");
+        let failed = every_module(|| ValueState::Failed("panicked: no drm".into()));
+        lines_for(failed, "✗", "panicked: no drm");
+        lines_for(every_module(|| ValueState::Disabled), "–", "doesn't apply here");
+    }
+
+    // Timing out has a fix, with a hint under each line
+    #[test]
+    fn timed_out_modules_get_a_hint() {
+        let report = every_module(|| ValueState::Failed("timed out".into()));
+        let lines: Vec<&str> = report.text.lines().collect();
+        assert_eq!(lines.len(), collect::module_names().len() * 2);
+        for pair in lines.chunks(2) {
+            assert!(pair[0].ends_with(": timed out"), "{}", pair[0]);
+            assert!(pair[1].starts_with("    → raise max_fetch_ms"), "{}", pair[1]);
+        }
+    }
+
+    // A fake slowfetch answering --version with `version`
+    fn fake(dir: &TempDir, relative: &str, version: &str) -> PathBuf {
+        let script = format!("#!/bin/sh\necho 'slowfetch {}'\n", version);
+        let path = dir.write(relative, script);
+        fs::set_permissions(&path, fs::Permissions::from_mode(0o755)).unwrap();
+        path
+    }
+
+    fn path_of(dir: &TempDir, dirs: &[&str]) -> String {
+        let dirs: Vec<String> = dirs.iter().map(|d| dir.join(d).display().to_string()).collect();
+        dirs.join(":")
+    }
+
+    // PATH order kept, files that aren't executable or aren't files skipped, and a binary
+    // reached twice (the same dir listed again, a symlink) counted once
+    #[test]
+    fn binaries_on_path() {
+        let dir = TempDir::new();
+        let cargo = fake(&dir, "cargo/bin/slowfetch", "0.2.5");
+        let usr = fake(&dir, "usr/bin/slowfetch", "0.2.4");
+        dir.write("plain/slowfetch", "not executable");
+        fs::create_dir_all(dir.join("folder/slowfetch")).unwrap();
+        dir.symlink("local/slowfetch", &usr);
+        fs::create_dir_all(dir.join("empty")).unwrap();
+
+        let dirs = ["plain", "folder", "cargo/bin", "empty", "usr/bin", "cargo/bin", "local"];
+        let path = format!("{}::{}", path_of(&dir, &dirs), dir.join("missing").display());
+        let found = path_binaries(&path);
+        let paths: Vec<&Path> = found.iter().map(|(path, _)| path.as_path()).collect();
+        assert_eq!(paths, [&cargo, &usr]);
+        assert_eq!(found[1].1, usr.canonicalize().unwrap());
+        assert!(path_binaries("").is_empty());
+
+        assert_eq!(binary_version(&cargo).as_deref(), Some("0.2.5"));
+        assert_eq!(binary_version(&usr).as_deref(), Some("0.2.4"));
+        let other = dir.write("other/slowfetch", "#!/bin/sh\necho 'neofetch 7.1.0'\n");
+        fs::set_permissions(&other, fs::Permissions::from_mode(0o755)).unwrap();
+        assert_eq!(binary_version(&other), None);
+    }
+
+    // A different version ahead of the running one is a problem with a hint, the same
+    // version is only mentioned
+    #[test]
+    fn other_installs_are_flagged() {
+        let dir = TempDir::new();
+        let running = fake(&dir, "cargo/bin/slowfetch", VERSION);
+        let old = fake(&dir, "usr/bin/slowfetch", "0.1.0");
+        fake(&dir, "opt/bin/slowfetch", VERSION);
+        let install = |dirs: &[&str]| {
+            let mut report = Report { text: String::new(), problems: 0 };
+            install_from(&mut report, Some(running.clone()), &path_of(&dir, dirs));
+            report
+        };
+
+        let report = install(&["usr/bin", "cargo/bin", "opt/bin"]);
+        assert_eq!(report.problems, 1);
+        let lines: Vec<&str> = report.text.lines().collect();
+        assert_eq!(lines.len(), 4);
+        assert!(lines[1].contains(&format!("{} (0.1.0) comes first on PATH", old.display())));
+        assert!(lines[2].contains("typing slowfetch runs that one"), "{}", lines[2]);
+        assert!(lines[3].contains("same version, comes later on PATH"), "{}", lines[3]);
+
+        let report = install(&["cargo/bin", "usr/bin"]);
+        assert_eq!(report.problems, 1);
+        assert!(report.text.contains("(0.1.0) comes later on PATH"), "{}", report.text);
+
+        let report = install(&["cargo/bin"]);
+        assert_eq!(report.problems, 0);
+        assert!(report.text.ends_with("no other slowfetch on PATH\n"), "{}", report.text);
+
+        let report = install(&["opt/bin"]);
+        assert_eq!(report.problems, 0);
+        assert!(report.text.contains("same version, is on PATH, this one isn't"));
+    }
+}
@@ -0,0 +1,347 @@
+// Export module for Slowfetch
+// Turns the rendered (ANSI colored) layout into standalone HTML or SVG for sharing,
+// or writes it out as is. --json prints the collected values instead of the layout.
+
+use std::fmt::Write;
+
+use crate::renderer::{Section, visible_len};
+use crate::snapshot;
+
+// Page background and fallback text color for exports (Dracula-ish, matches the default theme)
+const EXPORT_BACKGROUND: &str = "#282A36";
+const EXPORT_FOREGROUND: &str = "#F8F8F2";
+
+// Rough monospace cell size used to size the SVG canvas
+const SVG_CELL_WIDTH: f64 = 8.4;
+const SVG_LINE_HEIGHT: f64 = 17.0;
+const SVG_PADDING: f64 = 16.0;
+
+// Supported export formats
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub enum ExportFormat {
+    Html,
+    Svg,
+    // The layout as printed, escape codes and all (for `cat` or a README code block)
+    Ansi,
+}
+
+impl ExportFormat {
+    pub fn parse(name: &str) -> Option<Self> {
+        match name.to_lowercase().as_str() {
+            "html" => Some(ExportFormat::Html),
+            "svg" => Some(ExportFormat::Svg),
+            "ansi" => Some(ExportFormat::Ansi),
+            _ => None,
+        }
+    }
+}
+
+// Text style active for a run of characters
+#[derive(Debug, Clone, Copy, Default, PartialEq)]
+struct SgrStyle {
+    fg: Option<(u8, u8, u8)>,
+    bold: bool,
+}
+
+impl SgrStyle {
+    // Apply the parameters of one SGR sequence (the part between "ESC[" and "m")
+    fn apply(&mut self, params: &str) {
+        let codes: Vec<u16> = params
+            .split(';')
+            .map(|code| code.parse().unwrap_or(0))
+            .collect();
+
+        let mut i = 0;
+        while i < codes.len() {
+            match codes[i] {
+                0 => *self = SgrStyle::default(),
+                1 => self.bold = true,
+                22 => self.bold = false,
+                39 => self.fg = None,
+                // Truecolor foreground: 38;2;r;g;b
+                38 if codes.get(i + 1) == Some(&2) && i + 4 < codes.len() => {
+                    self.fg = Some((
+                        codes[i + 2] as u8,
+                        codes[i + 3] as u8,
+                        codes[i + 4] as u8,
+                    ));
+                    i += 4;
+                }
+                // 256-color foreground (38;5;n) isn't emitted by slowfetch, skip its argument
+                38 if codes.get(i + 1) == Some(&5) => i += 2,
+                _ => {}
+            }
+            i += 1;
+        }
+    }
+
+    fn css(&self) -> String {
+        let mut css = String::new();
+        if let Some((r, g, b)) = self.fg {
+            let _ = write!(css, "color:#{:02X}{:02X}{:02X};", r, g, b);
+        }
+        if self.bold {
+            css.push_str("font-weight:bold;");
+        }
+        css
+    }
+}
+
+// Split one line of ANSI text into (style, text) runs.
+// The style carries over between lines, like it would in a terminal.
+fn parse_line(line: &str, style: &mut SgrStyle) -> Vec<(SgrStyle, String)> {
+    let mut runs: Vec<(SgrStyle, String)> = Vec::new();
+    let mut text = String::new();
+    let mut chars = line.chars().peekable();
+
+    while let Some(c) = chars.next() {
+        if c == '\x1b' && chars.peek() == Some(&'[') {
+            chars.next();
+            // Collect parameters up to the final byte of the CSI sequence
+            let mut params = String::new();
+            let mut final_byte = None;
+            for next in chars.by_ref() {
+                if ('\x40'..='\x7e').contains(&next) {
+                    final_byte = Some(next);
+                    break;
+                }
+                params.push(next);
+            }
+            // Only SGR ('m') changes the look, other CSI sequences are dropped
+            if final_byte == Some('m') {
+                if !text.is_empty() {
+                    runs.push((*style, std::mem::take(&mut text)));
+                }
+                style.apply(&params);
+            }
+        } else {
+            text.push(c);
+        }
+    }
+
+    if !text.is_empty() {
+        runs.push((*style, text));
+    }
+    runs
+}
+
+// Escape text for HTML/XML
+fn escape_xml(text: &str) -> String {
+    let mut escaped = String::with_capacity(text.len());
+    for c in text.chars() {
+        match c {
+            '&' => escaped.push_str("&amp;"),
+            '<' => escaped.push_str("&lt;"),
+            '>' => escaped.push_str("&gt;"),
+            '"' => escaped.push_str("&quot;"),
+            _ => escaped.push(c),
+        }
+    }
+    escaped
+}
+
+// Convert ANSI colored text into the body of a <pre> block with inline-styled spans
+pub fn ansi_to_html(ansi: &str) -> String {
+    let mut html = String::with_capacity(ansi.len() * 2);
+    let mut style = SgrStyle::default();
+
+    for line in ansi.lines() {
+        for (run_style, text) in parse_line(line, &mut style) {
+            let css = run_style.css();
+            if css.is_empty() {
+                html.push_str(&escape_xml(&text));
+            } else {
+                let _ = write!(html, "<span style=\"{}\">{}</span>", css, escape_xml(&text));
+            }
+        }
+        html.push('\n');
+    }
+
+    html
+}
+
+// Wrap the rendered layout in a standalone HTML page
+pub fn to_html(ansi: &str) -> String {
+    format!(
+        "<!DOCTYPE html>\n<html>\n<head>\n<meta charset=\"utf-8\">\n<title>slowfetch</title>\n</head>\n\
+         <body style=\"background:{bg};margin:0;padding:16px;\">\n\
+         <pre style=\"color:{fg};font-family:monospace;font-size:14px;line-height:1.2;margin:0;\">\n\
+         {body}</pre>\n</body>\n</html>\n",
+        bg = EXPORT_BACKGROUND,
+        fg = EXPORT_FOREGROUND,
+        body = ansi_to_html(ansi)
+    )
+}
+
+// Render the layout as a text-based SVG (one <text> per line, <tspan> per color run)
+pub fn to_svg(ansi: &str) -> String {
+    let lines: Vec<&str> = ansi.lines().collect();
+    let columns = lines.iter().map(|line| visible_len(line)).max().unwrap_or(0);
+    let width = columns as f64 * SVG_CELL_WIDTH + SVG_PADDING * 2.0;
+    let height = lines.len() as f64 * SVG_LINE_HEIGHT + SVG_PADDING * 2.0;
+
+    let mut svg = String::new();
+    let _ = writeln!(
+        svg,
+        "<svg xmlns=\"http://www.w3.org/2000/svg\" width=\"{:.0}\" height=\"{:.0}\" \
+         font-family=\"monospace\" font-size=\"14\">",
+        width, height
+    );
+    let _ = writeln!(svg, "<rect width=\"100%\" height=\"100%\" fill=\"{}\"/>", EXPORT_BACKGROUND);
+
+    let mut style = SgrStyle::default();
+    for (index, line) in lines.iter().enumerate() {
+        let y = SVG_PADDING + (index as f64 + 0.8) * SVG_LINE_HEIGHT;
+        let _ = write!(
+            svg,
+            "<text x=\"{:.0}\" y=\"{:.1}\" xml:space=\"preserve\" fill=\"{}\">",
+            SVG_PADDING, y, EXPORT_FOREGROUND
+        );
+        for (run_style, text) in parse_line(line, &mut style) {
+            let mut attrs = String::new();
+            if let Some((r, g, b)) = run_style.fg {
+                let _ = write!(attrs, " fill=\"#{:02X}{:02X}{:02X}\"", r, g, b);
+            }
+            if run_style.bold {
+                attrs.push_str(" font-weight=\"bold\"");
+            }
+            let _ = write!(svg, "<tspan{}>{}</tspan>", attrs, escape_xml(&text));
+        }
+        svg.push_str("</text>\n");
+    }
+
+    svg.push_str("</svg>\n");
+    svg
+}
+
+// Convert the rendered layout and write it to disk
+pub fn export(ansi: &str, format: ExportFormat, path: &std::path::Path) -> Result<(), String> {
+    let content = match format {
+        ExportFormat::Html => to_html(ansi),
+        ExportFormat::Svg => to_svg(ansi),
+        ExportFormat::Ansi => ansi.to_string(),
+    };
+    std::fs::write(path, content).map_err(|e| format!("Could not write {}: {}", path.display(), e))
+}
+
+// Quote text as a JSON string
+fn json_quote(text: &str) -> String {
+    let mut quoted = String::with_capacity(text.len() + 2);
+    quoted.push('"');
+    for c in text.chars() {
+        match c {
+            '"' => quoted.push_str("\\\""),
+            '\\' => quoted.push_str("\\\\"),
+            '\n' => quoted.push_str("\\n"),
+            c if c.is_control() => {
+                let _ = write!(quoted, "\\u{:04x}", c as u32);
+            }
+            _ => quoted.push(c),
+        }
+    }
+    quoted.push('"');
+    quoted
+}
+
+// The collected values for --json, for scripts. The raw numbers come first as top level fields
+// (see collect::json_numbers for the names), then every line as plain text the way --snapshot
+// names it:
+// {"load_1m": 0.52, ..., "lines": [{"section": "Core", "name": "OS", "value": "Arch Linux"}]}
+pub fn to_json(sections: &[Section], numbers: &[(&str, f64)]) -> String {
+    let mut json = String::from("{\n");
+    // NaN and infinity aren't JSON
+    for (name, number) in numbers.iter().filter(|(_, number)| number.is_finite()) {
+        let _ = writeln!(json, "  {}: {},", json_quote(name), number);
+    }
+    json.push_str("  \"lines\": [");
+    for (index, (section, name, value)) in snapshot::entries(sections).iter().enumerate() {
+        let _ = write!(
+            json,
+            "{}\n    {{\"section\": {}, \"name\": {}, \"value\": {}}}",
+            if index == 0 { "" } else { "," },
+            json_quote(section),
+            json_quote(name),
+            json_quote(value)
+        );
+    }
+    json.push_str("\n  ]\n}\n");
+    json
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::helpers::{json_number, json_objects, json_string};
+
+    #[test]
+    fn colored_text_becomes_spans() {
+        let ansi = "\x1b[38;2;189;147;249mslowfetch\x1b[0m on \x1b[1;38;2;80;250;123march\x1b[0m\n\
+                    plain <text> & \"quotes\"\n";
+        assert_eq!(
+            ansi_to_html(ansi),
+            "<span style=\"color:#BD93F9;\">slowfetch</span> on \
+             <span style=\"color:#50FA7B;font-weight:bold;\">arch</span>\n\
+             plain &lt;text&gt; &amp; &quot;quotes&quot;\n"
+        );
+    }
+
+    #[test]
+    fn style_carries_over_lines_until_reset() {
+        let ansi = "\x1b[38;2;255;0;0mred\nstill red\x1b[39m default\n";
+        assert_eq!(
+            ansi_to_html(ansi),
+            "<span style=\"color:#FF0000;\">red</span>\n\
+             <span style=\"color:#FF0000;\">still red</span> default\n"
+        );
+    }
+
+    #[test]
+    fn other_escapes_are_dropped() {
+        // Cursor movement and 256-color codes don't change the output
+        let ansi = "\x1b[2K\x1b[38;5;196mtext\x1b[1Gmore\n";
+        assert_eq!(ansi_to_html(ansi), "textmore\n");
+    }
+
+    #[test]
+    fn html_page_wraps_the_body() {
+        let page = to_html("\x1b[38;2;0;0;0mx\x1b[0m");
+        assert!(page.starts_with("<!DOCTYPE html>"));
+        assert!(page.contains("<pre style=\"color:#F8F8F2;"));
+        assert!(page.contains("<span style=\"color:#000000;\">x</span>\n</pre>"));
+    }
+
+    #[test]
+    fn json_numbers_read_back() {
+        let sections = [Section::new(
+            "Core",
+            vec![
+                ("OS".into(), "Arch Linux".into()),
+                ("Load".into(), "0.52 0.48 0.45 (16 cores)".into()),
+            ],
+        )];
+        let numbers = [("load_1m", 0.52), ("load_5m", 0.48), ("load_15m", 0.45)];
+        let json = to_json(&sections, &numbers);
+        assert_eq!(json_number(&json, "load_1m"), Some(0.52));
+        assert_eq!(json_number(&json, "load_5m"), Some(0.48));
+        assert_eq!(json_number(&json, "load_15m"), Some(0.45));
+
+        let lines = json_objects(&json[json.find('[').unwrap()..]);
+        assert_eq!(lines.len(), 2);
+        assert_eq!(json_string(lines[0], "section"), Some("Core"));
+        assert_eq!(json_string(lines[0], "name"), Some("OS"));
+        assert_eq!(json_string(lines[0], "value"), Some("Arch Linux"));
+        assert_eq!(json_string(lines[1], "value"), Some("0.52 0.48 0.45 (16 cores)"));
+
+        // Nothing measured (show_load off), no numbers and no trailing comma
+        let json = to_json(&sections, &[]);
+        assert_eq!(json_number(&json, "load_1m"), None);
+        assert!(json.starts_with("{\n  \"lines\": [\n"));
+        assert_eq!(to_json(&[], &[("load_1m", f64::NAN)]), "{\n  \"lines\": [\n  ]\n}\n");
+    }
+
+    #[test]
+    fn json_strings_are_escaped() {
+        assert_eq!(json_quote("LG \"UltraGear\""), "\"LG \\\"UltraGear\\\"\"");
+        assert_eq!(json_quote("C:\\ a\tb"), "\"C:\\\\ a\\u0009b\"");
+    }
+}
@@ -0,0 +1,55 @@
+// The cargo feature matrix: slowfetch has to build with no default features, with each
+// feature on its own and with all of them. Every combination is a full cargo check, so the
+// builds only run on request: cargo test features -- --ignored
+// They go to their own target dir, the one cargo test is using stays locked meanwhile.
+
+use std::path::PathBuf;
+use std::process::Command;
+
+// Everything in [features] but default, keep in sync with Cargo.toml
+const FEATURES: &[&str] = &["cli-extras", "image", "art", "pci-ids"];
+
+fn manifest_dir() -> PathBuf {
+    PathBuf::from(env!("CARGO_MANIFEST_DIR"))
+}
+
+// Feature names declared in Cargo.toml's [features] table
+fn declared_features() -> Vec<String> {
+    let manifest = std::fs::read_to_string(manifest_dir().join("Cargo.toml")).unwrap();
+    manifest
+        .lines()
+        .skip_while(|line| line.trim() != "[features]")
+        .skip(1)
+        .take_while(|line| !line.starts_with('['))
+        .filter_map(|line| line.split_once('=').map(|(name, _)| name.trim().to_string()))
+        .filter(|name| !name.starts_with('#') && name != "default")
+        .collect()
+}
+
+#[test]
+fn matrix_covers_every_feature() {
+    assert_eq!(declared_features(), FEATURES);
+}
+
+#[test]
+#[ignore = "runs a cargo check per feature combination"]
+fn builds_with_every_feature_combination() {
+    let mut combinations = vec![vec![], FEATURES.to_vec()];
+    combinations.extend(FEATURES.iter().map(|&feature| vec![feature]));
+
+    let mut failures = Vec::new();
+    for features in combinations {
+        let output = Command::new(env!("CARGO"))
+            .args(["check", "--quiet", "--all-targets", "--no-default-features"])
+            .args(["--features", &features.join(",")])
+            .env("CARGO_TARGET_DIR", manifest_dir().join("target").join("feature-matrix"))
+            .current_dir(manifest_dir())
+            .output()
+            .unwrap();
+        if !output.status.success() {
+            let stderr = String::from_utf8_lossy(&output.stderr);
+            failures.push(format!("features [{}]:\n{}", features.join(", "), stderr));
+        }
+    }
+    assert!(failures.is_empty(), "{}", failures.join("\n\n"));
+}
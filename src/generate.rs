@@ -0,0 +1,83 @@
+// Shell completions and the man page, generated from the clap definition so they never
+// drift from the real flags. Printed to stdout for packaging scripts to redirect, e.g.
+// slowfetch --generate completions zsh > _slowfetch
+
+use clap::Command;
+use clap_complete::Shell;
+use std::io::{self, Write};
+
+// `what` is ["completions", "<shell>"] or ["man"]
+pub fn generate(command: Command, what: &[String]) -> Result<(), String> {
+    write_generated(command, what, &mut io::stdout())
+}
+
+// generate, writing to `out` instead of stdout
+fn write_generated(
+    mut command: Command,
+    what: &[String],
+    out: &mut dyn Write,
+) -> Result<(), String> {
+    let name = command.get_name().to_string();
+
+    match what {
+        [kind, shell] if kind == "completions" => {
+            match shell.as_str() {
+                "bash" => clap_complete::generate(Shell::Bash, &mut command, name, out),
+                "zsh" => clap_complete::generate(Shell::Zsh, &mut command, name, out),
+                "fish" => clap_complete::generate(Shell::Fish, &mut command, name, out),
+                "nu" => clap_complete::generate(
+                    clap_complete_nushell::Nushell,
+                    &mut command,
+                    name,
+                    out,
+                ),
+                _ => {
+                    return Err(format!(
+                        "Unknown shell '{}' (expected bash, zsh, fish or nu)",
+                        shell
+                    ));
+                }
+            }
+            Ok(())
+        }
+        [kind] if kind == "man" => clap_mangen::Man::new(command)
+            .render(out)
+            .and_then(|_| out.flush())
+            .map_err(|e| format!("Couldn't write the man page: {}", e)),
+        _ => Err("Expected --generate completions <bash|zsh|fish|nu> or --generate man".into()),
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::Args;
+    use clap::CommandFactory;
+
+    fn generated(what: &[&str]) -> Result<String, String> {
+        let what: Vec<String> = what.iter().map(|part| part.to_string()).collect();
+        let mut out = Vec::new();
+        write_generated(Args::command(), &what, &mut out)?;
+        Ok(String::from_utf8(out).unwrap())
+    }
+
+    #[test]
+    fn bash_completion_has_the_flags() {
+        let bash = generated(&["completions", "bash"]).unwrap();
+        assert!(bash.contains("--os"));
+        assert!(bash.contains("--image"));
+    }
+
+    #[test]
+    fn every_shell_and_the_man_page() {
+        // fish spells it "-l refresh"
+        for shell in ["zsh", "fish", "nu"] {
+            assert!(generated(&["completions", shell]).unwrap().contains("refresh"), "{}", shell);
+        }
+        // The doc comments end up as the help text
+        let man = generated(&["man"]).unwrap();
+        assert!(man.contains("Force refresh of cached values"));
+        assert!(generated(&["completions", "tcsh"]).unwrap_err().contains("Unknown shell"));
+        assert!(generated(&["manual"]).is_err());
+    }
+}
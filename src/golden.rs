@@ -0,0 +1,417 @@
+// Golden file tests for the layouts: a fixed set of sections drawn at a handful of terminal
+// sizes and compared, escapes stripped, against tests/golden/<case>_<cols>x<rows>.txt.
+// After a deliberate layout change, SLOWFETCH_BLESS=1 cargo test rewrites the files and
+// git diff shows what moved.
+
+use std::fs;
+use std::path::{Path, PathBuf};
+
+use crate::accessible;
+use crate::configloader::{BarFill, BarGlyphs, BarStyle, Margins};
+use crate::helpers::{self, create_bar, draw_bar};
+#[cfg(feature = "art")]
+use crate::modules::asciimodule::{self, ArtSet};
+#[cfg(not(feature = "art"))]
+use crate::renderer::ArtSet;
+#[cfg(feature = "art")]
+use crate::renderer::draw_unified_sized;
+use crate::renderer::{Section, draw_badge, draw_layout_sized, strip_escapes, visible_len};
+use crate::snapshot;
+use crate::testutil::TempDir;
+
+// Roomy, the usual window, a narrow split, a tall and thin pane and a tiny one
+const SIZES: &[(usize, usize)] = &[(200, 50), (120, 35), (90, 30), (60, 40), (40, 15)];
+
+// Lines of a mismatch shown before the rest is left to git diff
+const MAX_DIFF_LINES: usize = 40;
+
+fn line(key: &str, value: &str) -> (String, String) {
+    (key.to_string(), value.to_string())
+}
+
+// Made up but realistic: a long CPU name, a display tree, every bar style and a colored value
+fn sections() -> Vec<Section> {
+    let style = |glyphs, fill| BarStyle { glyphs, fill, gradient: ((0, 255, 0), (255, 0, 0)) };
+    let ascii = style(BarGlyphs::Ascii, BarFill::Solid);
+    let blocks = style(BarGlyphs::Blocks, BarFill::Gradient);
+    vec![
+        Section::new(
+            "Core",
+            vec![
+                line("OS", "Arch Linux"),
+                line("Kernel", "6.12.10-arch1-1"),
+                line("Uptime", "3h 12m"),
+                line("Init", "systemd"),
+            ],
+        ),
+        Section::new(
+            "Hardware",
+            vec![
+                line("CPU", "AMD Ryzen Threadripper PRO 7995WX 96-Cores @ 5.10GHz"),
+                line("GPU", "NVIDIA GeForce RTX 4090"),
+                line("Memory", &format!("{} 15.2GB/32GB", create_bar("memory", 47.5))),
+                line("Storage", &format!("{} 412GB/1TB", draw_bar(ascii, 41.2))),
+                line("Battery", &format!("{} 82% 󰂐", create_bar("battery", 82.0))),
+                line("UPS", &format!("{} 100% Online", draw_bar(blocks, 100.0))),
+                line("Displays", ""),
+                line("├─", "DP-1 2560x1440 @ 165Hz"),
+                line("╰─", "eDP-1 1920x1200 @ 60Hz"),
+            ],
+        ),
+        Section::new(
+            "Userspace",
+            vec![
+                line("Packages", "1204 (pacman), 12 (flatpak)"),
+                line("Terminal", "\x1b[38;2;120;200;120mkitty\x1b[0m"),
+                line("Shell", "zsh 5.9"),
+                line("WM", "Hyprland"),
+            ],
+        ),
+    ]
+}
+
+fn golden_dir() -> PathBuf {
+    PathBuf::from(env!("CARGO_MANIFEST_DIR")).join("tests").join("golden")
+}
+
+// Line by line differences, numbered, for the panic message
+fn diff(expected: &str, actual: &str) -> String {
+    let expected: Vec<&str> = expected.lines().collect();
+    let actual: Vec<&str> = actual.lines().collect();
+    let mut output = Vec::new();
+    for i in 0..expected.len().max(actual.len()) {
+        let (old, new) = (expected.get(i), actual.get(i));
+        if old == new {
+            continue;
+        }
+        if let Some(old) = old {
+            output.push(format!("{:>4} - {}", i + 1, old));
+        }
+        if let Some(new) = new {
+            output.push(format!("{:>4} + {}", i + 1, new));
+        }
+    }
+    if output.len() > MAX_DIFF_LINES {
+        let more = output.len() - MAX_DIFF_LINES;
+        output.truncate(MAX_DIFF_LINES);
+        output.push(format!("     ... {} more", more));
+    }
+    output.join("\n")
+}
+
+// Compare output with its golden file, or write the file when blessing
+fn compare(path: &Path, output: &str, bless: bool) -> Option<String> {
+    if bless {
+        fs::create_dir_all(golden_dir()).unwrap();
+        fs::write(path, output).unwrap();
+        return None;
+    }
+    match fs::read_to_string(path) {
+        Ok(expected) if expected == output => None,
+        Ok(expected) => Some(format!("{}:\n{}", path.display(), diff(&expected, output))),
+        Err(err) => Some(format!("{}: {}", path.display(), err)),
+    }
+}
+
+// Draw one case at every size and compare, or write the files when blessing.
+// Collects every mismatch before failing so one run shows them all.
+fn check(
+    case: &str,
+    art: Option<&ArtSet>,
+    header: Option<&str>,
+    footer: Option<&str>,
+    margins: Margins,
+) {
+    check_drawn(case, |sections, size| {
+        draw_layout_sized(art, sections, header, footer, margins, size).0
+    });
+}
+
+// check() for any way of drawing the sections at a terminal size
+fn check_drawn(case: &str, draw: impl Fn(&[Section], (usize, usize)) -> String) {
+    // Nerd font bars whatever the machine running the tests has installed
+    helpers::init_font_cache(None, Some(true));
+    let bless = std::env::var_os("SLOWFETCH_BLESS").is_some();
+    let sections = sections();
+    let mut failures = Vec::new();
+    for &size in SIZES {
+        let output = strip_escapes(&draw(&sections, size));
+        let path = golden_dir().join(format!("{}_{}x{}.txt", case, size.0, size.1));
+        failures.extend(compare(&path, &output, bless));
+    }
+    assert!(
+        failures.is_empty(),
+        "layout changed, rerun with SLOWFETCH_BLESS=1 if that's intended\n\n{}",
+        failures.join("\n\n")
+    );
+}
+
+#[cfg(feature = "art")]
+#[test]
+fn default_art() {
+    check("default_art", Some(&asciimodule::default_art()), None, None, Margins::default());
+}
+
+#[test]
+fn sections_only() {
+    check("sections_only", None, None, None, Margins::default());
+}
+
+#[cfg(feature = "art")]
+#[test]
+fn greeting_footer_margins() {
+    let art = asciimodule::default_art();
+    let (header, footer) = (Some("Good evening, tui"), Some("slowfetch 0.2.5"));
+    check("greeting_footer_margins", Some(&art), header, footer, Margins::uniform(1));
+}
+
+// Centered on the whole layout, art included, not just the boxes under it
+#[cfg(feature = "art")]
+#[test]
+fn footer_under_art() {
+    let art = asciimodule::default_art();
+    check("footer_under_art", Some(&art), None, Some("~ dotfiles ~"), Margins::default());
+}
+
+// unified_box: art and sections in one border with a divider between them, back to separate
+// boxes at the sizes where the art goes above the sections
+#[cfg(feature = "art")]
+#[test]
+fn unified_box() {
+    let art = asciimodule::default_art();
+    check_drawn("unified_box", |sections, size| {
+        draw_unified_sized(Some(&art), sections, Margins::default(), size)
+    });
+}
+
+// Wider than the boxes at every size and wider than the smaller terminals, where it's cut short
+#[test]
+fn footer_wider_than_the_layout() {
+    let footer = "https://github.com/tui/dotfiles/tree/main/.config/slowfetch \
+                  (the one with the 40-line CPU module override and a pinned Hyprland)";
+    check("footer_wider_than_the_layout", None, None, Some(footer), Margins::default());
+}
+
+// The --badge box with the usual sections and with values far too long for it, always
+// BADGE_WIDTH columns whatever the terminal
+#[test]
+fn badge() {
+    let long = vec![Section::new(
+        "Core",
+        vec![
+            line("OS", "openSUSE Tumbleweed-Slowroll 20250212 (WSL2 on Windows 11 Pro 24H2)"),
+            line("Kernel", "6.13.2-1-default"),
+            line("Shell", "\x1b[32mnu 0.102.0 with every plugin, overlay and hook loaded\x1b[0m"),
+            line("WM", "Hyprland ウィンドウマネージャー 東京 ウィンドウ 🦀 東京東"),
+        ],
+    )];
+    let mut output = String::new();
+    for (name, sections) in [("usual", sections()), ("long values", long)] {
+        let badge = draw_badge(&sections, Margins::default());
+        for row in badge.lines() {
+            assert_eq!(visible_len(row), 60, "{:?}", strip_escapes(row));
+        }
+        output.push_str(&format!("{}:\n{}", name, strip_escapes(&badge)));
+    }
+
+    let path = golden_dir().join("badge.txt");
+    let bless = std::env::var_os("SLOWFETCH_BLESS").is_some();
+    if let Some(failure) = compare(&path, &output, bless) {
+        panic!("badge changed, rerun with SLOWFETCH_BLESS=1 if that's intended\n\n{}", failure);
+    }
+}
+
+// A small art file drawn with the default art colors and with [art_remap] "1" = 4, "2" = 4,
+// "3" = 7 and "9" = 1, escapes kept (as \e) since the colors are the point
+#[cfg(feature = "art")]
+#[test]
+fn art_remap() {
+    let source = "{0}.{1}/\\{2}__{3}/\\{9}*\n{0}'{1}\\/{2}  {3}\\/{9}*";
+    let remap = [(1, 4), (2, 4), (3, 7), (9, 1)].into_iter().collect();
+    let mut output = String::new();
+    for (name, remap) in [("default", Default::default()), ("remapped", remap)] {
+        output.push_str(&format!("{}:\n", name));
+        for line in asciimodule::remapped_lines(source, &remap) {
+            output.push_str(&line.replace('\x1b', "\\e"));
+            output.push('\n');
+        }
+    }
+
+    let path = golden_dir().join("art_remap.txt");
+    let bless = std::env::var_os("SLOWFETCH_BLESS").is_some();
+    if let Some(failure) = compare(&path, &output, bless) {
+        panic!("art colors changed, rerun with SLOWFETCH_BLESS=1 if intended\n\n{}", failure);
+    }
+}
+
+// The logo's gradient slots drawn with logo_accent for two distros. Arch and Ubuntu have to
+// come out different and unlike the plain art colors, distros without accents get nothing.
+#[cfg(feature = "art")]
+#[test]
+fn logo_accent() {
+    let source = "{0}.{5}/\\{6}__{7}/\\{8}__{9}/\\\n{0}'{5}\\/{6}  {7}\\/{8}  {9}\\/";
+    let mut output = String::new();
+    let mut drawn = Vec::new();
+    for os in ["Arch Linux", "Ubuntu 24.04 LTS"] {
+        let lines = asciimodule::accent_lines(source, os).unwrap();
+        output.push_str(&format!("{}:\n", os));
+        for line in &lines {
+            output.push_str(&line.replace('\x1b', "\\e"));
+            output.push('\n');
+        }
+        drawn.push(lines);
+    }
+    assert_ne!(drawn[0], drawn[1]);
+    assert_ne!(drawn[0], asciimodule::remapped_lines(source, &Default::default()));
+    assert!(asciimodule::accent_lines(source, "Gentoo").is_none());
+
+    let path = golden_dir().join("logo_accent.txt");
+    let bless = std::env::var_os("SLOWFETCH_BLESS").is_some();
+    if let Some(failure) = compare(&path, &output, bless) {
+        panic!("accent colors changed, rerun with SLOWFETCH_BLESS=1 if intended\n\n{}", failure);
+    }
+}
+
+// Every layout with its escapes kept (as \e), so the colors and resets around each padded
+// row and border run are pinned too, not just the text the other cases compare
+#[cfg(feature = "art")]
+#[test]
+fn layouts_escaped() {
+    helpers::init_font_cache(None, Some(true));
+    let art = asciimodule::default_art();
+    let sections = sections();
+    let (header, footer) = (Some("Good evening, tui"), Some("slowfetch 0.2.5"));
+    let mut output = String::new();
+    for size in [(200, 50), (60, 40), (40, 15)] {
+        for margins in [Margins::default(), Margins::uniform(1)] {
+            let drawn = [
+                draw_layout_sized(Some(&art), &sections, header, footer, margins, size).0,
+                draw_layout_sized(None, &sections, None, None, margins, size).0,
+                draw_unified_sized(Some(&art), &sections, margins, size),
+                draw_badge(&sections, margins),
+            ];
+            for (name, layout) in ["art", "sections", "unified", "badge"].iter().zip(drawn) {
+                output.push_str(&format!("{} {}x{} {:?}:\n", name, size.0, size.1, margins));
+                output.push_str(&layout.replace('\x1b', "\\e"));
+                output.push('\n');
+            }
+        }
+    }
+
+    let path = golden_dir().join("layouts_escaped.txt");
+    let bless = std::env::var_os("SLOWFETCH_BLESS").is_some();
+    if let Some(failure) = compare(&path, &output, bless) {
+        panic!("layout bytes changed, rerun with SLOWFETCH_BLESS=1 if intended\n\n{}", failure);
+    }
+}
+
+// Rows are pushed into one buffer, so a whole layout is about a hundred allocations (mostly
+// the colored fragments). Joining Vec<String> boxes with a repeat and a format! per row took
+// twice that.
+#[cfg(feature = "art")]
+#[test]
+fn layout_allocations() {
+    helpers::init_font_cache(None, Some(true));
+    let art = asciimodule::default_art();
+    let sections = sections();
+    let margins = Margins::uniform(1);
+    let draw = || draw_layout_sized(Some(&art), &sections, None, None, margins, (200, 50));
+    // The art is decoded on first use
+    draw();
+    let (_, count) = crate::testutil::allocations(draw);
+    assert!(count < 150, "{} allocations", count);
+}
+
+// cargo test --release -- --ignored --nocapture layout_timing
+#[cfg(feature = "art")]
+#[test]
+#[ignore]
+fn layout_timing() {
+    helpers::init_font_cache(None, Some(true));
+    let art = asciimodule::default_art();
+    let sections = sections();
+    let rounds = 10_000;
+    for size in SIZES {
+        let start = std::time::Instant::now();
+        for _ in 0..rounds {
+            draw_layout_sized(Some(&art), &sections, None, None, Margins::uniform(1), *size);
+        }
+        let per_render = start.elapsed().as_secs_f64() * 1e6 / rounds as f64;
+        println!("{}x{}: {:.1}µs per layout", size.0, size.1, per_render);
+    }
+}
+
+// --accessible over the usual sections and a line for every other kind of module: battery in
+// each state, throughput, deltas, repeated keys and trees. Read out as words, no box drawing
+// or icons left.
+#[test]
+fn accessible() {
+    helpers::init_font_cache(None, Some(true));
+    let bar = |module: &str, percent: f64, text: &str| {
+        format!("{} {}", create_bar(module, percent), text)
+    };
+    let battery = |percent: f64, text: &str| bar("battery", percent, text);
+    let mut sections = sections();
+    sections.push(Section::new(
+        "Everything else",
+        vec![
+            line("Battery", &battery(82.0, "82% 󰂐 · 24.6W (~1h 5m)")),
+            line("Battery", &battery(64.0, "64% 󰂍 · 12.4W (~3h 40m)")),
+            line("Battery", &battery(8.0, "8% 󰂃")),
+            line("Battery", &battery(100.0, "100% Full")),
+            line("Battery", &battery(80.0, "80% Not charging")),
+            line("Memory", &bar("memory", 24.0, "7.7GB/32GB (+1.2GB since last run)")),
+            line("Storage", &bar("storage", 48.6, "486GB/1TB (-14GB this week)")),
+            line("Activity", "󰋊 12MB/s · 󰈀 1.2MB/s ↓ 0.1MB/s ↑"),
+            line("Network", "󰖩 wlan0 · MyWifi (-52dBm) · 192.168.1.42"),
+            line("Uptime", "1d 3h 12m"),
+            line("Load", "0.52 0.48 0.45 (16 cores)"),
+            line("Today", "Feb 12 · 18 days until Vacation"),
+            line("Disk", "412GB/1TB"),
+            line("├─", "/home 300GB/500GB"),
+            line("╰─", "/ 1.2TB/2TB"),
+            line("Disk", "12GB/64GB"),
+            line("Kernel", "6.12.10-arch1-1 → 6.12.11-arch1-1 ⚠"),
+        ],
+    ));
+    let output = accessible::render(&sections, Some("Good evening, tui"), Some("slowfetch 0.2.5"));
+    for c in output.chars() {
+        assert!(!matches!(c, '─'..='╿' | '\u{E000}'..='\u{F8FF}' | '\u{F0000}'..), "{:?}", c);
+    }
+
+    let path = golden_dir().join("accessible.txt");
+    let bless = std::env::var_os("SLOWFETCH_BLESS").is_some();
+    if let Some(failure) = compare(&path, &output, bless) {
+        panic!("phrasing changed, rerun with SLOWFETCH_BLESS=1 if that's intended\n\n{}", failure);
+    }
+}
+
+// The sections above as a snapshot, what tests/simulate.rs feeds --simulate-data to draw the
+// same layouts through the binary. Read back, they're the same sections less the escapes.
+#[test]
+fn simulate_snapshot() {
+    helpers::init_font_cache(None, Some(true));
+    let dir = TempDir::new();
+    let written = dir.join("sections.snap");
+    snapshot::write(&sections(), &written).unwrap();
+    let output = fs::read_to_string(&written).unwrap();
+
+    let path = golden_dir().join("sections.snap");
+    let bless = std::env::var_os("SLOWFETCH_BLESS").is_some();
+    if let Some(failure) = compare(&path, &output, bless) {
+        panic!("snapshot changed, rerun with SLOWFETCH_BLESS=1 if that's intended\n\n{}", failure);
+    }
+
+    let plain = |sections: &[Section]| -> Vec<(String, String, String)> {
+        let mut lines = Vec::new();
+        for section in sections {
+            for (key, value) in &section.lines {
+                let value = strip_escapes(value).trim().to_string();
+                lines.push((section.title.clone(), strip_escapes(key), value));
+            }
+        }
+        lines
+    };
+    let read = snapshot::sections(&snapshot::read(&path).unwrap());
+    assert_eq!(plain(&read), plain(&sections()));
+}
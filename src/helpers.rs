@@ -1,34 +1,193 @@
 // Helper functions
 
+#[cfg(feature = "pci-ids")]
 use std::collections::HashMap;
 use std::fs::{self, File};
-use std::io::{BufRead, BufReader};
-use std::sync::OnceLock;
+use std::io::{BufRead, BufReader, Read};
+use std::path::Path;
+use std::process::{Command, Stdio};
+use std::sync::mpsc;
+use std::sync::{Mutex, OnceLock, PoisonError};
+use std::thread;
+use std::time::{Duration, Instant};
 
-use memchr::{memchr_iter, memmem};
+#[cfg(feature = "pci-ids")]
+use memchr::memchr_iter;
+use memchr::memmem;
 
-use crate::modules::fontmodule::{find_font, is_nerd_font};
+use crate::colorcontrol::{ThemePalette, fg_sgr, palette};
+use crate::configloader::{BarFill, BarGlyphs, BarStyle, BarStyles, expand_home, parse_hex_color};
+use crate::modules::fontmodule::{detect_nerd_font, find_font};
 
-// Cache for font detection - only computed once
+// Cache for font detection - only computed once. terminal_font and nerd_font from the config
+// go straight in (init_font_cache) so nothing detects what was declared.
 static CACHED_FONT: OnceLock<String> = OnceLock::new();
 static CACHED_IS_NERD: OnceLock<bool> = OnceLock::new();
 
-fn get_cached_is_nerd_font() -> bool {
-    *CACHED_IS_NERD.get_or_init(|| {
-        let font = CACHED_FONT.get_or_init(find_font);
-        is_nerd_font(font)
-    })
+// Fill the font caches from the config overrides. Call in main before any thread is spawned,
+// a bar drawn first would otherwise cache the detected answer.
+pub fn init_font_cache(font: Option<String>, nerd_font: Option<bool>) {
+    if let Some(font) = font {
+        let _ = CACHED_FONT.set(font);
+    }
+    if let Some(nerd_font) = nerd_font {
+        let _ = CACHED_IS_NERD.set(nerd_font);
+    }
+}
+
+// The terminal font, declared or detected once
+pub fn cached_font() -> &'static str {
+    cached_font_or(find_font)
+}
+
+// Same, for a caller that has the terminal config read already
+pub fn cached_font_or(detect: impl FnOnce() -> String) -> &'static str {
+    CACHED_FONT.get_or_init(detect)
+}
+
+pub fn get_cached_is_nerd_font() -> bool {
+    *CACHED_IS_NERD.get_or_init(|| detect_nerd_font(cached_font()))
+}
+
+// hyperlinks from the config, only honored on terminals known to handle OSC 8
+static HYPERLINKS: OnceLock<bool> = OnceLock::new();
+
+// Turn hyperlinks on/off, call before collecting modules
+pub fn set_hyperlinks(enabled: bool) {
+    let _ = HYPERLINKS.set(enabled && supports_hyperlinks());
+}
+
+// Terminals that render OSC 8 links (others may print the URL as garbage)
+fn supports_hyperlinks() -> bool {
+    use std::env::var;
+    use std::io::IsTerminal;
+
+    if !std::io::stdout().is_terminal() {
+        return false;
+    }
+    let term = var("TERM").unwrap_or_default();
+    let term_program = var("TERM_PROGRAM").unwrap_or_default();
+    term == "xterm-kitty"
+        || term.starts_with("foot")
+        || term_program == "WezTerm"
+        || var("KITTY_WINDOW_ID").is_ok()
+        || var("WEZTERM_EXECUTABLE").is_ok()
+        // gnome-terminal and other VTE terminals, links landed in VTE 0.50
+        || var("VTE_VERSION").is_ok_and(|version| version.parse::<u32>().is_ok_and(|v| v >= 5000))
+}
+
+// Make text clickable, or leave it alone when hyperlinks are off
+pub fn hyperlink(text: &str, url: &str) -> String {
+    if *HYPERLINKS.get().unwrap_or(&false) {
+        format!("\x1b]8;;{}\x1b\\{}\x1b]8;;\x1b\\", url, text)
+    } else {
+        text.to_string()
+    }
+}
+
+// Just enough JSON for the few tools that only speak it (rpm-ostree, hyprctl, swaymsg).
+// Keys are found by text search, so pass the narrowest object you have.
+
+// Text right after `"key" :`
+fn json_value<'a>(json: &'a str, key: &str) -> Option<&'a str> {
+    let key = format!("\"{}\"", key);
+    let rest = &json[json.find(&key)? + key.len()..];
+    Some(rest.trim_start().strip_prefix(':')?.trim_start())
+}
+
+// String value for key, no escape handling (names, versions and commits don't need it)
+pub fn json_string<'a>(json: &'a str, key: &str) -> Option<&'a str> {
+    let value = json_value(json, key)?.strip_prefix('"')?;
+    Some(&value[..value.find('"')?])
+}
+
+// Boolean value for key
+pub fn json_bool(json: &str, key: &str) -> Option<bool> {
+    let value = json_value(json, key)?;
+    if value.starts_with("true") {
+        Some(true)
+    } else if value.starts_with("false") {
+        Some(false)
+    } else {
+        None
+    }
+}
+
+// Number value for key, for reading --json back in tests
+#[cfg(test)]
+pub fn json_number(json: &str, key: &str) -> Option<f64> {
+    let value = json_value(json, key)?;
+    let end = value
+        .find(|c: char| !(c.is_ascii_digit() || matches!(c, '-' | '+' | '.' | 'e' | 'E')))
+        .unwrap_or(value.len());
+    value[..end].parse().ok()
+}
+
+// Number of entries in a string array
+pub fn json_array_len(json: &str, key: &str) -> Option<usize> {
+    let value = json_value(json, key)?.strip_prefix('[')?;
+    let array = &value[..value.find(']')?];
+    Some(array.matches('"').count() / 2)
+}
+
+// Top level objects of a JSON array, e.g. one per monitor
+pub fn json_objects(json: &str) -> Vec<&str> {
+    let mut objects = Vec::new();
+    let mut depth = 0;
+    let mut start = 0;
+    let mut in_string = false;
+    let mut escaped = false;
+
+    for (index, c) in json.char_indices() {
+        if in_string {
+            match c {
+                _ if escaped => escaped = false,
+                '\\' => escaped = true,
+                '"' => in_string = false,
+                _ => {}
+            }
+            continue;
+        }
+        match c {
+            '"' => in_string = true,
+            '{' => {
+                if depth == 0 {
+                    start = index;
+                }
+                depth += 1;
+            }
+            '}' if depth > 0 => {
+                depth -= 1;
+                if depth == 0 {
+                    objects.push(&json[start..=index]);
+                }
+            }
+            _ => {}
+        }
+    }
+    objects
 }
 
 // Parsed PCI database: vendor_id -> (vendor_name, device_id -> device_name)
+#[cfg(feature = "pci-ids")]
 pub type PciDatabase = HashMap<String, (String, HashMap<String, String>)>;
+#[cfg(feature = "pci-ids")]
 static PCI_DB: OnceLock<Option<PciDatabase>> = OnceLock::new();
 
+// Where distros put pci.ids (hwdata on Fedora/Arch, misc on Debian), first one wins
+#[cfg(feature = "pci-ids")]
+const PCI_IDS_PATHS: &[&str] = &["/usr/share/hwdata/pci.ids", "/usr/share/misc/pci.ids"];
+
+// The pci.ids file get_pci_database reads, if there is one
+#[cfg(feature = "pci-ids")]
+pub fn pci_ids_path() -> Option<&'static str> {
+    PCI_IDS_PATHS.iter().copied().find(|path| Path::new(path).is_file())
+}
+
+#[cfg(feature = "pci-ids")]
 pub fn get_pci_database() -> &'static Option<PciDatabase> {
     PCI_DB.get_or_init(|| {
-        let content = fs::read("/usr/share/hwdata/pci.ids")
-            .or_else(|_| fs::read("/usr/share/misc/pci.ids"))
-            .ok()?;
+        let content = fs::read(pci_ids_path()?).ok()?;
 
         let mut db: PciDatabase = HashMap::new();
         let mut current_vendor_id: Option<String> = None;
@@ -82,6 +241,322 @@ pub fn get_pci_database() -> &'static Option<PciDatabase> {
     })
 }
 
+// A running process from the /proc scan
+#[derive(Clone)]
+pub struct ProcessEntry {
+    pub pid: u32,
+    // Raw /proc/<pid>/cmdline, arguments separated by NULs
+    pub cmdline: Vec<u8>,
+}
+
+// Bytes of cmdline kept per process. Almost all fit, and the matches only care about the
+// program name and the first few arguments, so long ones (browsers, electron) are cut here.
+const CMDLINE_READ_LIMIT: usize = 256;
+
+// A walk over /proc that only reads as many processes as it's asked for. wm(), ui() and
+// friends each want the first process that matches, so the walk stops once they all have
+// theirs instead of reading every cmdline on the system. Each find starts with the processes
+// read so far, so the answers are the same as over a full scan.
+// Reading /proc directly instead of spawning ps | grep saves a good chunk of time.
+// Each cmdline is opened relative to one /proc dirfd and read with a single pread into a
+// reused buffer, so a process costs openat + pread + close instead of fs::read's
+// open + statx + read until EOF + close.
+pub struct ProcessScan {
+    processes: Vec<ProcessEntry>,
+    // /proc and its entries left to read, None once the walk reached the end
+    walk: Option<(File, fs::ReadDir)>,
+    buffer: [u8; CMDLINE_READ_LIMIT],
+    // Time spent walking, for --timings
+    elapsed: Duration,
+}
+
+impl ProcessScan {
+    // A walk over `proc`, standing in for /proc, nothing read yet
+    pub fn new(proc: &Path) -> Self {
+        let walk = match (File::open(proc), fs::read_dir(proc)) {
+            (Ok(proc_dir), Ok(entries)) => Some((proc_dir, entries)),
+            _ => None,
+        };
+        let buffer = [0; CMDLINE_READ_LIMIT];
+        Self { processes: Vec::new(), walk, buffer, elapsed: Duration::ZERO }
+    }
+
+    // The first process `found` picks something from, in /proc order. The walk goes on only
+    // past what's been read already, and only until there's a match.
+    pub fn find_map<T>(&mut self, mut found: impl FnMut(&ProcessEntry) -> Option<T>) -> Option<T> {
+        if let Some(value) = self.processes.iter().find_map(&mut found) {
+            return Some(value);
+        }
+        let started = Instant::now();
+        let mut value = None;
+        while value.is_none() && self.read_next() {
+            value = self.processes.last().and_then(&mut found);
+        }
+        self.elapsed += started.elapsed();
+        value
+    }
+
+    // Read the next process into `processes`, false at the end of /proc
+    fn read_next(&mut self) -> bool {
+        let Some((proc_dir, entries)) = &mut self.walk else {
+            return false;
+        };
+        for entry in entries.flatten() {
+            // Fast check: only PID directories (all digits)
+            let name = entry.file_name();
+            let Some(pid) = name.to_str().and_then(|n| n.parse::<u32>().ok()) else {
+                continue;
+            };
+
+            // Read as bytes to avoid UTF-8 conversion overhead
+            if let Some(len) = read_cmdline(proc_dir, pid, &mut self.buffer) {
+                self.processes.push(ProcessEntry { pid, cmdline: self.buffer[..len].to_vec() });
+                return true;
+            }
+        }
+        self.walk = None;
+        false
+    }
+
+    // "0.3ms, stopped early after 112 processes" or "1.9ms, all 640 processes", for --timings
+    pub fn summary(&self) -> String {
+        let took = self.elapsed.as_secs_f64() * 1000.0;
+        let read = self.processes.len();
+        let plural = if read == 1 { "" } else { "es" };
+        if self.walk.is_some() {
+            format!("{:.1}ms, stopped early after {} process{}", took, read, plural)
+        } else {
+            format!("{:.1}ms, all {} process{}", took, read, plural)
+        }
+    }
+}
+
+// The one walk over /proc this run, started by the first find
+static PROCESS_SCAN: Mutex<Option<ProcessScan>> = Mutex::new(None);
+
+// The first running process `found` picks something from, over the walk shared by wm(), ui()
+// and friends
+pub fn find_process<T>(found: impl FnMut(&ProcessEntry) -> Option<T>) -> Option<T> {
+    let mut scan = PROCESS_SCAN.lock().unwrap_or_else(PoisonError::into_inner);
+    scan.get_or_insert_with(|| ProcessScan::new(Path::new("/proc"))).find_map(found)
+}
+
+// How the /proc walk went, None if nothing needed it
+pub fn process_scan_summary() -> Option<String> {
+    let scan = PROCESS_SCAN.lock().unwrap_or_else(PoisonError::into_inner);
+    scan.as_ref().map(ProcessScan::summary)
+}
+
+// Read up to buffer.len() bytes of <pid>/cmdline under the open /proc, None if the
+// process is gone (or hidden from us)
+fn read_cmdline(proc_dir: &File, pid: u32, buffer: &mut [u8]) -> Option<usize> {
+    use std::ffi::CString;
+    use std::os::fd::{AsRawFd, FromRawFd, OwnedFd};
+    use std::os::unix::fs::FileExt;
+
+    let path = CString::new(format!("{}/cmdline", pid)).ok()?;
+    let fd = unsafe {
+        libc::openat(proc_dir.as_raw_fd(), path.as_ptr(), libc::O_RDONLY | libc::O_CLOEXEC)
+    };
+    if fd < 0 {
+        return None;
+    }
+    // Closed when it goes out of scope
+    let file = File::from(unsafe { OwnedFd::from_raw_fd(fd) });
+    file.read_at(buffer, 0).ok()
+}
+
+// How long a process has been running, from its /proc/<pid>/stat contents.
+// Field 22 (starttime) is in clock ticks since boot. The comm field (2) can contain
+// spaces and parens, so fields are counted from the last ')'.
+pub fn process_age_seconds(stat: &str, uptime_seconds: f64, ticks_per_second: u64) -> Option<u64> {
+    if ticks_per_second == 0 {
+        return None;
+    }
+    let after_comm = &stat[stat.rfind(')')? + 1..];
+    // after_comm starts at field 3 (state), so starttime is the 20th token
+    let start_ticks: u64 = after_comm.split_whitespace().nth(19)?.parse().ok()?;
+    let started_at = start_ticks as f64 / ticks_per_second as f64;
+    Some((uptime_seconds - started_at).max(0.0) as u64)
+}
+
+// The DRM card a process has open ("card1"), from the links in <pid>/fd under `proc` (/proc).
+// Other users' processes can't be looked into, that's just None like no card at all.
+pub fn open_drm_card(proc: &Path, pid: u32) -> Option<String> {
+    let entries = fs::read_dir(proc.join(pid.to_string()).join("fd")).ok()?;
+    entries.flatten().find_map(|entry| {
+        let target = fs::read_link(entry.path()).ok()?;
+        let card = target.to_str()?.strip_prefix("/dev/dri/")?;
+        card.starts_with("card").then(|| card.to_string())
+    })
+}
+
+// Running inside a container: docker leaves /.dockerenv, podman /run/.containerenv,
+// systemd-nspawn/LXC and toolbox set $container (also in PID 1's environment)
+pub fn in_container() -> bool {
+    if Path::new("/.dockerenv").exists() || Path::new("/run/.containerenv").exists() {
+        return true;
+    }
+    if std::env::var_os("container").is_some() {
+        return true;
+    }
+    let Ok(environ) = fs::read("/proc/1/environ") else {
+        return false;
+    };
+    environ.split(|&b| b == 0).any(|var| var.starts_with(b"container="))
+}
+
+// WSL detection, done once
+static WSL: OnceLock<Option<u8>> = OnceLock::new();
+
+// Running under WSL, and which: WSL1 kernels say "4.4.0-19041-Microsoft", WSL2 ones
+// "5.15.153.1-microsoft-standard-WSL2". A custom WSL2 kernel may not say so, WSL still sets
+// WSL_DISTRO_NAME then. None on everything else.
+pub fn wsl_version() -> Option<u8> {
+    *WSL.get_or_init(|| {
+        let release = read_first_line("/proc/sys/kernel/osrelease").unwrap_or_default();
+        wsl_from(&release, std::env::var_os("WSL_DISTRO_NAME").is_some())
+    })
+}
+
+// wsl_version() for a kernel release, and whether WSL_DISTRO_NAME is set
+fn wsl_from(release: &str, distro_name: bool) -> Option<u8> {
+    let release = release.to_lowercase();
+    if release.contains("microsoft") {
+        let wsl2 = release.contains("wsl2") || release.contains("microsoft-standard");
+        return Some(if wsl2 { 2 } else { 1 });
+    }
+    distro_name.then_some(2)
+}
+
+// How finely format_duration writes a duration out
+#[derive(Clone, Copy, PartialEq, Debug)]
+pub enum Precision {
+    Minutes,
+    // For --watch, where a minutes-only uptime looks frozen
+    Seconds,
+}
+
+// Format a duration as "3h 12m" (or "12m" under an hour), with seconds "3h 12m 45s"
+pub fn format_duration(seconds: u64, precision: Precision) -> String {
+    let h = seconds / 3600;
+    let m = (seconds % 3600) / 60;
+    let s = seconds % 60;
+    match precision {
+        Precision::Minutes if h > 0 => format!("{}h {}m", h, m),
+        Precision::Minutes => format!("{}m", m),
+        Precision::Seconds if h > 0 => format!("{}h {}m {}s", h, m, s),
+        Precision::Seconds if m > 0 => format!("{}m {}s", m, s),
+        Precision::Seconds => format!("{}s", s),
+    }
+}
+
+// A file's path and modification time, "/usr/bin/zsh 1718000000". What a binary said is
+// cached with its stamp, so an update (a new mtime) has it asked again.
+pub fn file_stamp(path: &Path) -> Option<String> {
+    let modified = fs::metadata(path).and_then(|metadata| metadata.modified()).ok()?;
+    let seconds = modified.duration_since(std::time::UNIX_EPOCH).ok()?.as_secs();
+    Some(format!("{} {}", path.display(), seconds))
+}
+
+// Run a command and grab at most `max_bytes` of its stdout, giving up after `timeout`.
+// stdin/stderr are null so the child can't block on input or spam the terminal.
+// The child is killed once we have what we need, so a runaway script can't hold us up.
+pub fn command_output_limited(
+    command: &mut Command,
+    max_bytes: u64,
+    timeout: Duration,
+) -> Option<Vec<u8>> {
+    let mut child = command
+        .stdin(Stdio::null())
+        .stdout(Stdio::piped())
+        .stderr(Stdio::null())
+        .spawn()
+        .ok()?;
+    let stdout = child.stdout.take()?;
+
+    let (tx, rx) = mpsc::channel();
+    thread::spawn(move || {
+        let mut buf = Vec::new();
+        let result = stdout.take(max_bytes).read_to_end(&mut buf).map(|_| buf);
+        let _ = tx.send(result);
+    });
+
+    let output = rx.recv_timeout(timeout).ok().and_then(|result| result.ok());
+    let _ = child.kill();
+    let _ = child.wait();
+    output
+}
+
+// Wall clock time in the local timezone, dateformat writes it out
+pub struct LocalTime {
+    pub year: i32,
+    // 1 = January
+    pub month: u32,
+    pub day: u32,
+    pub hour: u32,
+    pub minute: u32,
+    // 0 = Sunday
+    pub weekday: u32,
+}
+
+// Current local time via libc's localtime_r, which reads /etc/localtime and TZ for us
+// (and falls back to UTC if neither works).
+pub fn local_time() -> Option<LocalTime> {
+    // SAFETY: time with a null pointer just returns the current time
+    local_time_at(unsafe { libc::time(std::ptr::null_mut()) })
+}
+
+// Local time of a unix timestamp, same as local_time
+pub fn local_time_at(timestamp: libc::time_t) -> Option<LocalTime> {
+    // localtime_r fills in the struct we hand it, so a zeroed tm is fine to start from
+    let tm = unsafe {
+        let mut tm = std::mem::MaybeUninit::<libc::tm>::zeroed();
+        if libc::localtime_r(&timestamp, tm.as_mut_ptr()).is_null() {
+            return None;
+        }
+        tm.assume_init()
+    };
+
+    Some(LocalTime {
+        year: tm.tm_year + 1900,
+        month: tm.tm_mon as u32 + 1,
+        day: tm.tm_mday as u32,
+        hour: tm.tm_hour as u32,
+        minute: tm.tm_min as u32,
+        weekday: tm.tm_wday as u32,
+    })
+}
+
+// Login name from USER/LOGNAME, or the passwd entry for our uid if those are unset
+pub fn username() -> Option<String> {
+    if let Some(name) = ["USER", "LOGNAME"]
+        .iter()
+        .find_map(|var| std::env::var(var).ok().filter(|name| !name.is_empty()))
+    {
+        return Some(name);
+    }
+
+    let mut buf = vec![0 as libc::c_char; 4096];
+    let mut passwd = std::mem::MaybeUninit::<libc::passwd>::zeroed();
+    let mut result: *mut libc::passwd = std::ptr::null_mut();
+    // getpwuid_r only writes into the buffers we pass it, result is null if there's no entry
+    unsafe {
+        let status = libc::getpwuid_r(
+            libc::getuid(),
+            passwd.as_mut_ptr(),
+            buf.as_mut_ptr(),
+            buf.len(),
+            &mut result,
+        );
+        if status != 0 || result.is_null() {
+            return None;
+        }
+        let name = std::ffi::CStr::from_ptr((*result).pw_name);
+        Some(name.to_string_lossy().into_owned())
+    }
+}
+
 // Helper to read the first line of a file using buffered I/O
 // Only reads until first newline instead of entire file
 pub fn read_first_line(path: &str) -> Option<String> {
@@ -99,6 +574,26 @@ pub fn read_first_line(path: &str) -> Option<String> {
     Some(line)
 }
 
+// Look up a key in an INI-style file (kdeglobals, kwinrc, Konsole profiles).
+// `section` is the name without brackets. Keys before the first section header are ignored.
+pub fn ini_value<'a>(content: &'a str, section: &str, key: &str) -> Option<&'a str> {
+    let mut in_section = false;
+    for line in content.lines() {
+        let line = line.trim();
+        if let Some(name) = line.strip_prefix('[').and_then(|l| l.strip_suffix(']')) {
+            in_section = name == section;
+            continue;
+        }
+        if in_section
+            && let Some((k, v)) = line.split_once('=')
+            && k.trim() == key
+        {
+            return Some(v.trim());
+        }
+    }
+    None
+}
+
 // Helper to capitalize the first letter of a string.
 // No im not importing a crate for this.
 pub fn capitalize(s: &str) -> String {
@@ -109,42 +604,131 @@ pub fn capitalize(s: &str) -> String {
     }
 }
 
-// Draw the bar with nerd font icons
-pub fn create_bar_pretty(usage_percent: f64) -> String {
-    // Calculate filled blocks, 10 blocks = 100%
-    let filled_blocks = ((usage_percent / 10.0).round() as usize).min(10);
+// Order two version strings like kernel releases: runs of digits compare as numbers, runs of
+// letters as text, separators are ignored. A number beats text ("6.12.1" > "6.12.a") and "rc"
+// sorts before everything, the end included, so "6.12-rc7" < "6.12" < "6.12.1".
+pub fn compare_versions(a: &str, b: &str) -> std::cmp::Ordering {
+    use std::cmp::Ordering;
 
-    if filled_blocks == 0 {
-        // Empty bar = Start empty + 9 empty middle + End
-        format!("{}", "".repeat(9))
-    } else {
-        // Filled/Semi-filled = Start filled + (N-1) filled middle + remaining empty + End
-        let filled_middle = filled_blocks - 1;
-        let empty_middle = 10 - filled_blocks;
-        format!(
-            "{}{}",
-            "".repeat(filled_middle),
-            "".repeat(empty_middle)
-        )
+    enum Part<'a> {
+        Number(u64),
+        Text(&'a str),
+    }
+    fn parts(version: &str) -> Vec<Part<'_>> {
+        let mut parts = Vec::new();
+        let mut rest = version;
+        while let Some(start) = rest.find(|c: char| c.is_ascii_alphanumeric()) {
+            rest = &rest[start..];
+            let digits = rest.starts_with(|c: char| c.is_ascii_digit());
+            let end = rest
+                .find(|c: char| if digits { !c.is_ascii_digit() } else { !c.is_ascii_alphabetic() })
+                .unwrap_or(rest.len());
+            let (part, tail) = rest.split_at(end);
+            parts.push(match part.parse() {
+                Ok(number) if digits => Part::Number(number),
+                _ => Part::Text(part),
+            });
+            rest = tail;
+        }
+        parts
+    }
+    let is_rc = |part: &Part| matches!(part, Part::Text(text) if text.eq_ignore_ascii_case("rc"));
+
+    let (a, b) = (parts(a), parts(b));
+    for i in 0..a.len().max(b.len()) {
+        let ordering = match (a.get(i), b.get(i)) {
+            (Some(x), Some(y)) if is_rc(x) || is_rc(y) => is_rc(y).cmp(&is_rc(x)),
+            (Some(Part::Number(x)), Some(Part::Number(y))) => x.cmp(y),
+            (Some(Part::Text(x)), Some(Part::Text(y))) => x.cmp(y),
+            (Some(Part::Number(_)), Some(Part::Text(_))) => Ordering::Greater,
+            (Some(Part::Text(_)), Some(Part::Number(_))) => Ordering::Less,
+            // One ran out: the longer is newer unless what it has left is a release candidate
+            (Some(x), None) if is_rc(x) => Ordering::Less,
+            (Some(_), None) => Ordering::Greater,
+            (None, Some(y)) if is_rc(y) => Ordering::Greater,
+            (None, Some(_)) => Ordering::Less,
+            (None, None) => Ordering::Equal,
+        };
+        if ordering != Ordering::Equal {
+            return ordering;
+        }
     }
+    Ordering::Equal
 }
 
-// Draw the bar with regular characters
-pub fn create_bar_ascii(usage_percent: f64) -> String {
-    // Calculate filled blocks, 10 blocks = 100%
-    let filled_blocks = ((usage_percent / 10.0).round() as usize).min(10);
-    let empty_blocks = 10 - filled_blocks;
+// [bars] and [bars.<metric>] from the config
+static BAR_STYLES: OnceLock<BarStyles> = OnceLock::new();
 
-    format!("[{}{}]", "=".repeat(filled_blocks), " ".repeat(empty_blocks))
+// Set the bar config, call before anything draws a bar
+pub fn set_bar_styles(styles: BarStyles) {
+    let _ = BAR_STYLES.set(styles);
 }
 
-// Draw the bar, auto-selecting style based on font (cached)
-pub fn create_bar(usage_percent: f64) -> String {
-    if get_cached_is_nerd_font() {
-        create_bar_pretty(usage_percent)
+// How a metric's bar is drawn, the defaults until main sets the config
+pub fn bar_style(metric: &str) -> BarStyle {
+    match BAR_STYLES.get() {
+        Some(styles) => styles.resolve(metric),
+        None => BarStyles::default().resolve(metric),
+    }
+}
+
+// Draw a metric's bar (memory, storage, battery, ups) in its configured style
+pub fn create_bar(metric: &str, usage_percent: f64) -> String {
+    draw_bar(bar_style(metric), usage_percent)
+}
+
+// Draw a bar, 10 cells = 100%. Every style is the same width whatever the fill, so lines
+// with bars don't shift as the numbers change.
+pub fn draw_bar(style: BarStyle, usage_percent: f64) -> String {
+    let filled = ((usage_percent / 10.0).round() as usize).min(10);
+    let glyphs = match style.glyphs {
+        BarGlyphs::Auto if get_cached_is_nerd_font() => BarGlyphs::Nerd,
+        BarGlyphs::Auto => BarGlyphs::Ascii,
+        glyphs => glyphs,
+    };
+    let (open, fill, empty, close) = match glyphs {
+        // Nerd font progress glyphs: start cap, middles and end cap, filled or not.
+        // The start cap is the first cell, the end cap is never filled.
+        BarGlyphs::Nerd => {
+            let open = if filled > 0 { "" } else { "" };
+            (open, "", "", "")
+        }
+        BarGlyphs::Blocks => ("", "█", "░", ""),
+        BarGlyphs::Ascii | BarGlyphs::Auto => ("[", "=", " ", "]"),
+    };
+    // The nerd start cap stands in for the first cell
+    let (first_cell, cells) = match glyphs {
+        BarGlyphs::Nerd => (1, 9),
+        _ => (0, 10),
+    };
+    let filled_cells = filled.saturating_sub(first_cell);
+
+    let mut bar = String::new();
+    if style.fill == BarFill::Gradient && filled > 0 {
+        let (start, end) = style.gradient;
+        let cell_color = |cell: usize| {
+            let t = cell as f64 / 9.0;
+            let mix = |a: u8, b: u8| (a as f64 + (b as f64 - a as f64) * t).round() as u8;
+            fg_sgr((mix(start.0, end.0), mix(start.1, end.1), mix(start.2, end.2)))
+        };
+        // A filled start cap is colored like the first cell
+        if first_cell == 1 {
+            bar.push_str(&cell_color(0));
+        }
+        bar.push_str(open);
+        for cell in 0..filled_cells {
+            bar.push_str(&cell_color(cell + first_cell));
+            bar.push_str(fill);
+        }
+        // The rest of the line carries on in the value color
+        bar.push_str(&palette().value);
     } else {
-        create_bar_ascii(usage_percent)
+        bar.push_str(open);
+        bar.push_str(&fill.repeat(filled_cells));
     }
+    bar.push_str(&empty.repeat(cells - filled_cells));
+    bar.push_str(close);
+    bar
 }
 
 // get the current Noctalia color scheme, yeah this one is just for me :P
@@ -224,3 +808,522 @@ pub fn get_dms_theme() -> Option<String> {
     }
     None
 }
+
+// Built-in DMS themes: name, primary, secondary. DMS works the exact shades out at runtime,
+// these are close enough to tint a fetch with
+const DMS_THEMES: &[(&str, &str, &str)] = &[
+    ("blue", "#42A5F5", "#8AB4F8"),
+    ("purple", "#D0BCFF", "#CCC2DC"),
+    ("green", "#81C995", "#B5CCBA"),
+    ("orange", "#FFB74D", "#E6BEAD"),
+    ("red", "#F28B82", "#E7BDB6"),
+    ("cyan", "#4DD0E1", "#B1CCD2"),
+    ("pink", "#F48FB1", "#E2BDC7"),
+    ("amber", "#FFCA28", "#D8C4A0"),
+    ("coral", "#FF8A80", "#E7BDB6"),
+];
+
+// Colors of the active DankMaterialShell theme for colors_from = "dms". A custom theme
+// file gives its own colors (from its "dark" or "light" variant when it has them), built-in
+// themes come from DMS_THEMES. Roles the theme doesn't define are left as None.
+pub fn get_dms_palette() -> Option<ThemePalette> {
+    dms_palette_in(Path::new(&std::env::var("HOME").ok()?))
+}
+
+fn dms_palette_in(home: &Path) -> Option<ThemePalette> {
+    let settings =
+        fs::read_to_string(home.join(".config/DankMaterialShell/settings.json")).ok()?;
+    let theme = json_string(&settings, "currentThemeName").unwrap_or("blue");
+
+    if !theme.eq_ignore_ascii_case("custom") {
+        let (_, primary, secondary) =
+            DMS_THEMES.iter().find(|(name, ..)| theme.eq_ignore_ascii_case(name))?;
+        return Some(ThemePalette {
+            primary: parse_hex_color(primary),
+            secondary: parse_hex_color(secondary),
+            ..ThemePalette::default()
+        });
+    }
+
+    let theme_file = expand_home(json_string(&settings, "customThemeFile")?);
+    let content = fs::read_to_string(theme_file).ok()?;
+    // Light mode is remembered in the session state, not the settings
+    let session = home.join(".local/state/DankMaterialShell/session.json");
+    let light = fs::read_to_string(session)
+        .is_ok_and(|session| json_bool(&session, "isLightMode") == Some(true));
+    let variant = if light { "\"light\"" } else { "\"dark\"" };
+    let colors = content
+        .find(variant)
+        .and_then(|start| json_objects(&content[start..]).first().copied())
+        .unwrap_or(&content);
+
+    let color = |role: &str| json_string(colors, role).and_then(parse_hex_color);
+    let palette = ThemePalette {
+        primary: color("primary"),
+        secondary: color("secondary"),
+        surface_text: color("surfaceText"),
+        warning: color("warning"),
+        error: color("error"),
+    };
+    (palette != ThemePalette::default()).then_some(palette)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::renderer::strip_escapes;
+    use crate::testutil::TempDir;
+
+    // A /proc/<pid>/stat line with the given comm and starttime (field 22)
+    fn stat_line(comm: &str, starttime: u64) -> String {
+        format!(
+            "1234 ({}) S 1 1234 1234 0 -1 4194560 5000 0 0 0 120 40 0 0 20 0 4 0 {} \
+             900000000 20000 18446744073709551615",
+            comm, starttime
+        )
+    }
+
+    #[test]
+    fn drm_card_from_the_fd_links() {
+        let proc = TempDir::new();
+        proc.symlink("900/fd/0", "/dev/null");
+        proc.symlink("900/fd/3", "socket:[48213]");
+        proc.symlink("900/fd/12", "anon_inode:[eventfd]");
+        // The render node isn't a card
+        proc.symlink("900/fd/14", "/dev/dri/renderD128");
+        proc.symlink("900/fd/15", "/dev/dri/card1");
+        assert_eq!(open_drm_card(proc.path(), 900).as_deref(), Some("card1"));
+
+        proc.symlink("901/fd/0", "/dev/null");
+        proc.symlink("901/fd/4", "/dev/dri/renderD128");
+        assert_eq!(open_drm_card(proc.path(), 901), None);
+        // Gone before its fds were looked at
+        assert_eq!(open_drm_card(proc.path(), 902), None);
+    }
+
+    #[test]
+    fn the_process_walk_stops_at_a_match() {
+        let proc = TempDir::new();
+        for pid in 100..140 {
+            proc.write(&format!("{}/cmdline", pid), format!("/usr/bin/app{}\0--flag\0", pid));
+        }
+        // Not a process, and one that's gone (no cmdline)
+        proc.write("self/cmdline", "slowfetch\0");
+        fs::create_dir(proc.join("141")).unwrap();
+        fn pid_of(name: &str) -> impl Fn(&ProcessEntry) -> Option<u32> + '_ {
+            move |process| process.cmdline.starts_with(name.as_bytes()).then_some(process.pid)
+        }
+
+        let mut scan = ProcessScan::new(proc.path());
+        assert_eq!(scan.find_map(pid_of("/usr/bin/app117\0")), Some(117));
+        let read = scan.processes.len();
+        assert!(read <= 40);
+        // Already read, found again without reading further
+        assert_eq!(scan.find_map(pid_of("/usr/bin/app117\0")), Some(117));
+        assert_eq!(scan.processes.len(), read);
+        // Found wherever it is, before or after the first match
+        assert_eq!(scan.find_map(pid_of("/usr/bin/app100\0")), Some(100));
+        assert_eq!(scan.find_map(pid_of("/usr/bin/app139\0")), Some(139));
+        // Nothing matches, so everything is read
+        assert_eq!(scan.find_map(pid_of("/usr/bin/sway\0")), None);
+        assert_eq!(scan.processes.len(), 40);
+        assert!(scan.summary().ends_with("ms, all 40 processes"), "{}", scan.summary());
+
+        // A walk that found its match up front says it stopped early
+        let mut scan = ProcessScan::new(proc.path());
+        assert!(scan.find_map(|process| Some(process.pid)).is_some());
+        assert!(scan.summary().ends_with("ms, stopped early after 1 process"));
+        assert_eq!(ProcessScan::new(&proc.join("missing")).find_map(|_| Some(())), None);
+    }
+
+    // Another user's process: its fd dir can't be listed, which is no card, not an error.
+    // Root lists it anyway.
+    #[test]
+    fn unreadable_fds_are_no_card() {
+        use std::os::unix::fs::PermissionsExt;
+
+        let proc = TempDir::new();
+        proc.symlink("1200/fd/9", "/dev/dri/card0");
+        let fd = proc.join("1200/fd");
+        fs::set_permissions(&fd, fs::Permissions::from_mode(0o000)).unwrap();
+        let card = open_drm_card(proc.path(), 1200);
+        fs::set_permissions(&fd, fs::Permissions::from_mode(0o755)).unwrap();
+        let root = unsafe { libc::geteuid() } == 0;
+        assert_eq!(card.as_deref(), root.then_some("card0"));
+    }
+
+    // localtime_r goes by whatever zone the machine running the tests is in, and real zones
+    // are whole quarter hours off UTC, so only what every zone agrees on is checked
+    #[test]
+    fn local_time_of_a_timestamp() {
+        // 2023-11-14 22:13:20 UTC, a Tuesday
+        let time = local_time_at(1_700_000_000).unwrap();
+        assert_eq!(time.year, 2023);
+        assert_eq!(time.month, 11);
+        assert_eq!(time.minute % 15, 13);
+        // The day and the weekday move together
+        let days_off = time.day as i32 - 14;
+        assert!((-1..=1).contains(&days_off));
+        assert_eq!(time.weekday as i32, 2 + days_off);
+    }
+
+    #[test]
+    fn local_time_on_a_leap_day() {
+        // 2024-02-29 12:00 UTC, a Thursday. Zones from -12 to +14 put it a day either side.
+        let time = local_time_at(1_709_208_000).unwrap();
+        let weekday = match (time.month, time.day) {
+            (2, 28) => 3,
+            (2, 29) => 4,
+            (3, 1) => 5,
+            other => panic!("{:?} isn't around the leap day", other),
+        };
+        assert_eq!((time.year, time.weekday), (2024, weekday));
+        assert_eq!(time.minute % 15, 0);
+    }
+
+    const KWINRC: &str = "\
+stray=before any section
+
+[Compositing]
+Backend = OpenGL
+Enabled=true
+
+[Xwayland]
+Scale=1.25
+
+[Compositing][Effect-blur]
+Enabled=false
+";
+
+    #[test]
+    fn ini_values_by_section() {
+        assert_eq!(ini_value(KWINRC, "Compositing", "Backend"), Some("OpenGL"));
+        assert_eq!(ini_value(KWINRC, "Compositing", "Enabled"), Some("true"));
+        assert_eq!(ini_value(KWINRC, "Xwayland", "Scale"), Some("1.25"));
+        // KDE's nested groups are their own section
+        assert_eq!(ini_value(KWINRC, "Compositing][Effect-blur", "Enabled"), Some("false"));
+    }
+
+    #[test]
+    fn ini_values_that_are_not_there() {
+        assert_eq!(ini_value(KWINRC, "Xwayland", "Backend"), None);
+        assert_eq!(ini_value(KWINRC, "Plugins", "blurEnabled"), None);
+        // Keys above the first header belong to no section
+        assert_eq!(ini_value(KWINRC, "", "stray"), None);
+        assert_eq!(ini_value("", "Compositing", "Backend"), None);
+    }
+
+    #[test]
+    fn konsole_profile_font() {
+        // CRLF line ends, and the font is the whole QFont string
+        let profile = "[Appearance]\r\nColorScheme=Breeze\r\n\
+                       Font=JetBrainsMono Nerd Font,11,-1,5,400,0,0,0,0,0,0,0,0,0,0,1\r\n";
+        let font = ini_value(profile, "Appearance", "Font");
+        assert_eq!(font, Some("JetBrainsMono Nerd Font,11,-1,5,400,0,0,0,0,0,0,0,0,0,0,1"));
+    }
+
+    #[test]
+    fn process_age_from_starttime() {
+        // Started 100s after boot at 100 ticks/s, uptime 3700s: up for an hour
+        assert_eq!(process_age_seconds(&stat_line("Hyprland", 10_000), 3700.0, 100), Some(3600));
+        // Same start in 250Hz ticks
+        assert_eq!(process_age_seconds(&stat_line("Hyprland", 25_000), 3700.0, 250), Some(3600));
+        // Fractions of a second are dropped
+        assert_eq!(process_age_seconds(&stat_line("sway", 150), 10.0, 100), Some(8));
+    }
+
+    #[test]
+    fn process_age_with_odd_comm() {
+        // Spaces and parens in comm don't shift the fields
+        let stat = stat_line("my wm) (x", 10_000);
+        assert_eq!(process_age_seconds(&stat, 3700.0, 100), Some(3600));
+    }
+
+    #[test]
+    fn process_age_edge_cases() {
+        // Started "after" the uptime read (the two files aren't read at the same instant)
+        assert_eq!(process_age_seconds(&stat_line("kwin", 10_000), 99.5, 100), Some(0));
+        assert_eq!(process_age_seconds(&stat_line("kwin", 10_000), 3700.0, 0), None);
+        assert_eq!(process_age_seconds("1234 (kwin) S 1 1234", 3700.0, 100), None);
+        assert_eq!(process_age_seconds("1234 kwin S 1", 3700.0, 100), None);
+        let garbled = stat_line("kwin", 10_000).replace("10000", "x");
+        assert_eq!(process_age_seconds(&garbled, 3700.0, 100), None);
+    }
+
+    // A flat custom theme file the way DMS exports them, primaryText ahead of primary
+    const DMS_FLAT: &str = r##"{
+  "name": "Catppuccin Mocha",
+  "primaryText": "#11111b",
+  "primary": "#cba6f7",
+  "primaryContainer": "#45475a",
+  "secondary": "#f5c2e7",
+  "surface": "#1e1e2e",
+  "surfaceText": "#cdd6f4",
+  "surfaceVariant": "#313244",
+  "error": "#f38ba8",
+  "warning": "#fab387",
+  "info": "#89b4fa"
+}"##;
+
+    // One with both variants, and a light one missing most roles
+    const DMS_VARIANTS: &str = r##"{
+  "name": "Gruvbox",
+  "dark": {
+    "primary": "#83a598",
+    "secondary": "#d3869b",
+    "surfaceText": "#ebdbb2",
+    "error": "#fb4934",
+    "warning": "#fabd2f"
+  },
+  "light": {
+    "primary": "#076678"
+  }
+}"##;
+
+    // A home with DMS settings and, when given, a custom theme file and the light mode flag
+    fn dms_home(settings: &str, theme: Option<&str>, light: Option<bool>) -> TempDir {
+        let home = TempDir::new();
+        let settings = settings.replace("$HOME", &home.path().display().to_string());
+        home.write(".config/DankMaterialShell/settings.json", &settings);
+        if let Some(theme) = theme {
+            home.write("themes/custom.json", theme);
+        }
+        if let Some(light) = light {
+            let session = format!("{{\n  \"isLightMode\": {}\n}}", light);
+            home.write(".local/state/DankMaterialShell/session.json", &session);
+        }
+        home
+    }
+
+    const CUSTOM: &str =
+        r#"{"currentThemeName": "custom", "customThemeFile": "$HOME/themes/custom.json"}"#;
+
+    #[test]
+    fn dms_builtin_themes() {
+        let home = dms_home(r#"{"currentThemeName": "purple", "cornerRadius": 12}"#, None, None);
+        let palette = dms_palette_in(home.path()).unwrap();
+        assert_eq!(palette.primary, Some((0xD0, 0xBC, 0xFF)));
+        assert_eq!(palette.secondary, Some((0xCC, 0xC2, 0xDC)));
+        assert_eq!(palette.surface_text, None);
+
+        // No theme name is DMS's default, blue
+        let home = dms_home(r#"{"cornerRadius": 12}"#, None, None);
+        assert_eq!(dms_palette_in(home.path()).unwrap().primary, Some((0x42, 0xA5, 0xF5)));
+
+        let home = dms_home(r#"{"currentThemeName": "dynamic"}"#, None, None);
+        assert_eq!(dms_palette_in(home.path()), None);
+        // No DMS at all
+        assert_eq!(dms_palette_in(TempDir::new().path()), None);
+    }
+
+    #[test]
+    fn dms_custom_theme_file() {
+        let home = dms_home(CUSTOM, Some(DMS_FLAT), None);
+        let palette = dms_palette_in(home.path()).unwrap();
+        assert_eq!(
+            palette,
+            ThemePalette {
+                primary: Some((0xcb, 0xa6, 0xf7)),
+                secondary: Some((0xf5, 0xc2, 0xe7)),
+                surface_text: Some((0xcd, 0xd6, 0xf4)),
+                warning: Some((0xfa, 0xb3, 0x87)),
+                error: Some((0xf3, 0x8b, 0xa8)),
+            }
+        );
+
+        // The theme file is gone, or has no colors slowfetch uses
+        let home = dms_home(CUSTOM, None, None);
+        assert_eq!(dms_palette_in(home.path()), None);
+        let home = dms_home(CUSTOM, Some(r##"{"name": "Empty", "info": "#89b4fa"}"##), None);
+        assert_eq!(dms_palette_in(home.path()), None);
+    }
+
+    #[test]
+    fn dms_dark_and_light_variants() {
+        // Dark unless the session says light
+        for light in [None, Some(false)] {
+            let home = dms_home(CUSTOM, Some(DMS_VARIANTS), light);
+            let palette = dms_palette_in(home.path()).unwrap();
+            assert_eq!(palette.primary, Some((0x83, 0xa5, 0x98)));
+            assert_eq!(palette.warning, Some((0xfa, 0xbd, 0x2f)));
+        }
+
+        // The light variant's missing roles stay missing, not borrowed from the dark one
+        let home = dms_home(CUSTOM, Some(DMS_VARIANTS), Some(true));
+        let palette = dms_palette_in(home.path()).unwrap();
+        assert_eq!(
+            palette,
+            ThemePalette { primary: Some((0x07, 0x66, 0x78)), ..ThemePalette::default() }
+        );
+    }
+
+    #[test]
+    fn durations_at_each_precision() {
+        let minutes = |seconds| format_duration(seconds, Precision::Minutes);
+        let seconds = |seconds| format_duration(seconds, Precision::Seconds);
+        assert_eq!(minutes(0), "0m");
+        assert_eq!(minutes(59), "0m");
+        assert_eq!(minutes(60), "1m");
+        assert_eq!(minutes(3599), "59m");
+        assert_eq!(minutes(3600), "1h 0m");
+        assert_eq!(minutes(11565), "3h 12m");
+        // Days stay hours
+        assert_eq!(minutes(100_000), "27h 46m");
+
+        assert_eq!(seconds(0), "0s");
+        assert_eq!(seconds(45), "45s");
+        assert_eq!(seconds(60), "1m 0s");
+        assert_eq!(seconds(3599), "59m 59s");
+        assert_eq!(seconds(3600), "1h 0m 0s");
+        assert_eq!(seconds(11565), "3h 12m 45s");
+        assert_eq!(seconds(100_000), "27h 46m 40s");
+    }
+
+    fn bar_styles() -> Vec<BarStyle> {
+        let glyphs = [BarGlyphs::Auto, BarGlyphs::Nerd, BarGlyphs::Ascii, BarGlyphs::Blocks];
+        let gradient = ((0x50, 0xFA, 0x7B), (0xFF, 0x55, 0x55));
+        let fills = [BarFill::Solid, BarFill::Gradient];
+        glyphs
+            .into_iter()
+            .flat_map(|glyphs| fills.map(|fill| BarStyle { glyphs, fill, gradient }))
+            .collect()
+    }
+
+    // Every style is one width whatever the fill, out of range percentages included
+    #[test]
+    fn bars_keep_their_width() {
+        // Auto is nerd font bars, whatever the machine running the tests has
+        init_font_cache(None, Some(true));
+        for style in bar_styles() {
+            let widths: Vec<usize> = (-2..=24)
+                .map(|step| strip_escapes(&draw_bar(style, step as f64 * 5.0)).chars().count())
+                .collect();
+            // Nerd bars are a start cap, nine middles and an end cap, ascii brackets ten cells
+            let expected = match style.glyphs {
+                BarGlyphs::Auto | BarGlyphs::Nerd => 11,
+                BarGlyphs::Ascii => 12,
+                BarGlyphs::Blocks => 10,
+            };
+            assert!(widths.iter().all(|&width| width == expected), "{:?} {:?}", style, widths);
+        }
+        let style = |glyphs| BarStyle { glyphs, ..bar_styles()[0] };
+        assert_eq!(strip_escapes(&draw_bar(style(BarGlyphs::Ascii), 40.0)), "[====      ]");
+        assert_eq!(strip_escapes(&draw_bar(style(BarGlyphs::Blocks), 70.0)), "███████░░░");
+    }
+
+    // A color per filled cell, from the gradient's start at the first to its end at the last,
+    // and no colors at all in a solid bar
+    #[test]
+    fn gradient_colors_per_cell() {
+        init_font_cache(None, Some(true));
+        let colors = |bar: &str| -> Vec<String> {
+            let bar = bar.replace(&palette().value, "");
+            let code = |rest: &str| rest[..=rest.find('m').unwrap()].to_string();
+            bar.split('\x1b').skip(1).map(|rest| format!("\x1b{}", code(rest))).collect()
+        };
+        for style in bar_styles() {
+            for percent in [0.0, 4.0, 5.0, 33.0, 50.0, 96.0, 100.0] {
+                let codes = colors(&draw_bar(style, percent));
+                let filled = (percent / 10.0f64).round() as usize;
+                match style.fill {
+                    BarFill::Solid => assert!(codes.is_empty(), "{:?}", style),
+                    BarFill::Gradient => {
+                        let mut distinct = codes.clone();
+                        distinct.sort();
+                        distinct.dedup();
+                        assert_eq!(codes.len(), filled, "{:?} at {}", style, percent);
+                        assert_eq!(distinct.len(), filled, "{:?} at {}", style, percent);
+                    }
+                }
+            }
+        }
+        let blocks = BarStyle { glyphs: BarGlyphs::Blocks, ..bar_styles()[1] };
+        assert_eq!(blocks.fill, BarFill::Gradient);
+        let full = colors(&draw_bar(blocks, 100.0));
+        assert_eq!(full.first().unwrap(), "\x1b[38;2;80;250;123m");
+        assert_eq!(full.last().unwrap(), "\x1b[38;2;255;85;85m");
+    }
+
+    // Real releases, each older than the next: rc before the release, point releases by
+    // number, Arch's pkgrel, Fedora's and Debian's ABI numbers
+    #[test]
+    fn kernel_release_order() {
+        use std::cmp::Ordering;
+
+        let ascending = [
+            "6.1.0-18-amd64",
+            "6.1.0-21-amd64",
+            "6.6.50-1-lts",
+            "6.11.2-arch1-1",
+            "6.11.9-200.fc40.x86_64",
+            "6.11.10-100.fc39.x86_64",
+            "6.11.10-200.fc40.x86_64",
+            "6.11.10-300.fc41.x86_64",
+            "6.12.0-rc2",
+            "6.12.0-rc7-1-mainline",
+            "6.12.0-rc10",
+            "6.12.0",
+            "6.12.1",
+            "6.12.4-arch1-1",
+            "6.12.10-arch1-1",
+            "6.12.10-arch2-1",
+            "6.12.10-arch2-1-zen",
+        ];
+        for pair in ascending.windows(2) {
+            assert_eq!(compare_versions(pair[0], pair[1]), Ordering::Less, "{:?}", pair);
+            assert_eq!(compare_versions(pair[1], pair[0]), Ordering::Greater, "{:?}", pair);
+        }
+        // The examples above compare_versions
+        assert_eq!(compare_versions("6.12-rc7", "6.12"), Ordering::Less);
+        assert_eq!(compare_versions("6.12", "6.12.1"), Ordering::Less);
+        assert_eq!(compare_versions("6.12.1", "6.12.a"), Ordering::Greater);
+        // Separators don't count, "rc" is the same in any case
+        assert_eq!(compare_versions("6.12.4-arch1-1", "6.12.4-arch1-1"), Ordering::Equal);
+        assert_eq!(compare_versions("6.12.4", "6_12-4"), Ordering::Equal);
+        assert_eq!(compare_versions("6.12-RC3", "6.12-rc3"), Ordering::Equal);
+        assert_eq!(compare_versions("", ""), Ordering::Equal);
+    }
+
+    #[test]
+    fn wsl_from_the_kernel_release() {
+        assert_eq!(wsl_from("4.4.0-19041-Microsoft", false), Some(1));
+        assert_eq!(wsl_from("5.15.153.1-microsoft-standard-WSL2", false), Some(2));
+        assert_eq!(wsl_from("6.6.36.3-microsoft-standard-WSL2+", false), Some(2));
+        // A custom kernel, WSL still names the distro
+        assert_eq!(wsl_from("6.12.4-custom", true), Some(2));
+        assert_eq!(wsl_from("6.12.4-arch1-1", false), None);
+        assert_eq!(wsl_from("", false), None);
+    }
+
+    #[test]
+    fn json_from_compositor_output() {
+        // Trimmed from hyprctl monitors -j, nested objects and braces inside strings included
+        let json = r#"[{
+    "id": 0, "name": "DP-1", "description": "Dell Inc. {AW2723DF}",
+    "activeWorkspace": { "id": 1, "name": "1" },
+    "vrr": true, "hdr": false,
+    "availableModes": ["2560x1440@165.00Hz", "2560x1440@59.95Hz"]
+},{
+    "id": 1, "name": "HDMI-A-1", "description": "LG \"UltraGear\" }",
+    "vrr":false
+}]"#;
+        let monitors = json_objects(json);
+        assert_eq!(monitors.len(), 2);
+        assert_eq!(json_string(monitors[0], "name"), Some("DP-1"));
+        assert_eq!(json_bool(monitors[0], "vrr"), Some(true));
+        assert_eq!(json_bool(monitors[0], "hdr"), Some(false));
+        assert_eq!(json_array_len(monitors[0], "availableModes"), Some(2));
+        assert_eq!(json_string(monitors[1], "name"), Some("HDMI-A-1"));
+        assert_eq!(json_bool(monitors[1], "vrr"), Some(false));
+        assert_eq!(json_number(monitors[1], "id"), Some(1.0));
+
+        // Missing keys and values of the wrong type
+        assert_eq!(json_string(monitors[1], "adaptive_sync_status"), None);
+        assert_eq!(json_bool(monitors[0], "id"), None);
+        assert_eq!(json_string(monitors[0], "vrr"), None);
+        assert_eq!(json_number(monitors[0], "name"), None);
+        assert_eq!(json_array_len("{\"packages\" : [ ]}", "packages"), Some(0));
+        assert!(json_objects("").is_empty());
+        assert!(json_objects("[{\"name\": \"DP-1\"").is_empty());
+    }
+}
@@ -1,20 +1,136 @@
 // Image handling module for Slowfetch
 // Uses the Kitty graphics protocol to display images in the terminal
 
-use std::path::Path;
+use std::cell::Cell;
+use std::fs::{self, File};
+use std::io::Read;
+use std::num::NonZeroU32;
+use std::path::{Path, PathBuf};
+use std::sync::atomic::{AtomicUsize, Ordering};
+use std::time::{Duration, Instant, SystemTime, UNIX_EPOCH};
+
+use crate::cache;
+use crate::terminalsize;
+use crate::termquery;
+
+// Image id sent with verify_image so Kitty answers, anything nonzero works
+const VERIFY_ID: u32 = 7;
+
+// How long to wait for Kitty to say whether it could load the image
+const VERIFY_TIMEOUT: Duration = Duration::from_millis(500);
+
+// Converted images kept in ~/.cache/slowfetch/images, the least recently used go past this
+const IMAGE_CACHE_BYTES: u64 = 20 * 1024 * 1024;
+
+// How long to wait for another slowfetch converting the same image, and how often to look
+const CONVERT_WAIT: Duration = Duration::from_secs(1);
+const CONVERT_RETRY: Duration = Duration::from_millis(10);
+
+thread_local! {
+    // Images this thread decoded so far, the cache tests count on it
+    static DECODES: Cell<usize> = const { Cell::new(0) };
+}
+
+// One-off PNGs written so far, numbered so they never share a name
+static SCRATCH_PNGS: AtomicUsize = AtomicUsize::new(0);
+
+// Image settings from the config
+#[derive(Clone, Copy)]
+pub struct ImageOptions {
+    // verify_image: wait for Kitty's answer
+    pub verify: bool,
+    // image_resample = "auto": keep the aspect ratio, resample big images to the box's pixels
+    pub resample: bool,
+    // image_max_width: widest the image box gets, in columns
+    pub max_width: usize,
+}
+
+// Where the image goes in its box: the cells Kitty is asked to fill (0 = work it out from
+// the image), how far in from the box's top left corner it starts (columns, rows), the
+// pixel size it's resampled to first (None = sent as it is), and whether it was fitted by
+// its real size (image_resample = "auto") or stretched over the box
+pub struct Placement {
+    pub columns: u32,
+    pub rows: u32,
+    pub offset: (usize, usize),
+    pub pixels: Option<(u32, u32)>,
+    pub resample: bool,
+}
+
+// Fit the image into a box of `box_cols` x `box_rows` cells.
+// Without resampling, or when the terminal doesn't report its pixel size, it's stretched over
+// the whole box. Otherwise see fit.
+pub fn placement(path: &Path, box_cols: u16, box_rows: u16, resample: bool) -> Placement {
+    let size = resample.then(|| terminalsize::get_cell_size().zip(image_dimensions(path)));
+    match size.flatten() {
+        Some((cell, image)) => fit(image, cell, box_cols, box_rows),
+        None => Placement {
+            columns: box_cols as u32,
+            rows: box_rows as u32,
+            offset: (0, 0),
+            pixels: None,
+            resample: false,
+        },
+    }
+}
+
+// Fit an `image` (width, height in pixels) into a box of `box_cols` x `box_rows` cells of
+// `cell` pixels, keeping its aspect ratio, centered. An image that's bigger than the box is
+// resampled (Lanczos3) to exactly the pixels it covers, so Kitty shows it as it is instead of
+// scaling it. Kitty still gets the side that limits it, which is then the same size, and
+// what it scales the original to if resampling fails. Images that already fit are shown at
+// their own size.
+fn fit(image: (u32, u32), cell: (u16, u16), box_cols: u16, box_rows: u16) -> Placement {
+    let (cell_width, cell_height) = (cell.0 as u64, cell.1 as u64);
+    let (image_width, image_height) = (image.0 as u64, image.1 as u64);
+    let box_width = box_cols as u64 * cell_width;
+    let box_height = box_rows as u64 * cell_height;
+
+    // Size the image ends up in pixels, and which sides Kitty is told about
+    let (shown_width, shown_height, columns, rows) =
+        if image_width <= box_width && image_height <= box_height {
+            (image_width, image_height, 0, 0)
+        } else if image_width * box_height >= image_height * box_width {
+            // Wider than the box, the width limits it
+            (box_width, image_height * box_width / image_width, box_cols as u32, 0)
+        } else {
+            (image_width * box_height / image_height, box_height, 0, box_rows as u32)
+        };
+
+    // A sliver of an image still gets a pixel
+    let (shown_width, shown_height) = (shown_width.max(1), shown_height.max(1));
+    let shown_cols = shown_width.div_ceil(cell_width) as usize;
+    let shown_rows = shown_height.div_ceil(cell_height) as usize;
+    let resized = (columns, rows) != (0, 0);
+    Placement {
+        columns,
+        rows,
+        offset: (
+            (box_cols as usize).saturating_sub(shown_cols) / 2,
+            (box_rows as usize).saturating_sub(shown_rows) / 2,
+        ),
+        pixels: resized.then_some((shown_width as u32, shown_height as u32)),
+        resample: true,
+    }
+}
+
+// Width and height from the image's header, without decoding the rest
+fn image_dimensions(path: &Path) -> Option<(u32, u32)> {
+    let reader = ::image::ImageReader::open(path).ok()?.with_guessed_format().ok()?;
+    let (width, height) = reader.into_dimensions().ok()?;
+    (width > 0 && height > 0).then_some((width, height))
+}
 
 // Display an image using the Kitty graphics protocol.
-// Kitty handles the scaling - we just tell it the target dimensions in terminal cells.
+// Kitty scales it to the cells in the placement, unless it was resampled to their pixels first.
 // arguments:
-// `path` - Path to the image file (PNG, JPEG, etc.)
-//  `box_cols` - Width of the box in terminal columns
-//  `box_rows` - Height of the box in terminal rows\
-//
-// currently hardcoded image path
+// `path` - Path to the image file (PNG, or JPEG/WebP/GIF converted to one, see convert)
+//  `placement` - Cells to fill, see placement
+//  `verify` - Ask Kitty to answer, see send_verified
 //
 // returns the escape sequence string to display the image or an error message dun dun duuuun
 
-pub fn display_image(path: &Path, box_cols: u16, box_rows: u16) -> Result<String, String> {
+pub fn display_image(path: &Path, placement: &Placement, verify: bool) -> Result<String, String> {
     // Ensure we have an absolute path for Kitty to read
     let abs_path = if path.is_absolute() {
         path.to_path_buf()
@@ -24,31 +140,270 @@ pub fn display_image(path: &Path, box_cols: u16, box_rows: u16) -> Result<String
             .join(path)
     };
 
-    // Verify file exists
+    // Verify file exists and is something Kitty can load
     if !abs_path.exists() {
         return Err(format!("Image file not found: {}", abs_path.display()));
     }
+    // A PNG that's sent at the size it is goes as it is, resampling falls back to Kitty scaling
+    // the original
+    let original = || Converted { path: abs_path.clone(), medium: kitty_image::Medium::File };
+    let file = match (validate_image(&abs_path)?, placement.pixels) {
+        ("PNG", None) => original(),
+        ("PNG", _) => convert(&abs_path, placement).unwrap_or_else(|_| original()),
+        _ => convert(&abs_path, placement)?,
+    };
 
     // Create the kitty graphics command - let Kitty handle the scaling
     let action = kitty_image::Action::TransmitAndDisplay(
         kitty_image::ActionTransmission {
             format: kitty_image::Format::Png,
-            medium: kitty_image::Medium::File,
+            medium: file.medium,
             ..Default::default()
         },
         kitty_image::ActionPut {
-            columns: box_cols as u32,
-            rows: box_rows as u32,
+            columns: placement.columns,
+            rows: placement.rows,
             ..Default::default()
         },
     );
 
-    let command = kitty_image::Command::with_payload_from_path(action, &abs_path);
+    let mut command = kitty_image::Command::with_payload_from_path(action, &file.path);
+    // Kitty only answers commands that carry an id
+    if verify {
+        command.id = NonZeroU32::new(VERIFY_ID).map(kitty_image::ID);
+    }
     let wrapped = kitty_image::WrappedCommand::new(command);
 
     Ok(wrapped.to_string())
 }
 
+// What an image file is, going by its first bytes
+fn image_type(header: &[u8]) -> Option<&'static str> {
+    if header.starts_with(b"\x89PNG\r\n\x1a\n") {
+        Some("PNG")
+    } else if header.starts_with(&[0xFF, 0xD8, 0xFF]) {
+        Some("JPEG")
+    } else if header.len() >= 12 && &header[..4] == b"RIFF" && &header[8..12] == b"WEBP" {
+        Some("WebP")
+    } else if header.starts_with(b"GIF8") {
+        Some("GIF")
+    } else {
+        None
+    }
+}
+
+// Check the file really is an image before handing it over, and say which kind. Kitty decodes
+// PNG itself (f=100), but anything else sent that way just fails on its side with nothing on
+// ours, so the others are converted first.
+pub fn validate_image(path: &Path) -> Result<&'static str, String> {
+    let mut header = [0u8; 12];
+    let read = File::open(path)
+        .and_then(|mut file| file.read(&mut header))
+        .map_err(|e| format!("Can't read image {}: {}", path.display(), e))?;
+
+    image_type(&header[..read])
+        .ok_or_else(|| format!("{} isn't a PNG, JPEG, WebP or GIF image", path.display()))
+}
+
+// A PNG to send and how Kitty reads it: File for one that's kept (the original, or the
+// converted one in the cache), TemporaryFile for a one-off Kitty deletes once it's read it
+pub struct Converted {
+    pub path: PathBuf,
+    pub medium: kitty_image::Medium,
+}
+
+// The image converted to the PNG Kitty is sent for `placement`, see convert_in
+fn convert(path: &Path, placement: &Placement) -> Result<Converted, String> {
+    let dir = cache::images_dir().ok_or("no $HOME, or ~/.cache/slowfetch/images can't be made")?;
+    convert_in(&dir, path, placement, cache::should_refresh_images())
+}
+
+// Decode the image, resample it to the placement's pixels (width, height) if it has any, and
+// write it to `dir` as a PNG. It's named after what it was made from (see cache_key) so the
+// next run finds it and sends it without decoding anything. `refresh` (--refresh, or
+// --refresh images) makes it again anyway. The cache is kept under IMAGE_CACHE_BYTES by
+// dropping the images that went unused the longest.
+// It's made under the cache dir's lock (the one `dir` is in). Another slowfetch holding it
+// (tmux restoring a few panes showing the same image) is most likely making the same file,
+// which is used once it's there. If it isn't there within CONVERT_WAIT the image is made
+// anyway, as a one-off outside the cache (see convert_scratch).
+pub fn convert_in(
+    dir: &Path,
+    source: &Path,
+    placement: &Placement,
+    refresh: bool,
+) -> Result<Converted, String> {
+    let metadata = fs::metadata(source)
+        .map_err(|e| format!("Can't read image {}: {}", source.display(), e))?;
+    let path = dir.join(format!("{:016x}.png", cache_key(source, &metadata, placement)));
+    if !refresh && path.is_file() {
+        return Ok(cached(path));
+    }
+
+    let started = Instant::now();
+    let _lock = loop {
+        if let Some(lock) = cache::try_lock(dir.parent().unwrap_or(dir)) {
+            break lock;
+        }
+        // Renamed into place, so it's whole once it's there. With --refresh that's the old
+        // one until the other run is done, still the same image.
+        if path.is_file() {
+            return Ok(cached(path));
+        }
+        if started.elapsed() >= CONVERT_WAIT {
+            return convert_scratch(source, placement);
+        }
+        std::thread::sleep(CONVERT_RETRY);
+    };
+    // Made by whoever had the lock before
+    if !refresh && path.is_file() {
+        return Ok(cached(path));
+    }
+
+    let image = decode(source, placement)?;
+    // Written next to its place and renamed into it, so another slowfetch sending the same
+    // image never gets half a file
+    let tmp_path = path.with_extension(format!("tmp.{}", std::process::id()));
+    let written = image
+        .save_with_format(&tmp_path, ::image::ImageFormat::Png)
+        .map_err(|e| e.to_string())
+        .and_then(|_| fs::rename(&tmp_path, &path).map_err(|e| e.to_string()));
+    if let Err(error) = written {
+        let _ = fs::remove_file(&tmp_path);
+        return Err(format!("Can't write {}: {}", path.display(), error));
+    }
+    evict(dir, IMAGE_CACHE_BYTES, &path);
+    Ok(Converted { path, medium: kitty_image::Medium::File })
+}
+
+// A converted image found in the cache. Used again, so it's the last to go.
+fn cached(path: PathBuf) -> Converted {
+    let _ = File::options()
+        .write(true)
+        .open(&path)
+        .and_then(|file| file.set_modified(SystemTime::now()));
+    Converted { path, medium: kitty_image::Medium::File }
+}
+
+// The image decoded and resampled to the placement's pixels, if it has any
+fn decode(source: &Path, placement: &Placement) -> Result<::image::DynamicImage, String> {
+    DECODES.set(DECODES.get() + 1);
+    let image = ::image::ImageReader::open(source)
+        .and_then(|reader| reader.with_guessed_format())
+        .map_err(::image::ImageError::IoError)
+        .and_then(|reader| reader.decode())
+        .map_err(|e| format!("Can't decode {}: {}", source.display(), e))?;
+    Ok(match placement.pixels {
+        Some((width, height)) => {
+            image.resize_exact(width, height, ::image::imageops::FilterType::Lanczos3)
+        }
+        None => image,
+    })
+}
+
+// The image converted into the temp dir instead of the cache, for when another slowfetch
+// holds the cache's lock for too long. Kitty deletes it once it's read it, it only does that
+// in a temp dir and with "tty-graphics-protocol" in the name.
+fn convert_scratch(source: &Path, placement: &Placement) -> Result<Converted, String> {
+    let image = decode(source, placement)?;
+    let path = std::env::temp_dir().join(format!(
+        "slowfetch-{}-{}-tty-graphics-protocol.png",
+        std::process::id(),
+        SCRATCH_PNGS.fetch_add(1, Ordering::Relaxed)
+    ));
+    if let Err(error) = image.save_with_format(&path, ::image::ImageFormat::Png) {
+        let _ = fs::remove_file(&path);
+        return Err(format!("Can't write {}: {}", path.display(), error));
+    }
+    Ok(Converted { path, medium: kitty_image::Medium::TemporaryFile })
+}
+
+// What a converted image is made from, as an FNV-1a hash: the source's path, modification
+// time and size, how it was fitted (image_resample and the cells and pixels it fills) and the
+// protocol it's for
+fn cache_key(source: &Path, metadata: &fs::Metadata, placement: &Placement) -> u64 {
+    let modified = metadata
+        .modified()
+        .ok()
+        .and_then(|modified| modified.duration_since(UNIX_EPOCH).ok())
+        .unwrap_or_default();
+    let (width, height) = placement.pixels.unwrap_or_default();
+    let key = format!(
+        "{}\n{}.{}\n{}\n{}\n{}x{}\n{}x{}\nkitty",
+        source.display(),
+        modified.as_secs(),
+        modified.subsec_nanos(),
+        metadata.len(),
+        if placement.resample { "auto" } else { "off" },
+        placement.columns,
+        placement.rows,
+        width,
+        height
+    );
+    key.bytes()
+        .fold(0xcbf29ce484222325, |hash, byte| (hash ^ byte as u64).wrapping_mul(0x100000001b3))
+}
+
+// Remove the least recently used PNGs in `dir` until they're under `cap` bytes, `keep` (the
+// one just made) staying whatever its size
+fn evict(dir: &Path, cap: u64, keep: &Path) {
+    let Ok(entries) = fs::read_dir(dir) else {
+        return;
+    };
+    let mut images: Vec<(SystemTime, u64, PathBuf)> = entries
+        .filter_map(|entry| {
+            let entry = entry.ok()?;
+            let metadata = entry.metadata().ok()?;
+            let path = entry.path();
+            (metadata.is_file() && path.extension()? == "png")
+                .then_some((metadata.modified().ok()?, metadata.len(), path))
+        })
+        .collect();
+    images.sort();
+
+    let mut total: u64 = images.iter().map(|(_, size, _)| size).sum();
+    for (_, size, path) in images {
+        if total <= cap {
+            break;
+        }
+        if path != keep && fs::remove_file(&path).is_ok() {
+            total -= size;
+        }
+    }
+}
+
+// Send a display_image command built with verify and wait for Kitty's answer.
+// Replies look like "\x1b_Gi=7;OK\x1b\\" or "\x1b_Gi=7;ENOENT:No such file\x1b\\".
+pub fn send_verified(command: &str) -> Result<(), String> {
+    let reply = termquery::query(command.as_bytes(), VERIFY_TIMEOUT)
+        .ok_or("no terminal to send the image to")?;
+    kitty_reply(&reply)
+}
+
+// Kitty's answer to a verified command, whatever else came in before it
+fn kitty_reply(reply: &[u8]) -> Result<(), String> {
+    let start = memchr::memmem::find(reply, b"\x1b_G").ok_or("the terminal didn't answer")?;
+    let reply = String::from_utf8_lossy(&reply[start + 3..]);
+    let message = reply
+        .split_once(';')
+        .and_then(|(_, message)| message.split('\x1b').next())
+        .unwrap_or_default();
+    if message == "OK" {
+        Ok(())
+    } else {
+        Err(format!("the terminal rejected the image: {}", message))
+    }
+}
+
+// Ask the terminal itself with a kitty graphics query (a 1x1 pixel that's never stored or
+// drawn). Terminals without the protocol only answer the DA1 behind it. None when there's no
+// terminal to ask. supports_kitty_graphics goes by the environment, doctor shows both.
+pub fn query_kitty_graphics() -> Option<bool> {
+    let query = format!("\x1b_Gi={},s=1,v=1,a=q,t=d,f=24;AAAA\x1b\\", VERIFY_ID);
+    let reply = termquery::query(query.as_bytes(), VERIFY_TIMEOUT)?;
+    Some(memchr::memmem::find(&reply, format!("_Gi={};OK", VERIFY_ID).as_bytes()).is_some())
+}
+
 /// Check if the terminal supports the Kitty graphics protocol
 pub fn supports_kitty_graphics() -> bool {
     // Check for Kitty
@@ -77,3 +432,220 @@ pub fn supports_kitty_graphics() -> bool {
 pub fn get_default_image_path() -> std::path::PathBuf {
     std::path::PathBuf::from("/home/tui/Rice/Rust Projects/SlowfetchV2/src/assets/default/slowfetch.png")
 }
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::testutil::TempDir;
+
+    const PNG: &[u8] = b"\x89PNG\r\n\x1a\n\0\0\0\rIHDR\0\0\x01\0\0\0\x01\0\x08\x06\0\0\0";
+
+    #[test]
+    fn image_types_by_magic_bytes() {
+        assert_eq!(image_type(PNG), Some("PNG"));
+        assert_eq!(image_type(b"\xFF\xD8\xFF\xE0\0\x10JFIF\0"), Some("JPEG"));
+        assert_eq!(image_type(b"RIFF\x24\x0e\0\0WEBPVP8 "), Some("WebP"));
+        assert_eq!(image_type(b"GIF89a\x01\0\x01\0"), Some("GIF"));
+        // Cut short, or not an image at all
+        assert_eq!(image_type(b"\x89PNG\r\n"), None);
+        assert_eq!(image_type(b"RIFF\x24\x0e\0\0WAVE"), None);
+        assert_eq!(image_type(b"RIFF"), None);
+        assert_eq!(image_type(b"<svg xmlns="), None);
+        assert_eq!(image_type(b""), None);
+    }
+
+    #[test]
+    fn only_images_are_sent() {
+        let dir = TempDir::new();
+        let png = dir.write("logo.png", PNG);
+        let jpeg = dir.write("photo.png", b"\xFF\xD8\xFF\xE1\0\x18Exif");
+        let text = dir.write("notes.png", "not an image\n");
+        let empty = dir.write("empty.png", "");
+
+        assert_eq!(validate_image(&png), Ok("PNG"));
+        // Going by what's in it, whatever it's called
+        assert_eq!(validate_image(&jpeg), Ok("JPEG"));
+        let not_an_image =
+            |path: &Path| format!("{} isn't a PNG, JPEG, WebP or GIF image", path.display());
+        assert_eq!(validate_image(&text), Err(not_an_image(&text)));
+        assert_eq!(validate_image(&empty), Err(not_an_image(&empty)));
+        let missing = validate_image(&dir.join("missing.png")).unwrap_err();
+        assert!(missing.starts_with("Can't read image"), "{}", missing);
+    }
+
+    // 10x20 pixel cells, a 40x20 cell box is 400x400 pixels
+    #[test]
+    fn pixel_box_from_the_cell_size() {
+        let fit = |image| {
+            let placement = fit(image, (10, 20), 40, 20);
+            (placement.pixels, placement.columns, placement.rows, placement.offset)
+        };
+        // Wide: as wide as the box, 10 of the 20 rows, centered vertically
+        assert_eq!(fit((1000, 500)), (Some((400, 200)), 40, 0, (0, 5)));
+        // Tall: as tall as the box, 14 of the 40 columns (133 pixels)
+        assert_eq!(fit((300, 900)), (Some((133, 400)), 0, 20, (13, 0)));
+        // Exactly the box
+        assert_eq!(fit((800, 800)), (Some((400, 400)), 40, 0, (0, 0)));
+        // Small enough already, shown at its own size with nothing resampled
+        assert_eq!(fit((200, 100)), (None, 0, 0, (10, 7)));
+        assert_eq!(fit((400, 400)), (None, 0, 0, (0, 0)));
+        // A sliver still gets a pixel, and a row
+        assert_eq!(fit((4000, 1)), (Some((400, 1)), 40, 0, (0, 9)));
+    }
+
+    // A small JPEG, as a photo used for the art would be
+    fn jpeg(dir: &TempDir) -> PathBuf {
+        let path = dir.join("photo.jpg");
+        let pixels =
+            ::image::RgbImage::from_fn(48, 32, |x, y| ::image::Rgb([x as u8 * 5, y as u8 * 7, 90]));
+        pixels.save(&path).unwrap();
+        path
+    }
+
+    // The first run decodes the JPEG and keeps the PNG, the second sends that without decoding
+    #[test]
+    fn converted_images_are_cached() {
+        let dir = TempDir::new();
+        let source = jpeg(&dir);
+        let cache = dir.join("images");
+        fs::create_dir(&cache).unwrap();
+
+        // Counted for this thread, tests decoding alongside don't move it
+        let decodes = || DECODES.get();
+        let before = decodes();
+
+        let stretched_over = |columns, rows| Placement {
+            columns,
+            rows,
+            offset: (0, 0),
+            pixels: None,
+            resample: false,
+        };
+        let converted = |placement: &Placement, refresh| {
+            convert_in(&cache, &source, placement, refresh).unwrap().path
+        };
+        let stretched = stretched_over(40, 20);
+        let first = converted(&stretched, false);
+        assert_eq!(decodes(), before + 1);
+        let written = fs::read(&first).unwrap();
+        assert_eq!(image_type(&written), Some("PNG"));
+        assert_eq!(::image::load_from_memory(&written).unwrap().width(), 48);
+
+        assert_eq!(converted(&stretched, false), first);
+        assert_eq!(decodes(), before + 1);
+        // --refresh converts it again, into the same file
+        assert_eq!(converted(&stretched, true), first);
+        assert_eq!(decodes(), before + 2);
+
+        // An edited image is a different one
+        let later = SystemTime::now() + Duration::from_secs(60);
+        File::options().write(true).open(&source).unwrap().set_modified(later).unwrap();
+        let edited = converted(&stretched, false);
+        assert_ne!(edited, first);
+        assert_eq!(decodes(), before + 3);
+
+        // Resampled to a pixel box it's a different file again, at exactly that size
+        assert_eq!(image_dimensions(&source), Some((48, 32)));
+        let resampled_to = |width, height| Placement {
+            columns: 3,
+            rows: 0,
+            offset: (0, 0),
+            pixels: Some((width, height)),
+            resample: true,
+        };
+        let resampled = converted(&resampled_to(30, 20), false);
+        assert_eq!(decodes(), before + 4);
+        assert_eq!(image_dimensions(&resampled), Some((30, 20)));
+        assert_eq!(converted(&resampled_to(30, 20), false), resampled);
+        assert_eq!(decodes(), before + 4);
+
+        // Fitted by its real size, or stretched over another box, isn't the same render
+        let fitted = Placement { resample: true, ..stretched_over(40, 20) };
+        assert_ne!(converted(&fitted, false), edited);
+        assert_ne!(converted(&stretched_over(20, 10), false), edited);
+        assert_eq!(decodes(), before + 6);
+
+        let broken = dir.write("broken.jpg", b"\xFF\xD8\xFF\xE0 and then nothing");
+        let error = convert_in(&cache, &broken, &stretched, false).err().unwrap();
+        assert!(error.starts_with("Can't decode"), "{}", error);
+    }
+
+    // Another slowfetch converting the same image holds the cache's lock: without its file
+    // the image is made outside the cache after CONVERT_WAIT, with it that's sent as soon as
+    // it's there
+    #[test]
+    fn a_held_lock_waits_for_the_other_conversion() {
+        let dir = TempDir::new();
+        let source = jpeg(&dir);
+        let cache = dir.join("images");
+        fs::create_dir(&cache).unwrap();
+        let stretched =
+            Placement { columns: 40, rows: 20, offset: (0, 0), pixels: None, resample: false };
+        let held = cache::try_lock(dir.path()).unwrap();
+
+        let started = Instant::now();
+        let scratch = convert_in(&cache, &source, &stretched, false).unwrap();
+        assert!(started.elapsed() >= CONVERT_WAIT);
+        assert_eq!(scratch.medium, kitty_image::Medium::TemporaryFile);
+        assert!(scratch.path.to_string_lossy().contains("tty-graphics-protocol"));
+        assert_eq!(image_dimensions(&scratch.path), Some((48, 32)));
+        assert_eq!(fs::read_dir(&cache).unwrap().count(), 0);
+
+        let key = cache_key(&source, &fs::metadata(&source).unwrap(), &stretched);
+        let path = cache.join(format!("{:016x}.png", key));
+        let started = Instant::now();
+        std::thread::scope(|scope| {
+            let waiting = scope.spawn(|| {
+                let converted = convert_in(&cache, &source, &stretched, false).unwrap();
+                (converted.path, converted.medium, DECODES.get())
+            });
+            // The other run's file, renamed into place as it would be
+            std::thread::sleep(5 * CONVERT_RETRY);
+            fs::rename(&scratch.path, path.with_extension("tmp.42")).unwrap();
+            fs::rename(path.with_extension("tmp.42"), &path).unwrap();
+            assert_eq!(waiting.join().unwrap(), (path.clone(), kitty_image::Medium::File, 0));
+        });
+        assert!(started.elapsed() < CONVERT_WAIT);
+        drop(held);
+    }
+
+    #[test]
+    fn the_image_cache_keeps_under_its_cap() {
+        let dir = TempDir::new();
+        let hour = Duration::from_secs(3600);
+        let old = SystemTime::now() - 10 * hour;
+        // 100 bytes each, last used an hour apart, oldest first
+        for (i, name) in ["a.png", "b.png", "c.png", "d.png"].into_iter().enumerate() {
+            let path = dir.write(name, "x".repeat(100));
+            let used = old + i as u32 * hour;
+            File::options().write(true).open(&path).unwrap().set_modified(used).unwrap();
+        }
+        dir.write("e.png.tmp.42", "x".repeat(500));
+
+        let left = |dir: &TempDir| {
+            let mut names: Vec<String> = fs::read_dir(dir.path())
+                .unwrap()
+                .map(|entry| entry.unwrap().file_name().to_string_lossy().into_owned())
+                .collect();
+            names.sort();
+            names
+        };
+        evict(dir.path(), 250, &dir.join("d.png"));
+        assert_eq!(left(&dir), ["c.png", "d.png", "e.png.tmp.42"]);
+        // The one just made stays even when it's the oldest
+        evict(dir.path(), 50, &dir.join("c.png"));
+        assert_eq!(left(&dir), ["c.png", "e.png.tmp.42"]);
+    }
+
+    #[test]
+    fn kitty_answers() {
+        assert_eq!(kitty_reply(b"\x1b_Gi=7;OK\x1b\\"), Ok(()));
+        // Something else the terminal sent first doesn't get in the way
+        assert_eq!(kitty_reply(b"\x1b[?62;4c\x1b_Gi=7;OK\x1b\\"), Ok(()));
+        assert_eq!(
+            kitty_reply(b"\x1b_Gi=7;ENOENT:No such file\x1b\\"),
+            Err("the terminal rejected the image: ENOENT:No such file".to_string())
+        );
+        assert_eq!(kitty_reply(b"\x1b[?62;4c"), Err("the terminal didn't answer".to_string()));
+    }
+}
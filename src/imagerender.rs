@@ -1,24 +1,94 @@
 // Image rendering module for Slowfetch
 // Handles layout and display of images using the Kitty graphics protocol
 
-use crate::renderer::{build_box, build_sections_lines, visible_len, Section};
+use crate::configloader::Margins;
+use crate::image::{self, ImageOptions};
+use crate::renderer::{
+    apply_margins, build_box, build_sections_lines, footer_line, visible_len, Section,
+};
 use crate::terminalsize::get_terminal_size;
 
+// Smallest image worth showing, in cells. Below this the image is left out, a zero sized
+// placement would have some terminals draw the image at full size over everything.
+const MIN_IMAGE_COLS: usize = 8;
+const MIN_IMAGE_ROWS: usize = 4;
+
+// Where the image goes inside its empty box, in cells: the area it's fitted into and how
+// far in from the box's left edge that area starts
+struct ImageArea {
+    columns: usize,
+    rows: usize,
+    inset: usize,
+}
+
 // Draw a side-by-side or vertically stacked layout with an image placeholder.
 // The image is rendered using Kitty graphics protocol after the box layout is printed.
 // Cursor positioning is used to overlay the image inside the empty box.
-pub fn draw_image_layout(sections: &[Section], image_path: &std::path::Path) {
-    // --- step 1: Get terminal dimensions ---
+// With no image_path only the boxes are drawn, keeping an image that's already on screen
+// (--watch transmits it on the first frame only).
+pub fn draw_image_layout(
+    sections: &[Section],
+    image_path: Option<&std::path::Path>,
+    header: Option<&str>,
+    footer: Option<&str>,
+    margins: Margins,
+    options: ImageOptions,
+) {
+    use std::io::Write;
+
+    // --- step 1: Get terminal dimensions, minus the space margins take up ---
     let (terminal_width, terminal_height) = get_terminal_size()
         .map(|(cols, rows)| (cols as usize, rows as usize))
         .unwrap_or((80, 24)); // Fallback to standard 80x24 terminal
+    let terminal_width = terminal_width.saturating_sub(margins.left);
+    let terminal_height = terminal_height.saturating_sub(margins.top + margins.bottom);
+    let terminal_height = terminal_height
+        .saturating_sub(header.map_or(0, |_| 1))
+        .saturating_sub(footer.map_or(0, |_| 1));
+
+    // Top margin and header go out before anything else so cursor math below only covers the layout
+    print!("{}", "\n".repeat(margins.top));
+    if let Some(header) = header {
+        println!("{}{}", " ".repeat(margins.left), header);
+    }
+
+    // --- steps 2-4: Size the boxes and pick the layout ---
+    let (mut output, image_area) =
+        image_layout(sections, terminal_width, terminal_height, options.max_width);
+
+    // --- step 5: Footer, centered under everything ---
+    if let Some(footer) = footer {
+        let layout_width = output.lines().map(visible_len).max().unwrap_or(0);
+        output.push_str(&footer_line(footer, layout_width, terminal_width));
+        output.push('\n');
+    }
+
+    // --- step 6: Print the layout, then overlay the image on its empty box ---
+    print!("{}", indent(&output, margins.left));
+    let _ = std::io::stdout().flush();
+
+    if let (Some(image_path), Some(image_area)) = (image_path, image_area) {
+        place_image(image_path, image_area, output.lines().count(), margins.left, options);
+    }
+
+    print!("{}", "\n".repeat(margins.bottom));
+}
 
+// Lay out the sections next to or above an empty image box in a terminal of the given size
+// (margins, header and footer already taken off). The image area is None when the image
+// doesn't fit anywhere, otherwise it's at least MIN_IMAGE_COLS x MIN_IMAGE_ROWS.
+fn image_layout(
+    sections: &[Section],
+    terminal_width: usize,
+    terminal_height: usize,
+    max_width: usize,
+) -> (String, Option<ImageArea>) {
     // --- step 2: Calculate sections dimensions ---
     // Each line is "Key: Value", so width = key_len + 2 (": ") + value_len
     let sections_content_width = sections
         .iter()
         .flat_map(|section| {
-            std::iter::once(section.title.chars().count()).chain(
+            std::iter::once(visible_len(&section.title)).chain(
                 section
                     .lines
                     .iter()
@@ -39,42 +109,49 @@ pub fn draw_image_layout(sections: &[Section], image_path: &std::path::Path) {
 
     // --- step 3: Calculate image box dimensions ---
     // Image box should be roughly square based on sections height
-    // Terminal cells are typically ~2:1 height:width ratio, so multiply height by 2
-    let image_content_width = (sections_total_height as f64 * 2.0) as usize;
-    let image_box_width = image_content_width + 4; // Add borders + margins
-
-    // Total width needed for side-by-side layout: image_box + gap + sections_box
-    let side_by_side_total_width = image_box_width + 1 + sections_box_width;
+    // Terminal cells are typically ~2:1 height:width ratio, so multiply height by 2.
+    // Tall sections would make that huge, so it's capped at image_max_width, and shrunk
+    // to the room left next to the sections (image box = content + 4, plus a 1 column gap)
+    let max_width = max_width.max(MIN_IMAGE_COLS);
+    let room = terminal_width.saturating_sub(sections_box_width + 1 + 4);
+    let image_content_width =
+        (sections_total_height * 2).clamp(MIN_IMAGE_COLS, max_width).min(room);
+    // The image box is as tall as the sections, minus its borders
+    let image_rows = sections_total_height.saturating_sub(2);
 
     // --- step 4: Choose layout based on terminal width ---
-    if terminal_width >= side_by_side_total_width {
+    if image_content_width >= MIN_IMAGE_COLS && image_rows >= MIN_IMAGE_ROWS {
         // layout 1: Side-by-side (image on left, sections on right)
-        render_side_by_side_with_image(
-            sections,
-            image_path,
-            image_content_width,
-        );
+        render_side_by_side_with_image(sections, image_content_width)
     } else {
         // layout 2: Stacked (image on top, sections below) or sections only
         render_stacked_with_image(
             sections,
-            image_path,
             sections_content_width,
             sections_total_height,
-            terminal_height,
-        );
+            (terminal_width, terminal_height),
+            max_width,
+        )
     }
 }
 
-// ender side-by-side layout: empty image box on left, sections on right.
-// After printing the layout, cursor is repositioned to overlay the image.
+// Indent the layout by the left margin only (top/bottom are printed separately)
+fn indent(output: &str, margin_left: usize) -> String {
+    apply_margins(
+        output,
+        Margins {
+            left: margin_left,
+            ..Margins::default()
+        },
+    )
+}
+
+// Render side-by-side layout: empty image box on left, sections on right.
+// Returns the layout and where the image goes for place_image.
 fn render_side_by_side_with_image(
     sections: &[Section],
-    image_path: &std::path::Path,
     image_content_width: usize,
-) {
-    use std::io::Write;
-
+) -> (String, Option<ImageArea>) {
     // --- step 1: Build the sections box ---
     let sections_box = build_sections_lines(sections, None);
     let sections_box_height = sections_box.len();
@@ -115,64 +192,91 @@ fn render_side_by_side_with_image(
         output.push('\n');
     }
 
-    // --- step 4: Print layout and position cursor for image ---
-    let total_output_lines = output.lines().count();
-    let image_display_cols = image_content_width;
-    let image_display_rows = sections_box_height.saturating_sub(2); // Subtract borders
+    let image_area = ImageArea {
+        columns: image_content_width,
+        rows: sections_box_height.saturating_sub(2), // Subtract borders
+        inset: 0,
+    };
+    (output, Some(image_area))
+}
 
-    // Print the box layout first
-    print!("{}", output);
-    let _ = std::io::stdout().flush();
+// Overlay the image on the empty box. The cursor position at the end of the layout is saved,
+// then it moves up to the box's inside top left corner for the image, and comes back after,
+// so the prompt lands below the layout whether the image made it or not.
+fn place_image(
+    image_path: &std::path::Path,
+    area: ImageArea,
+    total_output_lines: usize,
+    margin_left: usize,
+    options: ImageOptions,
+) {
+    use std::io::Write;
 
-    // Move cursor up to the top of the image box area
-    // ANSI escape: \x1b[nA = move cursor up n lines
-    print!("\x1b[{}A", total_output_lines - 1);
-    // Move cursor right to skip the left border
-    // ANSI escape: \x1b[nC = move cursor right n columns
-    print!("\x1b[2C");
+    // The layouts only hand out areas of at least MIN_IMAGE_COLS x MIN_IMAGE_ROWS,
+    // never ask the terminal for an empty one
+    if area.columns < MIN_IMAGE_COLS || area.rows < MIN_IMAGE_ROWS {
+        return;
+    }
+    let (cols, rows) = (area.columns.min(u16::MAX as usize), area.rows.min(u16::MAX as usize));
+    let placement = image::placement(image_path, cols as u16, rows as u16, options.resample);
+    let (offset_cols, offset_rows) = placement.offset;
+
+    // ANSI escapes: \x1b7 = save cursor, \x1b[nA = move cursor up n lines,
+    // \x1b[nC = move cursor right n columns (skip the margin and left border)
+    let up = total_output_lines.saturating_sub(offset_rows);
+    let right = margin_left + 2 + area.inset + offset_cols;
+    print!("\x1b7\x1b[{}A\x1b[{}C", up, right);
     let _ = std::io::stdout().flush();
 
-    // --- step 5: Display the image using Kitty protocol ---
-    match crate::image::display_image(image_path, image_display_cols as u16, image_display_rows as u16) {
-        Ok(image_output) => {
-            print!("{}", image_output);
-            let _ = std::io::stdout().flush();
-        }
-        Err(image_error) => eprintln!("Image error: {}", image_error),
-    }
+    let result = image::display_image(image_path, &placement, options.verify)
+        .and_then(|image_output| {
+            if options.verify {
+                image::send_verified(&image_output)
+            } else {
+                print!("{}", image_output);
+                Ok(())
+            }
+        });
 
-    // --- step 6: Move cursor back down to after the layout ---
-    // ANSI escape: \x1b[nB = move cursor down n lines
-    println!("\x1b[{}B", total_output_lines);
+    // \x1b8 = restore cursor, back below the layout
+    println!("\x1b8");
     let _ = std::io::stdout().flush();
+    if let Err(image_error) = result {
+        eprintln!("Image error: {}", image_error);
+    }
 }
 
 // Render stacked layout: image box on top, sections below.
-// Falls back to sections-only if terminal is too small.
+// Falls back to sections-only (and no image) if terminal is too small.
 fn render_stacked_with_image(
     sections: &[Section],
-    image_path: &std::path::Path,
     sections_content_width: usize,
     sections_total_height: usize,
-    terminal_height: usize,
-) {
-    use std::io::Write;
-
+    (terminal_width, terminal_height): (usize, usize),
+    max_width: usize,
+) -> (String, Option<ImageArea>) {
     // --- step 1: Calculate image box dimensions for stacked layout ---
-    // Image box width matches sections width for visual consistency
+    // Image box width matches sections width for visual consistency,
+    // the image itself is capped at image_max_width and centered in it
     let image_content_width = sections_content_width;
+    let image_columns = image_content_width.min(max_width);
 
     // Calculate image box height to maintain ~1:1 aspect ratio
     // Terminal cells are ~2:1 height:width, so divide total visual width by 2
     // Visual width = content + 6 (2 borders + 2 margins + 2 for padding)
-    let image_box_total_height = ((sections_content_width + 6) as f64 / 2.0).ceil() as usize;
+    let image_box_total_height = (image_columns + 6).div_ceil(2);
     let image_content_height = image_box_total_height.saturating_sub(2); // Subtract borders
 
     // --- step 2: Check if we have enough vertical space ---
     let stacked_total_height = image_box_total_height + sections_total_height;
 
-    // Minimum content width of 8 ensures image is visible
-    if terminal_height >= stacked_total_height && image_content_width > 8 {
+    // Too small an image isn't worth the space (or a zero sized placement), and a box the
+    // terminal wraps would put the image somewhere else entirely
+    if terminal_height >= stacked_total_height
+        && terminal_width >= image_content_width + 4
+        && image_columns >= MIN_IMAGE_COLS
+        && image_content_height >= MIN_IMAGE_ROWS
+    {
         // --- step 3: Build image box (empty placeholder) ---
         let empty_content: Vec<String> = Vec::new();
         let image_box = build_box(
@@ -201,36 +305,87 @@ fn render_stacked_with_image(
             output.push('\n');
         }
 
-        // --- step 6: Print layout and position cursor for image ---
-        let total_output_lines = output.lines().count();
+        let image_area = ImageArea {
+            columns: image_columns,
+            rows: image_content_height,
+            inset: (image_content_width - image_columns) / 2,
+        };
+        (output, Some(image_area))
+    } else {
+        // --- fallback: Terminal too small, show sections only ---
+        let sections_box = build_sections_lines(sections, None);
+        let mut output = String::new();
+        for line in &sections_box {
+            output.push_str(line);
+            output.push('\n');
+        }
+        (output, None)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use std::path::Path;
 
-        print!("{}", output);
-        let _ = std::io::stdout().flush();
+    // A section of `count` lines, each "keyN: value"
+    fn section(count: usize) -> Section {
+        let lines = (0..count).map(|i| (format!("key{}", i), "value".to_string())).collect();
+        Section::new("Core", lines)
+    }
 
-        // Move cursor up to the top of the image box
-        print!("\x1b[{}A", total_output_lines - 1);
-        // Move cursor right to skip the left border
-        print!("\x1b[2C");
-        let _ = std::io::stdout().flush();
+    // Every image area handed out is big enough to send, whatever the terminal size
+    fn area_ok(area: &Option<ImageArea>) {
+        if let Some(area) = area {
+            assert!(area.columns >= MIN_IMAGE_COLS && area.rows >= MIN_IMAGE_ROWS);
+            let (columns, rows) = (area.columns as u16, area.rows as u16);
+            let placement = image::placement(Path::new("x.png"), columns, rows, false);
+            assert!(placement.columns > 0 && placement.rows > 0);
+        }
+    }
 
-        // --- step 7: Display the image ---
-        match crate::image::display_image(image_path, image_content_width as u16, image_content_height as u16) {
-            Ok(image_output) => {
-                print!("{}", image_output);
-                let _ = std::io::stdout().flush();
-            }
-            Err(image_error) => eprintln!("Image error: {}", image_error),
+    #[test]
+    fn degenerate_sizes_place_no_image() {
+        // No sections at all
+        let (output, area) = image_layout(&[], 80, 24, 40);
+        assert!(area.is_none());
+        assert!(output.lines().all(|line| visible_len(line) <= 80));
+        // A one line terminal, a one column one and nothing at all
+        for (width, height) in [(80, 1), (1, 24), (0, 0)] {
+            let (_, area) = image_layout(&[section(1)], width, height, 40);
+            assert!(area.is_none(), "{}x{}", width, height);
         }
+        // image_max_width of 0 still means the smallest image, not an empty one
+        let (_, area) = image_layout(&[section(6)], 120, 40, 0);
+        assert_eq!(area.map(|area| area.columns), Some(MIN_IMAGE_COLS));
+    }
 
-        // --- step 8: Move cursor back down ---
-        println!("\x1b[{}B", total_output_lines);
-        let _ = std::io::stdout().flush();
-    } else {
-        // --- fallback: Terminal too small, show sections only ---
-        let sections_box = build_sections_lines(sections, None);
+    #[test]
+    fn tall_sections_stay_side_by_side() {
+        // 30 lines used to ask for a 64 column image and fall back to stacked
+        let (output, area) = image_layout(&[section(30)], 120, 40, 40);
+        let area = area.unwrap();
+        assert_eq!((area.columns, area.rows, area.inset), (40, 30, 0));
+        assert_eq!(output.lines().count(), 32);
+        // Less room next to the sections shrinks the image instead
+        let (_, area) = image_layout(&[section(30)], 60, 40, 40);
+        assert_eq!(area.unwrap().columns, 60 - (12 + 4) - 1 - 4);
+    }
 
-        for line in &sections_box {
-            println!("{}", line);
+    #[test]
+    fn every_size_gives_a_usable_area() {
+        for count in [0, 1, 2, 3, 5, 12, 40] {
+            for width in [0, 1, 10, 30, 45, 80, 200] {
+                for height in [0, 1, 4, 6, 24, 60] {
+                    let (output, area) = image_layout(&[section(count)], width, height, 40);
+                    area_ok(&area);
+                    if let Some(area) = area {
+                        // The image fits inside the box it was given
+                        let box_width = output.lines().map(visible_len).max().unwrap_or(0);
+                        assert!(area.inset + area.columns + 4 <= box_width);
+                    }
+                }
+            }
         }
     }
 }
@@ -0,0 +1,381 @@
+// Just enough gzip to read pacman's sync databases without a zlib: the gzip header is skipped
+// and the deflate stream (RFC 1951) inflated into memory. The CRC isn't checked, a damaged
+// file fails to inflate or gives garbage names, and the worst that does is a wrong count.
+
+// Length codes 257..285: base length and extra bits
+const LENGTH_BASE: [u16; 29] = [
+    3, 4, 5, 6, 7, 8, 9, 10, 11, 13, 15, 17, 19, 23, 27, 31, 35, 43, 51, 59, 67, 83, 99, 115, 131,
+    163, 195, 227, 258,
+];
+const LENGTH_EXTRA: [u8; 29] =
+    [0, 0, 0, 0, 0, 0, 0, 0, 1, 1, 1, 1, 2, 2, 2, 2, 3, 3, 3, 3, 4, 4, 4, 4, 5, 5, 5, 5, 0];
+// Distance codes 0..29: base distance and extra bits
+const DIST_BASE: [u16; 30] = [
+    1, 2, 3, 4, 5, 7, 9, 13, 17, 25, 33, 49, 65, 97, 129, 193, 257, 385, 513, 769, 1025, 1537,
+    2049, 3073, 4097, 6145, 8193, 12289, 16385, 24577,
+];
+const DIST_EXTRA: [u8; 30] = [
+    0, 0, 0, 0, 1, 1, 2, 2, 3, 3, 4, 4, 5, 5, 6, 6, 7, 7, 8, 8, 9, 9, 10, 10, 11, 11, 12, 12, 13,
+    13,
+];
+// The order a dynamic block lists its code length code lengths in
+const CLEN_ORDER: [usize; 19] = [16, 17, 18, 0, 8, 7, 9, 6, 10, 5, 11, 4, 12, 3, 13, 2, 14, 1, 15];
+
+// Codes up to this long are looked up in one go, longer ones are walked a bit at a time
+const FAST_BITS: u32 = 9;
+
+// Inflate a .gz file, None when it isn't gzip or the stream is broken.
+// `limit` caps the inflated size, a sync database is tens of MB at most.
+pub fn gunzip(data: &[u8], limit: usize) -> Option<Vec<u8>> {
+    if data.get(..3)? != [0x1f, 0x8b, 8] {
+        return None;
+    }
+    let flags = *data.get(3)?;
+    let mut pos = 10;
+    // FEXTRA, then the zero terminated FNAME and FCOMMENT, then FHCRC
+    if flags & 4 != 0 {
+        let extra = u16::from_le_bytes([*data.get(pos)?, *data.get(pos + 1)?]);
+        pos += 2 + usize::from(extra);
+    }
+    for flag in [8, 16] {
+        if flags & flag != 0 {
+            pos += data.get(pos..)?.iter().position(|&b| b == 0)? + 1;
+        }
+    }
+    if flags & 2 != 0 {
+        pos += 2;
+    }
+    inflate(data.get(pos..)?, limit)
+}
+
+// Inflate a raw deflate stream
+pub fn inflate(data: &[u8], limit: usize) -> Option<Vec<u8>> {
+    let mut bits = Bits { data, pos: 0, buffer: 0, count: 0 };
+    let mut out = Vec::new();
+    loop {
+        let last = bits.take(1)? == 1;
+        match bits.take(2)? {
+            0 => stored(&mut bits, &mut out)?,
+            1 => {
+                let (literals, distances) = fixed_tables();
+                codes(&mut bits, &mut out, &literals, &distances, limit)?
+            }
+            2 => {
+                let (literals, distances) = dynamic_tables(&mut bits)?;
+                codes(&mut bits, &mut out, &literals, &distances, limit)?
+            }
+            _ => return None,
+        }
+        if out.len() > limit {
+            return None;
+        }
+        if last {
+            return Some(out);
+        }
+    }
+}
+
+// Deflate reads its bits least significant first
+struct Bits<'a> {
+    data: &'a [u8],
+    pos: usize,
+    buffer: u64,
+    count: u32,
+}
+
+impl Bits<'_> {
+    fn fill(&mut self) {
+        while self.count <= 56 {
+            let Some(&byte) = self.data.get(self.pos) else {
+                break;
+            };
+            self.buffer |= u64::from(byte) << self.count;
+            self.pos += 1;
+            self.count += 8;
+        }
+    }
+
+    fn take(&mut self, n: u32) -> Option<u32> {
+        if self.count < n {
+            self.fill();
+            if self.count < n {
+                return None;
+            }
+        }
+        let value = (self.buffer & ((1 << n) - 1)) as u32;
+        self.buffer >>= n;
+        self.count -= n;
+        Some(value)
+    }
+
+    // Stored blocks start on a byte boundary
+    fn align(&mut self) {
+        let partial = self.count % 8;
+        self.buffer >>= partial;
+        self.count -= partial;
+    }
+}
+
+// A canonical Huffman code, from the code length of every symbol
+struct Huffman {
+    // How many codes there are of each length
+    counts: [u16; 16],
+    // Symbols in code order
+    symbols: Vec<u16>,
+    // Indexed by the next FAST_BITS bits: (symbol, code length), length 0 for longer codes
+    fast: Vec<(u16, u8)>,
+}
+
+impl Huffman {
+    fn new(lengths: &[u8]) -> Huffman {
+        let mut counts = [0u16; 16];
+        for &length in lengths {
+            counts[usize::from(length)] += 1;
+        }
+        counts[0] = 0;
+
+        let mut offsets = [0u16; 16];
+        let mut next_code = [0u32; 16];
+        let mut code = 0;
+        for length in 1..16 {
+            code = (code + u32::from(counts[length - 1])) << 1;
+            next_code[length] = code;
+            if length < 15 {
+                offsets[length + 1] = offsets[length] + counts[length];
+            }
+        }
+
+        let mut symbols = vec![0; lengths.len()];
+        let mut fast = vec![(0, 0); 1 << FAST_BITS];
+        for (symbol, &length) in lengths.iter().enumerate() {
+            let length = usize::from(length);
+            if length == 0 {
+                continue;
+            }
+            symbols[usize::from(offsets[length])] = symbol as u16;
+            offsets[length] += 1;
+
+            let code = next_code[length];
+            next_code[length] += 1;
+            if length as u32 <= FAST_BITS {
+                // Codes are sent most significant bit first, the buffer reads least first
+                let reversed = (code.reverse_bits() >> (32 - length)) as usize;
+                for index in (reversed..fast.len()).step_by(1 << length) {
+                    fast[index] = (symbol as u16, length as u8);
+                }
+            }
+        }
+        Huffman { counts, symbols, fast }
+    }
+
+    fn decode(&self, bits: &mut Bits) -> Option<u16> {
+        bits.fill();
+        let (symbol, length) = self.fast[(bits.buffer & ((1 << FAST_BITS) - 1)) as usize];
+        if length > 0 && u32::from(length) <= bits.count {
+            bits.buffer >>= length;
+            bits.count -= u32::from(length);
+            return Some(symbol);
+        }
+
+        // Longer codes: the first code of each length and the symbols before it
+        let (mut code, mut first, mut index) = (0i32, 0i32, 0i32);
+        for length in 1..16 {
+            code |= bits.take(1)? as i32;
+            let count = i32::from(self.counts[length]);
+            if code - count < first {
+                return self.symbols.get((index + code - first) as usize).copied();
+            }
+            index += count;
+            first = (first + count) << 1;
+            code <<= 1;
+        }
+        None
+    }
+}
+
+fn stored(bits: &mut Bits, out: &mut Vec<u8>) -> Option<()> {
+    bits.align();
+    let length = bits.take(16)?;
+    if length != !bits.take(16)? & 0xffff {
+        return None;
+    }
+    for _ in 0..length {
+        out.push(bits.take(8)? as u8);
+    }
+    Some(())
+}
+
+fn fixed_tables() -> (Huffman, Huffman) {
+    let mut lengths = [0u8; 288];
+    lengths[..144].fill(8);
+    lengths[144..256].fill(9);
+    lengths[256..280].fill(7);
+    lengths[280..].fill(8);
+    (Huffman::new(&lengths), Huffman::new(&[5; 30]))
+}
+
+fn dynamic_tables(bits: &mut Bits) -> Option<(Huffman, Huffman)> {
+    let literals = bits.take(5)? as usize + 257;
+    let distances = bits.take(5)? as usize + 1;
+    let clen_count = bits.take(4)? as usize + 4;
+    let mut clen = [0u8; 19];
+    for &symbol in &CLEN_ORDER[..clen_count] {
+        clen[symbol] = bits.take(3)? as u8;
+    }
+    let clen = Huffman::new(&clen);
+
+    // The literal and distance code lengths, run length encoded
+    let mut lengths = vec![0u8; literals + distances];
+    let mut i = 0;
+    while i < lengths.len() {
+        let (value, repeat) = match clen.decode(bits)? {
+            symbol @ 0..=15 => (symbol as u8, 1),
+            16 => (*lengths.get(i.checked_sub(1)?)?, 3 + bits.take(2)?),
+            17 => (0, 3 + bits.take(3)?),
+            18 => (0, 11 + bits.take(7)?),
+            _ => return None,
+        };
+        let end = i + repeat as usize;
+        lengths.get_mut(i..end)?.fill(value);
+        i = end;
+    }
+    Some((Huffman::new(&lengths[..literals]), Huffman::new(&lengths[literals..])))
+}
+
+// One block's literals and back references, up to the end of block code
+fn codes(
+    bits: &mut Bits,
+    out: &mut Vec<u8>,
+    literals: &Huffman,
+    distances: &Huffman,
+    limit: usize,
+) -> Option<()> {
+    loop {
+        let symbol = literals.decode(bits)?;
+        let code = match symbol {
+            0..=255 => {
+                out.push(symbol as u8);
+                continue;
+            }
+            256 => return Some(()),
+            _ => usize::from(symbol - 257),
+        };
+        let extra = bits.take(u32::from(*LENGTH_EXTRA.get(code)?))? as usize;
+        let length = usize::from(LENGTH_BASE[code]) + extra;
+        let code = usize::from(distances.decode(bits)?);
+        let extra = bits.take(u32::from(*DIST_EXTRA.get(code)?))? as usize;
+        let start = out.len().checked_sub(usize::from(DIST_BASE[code]) + extra)?;
+        // The copy can overlap what it's writing, byte by byte does the right thing
+        for i in start..start + length {
+            out.push(out[i]);
+        }
+        if out.len() > limit {
+            return None;
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn hex(text: &str) -> Vec<u8> {
+        let byte = |i| u8::from_str_radix(&text[i..i + 2], 16).unwrap();
+        (0..text.len()).step_by(2).map(byte).collect()
+    }
+
+    // zlib's raw deflate (wbits -15) of each, one block of each type
+    const STORED: &str = "010900f6ff736c6f776665746368";
+    const FIXED: &str = "4b4c4a4e44423a0a699915a9290a1ea56969b9897900";
+    const DYNAMIC: &str = "65d23b0ac3400c45d13e7bb13c4f9aef728c93d2c190fd438a1401ddf636efa099fb38\
+                           afe3bd152b9bf6e7eb733eee5f912915374f252c52a9565369d652e9d65319589f585f\
+                           5857c1bc847d39000a0854798006833a11838849c402c20b102e20dcf90c01845720bc\
+                           01e19d8841c424620111859f414084031101445420a201119d8841c42462fd115f";
+    // Two blocks with a sync flush (an empty stored block) between them, the second one
+    // copying from the first
+    const FLUSHED: &str = "4acb2c2a2e5148cac94fce5600000000ffff4b43b075148a5393f3f352203c00";
+
+    fn pacman_lines() -> Vec<u8> {
+        (0..40).flat_map(|i| format!("pacman-{}.{}-1/desc\n", i, i % 7).into_bytes()).collect()
+    }
+
+    #[test]
+    fn each_block_type() {
+        // The block type is in bits 1 and 2 of the first byte
+        for (stream, kind) in [(STORED, 0), (FIXED, 1), (DYNAMIC, 2)] {
+            assert_eq!((hex(stream)[0] >> 1) & 3, kind);
+        }
+        assert_eq!(inflate(&hex(STORED), 1024).unwrap(), b"slowfetch");
+        assert_eq!(inflate(&hex(FIXED), 1024).unwrap(), b"abcabcabcabcabc, fixed Huffman");
+        assert_eq!(inflate(&hex(DYNAMIC), 1024).unwrap(), pacman_lines());
+        let flushed = inflate(&hex(FLUSHED), 1024).unwrap();
+        assert_eq!(flushed, b"first block first block, second block");
+    }
+
+    #[test]
+    fn gzip_headers() {
+        let body = hex(FIXED);
+        let trailer = [0u8; 8];
+        // Plain, then with FEXTRA, FNAME, FCOMMENT and FHCRC all set
+        let plain = [&[0x1f, 0x8b, 8, 0, 0, 0, 0, 0, 0, 3][..], &body, &trailer].concat();
+        assert_eq!(gunzip(&plain, 1024).unwrap(), b"abcabcabcabcabc, fixed Huffman");
+        let header = [0x1f, 0x8b, 8, 4 | 8 | 16 | 2, 0, 0, 0, 0, 0, 3, 3, 0, b'x', b'y', b'z'];
+        let names = b"core.db.tar\0repo-add\0\xab\xcd";
+        let full = [&header[..], names, &body, &trailer].concat();
+        assert_eq!(gunzip(&full, 1024).unwrap(), b"abcabcabcabcabc, fixed Huffman");
+
+        // Not gzip, or not deflate inside
+        assert_eq!(gunzip(&plain[1..], 1024), None);
+        assert_eq!(gunzip(b"\x28\xb5\x2f\xfd zstd", 1024), None);
+        let mut method = plain.clone();
+        method[2] = 7;
+        assert_eq!(gunzip(&method, 1024), None);
+        assert_eq!(gunzip(&[0x1f, 0x8b, 8], 1024), None);
+        // A name that's never terminated
+        let unterminated = [&[0x1f, 0x8b, 8, 8, 0, 0, 0, 0, 0, 3][..], b"core.db"].concat();
+        assert_eq!(gunzip(&unterminated, 1024), None);
+    }
+
+    #[test]
+    fn truncated_streams() {
+        for stream in [STORED, FIXED, DYNAMIC, FLUSHED] {
+            let data = hex(stream);
+            for end in 0..data.len() {
+                assert_eq!(inflate(&data[..end], 1024), None, "{} cut at {}", stream, end);
+            }
+        }
+    }
+
+    #[test]
+    fn corrupt_streams() {
+        // Block type 3 doesn't exist
+        assert_eq!(inflate(&[0x07, 0x00], 1024), None);
+        // A stored block whose length and its complement don't match
+        let mut stored = hex(STORED);
+        stored[3] ^= 1;
+        assert_eq!(inflate(&stored, 1024), None);
+        // A fixed block starting with a length 3 copy from one byte back, before there's
+        // anything to copy
+        assert_eq!(inflate(&[0x03, 0x02], 1024), None);
+        // Damage anywhere in the code tables or the data: wrong output or None, never a panic
+        let dynamic = hex(DYNAMIC);
+        for i in 0..dynamic.len() {
+            for flip in [0x01, 0x10, 0xff] {
+                let mut damaged = dynamic.clone();
+                damaged[i] ^= flip;
+                let _ = inflate(&damaged, 4096);
+            }
+        }
+    }
+
+    #[test]
+    fn limit_is_respected() {
+        let dynamic = hex(DYNAMIC);
+        let size = pacman_lines().len();
+        assert_eq!(inflate(&dynamic, size).map(|out| out.len()), Some(size));
+        assert_eq!(inflate(&dynamic, size - 1), None);
+        assert_eq!(inflate(&dynamic, 0), None);
+        assert_eq!(inflate(&hex(STORED), 8), None);
+        assert_eq!(inflate(&hex(STORED), 9).unwrap(), b"slowfetch");
+    }
+}
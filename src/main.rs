@@ -1,222 +1,903 @@
 //Slowfetch by Tūī
 
+mod accessible;
+#[cfg(feature = "art")]
+mod animate;
+mod background;
+mod balance;
 mod cache;
+mod collect;
 mod colorcontrol;
+mod compact;
 mod configloader;
+mod dateformat;
+mod deltas;
+mod doctor;
+mod export;
+#[cfg(test)]
+mod features;
+#[cfg(feature = "cli-extras")]
+mod generate;
+#[cfg(test)]
+mod golden;
 mod helpers;
+#[cfg(feature = "image")]
 mod image;
+#[cfg(feature = "image")]
 mod imagerender;
+mod inflate;
 mod modules;
+mod pool;
 mod renderer;
+mod snapshot;
+mod template;
 mod terminalsize;
+mod termquery;
+#[cfg(test)]
+mod testutil;
+mod watch;
 
-use clap::Parser;
+use clap::{Parser, Subcommand};
 use configloader::OsArtSetting;
-use renderer::Section;
+#[cfg(feature = "art")]
+use modules::asciimodule::{self, ArtSet};
+#[cfg(not(feature = "art"))]
+use renderer::ArtSet;
+use std::io::IsTerminal;
+use std::process::ExitCode;
 use std::thread;
+use std::time::Duration;
+
+// Listed under --help, scripts can count on these
+const EXIT_CODES: &str = "\
+Exit codes:
+  0  Everything shown
+  1  Error (bad arguments, unreadable files, failed export, --warm-cache misses)
+  2  Config problems with --strict or strict_config
+  3  Unknown module names in [order] or [modules] (the fetch is still shown)
+  4  Image mode asked for but the terminal can't show images (art is shown instead)
+  5  Modules missed max_fetch_ms (the fetch is still shown)";
 
 // cmd line args, *claps*
 #[derive(Parser)]
-#[command(name = "slowfetch", about = "A slow system info fetcher")]
+#[command(
+    name = "slowfetch",
+    about = "A slow system info fetcher",
+    version,
+    after_help = EXIT_CODES
+)]
 struct Args {
-    // Display OS-specific art. Optionally specify OS name (example: --os arch)
+    #[command(subcommand)]
+    command: Option<Command>,
+
+    /// Display OS-specific art. Optionally specify OS name or an art file
+    /// (example: --os arch, --os ~/logo.txt)
     #[arg(short = 'o', long = "os", num_args = 0..=1, default_missing_value = "")]
     os_art: Option<String>,
 
-    // Force refresh of cached values (OS name and GPU)
-    #[arg(short = 'r', long = "refresh")]
-    refresh: bool,
+    /// Display art by name, bundled or from art_dir, see --list-art (example: --art archmeme)
+    #[arg(long = "art", conflicts_with_all = ["os_art", "random_art"])]
+    art: Option<String>,
+
+    /// Display a random art every run
+    #[arg(long = "random-art", conflicts_with = "os_art")]
+    random_art: bool,
+
+    /// List the art names --art accepts and exit
+    #[arg(long = "list-art")]
+    list_art: bool,
+
+    /// Show who made Slowfetch and exit
+    #[arg(long = "credits")]
+    credits: bool,
 
-    // Display image instead of ASCII art (uses Kitty graphics protocol)
+    /// Force refresh of cached values (OS name, CPU, GPU, nerd font check), or with
+    /// `--refresh images` only convert the images in ~/.cache/slowfetch/images again
+    #[arg(
+        short = 'r',
+        long = "refresh",
+        value_name = "WHAT",
+        num_args = 0..=1,
+        default_missing_value = "all",
+        value_parser = ["all", "images"]
+    )]
+    refresh: Option<String>,
+
+    /// Display image instead of ASCII art (uses Kitty graphics protocol)
     #[arg(short = 'i', long = "image", num_args = 0..=1, default_missing_value = "")]
     image: Option<String>,
+
+    /// Just OS, kernel, WM and shell in one small box, the same size on any terminal
+    /// (for README screenshots, combines with --export)
+    #[arg(long = "badge", conflicts_with_all = ["watch", "image"])]
+    badge: bool,
+
+    /// Save the collected values to a file to compare against later with --diff
+    #[arg(long = "snapshot", value_name = "FILE", conflicts_with_all = ["watch", "export", "diff"])]
+    snapshot: Option<std::path::PathBuf>,
+
+    /// Show only what changed since a --snapshot ("Kernel: 6.11.2 → 6.12.4")
+    #[arg(long = "diff", value_name = "FILE", conflicts_with_all = ["watch", "export", "badge"])]
+    diff: Option<std::path::PathBuf>,
+
+    /// Lay out for a terminal this many columns wide instead of the real one (for themes and
+    /// layout bug reports)
+    #[arg(long = "simulate-width", value_name = "N")]
+    simulate_width: Option<u16>,
+
+    /// Lay out for a terminal this many rows tall instead of the real one
+    #[arg(long = "simulate-height", value_name = "N")]
+    simulate_height: Option<u16>,
+
+    /// Draw the values from a --snapshot file instead of collecting this system's,
+    /// to reproduce someone else's layout
+    #[arg(
+        long = "simulate-data",
+        value_name = "FILE",
+        conflicts_with_all = ["watch", "image", "snapshot", "diff", "badge", "accessible"]
+    )]
+    simulate_data: Option<std::path::PathBuf>,
+
+    /// Override a color for this run, wins over the config and SLOWFETCH_COLORS_* (repeatable,
+    /// example: --color border=#50FA7B --color art_3=FFB86C)
+    #[arg(long = "color", value_name = "KEY=HEX")]
+    color: Vec<String>,
+
+    /// Screen reader friendly output: plain lines without borders, bars or icons, units written out
+    #[arg(long = "accessible", conflicts_with_all = ["watch", "image", "badge"])]
+    accessible: bool,
+
+    /// Blank space around the output, overrides the margin_* config keys (example: --margin 2)
+    #[arg(long = "margin")]
+    margin: Option<usize>,
+
+    /// Write the output to a file instead of the terminal, as html, svg or ansi
+    /// (example: --export html fetch.html)
+    #[arg(long = "export", num_args = 2, value_names = ["FORMAT", "PATH"])]
+    export: Option<Vec<String>>,
+
+    /// Print the collected values as JSON instead of the layout: every line as text, plus the raw
+    /// numbers behind them. Numbers are named <module>_<unit>, with what's measured in between
+    /// when a module has more than one: uptime_seconds, memory_used_kb, memory_total_kb,
+    /// storage_used_bytes, storage_total_bytes, battery_percent. Load averages are named by
+    /// their window: load_1m, load_5m, load_15m. Values that weren't measured are left out.
+    #[arg(
+        long = "json",
+        conflicts_with_all =
+            ["watch", "export", "snapshot", "diff", "badge", "accessible", "animate"]
+    )]
+    json: bool,
+
+    /// Keep redrawing in place, refreshing memory/storage/uptime etc. every N seconds (default 2)
+    #[arg(
+        short = 'w',
+        long = "watch",
+        num_args = 0..=1,
+        default_missing_value = "2",
+        value_name = "SECONDS",
+        conflicts_with = "export"
+    )]
+    watch: Option<u64>,
+
+    /// Use a [profile.NAME] from the config on top of the regular settings
+    /// (default: $SLOWFETCH_PROFILE)
+    #[arg(long = "profile", value_name = "NAME")]
+    profile: Option<String>,
+
+    /// Exit non-zero on unknown config keys or bad values (same as strict_config = true)
+    #[arg(long = "strict")]
+    strict: bool,
+
+    /// Rename old keys and module ids in the config file to the current names (the old file is
+    /// kept as config.toml.bak)
+    #[arg(long = "migrate-config")]
+    migrate_config: bool,
+
+    /// Refresh the caches without printing a fetch, for login hooks and systemd user services
+    #[arg(long = "warm-cache")]
+    warm_cache: bool,
+
+    /// Print nothing unless something goes wrong. Modules are still collected and cached,
+    /// so the exit code works as a health check
+    #[arg(short = 'q', long = "quiet", conflicts_with_all = ["verbose", "watch"])]
+    quiet: bool,
+
+    /// Print more detail (with --warm-cache: every refreshed value, otherwise cache hits/misses)
+    #[arg(short = 'v', long = "verbose")]
+    verbose: bool,
+
+    /// Cycle the art's colors for a few seconds before settling (any key stops it)
+    #[arg(long = "animate", conflicts_with_all = ["watch", "export", "badge", "accessible"])]
+    animate: bool,
+
+    /// Print how long each slow module took, slowest first, each cached module's hit or miss
+    /// ("gpu: cache hit (age 3d)") and what writing the cache took (on stderr)
+    #[arg(long = "timings")]
+    timings: bool,
+
+    /// Print shell completions or the man page and exit
+    /// (example: --generate completions zsh, --generate man)
+    #[cfg(feature = "cli-extras")]
+    #[arg(long = "generate", num_args = 1..=2, value_names = ["WHAT", "SHELL"])]
+    generate: Option<Vec<String>>,
 }
 
-fn main() {
-    let args = Args::parse();
+#[derive(Subcommand)]
+enum Command {
+    /// Check every module, GPU probe, path and terminal feature and print what works and what
+    /// doesn't, with hints (handy for bug reports)
+    Doctor,
+}
 
-    // Set cache refresh flag if --refresh/-r was passed
-    if args.refresh {
-        cache::set_force_refresh(true);
-    }
+// Run the cached (slow) collectors fresh in parallel and write their caches.
+// Counts the collectors that came back empty for the exit code.
+fn warm_cache(
+    config: &configloader::Config,
+    quiet: bool,
+    verbose: bool,
+    timings: bool,
+) -> ExitSummary {
+    cache::set_force_refresh(true);
 
-    // Load config first and initialize colors before spawning threads
-    let config = configloader::load_config();
-    colorcontrol::init_colors(config.colors.clone());
-
-    // Only spawn threads for slow I/O operations (subprocesses)
-    // These may run external commands like vulkaninfo, df, shell --version, etc.
-    let gpu_handler = thread::spawn(modules::hardwaremodules::gpu);
-    let storage_handler = thread::spawn(modules::hardwaremodules::storage);
-    let packages_handler = thread::spawn(modules::userspacemodules::packages);
-    let shell_handler = thread::spawn(modules::userspacemodules::shell);
-    let font_handler = thread::spawn(modules::fontmodule::find_font);
-    let screen_handler = thread::spawn(modules::hardwaremodules::screen);
-
-    // Fast operations - just file reads or env var checks, no benefit from threading
-    let os = modules::coremodules::os();
-    let kernel = modules::coremodules::kernel();
-    let uptime = modules::coremodules::uptime();
-    let cpu = modules::hardwaremodules::cpu();
-    let memory = modules::hardwaremodules::memory();
-    let battery = modules::hardwaremodules::laptop_battery();
+    type Collector = Box<dyn FnOnce() -> String + Send>;
+    // (name, cache file, collector)
+    let mut collectors: Vec<(&str, &str, Collector)> = vec![
+        ("os", "os", Box::new(modules::coremodules::os)),
+        ("cpu", "cpu_model", Box::new(modules::hardwaremodules::cpu_model)),
+        ("gpu", "gpu", Box::new(modules::hardwaremodules::gpu)),
+        ("shell", "shell", Box::new(modules::userspacemodules::shell)),
+    ];
+    // Only terminals that are asked with --version have theirs cached
     let terminal = modules::userspacemodules::terminal();
-    let wm = modules::userspacemodules::wm();
-    let ui = modules::userspacemodules::ui();
-    let editor = modules::userspacemodules::editor();
-
-    // Load ASCII art synchronously - just reading static data
-    let wide_logo = modules::asciimodule::get_wide_logo_lines();
-    let medium_logo = modules::asciimodule::get_medium_logo_lines();
-    let narrow_logo = modules::asciimodule::get_narrow_logo_lines();
-
-    // Collect results and build sections
-    let core = Section::new(
-        "Core",
-        vec![
-            ("OS".to_string(), os),
-            ("Kernel".to_string(), kernel),
-            ("Uptime".to_string(), uptime),
-        ],
-    );
+    if modules::userspacemodules::terminal_binary(&terminal).is_some() {
+        let version = move || {
+            let version = modules::userspacemodules::terminal_version(&terminal);
+            version.map_or("unknown".into(), |version| format!("{} {}", terminal, version))
+        };
+        collectors.push(("terminal", "terminal_version", Box::new(version)));
+    }
+    if helpers::wsl_version().is_some() {
+        collectors.push(("windows", "windows", Box::new(modules::coremodules::windows_version)));
+    }
+    // The nerd font check is only cached when it's left on auto
+    if config.nerd_font.is_none() {
+        let nerd_font = || helpers::get_cached_is_nerd_font().to_string();
+        collectors.push(("nerd_font", "nerd_font", Box::new(nerd_font)));
+    }
+    // The AUR count is only shown with packages_detail
+    if config.packages_detail && modules::pacman::has_database() {
+        let foreign = || {
+            modules::pacman::foreign_count().map_or("unknown".into(), |count| count.to_string())
+        };
+        collectors.push(("packages", "pacman_foreign", Box::new(foreign)));
+    }
+    // Modules set to "always" in [cache] are never read from it, no point warming them
+    let collectors: Vec<(&str, pool::Task<String>)> = collectors
+        .into_iter()
+        .filter(|(_, file, _)| cache::is_cached(file))
+        .map(|(name, _, collector)| (name, pool::spawn(collector)))
+        .collect();
 
-    let mut hardware_lines = vec![
-        ("CPU".to_string(), cpu),
-        ("GPU".to_string(), gpu_handler.join().unwrap_or_else(|_| "error".into())),
-        ("Memory".to_string(), memory),
-        ("Storage".to_string(), storage_handler.join().unwrap_or_else(|_| "error".into())),
-    ];
+    let total = collectors.len();
+    let mut failed = 0;
+    for (name, task) in collectors {
+        match task.wait(None).map(|finished| finished.result) {
+            Ok(Ok(value)) if value != "unknown" && !value.is_empty() => {
+                if verbose {
+                    println!("{}: {}", name, value);
+                }
+            }
+            Ok(Ok(_)) | Err(_) => {
+                failed += 1;
+                eprintln!("{}: failed", name);
+            }
+            Ok(Err(reason)) => {
+                failed += 1;
+                eprintln!("{}: failed ({})", name, reason);
+            }
+        }
+    }
 
-    if battery != "unknown" {
-        hardware_lines.push(("Battery".to_string(), battery));
+    // On stderr like the other --timings
+    let flush = cache::flush();
+    if timings {
+        eprintln!("cache flush: {}", flush);
     }
+    if !quiet {
+        println!("Warmed {} of {} caches", total - failed, total);
+    }
+    ExitSummary { failed_caches: failed, ..ExitSummary::default() }
+}
 
-    let screen_entries = screen_handler.join().unwrap_or_else(|_| vec![]);
-    hardware_lines.extend(screen_entries);
+// Why a run stopped before showing anything
+enum Failure {
+    // Exit code 1, with the message to print
+    Error(String),
+    // Exit code 2, --strict with this many config problems (already printed as warnings)
+    StrictConfig(usize),
+}
 
-    let hardware = Section::new("Hardware", hardware_lines);
+impl Failure {
+    fn report(self) -> ExitCode {
+        match self {
+            Failure::Error(message) => {
+                eprintln!("{}", message);
+                ExitCode::from(1)
+            }
+            Failure::StrictConfig(problems) => {
+                eprintln!("Config has {} problem(s) and strict_config is on", problems);
+                ExitCode::from(2)
+            }
+        }
+    }
+}
 
-    let mut userspace_lines = vec![
-        ("Packages".to_string(), packages_handler.join().unwrap_or_else(|_| "error".into())),
-        ("Terminal".to_string(), terminal),
-        ("Shell".to_string(), shell_handler.join().unwrap_or_else(|_| "error".into())),
-        ("WM".to_string(), wm),
-        ("UI".to_string(), ui),
-    ];
+// How a run that got to the end went, see EXIT_CODES
+#[derive(Default)]
+struct ExitSummary {
+    // --warm-cache collectors that came back empty
+    failed_caches: usize,
+    unknown_modules: bool,
+    // Image mode was asked for and art was shown instead
+    image_unsupported: bool,
+    // Some module missed max_fetch_ms
+    timed_out: bool,
+}
 
-    if !editor.is_empty() {
-        userspace_lines.push(("Editor".to_string(), editor));
+impl ExitSummary {
+    fn code(&self) -> ExitCode {
+        if self.failed_caches > 0 {
+            ExitCode::from(1)
+        } else if self.unknown_modules {
+            ExitCode::from(3)
+        } else if self.image_unsupported {
+            ExitCode::from(4)
+        } else if self.timed_out {
+            ExitCode::from(5)
+        } else {
+            ExitCode::SUCCESS
+        }
     }
+}
 
-    userspace_lines.push((
-        "Terminal Font".to_string(),
-        font_handler.join().unwrap_or_else(|_| "error".into()),
-    ));
+// Pick the ASCII art: --art/--random-art/an --os art file, then custom_art, then --os,
+// then the art and os_art config settings, then the Slowfetch logo
+#[cfg(feature = "art")]
+fn choose_art(args: &Args, config: &configloader::Config, os_name: &str) -> ArtSet {
+    let art_dir = config.art_dir.as_deref();
 
-    let userspace = Section::new("Userspace", userspace_lines);
+    if args.random_art {
+        return asciimodule::random_art(art_dir);
+    }
+    // Already checked against --list-art names in main
+    if let Some(art) = args.art.as_deref().and_then(|name| asciimodule::named_art(name, art_dir)) {
+        return art;
+    }
 
-    // Check if image mode is requested (CLI arg or config) AND terminal supports it
-    let use_image = args.image.is_some() || config.image;
+    // An art file passed to --os, already checked in main
+    if let Some(path) = args.os_art.as_deref().filter(|arg| asciimodule::is_art_path(arg))
+        && let Some(art) = asciimodule::custom_art(&configloader::expand_home(path))
+    {
+        return art;
+    }
 
-    if use_image && image::supports_kitty_graphics() {
-        // Determine image path:
-        // 1. CLI arg with explicit path takes highest priority
-        // 2. CLI arg empty (-i/--image) uses config.image_path if set, else default
-        // 3. Config image=true uses config.image_path if set, else default
-        let image_path = if let Some(ref image_arg) = args.image {
-            if image_arg.is_empty() {
-                // CLI flag without path - use config image_path if available
-                if let Some(ref config_path) = config.image_path {
-                    std::path::PathBuf::from(config_path)
-                } else {
-                    image::get_default_image_path()
-                }
-            } else if image_arg.starts_with("~/") {
-                // CLI flag with explicit path (expand ~)
-                if let Some(home) = std::env::var_os("HOME") {
-                    std::path::PathBuf::from(home).join(&image_arg[2..])
-                } else {
-                    std::path::PathBuf::from(image_arg)
-                }
-            } else {
-                // CLI flag with explicit path
-                std::path::PathBuf::from(image_arg)
+    // Custom art overrides the other settings
+    if let Some(art) = config.custom_art.as_deref().and_then(asciimodule::custom_art) {
+        return art;
+    }
+
+    // Determine OS art setting: CLI args override config
+    let os_art_setting = match args.os_art.as_deref() {
+        Some("") => OsArtSetting::Auto,
+        Some(os_override) => OsArtSetting::Specific(os_override.to_string()),
+        None => {
+            match config.art.as_deref() {
+                Some("random") => return asciimodule::random_art(art_dir),
+                Some(name) => match asciimodule::named_art(name, art_dir) {
+                    Some(art) => return art,
+                    None => eprintln!("Warning: Unknown art '{}', see --list-art", name),
+                },
+                None => {}
             }
-        } else {
-            // Config image=true, use config image_path if set, else default
+            config.os_art.clone()
+        }
+    };
+
+    // Apply OS art setting
+    let os_art = match os_art_setting {
+        OsArtSetting::Disabled => None,
+        OsArtSetting::Auto => asciimodule::os_art(os_name),
+        OsArtSetting::Specific(ref os_name) => asciimodule::os_art(os_name),
+    };
+    match (os_art, os_art_setting) {
+        (Some(art), _) => art,
+        // The logo in the distro's colors, --os "fedora" picks Fedora's
+        (None, OsArtSetting::Specific(name)) if config.logo_accent => {
+            asciimodule::accent_art(&name)
+        }
+        (None, _) if config.logo_accent => asciimodule::accent_art(os_name),
+        (None, _) => asciimodule::default_art(),
+    }
+}
+
+// Built without the art feature there's only the sections, say so when art was asked for
+#[cfg(not(feature = "art"))]
+fn no_art(args: &Args, config: &configloader::Config) -> Option<ArtSet> {
+    let asked = args.art.is_some()
+        || args.random_art
+        || args.os_art.is_some()
+        || config.art.is_some()
+        || config.custom_art.is_some()
+        || config.art_dir.is_some()
+        || !config.art_remap.is_empty()
+        || args.animate
+        || config.animate
+        || !matches!(config.os_art, OsArtSetting::Disabled);
+    if asked {
+        eprintln!("slowfetch was built without art support, showing just the sections");
+    }
+    None
+}
+
+// Kitty image mode: the image next to the sections, or under them on narrow terminals
+#[cfg(feature = "image")]
+fn draw_image_mode(
+    args: &Args,
+    config: &configloader::Config,
+    statics: &collect::StaticInfo,
+    sections: Vec<renderer::Section>,
+    greeting: Option<String>,
+    watch_interval: Option<Duration>,
+) {
+    // Determine image path:
+    // 1. CLI arg with explicit path takes highest priority
+    // 2. CLI arg empty (-i/--image) uses config.image_path if set, else default
+    // 3. Config image=true uses config.image_path if set, else default
+    let image_path = if let Some(ref image_arg) = args.image {
+        if image_arg.is_empty() {
+            // CLI flag without path - use config image_path if available
             if let Some(ref config_path) = config.image_path {
                 std::path::PathBuf::from(config_path)
             } else {
                 image::get_default_image_path()
             }
+        } else if let Some(rest) = image_arg.strip_prefix("~/") {
+            // CLI flag with explicit path (expand ~)
+            if let Some(home) = std::env::var_os("HOME") {
+                std::path::PathBuf::from(home).join(rest)
+            } else {
+                std::path::PathBuf::from(image_arg)
+            }
+        } else {
+            // CLI flag with explicit path
+            std::path::PathBuf::from(image_arg)
+        }
+    } else {
+        // Config image=true, use config image_path if set, else default
+        if let Some(ref config_path) = config.image_path {
+            std::path::PathBuf::from(config_path)
+        } else {
+            image::get_default_image_path()
+        }
+    };
+
+    let image_options = image::ImageOptions {
+        verify: config.verify_image,
+        resample: config.image_resample,
+        max_width: config.image_max_width,
+    };
+
+    // Draw image layout (imagerender handles all the logic)
+    if let Some(interval) = watch_interval {
+        watch::run(config, statics, sections, interval, |sections, greeting, first_frame| {
+            // Transmit the image once, later frames keep its placement and only redraw boxes
+            let image_path = first_frame.then_some(image_path.as_path());
+            imagerender::draw_image_layout(
+                sections,
+                image_path,
+                greeting,
+                config.footer.as_deref(),
+                config.margins,
+                image_options,
+            );
+        });
+    } else {
+        imagerender::draw_image_layout(
+            &sections,
+            Some(&image_path),
+            greeting.as_deref(),
+            config.footer.as_deref(),
+            config.margins,
+            image_options,
+        );
+    }
+}
+
+// Whether image mode can draw here
+#[cfg(feature = "image")]
+fn image_supported() -> bool {
+    image::supports_kitty_graphics()
+}
+
+#[cfg(not(feature = "image"))]
+fn image_supported() -> bool {
+    false
+}
+
+fn main() -> ExitCode {
+    // clap's own exit code for bad arguments is 2, which EXIT_CODES gives to --strict
+    let args = match Args::try_parse() {
+        Ok(args) => args,
+        Err(error) => {
+            let _ = error.print();
+            // --help and --version come through here too
+            return if error.use_stderr() { ExitCode::from(1) } else { ExitCode::SUCCESS };
+        }
+    };
+    match run(args) {
+        Ok(summary) => summary.code(),
+        Err(failure) => failure.report(),
+    }
+}
+
+fn run(args: Args) -> Result<ExitSummary, Failure> {
+    // Completions and man page for packagers, nothing else needs to run
+    #[cfg(feature = "cli-extras")]
+    if let Some(ref what) = args.generate {
+        use clap::CommandFactory;
+        generate::generate(Args::command(), what).map_err(Failure::Error)?;
+        return Ok(ExitSummary::default());
+    }
+
+    // Before loading, the config would only warn about what this is about to fix
+    if args.migrate_config {
+        print!("{}", configloader::migrate_config().map_err(Failure::Error)?);
+        return Ok(ExitSummary::default());
+    }
+
+    terminalsize::set_simulated_size(args.simulate_width, args.simulate_height);
+
+    // Set cache refresh flag if --refresh/-r was passed
+    match args.refresh.as_deref() {
+        Some("images") => cache::set_refresh_images(true),
+        Some(_) => cache::set_force_refresh(true),
+        None => {}
+    }
+
+    // Validate --export up front so we don't collect everything just to fail
+    let export_target = match args.export.as_deref() {
+        Some([format, path]) => {
+            let format = export::ExportFormat::parse(format).ok_or_else(|| {
+                Failure::Error(format!(
+                    "Unknown export format '{}' (expected html, svg or ansi)",
+                    format
+                ))
+            })?;
+            Some((format, std::path::PathBuf::from(path)))
+        }
+        _ => None,
+    };
+
+    // Redrawing in place only makes sense on a terminal, render once when piped
+    let watch_interval = args.watch.and_then(|seconds| {
+        if std::io::stdout().is_terminal() {
+            Some(Duration::from_secs(seconds.max(1)))
+        } else {
+            eprintln!("stdout is not a terminal, ignoring --watch");
+            None
+        }
+    });
+
+    // Load config first and initialize colors before spawning threads
+    let profile = args.profile.clone().or_else(|| {
+        std::env::var("SLOWFETCH_PROFILE").ok().filter(|profile| !profile.is_empty())
+    });
+    let (mut config, warnings) =
+        configloader::load_config(profile.as_deref()).map_err(Failure::Error)?;
+    for warning in &warnings {
+        eprintln!("Warning: {}", warning);
+    }
+    // Old names are still honored, so they're not warnings (and don't trip --strict)
+    for notice in config.deprecated.iter().filter(|_| !args.quiet) {
+        eprintln!("Deprecated: {}", notice);
+    }
+    // Strict mode turns config typos into a failure, handy for dotfiles checked in CI
+    if (args.strict || config.strict_config) && !warnings.is_empty() {
+        return Err(Failure::StrictConfig(warnings.len()));
+    }
+    // Precedence: defaults < colors_from theme < [colors] < profile < environment < --color
+    config.colors.apply_overrides(&args.color).map_err(Failure::Error)?;
+    // Colors that disappear into the terminal background: hint, or fix them with auto_contrast.
+    // Only when there's a fetch on the terminal to look at.
+    if !args.warm_cache && !args.list_art && !args.credits && export_target.is_none() {
+        let background = config.background.or_else(background::detect);
+        if let Some(background) = background {
+            let hints =
+                colorcontrol::check_contrast(&mut config.colors, background, config.auto_contrast);
+            for hint in hints.iter().filter(|_| !args.quiet) {
+                eprintln!("Hint: {}", hint);
+            }
+        }
+    }
+
+    // Exports parse truecolor escapes, they always get the exact colors
+    let color_mode = match config.color_mode {
+        _ if export_target.is_some() => colorcontrol::ColorMode::Truecolor,
+        Some(mode) => mode,
+        None => colorcontrol::ColorMode::detect(),
+    };
+    colorcontrol::init_colors(config.colors.clone(), color_mode);
+    cache::set_ttl_overrides(config.cache_ttl.clone());
+    config.uptime_seconds.get_or_insert(watch_interval.is_some());
+    #[cfg(feature = "art")]
+    asciimodule::set_art_remap(config.art_remap.clone());
+    #[cfg(feature = "art")]
+    asciimodule::set_tab_width(config.tab_width);
+    // Accessible output is plain lines, nothing to undo later: no icons, links or packed lines
+    let accessible = args.accessible || config.accessible;
+    if accessible {
+        config.vendor_icons = false;
+        config.compact = false;
+        config.compact_width = 0;
+        config.balance_sections = false;
+    }
+    let nerd_font = if accessible { Some(false) } else { config.nerd_font };
+    helpers::init_font_cache(config.terminal_font.clone(), nerd_font);
+    modules::userspacemodules::set_terminal_override(config.terminal.clone());
+    // Accessible output takes plain bars out, other styles would be read out cell by cell
+    helpers::set_bar_styles(if accessible { Default::default() } else { config.bars.clone() });
+    renderer::set_unified_box(config.unified_box);
+    modules::hardwaremodules::set_respect_cgroups(config.respect_cgroups);
+    modules::hardwaremodules::set_include_network_fs(config.include_network_fs);
+    pool::set_threads(config.threads);
+    // The badge cuts long values short, which could cut a link in half
+    helpers::set_hyperlinks(
+        config.hyperlinks && export_target.is_none() && !args.badge && !accessible,
+    );
+
+    if let Some(Command::Doctor) = args.command {
+        print!("{}", doctor::report(config, &warnings));
+        return Ok(ExitSummary::default());
+    }
+
+    if args.warm_cache {
+        return Ok(warm_cache(&config, args.quiet, args.verbose, args.timings));
+    }
+
+    #[cfg(feature = "art")]
+    let art_dir = config.art_dir.as_deref();
+    if args.list_art {
+        #[cfg(feature = "art")]
+        for (name, source, smol) in modules::asciimodule::art_names(art_dir) {
+            let smol = if smol { ", smol" } else { "" };
+            println!("{:<16} ({}{})", name, source, smol);
+        }
+        #[cfg(not(feature = "art"))]
+        eprintln!("slowfetch was built without art support, there's no art to list");
+        return Ok(ExitSummary::default());
+    }
+
+    if args.credits {
+        let credits = [collect::credits()];
+        let footer = Some("Thanks for using Slowfetch!");
+        #[cfg(feature = "art")]
+        let heart = Some(asciimodule::credits_art());
+        #[cfg(not(feature = "art"))]
+        let heart = None;
+        let output = renderer::draw_layout(
+            heart.as_ref(),
+            &credits,
+            None,
+            footer,
+            config.margins,
+        );
+        print!("{}", output);
+        return Ok(ExitSummary::default());
+    }
+
+    // Check --art before collecting everything just to fail
+    #[cfg(feature = "art")]
+    if let Some(ref name) = args.art
+        && !modules::asciimodule::art_names(art_dir).iter().any(|(known, ..)| known == name)
+    {
+        return Err(Failure::Error(format!("Unknown art '{}', see --list-art", name)));
+    }
+
+    // An art file passed to --os has to exist, falling back to the logo would hide the typo
+    #[cfg(feature = "art")]
+    if let Some(ref arg) = args.os_art
+        && asciimodule::is_art_path(arg)
+    {
+        asciimodule::check_art_file(&configloader::expand_home(arg)).map_err(Failure::Error)?;
+    }
+
+    config.verbose = args.verbose;
+
+    // CLI margin wins over the config
+    if let Some(margin) = args.margin {
+        config.margins = configloader::Margins::uniform(margin);
+    }
+
+    // Someone else's values from a snapshot, nothing on this system is collected
+    if let Some(ref path) = args.simulate_data {
+        let entries = snapshot::read(path).map_err(Failure::Error)?;
+        // A snapshot only has the lines, no numbers
+        if args.json {
+            let json = export::to_json(&snapshot::sections(&entries), &[]);
+            write_output(&json, None, args.quiet)?;
+            return Ok(ExitSummary::default());
+        }
+        #[cfg(feature = "art")]
+        let art = {
+            let os = entries.iter().find(|(_, name, _)| name == "OS");
+            Some(choose_art(&args, &config, os.map_or("", |(_, _, os)| os)))
         };
+        #[cfg(not(feature = "art"))]
+        let art = no_art(&args, &config);
+        let output = renderer::draw_layout(
+            art.as_ref(),
+            &snapshot::sections(&entries),
+            collect::greeting(&config).as_deref(),
+            config.footer.as_deref(),
+            config.margins,
+        );
+        write_output(&output, export_target, args.quiet)?;
+        return Ok(ExitSummary::default());
+    }
+
+    // Static and dynamic modules are collected side by side, the slow probes in each overlap
+    let (statics, dynamic) = thread::scope(|scope| {
+        let dynamic_handler = scope.spawn(|| collect::collect_dynamic(&config));
+        let statics = collect::collect_static(&config);
+        let dynamic = dynamic_handler.join().unwrap_or_else(|payload| {
+            collect::DynamicInfo::failed(collect::panic_reason(&*payload))
+        });
+        (statics, dynamic)
+    });
+
+    // On stderr so it stays out of exports and pipes
+    if args.verbose {
+        eprintln!("color mode: {}", color_mode.name());
+        // --timings prints them along with the rest
+        for (name, status) in cache::status().iter().filter(|_| !args.timings) {
+            eprintln!("{}: {}", name, status);
+        }
+        for (module, reason) in statics.failures().iter().chain(dynamic.failures()) {
+            eprintln!("{}: unavailable ({})", module, reason);
+        }
+    }
+    if args.timings {
+        let mut timings: Vec<_> = statics.timings().iter().chain(dynamic.timings()).collect();
+        timings.sort_by_key(|&&(_, took)| std::cmp::Reverse(took));
+        for (module, took) in timings {
+            eprintln!("{}: {:.1}ms", module, took.as_secs_f64() * 1000.0);
+        }
+        if let Some(scan) = helpers::process_scan_summary() {
+            eprintln!("process scan: {}", scan);
+        }
+        // "gpu: cache hit (age 3d)", what the cached modules didn't have to do
+        for (name, status) in cache::status() {
+            eprintln!("{}: {}", name, status);
+        }
+    }
+    let mut summary = ExitSummary {
+        unknown_modules: !collect::unknown_modules(&config).is_empty(),
+        timed_out: statics.timed_out() || dynamic.timed_out(),
+        ..ExitSummary::default()
+    };
+    let numbers = dynamic.numbers().to_vec();
+    let sections = collect::build_sections(&config, &statics, dynamic);
+    let greeting = collect::greeting(&config);
 
-        // Draw image layout (imagerender handles all the logic)
-        imagerender::draw_image_layout(&[core, hardware, userspace], &image_path);
+    // Check if image mode is requested (CLI arg or config) AND terminal supports it
+    let use_image = args.image.is_some() || config.image;
+
+    // Kitty images can't be exported, bail out rather than silently dropping the image
+    if use_image && !args.badge && export_target.is_some() {
+        return Err(Failure::Error(
+            "Export doesn't support image mode, run without --image (or set image = false)".into(),
+        ));
+    }
+
+    if args.json {
+        write_output(&export::to_json(&sections, &numbers), None, args.quiet)?;
+    } else if let Some(ref path) = args.snapshot {
+        snapshot::write(&sections, path).map_err(Failure::Error)?;
+        if !args.quiet {
+            println!("Snapshot written to {}", path.display());
+        }
+    } else if let Some(ref path) = args.diff {
+        let old = snapshot::read(path).map_err(Failure::Error)?;
+        match snapshot::diff(&old, &sections, &config.diff_include) {
+            _ if args.quiet => {}
+            Some(changes) => {
+                let output = renderer::build_sections_lines(&[changes], None).join("\n");
+                print!("{}", renderer::apply_margins(&(output + "\n"), config.margins));
+            }
+            None => println!("Nothing changed since {}", path.display()),
+        }
+    } else if accessible {
+        let output =
+            accessible::render(&sections, greeting.as_deref(), config.footer.as_deref());
+        write_output(&output, export_target, args.quiet)?;
+    } else if args.badge {
+        let output = renderer::draw_badge(&sections, config.margins);
+        write_output(&output, export_target, args.quiet)?;
+    } else if use_image && image_supported() {
+        #[cfg(feature = "image")]
+        if !args.quiet {
+            draw_image_mode(&args, &config, &statics, sections, greeting, watch_interval);
+        }
     } else {
+        summary.image_unsupported = use_image;
+        // Quiet like the other notices, the exit code still says it
+        #[cfg(not(feature = "image"))]
+        if use_image && !args.quiet {
+            eprintln!("slowfetch was built without image support, showing art instead");
+        }
         // Standard ASCII art mode
-        // Check for custom art first (overrides everything else)
-        let (wide, medium, narrow, smol) = if let Some(ref custom_path) = config.custom_art {
-            if let Some(custom_art) = modules::asciimodule::get_custom_art_lines(custom_path) {
-                (custom_art.clone(), custom_art.clone(), custom_art, None)
-            } else {
-                // Custom art file not found, fall back to default
-                (
-                    wide_logo.clone(),
-                    medium_logo.clone(),
-                    narrow_logo.clone(),
-                    None,
-                )
-            }
+        // Art is only rendered once draw_layout settles on a layout that shows it
+        #[cfg(feature = "art")]
+        let art = Some(choose_art(&args, &config, &statics.os_name));
+        #[cfg(not(feature = "art"))]
+        let art = no_art(&args, &config);
+
+        if let Some(interval) = watch_interval {
+            watch::run(&config, &statics, sections, interval, |sections, greeting, _| {
+                // Erase whatever the previous frame left below this one
+                let footer = config.footer.as_deref();
+                let art = art.as_ref();
+                let output = renderer::draw_layout(art, sections, greeting, footer, config.margins);
+                print!("{}\x1b[J", output);
+            });
         } else {
-            // Determine OS art setting: CLI args override config
-            let os_art_setting = if let Some(ref os_override) = args.os_art {
-                if os_override.is_empty() {
-                    OsArtSetting::Auto
-                } else {
-                    OsArtSetting::Specific(os_override.clone())
-                }
-            } else {
-                config.os_art.clone()
-            };
-
-            // Apply OS art setting
-            match os_art_setting {
-                OsArtSetting::Disabled => (wide_logo, medium_logo, narrow_logo, None),
-                OsArtSetting::Auto => {
-                    let os_name = core
-                        .lines
-                        .iter()
-                        .find(|(k, _)| k == "OS")
-                        .map(|(_, v)| v.as_str())
-                        .unwrap_or("");
-                    if let Some(os_logo) = modules::asciimodule::get_os_logo_lines(os_name) {
-                        let smol_logo = modules::asciimodule::get_os_logo_lines_smol(os_name);
-                        (os_logo.clone(), os_logo.clone(), os_logo, smol_logo)
-                    } else {
-                        (wide_logo, medium_logo, narrow_logo, None)
-                    }
-                }
-                OsArtSetting::Specific(ref os_name) => {
-                    if let Some(os_logo) = modules::asciimodule::get_os_logo_lines(os_name) {
-                        let smol_logo = modules::asciimodule::get_os_logo_lines_smol(os_name);
-                        (os_logo.clone(), os_logo.clone(), os_logo, smol_logo)
-                    } else {
-                        (wide_logo, medium_logo, narrow_logo, None)
-                    }
-                }
+            // Animating needs the art where it was printed, not in a file or a pipe, and a
+            // simulated size the real terminal doesn't have would put it elsewhere
+            #[cfg(feature = "art")]
+            let animate = (args.animate || config.animate)
+                && export_target.is_none()
+                && !args.quiet
+                && args.simulate_width.is_none()
+                && args.simulate_height.is_none()
+                && std::io::stdout().is_terminal();
+            let (output, _placement) = renderer::draw_layout_placed(
+                art.as_ref(),
+                &sections,
+                greeting.as_deref(),
+                config.footer.as_deref(),
+                config.margins,
+            );
+            write_output(&output, export_target, args.quiet)?;
+            #[cfg(feature = "art")]
+            if animate
+                && let (Some(art), Some(placement)) = (&art, _placement)
+            {
+                animate::run(art, placement, output.lines().count());
             }
-        };
+        }
+    }
 
-        print!(
-            "{}",
-            renderer::draw_layout(
-                &wide,
-                &medium,
-                &narrow,
-                &[core, hardware, userspace],
-                smol.as_deref()
-            )
-        );
+    // Values cached this run hit the disk only now, with the output already shown
+    let flush = cache::flush();
+    if args.timings {
+        eprintln!("cache flush: {}", flush);
+    }
+    Ok(summary)
+}
+
+// Print the finished layout, or export it with --export. --quiet still exports, just silently.
+fn write_output(
+    output: &str,
+    export_target: Option<(export::ExportFormat, std::path::PathBuf)>,
+    quiet: bool,
+) -> Result<(), Failure> {
+    if let Some((format, path)) = export_target {
+        export::export(output, format, &path).map_err(Failure::Error)?;
+        if !quiet {
+            eprintln!("Exported to {}", path.display());
+        }
+    } else if !quiet {
+        print!("{}", output);
     }
+    Ok(())
 }
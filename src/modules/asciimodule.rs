@@ -1,9 +1,17 @@
 // ASCII art module for Slowfetch
 // Uses inkline to render colorized ASCII art
 
-use crate::colorcontrol::get_art_colors;
+use crate::colorcontrol::{art_color, get_art_colors};
+use crate::renderer::{ArtSize, char_width, visible_len};
 use inkline::AsciiArt;
+use tintify::{AnsiColors, DynColors};
+use std::borrow::Cow;
+use std::cell::OnceCell;
+use std::collections::HashMap;
 use std::fs;
+use std::path::{Path, PathBuf};
+use std::sync::OnceLock;
+use std::time::{SystemTime, UNIX_EPOCH};
 
 // The ASCII art for the Slowfetch logo Wide version.
 const ASCII_ART_WIDE: &str = include_str!("../assets/default/wide.txt");
@@ -20,6 +28,7 @@ const ASCII_ART_CACHYOS: &str = include_str!("../assets/cachy.txt");
 const ASCII_ART_FEDORA: &str = include_str!("../assets/fedora.txt");
 const ASCII_ART_UBUNTU: &str = include_str!("../assets/ubuntu.txt");
 const ASCII_ART_NIX: &str = include_str!("../assets/nix.txt");
+const ASCII_ART_STEAMOS: &str = include_str!("../assets/steamos.txt");
 
 // Smol versions of OS-specific ASCII art
 const ASCII_ART_ARCH_SMOL: &str = include_str!("../assets/archsmol.txt");
@@ -27,81 +36,573 @@ const ASCII_ART_CACHYOS_SMOL: &str = include_str!("../assets/cachysmol.txt");
 const ASCII_ART_FEDORA_SMOL: &str = include_str!("../assets/fedorasmol.txt");
 const ASCII_ART_UBUNTU_SMOL: &str = include_str!("../assets/ubuntusmol.txt");
 const ASCII_ART_NIX_SMOL: &str = include_str!("../assets/nixsmol.txt");
+const ASCII_ART_STEAMOS_SMOL: &str = include_str!("../assets/steamossmol.txt");
 
-// Render the wide ASCII art logo and return lines as a Vec
-pub fn get_wide_logo_lines() -> Vec<String> {
-    let colors = get_art_colors();
-    let art = AsciiArt::new(ASCII_ART_WIDE, &colors, true);
-    art.map(|line| line.to_string()).collect()
+// Just-for-fun art, only picked by name (--art) or at random
+const ASCII_ART_ARCH_MEME: &str = include_str!("../assets/archmeme.txt");
+const ASCII_ART_NIX_MEME: &str = include_str!("../assets/nixmeme.txt");
+
+// Only shown by --credits
+const ASCII_ART_HEART: &str = include_str!("../assets/heart.txt");
+
+// Bundled art registry: name, words that pick it from the OS name, art, smol art.
+// Entries without words are never auto-detected. Order matters for detection.
+const BUNDLED_ART: &[(&str, &[&str], &str, Option<&str>)] = &[
+    // SteamOS is Arch based, keep it ahead of arch in case the name ever mentions it
+    ("steamos", &["steamos"], ASCII_ART_STEAMOS, Some(ASCII_ART_STEAMOS_SMOL)),
+    ("arch", &["arch"], ASCII_ART_ARCH, Some(ASCII_ART_ARCH_SMOL)),
+    ("cachyos", &["cachyos", "cachy"], ASCII_ART_CACHYOS, Some(ASCII_ART_CACHYOS_SMOL)),
+    ("fedora", &["fedora"], ASCII_ART_FEDORA, Some(ASCII_ART_FEDORA_SMOL)),
+    ("ubuntu", &["ubuntu"], ASCII_ART_UBUNTU, Some(ASCII_ART_UBUNTU_SMOL)),
+    ("nixos", &["nixos", "nix"], ASCII_ART_NIX, Some(ASCII_ART_NIX_SMOL)),
+    ("archmeme", &[], ASCII_ART_ARCH_MEME, None),
+    ("nixmeme", &[], ASCII_ART_NIX_MEME, None),
+];
+
+// Distro accent colors for logo_accent, words that pick them from the OS name and the
+// 0xRRGGBB colors for the logo's {5}-{9} slots, light to dark like the default gradient.
+// Same word matching as BUNDLED_ART, so order matters here too.
+const DISTRO_ACCENTS: &[(&[&str], [u32; 5])] = &[
+    (&["cachyos", "cachy"], [0x9AF5E0, 0x4FE0C2, 0x00C9A7, 0x009E86, 0x175E57]),
+    (&["arch"], [0x7FD3F5, 0x4FB8E8, 0x1793D1, 0x106FA8, 0x0A4F80]),
+    (&["fedora"], [0xFFFFFF, 0xC8DBF0, 0x51A2DA, 0x3C6EB4, 0x294B8F]),
+    (&["ubuntu"], [0xF6A06E, 0xF2824A, 0xE95420, 0xC3431A, 0x77216F]),
+    (&["nixos", "nix"], [0x7EBAE4, 0x6AACDD, 0x5277C3, 0x415E9A, 0x2C3E66]),
+    (&["debian"], [0xF28BAE, 0xE84F85, 0xD70A53, 0xA80841, 0x75062E]),
+];
+
+// Art that's only colorized through inkline the first time a layout actually needs it.
+// Narrow terminals that end up sections-only never pay for rendering art at all.
+pub struct LazyArt {
+    source: Cow<'static, str>,
+    // Colors to draw with instead of the configured art colors
+    palette: Option<Vec<DynColors>>,
+    lines: OnceCell<Vec<String>>,
 }
 
-// Render the medium ASCII art logo and return lines as a Vec
-pub fn get_medium_logo_lines() -> Vec<String> {
-    let colors = get_art_colors();
-    let art = AsciiArt::new(ASCII_ART_MEDIUM, &colors, true);
-    art.map(|line| line.to_string()).collect()
+impl LazyArt {
+    pub fn new(source: impl Into<Cow<'static, str>>) -> Self {
+        Self {
+            source: source.into(),
+            palette: None,
+            lines: OnceCell::new(),
+        }
+    }
+
+    pub fn with_palette(source: impl Into<Cow<'static, str>>, palette: Vec<DynColors>) -> Self {
+        Self {
+            palette: Some(palette),
+            ..Self::new(source)
+        }
+    }
+
+    // Rendered lines, rendered on first use
+    pub fn lines(&self) -> &[String] {
+        self.lines.get_or_init(|| match &self.palette {
+            Some(palette) => render_art_with(&self.source, palette),
+            None => render_art(&self.source),
+        })
+    }
+
+    // The art drawn with its colors moved `shift` slots along, a frame of --animate.
+    // Not cached, every frame has its own colors.
+    fn shifted_lines(&self, shift: usize) -> Vec<String> {
+        let mut palette = self.palette.clone().unwrap_or_else(art_palette);
+        if !palette.is_empty() {
+            let len = palette.len();
+            palette.rotate_left(shift % len);
+        }
+        render_art_with(&self.source, &palette)
+    }
+}
+
+// The art variants draw_layout picks from.
+// OS and custom art only have one size, medium/narrow fall back to wide so it's rendered once.
+pub struct ArtSet {
+    wide: LazyArt,
+    medium: Option<LazyArt>,
+    narrow: Option<LazyArt>,
+    smol: Option<LazyArt>,
 }
 
-// Render the narrow ASCII art logo and return lines as a Vec
-pub fn get_narrow_logo_lines() -> Vec<String> {
+impl ArtSet {
+    pub fn wide(&self) -> &[String] {
+        self.wide.lines()
+    }
+
+    pub fn medium(&self) -> &[String] {
+        self.medium.as_ref().unwrap_or(&self.wide).lines()
+    }
+
+    pub fn narrow(&self) -> &[String] {
+        self.narrow.as_ref().unwrap_or(&self.wide).lines()
+    }
+
+    pub fn smol(&self) -> Option<&[String]> {
+        self.smol.as_ref().map(LazyArt::lines)
+    }
+
+    // One size of the art with its colors shifted, for --animate
+    pub fn shifted(&self, size: ArtSize, shift: usize) -> Vec<String> {
+        let art = match size {
+            ArtSize::Wide => Some(&self.wide),
+            ArtSize::Medium => self.medium.as_ref(),
+            ArtSize::Narrow => self.narrow.as_ref(),
+            ArtSize::Smol => self.smol.as_ref(),
+        };
+        art.unwrap_or(&self.wide).shifted_lines(shift)
+    }
+
+    // How many variants have been rendered so far
+    #[cfg(test)]
+    fn rendered(&self) -> usize {
+        let variants = [Some(&self.wide), self.medium.as_ref(), self.narrow.as_ref()];
+        let variants = variants.into_iter().chain([self.smol.as_ref()]).flatten();
+        variants.filter(|art| art.lines.get().is_some()).count()
+    }
+}
+
+// [art_remap] from the config, slot -> slot whose color it's drawn in
+static ART_REMAP: OnceLock<HashMap<usize, usize>> = OnceLock::new();
+
+pub fn set_art_remap(remap: HashMap<usize, usize>) {
+    let _ = ART_REMAP.set(remap);
+}
+
+// tab_width from the config, columns between tab stops in art files
+static TAB_WIDTH: OnceLock<usize> = OnceLock::new();
+
+pub fn set_tab_width(width: usize) {
+    let _ = TAB_WIDTH.set(width);
+}
+
+// The art colors with [art_remap] applied. inkline draws {N} in the art file with colors[N],
+// a remap "N" = M puts the art_M color there. Remaps all read the configured colors,
+// so "1" = 2 and "2" = 3 don't chain.
+fn art_palette() -> Vec<DynColors> {
     let colors = get_art_colors();
-    let art = AsciiArt::new(ASCII_ART_NARROW, &colors, true);
-    art.map(|line| line.to_string()).collect()
+    match ART_REMAP.get() {
+        Some(remap) => remapped_palette(&colors, remap),
+        None => colors,
+    }
 }
 
-// Get OS-specific art if available, returns None if no match
-pub fn get_os_logo_lines(os_name: &str) -> Option<Vec<String>> {
+// `colors` with `remap` applied, the config checked the slots are 1-9
+fn remapped_palette(colors: &[DynColors], remap: &HashMap<usize, usize>) -> Vec<DynColors> {
+    let mut palette = colors.to_vec();
+    for (&slot, &color) in remap {
+        if palette.len() <= slot {
+            palette.resize(slot + 1, DynColors::Ansi(AnsiColors::Default));
+        }
+        palette[slot] = colors[color - 1];
+    }
+    palette
+}
+
+// Art rendered with the art colors and `remap`, for the golden test
+#[cfg(test)]
+pub fn remapped_lines(source: &str, remap: &HashMap<usize, usize>) -> Vec<String> {
+    render_art_with(source, &remapped_palette(&get_art_colors(), remap))
+}
+
+// Art rendered with `os_name`'s accent colors, for the golden test
+#[cfg(test)]
+pub fn accent_lines(source: &str, os_name: &str) -> Option<Vec<String>> {
+    Some(render_art_with(source, &accent_palette(os_name)?))
+}
+
+// Render ASCII art through inkline and return lines as a Vec
+fn render_art(source: &str) -> Vec<String> {
+    render_art_with(source, &art_palette())
+}
+
+// Render ASCII art with explicit colors.
+// inkline pads every line to the longest one counting characters, which comes up a column
+// short per wide character, so the lines are padded again by their visible width.
+fn render_art_with(source: &str, colors: &[DynColors]) -> Vec<String> {
+    let art = AsciiArt::new(source, colors, true);
+    let mut lines: Vec<String> = art.map(|line| line.trim_end_matches(' ').to_string()).collect();
+    let width = lines.iter().map(|line| visible_len(line)).max().unwrap_or(0);
+    for line in &mut lines {
+        let padding = width - visible_len(line);
+        line.push_str(&" ".repeat(padding));
+    }
+    lines
+}
+
+// The art colors with the logo's slots swapped for the distro's accent colors,
+// None when the distro has no accents
+fn accent_palette(os_name: &str) -> Option<Vec<DynColors>> {
     let os_lower = os_name.to_lowercase();
-    let art_str = if os_lower.contains("arch") {
-        Some(ASCII_ART_ARCH)
-    } else if os_lower.contains("cachyos") || os_lower.contains("cachy") {
-        Some(ASCII_ART_CACHYOS)
-    } else if os_lower.contains("fedora") {
-        Some(ASCII_ART_FEDORA)
-    } else if os_lower.contains("ubuntu") {
-        Some(ASCII_ART_UBUNTU)
-    } else if os_lower.contains("nixos") || os_lower.contains("nix") {
-        Some(ASCII_ART_NIX)
-    } else {
-        None
+    let (_, accents) = DISTRO_ACCENTS
+        .iter()
+        .find(|(words, _)| words.iter().any(|word| os_lower.contains(word)))?;
+    let mut palette = art_palette();
+    if palette.len() < 10 {
+        palette.resize(10, DynColors::Ansi(AnsiColors::Default));
+    }
+    for (slot, &hex) in (5..=9).zip(accents) {
+        palette[slot] = art_color(((hex >> 16) as u8, (hex >> 8) as u8, hex as u8));
+    }
+    Some(palette)
+}
+
+// The default Slowfetch logo in all three sizes
+pub fn default_art() -> ArtSet {
+    ArtSet {
+        wide: LazyArt::new(ASCII_ART_WIDE),
+        medium: Some(LazyArt::new(ASCII_ART_MEDIUM)),
+        narrow: Some(LazyArt::new(ASCII_ART_NARROW)),
+        smol: None,
+    }
+}
+
+// The Slowfetch logo in the distro's colors (logo_accent), the plain logo for distros
+// without accent colors
+pub fn accent_art(os_name: &str) -> ArtSet {
+    let Some(palette) = accent_palette(os_name) else {
+        return default_art();
     };
+    ArtSet {
+        wide: LazyArt::with_palette(ASCII_ART_WIDE, palette.clone()),
+        medium: Some(LazyArt::with_palette(ASCII_ART_MEDIUM, palette.clone())),
+        narrow: Some(LazyArt::with_palette(ASCII_ART_NARROW, palette)),
+        smol: None,
+    }
+}
 
-    art_str.map(|s| {
-        let colors = get_art_colors();
-        let art = AsciiArt::new(s, &colors, true);
-        art.map(|line| line.to_string()).collect()
-    })
+// The heart --credits shows
+pub fn credits_art() -> ArtSet {
+    bundled_art(ASCII_ART_HEART, None)
+}
+
+// Build an ArtSet for a bundled registry entry
+fn bundled_art(art: &'static str, smol: Option<&'static str>) -> ArtSet {
+    ArtSet {
+        wide: LazyArt::new(art),
+        medium: None,
+        narrow: None,
+        smol: smol.map(LazyArt::new),
+    }
 }
 
-// Get smol OS-specific art if available, returns None if no match
-pub fn get_os_logo_lines_smol(os_name: &str) -> Option<Vec<String>> {
+// Get OS-specific art if available, returns None if no match
+pub fn os_art(os_name: &str) -> Option<ArtSet> {
     let os_lower = os_name.to_lowercase();
-    let art_str = if os_lower.contains("arch") {
-        Some(ASCII_ART_ARCH_SMOL)
-    } else if os_lower.contains("cachyos") || os_lower.contains("cachy") {
-        Some(ASCII_ART_CACHYOS_SMOL)
-    } else if os_lower.contains("fedora") {
-        Some(ASCII_ART_FEDORA_SMOL)
-    } else if os_lower.contains("ubuntu") {
-        Some(ASCII_ART_UBUNTU_SMOL)
-    } else if os_lower.contains("nixos") || os_lower.contains("nix") {
-        Some(ASCII_ART_NIX_SMOL)
-    } else {
-        None
-    };
+    BUNDLED_ART
+        .iter()
+        .find(|(_, words, _, _)| words.iter().any(|word| os_lower.contains(word)))
+        .map(|(_, _, art, smol)| bundled_art(art, *smol))
+}
 
-    art_str.map(|s| {
-        let colors = get_art_colors();
-        let art = AsciiArt::new(s, &colors, true);
-        art.map(|line| line.to_string()).collect()
-    })
+// Read an art file, rejecting ones with nothing to draw
+fn read_art_file(path: &Path) -> Option<String> {
+    let content = fs::read_to_string(path).ok()?;
+    if content.trim().is_empty() {
+        eprintln!("Warning: Art file {} is empty", path.display());
+        return None;
+    }
+    Some(expand_tabs(&content, TAB_WIDTH.get().copied().unwrap_or(8)))
+}
+
+// Tabs to spaces up to the next stop every `tab_width` columns, and trailing whitespace
+// (ideographic spaces too) cut off. Columns are counted like the renderer counts them:
+// {N} color tokens take none, wide characters two.
+fn expand_tabs(content: &str, tab_width: usize) -> String {
+    let mut expanded = String::with_capacity(content.len());
+    for line in content.lines() {
+        let start = expanded.len();
+        let mut column = 0;
+        let mut rest = line;
+        while let Some(c) = rest.chars().next() {
+            let token = rest.len() >= 3
+                && rest.as_bytes()[0] == b'{'
+                && rest.as_bytes()[1].is_ascii_digit()
+                && rest.as_bytes()[2] == b'}';
+            if token {
+                expanded.push_str(&rest[..3]);
+                rest = &rest[3..];
+                continue;
+            }
+            if c == '\t' {
+                let spaces = tab_width - column % tab_width;
+                expanded.push_str(&" ".repeat(spaces));
+                column += spaces;
+            } else {
+                expanded.push(c);
+                column += char_width(c);
+            }
+            rest = &rest[c.len_utf8()..];
+        }
+        expanded.truncate(start + expanded[start..].trim_end().len());
+        expanded.push('\n');
+    }
+    expanded
 }
 
 // Load custom ASCII art from a file path
-// Returns None if file doesn't exist or can't be read
-pub fn get_custom_art_lines(path: &str) -> Option<Vec<String>> {
-    let content = fs::read_to_string(path).ok()?;
-    let colors = get_art_colors();
-    let art = AsciiArt::new(&content, &colors, true);
-    Some(art.map(|line| line.to_string()).collect())
+// Returns None if file doesn't exist, can't be read or is empty
+pub fn custom_art(path: &str) -> Option<ArtSet> {
+    let content = read_art_file(Path::new(path))?;
+    Some(ArtSet {
+        wide: LazyArt::new(content),
+        medium: None,
+        narrow: None,
+        smol: None,
+    })
+}
+
+// Does an --os argument name an art file rather than a distro?
+// Only a '/' or a .txt ending makes it a path, so `--os arch` stays the distro even when
+// there's a file called arch in the current directory. Use ./arch for that one.
+pub fn is_art_path(arg: &str) -> bool {
+    arg.contains('/') || arg.ends_with(".txt")
+}
+
+// An art file from --os has to exist and be usable art
+pub fn check_art_file(path: &str) -> Result<(), String> {
+    if !Path::new(path).is_file() {
+        return Err(format!("Art file {} doesn't exist", path));
+    }
+    if custom_art(path).is_none() {
+        return Err(format!("Can't read art file {}", path));
+    }
+    Ok(())
+}
+
+// *.txt files in art_dir that pass the same checks as custom_art, sorted by name
+fn art_dir_files(art_dir: Option<&str>) -> Vec<PathBuf> {
+    let Some(Ok(entries)) = art_dir.map(fs::read_dir) else {
+        return Vec::new();
+    };
+    let mut files: Vec<PathBuf> = entries
+        .filter_map(|entry| entry.ok().map(|entry| entry.path()))
+        .filter(|path| path.extension().is_some_and(|ext| ext == "txt"))
+        .filter(|path| read_art_file(path).is_some())
+        .collect();
+    files.sort();
+    files
+}
+
+// Name of an art_dir file, its file stem
+fn art_file_name(path: &Path) -> String {
+    path.file_stem().unwrap_or_default().to_string_lossy().into_owned()
+}
+
+// Everything --art can pick: name, where it comes from, and whether there's a smol version
+pub fn art_names(art_dir: Option<&str>) -> Vec<(String, &'static str, bool)> {
+    let mut names = vec![("default".to_string(), "bundled", false)];
+    names.extend(
+        BUNDLED_ART
+            .iter()
+            .map(|(name, _, _, smol)| (name.to_string(), "bundled", smol.is_some())),
+    );
+    // An art_dir file named like bundled art can't be picked, so it isn't listed either
+    let files: Vec<_> = art_dir_files(art_dir)
+        .iter()
+        .map(|path| (art_file_name(path), "art_dir", false))
+        .filter(|(name, _, _)| !names.iter().any(|(bundled, _, _)| bundled == name))
+        .collect();
+    names.extend(files);
+    names
+}
+
+// Pick art by name from the bundled registry and art_dir. art_dir files don't shadow bundled art.
+pub fn named_art(name: &str, art_dir: Option<&str>) -> Option<ArtSet> {
+    if name == "default" {
+        return Some(default_art());
+    }
+    if let Some((_, _, art, smol)) = BUNDLED_ART.iter().find(|(bundled, ..)| *bundled == name) {
+        return Some(bundled_art(art, *smol));
+    }
+    art_dir_files(art_dir)
+        .iter()
+        .find(|path| art_file_name(path) == name)
+        .and_then(|path| path.to_str())
+        .and_then(custom_art)
+}
+
+// Pick any art at random, seeded from the clock.
+// Art without a smol version is fine, draw_layout falls back to the other sizes on its own.
+pub fn random_art(art_dir: Option<&str>) -> ArtSet {
+    let seed = SystemTime::now()
+        .duration_since(UNIX_EPOCH)
+        .map_or(0, |since| since.as_nanos() as usize);
+    seeded_art(art_dir, seed)
+}
+
+fn seeded_art(art_dir: Option<&str>, seed: usize) -> ArtSet {
+    let names = art_names(art_dir);
+    let (name, _, _) = &names[seed % names.len()];
+    // A file that disappeared since listing gets the default logo instead
+    named_art(name, art_dir).unwrap_or_else(default_art)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::configloader::Margins;
+    use crate::renderer::{Section, draw_layout_sized};
+    use crate::testutil::TempDir;
+
+    fn sections() -> Vec<Section> {
+        let lines = vec![
+            ("OS".to_string(), "Arch Linux".to_string()),
+            ("Kernel".to_string(), "6.12.10-arch1-1".to_string()),
+        ];
+        vec![Section::new("Core", lines)]
+    }
+
+    #[test]
+    fn os_argument_path_or_name() {
+        // Distro names, even when a file by that name sits in the current directory
+        for name in ["arch", "fedora", "nixos", "Ubuntu", ""] {
+            assert!(!is_art_path(name), "{:?}", name);
+        }
+        // Anything with a slash, relative or not, and .txt files
+        for path in ["./arch", "art/logo", "../logo", "/usr/share/art/logo", "~/logo", "logo.txt"] {
+            assert!(is_art_path(path), "{:?}", path);
+        }
+    }
+
+    #[test]
+    fn art_file_has_to_exist() {
+        let dir = TempDir::new();
+        let logo = dir.write("logo.txt", "{1} /\\\n{2}/  \\\n");
+        let empty = dir.write("empty.txt", "  \n\n");
+        let missing = dir.join("missing.txt");
+        let path = |path: &Path| path.to_str().unwrap().to_string();
+
+        assert_eq!(check_art_file(&path(&logo)), Ok(()));
+        assert_eq!(
+            check_art_file(&path(&missing)),
+            Err(format!("Art file {} doesn't exist", missing.display()))
+        );
+        // A directory isn't an art file either
+        assert!(check_art_file(&path(dir.path())).unwrap_err().ends_with("doesn't exist"));
+        assert_eq!(
+            check_art_file(&path(&empty)),
+            Err(format!("Can't read art file {}", empty.display()))
+        );
+    }
+
+    #[test]
+    fn tiny_terminal_renders_no_art() {
+        // Too narrow to put anything next to the 27 column box, too short to put anything
+        // above its 4 lines: sections-only without looking at the art
+        let art = default_art();
+        let (_, placement) =
+            draw_layout_sized(Some(&art), &sections(), None, None, Margins::default(), (30, 5));
+        assert!(placement.is_none());
+        assert_eq!(art.rendered(), 0);
+    }
+
+    #[test]
+    fn only_the_chosen_art_is_rendered() {
+        let art = default_art();
+        let (_, placement) =
+            draw_layout_sized(Some(&art), &sections(), None, None, Margins::default(), (200, 50));
+        assert!(placement.is_some());
+        assert_eq!(art.rendered(), 1);
+    }
+
+    // SteamOS gets its own art, not Arch's, and both its sizes
+    #[test]
+    fn steamos_art_from_the_os_name() {
+        for name in ["SteamOS", "SteamOS Holo", "steamos 3.6.19"] {
+            let art = os_art(name).unwrap();
+            assert_eq!(art.wide.source, ASCII_ART_STEAMOS, "{}", name);
+            assert_eq!(art.smol.map(|smol| smol.source), Some(ASCII_ART_STEAMOS_SMOL.into()));
+        }
+        assert_eq!(os_art("Arch Linux").unwrap().wide.source, ASCII_ART_ARCH);
+    }
+
+    #[test]
+    fn tabs_go_to_the_next_stop() {
+        assert_eq!(expand_tabs("\tx\n", 8), "        x\n");
+        assert_eq!(expand_tabs("ab\tc\td\n", 8), "ab      c       d\n");
+        assert_eq!(expand_tabs("ab\tc\n", 4), "ab  c\n");
+        // Color tokens take no columns, wide characters two
+        assert_eq!(expand_tabs("{1}ab\t{2}c\n", 4), "{1}ab  {2}c\n");
+        assert_eq!(expand_tabs("東\tx\n", 4), "東  x\n");
+        assert_eq!(expand_tabs("東京\tx\n", 4), "東京    x\n");
+        // A "{" that isn't a token is a column like any other
+        assert_eq!(expand_tabs("{x}\ty\n", 4), "{x} y\n");
+        // Trailing tabs, spaces and ideographic spaces all go, leading ones stay
+        assert_eq!(expand_tabs("\u{3000}x\u{3000}\u{3000}\t \n\t\n", 8), "\u{3000}x\n\n");
+        // No newline at the end of the file gets one
+        assert_eq!(expand_tabs("a\tb", 2), "a b\n");
+    }
+
+    // An art file aligned with tabs, with ideographic spaces and CJK mixed in: every line of
+    // the art is as wide as the widest, and the art box's right border runs straight down
+    #[test]
+    fn tabs_and_wide_spaces_keep_the_border_straight() {
+        let dir = TempDir::new();
+        let path = dir.write(
+            "logo.txt",
+            "{1}\t/\\\t\u{3000}\u{3000}\n\
+             {2}\t/  \\\t東京\n\
+             {3}\u{3000}/____\\\t\u{3000}x\n\
+             {4}\t\tend\t\t\n",
+        );
+        let art = custom_art(path.to_str().unwrap()).unwrap();
+        let widths: Vec<usize> = art.wide().iter().map(|line| visible_len(line)).collect();
+        // The second line is the widest, "東京" after the second tab stop
+        assert_eq!(widths, [20; 4]);
+
+        let (output, _) =
+            draw_layout_sized(Some(&art), &sections(), None, None, Margins::default(), (200, 50));
+        let output = crate::renderer::strip_escapes(&output);
+        // Column of the first border character after the art box's left edge, per row
+        let right_border = |row: &str| {
+            let mut column = 0;
+            for (i, c) in row.chars().enumerate() {
+                if i > 0 && matches!(c, '│' | '╮' | '╯') {
+                    return Some(column);
+                }
+                column += crate::renderer::char_width(c);
+            }
+            None
+        };
+        let borders: Vec<Option<usize>> = output.lines().map(right_border).collect();
+        assert_eq!(borders.len(), 6, "{}", output);
+        assert!(borders.iter().all(|&border| border == Some(23)), "{:?}\n{}", borders, output);
+    }
+
+    #[test]
+    fn art_dir_joins_the_registry() {
+        let dir = TempDir::new();
+        let zen = dir.write("zen.txt", "{1}  o\n{2} /|\\\n");
+        dir.write("arch.txt", "{1}not the arch logo\n");
+        dir.write("blank.txt", "\n  \n");
+        dir.write("notes.md", "{1}not art\n");
+        let art_dir = dir.path().to_str();
+
+        // Bundled first, then the art_dir files that pass custom_art's checks, by name.
+        // arch.txt can't be picked over the bundled arch, so it isn't listed.
+        let names = art_names(art_dir);
+        let from_dir: Vec<&str> = names
+            .iter()
+            .filter(|(_, from, _)| *from == "art_dir")
+            .map(|(name, _, _)| name.as_str())
+            .collect();
+        assert_eq!(from_dir, ["zen"]);
+        assert_eq!(names[0], ("default".to_string(), "bundled", false));
+        assert_eq!(names.len(), art_names(None).len() + 1);
+
+        let zen_art = named_art("zen", art_dir).unwrap();
+        assert_eq!(zen_art.wide(), custom_art(zen.to_str().unwrap()).unwrap().wide());
+        // A file named like bundled art doesn't shadow it
+        assert!(named_art("arch", art_dir).unwrap().smol().is_some());
+        assert!(named_art("blank", art_dir).is_none());
+        assert!(named_art("notes", art_dir).is_none());
+        assert!(named_art("zen", None).is_none());
+
+        // Every pick works, with or without a smol version
+        let smol: Vec<bool> =
+            (0..names.len()).map(|seed| seeded_art(art_dir, seed).smol().is_some()).collect();
+        let expected: Vec<bool> = names.iter().map(|(_, _, smol)| *smol).collect();
+        assert_eq!(smol, expected);
+    }
 }
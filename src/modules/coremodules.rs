@@ -1,9 +1,19 @@
 // Core system information modules for Slowfetch.
 
 use std::fs;
+use std::path::{Path, PathBuf};
+use std::process::Command;
+use std::sync::OnceLock;
+use std::time::Duration;
 
 use crate::cache;
-use crate::helpers::read_first_line;
+use crate::configloader::Event;
+use crate::dateformat::{days_from_civil, format_date};
+use crate::helpers::{
+    LocalTime, Precision, command_output_limited, compare_versions, format_duration,
+    json_array_len, json_string, local_time, local_time_at, read_first_line,
+};
+use crate::modules::hardwaremodules;
 
 // Get the OS name from /etc/os-release.
 // Uses persistent cache to avoid repeated file reads.
@@ -37,26 +47,1116 @@ fn os_fresh() -> String {
     "Linux".to_string()
 }
 
+// rpm-ostree talks to its daemon, give it a bit longer than the other probes
+const OSTREE_STATUS_MAX_BYTES: u64 = 256 * 1024;
+const OSTREE_STATUS_TIMEOUT: Duration = Duration::from_secs(2);
+
+// Booted rpm-ostree deployment (Silverblue, Kinoite, etc.)
+pub struct OstreeStatus {
+    // Image version, or the short commit when the image doesn't set one
+    pub deployment: String,
+    // Packages layered on top of the base image
+    pub layered: usize,
+}
+
+// Is this an image based system we know how to describe?
+// Only checks marker files, so ordinary distros pay nothing.
+pub fn is_immutable() -> bool {
+    Path::new("/run/ostree-booted").exists()
+        || Path::new("/etc/NIXOS").exists()
+        || Path::new("/usr/sbin/transactional-update").exists()
+}
+
+// OS line for image based distros, with the booted deployment tacked on:
+// "Fedora Silverblue 41 (deploy 41.20250110.0)", "NixOS 24.11 (Vicuna) (generation 412)".
+// Not cached, the deployment changes on every update + reboot.
+pub fn immutable_os(os_name: String) -> String {
+    let deployment = deployment();
+    if deployment.is_empty() {
+        return os_name;
+    }
+
+    // Fedora's PRETTY_NAME buries the variant ("Fedora Linux 41.20250110.0 (Silverblue)"),
+    // rebuild it as "Fedora Silverblue 41"
+    let os_name = ostree_os_name().unwrap_or(os_name);
+    format!("{} ({})", os_name, deployment)
+}
+
+// "Fedora Silverblue 41" from NAME, VARIANT and VERSION_ID in os-release
+fn ostree_os_name() -> Option<String> {
+    ostree_status()?;
+    variant_os_name(&fs::read_to_string("/etc/os-release").ok()?)
+}
+
+fn variant_os_name(content: &str) -> Option<String> {
+    let field = |key: &str| {
+        content
+            .lines()
+            .find_map(|line| line.strip_prefix(key)?.strip_prefix('='))
+            .map(|value| value.trim_matches(|c| c == '"' || c == '\''))
+    };
+    let name = field("NAME")?.trim_end_matches(" Linux");
+    let variant = field("VARIANT")?;
+    let version = field("VERSION_ID")?;
+    Some(format!("{} {} {}", name, variant, version))
+}
+
+// "deploy 41.20250110.0", "generation 412", "snapshot 57", empty if there's nothing to show
+fn deployment() -> String {
+    if let Some(status) = ostree_status() {
+        return format!("deploy {}", status.deployment);
+    }
+
+    // NixOS: the system profile points at system-<generation>-link
+    if let Ok(target) = fs::read_link(NIX_SYSTEM_PROFILE)
+        && let Some(generation) = nix_generation(&target)
+    {
+        return format!("generation {}", generation);
+    }
+
+    // openSUSE MicroOS/Aeon: / is mounted from /@/.snapshots/<n>/snapshot
+    if Path::new("/usr/sbin/transactional-update").exists()
+        && let Some(snapshot) = root_snapshot()
+    {
+        return format!("snapshot {}", snapshot);
+    }
+
+    String::new()
+}
+
+// The NixOS system profile, a symlink to the current system-<generation>-link
+const NIX_SYSTEM_PROFILE: &str = "/nix/var/nix/profiles/system";
+
+// NixOS leaves an empty /etc/NIXOS
+pub fn is_nixos() -> bool {
+    Path::new("/etc/NIXOS").exists()
+}
+
+// Generation number from a system profile link target, "system-142-link" -> 142
+fn nix_generation(target: &Path) -> Option<u32> {
+    target.file_name()?.to_str()?.strip_prefix("system-")?.strip_suffix("-link")?.parse().ok()
+}
+
+// Tree lines under the OS for nixos_detail: "generation 142 (2025-01-10)", "flakes" and
+// "store 48GB" when /nix is its own filesystem. Symlink reads and stats only.
+// Empty when this isn't NixOS.
+pub fn nixos_detail() -> Vec<String> {
+    if !is_nixos() {
+        return Vec::new();
+    }
+    let mut lines = nix_profile_detail(Path::new("/"));
+    if let Some(size) = hardwaremodules::nix_store_size() {
+        lines.push(format!("store {}", size));
+    }
+    lines
+}
+
+// The generation and flakes lines of nixos_detail() with every path under `root`
+fn nix_profile_detail(root: &Path) -> Vec<String> {
+    let under = |path: &str| root.join(path.trim_start_matches('/'));
+    let mut lines = Vec::new();
+
+    let profile = under(NIX_SYSTEM_PROFILE);
+    if let Ok(target) = fs::read_link(&profile)
+        && let Some(generation) = nix_generation(&target)
+    {
+        // The generation link is made when the system is built, its mtime is the build date.
+        // The target is relative to the profiles directory.
+        let link = profile.with_file_name(target.file_name().unwrap_or_default());
+        let built = fs::symlink_metadata(link)
+            .and_then(|metadata| metadata.modified())
+            .ok()
+            .and_then(|time| time.duration_since(std::time::UNIX_EPOCH).ok())
+            .and_then(|age| local_time_at(age.as_secs() as libc::time_t));
+        lines.push(match built {
+            Some(built) => {
+                format!("generation {} ({})", generation, format_date(&built, "%Y-%m-%d"))
+            }
+            None => format!("generation {}", generation),
+        });
+    }
+
+    if under("/etc/nixos/flake.nix").exists() {
+        lines.push("flakes".to_string());
+    }
+    lines
+}
+
+// Ask rpm-ostree about the booted deployment, once per run.
+// Shared between the OS line and the package count.
+pub fn ostree_status() -> Option<&'static OstreeStatus> {
+    static STATUS: OnceLock<Option<OstreeStatus>> = OnceLock::new();
+    STATUS
+        .get_or_init(|| {
+            if !Path::new("/run/ostree-booted").exists() {
+                return None;
+            }
+            let output = command_output_limited(
+                Command::new("rpm-ostree").args(["status", "--json", "--booted"]),
+                OSTREE_STATUS_MAX_BYTES,
+                OSTREE_STATUS_TIMEOUT,
+            )?;
+            parse_ostree_status(&String::from_utf8_lossy(&output))
+        })
+        .as_ref()
+}
+
+// Pull the version (or commit) and layered package count out of `rpm-ostree status --json`.
+// With --booted the only deployment listed is the booted one, so the first match is ours.
+fn parse_ostree_status(json: &str) -> Option<OstreeStatus> {
+    let deployments = &json[json.find("\"deployments\"")?..];
+    let deployment = json_string(deployments, "version").or_else(|| {
+        json_string(deployments, "checksum").map(|commit| &commit[..commit.len().min(10)])
+    })?;
+    let layered = json_array_len(deployments, "packages").unwrap_or(0);
+
+    Some(OstreeStatus {
+        deployment: deployment.to_string(),
+        layered,
+    })
+}
+
+// Snapshot number the root filesystem is mounted from, from /proc/self/mountinfo
+fn root_snapshot() -> Option<String> {
+    snapshot_from_mountinfo(&fs::read_to_string("/proc/self/mountinfo").ok()?)
+}
+
+fn snapshot_from_mountinfo(mountinfo: &str) -> Option<String> {
+    mountinfo.lines().find_map(|line| {
+        // Fields: id parent major:minor root mount_point ...
+        let mut fields = line.split_whitespace().skip(3);
+        let root = fields.next()?;
+        if fields.next()? != "/" {
+            return None;
+        }
+        let snapshot = root.split("/.snapshots/").nth(1)?.strip_suffix("/snapshot")?;
+        snapshot
+            .chars()
+            .all(|c| c.is_ascii_digit())
+            .then(|| snapshot.to_string())
+    })
+}
+
+// Homepages for distros whose os-release has no HOME_URL
+const DISTRO_URLS: &[(&str, &str)] = &[
+    ("Arch", "https://archlinux.org"),
+    ("CachyOS", "https://cachyos.org"),
+    ("Debian", "https://www.debian.org"),
+    ("EndeavourOS", "https://endeavouros.com"),
+    ("Fedora", "https://fedoraproject.org"),
+    ("Gentoo", "https://www.gentoo.org"),
+    ("Manjaro", "https://manjaro.org"),
+    ("Mint", "https://linuxmint.com"),
+    ("NixOS", "https://nixos.org"),
+    ("openSUSE", "https://www.opensuse.org"),
+    ("Pop!_OS", "https://pop.system76.com"),
+    ("Ubuntu", "https://ubuntu.com"),
+    ("Void", "https://voidlinux.org"),
+];
+
+// Distro homepage for the OS hyperlink, os-release HOME_URL first
+pub fn os_url(os_name: &str) -> Option<String> {
+    if let Ok(content) = fs::read_to_string("/etc/os-release")
+        && let Some(url) = content.lines().find_map(|line| line.strip_prefix("HOME_URL="))
+    {
+        let url = url.trim_matches(|c| c == '"' || c == '\'');
+        if !url.is_empty() {
+            return Some(url.to_string());
+        }
+    }
+
+    DISTRO_URLS
+        .iter()
+        .find(|(name, _)| os_name.contains(name))
+        .map(|(_, url)| url.to_string())
+}
+
 // Get the kernel version
 pub fn kernel() -> String {
     read_first_line("/proc/sys/kernel/osrelease").unwrap_or_else(|| "unknown".to_string())
 }
 
-// Get the system uptime
-pub fn uptime() -> String {
-    if let Ok(content) = fs::read_to_string("/proc/uptime") {
-        if let Some(seconds_str) = content.split_whitespace().next() {
-            if let Ok(seconds) = seconds_str.parse::<f64>() {
-                let s = seconds as u64;
-                let h = s / 3600;
-                let m = (s % 3600) / 60;
-                if h > 0 {
-                    return format!("{}h {}m", h, m);
-                } else {
-                    return format!("{}m", m);
-                }
+// How long cmd.exe gets to answer, starting Windows processes from WSL is slow
+const WINDOWS_VERSION_TIMEOUT: Duration = Duration::from_secs(2);
+
+// Windows feature updates by build number, "ver" only gives the build
+const WINDOWS_RELEASES: &[(u32, &str)] = &[
+    (26100, "24H2"),
+    (22631, "23H2"),
+    (22621, "22H2"),
+    (22000, "21H2"),
+    (19045, "22H2"),
+    (19044, "21H2"),
+    (19043, "21H1"),
+    (19042, "20H2"),
+    (19041, "2004"),
+];
+
+// The Windows version WSL runs on ("10.0.22631.3880"), "unknown" without WSL interop.
+// Uses persistent cache, cmd.exe takes a few hundred ms to start.
+pub fn windows_version() -> String {
+    if let Some(cached) = cache::get_cached_windows() {
+        return cached;
+    }
+    let Some(version) = windows_version_fresh() else {
+        return "unknown".to_string();
+    };
+    cache::cache_windows(&version);
+    version
+}
+
+// Ask cmd.exe through WSL interop, it says "Microsoft Windows [Version 10.0.22631.3880]".
+// Interop can be turned off in wsl.conf, then there's no asking.
+fn windows_version_fresh() -> Option<String> {
+    let interop = std::env::var_os("WSL_INTEROP").is_some()
+        || Path::new("/proc/sys/fs/binfmt_misc/WSLInterop").exists();
+    if !interop {
+        return None;
+    }
+    // Windows' PATH is usually appended to WSL's, the default install location otherwise
+    let cmd = ["cmd.exe", "/mnt/c/Windows/System32/cmd.exe"].into_iter().find_map(|cmd| {
+        let mut command = Command::new(cmd);
+        command.args(["/c", "ver"]);
+        command_output_limited(&mut command, 4096, WINDOWS_VERSION_TIMEOUT)
+    })?;
+    parse_windows_ver(&String::from_utf8_lossy(&cmd))
+}
+
+// "Microsoft Windows [Version 10.0.22631.3880]" -> "10.0.22631.3880"
+fn parse_windows_ver(output: &str) -> Option<String> {
+    let start = output.find("[Version ")? + "[Version ".len();
+    let version = output[start..].split(']').next()?.trim();
+    version.starts_with(|c: char| c.is_ascii_digit()).then(|| version.to_string())
+}
+
+// "10.0.22631.3880" -> "Windows 11 23H2". Windows 11 kept the 10.0 version, only its builds
+// start at 22000.
+pub fn windows_name(version: &str) -> String {
+    let Some(build) = version.split('.').nth(2).and_then(|build| build.parse::<u32>().ok()) else {
+        return "Windows".to_string();
+    };
+    let name = if build >= 22000 { "Windows 11" } else { "Windows 10" };
+    match WINDOWS_RELEASES.iter().find(|(release, _)| *release == build) {
+        Some((_, release)) => format!("{} {}", name, release),
+        None => name.to_string(),
+    }
+}
+
+// A newer kernel than the running one that's installed and waiting on a reboot, None when
+// the running kernel is the newest of its flavour (or it can't be told)
+pub fn pending_kernel(running: &str) -> Option<String> {
+    newer_kernel(running, Path::new("/usr/lib/modules"), Path::new("/boot"))
+}
+
+fn newer_kernel(running: &str, modules: &Path, boot: &Path) -> Option<String> {
+    // Arch keeps the running kernel's modules directory until the next boot only with a hook,
+    // usually it's gone after an upgrade and the flavour has to do
+    let running_base = read_first_line(&modules.join(running).join("pkgbase").to_string_lossy());
+    let flavour = kernel_flavour(running);
+    installed_kernels(modules, boot)
+        .into_iter()
+        .filter(|(release, base)| match (&running_base, base) {
+            (Some(running_base), Some(base)) => running_base == base,
+            _ => kernel_flavour(release) == flavour,
+        })
+        .map(|(release, _)| release)
+        .max_by(|a, b| compare_versions(a, b))
+        .filter(|newest| compare_versions(newest, running).is_gt())
+}
+
+// Installed kernel releases and their pkgbase. Arch: the /usr/lib/modules directories with a
+// pkgbase file (leftovers from DKMS don't have one). Elsewhere the /boot/vmlinuz-<release>
+// files, skipping Fedora's vmlinuz-0-rescue-<machine id>.
+fn installed_kernels(modules: &Path, boot: &Path) -> Vec<(String, Option<String>)> {
+    let names = |dir: &Path| -> Vec<String> {
+        fs::read_dir(dir)
+            .map(|entries| {
+                entries.flatten().map(|entry| entry.file_name().to_string_lossy().into()).collect()
+            })
+            .unwrap_or_default()
+    };
+    let arch: Vec<(String, Option<String>)> = names(modules)
+        .into_iter()
+        .filter_map(|release| {
+            let base = read_first_line(&modules.join(&release).join("pkgbase").to_string_lossy())?;
+            Some((release, Some(base)))
+        })
+        .collect();
+    if !arch.is_empty() {
+        return arch;
+    }
+    names(boot)
+        .into_iter()
+        .filter_map(|name| name.strip_prefix("vmlinuz-").map(str::to_string))
+        .filter(|release| {
+            release.starts_with(|c: char| c.is_ascii_digit()) && !release.contains("rescue")
+        })
+        .map(|release| (release, None))
+        .collect()
+}
+
+// The words in a kernel release, which kernel package it comes from: "6.11.2-arch1-1" and
+// "6.12.4-arch1-1" are both ["arch"], "6.6.50-1-lts" is ["lts"], "6.1.0-18-amd64" ["amd"].
+// "rc" is part of the version, not the flavour.
+fn kernel_flavour(release: &str) -> Vec<&str> {
+    release
+        .split(|c: char| !c.is_ascii_alphabetic())
+        .filter(|word| !word.is_empty() && !word.eq_ignore_ascii_case("rc"))
+        .collect()
+}
+
+// Get the system uptime in seconds from /proc/uptime
+pub fn uptime_seconds() -> Option<f64> {
+    let content = fs::read_to_string("/proc/uptime").ok()?;
+    content.split_whitespace().next()?.parse::<f64>().ok()
+}
+
+// Get the system uptime, and the seconds it was made from for --json
+pub fn uptime(precision: Precision) -> (String, Option<f64>) {
+    let seconds = uptime_seconds();
+    match seconds {
+        Some(seconds) => (format_duration(seconds as u64, precision), Some(seconds)),
+        None => ("unknown".to_string(), None),
+    }
+}
+
+// Today's date and the nearest [[event]] still to come, "Feb 12 · 18 days until Vacation".
+// Empty without events, past events are skipped.
+pub fn today(events: &[Event]) -> String {
+    if events.is_empty() {
+        return String::new();
+    }
+    match local_time() {
+        Some(now) => today_at(&now, events),
+        None => "unknown".to_string(),
+    }
+}
+
+// today() on the day `now`
+fn today_at(now: &LocalTime, events: &[Event]) -> String {
+    let date = format!("{} {}", format_date(now, "%b"), now.day);
+    let today = days_from_civil(now.year, now.month, now.day);
+    let next = events
+        .iter()
+        .map(|event| {
+            let (year, month, day) = event.date;
+            (days_from_civil(year, month, day) - today, &event.name)
+        })
+        .filter(|(days, _)| *days >= 0)
+        .min_by_key(|(days, _)| *days);
+    match next {
+        Some((0, name)) => format!("{} · {} is today", date, name),
+        Some((1, name)) => format!("{} · 1 day until {}", date, name),
+        Some((days, name)) => format!("{} · {} days until {}", date, days, name),
+        None => date,
+    }
+}
+
+// Get the 1/5/15 minute load averages with the core count to judge them by,
+// e.g. "0.52 0.48 0.45 (16 cores)", and the averages as numbers for --json
+pub fn load() -> (String, Option<[f64; 3]>) {
+    let Ok(content) = fs::read_to_string("/proc/loadavg") else {
+        return ("unknown".to_string(), None);
+    };
+    let cores = hardwaremodules::physical_core_count()
+        .or_else(|| std::thread::available_parallelism().ok().map(|n| n.get()));
+    (load_from(&content, cores), load_averages(&content))
+}
+
+// load over a /proc/loadavg content, "0.52 0.48 0.45 1/1234 5678"
+fn load_from(content: &str, cores: Option<usize>) -> String {
+    let averages: Vec<&str> = content.split_whitespace().take(3).collect();
+    if load_averages(content).is_none() {
+        return "unknown".to_string();
+    }
+
+    match cores {
+        Some(1) => format!("{} (1 core)", averages.join(" ")),
+        Some(cores) => format!("{} ({} cores)", averages.join(" "), cores),
+        None => averages.join(" "),
+    }
+}
+
+// The three averages of a /proc/loadavg content
+fn load_averages(content: &str) -> Option<[f64; 3]> {
+    let mut averages = content.split_whitespace().map(|average| average.parse::<f64>().ok());
+    Some([averages.next()??, averages.next()??, averages.next()??])
+}
+
+// Where the kernel exposes EFI variables, one file per variable named <name>-<guid>
+const EFIVARS_DIR: &str = "/sys/firmware/efi/efivars";
+
+// EFI global variable GUID, Boot#### entries live under this vendor
+const EFI_GLOBAL_GUID: &str = "8be4df61-93ca-11d2-aa0d-00e098032b8c";
+
+// systemd-boot's vendor GUID, it leaves LoaderInfo ("systemd-boot 255.4") under it
+const LOADER_GUID: &str = "4a67b082-0a4c-41cf-b6c7-440b29bb8c4f";
+
+// Where the ESP gets mounted
+const ESP_DIRS: &[&str] = &["/boot/efi", "/boot", "/efi"];
+
+// Where a Windows bootloader shows up on the ESP, depending on how it's mounted
+const WINDOWS_EFI_DIRS: &[&str] = &[
+    "/boot/efi/EFI/Microsoft",
+    "/boot/EFI/Microsoft",
+    "/efi/EFI/Microsoft",
+];
+
+// Boot entry description keyword -> display name
+const BOOT_OS_NAMES: &[(&str, &str)] = &[
+    ("windows", "Windows"),
+    ("mac os", "macOS"),
+    ("ubuntu", "Ubuntu"),
+    ("fedora", "Fedora"),
+    ("debian", "Debian"),
+    ("opensuse", "openSUSE"),
+    ("manjaro", "Manjaro"),
+    ("cachyos", "CachyOS"),
+    ("endeavouros", "EndeavourOS"),
+    ("pop!_os", "Pop!_OS"),
+    ("nixos", "NixOS"),
+    ("arch", "Arch"),
+];
+
+// Get other operating systems from the EFI boot entries.
+// Returns a "Dual boot" line when other OSes are found, a "Boot entries" count otherwise,
+// or nothing when the entries can't be read (permissions, BIOS boot, etc).
+pub fn boot_entries() -> Vec<(String, String)> {
+    let descriptions = boot_descriptions_from_efivars(Path::new(EFIVARS_DIR))
+        .filter(|d| !d.is_empty())
+        .or_else(boot_descriptions_from_efibootmgr)
+        .unwrap_or_default();
+
+    let current_os = os().to_lowercase();
+    let mut others: Vec<&str> = Vec::new();
+    for description in &descriptions {
+        let lower = description.to_lowercase();
+        if let Some((keyword, name)) = BOOT_OS_NAMES.iter().find(|(k, _)| lower.contains(k)) {
+            // Skip our own entry and duplicates (e.g. two Windows entries)
+            if !current_os.contains(keyword) && !others.contains(name) {
+                others.push(name);
+            }
+        }
+    }
+
+    // Windows bootloader on the ESP without its own boot entry (e.g. chainloaded from GRUB)
+    if !others.contains(&"Windows") && WINDOWS_EFI_DIRS.iter().any(|d| Path::new(d).exists()) {
+        others.push("Windows");
+    }
+
+    if !others.is_empty() {
+        vec![("Dual boot".to_string(), others.join(", "))]
+    } else if !descriptions.is_empty() {
+        vec![("Boot entries".to_string(), descriptions.len().to_string())]
+    } else {
+        vec![]
+    }
+}
+
+// Read Boot#### descriptions straight from efivarfs.
+// Returns None if efivars aren't there or can't be listed.
+fn boot_descriptions_from_efivars(efivars: &Path) -> Option<Vec<String>> {
+    let entries = fs::read_dir(efivars).ok()?;
+    let mut descriptions = Vec::new();
+
+    for entry in entries.flatten() {
+        let name = entry.file_name();
+        let name = name.to_string_lossy();
+
+        // Boot0000-<guid>, four hex digits only (skips BootOrder, BootCurrent etc)
+        let Some(rest) = name.strip_prefix("Boot") else {
+            continue;
+        };
+        let Some((number, guid)) = rest.split_once('-') else {
+            continue;
+        };
+        if number.len() != 4
+            || !number.bytes().all(|b| b.is_ascii_hexdigit())
+            || guid != EFI_GLOBAL_GUID
+        {
+            continue;
+        }
+
+        // Reading can fail with EPERM on locked down systems, just skip those
+        if let Some(data) = read_efivar_in(efivars, &format!("Boot{}", number), guid)
+            && let Some(description) = parse_load_option_description(&data)
+        {
+            descriptions.push(description);
+        }
+    }
+
+    Some(descriptions)
+}
+
+// Read an EFI variable from efivarfs, without the u32 attributes efivarfs puts in front.
+// None when it's not there or can't be read (EPERM, immutable on locked down systems).
+fn read_efivar_in(efivars: &Path, name: &str, guid: &str) -> Option<Vec<u8>> {
+    let mut data = fs::read(efivars.join(format!("{}-{}", name, guid))).ok()?;
+    (data.len() > 4).then(|| data.split_off(4))
+}
+
+// Null terminated UTF-16LE text, how EFI variables store strings
+fn efi_string(bytes: &[u8]) -> Option<String> {
+    let units: Vec<u16> = bytes
+        .chunks_exact(2)
+        .map(|pair| u16::from_le_bytes([pair[0], pair[1]]))
+        .take_while(|&unit| unit != 0)
+        .collect();
+    String::from_utf16(&units).ok()
+}
+
+// Pull the description out of a Boot#### variable, an EFI_LOAD_OPTION:
+// u32 Attributes, u16 FilePathListLength, null terminated UTF-16LE Description, device paths...
+fn parse_load_option_description(data: &[u8]) -> Option<String> {
+    let description = efi_string(data.get(6..)?)?;
+    let description = description.trim();
+    if description.is_empty() {
+        None
+    } else {
+        Some(description.to_string())
+    }
+}
+
+// The bootloader and Secure Boot state, "systemd-boot · Secure Boot off".
+// Either half is left out when it can't be found out, "unknown" when neither can.
+pub fn bootloader() -> String {
+    bootloader_in(Path::new("/"))
+}
+
+// bootloader() with every path under `root`
+fn bootloader_in(root: &Path) -> String {
+    let under = |path: &str| root.join(path.trim_start_matches('/'));
+    let efivars = under(EFIVARS_DIR);
+    let secure_boot =
+        secure_boot(&efivars).map(|on| if on { "Secure Boot on" } else { "Secure Boot off" });
+    match (find_bootloader(&under, &efivars), secure_boot) {
+        (Some(loader), Some(secure_boot)) => format!("{} · {}", loader, secure_boot),
+        (Some(loader), None) => loader.to_string(),
+        (None, Some(secure_boot)) => secure_boot.to_string(),
+        (None, None) => "unknown".to_string(),
+    }
+}
+
+// Which bootloader is installed. systemd-boot says so in LoaderInfo when it booted us,
+// otherwise it's its loader.conf on the ESP (rEFInd its EFI directory, both EFI only),
+// and GRUB its grub.cfg. `under` puts an absolute path where it is on this system.
+fn find_bootloader(under: &dyn Fn(&str) -> PathBuf, efivars: &Path) -> Option<&'static str> {
+    if under("/sys/firmware/efi").exists() {
+        if read_efivar_in(efivars, "LoaderInfo", LOADER_GUID)
+            .and_then(|data| efi_string(&data))
+            .is_some_and(|info| info.starts_with("systemd-boot"))
+        {
+            return Some("systemd-boot");
+        }
+        for esp in ESP_DIRS.iter().map(|esp| under(esp)) {
+            if esp.join("loader/loader.conf").exists() {
+                return Some("systemd-boot");
+            }
+            if esp.join("EFI/refind").exists() {
+                return Some("rEFInd");
             }
         }
     }
-    "unknown".to_string()
+    ["/boot/grub/grub.cfg", "/boot/grub2/grub.cfg"]
+        .iter()
+        .any(|path| under(path).exists())
+        .then_some("GRUB")
+}
+
+// SecureBoot is a single byte after the attributes, 1 = enabled
+fn secure_boot(efivars: &Path) -> Option<bool> {
+    let data = read_efivar_in(efivars, "SecureBoot", EFI_GLOBAL_GUID)?;
+    Some(data.last() == Some(&1))
+}
+
+// Fallback for when efivars can't be read directly: parse `efibootmgr` output
+// Lines look like "Boot0001* Windows Boot Manager\tHD(1,GPT,...)"
+fn boot_descriptions_from_efibootmgr() -> Option<Vec<String>> {
+    let output = Command::new("efibootmgr").output().ok()?;
+    if !output.status.success() {
+        return None;
+    }
+    let stdout = String::from_utf8_lossy(&output.stdout);
+
+    let descriptions: Vec<String> = stdout
+        .lines()
+        .filter_map(|line| {
+            let rest = line.strip_prefix("Boot")?;
+            let number = rest.get(..4)?;
+            if !number.bytes().all(|b| b.is_ascii_hexdigit()) {
+                return None;
+            }
+            let description = rest[4..].trim_start_matches('*').trim_start();
+            let description = description.split('\t').next().unwrap_or(description).trim();
+            (!description.is_empty()).then(|| description.to_string())
+        })
+        .collect();
+
+    Some(descriptions)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::testutil::TempDir;
+
+    // UTF-16LE bytes of a string, without the terminating null
+    fn utf16(text: &str) -> Vec<u8> {
+        text.encode_utf16().flat_map(u16::to_le_bytes).collect()
+    }
+
+    // An EFI_LOAD_OPTION laid out like firmware writes them: active attribute, the file path
+    // list length, the null terminated description, a hard drive + file path device path and
+    // the optional data Windows puts after it
+    fn load_option(description: &str) -> Vec<u8> {
+        let mut path = vec![0x04, 0x01, 0x2a, 0x00];
+        path.extend([0u8; 38]);
+        let file = utf16("\\EFI\\Microsoft\\Boot\\bootmgfw.efi\0");
+        path.extend([0x04, 0x04, (file.len() + 4) as u8, 0x00]);
+        path.extend(file);
+        path.extend([0x7f, 0xff, 0x04, 0x00]);
+
+        let mut data = vec![0x01, 0x00, 0x00, 0x00];
+        data.extend((path.len() as u16).to_le_bytes());
+        data.extend(utf16(description));
+        data.extend([0, 0]);
+        data.extend(path);
+        data.extend(b"WINDOWS\0\x01\0\0\0");
+        data
+    }
+
+    // The file efivarfs shows: the u32 attributes (non-volatile, boot and runtime access) first
+    fn efivar_file(data: &[u8]) -> Vec<u8> {
+        let mut file = vec![0x07, 0x00, 0x00, 0x00];
+        file.extend(data);
+        file
+    }
+
+    #[test]
+    fn load_option_description_is_read() {
+        let windows = load_option("Windows Boot Manager");
+        let description = parse_load_option_description(&windows);
+        assert_eq!(description.as_deref(), Some("Windows Boot Manager"));
+        let padded = load_option("  Fedora  ");
+        assert_eq!(parse_load_option_description(&padded).as_deref(), Some("Fedora"));
+        assert_eq!(parse_load_option_description(&load_option("")), None);
+        assert_eq!(parse_load_option_description(&load_option("   ")), None);
+    }
+
+    #[test]
+    fn broken_descriptions() {
+        let header = [0x01, 0x00, 0x00, 0x00, 0x00, 0x00];
+        // No terminating null, the description runs to the end of the data
+        let unterminated = [&header[..], &utf16("Linux Boot Manager")].concat();
+        let description = parse_load_option_description(&unterminated);
+        assert_eq!(description.as_deref(), Some("Linux Boot Manager"));
+        // A dangling half of a UTF-16 unit is dropped
+        let odd = [&header[..], &utf16("ubuntu"), &[0x41]].concat();
+        assert_eq!(parse_load_option_description(&odd).as_deref(), Some("ubuntu"));
+        // A lone surrogate isn't text
+        let surrogate = [&header[..], &[0x00, 0xd8, 0x41, 0x00, 0x00, 0x00]].concat();
+        assert_eq!(parse_load_option_description(&surrogate), None);
+    }
+
+    #[test]
+    fn too_short_for_the_header() {
+        assert_eq!(parse_load_option_description(&[]), None);
+        assert_eq!(parse_load_option_description(&[0x01, 0x00, 0x00, 0x00, 0x74]), None);
+        assert_eq!(parse_load_option_description(&[0x01, 0x00, 0x00, 0x00, 0x74, 0x00]), None);
+    }
+
+    #[test]
+    fn boot_entries_from_an_efivars_tree() {
+        let efivars = TempDir::new();
+        let var = |name: &str, guid: &str, data: &[u8]| {
+            efivars.write(&format!("{}-{}", name, guid), efivar_file(data));
+        };
+        var("Boot0000", EFI_GLOBAL_GUID, &load_option("Windows Boot Manager"));
+        var("Boot001F", EFI_GLOBAL_GUID, &load_option("ubuntu"));
+        // Not boot entries: the order list, another vendor's variable, a bad number
+        var("BootOrder", EFI_GLOBAL_GUID, &[0x00, 0x00, 0x1f, 0x00]);
+        var("Boot0002", LOADER_GUID, &load_option("Not a boot entry"));
+        var("Boot00G1", EFI_GLOBAL_GUID, &load_option("Not hex"));
+        // Shorter than the 10 bytes of attributes and load option header
+        efivars.write(&format!("Boot0003-{}", EFI_GLOBAL_GUID), [0x07, 0, 0, 0, 0x01, 0, 0, 0]);
+        // Nothing after the attributes
+        efivars.write(&format!("Boot0004-{}", EFI_GLOBAL_GUID), [0x07, 0, 0, 0]);
+
+        let mut descriptions = boot_descriptions_from_efivars(efivars.path()).unwrap();
+        descriptions.sort();
+        assert_eq!(descriptions, ["Windows Boot Manager", "ubuntu"]);
+        assert_eq!(read_efivar_in(efivars.path(), "Boot0004", EFI_GLOBAL_GUID), None);
+        assert_eq!(boot_descriptions_from_efivars(&efivars.join("missing")), None);
+    }
+
+    // A filesystem root with efivars, and whatever else each case adds
+    fn efi_root() -> TempDir {
+        let root = TempDir::new();
+        root.write("sys/firmware/efi/fw_platform_size", "64\n");
+        root
+    }
+
+    fn efivar(root: &TempDir, name: &str, guid: &str, data: &[u8]) {
+        root.write(&format!("sys/firmware/efi/efivars/{}-{}", name, guid), efivar_file(data));
+    }
+
+    #[test]
+    fn secure_boot_states() {
+        let root = efi_root();
+        let efivars = root.join("sys/firmware/efi/efivars");
+        // Missing or unreadable, there's no telling
+        assert_eq!(secure_boot(&efivars), None);
+        efivar(&root, "SecureBoot", EFI_GLOBAL_GUID, &[1]);
+        assert_eq!(secure_boot(&efivars), Some(true));
+        efivar(&root, "SecureBoot", EFI_GLOBAL_GUID, &[0]);
+        assert_eq!(secure_boot(&efivars), Some(false));
+        // Just the attributes, nothing to read
+        efivar(&root, "SecureBoot", EFI_GLOBAL_GUID, &[]);
+        assert_eq!(secure_boot(&efivars), None);
+    }
+
+    #[test]
+    fn bootloader_layouts() {
+        // systemd-boot that booted us says so, whatever's on the ESP
+        let root = efi_root();
+        let info = [utf16("systemd-boot 255.4"), vec![0, 0]].concat();
+        efivar(&root, "LoaderInfo", LOADER_GUID, &info);
+        efivar(&root, "SecureBoot", EFI_GLOBAL_GUID, &[0]);
+        root.write("boot/grub/grub.cfg", "");
+        assert_eq!(bootloader_in(root.path()), "systemd-boot · Secure Boot off");
+
+        // Its loader.conf on any of the ESP mount points
+        for esp in ["boot/efi", "boot", "efi"] {
+            let root = efi_root();
+            root.write(&format!("{}/loader/loader.conf", esp), "timeout 3\n");
+            efivar(&root, "SecureBoot", EFI_GLOBAL_GUID, &[1]);
+            assert_eq!(bootloader_in(root.path()), "systemd-boot · Secure Boot on", "{}", esp);
+        }
+
+        let root = efi_root();
+        root.write("boot/efi/EFI/refind/refind.conf", "");
+        assert_eq!(bootloader_in(root.path()), "rEFInd");
+
+        // GRUB on EFI and on BIOS, where there's no Secure Boot to speak of
+        let root = efi_root();
+        root.write("boot/grub2/grub.cfg", "");
+        efivar(&root, "SecureBoot", EFI_GLOBAL_GUID, &[1]);
+        assert_eq!(bootloader_in(root.path()), "GRUB · Secure Boot on");
+        let root = TempDir::new();
+        root.write("boot/grub/grub.cfg", "");
+        assert_eq!(bootloader_in(root.path()), "GRUB");
+        // A loader.conf without EFI isn't systemd-boot
+        root.write("boot/loader/loader.conf", "");
+        assert_eq!(bootloader_in(root.path()), "GRUB");
+
+        // Only Secure Boot known, then nothing at all
+        let root = efi_root();
+        efivar(&root, "SecureBoot", EFI_GLOBAL_GUID, &[0]);
+        assert_eq!(bootloader_in(root.path()), "Secure Boot off");
+        assert_eq!(bootloader_in(TempDir::new().path()), "unknown");
+    }
+
+    #[test]
+    fn load_averages_with_cores() {
+        let loadavg = "0.52 0.48 0.45 2/1234 56789\n";
+        assert_eq!(load_from(loadavg, Some(16)), "0.52 0.48 0.45 (16 cores)");
+        assert_eq!(load_from(loadavg, Some(1)), "0.52 0.48 0.45 (1 core)");
+        assert_eq!(load_from(loadavg, None), "0.52 0.48 0.45");
+        // A busy box and an idle one
+        let busy = "128.07 96.30 64.00 130/2048 1\n";
+        assert_eq!(load_from(busy, Some(64)), "128.07 96.30 64.00 (64 cores)");
+        assert_eq!(load_from("0.00 0.00 0.00 1/90 12\n", Some(2)), "0.00 0.00 0.00 (2 cores)");
+    }
+
+    #[test]
+    fn load_unknown_on_garbage() {
+        assert_eq!(load_from("", Some(8)), "unknown");
+        assert_eq!(load_from("0.52 0.48\n", Some(8)), "unknown");
+        assert_eq!(load_from("load: high\n", Some(8)), "unknown");
+        assert_eq!(load_averages("0.52 0.48\n"), None);
+    }
+
+    #[test]
+    fn load_averages_as_numbers() {
+        assert_eq!(load_averages("0.52 0.48 0.45 2/1234 56789\n"), Some([0.52, 0.48, 0.45]));
+        assert_eq!(load_averages("128.07 96.30 64.00 130/2048 1\n"), Some([128.07, 96.3, 64.0]));
+    }
+
+    // A NixOS root with the system profile on generation 142 the way nixos-rebuild leaves it
+    // (relative targets), and the older generation 141 next to it
+    fn nix_root() -> TempDir {
+        let root = TempDir::new();
+        root.write("etc/NIXOS", "");
+        let store = root.join("nix/store/abc123-nixos-system-nixos-24.11");
+        fs::create_dir_all(&store).unwrap();
+        root.symlink("nix/var/nix/profiles/system-141-link", &store);
+        root.symlink("nix/var/nix/profiles/system-142-link", &store);
+        root.symlink("nix/var/nix/profiles/system", "system-142-link");
+        root
+    }
+
+    #[test]
+    fn generation_from_link_targets() {
+        assert_eq!(nix_generation(Path::new("system-142-link")), Some(142));
+        assert_eq!(nix_generation(Path::new("/nix/var/nix/profiles/system-7-link")), Some(7));
+        // Anything that isn't a system-<n>-link
+        for target in [
+            "system-abc-link",
+            "system--link",
+            "system-142",
+            "142-link",
+            "system-1.2-link",
+            "/nix/store/abc123-nixos-system-nixos-24.11",
+            "",
+        ] {
+            assert_eq!(nix_generation(Path::new(target)), None, "{}", target);
+        }
+    }
+
+    #[test]
+    fn generation_and_flakes_from_a_profile_tree() {
+        let root = nix_root();
+        // The build date is the generation link's own mtime, not the store path's
+        let link = root.join("nix/var/nix/profiles/system-142-link");
+        let built: libc::time_t = 1736510400;
+        let path = std::ffi::CString::new(link.as_os_str().as_encoded_bytes()).unwrap();
+        let times = [libc::timespec { tv_sec: built, tv_nsec: 0 }; 2];
+        // SAFETY: a valid path and two timespecs, the link itself gets the times
+        let set = unsafe {
+            libc::utimensat(
+                libc::AT_FDCWD,
+                path.as_ptr(),
+                times.as_ptr(),
+                libc::AT_SYMLINK_NOFOLLOW,
+            )
+        };
+        assert_eq!(set, 0);
+        let date = format_date(&local_time_at(built).unwrap(), "%Y-%m-%d");
+        assert_eq!(nix_profile_detail(root.path()), [format!("generation 142 ({})", date)]);
+
+        root.write("etc/nixos/flake.nix", "{ }\n");
+        assert_eq!(nix_profile_detail(root.path())[1], "flakes");
+    }
+
+    #[test]
+    fn profile_trees_without_a_generation() {
+        // An absolute target works the same
+        let root = nix_root();
+        fs::remove_file(root.join("nix/var/nix/profiles/system")).unwrap();
+        root.symlink("nix/var/nix/profiles/system", "/nix/var/nix/profiles/system-142-link");
+        assert!(nix_profile_detail(root.path())[0].starts_with("generation 142 ("));
+
+        // Pointing at a generation link that's gone, the date is left out
+        let root = nix_root();
+        fs::remove_file(root.join("nix/var/nix/profiles/system-142-link")).unwrap();
+        assert_eq!(nix_profile_detail(root.path()), ["generation 142"]);
+
+        // Straight into the store, no profile at all, or not a link
+        let root = nix_root();
+        fs::remove_file(root.join("nix/var/nix/profiles/system")).unwrap();
+        root.symlink("nix/var/nix/profiles/system", root.join("nix/store"));
+        assert!(nix_profile_detail(root.path()).is_empty());
+        fs::remove_file(root.join("nix/var/nix/profiles/system")).unwrap();
+        assert!(nix_profile_detail(root.path()).is_empty());
+        root.write("nix/var/nix/profiles/system", "");
+        root.write("etc/nixos/flake.nix", "{ }\n");
+        assert_eq!(nix_profile_detail(root.path()), ["flakes"]);
+    }
+
+    fn event(name: &str, date: &str) -> Event {
+        Event { name: name.to_string(), date: crate::dateformat::parse_date(date).unwrap() }
+    }
+
+    // Midday on `date`, the weekday doesn't matter here
+    fn day(date: &str) -> LocalTime {
+        let (year, month, day) = crate::dateformat::parse_date(date).unwrap();
+        LocalTime { year, month, day, hour: 12, minute: 0, weekday: 0 }
+    }
+
+    #[test]
+    fn nearest_event_still_to_come() {
+        let events = [
+            event("Christmas", "2024-12-25"),
+            event("Vacation", "2025-01-02"),
+            event("Birthday", "2025-12-30"),
+            event("Launch", "2026-06-01"),
+        ];
+        // Over the new year, Christmas being over already
+        assert_eq!(today_at(&day("2024-12-30"), &events), "Dec 30 · 3 days until Vacation");
+        assert_eq!(today_at(&day("2025-01-01"), &events), "Jan 1 · 1 day until Vacation");
+        assert_eq!(today_at(&day("2025-01-02"), &events), "Jan 2 · Vacation is today");
+        // Most of a year ahead, then into the year after
+        assert_eq!(today_at(&day("2025-01-03"), &events), "Jan 3 · 361 days until Birthday");
+        assert_eq!(today_at(&day("2025-12-31"), &events), "Dec 31 · 152 days until Launch");
+        // All of them past
+        assert_eq!(today_at(&day("2026-06-02"), &events), "Jun 2");
+        // Listed order doesn't matter
+        let mut reversed = events;
+        reversed.reverse();
+        assert_eq!(today_at(&day("2024-12-30"), &reversed), "Dec 30 · 3 days until Vacation");
+    }
+
+    #[test]
+    fn event_countdown_over_leap_days() {
+        let march = [event("Spring", "2024-03-01")];
+        assert_eq!(today_at(&day("2024-02-28"), &march), "Feb 28 · 2 days until Spring");
+        assert_eq!(today_at(&day("2024-02-29"), &march), "Feb 29 · 1 day until Spring");
+        let march = [event("Spring", "2025-03-01")];
+        assert_eq!(today_at(&day("2025-02-28"), &march), "Feb 28 · 1 day until Spring");
+        // A whole year with a leap day in it, and one without
+        let leap = [event("Leap", "2028-02-29")];
+        assert_eq!(today_at(&day("2027-02-28"), &leap), "Feb 28 · 366 days until Leap");
+        let next = [event("Next", "2029-03-01")];
+        assert_eq!(today_at(&day("2028-03-01"), &next), "Mar 1 · 365 days until Next");
+    }
+
+    // An Arch /usr/lib/modules tree: release directories with their pkgbase
+    fn modules_tree(kernels: &[(&str, &str)]) -> TempDir {
+        let root = TempDir::new();
+        for (release, base) in kernels {
+            root.write(&format!("usr/lib/modules/{}/pkgbase", release), format!("{}\n", base));
+        }
+        fs::create_dir_all(root.join("boot")).unwrap();
+        root
+    }
+
+    fn newer(root: &TempDir, running: &str) -> Option<String> {
+        newer_kernel(running, &root.join("usr/lib/modules"), &root.join("boot"))
+    }
+
+    #[test]
+    fn newer_kernels_on_arch() {
+        let root = modules_tree(&[
+            ("6.12.4-arch1-1", "linux"),
+            ("6.6.50-1-lts", "linux-lts"),
+            ("6.13.0-rc3-1-mainline", "linux-mainline"),
+        ]);
+        // DKMS leaves a directory behind with no pkgbase
+        root.write("usr/lib/modules/6.13.1-arch1-1/extramodules/nvidia.ko.zst", "");
+        // The running kernel's modules are gone after the upgrade, its flavour picks
+        assert_eq!(newer(&root, "6.11.2-arch1-1").as_deref(), Some("6.12.4-arch1-1"));
+        assert_eq!(newer(&root, "6.6.49-1-lts").as_deref(), Some("6.6.50-1-lts"));
+        assert_eq!(newer(&root, "6.12.4-arch1-1"), None);
+        assert_eq!(newer(&root, "6.12.10-arch1-1"), None);
+        assert_eq!(newer(&root, "6.13.0-rc2-1-mainline").as_deref(), Some("6.13.0-rc3-1-mainline"));
+
+        // Still there (a hook kept it), its pkgbase picks, whatever the release says
+        root.write("usr/lib/modules/6.12.1-arch1-1/pkgbase", "linux-lts\n");
+        assert_eq!(newer(&root, "6.12.1-arch1-1"), None);
+        root.write("usr/lib/modules/6.6.48-1-lts/pkgbase", "linux\n");
+        assert_eq!(newer(&root, "6.6.48-1-lts").as_deref(), Some("6.12.4-arch1-1"));
+    }
+
+    #[test]
+    fn newer_kernels_in_boot() {
+        let root = modules_tree(&[]);
+        let boot = [
+            "vmlinuz-6.11.9-200.fc40.x86_64",
+            "vmlinuz-6.11.10-200.fc40.x86_64",
+            "vmlinuz-0-rescue-9f8e7d6c5b4a39281706f5e4d3c2b1a0",
+            "vmlinuz-6.1.0-18-amd64",
+            "vmlinuz-6.1.0-21-amd64",
+            "vmlinuz",
+            "initramfs-6.12.0-300.fc41.x86_64.img",
+        ];
+        for name in boot {
+            root.write(&format!("boot/{}", name), "");
+        }
+        let fedora = newer(&root, "6.11.9-200.fc40.x86_64");
+        assert_eq!(fedora.as_deref(), Some("6.11.10-200.fc40.x86_64"));
+        assert_eq!(newer(&root, "6.11.10-200.fc40.x86_64"), None);
+        assert_eq!(newer(&root, "6.1.0-18-amd64").as_deref(), Some("6.1.0-21-amd64"));
+        assert_eq!(newer(&root, "6.1.0-21-amd64"), None);
+
+        // Modules with a pkgbase mean Arch, /boot isn't looked at
+        root.write("usr/lib/modules/6.12.4-arch1-1/pkgbase", "linux\n");
+        assert_eq!(newer(&root, "6.11.9-200.fc40.x86_64"), None);
+
+        // Nothing installed that can be read
+        let empty = TempDir::new();
+        assert_eq!(newer(&empty, "6.12.4-arch1-1"), None);
+    }
+
+    #[test]
+    fn windows_version_from_ver() {
+        let ver = |output| parse_windows_ver(output);
+        let output = "\r\nMicrosoft Windows [Version 10.0.22631.3880]\r\n";
+        assert_eq!(ver(output).as_deref(), Some("10.0.22631.3880"));
+        // A localized cmd.exe words it differently, that's no version
+        let output = "\r\nMicrosoft Windows [Versión 10.0.19045.4529]\r\n";
+        assert_eq!(ver(output), None);
+        // No closing bracket
+        let output = "Microsoft Windows [Version 10.0.26100.1742";
+        assert_eq!(ver(output).as_deref(), Some("10.0.26100.1742"));
+        assert_eq!(ver("Microsoft Windows [Version ]"), None);
+        assert_eq!(ver("'cmd.exe' is not recognized"), None);
+        assert_eq!(ver(""), None);
+    }
+
+    #[test]
+    fn windows_names_by_build() {
+        assert_eq!(windows_name("10.0.22631.3880"), "Windows 11 23H2");
+        assert_eq!(windows_name("10.0.26100.1742"), "Windows 11 24H2");
+        assert_eq!(windows_name("10.0.22000.2538"), "Windows 11 21H2");
+        assert_eq!(windows_name("10.0.19045.4529"), "Windows 10 22H2");
+        assert_eq!(windows_name("10.0.19041.1"), "Windows 10 2004");
+        // Builds not in the table, Insider ones, keep the major name
+        assert_eq!(windows_name("10.0.27686.1000"), "Windows 11");
+        assert_eq!(windows_name("10.0.18363.1556"), "Windows 10");
+        assert_eq!(windows_name("10.0"), "Windows");
+        assert_eq!(windows_name("unknown"), "Windows");
+    }
+
+    #[test]
+    fn booted_ostree_deployments() {
+        // Trimmed from rpm-ostree status --json --booted on Silverblue
+        let status = |deployment: &str| {
+            let json = format!(
+                "{{\"deployments\" : [ {{ \"id\" : \"fedora-abc.0\", {}, \
+                 \"packages\" : [ \"distrobox\", \"fish\", \"langpacks-en\" ], \
+                 \"requested-packages\" : [ ], \"booted\" : true }} ], \
+                 \"transaction\" : null }}",
+                deployment
+            );
+            parse_ostree_status(&json).map(|status| (status.deployment, status.layered))
+        };
+        let version = "\"checksum\" : \"2b7d4c5e8f9a0b1c\", \"version\" : \"41.20250110.0\"";
+        assert_eq!(status(version), Some(("41.20250110.0".to_string(), 3)));
+        // No version in a custom image, the short commit instead
+        let checksum = "\"checksum\" : \"2b7d4c5e8f9a0b1c\"";
+        assert_eq!(status(checksum), Some(("2b7d4c5e8f".to_string(), 3)));
+        assert_eq!(status("\"origin\" : \"fedora:fedora/41/x86_64/silverblue\""), None);
+
+        // Nothing layered
+        let json = "{\"deployments\":[{\"version\":\"41.20250110.0\",\"booted\":true}]}";
+        assert_eq!(parse_ostree_status(json).map(|status| status.layered), Some(0));
+        assert!(parse_ostree_status("error: Unit rpm-ostreed.service is masked").is_none());
+    }
+
+    #[test]
+    fn ostree_os_names() {
+        let os_release = "NAME=\"Fedora Linux\"\nVERSION=\"41.20250110.0 (Silverblue)\"\n\
+                          ID=fedora\nVERSION_ID=41\nVARIANT=\"Silverblue\"\n\
+                          VARIANT_ID=silverblue\n";
+        assert_eq!(variant_os_name(os_release).as_deref(), Some("Fedora Silverblue 41"));
+        let os_release = "NAME='Fedora Linux'\nVERSION_ID=41\nVARIANT=Kinoite\n";
+        assert_eq!(variant_os_name(os_release).as_deref(), Some("Fedora Kinoite 41"));
+        // VARIANT_ID alone isn't a variant
+        let os_release = "NAME=\"Fedora Linux\"\nVERSION_ID=41\nVARIANT_ID=silverblue\n";
+        assert_eq!(variant_os_name(os_release), None);
+    }
+
+    #[test]
+    fn microos_snapshots_from_mountinfo() {
+        let mountinfo = "\
+22 1 0:21 /@/.snapshots/57/snapshot / ro,relatime shared:1 - btrfs /dev/vda3 ro
+23 22 0:21 /@/home /home rw,relatime shared:2 - btrfs /dev/vda3 rw,subvol=/@/home
+";
+        assert_eq!(snapshot_from_mountinfo(mountinfo).as_deref(), Some("57"));
+        // A snapshot mounted somewhere else, and a plain btrfs root
+        let elsewhere = "40 22 0:21 /@/.snapshots/12/snapshot /mnt rw - btrfs /dev/vda3 rw\n";
+        assert_eq!(snapshot_from_mountinfo(elsewhere), None);
+        let plain = "22 1 0:21 /@ / rw,relatime shared:1 - btrfs /dev/vda3 rw,subvol=/@\n";
+        assert_eq!(snapshot_from_mountinfo(plain), None);
+    }
 }
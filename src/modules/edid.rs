@@ -0,0 +1,134 @@
+// Monitor identification from EDID, the blob a monitor hands the GPU describing itself.
+// The kernel has it at /sys/class/drm/card<N>-<connector>/edid for every connected output.
+// Only the 128 byte base block is read, extension blocks (CTA, DisplayID) are ignored.
+
+const BLOCK_LEN: usize = 128;
+const HEADER: [u8; 8] = [0x00, 0xff, 0xff, 0xff, 0xff, 0xff, 0xff, 0x00];
+// The four 18 byte descriptors in the base block
+const DESCRIPTOR_OFFSETS: [usize; 4] = [54, 72, 90, 108];
+// Display descriptor tag for the monitor name
+const TAG_NAME: u8 = 0xfc;
+
+// What the base block says about the monitor
+#[derive(Debug, Default, PartialEq)]
+pub struct EdidInfo {
+    // Model name from the name descriptor ("DELL U2723QE"), not every monitor has one
+    pub name: Option<String>,
+}
+
+// Parse an EDID blob, None when it's too short, has the wrong header or a bad checksum.
+// A corrupt blob is better ignored than shown as a garbled name.
+pub fn parse(data: &[u8]) -> Option<EdidInfo> {
+    let block = data.get(..BLOCK_LEN)?;
+    if block[..8] != HEADER {
+        return None;
+    }
+    // Every byte of the block, the checksum byte included, adds up to 0
+    if block.iter().fold(0u8, |sum, &byte| sum.wrapping_add(byte)) != 0 {
+        return None;
+    }
+
+    let name = DESCRIPTOR_OFFSETS.iter().find_map(|&offset| {
+        let descriptor = &block[offset..offset + 18];
+        // Display descriptors start with a zero pixel clock, detailed timings don't
+        if descriptor[..2] != [0, 0] || descriptor[3] != TAG_NAME {
+            return None;
+        }
+        descriptor_text(&descriptor[5..])
+    });
+    Some(EdidInfo { name })
+}
+
+// Descriptor text is up to 13 bytes of ASCII, ended by a newline and padded with spaces
+fn descriptor_text(bytes: &[u8]) -> Option<String> {
+    let end = bytes.iter().position(|&b| b == b'\n').unwrap_or(bytes.len());
+    let text: String = bytes[..end]
+        .iter()
+        .map(|&b| if b.is_ascii_graphic() || b == b' ' { b as char } else { ' ' })
+        .collect();
+    let text = text.trim();
+    (!text.is_empty()).then(|| text.to_string())
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    // A base block laid out like a DELL U2723QE's: a 3840x2160 detailed timing, then serial,
+    // name and range limit descriptors, one extension block to follow
+    const U2723QE: &str = "\
+        00 ff ff ff ff ff ff 00 10 ac e6 41 4c 34 32 30
+        20 20 01 04 b5 3c 22 78 3a 85 15 ad 51 44 38 b5
+        26 0d 50 a5 4b 00 d1 c0 81 80 a9 c0 b3 00 95 00
+        01 01 01 01 01 01 4d d0 00 a0 f0 70 3e 80 30 20
+        35 00 54 4f 21 00 00 1a 00 00 00 ff 00 46 37 4b
+        53 37 4d 33 0a 20 20 20 20 20 00 00 00 fc 00 44
+        45 4c 4c 20 55 32 37 32 33 51 45 0a 00 00 00 fd
+        00 18 4b 1e 8c 3c 00 0a 20 20 20 20 20 20 01 e8";
+
+    fn blob() -> Vec<u8> {
+        let digits: Vec<u8> = U2723QE.bytes().filter(u8::is_ascii_hexdigit).collect();
+        let hex = |pair: &[u8]| u8::from_str_radix(std::str::from_utf8(pair).unwrap(), 16);
+        digits.chunks(2).map(|pair| hex(pair).unwrap()).collect()
+    }
+
+    // Checksum byte set so the block adds up to 0 again after an edit
+    fn fix_checksum(block: &mut [u8]) {
+        let sum = block[..BLOCK_LEN - 1].iter().fold(0u8, |sum, &byte| sum.wrapping_add(byte));
+        block[BLOCK_LEN - 1] = sum.wrapping_neg();
+    }
+
+    #[test]
+    fn name_from_a_real_blob() {
+        let blob = blob();
+        assert_eq!(blob.len(), BLOCK_LEN);
+        assert_eq!(parse(&blob), Some(EdidInfo { name: Some("DELL U2723QE".to_string()) }));
+        // Extension blocks after the base block aren't looked at
+        let mut extended = blob.clone();
+        extended.extend([0x02; BLOCK_LEN]);
+        assert_eq!(parse(&extended).unwrap().name.as_deref(), Some("DELL U2723QE"));
+    }
+
+    #[test]
+    fn corrupt_or_short_blobs() {
+        let blob = blob();
+        let mut corrupt = blob.clone();
+        corrupt[100] ^= 0x01;
+        assert_eq!(parse(&corrupt), None);
+        let mut header = blob.clone();
+        header[0] = 0x01;
+        fix_checksum(&mut header);
+        assert_eq!(parse(&header), None);
+        assert_eq!(parse(&blob[..BLOCK_LEN - 1]), None);
+        assert_eq!(parse(&[]), None);
+    }
+
+    #[test]
+    fn name_descriptor_found_or_not() {
+        // No name descriptor, the serial and range limits are still there
+        let mut unnamed = blob();
+        unnamed[90 + 3] = 0x10;
+        fix_checksum(&mut unnamed);
+        assert_eq!(parse(&unnamed), Some(EdidInfo { name: None }));
+
+        // A full 13 bytes with no newline, in the last descriptor
+        let mut moved = blob();
+        moved[90 + 3] = 0x10;
+        moved[108..126].copy_from_slice(b"\0\0\0\xfc\0LG ULTRAGEAR+");
+        fix_checksum(&mut moved);
+        assert_eq!(parse(&moved).unwrap().name.as_deref(), Some("LG ULTRAGEAR+"));
+
+        // Name tag on a detailed timing (non-zero pixel clock) isn't a name
+        let mut timing = blob();
+        timing[54 + 3] = TAG_NAME;
+        timing[90 + 3] = 0x10;
+        fix_checksum(&mut timing);
+        assert_eq!(parse(&timing).unwrap().name, None);
+
+        // Blank or control characters only is no name
+        let mut blank = blob();
+        blank[95..108].copy_from_slice(b"\x01\x02   \n       ");
+        fix_checksum(&mut blank);
+        assert_eq!(parse(&blank).unwrap().name, None);
+    }
+}
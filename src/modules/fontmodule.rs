@@ -1,112 +1,268 @@
 // Font finder module for Slowfetch.
-// Parses terminal configs to find the in-use font.
+// Parses terminal configs to find the in-use font, plus opacity and padding for terminal_detail.
 
 use std::fs;
 use std::env;
+use std::process::Command;
+use std::time::Duration;
 use super::userspacemodules::terminal;
+use crate::cache;
+use crate::helpers::{cached_font_or, command_output_limited, ini_value};
+
+// What a terminal's config says, anything not set there is None
+#[derive(Default)]
+pub struct TerminalConfig {
+    pub font: Option<String>,
+    // As written, e.g. "0.92"
+    pub opacity: Option<String>,
+    // Horizontal and vertical padding, as written ("4,8" on ghostty means left,right)
+    pub padding_x: Option<String>,
+    pub padding_y: Option<String>,
+}
+
+impl TerminalConfig {
+    // "8px" when both sides match, "x 8px, y 4px" otherwise
+    pub fn padding(&self) -> Option<String> {
+        let px = |value: &str| {
+            value.split(',').map(|v| format!("{}px", v.trim())).collect::<Vec<_>>().join(",")
+        };
+        match (self.padding_x.as_deref(), self.padding_y.as_deref()) {
+            (Some(x), Some(y)) if x == y => Some(px(x)),
+            (Some(x), Some(y)) => Some(format!("x {}, y {}", px(x), px(y))),
+            (Some(x), None) => Some(format!("x {}", px(x))),
+            (None, Some(y)) => Some(format!("y {}", px(y))),
+            (None, None) => None,
+        }
+    }
+}
 
 // Get the terminal font by parsing config files
 pub fn find_font() -> String {
+    terminal_config().font.unwrap_or_else(|| "unknown".to_string())
+}
+
+// Parse the detected terminal's config
+pub fn terminal_config() -> TerminalConfig {
     // Use the terminal detection from userspacemodules
     let term = terminal();
 
     // Try terminal-specific configs based on detected terminal
     let result = match term.to_lowercase().as_str() {
-        "alacritty" => font_from_alacritty(),
-        "kitty" => font_from_kitty(),
-        "foot" => font_from_foot(),
-        "ghostty" => font_from_ghostty(),
-        "gnome terminal" => font_from_gnome_terminal(),
-        "konsole" => font_from_konsole(),
+        "alacritty" => config_from_alacritty(),
+        "kitty" => config_from_kitty(),
+        "foot" => config_from_foot(),
+        "ghostty" => config_from_ghostty(),
+        "gnome terminal" => font_only(font_from_gnome_terminal()),
+        "konsole" => font_only(font_from_konsole()),
         _ => None,
     };
 
-    result.unwrap_or_else(|| "unknown".to_string())
+    result.unwrap_or_default()
+}
+
+// Config file of each terminal whose config we parse, relative to $HOME
+const CONFIG_FILES: &[(&str, &str)] = &[
+    ("kitty", ".config/kitty/kitty.conf"),
+    ("alacritty", ".config/alacritty/alacritty.toml"),
+    ("foot", ".config/foot/foot.ini"),
+    ("ghostty", ".config/ghostty/config"),
+];
+
+// Where a terminal's config lives, None for terminals we don't read a file of
+pub fn config_file(term: &str) -> Option<String> {
+    let term = term.to_lowercase();
+    let (_, file) = CONFIG_FILES.iter().find(|(name, _)| *name == term)?;
+    Some(format!("{}/{}", env::var("HOME").ok()?, file))
+}
+
+// Terminals we only read the font of
+fn font_only(font: Option<String>) -> Option<TerminalConfig> {
+    Some(TerminalConfig { font: Some(font?), ..Default::default() })
+}
+
+// The lines of the Terminal tree (terminal_detail), whatever the config had
+pub fn terminal_detail() -> Vec<(String, String)> {
+    let config = terminal_config();
+    let padding = config.padding();
+    // A declared terminal_font wins, and the nerd font guess reuses this read
+    let font = cached_font_or(|| config.font.unwrap_or_else(|| "unknown".to_string()));
+    [
+        ("Font", Some(font.to_string())),
+        ("Opacity", config.opacity),
+        ("Padding", padding),
+    ]
+    .into_iter()
+    .filter_map(|(key, value)| Some((key.to_string(), value?)))
+    .collect()
 }
 
 // Parse Kitty config (~/.config/kitty/kitty.conf)
-fn font_from_kitty() -> Option<String> {
-    let home = env::var("HOME").ok()?;
-    let path = format!("{}/.config/kitty/kitty.conf", home);
-    let content = fs::read_to_string(path).ok()?;
+fn config_from_kitty() -> Option<TerminalConfig> {
+    Some(kitty_config(&fs::read_to_string(config_file("kitty")?).ok()?))
+}
 
+fn kitty_config(content: &str) -> TerminalConfig {
+    let mut config = TerminalConfig::default();
     for line in content.lines() {
         let line = line.trim();
-        if line.starts_with("font_family") && !line.starts_with('#') {
-            // Format: font_family JetBrains Mono
-            let font = line.trim_start_matches("font_family").trim();
-            if !font.is_empty() {
-                return Some(clean_font_name(font));
+        if line.starts_with('#') {
+            continue;
+        }
+        // Format: font_family JetBrains Mono
+        let (key, value) = line.split_once(char::is_whitespace).unwrap_or((line, ""));
+        let value = value.trim();
+        if value.is_empty() {
+            continue;
+        }
+        match key {
+            "font_family" if config.font.is_none() => config.font = Some(clean_font_name(value)),
+            "background_opacity" => config.opacity = Some(value.to_string()),
+            // One value for all sides, or CSS style "vertical horizontal" / "top right bottom left"
+            "window_padding_width" => {
+                let sides: Vec<&str> = value.split_whitespace().collect();
+                let (x, y) = match sides.as_slice() {
+                    [all] => (*all, *all),
+                    [vertical, horizontal, ..] => (*horizontal, *vertical),
+                    [] => continue,
+                };
+                config.padding_x = Some(x.to_string());
+                config.padding_y = Some(y.to_string());
             }
+            _ => {}
         }
     }
-    None
+    config
 }
 
 // Parse Alacritty config (~/.config/alacritty/alacritty.toml)
-fn font_from_alacritty() -> Option<String> {
-    let home = env::var("HOME").ok()?;
-    let path = format!("{}/.config/alacritty/alacritty.toml", home);
-    let content = fs::read_to_string(&path).ok()?;
+fn config_from_alacritty() -> Option<TerminalConfig> {
+    Some(alacritty_config(&fs::read_to_string(config_file("alacritty")?).ok()?))
+}
 
+fn alacritty_config(content: &str) -> TerminalConfig {
+    let mut config = TerminalConfig::default();
+    let mut section = String::new();
     for line in content.lines() {
         let line = line.trim();
-        if line.starts_with('#') || line.starts_with('[') {
+        if line.starts_with('#') {
+            continue;
+        }
+        if line.starts_with('[') {
+            section = line.trim_matches(['[', ']']).trim().to_string();
             continue;
         }
-        // Match any line ending with family = "..."
-        if line.contains("family") && line.contains('=') {
-            if let Some(val) = line.split('=').nth(1) {
-                let font = val.trim().trim_matches('"').trim_matches('\'');
+        let Some((key, value)) = line.split_once('=') else {
+            continue;
+        };
+        // Full dotted key, so "[window] padding.x = 8" and "[window.padding] x = 8" both work
+        let key = if section.is_empty() {
+            key.trim().to_string()
+        } else {
+            format!("{}.{}", section, key.trim())
+        };
+        let value = value.trim();
+        match key.as_str() {
+            "window.opacity" => config.opacity = Some(value.to_string()),
+            // Inline table: padding = { x = 8, y = 8 }
+            "window.padding" => {
+                config.padding_x = inline_value(value, "x");
+                config.padding_y = inline_value(value, "y");
+            }
+            "window.padding.x" => config.padding_x = Some(value.to_string()),
+            "window.padding.y" => config.padding_y = Some(value.to_string()),
+            // Match any line ending with family = "...", or [font] normal = { family = "..." }
+            key if (key.ends_with("family") || key == "font.normal") && config.font.is_none() => {
+                let font = if key == "font.normal" {
+                    inline_value(value, "family").unwrap_or_default()
+                } else {
+                    value.to_string()
+                };
+                let font = font.trim_matches('"').trim_matches('\'');
                 if !font.is_empty() {
-                    return Some(clean_font_name(font));
+                    config.font = Some(clean_font_name(font));
                 }
             }
+            _ => {}
         }
     }
-    None
+    config
+}
+
+// A key of a TOML inline table, "{ x = 8, y = 4 }" -> "8" for x
+fn inline_value(table: &str, key: &str) -> Option<String> {
+    table.trim_matches(['{', '}']).split(',').find_map(|pair| {
+        let (name, value) = pair.split_once('=')?;
+        (name.trim() == key).then(|| value.trim().to_string())
+    })
 }
 
 // Parse Foot config (~/.config/foot/foot.ini)
-fn font_from_foot() -> Option<String> {
-    let home = env::var("HOME").ok()?;
-    let path = format!("{}/.config/foot/foot.ini", home);
-    let content = fs::read_to_string(path).ok()?;
+fn config_from_foot() -> Option<TerminalConfig> {
+    Some(foot_config(&fs::read_to_string(config_file("foot")?).ok()?))
+}
 
+fn foot_config(content: &str) -> TerminalConfig {
+    let mut config = TerminalConfig::default();
     for line in content.lines() {
         let line = line.trim();
-        if line.starts_with("font=") && !line.starts_with('#') {
+        if line.starts_with('#') {
+            continue;
+        }
+        let Some((key, value)) = line.split_once('=') else {
+            continue;
+        };
+        match key.trim() {
             // Format: font=JetBrains Mono:size=12
-            let font = line.trim_start_matches("font=");
             // Take just the font name, before any :size or :style
-            let font = font.split(':').next().unwrap_or(font);
-            return Some(clean_font_name(font));
+            "font" if config.font.is_none() => {
+                let font = value.split(':').next().unwrap_or(value);
+                config.font = Some(clean_font_name(font));
+            }
+            // Format: pad=8x8, optionally followed by "center"
+            "pad" => {
+                let pad = value.split_whitespace().next().unwrap_or_default();
+                if let Some((x, y)) = pad.split_once('x') {
+                    config.padding_x = Some(x.to_string());
+                    config.padding_y = Some(y.to_string());
+                }
+            }
+            // [colors] alpha=0.9
+            "alpha" => config.opacity = Some(value.trim().to_string()),
+            _ => {}
         }
     }
-    None
+    config
 }
 
 // Parse Ghostty config (~/.config/ghostty/config)
-fn font_from_ghostty() -> Option<String> {
-    let home = env::var("HOME").ok()?;
-    let path = format!("{}/.config/ghostty/config", home);
-    let content = fs::read_to_string(path).ok()?;
+fn config_from_ghostty() -> Option<TerminalConfig> {
+    Some(ghostty_config(&fs::read_to_string(config_file("ghostty")?).ok()?))
+}
 
+fn ghostty_config(content: &str) -> TerminalConfig {
+    let mut config = TerminalConfig::default();
     for line in content.lines() {
         let line = line.trim();
-        if line.starts_with("font-family") && !line.starts_with('#') {
-            // Format: font-family = JetBrains Mono
-            let font = line
-                .trim_start_matches("font-family")
-                .trim()
-                .trim_start_matches('=')
-                .trim();
-            if !font.is_empty() {
-                return Some(clean_font_name(font));
-            }
+        if line.starts_with('#') {
+            continue;
+        }
+        // Format: font-family = JetBrains Mono
+        let Some((key, value)) = line.split_once('=') else {
+            continue;
+        };
+        let value = value.trim();
+        if value.is_empty() {
+            continue;
+        }
+        match key.trim() {
+            "font-family" if config.font.is_none() => config.font = Some(clean_font_name(value)),
+            "background-opacity" => config.opacity = Some(value.to_string()),
+            "window-padding-x" => config.padding_x = Some(value.to_string()),
+            "window-padding-y" => config.padding_y = Some(value.to_string()),
+            _ => {}
         }
     }
-    None
+    config
 }
 
 // Parse Konsole profile (~/.local/share/konsole/*.profile)
@@ -119,15 +275,12 @@ fn font_from_konsole() -> Option<String> {
     for entry in entries.flatten() {
         let path = entry.path();
         if path.extension().is_some_and(|e| e == "profile") {
-            if let Ok(content) = fs::read_to_string(&path) {
-                for line in content.lines() {
-                    if line.starts_with("Font=") {
-                        // Format: Font=JetBrains Mono,12,-1,5,50,0,0,0,0,0
-                        let font = line.trim_start_matches("Font=");
-                        let font = font.split(',').next().unwrap_or(font);
-                        return Some(clean_font_name(font));
-                    }
-                }
+            if let Ok(content) = fs::read_to_string(&path)
+                && let Some(font) = ini_value(&content, "Appearance", "Font")
+            {
+                // Format: Font=JetBrains Mono,12,-1,5,50,0,0,0,0,0
+                let font = font.split(',').next().unwrap_or(font);
+                return Some(clean_font_name(font));
             }
         }
     }
@@ -185,6 +338,89 @@ pub fn is_nerd_font(font: &str) -> bool {
     font.contains("NF") || font.contains("Nerd Font")
 }
 
+// Codepoint to look for in a font's charset, U+EE00 is the start of the progress bar glyphs
+// nerd font bars are drawn with, so if it's there the bars will render
+const NERD_PROBE_CODEPOINT: u32 = 0xEE00;
+
+// Full nerd font check for `auto`: the name heuristic first, then (cached per font) ask kitty
+// which font it's actually rendering and/or check the installed font's charset with fc-list.
+pub fn detect_nerd_font(font: &str) -> bool {
+    if is_nerd_font(font) {
+        return true;
+    }
+    if let Some(cached) = cache::get_cached_nerd_font(font) {
+        return cached;
+    }
+
+    let kitty_font = kitty_rendered_font();
+    let is_nerd = kitty_font.as_deref().is_some_and(is_nerd_font)
+        || kitty_font.as_deref().is_some_and(font_has_nerd_glyphs)
+        || font_has_nerd_glyphs(font);
+
+    cache::cache_nerd_font(font, is_nerd);
+    is_nerd
+}
+
+// What detect_nerd_font looks at, checked fresh (the cache is skipped), for doctor.
+// Each piece of evidence with whether it points at a nerd font.
+pub fn nerd_font_evidence(font: &str) -> Vec<(bool, String)> {
+    let mut evidence = vec![(is_nerd_font(font), format!("font name \"{}\"", font))];
+    if let Some(kitty_font) = kitty_rendered_font() {
+        let is_nerd = is_nerd_font(&kitty_font) || font_has_nerd_glyphs(&kitty_font);
+        evidence.push((is_nerd, format!("kitty renders \"{}\"", kitty_font)));
+    }
+    evidence.push((
+        font_has_nerd_glyphs(font),
+        format!("fc-list charset of \"{}\" has U+{:X}", font, NERD_PROBE_CODEPOINT),
+    ));
+    evidence
+}
+
+// Ask kitty which font it's really using (needs kitty 0.36+ for query-terminal)
+fn kitty_rendered_font() -> Option<String> {
+    if env::var("TERM").ok()? != "xterm-kitty" {
+        return None;
+    }
+    let output = command_output_limited(
+        Command::new("kitten").args(["query-terminal", "font_family"]),
+        4096,
+        Duration::from_millis(500),
+    )?;
+    kitty_font_family(&String::from_utf8_lossy(&output))
+}
+
+// query-terminal answers with "key: value" lines
+fn kitty_font_family(output: &str) -> Option<String> {
+    let family = output.lines().find_map(|line| line.strip_prefix("font_family:"))?;
+    let family = family.trim();
+    (!family.is_empty()).then(|| family.to_string())
+}
+
+// Check whether any installed font of this family covers the nerd font probe glyph
+fn font_has_nerd_glyphs(family: &str) -> bool {
+    command_output_limited(
+        Command::new("fc-list").args(["--format=%{charset}\n", family]),
+        256 * 1024,
+        Duration::from_millis(500),
+    )
+    .is_some_and(|output| {
+        String::from_utf8_lossy(&output)
+            .lines()
+            .any(|charset| charset_contains(charset, NERD_PROBE_CODEPOINT))
+    })
+}
+
+// fontconfig charsets are space separated hex ranges, e.g. "20-7e a0-17f e000-e0d4 f000"
+fn charset_contains(charset: &str, codepoint: u32) -> bool {
+    charset.split_whitespace().any(|range| {
+        let (start, end) = range.split_once('-').unwrap_or((range, range));
+        match (u32::from_str_radix(start, 16), u32::from_str_radix(end, 16)) {
+            (Ok(start), Ok(end)) => (start..=end).contains(&codepoint),
+            _ => false,
+        }
+    })
+}
+
 // Clean up font name - remove style suffixes, normalize, and beautify for display
 fn clean_font_name(font: &str) -> String {
     let font = font.trim();
@@ -249,3 +485,128 @@ fn resolve_font_alias(font: &str) -> String {
 
     font.to_string()
 }
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    // Trimmed down from real configs, each sets font, opacity and padding in its own way
+    const KITTY: &str = "\
+# BEGIN_KITTY_THEME
+include current-theme.conf
+# END_KITTY_THEME
+font_family      JetBrainsMono Nerd Font
+bold_font        auto
+font_size 11.5
+#background_opacity 1.0
+background_opacity 0.92
+window_padding_width 4 8
+";
+
+    const ALACRITTY: &str = r#"
+[window]
+opacity = 0.85
+padding = { x = 12, y = 6 }
+
+[font]
+normal = { family = "Iosevka Term", style = "Regular" }
+size = 12.0
+
+[font.bold]
+family = "Iosevka Term Bold"
+"#;
+
+    const FOOT: &str = "\
+[main]
+font=Fira Code:size=11
+pad=8x8 center
+# font=Hack:size=12
+
+[colors]
+alpha=0.9
+";
+
+    const GHOSTTY: &str = "\
+theme = catppuccin-mocha
+font-family = Maple Mono NF
+font-size = 13
+background-opacity = 0.95
+window-padding-x = 4,8
+window-padding-y = 6
+";
+
+    // Font, opacity and padding as the Terminal tree shows them
+    type Findings = (Option<String>, Option<String>, Option<String>);
+
+    fn findings(config: TerminalConfig) -> Findings {
+        let padding = config.padding();
+        (config.font, config.opacity, padding)
+    }
+
+    fn all(font: &str, opacity: &str, padding: &str) -> Findings {
+        (Some(font.into()), Some(opacity.into()), Some(padding.into()))
+    }
+
+    #[test]
+    fn kitty_conf() {
+        // "vertical horizontal" padding
+        let expected = all("JetBrainsMono NF", "0.92", "x 8px, y 4px");
+        assert_eq!(findings(kitty_config(KITTY)), expected);
+        let one = kitty_config("window_padding_width 10\n");
+        assert_eq!(findings(one), (None, None, Some("10px".into())));
+    }
+
+    #[test]
+    fn alacritty_toml() {
+        let expected = all("Iosevka Term", "0.85", "x 12px, y 6px");
+        assert_eq!(findings(alacritty_config(ALACRITTY)), expected);
+        // Dotted keys instead of inline tables
+        let dotted = "[window.padding]\nx = 5\ny = 5\n\n[font.normal]\nfamily = \"Hack\"\n";
+        let expected = (Some("Hack".into()), None, Some("5px".into()));
+        assert_eq!(findings(alacritty_config(dotted)), expected);
+    }
+
+    #[test]
+    fn foot_ini() {
+        assert_eq!(findings(foot_config(FOOT)), all("Fira Code", "0.9", "8px"));
+    }
+
+    #[test]
+    fn ghostty_file() {
+        // Two values on padding-x are left and right
+        let expected = all("Maple Mono NF", "0.95", "x 4px,8px, y 6px");
+        assert_eq!(findings(ghostty_config(GHOSTTY)), expected);
+    }
+
+    #[test]
+    fn nothing_set_is_nothing_found() {
+        for config in [kitty_config(""), alacritty_config("# empty\n"), foot_config("[main]\n")] {
+            assert_eq!(findings(config), (None, None, None));
+        }
+        assert_eq!(findings(ghostty_config("font-family =\n")), (None, None, None));
+    }
+
+    #[test]
+    fn nerd_glyphs_in_the_charset() {
+        // Trimmed from fc-list --format=%{charset} for a patched and a stock font
+        let patched = "20-7e a0-17f 2500-259f e000-e00a e0a0-e0a3 e5fa-e6b5 e700-e8ef ea60-ec1e \
+                       ed00-efc1 f000-f2ff f0001-f1af0";
+        let stock = "20-7e a0-17f 2500-259f fb01-fb02";
+        assert!(charset_contains(patched, NERD_PROBE_CODEPOINT));
+        assert!(!charset_contains(stock, NERD_PROBE_CODEPOINT));
+        // Single codepoints, range edges, and junk
+        assert!(charset_contains("20-7e ee00", 0xEE00));
+        assert!(charset_contains("ed00-ee00", 0xEE00));
+        assert!(!charset_contains("ed00-edff ee01", 0xEE00));
+        assert!(!charset_contains("zz-ee00 -", 0xEE00));
+        assert!(!charset_contains("", 0xEE00));
+    }
+
+    #[test]
+    fn kitty_query_terminal_output() {
+        let output = "font_family: Maple Mono NF\nfont_size: 11.5\n";
+        assert_eq!(kitty_font_family(output).as_deref(), Some("Maple Mono NF"));
+        assert_eq!(kitty_font_family("font_size: 11.5\nfont_family:  \n"), None);
+        assert_eq!(kitty_font_family("Unknown command: query-terminal\n"), None);
+    }
+}
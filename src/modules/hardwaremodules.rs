@@ -4,164 +4,717 @@
 use std::fs::{self, File};
 use std::io::{BufRead, BufReader};
 use std::process::Command;
+use std::sync::OnceLock;
+use std::time::{Duration, Instant};
 
 use memchr::{memchr_iter, memmem};
 
 use crate::cache;
-use crate::helpers::{create_bar, get_pci_database, read_first_line};
+use crate::colorcontrol::{color_critical, color_warning, fg_sgr, palette};
+use crate::configloader::{BarFill, BatteryThresholds, CpuClock};
+use crate::modules::edid;
+use crate::helpers::{
+    Precision, bar_style, command_output_limited, create_bar, format_duration,
+    get_cached_is_nerd_font, in_container, json_bool, json_objects, json_string, read_first_line,
+    wsl_version,
+};
+#[cfg(feature = "pci-ids")]
+use crate::helpers::get_pci_database;
+use crate::template::Fields;
+
+// Who made a CPU or GPU, for the vendor icons.
+// Classified from ids (PCI vendor id, cpuinfo vendor) rather than the display name,
+// shared by everything that shows an icon so the same vendor always gets the same one.
+#[derive(Clone, Copy, PartialEq, Debug)]
+pub enum Vendor {
+    Amd,
+    Intel,
+    Nvidia,
+    Arm,
+    Apple,
+    Unknown,
+}
+
+impl Vendor {
+    // PCI vendor id, "1002" or "0x1002"
+    fn from_pci_id(id: &str) -> Self {
+        let id = id.trim().trim_start_matches("0x").to_lowercase();
+        match id.as_str() {
+            "1002" | "1022" => Vendor::Amd,
+            "8086" => Vendor::Intel,
+            "10de" => Vendor::Nvidia,
+            "106b" => Vendor::Apple,
+            _ => Vendor::Unknown,
+        }
+    }
+
+    // cpuinfo "vendor_id" on x86, "CPU implementer" on ARM
+    fn from_cpuinfo(vendor: &str) -> Self {
+        match vendor.trim() {
+            "GenuineIntel" => Vendor::Intel,
+            "AuthenticAMD" | "HygonGenuine" => Vendor::Amd,
+            "0x61" => Vendor::Apple,
+            implementer if implementer.starts_with("0x") => Vendor::Arm,
+            _ => Vendor::Unknown,
+        }
+    }
+
+    // Vendor names as tools report them
+    // ("Advanced Micro Devices, Inc. [AMD/ATI]", "NVIDIA Corporation")
+    fn from_vendor_name(name: &str) -> Self {
+        if name.contains("Advanced Micro Devices") || name.contains("AMD") {
+            Vendor::Amd
+        } else if name.contains("NVIDIA") {
+            Vendor::Nvidia
+        } else if name.contains("Intel") {
+            Vendor::Intel
+        } else {
+            Vendor::Unknown
+        }
+    }
+
+    // Name stored in the cache
+    fn key(self) -> &'static str {
+        match self {
+            Vendor::Amd => "amd",
+            Vendor::Intel => "intel",
+            Vendor::Nvidia => "nvidia",
+            Vendor::Arm => "arm",
+            Vendor::Apple => "apple",
+            Vendor::Unknown => "unknown",
+        }
+    }
+
+    fn from_key(key: &str) -> Self {
+        [Vendor::Amd, Vendor::Intel, Vendor::Nvidia, Vendor::Arm, Vendor::Apple]
+            .into_iter()
+            .find(|vendor| vendor.key() == key)
+            .unwrap_or(Vendor::Unknown)
+    }
+
+    // Brand color for the nerd glyph, and the plain text tag for fonts without nerd glyphs
+    fn brand(self) -> Option<((u8, u8, u8), &'static str)> {
+        match self {
+            Vendor::Amd => Some(((0xED, 0x1C, 0x24), "[AMD]")),
+            Vendor::Intel => Some(((0x00, 0x71, 0xC5), "[Intel]")),
+            Vendor::Nvidia => Some(((0x76, 0xB9, 0x00), "[NVIDIA]")),
+            Vendor::Arm => Some(((0x00, 0x91, 0xBD), "[ARM]")),
+            Vendor::Apple => Some(((0xA2, 0xAA, 0xAD), "[Apple]")),
+            Vendor::Unknown => None,
+        }
+    }
+
+    // Icon to put in front of a value: the glyph in the vendor's brand color with nerd fonts,
+    // "[AMD]" otherwise. Empty for unknown vendors.
+    pub fn icon(self, glyph: &str) -> String {
+        let Some((brand_color, tag)) = self.brand() else {
+            return String::new();
+        };
+        if get_cached_is_nerd_font() {
+            // The value color is picked back up after the glyph
+            format!("{}{}{} ", fg_sgr(brand_color), glyph, palette().value)
+        } else {
+            format!("{} ", tag)
+        }
+    }
+}
+
+// CPU and GPU icons: 󰻠 (cpu-64-bit) and 󰢮 (expansion-card)
+pub const CPU_GLYPH: &str = "󰻠";
+pub const GPU_GLYPH: &str = "󰢮";
 
-// Get the CPU model name with boost clock.
+// Get the CPU model name with the configured clock suffix, and the vendor.
+// Only the model is cached, the clock is read fresh so "current" stays current.
+pub fn cpu_with_vendor(clock: CpuClock) -> (String, Vendor) {
+    let (model, vendor) = cpu_model_with_vendor();
+    if model == "unknown" {
+        return (model, vendor);
+    }
+    (format!("{}{}", model, cpu_clock_suffix(clock)), vendor)
+}
+
+// Get the CPU model name.
+pub fn cpu_model() -> String {
+    cpu_model_with_vendor().0
+}
+
+// Get the CPU model name and vendor.
 // Uses persistent cache to avoid repeated /proc reads.
-pub fn cpu() -> String {
+pub fn cpu_model_with_vendor() -> (String, Vendor) {
     // Check cache first (unless --refresh was passed)
-    if let Some(cached) = cache::get_cached_cpu() {
-        return cached;
+    if let Some((model, vendor)) = cache::get_cached_cpu() {
+        return (model, Vendor::from_key(&vendor));
     }
 
     // No cache hit, fetch fresh value
-    let result = cpu_fresh();
+    let (model, vendor) = cpu_fresh();
 
     // Cache the result for next time
-    cache::cache_cpu(&result);
+    cache::cache_cpu(&model, vendor.key());
 
-    result
+    (model, vendor)
 }
 
-// Fetch CPU info fresh (no cache)
+// Fetch CPU model name and vendor fresh (no cache)
 // Uses BufReader to stop reading after finding model name (avoids reading entire /proc/cpuinfo)
-fn cpu_fresh() -> String {
-    let model = if let Ok(file) = File::open("/proc/cpuinfo") {
+fn cpu_fresh() -> (String, Vendor) {
+    let mut model: Option<String> = None;
+    let mut vendor = Vendor::Unknown;
+
+    if let Ok(file) = File::open("/proc/cpuinfo") {
         let reader = BufReader::new(file);
-        let mut found_model: Option<String> = None;
 
         for line in reader.lines().map_while(Result::ok) {
-            if line.starts_with("model name") {
-                if let Some(name) = line.split(':').nth(1) {
-                    let words: Vec<&str> = name.split_whitespace().collect();
-                    // Find where GPU info starts (e.g., "with Radeon Graphics", "w/ Intel UHD")
-                    let gpu_start = words.iter().position(|&w| {
-                        w.eq_ignore_ascii_case("with") || w.eq_ignore_ascii_case("w/")
-                    });
-                    let words = match gpu_start {
-                        Some(idx) => &words[..idx],
-                        None => &words[..],
-                    };
-                    found_model = Some(
-                        words
-                            .iter()
-                            .filter(|&&w| !w.ends_with("-Core") && w != "Processor")
-                            .copied()
-                            .collect::<Vec<_>>()
-                            .join(" "),
-                    );
+            let Some((key, value)) = line.split_once(':') else {
+                continue;
+            };
+            // The vendor comes before the model name on x86
+            match key.trim() {
+                "vendor_id" | "CPU implementer" => vendor = Vendor::from_cpuinfo(value),
+                "model name" => {
+                    model = Some(normalize_cpu_model(value));
                     break; // Stop reading after finding model name
                 }
+                _ => {}
             }
         }
-        found_model
-    } else {
-        None
-    };
+    }
+
+    (model.unwrap_or_else(|| "unknown".to_string()), vendor)
+}
+
+// Clean up a raw cpuinfo model name:
+// "Intel(R) Xeon(R) Gold 6230R CPU @ 2.10GHz" -> "Intel Xeon Gold 6230R"
+// "AMD Ryzen 7 7840U w/ Radeon 780M Graphics" -> "AMD Ryzen 7 7840U"
+// "AMD EPYC 7763 64-Core Processor" -> "AMD EPYC 7763"
+fn normalize_cpu_model(raw: &str) -> String {
+    let mut name = raw.to_string();
+    for mark in ["(R)", "(r)", "(TM)", "(tm)"] {
+        name = name.replace(mark, "");
+    }
+    let name = name.replace("GenuineIntel", "Intel").replace("Genuine Intel", "Intel");
+
+    let mut words: Vec<&str> = Vec::new();
+    let mut iter = name.split_whitespace().peekable();
+    while let Some(word) = iter.next() {
+        // Where GPU info starts (e.g., "with Radeon Graphics", "w/ Intel UHD"), nothing useful after
+        if word.eq_ignore_ascii_case("with") || word.eq_ignore_ascii_case("w/") {
+            break;
+        }
+        // Embedded "@ 2.10GHz", the clock suffix comes from cpufreq instead
+        if word == "@" {
+            if iter.peek().is_some_and(|next| next.ends_with("GHz") || next.ends_with("MHz")) {
+                iter.next();
+            }
+            continue;
+        }
+        let core_count = word.ends_with("-Core") || word.ends_with("-Cores");
+        if core_count || word == "Processor" || word == "CPU" {
+            continue;
+        }
+        words.push(word);
+    }
+
+    words.join(" ")
+}
+
+// Anything above this is a bogus cpufreq value (seen on some VMs), in kHz
+const MAX_SANE_CPU_KHZ: u64 = 8_000_000;
+
+// Read a cpufreq value in kHz from cpu0, `cpu` stands in for /sys/devices/system/cpu
+fn read_cpufreq_khz(cpu: &std::path::Path, file: &str) -> Option<u64> {
+    fs::read_to_string(cpu.join("cpu0/cpufreq").join(file)).ok()?.trim().parse::<u64>().ok()
+}
+
+// Base (non-turbo) clock in kHz.
+// intel_pstate exposes base_frequency directly. Otherwise use the highest entry in
+// scaling_available_frequencies, skipping acpi-cpufreq's turbo marker (base + 1000kHz).
+// Not the lowest entry: that's the idle clock the CPU drops to, the base clock is the top
+// non-turbo state.
+fn cpu_base_khz(cpu: &std::path::Path) -> Option<u64> {
+    if let Some(khz) = read_cpufreq_khz(cpu, "base_frequency") {
+        return Some(khz);
+    }
+
+    let available =
+        fs::read_to_string(cpu.join("cpu0/cpufreq/scaling_available_frequencies")).ok()?;
+    let mut frequencies: Vec<u64> = available
+        .split_whitespace()
+        .filter_map(|f| f.parse().ok())
+        .collect();
+    frequencies.sort_unstable_by(|a, b| b.cmp(a));
+
+    match frequencies.as_slice() {
+        [turbo, base, ..] if turbo - base == 1000 => Some(*base),
+        [highest, ..] => Some(*highest),
+        [] => None,
+    }
+}
+
+// Current clock in kHz, averaged across all cpufreq policies
+fn cpu_current_khz(cpu: &std::path::Path) -> Option<u64> {
+    let mut total: u64 = 0;
+    let mut count: u64 = 0;
+
+    for entry in fs::read_dir(cpu.join("cpufreq")).ok()?.flatten() {
+        if !entry.file_name().to_string_lossy().starts_with("policy") {
+            continue;
+        }
+        let path = entry.path().join("scaling_cur_freq");
+        if let Some(khz) = fs::read_to_string(path).ok().and_then(|f| f.trim().parse::<u64>().ok())
+        {
+            total += khz;
+            count += 1;
+        }
+    }
+
+    total.checked_div(count)
+}
+
+// Build the " @ X.XXGHz" suffix for the CPU line, or nothing if the clock is unavailable/bogus
+fn cpu_clock_suffix(clock: CpuClock) -> String {
+    cpu_clock_suffix_in(std::path::Path::new("/sys/devices/system/cpu"), clock)
+}
 
-    let model = match model {
-        Some(m) => m,
-        None => return "unknown".to_string(),
+// `cpu` stands in for /sys/devices/system/cpu
+fn cpu_clock_suffix_in(cpu: &std::path::Path, clock: CpuClock) -> String {
+    let khz = match clock {
+        CpuClock::Boost => read_cpufreq_khz(cpu, "cpuinfo_max_freq"),
+        CpuClock::Base => cpu_base_khz(cpu),
+        CpuClock::Current => cpu_current_khz(cpu),
+        CpuClock::None => None,
     };
 
-    // Get boost clock from cpufreq (in kHz)
-    let boost_clock = read_first_line("/sys/devices/system/cpu/cpu0/cpufreq/cpuinfo_max_freq")
-        .and_then(|khz_str| khz_str.parse::<u64>().ok())
-        .map(|khz| {
+    match khz {
+        Some(khz) if khz > 0 && khz <= MAX_SANE_CPU_KHZ => {
             let ghz = khz as f64 / 1_000_000.0;
             format!(" @ {:.2}GHz", ghz)
-        })
-        .unwrap_or_default();
+        }
+        _ => String::new(),
+    }
+}
 
-    format!("{}{}", model, boost_clock)
+// Extra template fields for the CPU line: model, ghz, cores, threads
+pub fn cpu_fields(value: &str) -> Fields {
+    let (model, ghz) = match value.rsplit_once(" @ ") {
+        Some((model, clock)) => (model, clock.trim_end_matches("GHz")),
+        None => (value, ""),
+    };
+    let threads = std::thread::available_parallelism()
+        .map(|n| n.get())
+        .unwrap_or(0);
+    let cores = physical_core_count().unwrap_or(threads);
+
+    vec![
+        ("model", model.to_string()),
+        ("ghz", ghz.to_string()),
+        ("cores", cores.to_string()),
+        ("threads", threads.to_string()),
+    ]
 }
 
-// Get memory usage as a visual bar, 10 blocks = 100% usage
-// Uses BufReader to stop reading after finding MemTotal and MemAvailable
-pub fn memory() -> String {
-    let mut total: u64 = 0;
-    let mut available: u64 = 0;
+// Count physical cores from sysfs topology (unique package + core id pairs)
+pub fn physical_core_count() -> Option<usize> {
+    let mut cores = std::collections::HashSet::new();
 
-    if let Ok(file) = File::open("/proc/meminfo") {
-        let reader = BufReader::new(file);
+    for entry in fs::read_dir("/sys/devices/system/cpu").ok()?.flatten() {
+        let name = entry.file_name();
+        let name_bytes = name.as_encoded_bytes();
+        // Only cpuN directories, not cpufreq/cpuidle
+        if name_bytes.len() < 4
+            || &name_bytes[..3] != b"cpu"
+            || !name_bytes[3..].iter().all(|b| b.is_ascii_digit())
+        {
+            continue;
+        }
 
-        for line in reader.lines().map_while(Result::ok) {
-            if line.starts_with("MemTotal:") {
-                if let Some(val) = line.split_whitespace().nth(1) {
-                    total = val.parse().unwrap_or(0);
-                }
-            } else if line.starts_with("MemAvailable:") {
-                if let Some(val) = line.split_whitespace().nth(1) {
-                    available = val.parse().unwrap_or(0);
-                }
-            }
-            // MemTotal is line 1, MemAvailable is line 3 in /proc/meminfo
-            // Stop reading once we have both values
-            if total > 0 && available > 0 {
-                break;
-            }
+        let topology = entry.path().join("topology");
+        let package = read_first_line(topology.join("physical_package_id").to_str()?);
+        let core = read_first_line(topology.join("core_id").to_str()?);
+        if let (Some(package), Some(core)) = (package, core) {
+            cores.insert((package, core));
+        }
+    }
+
+    if cores.is_empty() {
+        None
+    } else {
+        Some(cores.len())
+    }
+}
+
+// Read total and available memory (in KB) from /proc/meminfo.
+// Returns None if the file can't be read or has no MemTotal (hardened /proc, broken containers).
+// Kernels before 3.14 (and some locked down setups) don't have MemAvailable, in which case
+// it's approximated the old way as MemFree + Buffers + Cached.
+// Uses BufReader to stop reading once MemTotal and MemAvailable are found
+fn read_meminfo() -> Option<(u64, u64)> {
+    read_meminfo_from(std::path::Path::new("/proc/meminfo"))
+}
+
+fn read_meminfo_from(path: &std::path::Path) -> Option<(u64, u64)> {
+    let file = File::open(path).ok()?;
+    let reader = BufReader::new(file);
+
+    let mut total: Option<u64> = None;
+    let mut available: Option<u64> = None;
+    let mut free: Option<u64> = None;
+    let mut buffers: Option<u64> = None;
+    let mut cached: Option<u64> = None;
+
+    for line in reader.lines().map_while(Result::ok) {
+        let Some((key, rest)) = line.split_once(':') else {
+            continue;
+        };
+        let value = rest.split_whitespace().next().and_then(|v| v.parse::<u64>().ok());
+        match key {
+            "MemTotal" => total = value,
+            "MemAvailable" => available = value,
+            "MemFree" => free = value,
+            "Buffers" => buffers = value,
+            "Cached" => cached = value,
+            _ => {}
+        }
+        // MemTotal is line 1, MemAvailable is line 3 in /proc/meminfo
+        // Stop reading once we have both values
+        if total.is_some() && available.is_some() {
+            break;
+        }
+    }
+
+    let total = total.filter(|&t| t > 0)?;
+    let available = match available {
+        Some(available) => available,
+        // Pre-3.14 formula, needs at least MemFree to mean anything
+        None => free? + buffers.unwrap_or(0) + cached.unwrap_or(0),
+    };
+
+    Some((total, available.min(total)))
+}
+
+// respect_cgroups from the config, None = auto (only inside a container)
+static RESPECT_CGROUPS: OnceLock<bool> = OnceLock::new();
+// include_network_fs from the config
+static INCLUDE_NETWORK_FS: OnceLock<bool> = OnceLock::new();
+
+// Set the include_network_fs config, call before collecting modules
+pub fn set_include_network_fs(value: bool) {
+    let _ = INCLUDE_NETWORK_FS.set(value);
+}
+
+// Set the respect_cgroups config override, call before collecting modules
+pub fn set_respect_cgroups(value: Option<bool>) {
+    let _ = RESPECT_CGROUPS.set(value.unwrap_or_else(in_container));
+}
+
+fn respect_cgroups() -> bool {
+    *RESPECT_CGROUPS.get().unwrap_or(&false)
+}
+
+// A cgroup v2 memory.max, "max" (or v1's page aligned i64::MAX) means no limit
+fn parse_memory_max(value: &str) -> Option<u64> {
+    let bytes: u64 = value.trim().parse().ok()?;
+    (bytes < 1 << 62).then_some(bytes)
+}
+
+// A cgroup v2 cpu.max "quota period" pair ("400000 100000" -> 4.0 cores).
+// "max 100000" means no quota.
+fn parse_cpu_max(value: &str) -> Option<f64> {
+    let mut parts = value.split_whitespace();
+    let quota: f64 = parts.next()?.parse().ok()?;
+    let period: f64 = parts.next().unwrap_or("100000").parse().ok()?;
+    (quota > 0.0 && period > 0.0).then(|| quota / period)
+}
+
+// Our cgroup's memory limit and usage in KB, v2 first then the v1 memory controller.
+// Page cache that can be dropped (inactive_file) doesn't count as used, same as docker stats.
+fn cgroup_memory() -> Option<(u64, u64)> {
+    cgroup_memory_in(std::path::Path::new("/sys/fs/cgroup"))
+}
+
+// `cgroup` stands in for /sys/fs/cgroup
+fn cgroup_memory_in(cgroup: &std::path::Path) -> Option<(u64, u64)> {
+    let read = |file: &str| fs::read_to_string(cgroup.join(file));
+    let (limit, usage, stat, inactive_key) = match read("memory.max") {
+        Ok(max) => (max, read("memory.current").ok()?, "memory.stat", "inactive_file"),
+        Err(_) => (
+            read("memory/memory.limit_in_bytes").ok()?,
+            read("memory/memory.usage_in_bytes").ok()?,
+            "memory/memory.stat",
+            "total_inactive_file",
+        ),
+    };
+    let limit = parse_memory_max(&limit)?;
+    let usage: u64 = usage.trim().parse().ok()?;
+    let inactive = read(stat).ok().and_then(|stat| {
+        stat.lines()
+            .find_map(|line| line.strip_prefix(inactive_key)?.strip_prefix(' ')?.parse().ok())
+    });
+    let used = usage.saturating_sub(inactive.unwrap_or(0)).min(limit);
+    Some((limit / 1000, used / 1000))
+}
+
+// CPU quota in cores, v2 cpu.max or v1 cfs_quota_us/cfs_period_us (-1 = no quota)
+pub fn cgroup_cpu_limit() -> Option<f64> {
+    if !respect_cgroups() {
+        return None;
+    }
+    cgroup_cpu_limit_in(std::path::Path::new("/sys/fs/cgroup"))
+}
+
+fn cgroup_cpu_limit_in(cgroup: &std::path::Path) -> Option<f64> {
+    let read = |file: &str| fs::read_to_string(cgroup.join(file)).ok();
+    if let Some(max) = read("cpu.max") {
+        return parse_cpu_max(&max);
+    }
+    let (quota, period) = (read("cpu/cpu.cfs_quota_us")?, read("cpu/cpu.cfs_period_us")?);
+    parse_cpu_max(&format!("{} {}", quota.trim(), period.trim()))
+}
+
+// Total and available memory in KB, the cgroup limit when there is one below the host's
+// RAM and respect_cgroups is on, /proc/meminfo otherwise
+fn read_memory() -> Option<(u64, u64)> {
+    let cgroup = if respect_cgroups() { cgroup_memory() } else { None };
+    limited_memory(read_meminfo(), cgroup)
+}
+
+// The host's (total, available) or the cgroup's (limit, used) turned into the same, whichever
+// is smaller
+fn limited_memory(host: Option<(u64, u64)>, cgroup: Option<(u64, u64)>) -> Option<(u64, u64)> {
+    match cgroup {
+        Some((limit, used)) if host.is_none_or(|(total, _)| limit < total) => {
+            Some((limit, limit - used))
         }
+        _ => host,
     }
+}
 
-    if total > 0 {
+// Get memory usage as a visual bar, 10 blocks = 100% usage, and the (used, total) KB it was
+// made from for usage_deltas and --json
+pub fn memory() -> (String, Option<(u64, u64)>) {
+    if let Some((total, available)) = read_memory() {
         let used = total - available;
         let usage_percent = (used as f64 / total as f64) * 100.0;
-        let bar = create_bar(usage_percent);
+        let bar = create_bar("memory", usage_percent);
 
         // Convert to GB (decimal: 1 KB = 1000 bytes, meminfo reports in KB)
         let used_gb = used as f64 / 1_000_000.0;
         let total_gb = total as f64 / 1_000_000.0;
 
-        return format!(" {} {:.0}GB/{:.0}GB", bar, used_gb, total_gb);
+        let line = format!(" {} {:.0}GB/{:.0}GB", bar, used_gb, total_gb);
+        return (line, Some((used, total)));
     }
-    "unknown".to_string()
+    ("unknown".to_string(), None)
 }
 
-// Get the GPU model.
-// Uses persistent cache to avoid slow subprocess calls on repeated runs.
-// If cache isnt used, it tries vulkaninfo first for speed, then glxinfo, then sysfs + pci.ids, then lspci as final fallback
+// Extra template fields for the memory line: used, total, percent, bar
+pub fn memory_fields(_value: &str) -> Fields {
+    let Some((total, available)) = read_memory() else {
+        return vec![];
+    };
+    let used = total - available;
+    let usage_percent = (used as f64 / total as f64) * 100.0;
+    vec![
+        ("used", format!("{:.0}GB", used as f64 / 1_000_000.0)),
+        ("total", format!("{:.0}GB", total as f64 / 1_000_000.0)),
+        ("percent", format!("{:.0}%", usage_percent)),
+        ("bar", create_bar("memory", usage_percent)),
+    ]
+}
+
+// Integrated or discrete, only worked out when there's more than one GPU
+#[derive(Clone, Copy, PartialEq, Debug)]
+pub enum GpuKind {
+    Integrated,
+    Discrete,
+}
+
+impl GpuKind {
+    pub fn label(self) -> &'static str {
+        match self {
+            GpuKind::Integrated => "iGPU",
+            GpuKind::Discrete => "dGPU",
+        }
+    }
+
+    fn from_label(label: &str) -> Option<Self> {
+        match label {
+            "iGPU" => Some(GpuKind::Integrated),
+            "dGPU" => Some(GpuKind::Discrete),
+            _ => None,
+        }
+    }
+}
+
+pub struct Gpu {
+    pub name: String,
+    pub vendor: Vendor,
+    pub kind: Option<GpuKind>,
+}
+
+// Get the GPU model(s) as one line, for --warm-cache.
 pub fn gpu() -> String {
+    gpus().iter().map(|gpu| gpu.name.as_str()).collect::<Vec<_>>().join(", ")
+}
+
+// Get the GPUs with vendor, and iGPU/dGPU labels when there's more than one.
+// Uses persistent cache to avoid slow subprocess calls on repeated runs.
+pub fn gpus() -> Vec<Gpu> {
     // Check cache first (unless --refresh was passed)
-    if let Some(cached) = cache::get_cached_gpu() {
-        return cached;
+    if let Some(cached) = cache::get_cached_gpus() {
+        return cached
+            .into_iter()
+            .map(|(name, vendor, kind)| Gpu {
+                name,
+                vendor: Vendor::from_key(&vendor),
+                kind: GpuKind::from_label(&kind),
+            })
+            .collect();
     }
 
     // No cache hit, fetch fresh value
-    let result = gpu_fresh();
+    let gpus = gpus_fresh();
 
     // Cache the result for next time
-    cache::cache_gpu(&result);
+    let cached: Vec<(&str, &str, &str)> = gpus
+        .iter()
+        .map(|gpu| (gpu.name.as_str(), gpu.vendor.key(), gpu.kind.map_or("", GpuKind::label)))
+        .collect();
+    cache::cache_gpus(&cached);
+
+    gpus
+}
+
+// Fetch GPU info fresh (no cache).
+// Several GPUs are listed from sysfs + pci.ids, a single one gets the nicer name from the tools.
+fn gpus_fresh() -> Vec<Gpu> {
+    let adapters = sysfs_adapters();
+    if adapters.len() > 1 {
+        return classify_adapters(adapters);
+    }
 
-    result
+    let (name, vendor) = gpu_fresh();
+    vec![Gpu { name, vendor, kind: None }]
 }
 
-// Fetch GPU info fresh (no cache)
-fn gpu_fresh() -> String {
-    // Try vulkaninfo first - fastest option (~19ms)
-    if let Some(name) = gpu_from_vulkaninfo() {
-        return name;
+// Fetch the primary GPU fresh (no cache)
+// Tries vulkaninfo first for speed, then glxinfo, then sysfs + pci.ids, then lspci as final fallback
+fn gpu_fresh() -> (String, Vendor) {
+    // Try vulkaninfo first - fastest option (~19ms). Under WSL without the GPU passed through
+    // (/dev/dxg) it only finds llvmpipe, after taking its time.
+    let no_wsl_gpu = wsl_version().is_some() && !std::path::Path::new("/dev/dxg").exists();
+    if !no_wsl_gpu && let Some(gpu) = gpu_from_vulkaninfo() {
+        return gpu;
     }
 
     // Try glxinfo as fallback (~52ms)
-    if let Some(name) = gpu_from_glxinfo() {
-        return name;
+    if let Some(gpu) = gpu_from_glxinfo() {
+        return gpu;
     }
 
     // Fallback to sysfs + pci.ids lookup (~1ms but less accurate names)
-    if let Some(name) = gpu_from_sysfs() {
-        return name;
+    if let Some(gpu) = gpu_from_sysfs() {
+        return gpu;
     }
 
     // Final fallback: lspci -mm (slow af but should get it done)
-    gpu_from_lspci().unwrap_or_else(|| "unknown".to_string())
+    gpu_from_lspci().unwrap_or_else(|| ("unknown".to_string(), Vendor::Unknown))
+}
+
+// One of the ways gpu_fresh names the GPU
+type GpuProbe = fn() -> Option<(String, Vendor)>;
+
+// Every gpu_fresh fallback in order, with how long it took and what it found, for doctor.
+// Unlike gpu_fresh these all run, so it shows which ones would work.
+pub fn gpu_probes() -> Vec<(&'static str, Duration, Option<String>)> {
+    let probes: [(&'static str, GpuProbe); 4] = [
+        ("vulkaninfo", gpu_from_vulkaninfo),
+        ("glxinfo", gpu_from_glxinfo),
+        ("sysfs + pci.ids", gpu_from_sysfs),
+        ("lspci", gpu_from_lspci),
+    ];
+    probes
+        .into_iter()
+        .map(|(name, probe)| {
+            let started = Instant::now();
+            let found = probe().map(|(gpu, _)| gpu);
+            (name, started.elapsed(), found)
+        })
+        .collect()
 }
 
-// Get GPU name from vulkaninfo
-fn gpu_from_vulkaninfo() -> Option<String> {
+// A GPU found in /sys/class/drm, before it's labeled
+struct Adapter {
+    name: String,
+    vendor: Vendor,
+    // PCI address, e.g. "0000:00:02.0"
+    address: String,
+    // Dedicated memory in bytes (amdgpu only)
+    vram: Option<u64>,
+}
+
+// Every PCI GPU with a DRM card, in card order
+fn sysfs_adapters() -> Vec<Adapter> {
+    let Ok(entries) = fs::read_dir("/sys/class/drm") else {
+        return Vec::new();
+    };
+    let mut cards: Vec<(u32, std::path::PathBuf)> = entries
+        .flatten()
+        .filter_map(|entry| {
+            let name = entry.file_name();
+            // Only card entries, not card0-DP-1 etc
+            let number = name.to_str()?.strip_prefix("card")?.parse().ok()?;
+            Some((number, entry.path()))
+        })
+        .collect();
+    cards.sort();
+
+    cards
+        .into_iter()
+        .filter_map(|(_, card)| {
+            let (name, vendor) = sysfs_gpu_name(&card)?;
+            let device = fs::canonicalize(card.join("device")).ok()?;
+            let address = device.file_name()?.to_string_lossy().into_owned();
+            let vram = read_first_line(&format!("{}/mem_info_vram_total", device.display()))
+                .and_then(|bytes| bytes.parse().ok());
+            Some(Adapter { name, vendor, address, vram })
+        })
+        .collect()
+}
+
+// Label each adapter. Intel's integrated graphics always sit on the root bus (00:02.0), Arc
+// cards don't. NVIDIA only makes discrete PCI GPUs. AMD APUs hang off a bridge like their dGPUs,
+// so there it's the memory: APUs only get a small carve out (usually up to 2GB), no discrete
+// card has that little. Having less than another card says nothing, two dGPUs differ too.
+// boot_vga isn't used, it's whatever firmware picked.
+fn classify_adapters(adapters: Vec<Adapter>) -> Vec<Gpu> {
+    const APU_CARVE_OUT: u64 = 2 * 1024 * 1024 * 1024;
+
+    adapters
+        .into_iter()
+        .map(|adapter| {
+            let on_root_bus = adapter.address.split(':').nth(1) == Some("00");
+            let integrated = match adapter.vendor {
+                Vendor::Intel => on_root_bus,
+                Vendor::Nvidia => false,
+                _ => adapter.vram.is_some_and(|vram| vram <= APU_CARVE_OUT),
+            };
+            let kind = if integrated { GpuKind::Integrated } else { GpuKind::Discrete };
+            Gpu { name: adapter.name, vendor: adapter.vendor, kind: Some(kind) }
+        })
+        .collect()
+}
+
+// Value after `key` and `separator` on the same line, e.g. "vendorID = 0x1002" -> "0x1002"
+fn line_value<'a>(text: &'a [u8], key: &[u8], separator: u8) -> Option<&'a str> {
+    let after_key = &text[memmem::find(text, key)? + key.len()..];
+    let after_separator = &after_key[memchr::memchr(separator, after_key)? + 1..];
+    let line_end = memchr::memchr(b'\n', after_separator).unwrap_or(after_separator.len());
+    Some(std::str::from_utf8(&after_separator[..line_end]).ok()?.trim())
+}
+
+// Get GPU name and vendor from vulkaninfo
+fn gpu_from_vulkaninfo() -> Option<(String, Vendor)> {
     let output = Command::new("vulkaninfo")
         .arg("--summary")
         .output()
@@ -187,15 +740,20 @@ fn gpu_from_vulkaninfo() -> Option<String> {
     // Remove the parenthetical driver info
     let name = name.split('(').next().unwrap_or(name).trim();
 
+    // vendorID is a few lines above deviceName, in the same GPU block
+    let block_start = memmem::rfind(&stdout[..pos], b"vendorID").unwrap_or(pos);
+    let vendor = line_value(&stdout[block_start..pos], b"vendorID", b'=')
+        .map_or(Vendor::Unknown, Vendor::from_pci_id);
+
     // Skip CPU/APU devices (they also show up in vulkaninfo)
     if !name.is_empty() && !name.contains("Processor") && !name.contains("llvmpipe") {
-        return Some(name.to_string());
+        return Some((name.to_string(), vendor));
     }
     None
 }
 
-// Get GPU name from glxinfo (requires X11/Wayland with GL)
-fn gpu_from_glxinfo() -> Option<String> {
+// Get GPU name and vendor from glxinfo (requires X11/Wayland with GL)
+fn gpu_from_glxinfo() -> Option<(String, Vendor)> {
     let output = Command::new("glxinfo").output().ok()?;
     let stdout = &output.stdout;
 
@@ -217,22 +775,27 @@ fn gpu_from_glxinfo() -> Option<String> {
 
     // Remove the parenthetical info if present
     let name = renderer.split('(').next().unwrap_or(renderer).trim();
-    if !name.is_empty() && name != "llvmpipe" {
-        return Some(name.to_string());
+    if name.is_empty() || name == "llvmpipe" {
+        return None;
     }
-    None
+
+    // Mesa lists the PCI id ("Vendor: AMD (0x1002)"), the proprietary NVIDIA driver only the name
+    let vendor = line_value(stdout, b"    Vendor:", b':')
+        .and_then(|vendor| vendor.rsplit_once('(')?.1.strip_suffix(')'))
+        .map(Vendor::from_pci_id)
+        .filter(|vendor| *vendor != Vendor::Unknown)
+        .or_else(|| line_value(stdout, b"OpenGL vendor string", b':').map(Vendor::from_vendor_name))
+        .unwrap_or(Vendor::Unknown);
+    Some((name.to_string(), vendor))
 }
 
-// Get GPU name from sysfs + pci.ids database (using cached HashMap)
-fn gpu_from_sysfs() -> Option<String> {
+// Get GPU name and vendor from sysfs + pci.ids database (using cached HashMap)
+fn gpu_from_sysfs() -> Option<(String, Vendor)> {
     let drm_path = std::path::Path::new("/sys/class/drm");
     if !drm_path.exists() {
         return None;
     }
 
-    // Get cached PCI database
-    let pci_db = get_pci_database().as_ref()?;
-
     for entry in fs::read_dir(drm_path).ok()?.flatten() {
         let name = entry.file_name();
         let name_bytes = name.as_encoded_bytes();
@@ -246,46 +809,63 @@ fn gpu_from_sysfs() -> Option<String> {
             continue;
         }
 
-        let uevent_path = entry.path().join("device/uevent");
-        let uevent = fs::read(&uevent_path).ok()?;
-
-        // Find PCI_ID using SIMD search
-        let pci_id_needle = b"PCI_ID=";
-        let pos = memmem::find(&uevent, pci_id_needle)?;
-        let after_needle = &uevent[pos + pci_id_needle.len()..];
-
-        // Find end of line
-        let line_end = memchr::memchr(b'\n', after_needle).unwrap_or(after_needle.len());
-        let pci_id = std::str::from_utf8(&after_needle[..line_end]).ok()?;
+        return sysfs_gpu_name(&entry.path());
+    }
+    None
+}
 
-        // Find colon separator
-        let colon_pos = memchr::memchr(b':', pci_id.as_bytes())?;
-        let vendor_id = pci_id[..colon_pos].to_lowercase();
-        let device_id = pci_id[colon_pos + 1..].to_lowercase();
+// Built without pci-ids there's nothing to look the id up in, the tools and lspci have to do.
+// No names also means no multi GPU list, that's built from these.
+#[cfg(not(feature = "pci-ids"))]
+fn sysfs_gpu_name(_card: &std::path::Path) -> Option<(String, Vendor)> {
+    None
+}
 
-        // O(1) HashMap lookup instead of O(n) linear scan
-        let (vendor_name, devices) = pci_db.get(&vendor_id)?;
-        let device_name = devices.get(&device_id)?;
+// Name and vendor of one /sys/class/drm/cardN from its PCI id
+#[cfg(feature = "pci-ids")]
+fn sysfs_gpu_name(card: &std::path::Path) -> Option<(String, Vendor)> {
+    // Get cached PCI database
+    let pci_db = get_pci_database().as_ref()?;
 
-        // Extract the part in brackets if present
-        let display_name = device_name
-            .find('[')
-            .and_then(|start| device_name.rfind(']').map(|end| &device_name[start + 1..end]))
-            .unwrap_or(device_name);
+    let uevent_path = card.join("device/uevent");
+    let uevent = fs::read(&uevent_path).ok()?;
 
-        let vendor_short = vendor_name
-            .find('[')
-            .and_then(|start| vendor_name.rfind(']').map(|end| &vendor_name[start + 1..end]))
-            .and_then(|s| s.split('/').next())
-            .unwrap_or("GPU");
+    // Find PCI_ID using SIMD search
+    let pci_id_needle = b"PCI_ID=";
+    let pos = memmem::find(&uevent, pci_id_needle)?;
+    let after_needle = &uevent[pos + pci_id_needle.len()..];
 
-        return Some(format!("{} {}", vendor_short, display_name));
-    }
-    None
+    // Find end of line
+    let line_end = memchr::memchr(b'\n', after_needle).unwrap_or(after_needle.len());
+    let pci_id = std::str::from_utf8(&after_needle[..line_end]).ok()?;
+
+    // Find colon separator
+    let colon_pos = memchr::memchr(b':', pci_id.as_bytes())?;
+    let vendor_id = pci_id[..colon_pos].to_lowercase();
+    let device_id = pci_id[colon_pos + 1..].to_lowercase();
+
+    // O(1) HashMap lookup instead of O(n) linear scan
+    let (vendor_name, devices) = pci_db.get(&vendor_id)?;
+    let device_name = devices.get(&device_id)?;
+
+    // Extract the part in brackets if present
+    let display_name = device_name
+        .find('[')
+        .and_then(|start| device_name.rfind(']').map(|end| &device_name[start + 1..end]))
+        .unwrap_or(device_name);
+
+    let vendor_short = vendor_name
+        .find('[')
+        .and_then(|start| vendor_name.rfind(']').map(|end| &vendor_name[start + 1..end]))
+        .and_then(|s| s.split('/').next())
+        .unwrap_or("GPU");
+
+    let gpu = format!("{} {}", vendor_short, display_name);
+    Some((gpu, Vendor::from_pci_id(&vendor_id)))
 }
 
-// Get GPU name from lspci -mm (final fallback)
-fn gpu_from_lspci() -> Option<String> {
+// Get GPU name and vendor from lspci -mm (final fallback)
+fn gpu_from_lspci() -> Option<(String, Vendor)> {
     let output = Command::new("lspci").arg("-mm").output().ok()?;
     let stdout = &output.stdout;
 
@@ -348,7 +928,8 @@ fn gpu_from_lspci() -> Option<String> {
                     _ => vendor,
                 };
 
-                return Some(format!("{} {}", vendor_short, device));
+                let gpu = format!("{} {}", vendor_short, device);
+                return Some((gpu, Vendor::from_vendor_name(vendor)));
             }
         }
 
@@ -357,231 +938,1370 @@ fn gpu_from_lspci() -> Option<String> {
     None
 }
 
-// Get storage usage for all physical disks using statvfs syscall.
+// Sum (total_bytes, used_bytes) for all physical disks using statvfs syscall.
 // Reads /proc/mounts and uses statvfs for each real filesystem - much faster than spawning df
-pub fn storage() -> String {
+// Returns None when no mount could be measured, so callers don't report 0GB/0GB
+fn storage_totals() -> Option<(u64, u64)> {
+    let mounts = fs::read("/proc/mounts").ok()?;
+    storage_totals_in(&mounts, std::path::Path::new("/sys/class/block"), get_fs_stats)
+}
+
+// storage_totals over a /proc/mounts content, measuring each mount with `stats`
+fn storage_totals_in(
+    content: &[u8],
+    sys_class_block: &std::path::Path,
+    stats: impl Fn(&str) -> Option<(u64, u64, libc::c_ulong)>,
+) -> Option<(u64, u64)> {
     let mut total_bytes: u64 = 0;
     let mut used_bytes: u64 = 0;
+    // Filesystems already counted, by device and by statvfs fsid. Multipath and multi-device
+    // btrfs reach one filesystem through several device nodes, while btrfs subvolumes on one
+    // device each get their own fsid, so either match means it's been counted.
     let mut seen_devices = std::collections::HashSet::new();
+    let mut seen_fsids = std::collections::HashSet::new();
+    let mut measured_any = false;
+    let include_network_fs = *INCLUDE_NETWORK_FS.get().unwrap_or(&false);
+
+    // /proc/mounts is read as bytes for SIMD-accelerated parsing
+    let mut start = 0;
+    for end in memchr_iter(b'\n', content) {
+        let line = &content[start..end];
+        start = end + 1;
+
+        // Find first space (device ends here)
+        let Some(space1) = memchr::memchr(b' ', line) else {
+            continue;
+        };
+        let device = &line[..space1];
 
-    // Read /proc/mounts as bytes for SIMD-accelerated parsing
-    if let Ok(content) = fs::read("/proc/mounts") {
-        let mut start = 0;
-        for end in memchr_iter(b'\n', &content) {
-            let line = &content[start..end];
-            start = end + 1;
-
-            // Find first space (device ends here)
-            let Some(space1) = memchr::memchr(b' ', line) else {
-                continue;
-            };
-            let device = &line[..space1];
-
-            // Find second space (mount point ends here)
-            let rest = &line[space1 + 1..];
-            let Some(space2) = memchr::memchr(b' ', rest) else {
-                continue;
-            };
-            let mount_point_bytes = &rest[..space2];
-
-            // Filter for real disks: starts with /dev/ and not loop devices
-            if device.len() < 5
+        // Find second space (mount point ends here)
+        let rest = &line[space1 + 1..];
+        let Some(space2) = memchr::memchr(b' ', rest) else {
+            continue;
+        };
+        let mount_point_bytes = &rest[..space2];
+        let fs_type = rest[space2 + 1..].split(|&b| b == b' ').next().unwrap_or_default();
+
+        // Filter for real disks: starts with /dev/ and not loop devices.
+        // Network shares only with include_network_fs.
+        let network = include_network_fs && is_network_fs(fs_type, mount_point_bytes);
+        if !network
+            && (device.len() < 5
                 || &device[..5] != b"/dev/"
-                || memmem::find(device, b"/loop").is_some()
-            {
-                continue;
-            }
+                || memmem::find(device, b"/loop").is_some())
+        {
+            continue;
+        }
 
-            let Ok(device_str) = std::str::from_utf8(device) else {
-                continue;
-            };
-            let Ok(mount_point) = std::str::from_utf8(mount_point_bytes) else {
-                continue;
-            };
+        // Read-only images (snaps, live media) and overlays of other mounts aren't storage
+        if matches!(fs_type, b"squashfs" | b"erofs" | b"iso9660" | b"overlay") {
+            continue;
+        }
 
-            // Avoid double counting if device mounted multiple times
-            if !seen_devices.insert(device_str.to_string()) {
-                continue;
-            }
+        let Ok(device_str) = std::str::from_utf8(device) else {
+            continue;
+        };
+        let Ok(mount_point) = std::str::from_utf8(mount_point_bytes) else {
+            continue;
+        };
 
-            // Use statvfs syscall to get filesystem stats
-            if let Some((total, used)) = get_fs_stats(mount_point) {
-                total_bytes += total;
-                used_bytes += used;
-            }
+        // RAID/bcache/multipath members are counted through the device on top of them
+        if has_holders(sys_class_block, device_str) {
+            continue;
         }
-    }
 
-    if total_bytes > 0 {
-        let usage_percent = (used_bytes as f64 / total_bytes as f64) * 100.0;
-        let bar = create_bar(usage_percent);
+        // Use statvfs syscall to get filesystem stats
+        let Some((total, used, fsid)) = stats(mount_point) else {
+            continue;
+        };
+        // Avoid double counting a filesystem mounted multiple times. Some filesystems
+        // leave the fsid at 0, those only go by device.
+        let new_device = seen_devices.insert(device_str.to_string());
+        let new_fsid = fsid == 0 || seen_fsids.insert(fsid);
+        if !new_device || !new_fsid {
+            continue;
+        }
+        total_bytes += total;
+        used_bytes += used;
+        measured_any = true;
+    }
 
-        // Convert to GB (decimal: 1 GB = 1,000,000,000 bytes)
-        let used_gb = used_bytes as f64 / 1_000_000_000.0;
-        let total_gb = total_bytes as f64 / 1_000_000_000.0;
+    if measured_any && total_bytes > 0 {
+        Some((total_bytes, used_bytes))
+    } else {
+        None
+    }
+}
 
-        // Use TB for total if >= 1000GB, frees up horizontal line space
-        if total_gb >= 1000.0 {
-            let total_tb = total_gb / 1000.0;
-            // Trim .00 if it's a whole number (e.g., 1.00TB -> 1TB)
-            let total_str = if (total_tb - total_tb.round()).abs() < 0.005 {
-                format!("{}TB", total_tb.round() as u64)
-            } else {
-                format!("{:.2}TB", total_tb)
-            };
-            return format!("{} {:.0}GB/{}", bar, used_gb, total_str);
+// NFS, SMB and sshfs shares, and the Windows drives WSL mounts over 9p (/mnt/c). WSL has more
+// 9p mounts (its drivers and libraries, all on C:), only /mnt/<drive letter> ones count.
+fn is_network_fs(fs_type: &[u8], mount_point: &[u8]) -> bool {
+    match fs_type {
+        b"nfs" | b"nfs4" | b"cifs" | b"smb3" | b"fuse.sshfs" => true,
+        b"9p" | b"drvfs" => {
+            mount_point.strip_prefix(b"/mnt/").is_some_and(|drive| {
+                drive.len() == 1 && drive[0].is_ascii_alphabetic()
+            })
         }
-
-        return format!("{} {:.0}GB/{:.0}GB", bar, used_gb, total_gb);
+        _ => false,
     }
-    "unknown".to_string()
 }
 
-// Get filesystem stats using statvfs syscall
-// Returns (total_bytes, used_bytes) or None on failure
-fn get_fs_stats(path: &str) -> Option<(u64, u64)> {
-    use std::ffi::CString;
-    use std::mem::MaybeUninit;
+// Is the device part of something stacked on top of it (md array member, bcache backing
+// device, multipath path)? Those list the device above them in holders/.
+// `sys_class_block` stands in for /sys/class/block.
+fn has_holders(sys_class_block: &std::path::Path, device: &str) -> bool {
+    // /dev/disk/by-uuid/... and /dev/mapper/... are symlinks to the kernel name
+    let path = fs::canonicalize(device).unwrap_or_else(|_| device.into());
+    let Some(name) = path.file_name() else {
+        return false;
+    };
+    fs::read_dir(sys_class_block.join(name).join("holders"))
+        .is_ok_and(|mut holders| holders.next().is_some())
+}
 
-    let c_path = CString::new(path).ok()?;
-    let mut stat: MaybeUninit<libc::statvfs> = MaybeUninit::uninit();
+// Is "/" on a LUKS volume? Follows the device mapper layers down from the root device,
+// so LVM-on-LUKS (root LV -> dm-crypt -> partition) counts too.
+pub fn root_is_encrypted() -> bool {
+    let Some(root) = root_block_device() else {
+        return false;
+    };
+    is_on_luks(std::path::Path::new("/sys/block"), &root)
+}
 
-    // SAFETY: statvfs is a standard POSIX syscall, c_path is valid null-terminated string
-    let result = unsafe { libc::statvfs(c_path.as_ptr(), stat.as_mut_ptr()) };
+// Does `device` or anything under it have a LUKS device mapper uuid?
+// `sys_block` stands in for /sys/block.
+fn is_on_luks(sys_block: &std::path::Path, device: &str) -> bool {
+    block_device_layers(sys_block, device).iter().any(|device| {
+        fs::read_to_string(sys_block.join(device).join("dm/uuid"))
+            .is_ok_and(|uuid| uuid.starts_with("CRYPT-LUKS"))
+    })
+}
 
-    if result != 0 {
-        return None;
-    }
+// Kernel name of the block device mounted at "/" ("dm-1", "nvme0n1p2")
+fn root_block_device() -> Option<String> {
+    let mounts = fs::read_to_string("/proc/mounts").ok()?;
+    // /dev/mapper/root is a symlink to ../dm-1
+    let resolved = fs::canonicalize(root_mount_source(&mounts)?).ok()?;
+    Some(resolved.file_name()?.to_string_lossy().into_owned())
+}
 
-    // SAFETY: statvfs succeeded, stat is now initialized
-    let stat = unsafe { stat.assume_init() };
+// The device mounted on "/" in /proc/mounts content. The last mount on "/" is the one
+// that's visible.
+fn root_mount_source(mounts: &str) -> Option<&str> {
+    mounts.lines().rev().find_map(|line| {
+        let mut fields = line.split_whitespace();
+        let device = fields.next()?;
+        (fields.next()? == "/").then_some(device)
+    })
+}
+
+// A block device and everything it sits on: device mapper devices list their backing
+// devices in /sys/block/<dev>/slaves. Partitions have no /sys/block entry, the walk ends there.
+fn block_device_layers(sys_block: &std::path::Path, device: &str) -> Vec<String> {
+    let mut layers: Vec<String> = Vec::new();
+    let mut pending = vec![device.to_string()];
+    while let Some(device) = pending.pop() {
+        // Real stacks are a few layers deep, the cap is just in case sysfs loops
+        if layers.contains(&device) || layers.len() >= 32 {
+            continue;
+        }
+        if let Ok(entries) = fs::read_dir(sys_block.join(&device).join("slaves")) {
+            pending.extend(
+                entries
+                    .filter_map(|entry| entry.ok())
+                    .map(|entry| entry.file_name().to_string_lossy().into_owned()),
+            );
+        }
+        layers.push(device);
+    }
+    layers
+}
+
+// Get storage usage for all physical disks as a visual bar, and the (used, total) bytes it was
+// made from so usage_deltas and --json don't measure every mount a second time
+pub fn storage() -> (String, Option<(u64, u64)>) {
+    let Some((total_bytes, used_bytes)) = storage_totals() else {
+        return ("unknown".to_string(), None);
+    };
+    let bar = create_bar("storage", usage_percent(used_bytes, total_bytes));
+    let line =
+        format!("{} {}/{}", bar, format_storage_size(used_bytes), format_storage_size(total_bytes));
+    (line, Some((used_bytes, total_bytes)))
+}
+
+// Used share in percent, kept in 0-100 for filesystems that report more used than total
+// (overlayfs does that)
+fn usage_percent(used_bytes: u64, total_bytes: u64) -> f64 {
+    if total_bytes == 0 {
+        return 0.0;
+    }
+    (used_bytes as f64 / total_bytes as f64 * 100.0).clamp(0.0, 100.0)
+}
+
+// Decimal sizes (1 GB = 1,000,000,000 bytes) in the biggest unit that's at least 1:
+// "512MB", "271GB", "1.50TB", "2PB". GB and below are whole numbers, TB and up get two
+// decimals to stay useful, with .00 trimmed (1.00TB -> 1TB) to free up horizontal space.
+// Sizes that would round up to 1000 of a unit go to the next one (999.7MB is 1GB).
+fn format_storage_size(bytes: u64) -> String {
+    const UNITS: [(&str, f64); 4] = [("PB", 1e15), ("TB", 1e12), ("GB", 1e9), ("MB", 1e6)];
+
+    let bytes = bytes as f64;
+    let (unit, size) = UNITS
+        .iter()
+        .find(|(_, scale)| bytes >= scale * 0.9995)
+        .map_or(("MB", bytes / 1e6), |(unit, scale)| (*unit, bytes / scale));
+
+    if matches!(unit, "MB" | "GB") || (size - size.round()).abs() < 0.005 {
+        format!("{:.0}{}", size, unit)
+    } else {
+        format!("{:.2}{}", size, unit)
+    }
+}
+
+// Extra template fields for the storage line: used, total, percent, bar
+pub fn storage_fields(_value: &str) -> Fields {
+    let Some((total_bytes, used_bytes)) = storage_totals() else {
+        return vec![];
+    };
+    let usage_percent = usage_percent(used_bytes, total_bytes);
+    vec![
+        ("used", format_storage_size(used_bytes)),
+        ("total", format_storage_size(total_bytes)),
+        ("percent", format!("{:.0}%", usage_percent)),
+        ("bar", create_bar("storage", usage_percent)),
+    ]
+}
+
+// Space used on the filesystem /nix/store lives on, only when that's a filesystem of its own.
+// Sharing one with / the used space would be everything else too, and du is way too slow.
+pub fn nix_store_size() -> Option<String> {
+    use std::os::unix::fs::MetadataExt;
+    let store = fs::metadata("/nix/store").ok()?;
+    if store.dev() == fs::metadata("/").ok()?.dev() {
+        return None;
+    }
+    let (_, used, _) = get_fs_stats("/nix/store")?;
+    Some(format_storage_size(used))
+}
+
+// Get filesystem stats using statvfs syscall
+// Returns (total_bytes, used_bytes, fsid) or None on failure
+fn get_fs_stats(path: &str) -> Option<(u64, u64, libc::c_ulong)> {
+    use std::ffi::CString;
+    use std::mem::MaybeUninit;
+
+    let c_path = CString::new(path).ok()?;
+    let mut stat: MaybeUninit<libc::statvfs> = MaybeUninit::uninit();
+
+    // SAFETY: statvfs is a standard POSIX syscall, c_path is valid null-terminated string
+    let result = unsafe { libc::statvfs(c_path.as_ptr(), stat.as_mut_ptr()) };
+
+    if result != 0 {
+        return None;
+    }
+
+    // SAFETY: statvfs succeeded, stat is now initialized
+    let stat = unsafe { stat.assume_init() };
 
     let block_size = stat.f_frsize as u64;
     let total_blocks = stat.f_blocks as u64;
     let free_blocks = stat.f_bfree as u64;
 
-    let total = total_blocks * block_size;
-    let used = (total_blocks - free_blocks) * block_size;
+    // Pseudo filesystems that slipped past the filter report zero blocks, they'd only
+    // drag the bar around
+    if total_blocks == 0 {
+        return None;
+    }
+
+    let total = total_blocks.saturating_mul(block_size);
+    let used = total_blocks.saturating_sub(free_blocks).saturating_mul(block_size);
+
+    Some((total, used, stat.f_fsid))
+}
+
+// Sound servers get the same leash as the other subprocess probes
+const AUDIO_PROBE_MAX_BYTES: u64 = 256 * 1024;
+const AUDIO_PROBE_TIMEOUT: Duration = Duration::from_millis(500);
+
+// Get the default audio output device, e.g. "Schiit Modi 3" or "Family 17h HD Audio".
+// Asks PipeWire, then PulseAudio, then falls back to the first ALSA card.
+// Returns empty string when there's no audio hardware (hidden).
+pub fn audio() -> String {
+    audio_from_wpctl()
+        .or_else(audio_from_pactl)
+        .or_else(audio_from_asound)
+        .map(|name| clean_audio_name(&name))
+        .unwrap_or_default()
+}
+
+fn audio_probe(program: &str, args: &[&str]) -> Option<String> {
+    let output = command_output_limited(
+        Command::new(program).args(args),
+        AUDIO_PROBE_MAX_BYTES,
+        AUDIO_PROBE_TIMEOUT,
+    )?;
+    Some(String::from_utf8_lossy(&output).into_owned())
+}
+
+// PipeWire: node.description = "Schiit Modi 3 Analog Stereo"
+fn audio_from_wpctl() -> Option<String> {
+    wpctl_description(&audio_probe("wpctl", &["inspect", "@DEFAULT_AUDIO_SINK@"])?)
+}
+
+fn wpctl_description(output: &str) -> Option<String> {
+    output.lines().find_map(|line| {
+        let value = line.trim().trim_start_matches("* ").strip_prefix("node.description")?;
+        let value = value.trim_start().strip_prefix('=')?.trim().trim_matches('"');
+        (!value.is_empty()).then(|| value.to_string())
+    })
+}
+
+// PulseAudio: find the default sink's block in `pactl list sinks` and take its Description
+fn audio_from_pactl() -> Option<String> {
+    let default_sink = audio_probe("pactl", &["get-default-sink"])?;
+    let default_sink = default_sink.trim();
+    if default_sink.is_empty() {
+        return None;
+    }
+    pactl_description(default_sink, &audio_probe("pactl", &["list", "sinks"])?)
+}
+
+fn pactl_description(default_sink: &str, sinks: &str) -> Option<String> {
+    let mut in_default = false;
+    for line in sinks.lines() {
+        let line = line.trim();
+        if let Some(name) = line.strip_prefix("Name:") {
+            in_default = name.trim() == default_sink;
+        } else if in_default && let Some(description) = line.strip_prefix("Description:") {
+            return Some(description.trim().to_string());
+        }
+    }
+    None
+}
+
+// ALSA: " 0 [Generic        ]: HDA-Intel - HD-Audio Generic", the name is after " - "
+fn audio_from_asound() -> Option<String> {
+    asound_card(&read_first_line("/proc/asound/cards")?)
+}
+
+fn asound_card(line: &str) -> Option<String> {
+    let (_, name) = line.split_once(" - ")?;
+    let name = name.trim();
+    (!name.is_empty()).then(|| name.to_string())
+}
+
+// Strip the profile suffixes sound servers tack onto device names
+fn clean_audio_name(name: &str) -> String {
+    const SUFFIXES: &[&str] = &[
+        " Analog Stereo",
+        " Digital Stereo (HDMI)",
+        " Digital Stereo (IEC958)",
+        " Digital Surround 5.1",
+        " Analog Surround 5.1",
+        " Pro",
+    ];
+
+    let mut name = name.trim();
+    for suffix in SUFFIXES {
+        if let Some(stripped) = name.strip_suffix(suffix) {
+            name = stripped.trim_end();
+            break;
+        }
+    }
+    name.to_string()
+}
+
+// How long to wait between the two activity samples
+const ACTIVITY_SAMPLE_INTERVAL: Duration = Duration::from_millis(100);
+
+// Get disk and network throughput right now, e.g. "󰋊 12MB/s · 󰈀 1.2MB/s ↓ 0.1MB/s ↑".
+// Takes two samples ACTIVITY_SAMPLE_INTERVAL apart, so only ever call this from a worker thread.
+// Returns empty string if neither counter could be read.
+pub fn activity() -> String {
+    let interface = default_interface();
+    let sample = || {
+        let disk = read_disk_bytes();
+        let net = interface.as_deref().and_then(read_net_bytes);
+        (disk, net)
+    };
+
+    let (disk_before, net_before) = sample();
+    std::thread::sleep(ACTIVITY_SAMPLE_INTERVAL);
+    let (disk_after, net_after) = sample();
+
+    let seconds = ACTIVITY_SAMPLE_INTERVAL.as_secs_f64();
+    let rate = |before: u64, after: u64| counter_delta(before, after) as f64 / seconds;
+    let mut parts = Vec::new();
+
+    if let (Some(before), Some(after)) = (disk_before, disk_after) {
+        parts.push(format!("󰋊 {}", format_rate(rate(before, after))));
+    }
+    if let (Some((rx_before, tx_before)), Some((rx_after, tx_after))) = (net_before, net_after) {
+        parts.push(format!(
+            "󰈀 {} ↓ {} ↑",
+            format_rate(rate(rx_before, rx_after)),
+            format_rate(rate(tx_before, tx_after))
+        ));
+    }
+
+    parts.join(" · ")
+}
+
+// Difference between two counter samples.
+// Some counters are 32-bit on some kernels and wrap, a drop on a bigger counter means it was
+// reset (e.g. the interface came back up) so there's no meaningful delta.
+fn counter_delta(before: u64, after: u64) -> u64 {
+    if after >= before {
+        after - before
+    } else if before <= u32::MAX as u64 {
+        after + (u32::MAX as u64 + 1) - before
+    } else {
+        0
+    }
+}
+
+// "12MB/s" or "1.2MB/s" (decimal MB, like storage)
+fn format_rate(bytes_per_second: f64) -> String {
+    let mb = bytes_per_second / 1_000_000.0;
+    if mb >= 10.0 {
+        format!("{:.0}MB/s", mb)
+    } else {
+        format!("{:.1}MB/s", mb)
+    }
+}
+
+// Total bytes read + written on physical disks from /proc/diskstats.
+// Only devices with a /sys/block/<name>/device link count, that skips partitions (double counting)
+// and virtual devices like loop, zram and dm-*.
+fn read_disk_bytes() -> Option<u64> {
+    let content = fs::read_to_string("/proc/diskstats").ok()?;
+    disk_bytes(&content, std::path::Path::new("/sys/block"))
+}
+
+// read_disk_bytes over a diskstats content, with `sys_block` standing in for /sys/block
+fn disk_bytes(content: &str, sys_block: &std::path::Path) -> Option<u64> {
+    let mut sectors: u64 = 0;
+    let mut found = false;
+
+    for line in content.lines() {
+        // major minor name reads merged sectors_read ms writes merged sectors_written ...
+        let fields: Vec<&str> = line.split_whitespace().collect();
+        if fields.len() < 10 {
+            continue;
+        }
+        if !sys_block.join(fields[2]).join("device").exists() {
+            continue;
+        }
+        let read = fields[5].parse::<u64>().unwrap_or(0);
+        let written = fields[9].parse::<u64>().unwrap_or(0);
+        sectors = sectors.wrapping_add(read).wrapping_add(written);
+        found = true;
+    }
+
+    // diskstats sectors are always 512 bytes, whatever the real sector size is
+    found.then(|| sectors.wrapping_mul(512))
+}
+
+// Interface carrying the default route, from /proc/net/route
+fn default_interface() -> Option<String> {
+    route_interface(&fs::read_to_string("/proc/net/route").ok()?)
+}
+
+fn route_interface(content: &str) -> Option<String> {
+    content.lines().skip(1).find_map(|line| {
+        let mut fields = line.split_whitespace();
+        let interface = fields.next()?;
+        let destination = fields.next()?;
+        (destination == "00000000").then(|| interface.to_string())
+    })
+}
+
+// How long `iw dev <if> link` gets to name the Wi-Fi network
+const IW_TIMEOUT: Duration = Duration::from_secs(1);
+const IW_MAX_BYTES: u64 = 4096;
+
+// The interface with the default route and what's known about its link, e.g.
+// "󰖩 wlan0 · MyWifi (-52dBm) · 192.168.1.42" or "󰈀 eth0 · 2.5Gbps". Every part is looked up
+// on its own and left out when it can't be, the addresses only with show_ip.
+// Runs iw for the SSID, so only call this from a worker thread.
+pub fn network(show_ipv4: bool, show_ipv6: bool) -> String {
+    let Some(interface) = default_interface() else {
+        return String::new();
+    };
+    let wireless = fs::metadata(format!("/sys/class/net/{}/wireless", interface)).is_ok();
+    let icon = if wireless { "󰖩" } else { "󰈀" };
+    let mut parts = vec![format!("{} {}", icon, interface)];
+
+    if wireless {
+        let ssid = command_output_limited(
+            Command::new("iw").args(["dev", &interface, "link"]),
+            IW_MAX_BYTES,
+            IW_TIMEOUT,
+        )
+        .and_then(|output| parse_iw_ssid(&String::from_utf8_lossy(&output)));
+        let signal = fs::read_to_string("/proc/net/wireless")
+            .ok()
+            .and_then(|content| parse_wireless_signal(&content, &interface));
+        match (ssid, signal) {
+            (Some(ssid), Some(signal)) => parts.push(format!("{} ({}dBm)", ssid, signal)),
+            (Some(ssid), None) => parts.push(ssid),
+            (None, Some(signal)) => parts.push(format!("{}dBm", signal)),
+            (None, None) => {}
+        }
+    } else if let Some(speed) = read_first_line(&format!("/sys/class/net/{}/speed", interface))
+        .and_then(|speed| speed.trim().parse::<i64>().ok())
+        .filter(|&speed| speed > 0)
+    {
+        parts.push(format_link_speed(speed as u64));
+    }
+
+    if show_ipv4 && let Some(address) = ipv4_address(&interface) {
+        parts.push(address.to_string());
+    }
+    if show_ipv6
+        && let Some(address) = fs::read_to_string("/proc/net/if_inet6")
+            .ok()
+            .and_then(|content| parse_global_ipv6(&content, &interface))
+    {
+        parts.push(address.to_string());
+    }
+    parts.join(" · ")
+}
+
+// Link speed from sysfs (Mbit/s) as "100Mbps", "1Gbps", "2.5Gbps"
+fn format_link_speed(mbps: u64) -> String {
+    if mbps >= 1000 && mbps.is_multiple_of(1000) {
+        format!("{}Gbps", mbps / 1000)
+    } else if mbps >= 1000 {
+        format!("{:.1}Gbps", mbps as f64 / 1000.0)
+    } else {
+        format!("{}Mbps", mbps)
+    }
+}
+
+// The network name from `iw dev <if> link`, None when it says "Not connected."
+fn parse_iw_ssid(output: &str) -> Option<String> {
+    output
+        .lines()
+        .find_map(|line| line.trim().strip_prefix("SSID: "))
+        .map(|ssid| ssid.trim().to_string())
+        .filter(|ssid| !ssid.is_empty())
+}
+
+// Signal level in dBm for an interface from /proc/net/wireless:
+// " wlan0: 0000   70.  -40.  -256  ..." is status, link quality, level, noise.
+// Some drivers write the level as an unsigned byte, 216 for -40.
+fn parse_wireless_signal(content: &str, interface: &str) -> Option<i32> {
+    content.lines().skip(2).find_map(|line| {
+        let (name, fields) = line.split_once(':')?;
+        if name.trim() != interface {
+            return None;
+        }
+        let level: i32 = fields.split_whitespace().nth(2)?.trim_end_matches('.').parse().ok()?;
+        match level {
+            0 => None,
+            1..=255 => Some(level - 256),
+            _ => Some(level),
+        }
+    })
+}
+
+// The interface's first IPv4 address
+fn ipv4_address(interface: &str) -> Option<std::net::Ipv4Addr> {
+    let mut addresses: *mut libc::ifaddrs = std::ptr::null_mut();
+    // SAFETY: getifaddrs fills in a list we free below, nothing else touches it
+    if unsafe { libc::getifaddrs(&mut addresses) } != 0 {
+        return None;
+    }
+    let mut found = None;
+    let mut current = addresses;
+    while !current.is_null() && found.is_none() {
+        // SAFETY: current is a node of the list getifaddrs returned, which is still allocated
+        let entry = unsafe { &*current };
+        current = entry.ifa_next;
+        if entry.ifa_addr.is_null() || entry.ifa_name.is_null() {
+            continue;
+        }
+        // SAFETY: ifa_name is a NUL terminated string and ifa_addr a sockaddr, checked non-null.
+        // An AF_INET sockaddr is a sockaddr_in.
+        unsafe {
+            let name = std::ffi::CStr::from_ptr(entry.ifa_name);
+            if name.to_bytes() == interface.as_bytes()
+                && i32::from((*entry.ifa_addr).sa_family) == libc::AF_INET
+            {
+                let address = &*(entry.ifa_addr as *const libc::sockaddr_in);
+                found = Some(std::net::Ipv4Addr::from(u32::from_be(address.sin_addr.s_addr)));
+            }
+        }
+    }
+    // SAFETY: the list came from getifaddrs and isn't used after this
+    unsafe { libc::freeifaddrs(addresses) };
+    found
+}
+
+// The interface's global IPv6 address from /proc/net/if_inet6, one address per line as
+// "<32 hex digits> <index> <prefix length> <scope> <flags> <name>". Scope 00 is global,
+// deprecated addresses (flag 0x20) are on their way out and skipped.
+fn parse_global_ipv6(content: &str, interface: &str) -> Option<std::net::Ipv6Addr> {
+    content.lines().find_map(|line| {
+        let fields: Vec<&str> = line.split_whitespace().collect();
+        let [address, _, _, scope, flags, name] = fields[..] else {
+            return None;
+        };
+        let flags = u32::from_str_radix(flags, 16).ok()?;
+        if name != interface || scope != "00" || flags & 0x20 != 0 {
+            return None;
+        }
+        u128::from_str_radix(address, 16).ok().map(std::net::Ipv6Addr::from)
+    })
+}
+
+// (rx_bytes, tx_bytes) for an interface from /proc/net/dev
+fn read_net_bytes(interface: &str) -> Option<(u64, u64)> {
+    net_bytes(&fs::read_to_string("/proc/net/dev").ok()?, interface)
+}
+
+fn net_bytes(content: &str, interface: &str) -> Option<(u64, u64)> {
+    content.lines().find_map(|line| {
+        let (name, counters) = line.split_once(':')?;
+        if name.trim() != interface {
+            return None;
+        }
+        // rx: bytes packets errs drop fifo frame compressed multicast, then tx: bytes ...
+        let fields: Vec<u64> = counters
+            .split_whitespace()
+            .map(|f| f.parse().unwrap_or(0))
+            .collect();
+        Some((*fields.first()?, *fields.get(8)?))
+    })
+}
+
+// Handhelds by DMI: sys_vendor, the DMI file naming the model, its prefix and what the Host
+// line calls it. First match wins, so the Ally X and Go S come before the Ally and Go.
+const HANDHELDS: &[(&str, &str, &str, &str)] = &[
+    ("Valve", "product_name", "Jupiter", "Steam Deck LCD"),
+    ("Valve", "product_name", "Galileo", "Steam Deck OLED"),
+    ("ASUSTeK COMPUTER INC.", "product_name", "ROG Ally X", "ASUS ROG Ally X"),
+    ("ASUSTeK COMPUTER INC.", "product_name", "ROG Ally", "ASUS ROG Ally"),
+    ("LENOVO", "product_version", "Legion Go S", "Lenovo Legion Go S"),
+    ("LENOVO", "product_version", "Legion Go", "Lenovo Legion Go"),
+];
+
+// What board makers leave in DMI fields they didn't fill in
+const DMI_PLACEHOLDERS: &[&str] = &[
+    "",
+    "To Be Filled By O.E.M.",
+    "System Product Name",
+    "System Version",
+    "Default string",
+    "Not Applicable",
+    "None",
+];
+
+// Where the kernel puts the DMI tables' strings
+const DMI_DIR: &str = "/sys/class/dmi/id";
+
+// A DMI field, None when it's missing or a placeholder
+fn dmi(dmi_dir: &std::path::Path, field: &str) -> Option<String> {
+    read_first_line(&dmi_dir.join(field).to_string_lossy())
+        .map(|value| value.trim().to_string())
+        .filter(|value| !DMI_PLACEHOLDERS.contains(&value.as_str()))
+}
 
-    Some((total, used))
+// The handheld this is running on, as the Host line names it
+pub fn handheld() -> Option<&'static str> {
+    handheld_in(std::path::Path::new(DMI_DIR))
 }
 
-// Get battery status if device is a laptop (chassis check)
-pub fn laptop_battery() -> String {
+fn handheld_in(dmi_dir: &std::path::Path) -> Option<&'static str> {
+    let vendor = dmi(dmi_dir, "sys_vendor")?;
+    HANDHELDS
+        .iter()
+        .find(|(maker, field, prefix, _)| {
+            *maker == vendor && dmi(dmi_dir, field).is_some_and(|model| model.starts_with(prefix))
+        })
+        .map(|&(.., name)| name)
+}
+
+// The machine's make and model from DMI, handhelds get their marketing name.
+// Lenovo puts a part number in product_name, the model is in product_version there.
+pub fn host() -> String {
+    host_in(std::path::Path::new(DMI_DIR))
+}
+
+fn host_in(dmi_dir: &std::path::Path) -> String {
+    if let Some(name) = handheld_in(dmi_dir) {
+        return name.to_string();
+    }
+    let vendor = dmi(dmi_dir, "sys_vendor");
+    let model = match vendor.as_deref() {
+        Some("LENOVO") => {
+            dmi(dmi_dir, "product_version").or_else(|| dmi(dmi_dir, "product_name"))
+        }
+        _ => dmi(dmi_dir, "product_name"),
+    };
+    match (vendor, model) {
+        // "Framework Laptop 13" already says who made it
+        (Some(vendor), Some(model)) if model.starts_with(&vendor) => model,
+        (Some(vendor), Some(model)) => format!("{} {}", vendor, model),
+        (None, Some(model)) => model,
+        _ => "unknown".to_string(),
+    }
+}
+
+// Get battery status if device is a laptop (chassis check), with the power draw or charge rate
+// and the time left when `show_power` is on ("64% 󰂍 · 12.4W (~3h 40m)"). That takes two samples
+// BATTERY_SAMPLE_INTERVAL apart, so only call this from a worker thread.
+pub fn laptop_battery(thresholds: BatteryThresholds, show_power: bool) -> (String, Option<u8>) {
+    battery_in(std::path::Path::new("/"), thresholds, show_power)
+}
+
+// laptop_battery with `root` standing in for "/", for fixture trees. The charge in percent
+// comes along for --json.
+fn battery_in(
+    root: &std::path::Path,
+    thresholds: BatteryThresholds,
+    show_power: bool,
+) -> (String, Option<u8>) {
     // Check chassis type to determine if it's a laptop
     // 8: Portable, 9: Laptop, 10: Notebook, 11: Hand Held, 12: Docking Station,
     // 14: Sub Notebook, 30: Tablet, 31: Convertible, 32: Detachable
-    let is_laptop = read_first_line("/sys/class/dmi/id/chassis_type")
+    let chassis_type = root.join("sys/class/dmi/id/chassis_type");
+    let is_laptop = read_first_line(chassis_type.to_str().unwrap_or(""))
         .and_then(|t| t.trim().parse::<u32>().ok())
         .map(|t| matches!(t, 8 | 9 | 10 | 11 | 12 | 14 | 30 | 31 | 32))
         .unwrap_or(false);
 
     if !is_laptop {
-        return "unknown".to_string();
+        return ("unknown".to_string(), None);
     }
 
     // Find first available battery (usually BAT0 or BAT1)
-    let power_supply = std::path::Path::new("/sys/class/power_supply");
-    if let Ok(entries) = fs::read_dir(power_supply) {
-        for entry in entries.flatten() {
-            let name = entry.file_name();
-            let name_str = name.to_string_lossy();
+    let Some((capacity, status, path)) =
+        find_power_supply(root, |name, _| name.starts_with("BAT"))
+    else {
+        return ("unknown".to_string(), None);
+    };
+
+    let status_icon = match status.as_str() {
+        "Charging" => "󰂐",
+        _ if capacity < thresholds.critical => "󰂃",
+        "Discharging" => "󰂍",
+        _ => &status,
+    };
 
-            if name_str.starts_with("BAT") {
-                let path = entry.path();
+    let bar = create_bar("battery", capacity as f64);
+    let mut text = format!("{}% {}", capacity, status_icon);
+    let charging = match status.as_str() {
+        "Charging" => Some(true),
+        "Discharging" => Some(false),
+        // Full or "Not charging", nothing's flowing
+        _ => None,
+    };
+    if show_power
+        && let Some(charging) = charging
+        && let Some((watts, remaining)) = battery_rate(&path, charging)
+    {
+        text.push_str(&format!(" · {:.1}W", watts));
+        if let Some(seconds) = remaining {
+            text.push_str(&format!(" (~{})", format_duration(seconds, Precision::Minutes)));
+        }
+    }
+    (severity_colored("battery", &bar, &text, capacity, thresholds), Some(capacity))
+}
 
-                // Get capacity
-                let capacity = read_first_line(path.join("capacity").to_str().unwrap_or(""))
-                    .and_then(|c| c.parse::<u8>().ok())
-                    .unwrap_or(0);
+// How long to wait between the two battery power samples, power_now jumps around a lot
+const BATTERY_SAMPLE_INTERVAL: Duration = Duration::from_millis(50);
+// Longer than this to empty or full is a nearly idle battery, the estimate means nothing
+const MAX_BATTERY_ESTIMATE_SECS: f64 = 24.0 * 3600.0;
+
+// Watts going in or out of the battery, averaged over two samples, and the seconds until it's
+// empty (or full when charging). None when the driver reports neither power_now nor
+// current_now and voltage_now, or when nothing's flowing.
+// sysfs units are micro: µW, µA, µV, µWh and µAh. The sign of current_now and power_now
+// depends on the driver (some report discharging as negative), so it's ignored: the status says
+// which way it goes.
+fn battery_rate(battery: &std::path::Path, charging: bool) -> Option<(f64, Option<u64>)> {
+    let read = |name: &str| {
+        read_first_line(battery.join(name).to_str()?)?.trim().parse::<f64>().ok().map(f64::abs)
+    };
+    // (watts, amps), amps only when the driver has current_now
+    let sample = || -> Option<(f64, Option<f64>)> {
+        let amps = read("current_now").map(|current| current / 1e6);
+        let watts = match read("power_now") {
+            Some(power) => power / 1e6,
+            None => amps? * read("voltage_now")? / 1e6,
+        };
+        Some((watts, amps))
+    };
 
-                // Get status
-                let status = read_first_line(path.join("status").to_str().unwrap_or(""))
-                    .unwrap_or_else(|| "Unknown".to_string());
+    let first = sample()?;
+    std::thread::sleep(BATTERY_SAMPLE_INTERVAL);
+    let second = sample().unwrap_or(first);
+    let watts = (first.0 + second.0) / 2.0;
+    if watts < 0.05 {
+        return None;
+    }
 
-                let status_icon = match status.as_str() {
-                    "Charging" => "󰂐",
-                    "Discharging" => "󰂍",
-                    _ => &status,
-                };
+    // Energy based drivers count in µWh, charge based ones in µAh, which goes with the current
+    let left = |now: f64, full: f64| if charging { (full - now).max(0.0) } else { now };
+    let hours = match (read("energy_now"), read("energy_full")) {
+        (Some(now), Some(full)) => Some(left(now, full) / 1e6 / watts),
+        _ => match (read("charge_now"), read("charge_full"), first.1.zip(second.1)) {
+            (Some(now), Some(full), Some((a, b))) if a + b > 0.0 => {
+                Some(left(now, full) / 1e6 / ((a + b) / 2.0))
+            }
+            _ => None,
+        },
+    };
+    let seconds = hours
+        .map(|hours| hours * 3600.0)
+        .filter(|seconds| (60.0..MAX_BATTERY_ESTIMATE_SECS).contains(seconds))
+        .map(|seconds| seconds as u64);
+    Some((watts, seconds))
+}
+
+// Get UPS charge and whether we're running on it, for desktops behind a UPS.
+// Not gated on chassis type, plenty of desktops have one. "unknown" when there's no UPS.
+pub fn ups(thresholds: BatteryThresholds) -> String {
+    ups_in(std::path::Path::new("/"), thresholds)
+}
+
+fn ups_in(root: &std::path::Path, thresholds: BatteryThresholds) -> String {
+    let is_ups = |_: &str, path: &std::path::Path| {
+        read_first_line(path.join("type").to_str().unwrap_or("")).as_deref() == Some("UPS")
+    };
+    let Some((capacity, status, _)) = find_power_supply(root, is_ups) else {
+        return "unknown".to_string();
+    };
+
+    let state = if status == "Discharging" {
+        "On battery"
+    } else {
+        "Online"
+    };
+
+    let bar = create_bar("ups", capacity as f64);
+    let text = format!("{}% {}", capacity, state);
+    severity_colored("ups", &bar, &text, capacity, thresholds)
+}
+
+// First /sys/class/power_supply entry under `root` that `wanted` accepts (by name and path),
+// as (capacity, status, path)
+fn find_power_supply(
+    root: &std::path::Path,
+    wanted: impl Fn(&str, &std::path::Path) -> bool,
+) -> Option<(u8, String, std::path::PathBuf)> {
+    let entries = fs::read_dir(root.join("sys/class/power_supply")).ok()?;
+    for entry in entries.flatten() {
+        let path = entry.path();
+        if !wanted(&entry.file_name().to_string_lossy(), &path) {
+            continue;
+        }
+
+        // Get capacity
+        let capacity = read_first_line(path.join("capacity").to_str().unwrap_or(""))
+            .and_then(|c| c.parse::<u8>().ok())
+            .unwrap_or(0);
+
+        // Get status
+        let status = read_first_line(path.join("status").to_str().unwrap_or(""))
+            .unwrap_or_else(|| "Unknown".to_string());
+
+        return Some((capacity, status, path));
+    }
+    None
+}
+
+// Color a battery/UPS value ("<bar> <text>") by how low the charge is.
+// A gradient bar was asked for explicitly and keeps its own colors, only the text changes then.
+fn severity_colored(
+    metric: &str,
+    bar: &str,
+    text: &str,
+    capacity: u8,
+    thresholds: BatteryThresholds,
+) -> String {
+    let color: fn(&str) -> String = if capacity < thresholds.critical {
+        color_critical
+    } else if capacity < thresholds.low {
+        color_warning
+    } else {
+        return format!("{} {}", bar, text);
+    };
+    if bar_style(metric).fill == BarFill::Gradient {
+        format!("{} {}", bar, color(text))
+    } else {
+        color(&format!("{} {}", bar, text))
+    }
+}
+
+// powerprofilesctl asks the daemon over D-Bus, anything slower than this is stuck
+const POWERPROFILESCTL_TIMEOUT: Duration = Duration::from_millis(500);
+const POWERPROFILESCTL_MAX_BYTES: u64 = 1024;
+
+// The active power profile and the CPU governor for power_detail, "performance 󱐋 · schedutil".
+// "unknown" when nothing says what the profile is. Runs powerprofilesctl, so only call this
+// from a worker thread.
+pub fn power() -> String {
+    let root = std::path::Path::new("/");
+    let profile = power_profile(root, || {
+        let output = command_output_limited(
+            Command::new("powerprofilesctl").arg("get"),
+            POWERPROFILESCTL_MAX_BYTES,
+            POWERPROFILESCTL_TIMEOUT,
+        )?;
+        parse_profile_name(&String::from_utf8_lossy(&output))
+    });
+    let Some(profile) = profile else {
+        return "unknown".to_string();
+    };
+
+    let mut value = profile.to_string();
+    if get_cached_is_nerd_font() {
+        let icon = match profile {
+            "performance" => "󱐋",
+            "balanced" => "󰗑",
+            _ => "󰌪",
+        };
+        value = format!("{} {}", value, icon);
+    }
+    let governor = root.join("sys/devices/system/cpu/cpufreq/policy0/scaling_governor");
+    if let Some(governor) = read_first_line(governor.to_str().unwrap_or(""))
+        .map(|governor| governor.trim().to_string())
+        .filter(|governor| !governor.is_empty())
+    {
+        value = format!("{} · {}", value, governor);
+    }
+    value
+}
+
+// Where the profile comes from, first answer wins:
+// 1. /sys/firmware/acpi/platform_profile, what power-profiles-daemon sets on most laptops
+// 2. powerprofilesctl get, for power-profiles-daemon without platform profile support
+// 3. TLP's /run/tlp/last_pwr, the profile it last applied
+// 4. With TLP running but no last_pwr yet, AC or battery: TLP uses its AC settings on mains
+// `root` is "/" outside of checking the order against a fake tree.
+fn power_profile(
+    root: &std::path::Path,
+    powerprofilesctl: impl FnOnce() -> Option<&'static str>,
+) -> Option<&'static str> {
+    let read = |path: &str| fs::read_to_string(root.join(path)).ok();
+    if let Some(profile) = read("sys/firmware/acpi/platform_profile")
+        .and_then(|content| parse_platform_profile(&content))
+    {
+        return Some(profile);
+    }
+    if let Some(profile) = powerprofilesctl() {
+        return Some(profile);
+    }
+    if !root.join("run/tlp").is_dir() {
+        return None;
+    }
+    if let Some(profile) = read("run/tlp/last_pwr").and_then(|content| parse_tlp_last_pwr(&content))
+    {
+        return Some(profile);
+    }
+    let on_ac = on_ac_power(&root.join("sys/class/power_supply"))?;
+    Some(if on_ac { "performance" } else { "balanced" })
+}
+
+// The kernel's platform profile names, in power-profiles-daemon's three
+fn parse_platform_profile(content: &str) -> Option<&'static str> {
+    match content.trim() {
+        "performance" => Some("performance"),
+        "balanced" | "balanced-performance" => Some("balanced"),
+        "low-power" | "quiet" | "cool" => Some("power-saver"),
+        _ => None,
+    }
+}
+
+// powerprofilesctl get prints the profile name alone
+fn parse_profile_name(output: &str) -> Option<&'static str> {
+    match output.trim() {
+        "performance" => Some("performance"),
+        "balanced" => Some("balanced"),
+        "power-saver" => Some("power-saver"),
+        _ => None,
+    }
+}
+
+// last_pwr holds the profile TLP applied last: 0 AC, 1 battery, 2 the power saver profile.
+// Older TLP only writes 0 and 1, same meaning.
+fn parse_tlp_last_pwr(content: &str) -> Option<&'static str> {
+    match content.trim() {
+        "0" => Some("performance"),
+        "1" => Some("balanced"),
+        "2" => Some("power-saver"),
+        _ => None,
+    }
+}
+
+// Whether a mains supply says it's online, None when there's no mains supply to ask
+fn on_ac_power(power_supply: &std::path::Path) -> Option<bool> {
+    let mut found = false;
+    for entry in fs::read_dir(power_supply).ok()?.flatten() {
+        let path = entry.path();
+        let kind = fs::read_to_string(path.join("type")).unwrap_or_default();
+        if kind.trim() != "Mains" {
+            continue;
+        }
+        found = true;
+        if fs::read_to_string(path.join("online")).is_ok_and(|online| online.trim() == "1") {
+            return Some(true);
+        }
+    }
+    found.then_some(false)
+}
+
+// hyprctl/swaymsg answer over a local socket, anything slower than this is stuck
+const DISPLAY_PROBE_MAX_BYTES: u64 = 256 * 1024;
+const DISPLAY_PROBE_TIMEOUT: Duration = Duration::from_millis(500);
+
+// Extras for the Display lines with display_detail on, by connector name ("DP-3")
+#[derive(Default)]
+struct OutputDetails {
+    // Variable refresh rate turned on
+    vrr: Vec<String>,
+    // Running in HDR
+    hdr: Vec<String>,
+    // Laptop panel brightness in percent
+    backlight: Option<(String, u8)>,
+}
+
+// Asks the compositor when it can tell us what's active, else falls back to what DRM says
+// the connected monitors are capable of (VRR) and KDE's kscreen-doctor (HDR).
+fn output_details() -> OutputDetails {
+    let compositor_query: Option<(&str, &[&str])> =
+        if std::env::var_os("HYPRLAND_INSTANCE_SIGNATURE").is_some() {
+            Some(("hyprctl", &["monitors", "-j"]))
+        } else if std::env::var_os("SWAYSOCK").is_some() {
+            Some(("swaymsg", &["-t", "get_outputs", "-r"]))
+        } else {
+            None
+        };
+    let backlight = backlight(std::path::Path::new("/sys/class/backlight"));
+
+    if let Some((program, args)) = compositor_query
+        && let Some(output) = command_output_limited(
+            Command::new(program).args(args),
+            DISPLAY_PROBE_MAX_BYTES,
+            DISPLAY_PROBE_TIMEOUT,
+        )
+    {
+        return compositor_details(&String::from_utf8_lossy(&output), backlight);
+    }
 
-                let bar = create_bar(capacity as f64);
+    let is_kde = std::env::var("XDG_CURRENT_DESKTOP").is_ok_and(|desktop| desktop.contains("KDE"));
+    OutputDetails {
+        vrr: drm_vrr_outputs(std::path::Path::new("/sys/class/drm")),
+        hdr: if is_kde { kscreen_hdr_outputs() } else { Vec::new() },
+        backlight,
+    }
+}
+
+// OutputDetails from `hyprctl monitors -j` or `swaymsg -t get_outputs -r` JSON
+fn compositor_details(json: &str, backlight: Option<(String, u8)>) -> OutputDetails {
+    let monitors = json_objects(json);
+    let names = |keep: &dyn Fn(&str) -> bool| -> Vec<String> {
+        monitors
+            .iter()
+            .filter(|monitor| keep(monitor))
+            .filter_map(|monitor| json_string(monitor, "name").map(str::to_string))
+            .collect()
+    };
+    OutputDetails {
+        // Hyprland: "vrr": true, Sway: "adaptive_sync_status": "enabled"
+        vrr: names(&|monitor| {
+            json_bool(monitor, "vrr") == Some(true)
+                || json_string(monitor, "adaptive_sync_status") == Some("enabled")
+        }),
+        // Newer Hyprland: "hdr": true, or an "hdr"/"hdredid" color management preset
+        hdr: names(&|monitor| {
+            json_bool(monitor, "hdr") == Some(true)
+                || json_string(monitor, "colorManagementPreset")
+                    .is_some_and(|preset| preset.starts_with("hdr"))
+        }),
+        backlight,
+    }
+}
 
-                return format!("{} {}% {}", bar, capacity, status_icon);
+// Outputs with HDR on according to kscreen-doctor (Plasma 6), which prints blocks like
+// "Output: 1 DP-1 enabled connected priority 1 ..." followed by "HDR: enabled".
+fn kscreen_hdr_outputs() -> Vec<String> {
+    let Some(output) = command_output_limited(
+        Command::new("kscreen-doctor").arg("-o"),
+        DISPLAY_PROBE_MAX_BYTES,
+        DISPLAY_PROBE_TIMEOUT,
+    ) else {
+        return Vec::new();
+    };
+
+    kscreen_hdr_in(&String::from_utf8_lossy(&output))
+}
+
+// kscreen_hdr_outputs over `kscreen-doctor -o` output
+fn kscreen_hdr_in(output: &str) -> Vec<String> {
+    // kscreen-doctor colors its output even when piped
+    let text = strip_color_codes(output);
+    let mut outputs = Vec::new();
+    let mut current: Option<&str> = None;
+    for line in text.lines().map(str::trim) {
+        if let Some(rest) = line.strip_prefix("Output:") {
+            current = rest.split_whitespace().nth(1);
+        } else if line.starts_with("HDR:")
+            && line.contains("enabled")
+            && let Some(name) = current
+        {
+            outputs.push(name.to_string());
+        }
+    }
+    outputs
+}
+
+// Drop escape sequences (ESC up to the final letter) from a tool's output
+fn strip_color_codes(text: &str) -> String {
+    let mut stripped = String::with_capacity(text.len());
+    let mut chars = text.chars();
+    while let Some(c) = chars.next() {
+        if c == '\x1b' {
+            chars.by_ref().find(|c| c.is_ascii_alphabetic());
+        } else {
+            stripped.push(c);
+        }
+    }
+    stripped
+}
+
+// Laptop panel brightness as (connector, percent) from /sys/class/backlight.
+// Prefers the raw GPU interface over platform and firmware (ACPI) ones like the kernel docs say.
+// intel_backlight's device link points straight at the connector (card1-eDP-1), the others
+// are credited to the built in panel, which xrandr/the compositor call eDP-*, LVDS-* or DSI-*.
+// `class` stands in for /sys/class/backlight.
+fn backlight(class: &std::path::Path) -> Option<(String, u8)> {
+    let entries = fs::read_dir(class).ok()?;
+    let rank = |kind: &str| match kind {
+        "raw" => 0,
+        "platform" => 1,
+        _ => 2,
+    };
+
+    let path = entries
+        .filter_map(|entry| entry.ok())
+        .map(|entry| entry.path())
+        .min_by_key(|path| {
+            rank(&read_first_line(&format!("{}/type", path.display())).unwrap_or_default())
+        })?;
+    let brightness: u64 = read_first_line(&format!("{}/brightness", path.display()))?.parse().ok()?;
+    let max: u64 = read_first_line(&format!("{}/max_brightness", path.display()))?.parse().ok()?;
+    if max == 0 {
+        return None;
+    }
+    let percent = (brightness * 100 / max).min(100) as u8;
+
+    let device = fs::read_link(path.join("device")).ok();
+    let connector = device
+        .as_ref()
+        .and_then(|device| device.file_name()?.to_str()?.split_once('-'))
+        .filter(|(card, _)| card.starts_with("card"))
+        .map(|(_, connector)| connector.to_string())
+        .unwrap_or_else(|| "eDP".to_string());
+    Some((connector, percent))
+}
+
+// Does an output name from xrandr/the compositor match a DRM connector or a panel prefix?
+// X11 drivers sometimes drop the dash ("eDP1" vs "eDP-1").
+fn is_panel(output_name: &str, connector: &str) -> bool {
+    let output = output_name.replace('-', "");
+    let connector = connector.replace('-', "");
+    if connector == "eDP" {
+        return ["eDP", "LVDS", "DSI"].iter().any(|panel| output.starts_with(panel));
+    }
+    output == connector
+}
+
+// Connected DRM connectors that are VRR capable, from /sys/class/drm/card<N>-<connector>.
+// Connectors on a second GPU are returned the way xrandr names them, with the provider
+// number tacked on ("DP-1" on card1 -> "DP-1-1"), as well as bare for Wayland.
+// `class` stands in for /sys/class/drm.
+fn drm_vrr_outputs(class: &std::path::Path) -> Vec<String> {
+    let Ok(entries) = fs::read_dir(class) else {
+        return Vec::new();
+    };
+
+    // (card number, connector name)
+    let mut connectors: Vec<(u32, String)> = entries
+        .filter_map(|entry| entry.ok())
+        .filter_map(|entry| {
+            let name = entry.file_name().to_string_lossy().into_owned();
+            let (card, connector) = name.strip_prefix("card")?.split_once('-')?;
+            let card = card.parse::<u32>().ok()?;
+            let path = entry.path();
+            let connected = read_first_line(&format!("{}/status", path.display()))?;
+            let capable = read_first_line(&format!("{}/vrr_capable", path.display()))?;
+            (connected == "connected" && capable == "1").then(|| (card, connector.to_string()))
+        })
+        .collect();
+    connectors.sort();
+
+    // The GPU driving the desktop usually has the lowest card number
+    let first_card = connectors.first().map(|(card, _)| *card);
+    let mut names = Vec::with_capacity(connectors.len());
+    for (card, connector) in connectors {
+        if Some(card) != first_card {
+            names.push(format!("{}-{}", connector, card.saturating_sub(first_card.unwrap_or(0))));
+        }
+        names.push(connector);
+    }
+    names
+}
+
+// Monitor model names from EDID as (connector, name) for the connected DRM outputs that have
+// one, "DP-3" -> "DELL U2723QE"
+fn drm_monitor_names() -> Vec<(String, String)> {
+    let Ok(entries) = fs::read_dir("/sys/class/drm") else {
+        return Vec::new();
+    };
+    entries
+        .filter_map(|entry| entry.ok())
+        .filter_map(|entry| {
+            let name = entry.file_name().to_string_lossy().into_owned();
+            let (card, connector) = name.strip_prefix("card")?.split_once('-')?;
+            card.parse::<u32>().ok()?;
+            let path = entry.path();
+            if read_first_line(&format!("{}/status", path.display()))? != "connected" {
+                return None;
+            }
+            let edid = edid::parse(&fs::read(path.join("edid")).ok()?)?;
+            Some((connector.to_string(), edid.name?))
+        })
+        .collect()
+}
+
+// Does an xrandr output name belong to a DRM connector? X11 drivers drop the dash ("HDMI1")
+// and call HDMI-A just HDMI ("HDMI-1" for DRM's "HDMI-A-1").
+fn same_connector(output_name: &str, connector: &str) -> bool {
+    let normalize = |name: &str| name.replacen("HDMI-A", "HDMI", 1).replace('-', "");
+    normalize(output_name) == normalize(connector)
+}
+
+// Is the output on an xrandr "connected" line rotated left or right?
+// The rotation is the token right after the geometry, e.g.
+// "DP-1 connected primary 1440x2560+0+0 left (normal left inverted right x axis y axis) ..."
+// The part in parentheses lists available rotations, and the output name can be anything,
+// so only that one token counts. Inverted is still landscape.
+fn xrandr_is_sideways(line: &str) -> bool {
+    let mut tokens = line.split_whitespace().skip(2);
+    let is_geometry =
+        |token: &&str| token.starts_with(|c: char| c.is_ascii_digit()) && token.contains('+');
+    let rotation = tokens.find(is_geometry).and_then(|_| tokens.next());
+    matches!(rotation, Some("left" | "right"))
+}
+
+// The monitors in `xrandr --current` output as (is_primary, output name, display string),
+// primary first
+fn xrandr_screens(xrandr: &str, details: &OutputDetails) -> Vec<(bool, String, String)> {
+    // Store (is_primary, output_name, display_string)
+    let mut screens: Vec<(bool, String, String)> = Vec::new();
+    let mut current_output = String::new();
+    let mut current_is_primary = false;
+    let mut current_is_portrait = false;
+    let mut current_detail = String::new();
+
+    for line in xrandr.lines() {
+        // Check for output connection line (e.g., "DP-3 connected primary 2560x1440...")
+        if line.contains(" connected") {
+            current_is_primary = line.contains(" primary ");
+            let output_name = line.split_whitespace().next().unwrap_or_default();
+            current_output = output_name.to_string();
+            current_detail.clear();
+            if details.vrr.iter().any(|name| name == output_name) {
+                current_detail.push_str(" VRR");
+            }
+            if details.hdr.iter().any(|name| name == output_name) {
+                current_detail.push_str(" HDR");
+            }
+            if let Some((connector, percent)) = &details.backlight
+                && is_panel(output_name, connector)
+            {
+                current_detail.push_str(&format!(" 󰃠 {}%", percent));
+            }
+            current_is_portrait = xrandr_is_sideways(line);
+        }
+        // Look for lines indicating the active mode (contains *)
+        else if line.contains('*') {
+            let parts: Vec<&str> = line.split_whitespace().collect();
+            if parts.len() >= 2 {
+                // Modes are listed unrotated, a monitor turned on its side is taller than wide
+                let res = match parts[0].split_once('x') {
+                    Some((width, height)) if current_is_portrait => {
+                        format!("{}x{}", height, width)
+                    }
+                    _ => parts[0].to_string(),
+                };
+                // Rate often looks like "60.00*+" or "144.00*" or "59.95*", the starred one is
+                // in use and isn't always the first. Filter out non-numeric chars except dot
+                let rate_str =
+                    parts[1..].iter().find(|part| part.contains('*')).unwrap_or(&parts[1]);
+                let rate: String = rate_str
+                    .chars()
+                    .filter(|c| c.is_ascii_digit() || *c == '.')
+                    .collect();
+
+                // Orientation icon: 󰆠 for landscape, 󰆡 for portrait
+                let icon = if current_is_portrait { "󰆡" } else { "󰏠" };
+
+                // Parse as float for rounding
+                let mut display_str = if let Ok(rate_f) = rate.parse::<f64>() {
+                    format!("{} {} @ {}Hz", icon, res, rate_f.round() as u64)
+                } else {
+                    format!("{} {} @ {}Hz", icon, res, rate)
+                };
+                display_str.push_str(&current_detail);
+                screens.push((current_is_primary, current_output.clone(), display_str));
             }
         }
     }
 
-    "unknown".to_string()
+    // Sort so primary monitor comes first
+    screens.sort_by_key(|screen| std::cmp::Reverse(screen.0));
+    screens
 }
 
 // Get screen resolution and refresh rate using xrandr
-// Returns a Vec of (key, value) pairs for each monitor, primary first
-pub fn screen() -> Vec<(String, String)> {
+// Returns a Vec of (key, value) pairs for each monitor, primary first.
+// With detail on, monitors running with VRR or HDR get a "VRR"/"HDR" tag,
+// and the laptop panel its brightness. With more than one monitor each is named by its EDID
+// model, or its connector when the EDID doesn't say.
+pub fn screen(detail: bool) -> Vec<(String, String)> {
     let output = Command::new("xrandr")
         .arg("--current")
         .output()
         .ok();
 
     if let Some(out) = output {
-        let stdout = String::from_utf8_lossy(&out.stdout);
-        // Store (is_primary, display_string)
-        let mut screens: Vec<(bool, String)> = Vec::new();
-        let mut current_is_primary = false;
-        let mut current_is_portrait = false;
-
-        for line in stdout.lines() {
-            // Check for output connection line (e.g., "DP-3 connected primary 2560x1440...")
-            if line.contains(" connected") {
-                current_is_primary = line.contains(" primary ");
-                // Portrait mode indicated by "left" or "right" rotation before the parentheses
-                // The part in parentheses lists available rotations, not current rotation
-                let before_paren = line.split('(').next().unwrap_or(line);
-                current_is_portrait =
-                    before_paren.contains(" left") || before_paren.contains(" right");
-            }
-            // Look for lines indicating the active mode (contains *)
-            else if line.contains('*') {
-                let parts: Vec<&str> = line.split_whitespace().collect();
-                if parts.len() >= 2 {
-                    let res = parts[0];
-                    // Rate often looks like "60.00*+" or "144.00*" or "59.95*"
-                    // Filter out non-numeric chars except dot
-                    let rate_str = parts[1];
-                    let rate: String = rate_str
-                        .chars()
-                        .filter(|c| c.is_digit(10) || *c == '.')
-                        .collect();
-
-                    // Orientation icon: 󰆠 for landscape, 󰆡 for portrait
-                    let icon = if current_is_portrait { "󰆡" } else { "󰏠" };
-
-                    // Parse as float for rounding
-                    let display_str = if let Ok(rate_f) = rate.parse::<f64>() {
-                        format!("{} {} @ {}Hz", icon, res, rate_f.round() as u64)
-                    } else {
-                        format!("{} {} @ {}Hz", icon, res, rate)
-                    };
-                    screens.push((current_is_primary, display_str));
-                }
-            }
-        }
-
-        // Sort so primary monitor comes first
-        screens.sort_by(|a, b| b.0.cmp(&a.0));
+        let details = if detail { output_details() } else { OutputDetails::default() };
+        let screens = xrandr_screens(&String::from_utf8_lossy(&out.stdout), &details);
 
         if !screens.is_empty() {
             if screens.len() == 1 {
-                return vec![("Display".to_string(), screens[0].1.clone())];
+                return vec![("Display".to_string(), screens[0].2.clone())];
             }
-            // Multiple monitors: header line + tree-style entries
+            // Multiple monitors: header line + tree-style entries, named so they can be told apart
+            let monitor_names = drm_monitor_names();
             let mut result = vec![("Displays".to_string(), String::new())];
             let last_idx = screens.len() - 1;
-            for (i, (_, s)) in screens.iter().enumerate() {
+            for (i, (_, output_name, s)) in screens.iter().enumerate() {
+                let name = monitor_names
+                    .iter()
+                    .find(|(connector, _)| same_connector(output_name, connector))
+                    .map_or(output_name.as_str(), |(_, name)| name.as_str());
+                let s = format!("{}: {}", name, s);
                 if i == last_idx {
-                    result.push(("╰─".to_string(), s.clone()));
+                    result.push(("╰─".to_string(), s));
                 } else {
-                    result.push(("├─".to_string(), s.clone()));
+                    result.push(("├─".to_string(), s));
                 }
             }
             return result;
@@ -590,3 +2310,1171 @@ pub fn screen() -> Vec<(String, String)> {
 
     vec![]
 }
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::renderer::strip_escapes;
+    use crate::testutil::TempDir;
+
+    #[test]
+    fn cpu_model_names() {
+        // As /proc/cpuinfo has them, double spaces and all
+        let models = [
+            ("12th Gen Intel(R) Core(TM) i7-12700K", "12th Gen Intel Core i7-12700K"),
+            ("Intel(R) Core(TM) i5-4590 CPU @ 3.30GHz", "Intel Core i5-4590"),
+            ("Intel(R) Xeon(R) Gold 6230R CPU @ 2.10GHz", "Intel Xeon Gold 6230R"),
+            ("Intel(R) Xeon(R) CPU E5-2680 v4 @ 2.40GHz", "Intel Xeon E5-2680 v4"),
+            ("Intel(R) Celeron(R) CPU  N3050  @ 1.60GHz", "Intel Celeron N3050"),
+            ("Intel(R) Pentium(R) 4 CPU 3.00GHz", "Intel Pentium 4 3.00GHz"),
+            ("Genuine Intel(R) CPU 0000 @ 2.10GHz", "Intel 0000"),
+            ("AMD EPYC 7763 64-Core Processor", "AMD EPYC 7763"),
+            ("AMD EPYC 9654 96-Core Processor", "AMD EPYC 9654"),
+            ("AMD Ryzen Threadripper PRO 7995WX 96-Cores", "AMD Ryzen Threadripper PRO 7995WX"),
+            ("AMD Ryzen 9 7950X3D 16-Core Processor", "AMD Ryzen 9 7950X3D"),
+            ("AMD Ryzen 7 7840U w/ Radeon  780M Graphics", "AMD Ryzen 7 7840U"),
+            ("AMD Ryzen 5 5600G with Radeon Graphics", "AMD Ryzen 5 5600G"),
+            ("AMD Athlon(tm) II X2 250 Processor", "AMD Athlon II X2 250"),
+            // Raspberry Pi kernels on 32-bit ARM
+            ("ARMv7 Processor rev 4 (v7l)", "ARMv7 rev 4 (v7l)"),
+            ("QEMU Virtual CPU version 2.5+", "QEMU Virtual version 2.5+"),
+        ];
+        for (raw, clean) in models {
+            assert_eq!(normalize_cpu_model(&format!(" {}", raw)), clean);
+        }
+    }
+
+    // A fake /sys/devices/system/cpu: cpu0's cpufreq files and a scaling_cur_freq per policy
+    #[test]
+    fn cpu_clock_suffixes() {
+        let suffix = |cpu: &TempDir, clock| cpu_clock_suffix_in(cpu.path(), clock);
+
+        let cpu = TempDir::new();
+        cpu.write("cpu0/cpufreq/cpuinfo_max_freq", "5100000\n");
+        cpu.write("cpu0/cpufreq/base_frequency", "3800000\n");
+        cpu.write("cpu0/cpufreq/scaling_available_frequencies", "3401000 3400000 2200000 \n");
+        cpu.write("cpufreq/policy0/scaling_cur_freq", "2000000\n");
+        cpu.write("cpufreq/policy4/scaling_cur_freq", "3100000\n");
+        // Not policies, or not a number, so not in the average
+        cpu.write("cpufreq/boost", "1\n");
+        cpu.write("cpufreq/policy8/scaling_cur_freq", "<unknown>\n");
+        assert_eq!(suffix(&cpu, CpuClock::Boost), " @ 5.10GHz");
+        // intel_pstate's base_frequency wins over the list
+        assert_eq!(suffix(&cpu, CpuClock::Base), " @ 3.80GHz");
+        assert_eq!(suffix(&cpu, CpuClock::Current), " @ 2.55GHz");
+        assert_eq!(suffix(&cpu, CpuClock::None), "");
+
+        // acpi-cpufreq: the top entry is only the turbo marker, one step above base
+        fs::remove_file(cpu.join("cpu0/cpufreq/base_frequency")).unwrap();
+        assert_eq!(suffix(&cpu, CpuClock::Base), " @ 3.40GHz");
+        // Without the marker the top entry is the base clock
+        cpu.write("cpu0/cpufreq/scaling_available_frequencies", "2800000 1400000 2200000\n");
+        assert_eq!(suffix(&cpu, CpuClock::Base), " @ 2.80GHz");
+        cpu.write("cpu0/cpufreq/scaling_available_frequencies", "\n");
+        assert_eq!(suffix(&cpu, CpuClock::Base), "");
+
+        // VMs report 0 or nonsense, neither gets a suffix
+        cpu.write("cpu0/cpufreq/cpuinfo_max_freq", "0\n");
+        assert_eq!(suffix(&cpu, CpuClock::Boost), "");
+        cpu.write("cpu0/cpufreq/cpuinfo_max_freq", "8000001\n");
+        assert_eq!(suffix(&cpu, CpuClock::Boost), "");
+        cpu.write("cpu0/cpufreq/cpuinfo_max_freq", "8000000\n");
+        assert_eq!(suffix(&cpu, CpuClock::Boost), " @ 8.00GHz");
+
+        // No cpufreq at all
+        let empty = TempDir::new();
+        for clock in [CpuClock::Boost, CpuClock::Base, CpuClock::Current, CpuClock::None] {
+            assert_eq!(suffix(&empty, clock), "");
+        }
+    }
+
+    const DISKSTATS: &str = "\
+ 259       0 nvme0n1 48021 1633 6291690 9120 90210 44123 8388608 40310 0 51234 51820 0 0 0 0
+ 259       1 nvme0n1p1 210 0 10240 31 2 0 16 0 0 40 31 0 0 0 0
+ 253       0 dm-0 47500 0 6200000 9800 130000 0 8300000 61000 0 52000 70800 0 0 0 0
+   7       0 loop0 60 0 2328 8 0 0 0 0 0 20 8 0 0 0 0
+   8       0 sda 1000 0 2000 100 500 0 3000 200 0 300 300
+";
+
+    #[test]
+    fn diskstats_counts_physical_disks_only() {
+        let sys = TempDir::new();
+        sys.write("block/nvme0n1/device/model", "Samsung SSD 990 PRO\n");
+        sys.write("block/sda/device/model", "ST2000DM008\n");
+        sys.write("block/dm-0/dm/name", "root\n");
+        sys.write("block/loop0/loop/backing_file", "/var/lib/snapd/snaps/core.snap\n");
+        let sectors = 6_291_690 + 8_388_608 + 2000 + 3000;
+        assert_eq!(disk_bytes(DISKSTATS, &sys.join("block")), Some(sectors * 512));
+        // Nothing physical, nothing to report
+        assert_eq!(disk_bytes(DISKSTATS, &sys.join("missing")), None);
+        assert_eq!(disk_bytes("", &sys.join("block")), None);
+    }
+
+    // Column spacing squeezed, the kernel pads them wider
+    const NET_DEV: &str = "\
+Inter-|   Receive                                  |  Transmit
+ face |bytes packets errs drop fifo frame compressed multicast|bytes packets errs drop ...
+    lo: 9811204 61200 0 0 0 0 0 0 9811204 61200 0 0 0 0 0 0
+wlp2s0: 1843920384 1520000 0 12 0 0 0 3100 98123456 640000 0 0 0 0 0 0
+";
+
+    #[test]
+    fn net_dev_counters() {
+        assert_eq!(net_bytes(NET_DEV, "wlp2s0"), Some((1_843_920_384, 98_123_456)));
+        assert_eq!(net_bytes(NET_DEV, "lo"), Some((9_811_204, 9_811_204)));
+        assert_eq!(net_bytes(NET_DEV, "eth0"), None);
+        assert_eq!(net_bytes("eth0: 100 2 0\n", "eth0"), None);
+    }
+
+    #[test]
+    fn default_route_interface() {
+        let route = "Iface\tDestination\tGateway \tFlags\tRefCnt\tUse\tMetric\tMask\n\
+                     wlp2s0\t0000A8C0\t00000000\t0001\t0\t0\t600\t00FFFFFF\n\
+                     wlp2s0\t00000000\t0100A8C0\t0003\t0\t0\t600\t00000000\n";
+        assert_eq!(route_interface(route).as_deref(), Some("wlp2s0"));
+        assert_eq!(route_interface(&route[..route.rfind("wlp2s0").unwrap()]), None);
+    }
+
+    #[test]
+    fn counters_wrap_around() {
+        assert_eq!(counter_delta(1000, 4000), 3000);
+        // A 32-bit counter rolling over between the samples
+        assert_eq!(counter_delta(u32::MAX as u64 - 99, 400), 500);
+        // A 64-bit counter going down was reset, no delta to show
+        assert_eq!(counter_delta(10_000_000_000, 5), 0);
+        assert_eq!(counter_delta(7, 7), 0);
+    }
+
+    #[test]
+    fn rates() {
+        assert_eq!(format_rate(0.0), "0.0MB/s");
+        assert_eq!(format_rate(1_234_567.0), "1.2MB/s");
+        assert_eq!(format_rate(12_345_678.0), "12MB/s");
+    }
+
+    // A /sys with the given chassis type and power_supply entries, (name, [(file, content)])
+    fn power_tree(chassis_type: u32, supplies: &[(&str, &[(&str, &str)])]) -> TempDir {
+        let root = TempDir::new();
+        root.write("sys/class/dmi/id/chassis_type", format!("{}\n", chassis_type));
+        for (name, files) in supplies {
+            for (file, content) in *files {
+                root.write(&format!("sys/class/power_supply/{}/{}", name, file), content);
+            }
+        }
+        root
+    }
+
+    const AC: (&str, &[(&str, &str)]) = ("AC", &[("type", "Mains\n"), ("online", "1\n")]);
+
+    fn battery_text(root: &TempDir) -> String {
+        strip_escapes(&battery_in(root.path(), BatteryThresholds::default(), false).0)
+    }
+
+    fn ups_text(root: &TempDir) -> String {
+        strip_escapes(&ups_in(root.path(), BatteryThresholds::default()))
+    }
+
+    #[test]
+    fn laptop_battery_tree() {
+        let battery: &[(&str, &str)] =
+            &[("type", "Battery\n"), ("capacity", "64\n"), ("status", "Discharging\n")];
+        let root = power_tree(10, &[AC, ("BAT0", battery)]);
+        assert!(battery_text(&root).ends_with(" 64% 󰂍"), "{}", battery_text(&root));
+        assert_eq!(ups_text(&root), "unknown");
+
+        root.write("sys/class/power_supply/BAT0/status", "Charging\n");
+        assert!(battery_text(&root).ends_with(" 64% 󰂐"));
+        // Below critical and not charging: the alert icon
+        root.write("sys/class/power_supply/BAT0/status", "Not charging\n");
+        root.write("sys/class/power_supply/BAT0/capacity", "7\n");
+        assert!(battery_text(&root).ends_with(" 7% 󰂃"));
+    }
+
+    #[test]
+    fn desktop_with_a_ups() {
+        let ups: &[(&str, &str)] =
+            &[("type", "UPS\n"), ("capacity", "100\n"), ("status", "Full\n")];
+        let root = power_tree(3, &[AC, ("ups", ups)]);
+        assert!(ups_text(&root).ends_with(" 100% Online"), "{}", ups_text(&root));
+        // Desktops don't get a battery line, a UPS isn't a BAT entry anyway
+        assert_eq!(battery_text(&root), "unknown");
+
+        root.write("sys/class/power_supply/ups/status", "Discharging\n");
+        root.write("sys/class/power_supply/ups/capacity", "85\n");
+        assert!(ups_text(&root).ends_with(" 85% On battery"));
+    }
+
+    #[test]
+    fn ac_only_trees() {
+        // A desktop on mains, and a laptop with its battery pulled
+        for chassis_type in [3, 9] {
+            let root = power_tree(chassis_type, &[AC]);
+            assert_eq!(battery_text(&root), "unknown");
+            assert_eq!(ups_text(&root), "unknown");
+        }
+        // No power_supply class at all
+        let root = TempDir::new();
+        assert_eq!(battery_text(&root), "unknown");
+        assert_eq!(ups_text(&root), "unknown");
+    }
+
+    #[test]
+    fn meminfo_with_mem_available() {
+        let proc = TempDir::new();
+        let meminfo = proc.write(
+            "meminfo",
+            "MemTotal:       32000000 kB\nMemFree:         1000000 kB\n\
+             MemAvailable:   20000000 kB\nBuffers:          500000 kB\n",
+        );
+        assert_eq!(read_meminfo_from(&meminfo), Some((32_000_000, 20_000_000)));
+    }
+
+    #[test]
+    fn meminfo_without_mem_available() {
+        let proc = TempDir::new();
+        // Pre-3.14 kernels and hardened setups, MemFree + Buffers + Cached
+        let meminfo = proc.write(
+            "meminfo",
+            "MemTotal:       32000000 kB\nMemFree:         1000000 kB\n\
+             Buffers:          500000 kB\nCached:          4000000 kB\nSwapCached:  0 kB\n",
+        );
+        assert_eq!(read_meminfo_from(&meminfo), Some((32_000_000, 5_500_000)));
+        // Without MemFree there's nothing to go on
+        let meminfo = proc.write("meminfo", "MemTotal:       32000000 kB\nBuffers: 500000 kB\n");
+        assert_eq!(read_meminfo_from(&meminfo), None);
+    }
+
+    #[test]
+    fn meminfo_unreadable_or_without_total() {
+        let proc = TempDir::new();
+        assert_eq!(read_meminfo_from(&proc.join("meminfo")), None);
+        let meminfo = proc.write("meminfo", "MemFree: 1000000 kB\nMemAvailable: 2000000 kB\n");
+        assert_eq!(read_meminfo_from(&meminfo), None);
+        let meminfo = proc.write("meminfo", "MemTotal: 0 kB\nMemAvailable: 0 kB\n");
+        assert_eq!(read_meminfo_from(&meminfo), None);
+    }
+
+    // A /sys/class/block with nothing stacked on anything
+    fn no_sys() -> std::path::PathBuf {
+        std::path::PathBuf::from("/nonexistent/sys/class/block")
+    }
+
+    const MOUNTS: &[u8] = b"/dev/nvme0n1p2 / ext4 rw,relatime 0 0\n\
+        proc /proc proc rw,nosuid 0 0\n\
+        /dev/nvme0n1p1 /boot vfat rw 0 0\n\
+        /dev/loop3 /snap/core/1 squashfs ro 0 0\n";
+
+    #[test]
+    fn storage_sums_the_measured_mounts() {
+        let stats = |mount: &str| match mount {
+            "/" => Some((500_000_000_000, 200_000_000_000, 1)),
+            "/boot" => Some((1_000_000_000, 100_000_000, 2)),
+            _ => panic!("{} isn't a disk", mount),
+        };
+        assert_eq!(
+            storage_totals_in(MOUNTS, &no_sys(), stats),
+            Some((501_000_000_000, 200_100_000_000))
+        );
+    }
+
+    #[test]
+    fn images_and_overlays_arent_storage() {
+        let mounts = b"/dev/sda2 / btrfs rw 0 0\n\
+            overlay /var/lib/docker/overlay2/abc/merged overlay rw,lowerdir=/a 0 0\n\
+            /dev/sr0 /run/media/cdrom iso9660 ro 0 0\n\
+            /dev/mapper/live-base /run/rootfsbase squashfs ro 0 0\n\
+            /dev/vdb /usr/share/fonts erofs ro 0 0\n\
+            /dev/loop7 /snap/firefox/1 squashfs ro 0 0\n\
+            tmpfs /tmp tmpfs rw 0 0\n";
+        let stats = |mount: &str| match mount {
+            "/" => Some((250_000_000_000, 100_000_000_000, 7)),
+            _ => panic!("{} isn't a disk", mount),
+        };
+        assert_eq!(
+            storage_totals_in(mounts, &no_sys(), stats),
+            Some((250_000_000_000, 100_000_000_000))
+        );
+    }
+
+    #[test]
+    fn storage_sizes_from_mb_to_pb() {
+        let cases = [
+            (0, "0MB"),
+            (512_000_000, "512MB"),
+            (999_400_000, "999MB"),
+            (999_999_999, "1GB"),
+            (1_000_000_000, "1GB"),
+            (271_400_000_000, "271GB"),
+            (1_000_000_000_000, "1TB"),
+            (1_500_000_000_000, "1.50TB"),
+            (3_996_000_000_000, "4TB"),
+            (100_000_000_000_000, "100TB"),
+            (999_999_000_000_000, "1PB"),
+            (2_000_000_000_000_000, "2PB"),
+            (1_250_000_000_000_000, "1.25PB"),
+        ];
+        for (bytes, shown) in cases {
+            assert_eq!(format_storage_size(bytes), shown, "{} bytes", bytes);
+        }
+        // Used and total pick their units on their own, a nearly full 100TB NAS isn't 99000GB
+        assert_eq!(format_storage_size(99_000_000_000_000), "99TB");
+    }
+
+    #[test]
+    fn usage_stays_in_range() {
+        assert_eq!(usage_percent(50, 200), 25.0);
+        assert_eq!(usage_percent(0, 0), 0.0);
+        // overlayfs can report more used than there is
+        assert_eq!(usage_percent(300, 200), 100.0);
+    }
+
+    // statvfs for the stacked setups below: (total, used, fsid) by mount point
+    fn stacked_stats(mount: &str) -> Option<(u64, u64, libc::c_ulong)> {
+        match mount {
+            "/" => Some((100_000_000_000, 40_000_000_000, 1)),
+            // md RAID1 of two 1TB drives
+            "/srv" => Some((1_000_000_000_000, 300_000_000_000, 2)),
+            // Multipath LUN, mounted twice through different nodes
+            "/data" | "/mnt/lun" => Some((2_000_000_000_000, 500_000_000_000, 3)),
+            // btrfs over two devices, reached through the second one as well
+            "/pool" | "/pool-alt" => Some((4_000_000_000_000, 1_000_000_000_000, 4)),
+            // Subvolumes of that btrfs on the first device, each with its own fsid
+            "/pool/snapshots" => Some((4_000_000_000_000, 1_000_000_000_000, 5)),
+            _ => panic!("{} shouldn't be measured", mount),
+        }
+    }
+
+    #[test]
+    fn stacked_filesystems_count_once() {
+        let sys = TempDir::new();
+        // md0 on nvme0n1p1 + nvme1n1p1, dm-0 (multipath) on sdc + sdd, bcache0 on sde
+        for (member, holder) in [
+            ("nvme0n1p1", "md0"),
+            ("nvme1n1p1", "md0"),
+            ("sdc", "dm-0"),
+            ("sdd", "dm-0"),
+            ("sde", "bcache0"),
+        ] {
+            sys.symlink(&format!("{}/holders/{}", member, holder), format!("../../{}", holder));
+        }
+        sys.write("md0/size", "1953259520\n");
+        sys.write("sda2/size", "195311616\n");
+
+        let mounts = b"/dev/sda2 / ext4 rw 0 0\n\
+            /dev/md0 /srv ext4 rw 0 0\n\
+            /dev/nvme1n1p1 /mnt/member ext4 ro 0 0\n\
+            /dev/dm-0 /data xfs rw 0 0\n\
+            /dev/sdc /mnt/path xfs rw 0 0\n\
+            /dev/mapper/mpatha /mnt/lun xfs rw 0 0\n\
+            /dev/sde /mnt/backing ext4 rw 0 0\n\
+            /dev/nvme2n1 /pool btrfs rw,subvol=/@pool 0 0\n\
+            /dev/nvme2n1 /pool/snapshots btrfs rw,subvol=/@snapshots 0 0\n\
+            /dev/nvme3n1 /pool-alt btrfs rw,subvol=/@pool 0 0\n";
+        let (total, used) = storage_totals_in(mounts, sys.path(), stacked_stats).unwrap();
+        // root + md0 + the LUN + the btrfs pool, the members never measured
+        assert_eq!(total, 7_100_000_000_000);
+        assert_eq!(used, 1_840_000_000_000);
+    }
+
+    #[test]
+    fn members_go_by_their_kernel_name() {
+        let sys = TempDir::new();
+        sys.symlink("sdb1/holders/md127", "../../md127");
+        sys.write("sdb2/size", "2048\n");
+        assert!(has_holders(sys.path(), "/dev/sdb1"));
+        assert!(!has_holders(sys.path(), "/dev/sdb2"));
+        assert!(!has_holders(sys.path(), "/dev/sdz9"));
+        // Empty holders/ is just a disk
+        fs::create_dir_all(sys.join("sdc/holders")).unwrap();
+        assert!(!has_holders(sys.path(), "/dev/sdc"));
+    }
+
+    #[test]
+    fn storage_is_unknown_when_statvfs_fails_everywhere() {
+        assert_eq!(storage_totals_in(MOUNTS, &no_sys(), |_| None), None);
+        assert_eq!(storage_totals_in(b"", &no_sys(), |_| Some((1, 1, 1))), None);
+    }
+
+    // `xrandr --current` with a landscape primary, a second monitor rotated left and a
+    // laptop panel upside down
+    const XRANDR: &str = "\
+Screen 0: minimum 320 x 200, current 4000 x 2560, maximum 16384 x 16384
+eDP-1 connected 1920x1080+4000+0 inverted (normal left inverted right x axis y axis) 344mm x 194mm
+   1920x1080     60.01*+  48.00
+   1680x1050     59.95    59.88
+DP-1 connected primary 2560x1440+0+0 (normal left inverted right x axis y axis) 597mm x 336mm
+   2560x1440     59.95 +  143.97*
+   1920x1080     60.00    50.00    59.94
+DP-2 connected 1440x2560+2560+0 left (normal left inverted right x axis y axis) 597mm x 336mm
+   2560x1440     59.95*+
+   1920x1080     60.00
+HDMI-1 disconnected (normal left inverted right x axis y axis)
+";
+
+    #[test]
+    fn xrandr_rotations() {
+        let screens = xrandr_screens(XRANDR, &OutputDetails::default());
+        let shown: Vec<(bool, &str, &str)> = screens
+            .iter()
+            .map(|(primary, name, shown)| (*primary, name.as_str(), shown.as_str()))
+            .collect();
+        assert_eq!(
+            shown,
+            [
+                // Normal, with the active rate in the second column
+                (true, "DP-1", "󰏠 2560x1440 @ 144Hz"),
+                // Inverted is still landscape
+                (false, "eDP-1", "󰏠 1920x1080 @ 60Hz"),
+                // The mode is listed unrotated, shown the way the monitor stands
+                (false, "DP-2", "󰆡 1440x2560 @ 60Hz"),
+            ]
+        );
+    }
+
+    #[test]
+    fn rotation_is_the_token_after_the_geometry() {
+        let line = |rest: &str| format!("{} (normal left inverted right x axis y axis)", rest);
+        assert!(!xrandr_is_sideways(&line("DP-1 connected 2560x1440+0+0")));
+        assert!(!xrandr_is_sideways(&line("DP-1 connected 2560x1440+0+0 inverted")));
+        assert!(xrandr_is_sideways(&line("DP-1 connected 1440x2560+0+0 left")));
+        assert!(xrandr_is_sideways(&line("DP-1 connected primary 1440x2560+0+0 right")));
+        // Names that happen to contain the words don't count
+        assert!(!xrandr_is_sideways(&line("left connected 2560x1440+0+0")));
+        assert!(!xrandr_is_sideways(&line("DP-right connected primary 2560x1440+0+0")));
+        // Connected but switched off, no geometry at all
+        assert!(!xrandr_is_sideways(&line("DP-3 connected")));
+    }
+
+    // /sys/block with a LUKS volume and LVM on top of it, LVM straight on a disk, and two
+    // device mapper devices that claim each other (broken sysfs shouldn't hang the walk)
+    fn dm_tree() -> TempDir {
+        let sys = TempDir::new();
+        sys.write("nvme0n1/size", "1000215216\n");
+        sys.write("dm-0/dm/uuid", "CRYPT-LUKS2-3f1c0a9e5b7d4e2f-luks-root\n");
+        sys.symlink("dm-0/slaves/nvme0n1p2", "../../nvme0n1/nvme0n1p2");
+        sys.write("dm-1/dm/uuid", "LVM-Wq3pXcUe0Rj6rAaMhKzN4bD2T9sYvG1d\n");
+        sys.symlink("dm-1/slaves/dm-0", "../../dm-0");
+        sys.write("dm-2/dm/uuid", "LVM-HfZ8kLm2Qp0rS7tU1vW3xY5zA9bC4dE6\n");
+        sys.symlink("dm-2/slaves/sda2", "../../sda/sda2");
+        sys.write("dm-3/dm/uuid", "CRYPT-PLAIN-swap\n");
+        sys.symlink("dm-3/slaves/dm-4", "../../dm-4");
+        sys.symlink("dm-4/slaves/dm-3", "../../dm-3");
+        sys
+    }
+
+    #[test]
+    fn dm_layers_down_to_the_partition() {
+        let sys = dm_tree();
+        let mut layers = block_device_layers(sys.path(), "dm-1");
+        layers.sort();
+        assert_eq!(layers, ["dm-0", "dm-1", "nvme0n1p2"]);
+        assert_eq!(block_device_layers(sys.path(), "nvme0n1p2"), ["nvme0n1p2"]);
+        let mut looped = block_device_layers(sys.path(), "dm-3");
+        looped.sort();
+        assert_eq!(looped, ["dm-3", "dm-4"]);
+    }
+
+    #[test]
+    fn luks_found_through_lvm() {
+        let sys = dm_tree();
+        // Root on the LUKS volume itself, and on an LV inside it
+        assert!(is_on_luks(sys.path(), "dm-0"));
+        assert!(is_on_luks(sys.path(), "dm-1"));
+        // LVM without encryption, plain dm-crypt (not LUKS) and a bare partition
+        assert!(!is_on_luks(sys.path(), "dm-2"));
+        assert!(!is_on_luks(sys.path(), "dm-3"));
+        assert!(!is_on_luks(sys.path(), "nvme0n1p2"));
+        assert!(!is_on_luks(sys.path(), "sdz"));
+    }
+
+    #[test]
+    fn root_source_is_the_last_mount_on_slash() {
+        let mounts = "\
+/dev/nvme0n1p2 / ext4 rw,relatime 0 0
+proc /proc proc rw,nosuid,nodev,noexec,relatime 0 0
+/dev/mapper/vg-root / ext4 rw,relatime 0 0
+/dev/nvme0n1p1 /boot vfat rw,relatime 0 0
+";
+        assert_eq!(root_mount_source(mounts), Some("/dev/mapper/vg-root"));
+        assert_eq!(root_mount_source("proc /proc proc rw 0 0\n"), None);
+        assert_eq!(root_mount_source(""), None);
+    }
+
+    // `hyprctl monitors -j` on a newer Hyprland, trimmed to the fields that matter
+    const HYPRCTL: &str = r#"[{
+    "id": 0,
+    "name": "DP-1",
+    "description": "Dell Inc. DELL U2723QE",
+    "refreshRate": 59.99600,
+    "vrr": false,
+    "currentFormat": "XRGB2101010",
+    "colorManagementPreset": "hdr"
+},{
+    "id": 1,
+    "name": "HDMI-A-1",
+    "refreshRate": 143.98100,
+    "vrr": true,
+    "currentFormat": "XRGB8888",
+    "colorManagementPreset": "srgb"
+},{
+    "id": 2,
+    "name": "eDP-1",
+    "vrr": false,
+    "hdr": true
+}]"#;
+
+    const SWAYMSG: &str = r#"[
+  {"name": "DP-2", "make": "LG", "adaptive_sync_status": "enabled", "active": true},
+  {"name": "eDP-1", "make": "BOE", "adaptive_sync_status": "disabled", "active": true}
+]"#;
+
+    #[test]
+    fn vrr_and_hdr_from_the_compositor() {
+        let details = compositor_details(HYPRCTL, Some(("eDP-1".to_string(), 40)));
+        assert_eq!(details.vrr, ["HDMI-A-1"]);
+        assert_eq!(details.hdr, ["DP-1", "eDP-1"]);
+        assert_eq!(details.backlight, Some(("eDP-1".to_string(), 40)));
+
+        let details = compositor_details(SWAYMSG, None);
+        assert_eq!(details.vrr, ["DP-2"]);
+        assert!(details.hdr.is_empty());
+
+        let details = compositor_details("", None);
+        assert!(details.vrr.is_empty() && details.hdr.is_empty());
+    }
+
+    #[test]
+    fn hdr_from_kscreen_doctor() {
+        let output = "\x1b[01;32mOutput: \x1b[0;0m1 DP-1 enabled connected priority 1 DisplayPort\n\
+            \tModes: 0:3840x2160@60*!\n\
+            \tHDR: \x1b[01;32menabled\x1b[0;0m\n\
+            \tWide Color Gamut: enabled\n\
+            \x1b[01;32mOutput: \x1b[0;0m2 eDP-1 enabled connected priority 2 Panel\n\
+            \tHDR: \x1b[01;31mdisabled\x1b[0;0m\n\
+            \x1b[01;32mOutput: \x1b[0;0m3 HDMI-A-1 disabled disconnected priority 0 HDMI\n\
+            \tHDR: incapable\n";
+        assert_eq!(kscreen_hdr_in(output), ["DP-1"]);
+        assert!(kscreen_hdr_in("").is_empty());
+    }
+
+    #[test]
+    fn backlight_prefers_the_gpu_interface() {
+        let class = TempDir::new();
+        class.write("acpi_video0/type", "firmware\n");
+        class.write("acpi_video0/brightness", "5\n");
+        class.write("acpi_video0/max_brightness", "10\n");
+        class.write("intel_backlight/type", "raw\n");
+        class.write("intel_backlight/brightness", "19200\n");
+        class.write("intel_backlight/max_brightness", "96000\n");
+        class.symlink("intel_backlight/device", "../../card1-eDP-1");
+        assert_eq!(backlight(class.path()), Some(("eDP-1".to_string(), 20)));
+
+        // amdgpu's device link is the PCI device, credited to whatever the panel is called
+        let class = TempDir::new();
+        class.write("amdgpu_bl1/type", "raw\n");
+        class.write("amdgpu_bl1/brightness", "255\n");
+        class.write("amdgpu_bl1/max_brightness", "255\n");
+        class.symlink("amdgpu_bl1/device", "../../../0000:04:00.0");
+        assert_eq!(backlight(class.path()), Some(("eDP".to_string(), 100)));
+    }
+
+    #[test]
+    fn no_backlight_no_percent() {
+        let class = TempDir::new();
+        assert_eq!(backlight(class.path()), None);
+        assert_eq!(backlight(&class.join("missing")), None);
+        // A driver that hasn't set its range yet
+        class.write("nv_backlight/type", "raw\n");
+        class.write("nv_backlight/brightness", "0\n");
+        class.write("nv_backlight/max_brightness", "0\n");
+        assert_eq!(backlight(class.path()), None);
+    }
+
+    #[test]
+    fn backlight_goes_with_the_panel() {
+        assert!(is_panel("eDP-1", "eDP-1"));
+        assert!(is_panel("eDP1", "eDP-1"));
+        assert!(!is_panel("DP-1", "eDP-1"));
+        // No connector known, any built in panel name will do
+        assert!(is_panel("LVDS-1", "eDP"));
+        assert!(is_panel("DSI-1", "eDP"));
+        assert!(!is_panel("HDMI-A-1", "eDP"));
+    }
+
+    #[test]
+    fn vrr_capable_drm_connectors() {
+        let drm = TempDir::new();
+        for (entry, status, capable) in [
+            ("card0-DP-1", "connected", "1"),
+            ("card0-DP-2", "connected", "0"),
+            ("card0-HDMI-A-1", "disconnected", "1"),
+            ("card1-DP-3", "connected", "1"),
+        ] {
+            drm.write(&format!("{}/status", entry), format!("{}\n", status));
+            drm.write(&format!("{}/vrr_capable", entry), format!("{}\n", capable));
+        }
+        drm.write("card0/dev", "226:0\n");
+        drm.write("version", "drm 1.1.0 20060810\n");
+        // The second GPU's connector both the way xrandr names it and bare
+        assert_eq!(drm_vrr_outputs(drm.path()), ["DP-1", "DP-3-1", "DP-3"]);
+        assert!(drm_vrr_outputs(&drm.join("missing")).is_empty());
+    }
+
+    #[test]
+    fn cgroup_limits_parse() {
+        assert_eq!(parse_memory_max("2147483648\n"), Some(2147483648));
+        assert_eq!(parse_memory_max("max\n"), None);
+        // v1's "no limit", i64::MAX rounded down to a page
+        assert_eq!(parse_memory_max("9223372036854771712"), None);
+        assert_eq!(parse_memory_max(""), None);
+
+        assert_eq!(parse_cpu_max("400000 100000\n"), Some(4.0));
+        assert_eq!(parse_cpu_max("150000 100000"), Some(1.5));
+        assert_eq!(parse_cpu_max("50000 200000"), Some(0.25));
+        assert_eq!(parse_cpu_max("max 100000"), None);
+        // v1 quota -1 is no quota, a missing period is the kernel's default
+        assert_eq!(parse_cpu_max("-1 100000"), None);
+        assert_eq!(parse_cpu_max("200000"), Some(2.0));
+        assert_eq!(parse_cpu_max("100000 0"), None);
+    }
+
+    #[test]
+    fn cgroup_v2_files() {
+        let cgroup = TempDir::new();
+        cgroup.write("memory.max", "2147483648\n");
+        cgroup.write("memory.current", "1073741824\n");
+        cgroup.write("memory.stat", "anon 536870912\nfile 536870912\ninactive_file 268435456\n");
+        cgroup.write("cpu.max", "200000 100000\n");
+        // Page cache that can be dropped isn't used
+        assert_eq!(cgroup_memory_in(cgroup.path()), Some((2147483, 805306)));
+        assert_eq!(cgroup_cpu_limit_in(cgroup.path()), Some(2.0));
+
+        cgroup.write("memory.max", "max\n");
+        cgroup.write("cpu.max", "max 100000\n");
+        assert_eq!(cgroup_memory_in(cgroup.path()), None);
+        assert_eq!(cgroup_cpu_limit_in(cgroup.path()), None);
+    }
+
+    #[test]
+    fn cgroup_v1_files() {
+        let cgroup = TempDir::new();
+        cgroup.write("memory/memory.limit_in_bytes", "536870912\n");
+        cgroup.write("memory/memory.usage_in_bytes", "402653184\n");
+        // v1 has the hierarchy's total next to this cgroup's own
+        let stat = "cache 0\ninactive_file 9\ntotal_inactive_file 134217728\n";
+        cgroup.write("memory/memory.stat", stat);
+        cgroup.write("cpu/cpu.cfs_quota_us", "50000\n");
+        cgroup.write("cpu/cpu.cfs_period_us", "100000\n");
+        assert_eq!(cgroup_memory_in(cgroup.path()), Some((536870, 268435)));
+        assert_eq!(cgroup_cpu_limit_in(cgroup.path()), Some(0.5));
+
+        cgroup.write("memory/memory.limit_in_bytes", "9223372036854771712\n");
+        cgroup.write("cpu/cpu.cfs_quota_us", "-1\n");
+        assert_eq!(cgroup_memory_in(cgroup.path()), None);
+        assert_eq!(cgroup_cpu_limit_in(cgroup.path()), None);
+        // Not in a cgroup we can read at all
+        assert_eq!(cgroup_memory_in(TempDir::new().path()), None);
+        assert_eq!(cgroup_cpu_limit_in(TempDir::new().path()), None);
+    }
+
+    #[test]
+    fn the_smaller_of_host_and_cgroup() {
+        let host = Some((64_000_000, 48_000_000));
+        // A 2GB limit with 500MB used shows as 2GB
+        assert_eq!(limited_memory(host, Some((2_000_000, 500_000))), Some((2_000_000, 1_500_000)));
+        // A limit above the host's RAM means nothing, the host numbers stay
+        assert_eq!(limited_memory(host, Some((128_000_000, 500_000))), host);
+        assert_eq!(limited_memory(host, None), host);
+        assert_eq!(limited_memory(None, Some((2_000_000, 2_000_000))), Some((2_000_000, 0)));
+    }
+
+    // A /sys/class/dmi/id with sys_vendor, product_name and product_version, "" leaves one out
+    fn dmi_tree(vendor: &str, name: &str, version: &str) -> TempDir {
+        let dir = TempDir::new();
+        let fields = [("sys_vendor", vendor), ("product_name", name), ("product_version", version)];
+        for (field, value) in fields.into_iter().filter(|(_, value)| !value.is_empty()) {
+            dir.write(field, format!("{}\n", value));
+        }
+        dir
+    }
+
+    #[test]
+    fn handhelds_by_dmi() {
+        const ASUS: &str = "ASUSTeK COMPUTER INC.";
+        let cases = [
+            ("Valve", "Jupiter", "1", Some("Steam Deck LCD")),
+            ("Valve", "Galileo", "1", Some("Steam Deck OLED")),
+            (ASUS, "ROG Ally RC71L_RC71L", "1.0", Some("ASUS ROG Ally")),
+            (ASUS, "ROG Ally X RC72LA_RC72LA", "1.0", Some("ASUS ROG Ally X")),
+            ("LENOVO", "83E1", "Legion Go 8APU1", Some("Lenovo Legion Go")),
+            ("LENOVO", "83L3", "Legion Go S 8ARP1", Some("Lenovo Legion Go S")),
+            // The same name from another maker isn't the handheld
+            ("MSI", "Jupiter", "", None),
+            ("LENOVO", "83E1", "", None),
+        ];
+        for (vendor, name, version, handheld) in cases {
+            let dmi = dmi_tree(vendor, name, version);
+            assert_eq!(handheld_in(dmi.path()), handheld, "{} {}", vendor, name);
+            if let Some(handheld) = handheld {
+                assert_eq!(host_in(dmi.path()), handheld);
+            }
+        }
+    }
+
+    #[test]
+    fn hosts_by_dmi() {
+        let cases = [
+            ("Dell Inc.", "XPS 13 9340", "", "Dell Inc. XPS 13 9340"),
+            // Already says who made it
+            ("Framework", "Framework Laptop 13", "", "Framework Laptop 13"),
+            // Lenovo's model is in product_version, unless that's a placeholder
+            ("LENOVO", "21K5CTO1WW", "ThinkPad T14s Gen 5", "LENOVO ThinkPad T14s Gen 5"),
+            ("LENOVO", "21K5CTO1WW", "None", "LENOVO 21K5CTO1WW"),
+            // A board maker's placeholders are no model
+            ("System manufacturer", "System Product Name", "System Version", "unknown"),
+            ("", "  Standard PC (Q35 + ICH9, 2009)  ", "", "Standard PC (Q35 + ICH9, 2009)"),
+            ("MSI", "MS-7E12", "1.0", "MSI MS-7E12"),
+        ];
+        for (vendor, name, version, host) in cases {
+            assert_eq!(host_in(dmi_tree(vendor, name, version).path()), host);
+        }
+        // No DMI at all (ARM boards, containers)
+        assert_eq!(host_in(&TempDir::new().join("missing")), "unknown");
+        assert_eq!(handheld_in(TempDir::new().path()), None);
+    }
+
+    // /proc/net/wireless from an iwlwifi laptop with a second, idle card
+    const WIRELESS: &str = "\
+Inter-| sta-|   Quality        |   Discarded packets               | Missed | WE
+ face | tus | link level noise |  nwid  crypt   frag  retry   misc | beacon | 22
+wlp3s0: 0000   54.  -56.  -256        0      0      0      0    128        0
+ wlan1: 0000    0.    0.  -256        0      0      0      0      0        0
+";
+
+    #[test]
+    fn signal_from_proc_net_wireless() {
+        assert_eq!(parse_wireless_signal(WIRELESS, "wlp3s0"), Some(-56));
+        // Nothing heard yet
+        assert_eq!(parse_wireless_signal(WIRELESS, "wlan1"), None);
+        assert_eq!(parse_wireless_signal(WIRELESS, "wlp3s"), None);
+        // Level as an unsigned byte
+        let unsigned = WIRELESS.replace("-56.", "200.");
+        assert_eq!(parse_wireless_signal(&unsigned, "wlp3s0"), Some(-56));
+        // Just the two header lines when no card is up
+        let headers: String = WIRELESS.lines().take(2).map(|line| format!("{}\n", line)).collect();
+        assert_eq!(parse_wireless_signal(&headers, "wlp3s0"), None);
+        assert_eq!(parse_wireless_signal("", "wlp3s0"), None);
+        assert_eq!(parse_wireless_signal("a\nb\nwlp3s0: 0000 54.\n", "wlp3s0"), None);
+    }
+
+    #[test]
+    fn ssid_from_iw_link() {
+        let connected = "\
+Connected to 12:34:56:78:9a:bc (on wlp3s0)
+\tSSID: Café Wi-Fi 5G
+\tfreq: 5180
+\tRX: 123456 bytes (789 packets)
+\tTX: 23456 bytes (123 packets)
+\tsignal: -52 dBm
+\trx bitrate: 866.7 MBit/s VHT-MCS 9 80MHz short GI VHT-NSS 2
+\ttx bitrate: 780.0 MBit/s VHT-MCS 8 80MHz short GI VHT-NSS 2
+
+\tbss flags:\tshort-slot-time
+\tdtim period:\t1
+\tbeacon int:\t100
+";
+        assert_eq!(parse_iw_ssid(connected).as_deref(), Some("Café Wi-Fi 5G"));
+        assert_eq!(parse_iw_ssid("Not connected.\n"), None);
+        assert_eq!(parse_iw_ssid("Connected to 12:34:56:78:9a:bc (on wlp3s0)\n\tSSID: \n"), None);
+        assert_eq!(parse_iw_ssid(""), None);
+    }
+
+    #[test]
+    fn link_speeds() {
+        assert_eq!(format_link_speed(100), "100Mbps");
+        assert_eq!(format_link_speed(1000), "1Gbps");
+        assert_eq!(format_link_speed(2500), "2.5Gbps");
+        assert_eq!(format_link_speed(10000), "10Gbps");
+    }
+
+    #[test]
+    fn default_route_and_global_ipv6() {
+        let route = "\
+Iface\tDestination\tGateway \tFlags\tRefCnt\tUse\tMetric\tMask\t\tMTU\tWindow\tIRTT
+docker0\t000011AC\t00000000\t0001\t0\t0\t0\t0000FFFF\t0\t0\t0
+wlp3s0\t00000000\t0101A8C0\t0003\t0\t0\t600\t00000000\t0\t0\t0
+";
+        assert_eq!(route_interface(route).as_deref(), Some("wlp3s0"));
+        assert_eq!(route_interface(route.lines().take(2).collect::<String>().as_str()), None);
+
+        let inet6 = "\
+fe80000000000000021122fffe334455 03 40 20 80   wlp3s0
+20010db8000000000000000000000001 03 40 00 20   wlp3s0
+20010db8000000000000000000000002 03 40 00 00   wlp3s0
+00000000000000000000000000000001 01 80 10 80       lo
+";
+        let global = parse_global_ipv6(inet6, "wlp3s0").map(|address| address.to_string());
+        assert_eq!(global.as_deref(), Some("2001:db8::2"));
+        assert_eq!(parse_global_ipv6(inet6, "lo"), None);
+        assert_eq!(parse_global_ipv6(inet6, "eth0"), None);
+    }
+
+    // A mains supply and a battery under sys/class/power_supply
+    fn power_supplies(root: &TempDir, online: &str) {
+        root.write("sys/class/power_supply/AC/type", "Mains\n");
+        root.write("sys/class/power_supply/AC/online", format!("{}\n", online));
+        root.write("sys/class/power_supply/BAT0/type", "Battery\n");
+    }
+
+    // Each source wins over the ones after it, and is skipped when it has no answer
+    #[test]
+    fn power_profile_precedence() {
+        let root = TempDir::new();
+        let none = || None;
+        assert_eq!(power_profile(root.path(), none), None);
+
+        // No TLP: AC state alone isn't a profile
+        power_supplies(&root, "1");
+        assert_eq!(power_profile(root.path(), none), None);
+        // TLP without last_pwr goes by AC
+        fs::create_dir_all(root.join("run/tlp")).unwrap();
+        assert_eq!(power_profile(root.path(), none), Some("performance"));
+        power_supplies(&root, "0");
+        assert_eq!(power_profile(root.path(), none), Some("balanced"));
+        // then by what it applied last
+        root.write("run/tlp/last_pwr", "2\n");
+        assert_eq!(power_profile(root.path(), none), Some("power-saver"));
+        root.write("run/tlp/last_pwr", "garbage\n");
+        assert_eq!(power_profile(root.path(), none), Some("balanced"));
+        // powerprofilesctl over TLP
+        assert_eq!(power_profile(root.path(), || Some("performance")), Some("performance"));
+        // and the platform profile over everything
+        root.write("sys/firmware/acpi/platform_profile", "low-power\n");
+        assert_eq!(power_profile(root.path(), || Some("performance")), Some("power-saver"));
+        // unless it's a name it doesn't know
+        root.write("sys/firmware/acpi/platform_profile", "custom\n");
+        assert_eq!(power_profile(root.path(), || Some("performance")), Some("performance"));
+
+        // Without a mains supply TLP's AC fallback has nothing to go on
+        let tlp_only = TempDir::new();
+        fs::create_dir_all(tlp_only.join("run/tlp")).unwrap();
+        assert_eq!(power_profile(tlp_only.path(), none), None);
+    }
+
+    #[test]
+    fn power_profile_names() {
+        let platform = ["performance", "balanced-performance", "quiet", "cool", "low-power"];
+        let names: Vec<_> = platform.iter().map(|name| parse_platform_profile(name)).collect();
+        let expected = ["performance", "balanced", "power-saver", "power-saver", "power-saver"];
+        assert_eq!(names, expected.map(Some));
+        assert_eq!(parse_platform_profile("custom"), None);
+        assert_eq!(parse_profile_name("power-saver\n"), Some("power-saver"));
+        assert_eq!(parse_profile_name("low-power"), None);
+        assert_eq!(parse_tlp_last_pwr("0\n"), Some("performance"));
+        assert_eq!(parse_tlp_last_pwr("1"), Some("balanced"));
+        assert_eq!(parse_tlp_last_pwr(""), None);
+
+        // Any mains supply online is enough, none at all is no answer
+        let root = TempDir::new();
+        let supplies = root.join("sys/class/power_supply");
+        assert_eq!(on_ac_power(&supplies), None);
+        power_supplies(&root, "0");
+        assert_eq!(on_ac_power(&supplies), Some(false));
+        root.write("sys/class/power_supply/USB-C/type", "Mains\n");
+        root.write("sys/class/power_supply/USB-C/online", "1\n");
+        assert_eq!(on_ac_power(&supplies), Some(true));
+    }
+
+    #[test]
+    fn network_filesystems() {
+        for fs_type in ["nfs", "nfs4", "cifs", "smb3", "fuse.sshfs"] {
+            assert!(is_network_fs(fs_type.as_bytes(), b"/home/shared"), "{}", fs_type);
+        }
+        // WSL's Windows drives, not its other 9p mounts
+        assert!(is_network_fs(b"9p", b"/mnt/c"));
+        assert!(is_network_fs(b"drvfs", b"/mnt/d"));
+        assert!(!is_network_fs(b"9p", b"/usr/lib/wsl/drivers"));
+        assert!(!is_network_fs(b"9p", b"/mnt/wsl"));
+        assert!(!is_network_fs(b"9p", b"/mnt/"));
+        assert!(!is_network_fs(b"9p", b"/mnt/1"));
+        assert!(!is_network_fs(b"9p", b"/media/c"));
+        for fs_type in ["ext4", "btrfs", "fuse", "fuseblk", "tmpfs"] {
+            assert!(!is_network_fs(fs_type.as_bytes(), b"/mnt/c"), "{}", fs_type);
+        }
+    }
+
+    // A BAT0 with the given sysfs files, (watts, seconds left) for it charging or not
+    fn rate(files: &[(&str, &str)], charging: bool) -> Option<(f64, Option<u64>)> {
+        let root = TempDir::new();
+        for (file, content) in files {
+            root.write(&format!("BAT0/{}", file), format!("{}\n", content));
+        }
+        battery_rate(&root.join("BAT0"), charging)
+    }
+
+    fn watts(rate: Option<(f64, Option<u64>)>) -> f64 {
+        (rate.unwrap().0 * 100.0).round() / 100.0
+    }
+
+    // An energy based battery, 60Wh when full
+    fn energy<'a>(power_now: &'a str, energy_now: &'a str) -> [(&'a str, &'a str); 3] {
+        [("power_now", power_now), ("energy_now", energy_now), ("energy_full", "60000000")]
+    }
+
+    // Energy based driver with power_now in µW (most Intel and AMD laptops)
+    #[test]
+    fn battery_rate_from_power_now() {
+        let files = energy("12400000", "45570000");
+        let discharging = rate(&files, false);
+        assert_eq!(watts(discharging), 12.4);
+        // 45.57Wh at 12.4W
+        assert_eq!(discharging.unwrap().1, Some(13230));
+        // 14.43Wh to go
+        assert_eq!(rate(&files, true).unwrap().1, Some(4189));
+        // Some drivers report power_now negative while discharging
+        assert_eq!(rate(&energy("-12400000", "45570000"), false), discharging);
+    }
+
+    // Charge based driver with no power_now: µA × µV, and the time left by current
+    #[test]
+    fn battery_rate_from_current_and_voltage() {
+        let files = [
+            ("current_now", "1500000"),
+            ("voltage_now", "11800000"),
+            ("charge_now", "3000000"),
+            ("charge_full", "5000000"),
+        ];
+        let discharging = rate(&files, false);
+        assert_eq!(watts(discharging), 17.7);
+        // 3Ah at 1.5A, 2Ah to go charging
+        assert_eq!(discharging.unwrap().1, Some(7200));
+        assert_eq!(rate(&files, true).unwrap().1, Some(4800));
+        // Negative current while discharging (some Chromebook and ARM drivers)
+        let mut negative = files;
+        negative[0] = ("current_now", "-1500000");
+        assert_eq!(rate(&negative, false), discharging);
+    }
+
+    #[test]
+    fn battery_rate_edge_cases() {
+        // No power_now and only half of current × voltage: no rate at all
+        assert_eq!(rate(&[("current_now", "1500000")], false), None);
+        assert_eq!(rate(&[("voltage_now", "11800000")], false), None);
+        assert_eq!(rate(&[("capacity", "64")], false), None);
+        // power_now with a charge based capacity and no current: watts but no estimate
+        let files =
+            [("power_now", "8000000"), ("charge_now", "3000000"), ("charge_full", "5000000")];
+        assert_eq!(rate(&files, false), Some((8.0, None)));
+        // Nothing flowing
+        assert_eq!(rate(&energy("0", "45570000"), false), None);
+        // Full while "Charging", and an idle battery days from empty: no estimate
+        assert_eq!(rate(&energy("2000000", "60000000"), true), Some((2.0, None)));
+        assert_eq!(rate(&energy("100000", "45570000"), false), Some((0.1, None)));
+    }
+
+    // The rate on the battery line, with show_power
+    #[test]
+    fn battery_line_with_power() {
+        let battery: &[(&str, &str)] = &[
+            ("type", "Battery\n"),
+            ("capacity", "64\n"),
+            ("status", "Discharging\n"),
+            ("power_now", "12400000\n"),
+            ("energy_now", "45570000\n"),
+            ("energy_full", "60000000\n"),
+        ];
+        let root = power_tree(10, &[AC, ("BAT0", battery)]);
+        let (line, percent) = battery_in(root.path(), BatteryThresholds::default(), true);
+        assert!(strip_escapes(&line).ends_with(" 64% 󰂍 · 12.4W (~3h 40m)"), "{}", line);
+        assert_eq!(percent, Some(64));
+        // Not charging is nothing flowing, whatever power_now says
+        root.write("sys/class/power_supply/BAT0/status", "Not charging\n");
+        let (line, _) = battery_in(root.path(), BatteryThresholds::default(), true);
+        assert!(strip_escapes(&line).ends_with(" 64% Not charging"), "{}", line);
+    }
+
+    #[test]
+    fn audio_device_names() {
+        let wpctl = [
+            "id 52, type PipeWire:Interface:Node",
+            "    alsa.card = \"1\"",
+            "  * node.description = \"Schiit Modi 3 Analog Stereo\"",
+            "  * node.name = \"alsa_output.usb-Schiit_Modi_3-00.analog-stereo\"",
+        ]
+        .join("\n");
+        assert_eq!(wpctl_description(&wpctl).as_deref(), Some("Schiit Modi 3 Analog Stereo"));
+        assert_eq!(wpctl_description("Object not found\n"), None);
+        assert_eq!(wpctl_description("  * node.description = \"\"\n"), None);
+
+        // Only the default sink's block counts
+        let sinks = [
+            "Sink #0",
+            "\tState: SUSPENDED",
+            "\tName: alsa_output.pci-0000_0a_00.1.hdmi-stereo",
+            "\tDescription: Navi 21 HDMI Audio Digital Stereo (HDMI)",
+            "",
+            "Sink #1",
+            "\tState: RUNNING",
+            "\tName: alsa_output.pci-0000_0c_00.4.analog-stereo",
+            "\tDescription: Family 17h HD Audio Controller Analog Stereo",
+        ]
+        .join("\n");
+        let description = |sink| pactl_description(sink, &sinks);
+        assert_eq!(
+            description("alsa_output.pci-0000_0c_00.4.analog-stereo").as_deref(),
+            Some("Family 17h HD Audio Controller Analog Stereo")
+        );
+        assert_eq!(
+            description("alsa_output.pci-0000_0a_00.1.hdmi-stereo").as_deref(),
+            Some("Navi 21 HDMI Audio Digital Stereo (HDMI)")
+        );
+        assert_eq!(description("bluez_output.00_1B_66"), None);
+
+        let card = " 0 [Generic        ]: HDA-Intel - HD-Audio Generic";
+        assert_eq!(asound_card(card).as_deref(), Some("HD-Audio Generic"));
+        assert_eq!(asound_card("--- no soundcards ---"), None);
+
+        let names = [
+            ("Schiit Modi 3 Analog Stereo", "Schiit Modi 3"),
+            ("Navi 21 HDMI Audio Digital Stereo (HDMI)", "Navi 21 HDMI Audio"),
+            ("Scarlett 2i2 USB Pro", "Scarlett 2i2 USB"),
+            // Only one suffix goes, and only at the end
+            ("Stereo Analog Stereo Analog Stereo", "Stereo Analog Stereo"),
+            ("Analog Stereo Mixer", "Analog Stereo Mixer"),
+            ("  HD-Audio Generic ", "HD-Audio Generic"),
+        ];
+        for (raw, cleaned) in names {
+            assert_eq!(clean_audio_name(raw), cleaned, "{}", raw);
+        }
+    }
+
+    #[test]
+    fn vrr_and_hdr_tags_on_the_active_mode() {
+        let details = OutputDetails {
+            vrr: vec!["DP-1".into(), "DP-2".into()],
+            hdr: vec!["DP-1".into()],
+            backlight: None,
+        };
+        let shown: Vec<String> =
+            xrandr_screens(XRANDR, &details).into_iter().map(|(_, _, shown)| shown).collect();
+        assert_eq!(
+            shown,
+            ["󰏠 2560x1440 @ 144Hz VRR HDR", "󰏠 1920x1080 @ 60Hz", "󰆡 1440x2560 @ 60Hz VRR"]
+        );
+    }
+
+    #[test]
+    fn vendors_from_ids() {
+        let pci_ids = [
+            ("0x1002", Vendor::Amd),
+            ("1022", Vendor::Amd),
+            ("0x8086\n", Vendor::Intel),
+            ("0x10DE", Vendor::Nvidia),
+            ("0x106b", Vendor::Apple),
+            ("0x1af4", Vendor::Unknown),
+            ("", Vendor::Unknown),
+        ];
+        for (id, vendor) in pci_ids {
+            assert_eq!(Vendor::from_pci_id(id), vendor, "{}", id);
+        }
+
+        // x86 vendor_id, or the implementer on ARM (0x41 is Arm itself, 0x51 Qualcomm)
+        let cpuinfo = [
+            ("GenuineIntel", Vendor::Intel),
+            ("AuthenticAMD", Vendor::Amd),
+            ("HygonGenuine", Vendor::Amd),
+            ("0x41", Vendor::Arm),
+            ("0x51", Vendor::Arm),
+            ("0x61", Vendor::Apple),
+            ("CentaurHauls", Vendor::Unknown),
+        ];
+        for (value, vendor) in cpuinfo {
+            assert_eq!(Vendor::from_cpuinfo(value), vendor, "{}", value);
+        }
+
+        let names = [
+            ("Advanced Micro Devices, Inc. [AMD/ATI]", Vendor::Amd),
+            ("NVIDIA Corporation", Vendor::Nvidia),
+            ("Intel Corporation", Vendor::Intel),
+            ("Red Hat, Inc.", Vendor::Unknown),
+        ];
+        for (name, vendor) in names {
+            assert_eq!(Vendor::from_vendor_name(name), vendor, "{}", name);
+        }
+
+        // What the cache stores comes back as the same vendor, anything else is unknown
+        let all = [Vendor::Amd, Vendor::Intel, Vendor::Nvidia, Vendor::Arm, Vendor::Apple];
+        for vendor in all.into_iter().chain([Vendor::Unknown]) {
+            assert_eq!(Vendor::from_key(vendor.key()), vendor);
+        }
+        assert_eq!(Vendor::from_key("1002"), Vendor::Unknown);
+        // Every known vendor has a tag for fonts without nerd glyphs, unknown gets nothing
+        assert!(all.iter().all(|vendor| vendor.brand().is_some()));
+        assert_eq!(Vendor::Unknown.icon(GPU_GLYPH), "");
+    }
+
+    #[test]
+    fn integrated_and_discrete_gpus() {
+        const GB: u64 = 1024 * 1024 * 1024;
+        let adapter = |name: &str, vendor, address: &str, vram| Adapter {
+            name: name.to_string(),
+            vendor,
+            address: address.to_string(),
+            vram,
+        };
+        let labels = |adapters| -> Vec<(String, &str)> {
+            classify_adapters(adapters)
+                .into_iter()
+                .map(|gpu| (gpu.name, gpu.kind.map_or("", GpuKind::label)))
+                .collect()
+        };
+        let labeled = |pairs: &[(&str, &'static str)]| -> Vec<(String, &'static str)> {
+            pairs.iter().map(|(name, label)| (name.to_string(), *label)).collect()
+        };
+
+        // Intel laptop with an NVIDIA dGPU
+        let optimus = vec![
+            adapter("UHD Graphics 770", Vendor::Intel, "0000:00:02.0", None),
+            adapter("GeForce RTX 4070 Max-Q", Vendor::Nvidia, "0000:01:00.0", None),
+        ];
+        let expected = [("UHD Graphics 770", "iGPU"), ("GeForce RTX 4070 Max-Q", "dGPU")];
+        assert_eq!(labels(optimus), labeled(&expected));
+
+        // Ryzen APU next to a Radeon card, both behind bridges, told apart by their VRAM
+        let amd = vec![
+            adapter("Radeon RX 7900 XTX", Vendor::Amd, "0000:03:00.0", Some(24 * GB)),
+            adapter("Raphael", Vendor::Amd, "0000:10:00.0", Some(GB / 2)),
+        ];
+        let expected = [("Radeon RX 7900 XTX", "dGPU"), ("Raphael", "iGPU")];
+        assert_eq!(labels(amd), labeled(&expected));
+
+        // An Arc card isn't on the root bus, an APU's carve out can be the full 2GB
+        let intel = vec![
+            adapter("Arc A770", Vendor::Intel, "0000:03:00.0", None),
+            adapter("UHD Graphics 630", Vendor::Intel, "0000:00:02.0", None),
+            adapter("Phoenix1", Vendor::Amd, "0000:c1:00.0", Some(2 * GB)),
+            adapter("Radeon RX 7600M XT", Vendor::Amd, "0000:03:00.0", Some(8 * GB)),
+        ];
+        let expected = [
+            ("Arc A770", "dGPU"),
+            ("UHD Graphics 630", "iGPU"),
+            ("Phoenix1", "iGPU"),
+            ("Radeon RX 7600M XT", "dGPU"),
+        ];
+        assert_eq!(labels(intel), labeled(&expected));
+
+        // Two Radeon cards: the smaller one is still a card of its own
+        let radeons = vec![
+            adapter("Radeon RX 7600", Vendor::Amd, "0000:03:00.0", Some(8 * GB)),
+            adapter("Radeon RX 7900 XTX", Vendor::Amd, "0000:08:00.0", Some(24 * GB)),
+        ];
+        let expected = [("Radeon RX 7600", "dGPU"), ("Radeon RX 7900 XTX", "dGPU")];
+        assert_eq!(labels(radeons), labeled(&expected));
+
+        // The labels survive the cache
+        for kind in [GpuKind::Integrated, GpuKind::Discrete] {
+            assert_eq!(GpuKind::from_label(kind.label()), Some(kind));
+        }
+        assert_eq!(GpuKind::from_label(""), None);
+    }
+}
@@ -0,0 +1,355 @@
+// Microarchitecture names for arch_names, "Zen 4" and "RDNA 3" after the CPU and GPU.
+// CPUs are looked up by the cpu family and model from /proc/cpuinfo, GPUs by their PCI ids.
+// The tables cover the common desktop, laptop and server parts, not every chip ever made:
+// anything missing just gets no name.
+
+use std::fs;
+use std::path::Path;
+
+// (cpu family, first model, last model, name) per vendor, first match wins.
+// Models are the decimal "model" field from cpuinfo, written in hex like the docs do.
+const AMD_CPUS: &[(u32, u32, u32, &str)] = &[
+    (0x15, 0x00, 0x01, "Bulldozer"),
+    (0x15, 0x02, 0x1F, "Piledriver"),
+    (0x15, 0x30, 0x3F, "Steamroller"),
+    (0x15, 0x60, 0x7F, "Excavator"),
+    (0x16, 0x00, 0x0F, "Jaguar"),
+    (0x16, 0x30, 0x3F, "Puma"),
+    (0x17, 0x08, 0x08, "Zen+"),
+    (0x17, 0x18, 0x18, "Zen+"),
+    (0x17, 0x00, 0x2F, "Zen"),
+    (0x17, 0x30, 0xAF, "Zen 2"),
+    (0x19, 0x00, 0x0F, "Zen 3"),
+    (0x19, 0x10, 0x1F, "Zen 4"),
+    (0x19, 0x20, 0x2F, "Zen 3"),
+    (0x19, 0x40, 0x4F, "Zen 3+"),
+    (0x19, 0x50, 0x5F, "Zen 3"),
+    (0x19, 0x60, 0x7F, "Zen 4"),
+    (0x19, 0xA0, 0xAF, "Zen 4"),
+    (0x1A, 0x00, 0x7F, "Zen 5"),
+];
+
+const INTEL_CPUS: &[(u32, u32, u32, &str)] = &[
+    (6, 0x1A, 0x1A, "Nehalem"),
+    (6, 0x1E, 0x1F, "Nehalem"),
+    (6, 0x2E, 0x2E, "Nehalem"),
+    (6, 0x25, 0x25, "Westmere"),
+    (6, 0x2C, 0x2C, "Westmere"),
+    (6, 0x2F, 0x2F, "Westmere"),
+    (6, 0x2A, 0x2A, "Sandy Bridge"),
+    (6, 0x2D, 0x2D, "Sandy Bridge"),
+    (6, 0x3A, 0x3A, "Ivy Bridge"),
+    (6, 0x3E, 0x3E, "Ivy Bridge"),
+    (6, 0x3C, 0x3C, "Haswell"),
+    (6, 0x3F, 0x3F, "Haswell"),
+    (6, 0x45, 0x46, "Haswell"),
+    (6, 0x3D, 0x3D, "Broadwell"),
+    (6, 0x47, 0x47, "Broadwell"),
+    (6, 0x4F, 0x4F, "Broadwell"),
+    (6, 0x56, 0x56, "Broadwell"),
+    (6, 0x4E, 0x4E, "Skylake"),
+    (6, 0x5E, 0x5E, "Skylake"),
+    (6, 0x55, 0x55, "Skylake"),
+    (6, 0x5C, 0x5C, "Goldmont"),
+    (6, 0x5F, 0x5F, "Goldmont"),
+    (6, 0x7A, 0x7A, "Goldmont Plus"),
+    (6, 0x66, 0x66, "Cannon Lake"),
+    (6, 0x6A, 0x6A, "Ice Lake"),
+    (6, 0x6C, 0x6C, "Ice Lake"),
+    (6, 0x7D, 0x7E, "Ice Lake"),
+    (6, 0x86, 0x86, "Tremont"),
+    (6, 0x96, 0x96, "Tremont"),
+    (6, 0x9C, 0x9C, "Tremont"),
+    (6, 0x8C, 0x8D, "Tiger Lake"),
+    (6, 0x8F, 0x8F, "Sapphire Rapids"),
+    (6, 0xA5, 0xA6, "Comet Lake"),
+    (6, 0xA7, 0xA7, "Rocket Lake"),
+    (6, 0x97, 0x97, "Alder Lake"),
+    (6, 0x9A, 0x9A, "Alder Lake"),
+    (6, 0xBE, 0xBE, "Alder Lake-N"),
+    (6, 0xB7, 0xB7, "Raptor Lake"),
+    (6, 0xBA, 0xBA, "Raptor Lake"),
+    (6, 0xBF, 0xBF, "Raptor Lake"),
+    (6, 0xAA, 0xAC, "Meteor Lake"),
+    (6, 0xAD, 0xAD, "Granite Rapids"),
+    (6, 0xAF, 0xAF, "Sierra Forest"),
+    (6, 0xB5, 0xB5, "Arrow Lake"),
+    (6, 0xC5, 0xC6, "Arrow Lake"),
+    (6, 0xBD, 0xBD, "Lunar Lake"),
+    (6, 0xCC, 0xCC, "Panther Lake"),
+    (6, 0xCF, 0xCF, "Emerald Rapids"),
+];
+
+// Intel reused two model numbers for four generations, the stepping tells them apart.
+// (model, first stepping, name), checked before INTEL_CPUS, highest stepping first.
+const INTEL_STEPPINGS: &[(u32, u32, &str)] = &[
+    (0x8E, 12, "Comet Lake"),
+    (0x8E, 11, "Whiskey Lake"),
+    (0x8E, 0, "Kaby Lake"),
+    (0x9E, 10, "Coffee Lake"),
+    (0x9E, 0, "Kaby Lake"),
+];
+
+// (PCI vendor id, first device id, last device id, name), first match wins
+const GPUS: &[(u16, u16, u16, &str)] = &[
+    // AMD APUs, then the dGPU families by die
+    (0x1002, 0x15D8, 0x15DD, "Vega"),
+    (0x1002, 0x1636, 0x1638, "Vega"),
+    (0x1002, 0x163F, 0x163F, "RDNA 2"),
+    (0x1002, 0x164E, 0x164E, "RDNA 2"),
+    (0x1002, 0x1681, 0x1681, "RDNA 2"),
+    (0x1002, 0x1506, 0x1506, "RDNA 2"),
+    (0x1002, 0x15BF, 0x15BF, "RDNA 3"),
+    (0x1002, 0x15C8, 0x15C8, "RDNA 3"),
+    (0x1002, 0x1900, 0x1901, "RDNA 3"),
+    (0x1002, 0x150E, 0x150E, "RDNA 3.5"),
+    (0x1002, 0x1586, 0x1586, "RDNA 3.5"),
+    (0x1002, 0x1114, 0x1114, "RDNA 3.5"),
+    (0x1002, 0x67C0, 0x67FF, "Polaris"),
+    (0x1002, 0x6980, 0x699F, "Polaris"),
+    (0x1002, 0x6860, 0x687F, "Vega"),
+    (0x1002, 0x66A0, 0x66AF, "Vega"),
+    (0x1002, 0x7310, 0x736F, "RDNA"),
+    (0x1002, 0x73A0, 0x73FF, "RDNA 2"),
+    (0x1002, 0x7420, 0x743F, "RDNA 2"),
+    (0x1002, 0x7440, 0x749F, "RDNA 3"),
+    (0x1002, 0x74A0, 0x74BF, "CDNA 3"),
+    (0x1002, 0x7550, 0x755F, "RDNA 4"),
+    (0x1002, 0x7590, 0x759F, "RDNA 4"),
+    // NVIDIA ids go up with each generation, with a few dies out of order
+    (0x10DE, 0x0FC0, 0x12FF, "Kepler"),
+    (0x10DE, 0x15F0, 0x15FF, "Pascal"),
+    (0x10DE, 0x1340, 0x17FF, "Maxwell"),
+    (0x10DE, 0x1B00, 0x1D7F, "Pascal"),
+    (0x10DE, 0x1D80, 0x1DFF, "Volta"),
+    (0x10DE, 0x1E00, 0x21FF, "Turing"),
+    (0x10DE, 0x2200, 0x22FF, "Ampere"),
+    (0x10DE, 0x2300, 0x234F, "Hopper"),
+    (0x10DE, 0x2400, 0x25FF, "Ampere"),
+    (0x10DE, 0x2680, 0x28FF, "Ada Lovelace"),
+    (0x10DE, 0x2900, 0x2FFF, "Blackwell"),
+    // Intel iGPUs by platform, then Arc
+    (0x8086, 0x1900, 0x193F, "Gen9"),
+    (0x8086, 0x5900, 0x59FF, "Gen9.5"),
+    (0x8086, 0x3E90, 0x3EAF, "Gen9.5"),
+    (0x8086, 0x9BC0, 0x9BFF, "Gen9.5"),
+    (0x8086, 0x8A50, 0x8A5F, "Gen11"),
+    (0x8086, 0x9A40, 0x9AFF, "Xe-LP"),
+    (0x8086, 0x4C80, 0x4C9F, "Xe-LP"),
+    (0x8086, 0x4680, 0x46FF, "Xe-LP"),
+    (0x8086, 0xA780, 0xA7AF, "Xe-LP"),
+    (0x8086, 0x7D40, 0x7D67, "Xe-LPG"),
+    (0x8086, 0x64A0, 0x64A0, "Xe2"),
+    (0x8086, 0x5690, 0x56BF, "Xe-HPG"),
+    (0x8086, 0xE202, 0xE21F, "Xe2"),
+];
+
+// Microarchitecture of the CPU from the first processor in /proc/cpuinfo
+pub fn cpu_arch() -> Option<&'static str> {
+    cpu_arch_from(&fs::read_to_string("/proc/cpuinfo").ok()?)
+}
+
+fn cpu_arch_from(cpuinfo: &str) -> Option<&'static str> {
+    let (mut vendor, mut family, mut model, mut stepping) = ("", None, None, 0);
+    // Only the first processor block, they're all the same chip
+    for line in cpuinfo.lines().take_while(|line| !line.trim().is_empty()) {
+        let Some((key, value)) = line.split_once(':') else {
+            continue;
+        };
+        let value = value.trim();
+        match key.trim() {
+            "vendor_id" => vendor = value,
+            "cpu family" => family = value.parse().ok(),
+            "model" => model = value.parse().ok(),
+            "stepping" => stepping = value.parse().unwrap_or(0),
+            _ => {}
+        }
+    }
+    cpu_arch_for(vendor, family?, model?, stepping)
+}
+
+fn cpu_arch_for(vendor: &str, family: u32, model: u32, stepping: u32) -> Option<&'static str> {
+    let table = match vendor {
+        "AuthenticAMD" => AMD_CPUS,
+        "GenuineIntel" => {
+            if family == 6
+                && let Some(&(_, _, name)) = INTEL_STEPPINGS
+                    .iter()
+                    .find(|&&(m, first, _)| m == model && stepping >= first)
+            {
+                return Some(name);
+            }
+            INTEL_CPUS
+        }
+        _ => return None,
+    };
+    table
+        .iter()
+        .find(|&&(f, first, last, _)| f == family && (first..=last).contains(&model))
+        .map(|&(_, _, _, name)| name)
+}
+
+// Microarchitecture of every PCI GPU with a DRM card, in card order like the GPU list
+pub fn gpu_archs() -> Vec<Option<&'static str>> {
+    gpu_archs_in(Path::new("/sys/class/drm"))
+}
+
+fn gpu_archs_in(drm: &Path) -> Vec<Option<&'static str>> {
+    let Ok(entries) = fs::read_dir(drm) else {
+        return Vec::new();
+    };
+    let mut cards: Vec<(u32, std::path::PathBuf)> = entries
+        .flatten()
+        .filter_map(|entry| {
+            let name = entry.file_name();
+            let number = name.to_str()?.strip_prefix("card")?.parse().ok()?;
+            Some((number, entry.path()))
+        })
+        .collect();
+    cards.sort();
+
+    let id = |path: std::path::PathBuf| {
+        let text = fs::read_to_string(path).ok()?;
+        u16::from_str_radix(text.trim().trim_start_matches("0x"), 16).ok()
+    };
+    cards
+        .into_iter()
+        .filter_map(|(_, card)| {
+            let vendor = id(card.join("device/vendor"))?;
+            let device = id(card.join("device/device"))?;
+            Some(gpu_arch_for(vendor, device))
+        })
+        .collect()
+}
+
+fn gpu_arch_for(vendor: u16, device: u16) -> Option<&'static str> {
+    GPUS.iter()
+        .find(|&&(v, first, last, _)| v == vendor && (first..=last).contains(&device))
+        .map(|&(_, _, _, name)| name)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::testutil::TempDir;
+
+    // The first processor block of /proc/cpuinfo, trimmed to what's read and a bit around it
+    fn cpuinfo(vendor: &str, family: u32, model: u32, stepping: u32) -> String {
+        format!(
+            "processor\t: 0\nvendor_id\t: {}\ncpu family\t: {}\nmodel\t\t: {}\n\
+             model name\t: Some CPU\nstepping\t: {}\nflags\t\t: fpu vme de pse\n\n\
+             processor\t: 1\nvendor_id\t: {}\ncpu family\t: 1\nmodel\t\t: 1\n",
+            vendor, family, model, stepping, vendor
+        )
+    }
+
+    #[test]
+    fn cpus_from_cpuinfo() {
+        let cases = [
+            // Ryzen 7 7840HS, 5800X3D, 1700, 2700X, 9950X
+            ("AuthenticAMD", 25, 116, 1, Some("Zen 4")),
+            ("AuthenticAMD", 25, 33, 2, Some("Zen 3")),
+            ("AuthenticAMD", 23, 1, 1, Some("Zen")),
+            ("AuthenticAMD", 23, 8, 2, Some("Zen+")),
+            ("AuthenticAMD", 26, 68, 0, Some("Zen 5")),
+            // i7-13700K, i5-12400, i9-9900K, i7-7700K, i7-10510U, Xeon 8480+
+            ("GenuineIntel", 6, 183, 1, Some("Raptor Lake")),
+            ("GenuineIntel", 6, 151, 5, Some("Alder Lake")),
+            ("GenuineIntel", 6, 158, 12, Some("Coffee Lake")),
+            ("GenuineIntel", 6, 158, 9, Some("Kaby Lake")),
+            ("GenuineIntel", 6, 142, 12, Some("Comet Lake")),
+            ("GenuineIntel", 6, 143, 8, Some("Sapphire Rapids")),
+            // Not in the tables, or not a vendor with tables
+            ("AuthenticAMD", 16, 4, 3, None),
+            ("GenuineIntel", 15, 4, 1, None),
+            ("CentaurHauls", 6, 15, 2, None),
+        ];
+        for (vendor, family, model, stepping, arch) in cases {
+            let text = cpuinfo(vendor, family, model, stepping);
+            assert_eq!(cpu_arch_from(&text), arch, "{} {} {}", vendor, family, model);
+        }
+    }
+
+    #[test]
+    fn cpuinfo_without_the_numbers() {
+        // ARM cpuinfo has no family or model lines at all
+        let arm = "processor\t: 0\nBogoMIPS\t: 48.00\nCPU implementer\t: 0x41\nCPU part\t: 0xd0b\n";
+        assert_eq!(cpu_arch_from(arm), None);
+        assert_eq!(cpu_arch_from("vendor_id\t: GenuineIntel\ncpu family\t: 6\n"), None);
+        assert_eq!(cpu_arch_from("vendor_id\t: GenuineIntel\ncpu family\t: 6\nmodel\t: x\n"), None);
+        assert_eq!(cpu_arch_from(""), None);
+        // No stepping line counts as stepping 0
+        let text = "vendor_id\t: GenuineIntel\ncpu family\t: 6\nmodel\t\t: 158\n";
+        assert_eq!(cpu_arch_from(text), Some("Kaby Lake"));
+    }
+
+    #[test]
+    fn gpus_by_pci_id() {
+        let cases = [
+            // RX 7800 XT, RX 6800 XT, RX 9070 XT, RX 580, 780M, Steam Deck
+            (0x1002, 0x747E, Some("RDNA 3")),
+            (0x1002, 0x73BF, Some("RDNA 2")),
+            (0x1002, 0x7550, Some("RDNA 4")),
+            (0x1002, 0x67DF, Some("Polaris")),
+            (0x1002, 0x15BF, Some("RDNA 3")),
+            (0x1002, 0x1435, None),
+            // RTX 4090, RTX 3060, GTX 1080, Tesla P100, GTX 970, RTX 5090
+            (0x10DE, 0x2684, Some("Ada Lovelace")),
+            (0x10DE, 0x2503, Some("Ampere")),
+            (0x10DE, 0x1B80, Some("Pascal")),
+            (0x10DE, 0x15F7, Some("Pascal")),
+            (0x10DE, 0x13C2, Some("Maxwell")),
+            (0x10DE, 0x2B85, Some("Blackwell")),
+            // UHD 630, Iris Xe, Arc A770, Arc B580
+            (0x8086, 0x3E92, Some("Gen9.5")),
+            (0x8086, 0x9A49, Some("Xe-LP")),
+            (0x8086, 0x56A0, Some("Xe-HPG")),
+            (0x8086, 0xE20B, Some("Xe2")),
+            // virtio-gpu
+            (0x1AF4, 0x1050, None),
+        ];
+        for (vendor, device, arch) in cases {
+            assert_eq!(gpu_arch_for(vendor, device), arch, "{:04x}:{:04x}", vendor, device);
+        }
+    }
+
+    // A typo'd range can hide behind an earlier one, first match wins, so every row has to be
+    // the first match for at least one id
+    #[test]
+    fn every_row_is_reachable() {
+        for &(vendor, first, last, name) in GPUS {
+            assert!(first <= last, "{:04x}-{:04x}", first, last);
+            let reachable = (first..=last).any(|device| gpu_arch_for(vendor, device) == Some(name));
+            assert!(reachable, "{} {:04x}-{:04x}", name, first, last);
+        }
+        for (vendor, table) in [("AuthenticAMD", AMD_CPUS), ("GenuineIntel", INTEL_CPUS)] {
+            for &(family, first, last, name) in table {
+                assert!(first <= last, "{} {:x}-{:x}", name, first, last);
+                let reachable = (first..=last)
+                    .any(|model| cpu_arch_for(vendor, family, model, 0) == Some(name));
+                assert!(reachable, "{} {:x}-{:x}", name, first, last);
+            }
+        }
+    }
+
+    #[test]
+    fn gpu_archs_in_card_order() {
+        let drm = TempDir::new();
+        let card = |name: &str, vendor: &str, device: &str| {
+            drm.write(&format!("{}/device/vendor", name), format!("{}\n", vendor));
+            drm.write(&format!("{}/device/device", name), format!("{}\n", device));
+        };
+        card("card10", "0x8086", "0x56a0");
+        card("card1", "0x10de", "0x2684");
+        card("card2", "0x1af4", "0x1050");
+        card("card0", "0x1002", "0x747e");
+        // Connectors and render nodes aren't cards, a card without PCI ids is skipped
+        card("card0-DP-1", "0x1002", "0x747e");
+        card("renderD128", "0x1002", "0x747e");
+        drm.write("card3/dev", "226:3\n");
+
+        let archs = gpu_archs_in(drm.path());
+        assert_eq!(archs, [Some("RDNA 3"), Some("Ada Lovelace"), None, Some("Xe-HPG")]);
+        assert!(gpu_archs_in(&drm.join("missing")).is_empty());
+    }
+}
@@ -1,5 +1,9 @@
+#[cfg(feature = "art")]
 pub mod asciimodule;
 pub mod coremodules;
+pub mod edid;
 pub mod fontmodule;
 pub mod hardwaremodules;
+pub mod microarch;
+pub mod pacman;
 pub mod userspacemodules;
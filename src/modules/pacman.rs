@@ -0,0 +1,285 @@
+// Foreign packages for packages_detail: installed packages no sync database has, which is
+// the AUR and anything built by hand. It's the comparison pacman -Qm makes, done here by reading
+// the sync databases (gzipped tars) directly so there's no pacman to wait on. Databases that
+// can't be read (zstd, a half finished download) fall back to asking pacman -Qqm.
+// The count is cached until the local or a sync database changes.
+
+use std::collections::HashSet;
+use std::fs;
+use std::path::Path;
+use std::process::Command;
+use std::time::{Duration, UNIX_EPOCH};
+
+use crate::cache;
+use crate::helpers::command_output_limited;
+use crate::inflate;
+
+const LOCAL_DIR: &str = "/var/lib/pacman/local";
+const SYNC_DIR: &str = "/var/lib/pacman/sync";
+// Inflated size cap per sync database, extra is around 40MB
+const MAX_DB_BYTES: usize = 256 * 1024 * 1024;
+const PACMAN_MAX_BYTES: u64 = 1024 * 1024;
+const PACMAN_TIMEOUT: Duration = Duration::from_secs(3);
+
+// Whether there's a pacman database to count foreign packages in
+pub fn has_database() -> bool {
+    Path::new(LOCAL_DIR).is_dir()
+}
+
+// How many installed packages aren't in any sync database, None when there's no pacman
+// database to go by
+pub fn foreign_count() -> Option<usize> {
+    let stamp = stamp()?;
+    if let Some(count) = cache::get_cached_foreign(&stamp) {
+        return Some(count);
+    }
+    let count = match foreign_in(Path::new(LOCAL_DIR), Path::new(SYNC_DIR)) {
+        Some(count) => count,
+        None => pacman_foreign()?,
+    };
+    cache::cache_foreign(&stamp, count);
+    Some(count)
+}
+
+// The modification times of the local database and every sync database. Installing,
+// removing or upgrading changes the local dir, a -Sy replaces the .db files.
+fn stamp() -> Option<String> {
+    let mtime = |path: &Path| {
+        let modified = fs::metadata(path).and_then(|metadata| metadata.modified()).ok()?;
+        Some(modified.duration_since(UNIX_EPOCH).ok()?.as_secs())
+    };
+    let mut parts = vec![format!("local:{}", mtime(Path::new(LOCAL_DIR))?)];
+    for path in sync_databases(Path::new(SYNC_DIR)) {
+        let name = path.file_name()?.to_string_lossy().into_owned();
+        parts.push(format!("{}:{}", name, mtime(&path)?));
+    }
+    Some(parts.join(" "))
+}
+
+// Installed packages in the `local` database that no database in `sync` has, None when the
+// sync databases can't be read
+fn foreign_in(local: &Path, sync: &Path) -> Option<usize> {
+    let sync = sync_packages(sync)?;
+    Some(local_packages(local).iter().filter(|name| !sync.contains(*name)).count())
+}
+
+fn sync_databases(sync: &Path) -> Vec<std::path::PathBuf> {
+    let Ok(entries) = fs::read_dir(sync) else {
+        return Vec::new();
+    };
+    let mut paths: Vec<_> = entries
+        .filter_map(|entry| entry.ok())
+        .map(|entry| entry.path())
+        .filter(|path| path.extension().is_some_and(|extension| extension == "db"))
+        .collect();
+    paths.sort();
+    paths
+}
+
+// Installed package names, the %NAME% in each local/<pkg>/desc
+fn local_packages(local: &Path) -> Vec<String> {
+    let Ok(entries) = fs::read_dir(local) else {
+        return Vec::new();
+    };
+    entries
+        .filter_map(|entry| entry.ok())
+        .filter_map(|entry| fs::read_to_string(entry.path().join("desc")).ok())
+        .filter_map(|desc| {
+            let mut lines = desc.lines();
+            lines.find(|line| *line == "%NAME%")?;
+            lines.next().map(str::to_string)
+        })
+        .collect()
+}
+
+// Every package name in the sync databases, None when one of them can't be read.
+// A database holds a "<name>-<pkgver>-<pkgrel>/" directory per package, and neither pkgver
+// nor pkgrel can have a dash in it, so the name is everything before the last two dashes.
+fn sync_packages(sync: &Path) -> Option<HashSet<String>> {
+    let databases = sync_databases(sync);
+    if databases.is_empty() {
+        return None;
+    }
+    let mut names = HashSet::new();
+    for path in databases {
+        let archive = inflate::gunzip(&fs::read(path).ok()?, MAX_DB_BYTES)?;
+        for member in tar_names(&archive)? {
+            let directory = member.split('/').next().unwrap_or(&member);
+            if let Some(name) = directory.rsplitn(3, '-').nth(2) {
+                names.insert(name.to_string());
+            }
+        }
+    }
+    Some(names)
+}
+
+// The member names of a tar archive, with the ustar prefix. pax and GNU long name records are
+// skipped, repo-add's names fit the plain header. None when a header doesn't parse.
+fn tar_names(archive: &[u8]) -> Option<Vec<String>> {
+    let mut names = Vec::new();
+    let mut pos = 0;
+    while let Some(header) = archive.get(pos..pos + 512) {
+        // Two zero blocks end the archive, one is enough to stop at
+        if header[0] == 0 {
+            break;
+        }
+        let size = std::str::from_utf8(&header[124..136]).ok()?;
+        let size = usize::from_str_radix(size.trim_matches(['\0', ' ']), 8).ok()?;
+        if !matches!(header[156], b'x' | b'g' | b'L' | b'K') {
+            let field = |bytes: &[u8]| {
+                let end = bytes.iter().position(|&b| b == 0).unwrap_or(bytes.len());
+                String::from_utf8_lossy(&bytes[..end]).into_owned()
+            };
+            let (prefix, name) = (field(&header[345..500]), field(&header[..100]));
+            names.push(if prefix.is_empty() { name } else { format!("{}/{}", prefix, name) });
+        }
+        pos += 512 + size.div_ceil(512) * 512;
+    }
+    Some(names)
+}
+
+// pacman -Qqm lists the foreign packages one per line
+fn pacman_foreign() -> Option<usize> {
+    let output = command_output_limited(
+        Command::new("pacman").arg("-Qqm"),
+        PACMAN_MAX_BYTES,
+        PACMAN_TIMEOUT,
+    )?;
+    Some(output.split(|&b| b == b'\n').filter(|line| !line.is_empty()).count())
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::testutil::TempDir;
+
+    // A ustar archive of `members`, directories for names ending in '/'. Long names go in
+    // the prefix field the way repo-add's tar writes them.
+    fn tar(members: &[(&str, &str)]) -> Vec<u8> {
+        let mut archive = Vec::new();
+        for (path, content) in members {
+            let mut header = [0u8; 512];
+            let (prefix, name) = match path.len() {
+                0..=100 => ("", *path),
+                _ => path.rsplit_once('/').unwrap(),
+            };
+            header[..name.len()].copy_from_slice(name.as_bytes());
+            header[345..345 + prefix.len()].copy_from_slice(prefix.as_bytes());
+            header[124..136].copy_from_slice(format!("{:011o}\0", content.len()).as_bytes());
+            header[156] = if path.ends_with('/') { b'5' } else { b'0' };
+            header[257..263].copy_from_slice(b"ustar\0");
+            archive.extend_from_slice(&header);
+            archive.extend_from_slice(content.as_bytes());
+            archive.resize(archive.len().div_ceil(512) * 512, 0);
+        }
+        archive.resize(archive.len() + 1024, 0);
+        archive
+    }
+
+    // gzip with stored deflate blocks, gunzip doesn't check the CRC
+    fn gzip(data: &[u8]) -> Vec<u8> {
+        let mut out = vec![0x1f, 0x8b, 8, 0, 0, 0, 0, 0, 0, 3];
+        let chunks: Vec<&[u8]> = data.chunks(0xffff).collect();
+        for (i, chunk) in chunks.iter().enumerate() {
+            out.push(u8::from(i == chunks.len() - 1));
+            let length = chunk.len() as u16;
+            out.extend_from_slice(&length.to_le_bytes());
+            out.extend_from_slice(&(!length).to_le_bytes());
+            out.extend_from_slice(chunk);
+        }
+        out.extend_from_slice(&[0; 4]);
+        out.extend_from_slice(&(data.len() as u32).to_le_bytes());
+        out
+    }
+
+    // A sync database with a directory and desc per package, "name-pkgver-pkgrel"
+    fn database(packages: &[&str]) -> Vec<u8> {
+        let members: Vec<(String, String)> = packages
+            .iter()
+            .flat_map(|package| {
+                let desc = format!("%FILENAME%\n{}-x86_64.pkg.tar.zst\n", package);
+                [(format!("{}/", package), String::new()), (format!("{}/desc", package), desc)]
+            })
+            .collect();
+        let members: Vec<(&str, &str)> =
+            members.iter().map(|(path, content)| (path.as_str(), content.as_str())).collect();
+        gzip(&tar(&members))
+    }
+
+    // An installed package in local/<name>-<version>/desc
+    fn install(root: &TempDir, name: &str, version: &str) {
+        let desc = format!("%NAME%\n{}\n\n%VERSION%\n{}\n", name, version);
+        root.write(&format!("local/{}-{}/desc", name, version), &desc);
+    }
+
+    #[test]
+    fn tar_member_names() {
+        let long = format!("{}/desc", "a".repeat(120));
+        let members = [("linux-6.12.10.arch1-1/", ""), ("linux-6.12.10.arch1-1/desc", "x")];
+        let archive = tar(&[members[0], members[1], (&long, "")]);
+        let names = tar_names(&archive).unwrap();
+        assert_eq!(names, ["linux-6.12.10.arch1-1/", "linux-6.12.10.arch1-1/desc", long.as_str()]);
+
+        // pax headers aren't members
+        let mut pax = tar(&[("PaxHeaders/x", "30 mtime=1736510400.0\n"), ("zsh-5.9-5/", "")]);
+        pax[156] = b'x';
+        assert_eq!(tar_names(&pax).unwrap(), ["zsh-5.9-5/"]);
+        // Empty, cut short in a header, and a size that isn't octal
+        assert_eq!(tar_names(&[]).unwrap(), Vec::<String>::new());
+        assert_eq!(tar_names(&archive[..700]).unwrap(), ["linux-6.12.10.arch1-1/"]);
+        let mut bad = tar(&[("zsh-5.9-5/", "")]);
+        bad[124..136].copy_from_slice(b"0000000009z\0");
+        assert_eq!(tar_names(&bad), None);
+    }
+
+    #[test]
+    fn foreign_packages_in_a_synthetic_tree() {
+        let root = TempDir::new();
+        root.write("sync/core.db", database(&["linux-6.12.10.arch1-1", "zsh-5.9-5"]));
+        let extra = database(&["firefox-134.0-1", "python-pyqt6-sip-13.9.1-1", "git-2.48.1-1"]);
+        root.write("sync/extra.db", extra);
+        // Signatures and file lists aren't databases
+        root.write("sync/extra.db.sig", "not a database");
+        root.write("sync/core.files", "not a database");
+
+        for (name, version) in [
+            ("linux", "6.12.10.arch1-1"),
+            ("zsh", "5.9-5"),
+            ("firefox", "134.0-1"),
+            ("python-pyqt6-sip", "13.9.1-1"),
+            // From the AUR
+            ("yay", "12.4.2-1"),
+            ("visual-studio-code-bin", "1.97.0-1"),
+            ("python-pyqt6", "6.8.0-1"),
+        ] {
+            install(&root, name, version);
+        }
+        // Half installed, nothing to go by
+        root.write("local/broken-1.0-1/files", "");
+        root.write("local/ALPM_DB_VERSION", "9\n");
+        assert_eq!(foreign_in(&root.join("local"), &root.join("sync")), Some(3));
+
+        let names = sync_packages(&root.join("sync")).unwrap();
+        assert!(names.contains("python-pyqt6-sip") && names.contains("git"));
+        assert_eq!(names.len(), 5);
+    }
+
+    #[test]
+    fn unreadable_sync_databases() {
+        let root = TempDir::new();
+        install(&root, "yay", "12.4.2-1");
+        // No sync databases at all, pacman has to be asked
+        assert_eq!(foreign_in(&root.join("local"), &root.join("sync")), None);
+        // A zstd compressed one, or a download cut short
+        root.write("sync/core.db", "\x28\u{b5}\x2f\u{fd} zstd");
+        assert_eq!(foreign_in(&root.join("local"), &root.join("sync")), None);
+        let mut cut = database(&["zsh-5.9-5"]);
+        cut.truncate(cut.len() / 2);
+        root.write("sync/core.db", cut);
+        assert_eq!(foreign_in(&root.join("local"), &root.join("sync")), None);
+        // Nothing installed is nothing foreign
+        root.write("sync/core.db", database(&["zsh-5.9-5"]));
+        assert_eq!(foreign_in(&root.join("nowhere"), &root.join("sync")), Some(0));
+        assert_eq!(foreign_in(&root.join("local"), &root.join("sync")), Some(1));
+    }
+}
@@ -4,10 +4,21 @@ use std::env;
 use std::fs;
 use std::path::Path;
 use std::process::Command;
+use std::sync::OnceLock;
+use std::time::Duration;
 
 use memchr::{memchr_iter, memmem};
 
-use crate::helpers::{capitalize, get_dms_theme, get_noctalia_scheme};
+use crate::cache;
+use crate::dateformat;
+use crate::helpers::{
+    ProcessEntry, Precision, capitalize, command_output_limited, file_stamp, find_process,
+    format_duration, get_dms_theme, get_noctalia_scheme, ini_value, local_time, open_drm_card,
+    process_age_seconds, username,
+};
+use crate::modules::coremodules::{ostree_status, uptime_seconds};
+use crate::modules::pacman;
+use crate::template::Fields;
 
 /// Get the active shell with version.
 pub fn shell() -> String {
@@ -21,51 +32,234 @@ pub fn shell() -> String {
         _ => return "unknown".to_string(),
     };
 
-    // Try to get version by running shell --version
-    let version = Command::new(&shell_path)
-        .arg("--version")
-        .output()
-        .ok()
-        .and_then(|output| {
-            // Find first line directly in bytes to avoid full UTF-8 conversion
-            let stdout = &output.stdout;
-            let first_line_end = stdout.iter().position(|&b| b == b'\n').unwrap_or(stdout.len());
-            let first_line = std::str::from_utf8(&stdout[..first_line_end]).ok()?;
-
-            // Extract version number (e.g., "5.2.26" from "bash 5.2.26(1)-release")
-            first_line
-                .split_ascii_whitespace()
-                .find(|word| word.as_bytes().first().map_or(false, |b| b.is_ascii_digit()))
-                .map(|v| {
-                    // Clean up version string - find first ( or -
-                    let end = v.find(|c: char| c == '(' || c == '-').unwrap_or(v.len());
-                    v[..end].to_string()
-                })
-        });
+    // Asking is a subprocess, what it said is cached until the shell's binary changes
+    let stamp = file_stamp(Path::new(&shell_path));
+    if let Some(stamp) = &stamp
+        && let Some(cached) = cache::get_cached_shell(stamp)
+    {
+        return cached;
+    }
+
+    // Try to get version by running the shell. Output is capped and the child gets
+    // a short timeout since $SHELL can point at anything.
+    let args = SHELL_VERSION_ARGS
+        .iter()
+        .find(|(name, _)| *name == shell_name)
+        .map_or(&["--version"][..], |(_, args)| *args);
+    let version = if args.is_empty() {
+        None
+    } else {
+        command_output_limited(
+            Command::new(&shell_path).args(args),
+            SHELL_VERSION_MAX_BYTES,
+            SHELL_VERSION_TIMEOUT,
+        )
+        .and_then(|stdout| parse_shell_version(shell_name, &String::from_utf8_lossy(&stdout)))
+    };
 
-    match version {
+    let shell = match version {
         Some(v) => format!("{} {}", capitalize(shell_name), v),
         None => capitalize(shell_name),
+    };
+    if let Some(stamp) = &stamp {
+        cache::cache_shell(stamp, &shell);
+    }
+    shell
+}
+
+const SHELL_VERSION_MAX_BYTES: u64 = 4096;
+const SHELL_VERSION_TIMEOUT: Duration = Duration::from_millis(500);
+
+// Shells that don't answer "--version" sensibly. Empty args means don't bother asking.
+const SHELL_VERSION_ARGS: &[(&str, &[&str])] = &[
+    ("elvish", &["-version"]),
+    ("ksh", &["-c", "echo $KSH_VERSION"]),
+    ("mksh", &["-c", "echo $KSH_VERSION"]),
+    ("dash", &[]),
+    ("sh", &[]),
+];
+
+// Pull a version number out of a shell's version banner.
+// Looks at the first three lines for the first word with a digit-dot-digit in it, since some
+// shells (pwsh, elvish) start with a banner line that has no version at all.
+fn parse_shell_version(shell_name: &str, output: &str) -> Option<String> {
+    // mksh versions look like "R59c", no dots
+    if shell_name == "mksh" {
+        let mut words = output.lines().take(3).flat_map(str::split_ascii_whitespace);
+        return words.find_map(|word| {
+            let rest = word.strip_prefix('R')?;
+            rest.starts_with(|c: char| c.is_ascii_digit()).then(|| word.to_string())
+        });
+    }
+
+    parse_version(output)
+}
+
+// The first dotted version number in the first three lines of a --version banner
+fn parse_version(output: &str) -> Option<String> {
+    let word = output
+        .lines()
+        .take(3)
+        .flat_map(str::split_ascii_whitespace)
+        .find(|word| has_dotted_number(word))?;
+
+    // ksh93 reports "93u+m/1.0.8", the useful part is after the slash
+    let word = word.rsplit('/').next().unwrap_or(word);
+
+    // Trim "v0.19.2" / "5.2.26(1)-release" / "0.21.0+official" down to the number
+    let start = word.find(|c: char| c.is_ascii_digit())?;
+    let word = &word[start..];
+    let end = word
+        .find(|c: char| !c.is_ascii_digit() && c != '.')
+        .unwrap_or(word.len());
+    let version = word[..end].trim_end_matches('.');
+
+    (!version.is_empty()).then(|| version.to_string())
+}
+
+// True if the word contains a digit followed by '.' and another digit
+fn has_dotted_number(word: &str) -> bool {
+    word.as_bytes()
+        .windows(3)
+        .any(|w| w[0].is_ascii_digit() && w[1] == b'.' && w[2].is_ascii_digit())
+}
+
+// Extra template fields for the shell line: name, version
+pub fn shell_fields(value: &str) -> Fields {
+    let (name, version) = value.split_once(' ').unwrap_or((value, ""));
+    vec![("name", name.to_string()), ("version", version.to_string())]
+}
+
+// A prompt framework or plugin manager we can spot from env vars and config files alone.
+// Matches if any env var contains its substring ("" = just set), any file under $HOME exists,
+// or the fish_plugins list mentions it.
+struct PromptDetector {
+    name: &'static str,
+    env: &'static [(&'static str, &'static str)],
+    files: &'static [&'static str],
+    fish_plugin: Option<&'static str>,
+}
+
+// Prompt frameworks, first match wins
+const PROMPT_FRAMEWORKS: &[PromptDetector] = &[
+    PromptDetector {
+        name: "Starship",
+        env: &[("STARSHIP_SHELL", "")],
+        files: &[".config/starship.toml"],
+        fish_plugin: None,
+    },
+    PromptDetector {
+        name: "Powerlevel10k",
+        env: &[("POWERLEVEL9K_MODE", "")],
+        files: &[".p10k.zsh"],
+        fish_plugin: None,
+    },
+    PromptDetector {
+        name: "Oh My Posh",
+        env: &[("POSH_THEME", ""), ("POSH_PID", "")],
+        files: &[],
+        fish_plugin: None,
+    },
+    PromptDetector {
+        name: "Tide",
+        env: &[],
+        files: &[],
+        fish_plugin: Some("ilancosman/tide"),
+    },
+    PromptDetector {
+        name: "Pure",
+        env: &[],
+        files: &[".zsh/pure"],
+        fish_plugin: Some("pure-fish/pure"),
+    },
+];
+
+// Plugin managers, first match wins
+const PLUGIN_MANAGERS: &[PromptDetector] = &[
+    PromptDetector {
+        name: "oh-my-zsh",
+        env: &[("ZSH", "oh-my-zsh")],
+        files: &[".oh-my-zsh"],
+        fish_plugin: None,
+    },
+    PromptDetector {
+        name: "zinit",
+        env: &[("ZINIT_HOME", "")],
+        files: &[".local/share/zinit", ".zinit"],
+        fish_plugin: None,
+    },
+    PromptDetector {
+        name: "fisher",
+        env: &[],
+        files: &[".config/fish/functions/fisher.fish"],
+        fish_plugin: Some("jorgebucaran/fisher"),
+    },
+];
+
+impl PromptDetector {
+    fn matches(
+        &self,
+        home: &Path,
+        fish_plugins: &str,
+        var: &dyn Fn(&str) -> Option<String>,
+    ) -> bool {
+        self.env.iter().any(|(name, needle)| {
+            var(name).is_some_and(|value| !value.is_empty() && value.contains(needle))
+        }) || self.files.iter().any(|file| home.join(file).exists())
+            || self.fish_plugin.is_some_and(|plugin| fish_plugins.contains(plugin))
+    }
+}
+
+// Get the prompt framework and plugin manager, e.g. "Starship | oh-my-zsh".
+// Only env vars and file checks, no subprocesses. Empty string if nothing recognized.
+pub fn prompt() -> String {
+    let home = env::var_os("HOME").map(std::path::PathBuf::from).unwrap_or_default();
+    prompt_in(&home, &|name| env::var(name).ok())
+}
+
+fn prompt_in(home: &Path, var: &dyn Fn(&str) -> Option<String>) -> String {
+    let fish_plugins = fs::read_to_string(home.join(".config/fish/fish_plugins"))
+        .unwrap_or_default()
+        .to_lowercase();
+
+    let find = |detectors: &[PromptDetector]| {
+        detectors
+            .iter()
+            .find(|detector| detector.matches(home, &fish_plugins, var))
+            .map(|detector| detector.name)
+    };
+
+    match (find(PROMPT_FRAMEWORKS), find(PLUGIN_MANAGERS)) {
+        (Some(prompt), Some(plugins)) => format!("{} | {}", prompt, plugins),
+        (Some(name), None) | (None, Some(name)) => name.to_string(),
+        (None, None) => String::new(),
     }
 }
 
 // Get the total number of installed packages.
 // Supports pacman aka Arch, hopefully supports debian and fedora but idk, im not setting up a vm to test sorry
-pub fn packages() -> String {
+// With `detail` the pacman count is split into repo and foreign (AUR) packages.
+pub fn packages(detail: bool) -> String {
     let mut counts: Vec<String> = Vec::with_capacity(4);
 
     // Pacman - count directories in /var/lib/pacman/local/
     if let Ok(entries) = fs::read_dir("/var/lib/pacman/local") {
         let count = entries.filter(|e| e.is_ok()).count();
-        if count > 0 {
-            counts.push(format!("󰮯 {}", count));
+        let foreign = if detail { pacman::foreign_count() } else { None };
+        match foreign {
+            Some(foreign) if foreign > 0 && count > 0 => counts.push(format!(
+                "󰮯 {} + {} AUR",
+                count.saturating_sub(foreign),
+                foreign
+            )),
+            _ if count > 0 => counts.push(format!("󰮯 {}", count)),
+            _ => {}
         }
     }
 
-    // dpkg (Debian/Ubuntu) - count occurrences of status line using SIMD-accelerated search
+    // dpkg (Debian/Ubuntu)
     if let Ok(content) = fs::read("/var/lib/dpkg/status") {
-        const NEEDLE: &[u8] = b"\nStatus: install ok installed\n";
-        let count = memmem::find_iter(&content, NEEDLE).count();
+        let count = count_dpkg_installed(&content);
         if count > 0 {
             counts.push(format!(" {}", count));
         }
@@ -78,8 +272,15 @@ pub fn packages() -> String {
         if let Ok(output) = Command::new("rpm").arg("-qa").output() {
             // Count newlines using SIMD-accelerated memchr
             let count = memchr_iter(b'\n', &output.stdout).count();
-            if count > 0 {
-                counts.push(format!(" {}", count));
+            // On rpm-ostree systems split the base image from what the user layered on top
+            match ostree_status() {
+                Some(status) if count > 0 => counts.push(format!(
+                    " {} base + {} layered",
+                    count.saturating_sub(status.layered),
+                    status.layered
+                )),
+                _ if count > 0 => counts.push(format!(" {}", count)),
+                _ => {}
             }
         }
     }
@@ -132,93 +333,327 @@ pub fn packages() -> String {
     }
 }
 
+// dpkg package states that count as installed, the third word of a Status: line.
+// unpacked and half-configured are packages caught mid-upgrade (their files are there),
+// triggers-* are installed and configured with triggers still to run. config-files
+// (removed, config kept), half-installed and not-installed don't count.
+const DPKG_INSTALLED_STATES: &[&[u8]] = &[
+    b"installed",
+    b"unpacked",
+    b"half-configured",
+    b"triggers-awaited",
+    b"triggers-pending",
+];
+
+// Count the installed packages in /var/lib/dpkg/status. Stanzas are split on blank lines
+// with a byte scan, and each one's "Status: want flag state" field is checked.
+fn count_dpkg_installed(content: &[u8]) -> usize {
+    let mut count = 0;
+    let mut start = 0;
+    let boundaries = memmem::find_iter(content, b"\n\n").chain(std::iter::once(content.len()));
+    for end in boundaries {
+        let stanza = &content[start..end];
+        start = (end + 2).min(content.len());
+        let state = stanza
+            .split(|&b| b == b'\n')
+            .find_map(|line| line.strip_prefix(b"Status:"))
+            .and_then(|status| {
+                status.split(u8::is_ascii_whitespace).filter(|word| !word.is_empty()).nth(2)
+            });
+        if state.is_some_and(|state| DPKG_INSTALLED_STATES.contains(&state)) {
+            count += 1;
+        }
+    }
+    count
+}
+
 // Get the Window Manager (using /proc instead of subprocess)
 pub fn wm() -> String {
     // Check environment variables first - much faster than /proc scan
-    if let Ok(desktop) = env::var("XDG_CURRENT_DESKTOP") {
-        // Map common desktop values to their WM names
-        let wm = match desktop.to_lowercase().as_str() {
-            "hyprland" => "Hyprland",
-            "sway" => "Sway",
-            "kde" | "plasma" => "KWin",
-            "gnome" => "Mutter",
-            "xfce" => "Xfwm4",
-            "i3" => "i3",
-            "bspwm" => "bspwm",
-            "awesome" => "Awesome",
-            "qtile" => "Qtile",
-            "niri" => "Niri",
-            _ => return desktop,
-        };
+    let desktop = env::var("XDG_CURRENT_DESKTOP").ok();
+    if let Some(wm) = desktop.as_deref().and_then(wm_for_desktops) {
         return wm.to_string();
     }
 
+    // Unknown or unset desktop: scan /proc for WM processes, that finds the actual WM binary
+    if let Some((_, wm_display)) = find_wm_process() {
+        return wm_display.to_string();
+    }
+
+    // Last resort: show whatever the session calls itself
+    if let Some(desktop) = desktop.filter(|d| !d.is_empty()) {
+        return desktop;
+    }
     if let Ok(session) = env::var("DESKTOP_SESSION") {
         return capitalize(&session);
     }
 
-    // Fallback: scan /proc for WM processes
-    // Known WMs to search for (search term -> display name)
-    // Pre-compiled searchers for SIMD-accelerated matching
-    let wm_list: &[(&[u8], &str)] = &[
-        (b"mutter", "Mutter"),
-        (b"kwin", "KWin"),
-        (b"sway", "Sway"),
-        (b"hyprland", "Hyprland"),
-        (b"Hyprland", "Hyprland"),
-        (b"river", "River"),
-        (b"wayfire", "Wayfire"),
-        (b"labwc", "LabWC"),
-        (b"dwl", "dwl"),
-        (b"niri", "Niri"),
-        (b"openbox", "Openbox"),
-        (b"i3", "i3"),
-        (b"bspwm", "bspwm"),
-        (b"dwm", "dwm"),
-        (b"awesome", "Awesome"),
-        (b"xfwm4", "Xfwm4"),
-        (b"marco", "Marco"),
-        (b"metacity", "Metacity"),
-        (b"compiz", "Compiz"),
-        (b"enlightenment", "Enlightenment"),
-        (b"fluxbox", "Fluxbox"),
-        (b"icewm", "IceWM"),
-        (b"xmonad", "XMonad"),
-        (b"qtile", "Qtile"),
-        (b"herbstluftwm", "herbstluftwm"),
-        (b"weston", "Weston"),
-        (b"cage", "Cage"),
-        (b"gamescope", "Gamescope"),
-    ];
-
-    // Read /proc directly instead of spawning ps | grep (saves 0.3ish ms)
-    let proc_path = Path::new("/proc");
-    if let Ok(entries) = fs::read_dir(proc_path) {
-        for entry in entries.flatten() {
-            // Fast check: first byte must be a digit (PID directories)
-            let name = entry.file_name();
-            let name_bytes = name.as_encoded_bytes();
-            if name_bytes.is_empty() || !name_bytes[0].is_ascii_digit() {
-                continue;
-            }
+    "unknown".to_string()
+}
 
-            let cmdline_path = entry.path().join("cmdline");
-            // Read as bytes to avoid UTF-8 conversion overhead
-            if let Ok(cmdline) = fs::read(&cmdline_path) {
-                for (wm_search, wm_display) in wm_list {
-                    if memmem::find(&cmdline, wm_search).is_some() {
-                        return wm_display.to_string();
-                    }
-                }
-            }
+// Backend detail for KWin and Mutter (wm_detail), e.g. "wayland, card1" or "wayland, virtual".
+// KWin says which one it is in its binary name, Mutter runs inside gnome-shell.
+// The card is the DRM node the compositor holds open, left out when it can't be read.
+pub fn wm_backend(wm: &str) -> Option<String> {
+    let names = backend_processes(wm)?;
+    let process = find_process(|process| {
+        names.contains(&process_name(&process.cmdline)).then(|| process.clone())
+    });
+    let session_type = env::var("XDG_SESSION_TYPE").ok();
+    wm_backend_in(wm, process.as_ref(), Path::new("/proc"), session_type)
+}
+
+// The processes a WM with backend detail runs as
+fn backend_processes(wm: &str) -> Option<&'static [&'static [u8]]> {
+    match wm {
+        "KWin" => Some(&[b"kwin_wayland", b"kwin_x11"]),
+        "Mutter" => Some(&[b"gnome-shell", b"mutter"]),
+        _ => None,
+    }
+}
+
+// wm_backend with the WM's process found (None when it's out of sight), `proc` standing in
+// for /proc and XDG_SESSION_TYPE's value
+fn wm_backend_in(
+    wm: &str,
+    process: Option<&ProcessEntry>,
+    proc: &Path,
+    session_type: Option<String>,
+) -> Option<String> {
+    backend_processes(wm)?;
+    let env_session_type = || session_type.filter(|kind| !kind.is_empty());
+    let Some(process) = process else {
+        // gnome-shell not visible (another user's session, a sandbox), the session type will do
+        return if wm == "Mutter" { env_session_type() } else { None };
+    };
+    let name = process_name(&process.cmdline);
+    let has_arg = |arg: &[u8]| process.cmdline.split(|&b| b == 0).any(|a| a == arg);
+
+    let session_type = if name == b"kwin_wayland" || has_arg(b"--wayland") {
+        "wayland".to_string()
+    } else if name == b"kwin_x11" || has_arg(b"--x11") {
+        "x11".to_string()
+    } else {
+        env_session_type()?
+    };
+    // Nested/headless sessions don't drive a card
+    let output = if has_arg(b"--virtual") || has_arg(b"--headless") {
+        Some("virtual".to_string())
+    } else {
+        open_drm_card(proc, process.pid)
+    };
+
+    Some(match output {
+        Some(output) => format!("{}, {}", session_type, output),
+        None => session_type,
+    })
+}
+
+// XDG_CURRENT_DESKTOP is a colon-separated list, most specific first ("ubuntu:GNOME",
+// "budgie:GNOME"), so use the first component we know
+fn wm_for_desktops(desktops: &str) -> Option<&'static str> {
+    desktops.split(':').find_map(wm_for_desktop)
+}
+
+// Map one XDG_CURRENT_DESKTOP component to its WM name.
+// Desktops without a fixed WM (LXQt runs Openbox, KWin, labwc...) are left to the /proc scan.
+fn wm_for_desktop(desktop: &str) -> Option<&'static str> {
+    let wm = match desktop.to_lowercase().as_str() {
+        "hyprland" => "Hyprland",
+        "sway" => "Sway",
+        "kde" | "plasma" => "KWin",
+        "gnome" => "Mutter",
+        "xfce" => "Xfwm4",
+        "i3" => "i3",
+        "bspwm" => "bspwm",
+        "awesome" => "Awesome",
+        "qtile" => "Qtile",
+        "niri" => "Niri",
+        "unity" => "Compiz",
+        "x-cinnamon" | "cinnamon" => "Muffin",
+        "budgie" => "Budgie WM",
+        "mate" => "Marco",
+        _ => return None,
+    };
+    Some(wm)
+}
+
+// How a WM table entry is compared against a process's executable name
+enum NameMatch {
+    // Whole basename, e.g. "sway" but not "swaybg"
+    Exact(&'static [u8]),
+    // Basename starts with this, for suffixed binaries like "kwin_wayland" or "xmonad-x86_64-linux"
+    Prefix(&'static [u8]),
+}
+
+impl NameMatch {
+    fn matches(&self, name: &[u8]) -> bool {
+        match self {
+            NameMatch::Exact(expected) => name == *expected,
+            NameMatch::Prefix(prefix) => name.starts_with(prefix),
         }
     }
+}
 
-    "unknown".to_string()
+// Known WMs to search for in the process list (executable name -> display name)
+const WM_LIST: &[(NameMatch, &str)] = &[
+    (NameMatch::Exact(b"mutter"), "Mutter"),
+    (NameMatch::Prefix(b"kwin"), "KWin"),
+    (NameMatch::Exact(b"sway"), "Sway"),
+    (NameMatch::Exact(b"swayfx"), "SwayFX"),
+    (NameMatch::Exact(b"hyprland"), "Hyprland"),
+    (NameMatch::Exact(b"Hyprland"), "Hyprland"),
+    (NameMatch::Exact(b"river"), "River"),
+    (NameMatch::Exact(b"wayfire"), "Wayfire"),
+    (NameMatch::Exact(b"labwc"), "LabWC"),
+    (NameMatch::Exact(b"dwl"), "dwl"),
+    (NameMatch::Exact(b"niri"), "Niri"),
+    (NameMatch::Exact(b"hikari"), "Hikari"),
+    (NameMatch::Exact(b"cosmic-comp"), "COSMIC"),
+    (NameMatch::Exact(b"miracle-wm"), "Miracle WM"),
+    (NameMatch::Exact(b"jay"), "Jay"),
+    (NameMatch::Exact(b"openbox"), "Openbox"),
+    (NameMatch::Exact(b"i3"), "i3"),
+    (NameMatch::Exact(b"bspwm"), "bspwm"),
+    (NameMatch::Exact(b"dwm"), "dwm"),
+    (NameMatch::Exact(b"awesome"), "Awesome"),
+    (NameMatch::Exact(b"xfwm4"), "Xfwm4"),
+    (NameMatch::Exact(b"marco"), "Marco"),
+    (NameMatch::Exact(b"metacity"), "Metacity"),
+    (NameMatch::Exact(b"compiz"), "Compiz"),
+    (NameMatch::Prefix(b"enlightenment"), "Enlightenment"),
+    (NameMatch::Exact(b"fluxbox"), "Fluxbox"),
+    (NameMatch::Prefix(b"icewm"), "IceWM"),
+    (NameMatch::Prefix(b"xmonad"), "XMonad"),
+    (NameMatch::Exact(b"qtile"), "Qtile"),
+    (NameMatch::Exact(b"herbstluftwm"), "herbstluftwm"),
+    (NameMatch::Exact(b"weston"), "Weston"),
+    (NameMatch::Exact(b"cage"), "Cage"),
+    (NameMatch::Prefix(b"gamescope"), "Gamescope"),
+];
+
+// Project pages for the WM hyperlink, by display name
+const WM_URLS: &[(&str, &str)] = &[
+    ("Awesome", "https://awesomewm.org"),
+    ("bspwm", "https://github.com/baskerville/bspwm"),
+    ("COSMIC", "https://system76.com/cosmic"),
+    ("dwm", "https://dwm.suckless.org"),
+    ("Hyprland", "https://hyprland.org"),
+    ("i3", "https://i3wm.org"),
+    ("KWin", "https://invent.kde.org/plasma/kwin"),
+    ("LabWC", "https://labwc.github.io"),
+    ("Mutter", "https://gitlab.gnome.org/GNOME/mutter"),
+    ("Niri", "https://github.com/YaLTeR/niri"),
+    ("Openbox", "http://openbox.org"),
+    ("Qtile", "https://qtile.org"),
+    ("River", "https://codeberg.org/river/river"),
+    ("Sway", "https://swaywm.org"),
+    ("SwayFX", "https://github.com/WillPower3309/swayfx"),
+    ("Wayfire", "https://wayfire.org"),
+    ("XMonad", "https://xmonad.org"),
+];
+
+// Project page for the WM hyperlink
+pub fn wm_url(wm: &str) -> Option<&'static str> {
+    WM_URLS.iter().find(|(name, _)| *name == wm).map(|(_, url)| *url)
+}
+
+// Executable name of a process from its raw cmdline (NUL-separated args).
+// Python WMs (qtile) show up as "python3 /usr/bin/qtile", so look past the interpreter.
+fn process_name(cmdline: &[u8]) -> &[u8] {
+    let mut args = cmdline.split(|&b| b == 0).filter(|arg| !arg.is_empty());
+    let first = args.next().unwrap_or_default();
+    let name = first.rsplit(|&b| b == b'/').next().unwrap_or(first);
+
+    if name.starts_with(b"python")
+        && let Some(script) = args.find(|arg| !arg.starts_with(b"-"))
+    {
+        return script.rsplit(|&b| b == b'/').next().unwrap_or(script);
+    }
+    name
+}
+
+// Find the first running process that looks like a WM, returns (pid, display name)
+fn find_wm_process() -> Option<(u32, &'static str)> {
+    find_process(|process| Some((process.pid, wm_for_cmdline(&process.cmdline)?)))
+}
+
+// The WM a process is, by its executable name
+fn wm_for_cmdline(cmdline: &[u8]) -> Option<&'static str> {
+    let name = process_name(cmdline);
+    WM_LIST
+        .iter()
+        .find(|(wm_match, _)| wm_match.matches(name))
+        .map(|&(_, wm_display)| wm_display)
+}
+
+// Get how long the graphical session has been running, from the WM process start time.
+// Returns empty string when no WM process is found (e.g. TTY sessions)
+pub fn session() -> String {
+    let Some((pid, _)) = find_wm_process() else {
+        return String::new();
+    };
+    let Ok(stat) = fs::read_to_string(format!("/proc/{}/stat", pid)) else {
+        return String::new();
+    };
+    let Some(uptime) = uptime_seconds() else {
+        return String::new();
+    };
+
+    // SAFETY: sysconf has no preconditions, it just returns a config value or -1
+    let ticks_per_second = unsafe { libc::sysconf(libc::_SC_CLK_TCK) };
+    if ticks_per_second <= 0 {
+        return String::new();
+    }
+
+    process_age_seconds(&stat, uptime, ticks_per_second as u64)
+        .map(|seconds| format_duration(seconds, Precision::Minutes))
+        .unwrap_or_default()
+}
+
+// Where steamos-session-select leaves the session SDDM logs into next
+const STEAMOS_SESSION_FILE: &str = "/etc/sddm.conf.d/zz-steamos-autologin.conf";
+
+// Gaming Mode or Desktop Mode on SteamOS, and distros with the same session switcher.
+// Under gamescope it's Gaming Mode, under any other WM Desktop Mode. Without a WM (over ssh)
+// it's the session steamos-session-select picked last. Empty everywhere else.
+pub fn steamos_mode(os_name: &str, wm: &str) -> String {
+    mode_from(os_name, wm, fs::read_to_string(STEAMOS_SESSION_FILE).ok())
+}
+
+// steamos_mode with the session file's content, None when there's no such file
+fn mode_from(os_name: &str, wm: &str, session_file: Option<String>) -> String {
+    if session_file.is_none() && !os_name.to_lowercase().contains("steamos") {
+        return String::new();
+    }
+    match wm {
+        "Gamescope" => return "Gaming Mode".to_string(),
+        "unknown" | "" => {}
+        _ => return "Desktop Mode".to_string(),
+    }
+    let session = session_file.as_deref().and_then(|file| ini_value(file, "Autologin", "Session"));
+    match session {
+        Some(session) if session.starts_with("gamescope") => "Gaming Mode".to_string(),
+        Some(_) => "Desktop Mode".to_string(),
+        None => "unknown".to_string(),
+    }
+}
+
+// terminal from the config, it skips detection
+static TERMINAL_OVERRIDE: OnceLock<String> = OnceLock::new();
+
+// Set the terminal config override, call before collecting modules
+pub fn set_terminal_override(terminal: Option<String>) {
+    if let Some(terminal) = terminal {
+        let _ = TERMINAL_OVERRIDE.set(terminal);
+    }
 }
 
 // Get the active terminal
 pub fn terminal() -> String {
+    if let Some(terminal) = TERMINAL_OVERRIDE.get() {
+        return terminal.clone();
+    }
     // Check for specific terminal environment variables first
     if env::var("KITTY_PID").is_ok() {
         return "Kitty".to_string();
@@ -241,6 +676,68 @@ pub fn terminal() -> String {
     capitalize(name)
 }
 
+// Terminals that only tell their version when asked: the Terminal line's name and the binary
+const TERMINAL_BINARIES: &[(&str, &str)] = &[
+    ("Kitty", "kitty"),
+    ("Alacritty", "alacritty"),
+    ("Foot", "foot"),
+    ("Ghostty", "ghostty"),
+    ("Gnome Terminal", "gnome-terminal"),
+];
+
+const TERMINAL_VERSION_MAX_BYTES: u64 = 4096;
+const TERMINAL_VERSION_TIMEOUT: Duration = Duration::from_millis(500);
+
+// The binary `terminal` is asked for its version with
+pub fn terminal_binary(terminal: &str) -> Option<&'static str> {
+    TERMINAL_BINARIES.iter().find(|(name, _)| *name == terminal).map(|(_, binary)| *binary)
+}
+
+// The terminal's version. Konsole says it in KONSOLE_VERSION and terminals that set
+// TERM_PROGRAM in TERM_PROGRAM_VERSION, the ones in TERMINAL_BINARIES are asked with
+// --version. That's a subprocess (kitty takes a while to start), what it said is cached
+// until the binary changes.
+pub fn terminal_version(terminal: &str) -> Option<String> {
+    if terminal == "Konsole" {
+        return konsole_version(&env::var("KONSOLE_VERSION").ok()?);
+    }
+    let from_program =
+        env::var("TERM_PROGRAM").is_ok_and(|program| capitalize(&program) == terminal);
+    if from_program
+        && let Ok(version) = env::var("TERM_PROGRAM_VERSION")
+        && !version.is_empty()
+    {
+        return Some(version);
+    }
+
+    let binary = terminal_binary(terminal)?;
+    let path = env::split_paths(&env::var_os("PATH")?)
+        .map(|dir| dir.join(binary))
+        .find(|path| path.is_file())?;
+    let stamp = file_stamp(&path);
+    if let Some(stamp) = &stamp
+        && let Some(cached) = cache::get_cached_terminal_version(stamp)
+    {
+        return Some(cached);
+    }
+    let output = command_output_limited(
+        Command::new(&path).arg("--version"),
+        TERMINAL_VERSION_MAX_BYTES,
+        TERMINAL_VERSION_TIMEOUT,
+    )?;
+    let version = parse_version(&String::from_utf8_lossy(&output))?;
+    if let Some(stamp) = &stamp {
+        cache::cache_terminal_version(stamp, &version);
+    }
+    Some(version)
+}
+
+// KONSOLE_VERSION packs the release as yymmpp, 240202 is 24.02.2
+fn konsole_version(packed: &str) -> Option<String> {
+    let number: u32 = packed.trim().parse().ok()?;
+    Some(format!("{}.{:02}.{}", number / 10000, number / 100 % 100, number % 100))
+}
+
 // Get the active UI/Shell, i dont know what to call this shit because i already used shell for the terminal shell
 pub fn ui() -> String {
     // Fast path: check env vars for common desktop shells
@@ -253,52 +750,238 @@ pub fn ui() -> String {
     }
 
     // Scan /proc for custom shells (noctalia, dms, waybar) - i really dont want to do this but i cant think of another way rn
-    let proc_path = Path::new("/proc");
-    if let Ok(entries) = fs::read_dir(proc_path) {
-        for entry in entries.flatten() {
-            // Fast check: first byte must be a digit (PID directories)
-            let name = entry.file_name();
-            let name_bytes = name.as_encoded_bytes();
-            if name_bytes.is_empty() || !name_bytes[0].is_ascii_digit() {
-                continue;
+    let Some(shell) = find_process(|process| ui_for_cmdline(&process.cmdline)) else {
+        return "unknown".to_string();
+    };
+    match shell {
+        "Noctalia Shell" => match get_noctalia_scheme() {
+            Some(scheme) => format!("{} |  {}", shell, capitalize(&scheme)),
+            None => shell.to_string(),
+        },
+        "DMS" => match get_dms_theme() {
+            Some(theme) => {
+                let formatted_theme = theme
+                    .replace("cat-", "Catppuccin (")
+                    + if theme.starts_with("cat-") { ")" } else { "" };
+                format!("{} |  {}", shell, capitalize(&formatted_theme))
             }
+            None => shell.to_string(),
+        },
+        _ => shell.to_string(),
+    }
+}
 
-            let cmdline_path = entry.path().join("cmdline");
-            // Read as bytes to avoid UTF-8 conversion overhead
-            if let Ok(cmdline) = fs::read(&cmdline_path) {
-                if memmem::find(&cmdline, b"noctalia-shell").is_some() {
-                    let mut name = "Noctalia Shell".to_string();
-                    if let Some(scheme) = get_noctalia_scheme() {
-                        name = format!("{} |  {}", name, capitalize(&scheme));
-                    }
-                    return name;
-                }
-                if memmem::find(&cmdline, b"dms").is_some() {
-                    let mut name = "DMS".to_string();
-                    if let Some(theme) = get_dms_theme() {
-                        let formatted_theme = theme
-                            .replace("cat-", "Catppuccin (")
-                            + if theme.starts_with("cat-") { ")" } else { "" };
-                        name = format!("{} |  {}", name, capitalize(&formatted_theme));
-                    }
-                    return name;
-                }
+// Desktop shells and bars found anywhere in a cmdline, as (needle, display name), the
+// first that's in a process wins
+const UI_PROCESSES: &[(&[u8], &str)] = &[
+    (b"noctalia-shell", "Noctalia Shell"),
+    (b"dms", "DMS"),
+    //i know this janky but idk, its a fallback
+    (b"plasmashell", "Plasma Shell"),
+    (b"gnome-shell", "Gnome Shell"),
+    (b"waybar", "Custom Waybar setup"),
+];
 
-                //i know this janky but idk, its a fallback
-                if memmem::find(&cmdline, b"plasmashell").is_some() {
-                    return "Plasma Shell".to_string();
-                }
-                if memmem::find(&cmdline, b"gnome-shell").is_some() {
-                    return "Gnome Shell".to_string();
-                }
-                if memmem::find(&cmdline, b"waybar").is_some() {
-                    return "Custom Waybar setup".to_string();
-                }
-            }
+// The UI a process is part of, by what's in its cmdline
+fn ui_for_cmdline(cmdline: &[u8]) -> Option<&'static str> {
+    UI_PROCESSES
+        .iter()
+        .find(|(needle, _)| memmem::find(cmdline, needle).is_some())
+        .map(|&(_, name)| name)
+}
+
+// Display/login managers as (unit or daemon name, display name)
+const LOGIN_MANAGERS: &[(&str, &str)] = &[
+    ("sddm", "SDDM"),
+    ("gdm", "GDM"),
+    ("gdm3", "GDM"),
+    ("lightdm", "LightDM"),
+    ("greetd", "greetd"),
+    ("ly", "ly"),
+    ("lemurs", "lemurs"),
+];
+
+// The display manager that started the session, "SDDM" or "greetd (tuigreet)".
+// systemd points display-manager.service at the enabled one, elsewhere (runit, OpenRC) it's
+// whichever daemon is running. Empty (hidden) for startx and TTY logins.
+pub fn login_manager() -> String {
+    let from_unit = unit_login_manager(Path::new("/etc/systemd/system"));
+    let from_process = || {
+        find_process(|process| {
+            let name = process_name(&process.cmdline);
+            LOGIN_MANAGERS
+                .iter()
+                .find(|(daemon, _)| daemon.as_bytes() == name)
+                .map(|(_, display)| *display)
+        })
+    };
+    let Some(manager) = from_unit.or_else(from_process) else {
+        return String::new();
+    };
+
+    if manager == "greetd"
+        && let Some(greeter) = fs::read_to_string("/etc/greetd/config.toml")
+            .ok()
+            .and_then(|config| greetd_greeter(&config))
+    {
+        return format!("greetd ({})", greeter);
+    }
+    manager.to_string()
+}
+
+// The manager display-manager.service in a systemd unit dir links to
+fn unit_login_manager(units: &Path) -> Option<&'static str> {
+    let target = fs::read_link(units.join("display-manager.service")).ok()?;
+    login_manager_from_unit(&target.to_string_lossy())
+}
+
+// "/usr/lib/systemd/system/sddm.service" -> "SDDM". Templated units ("ly@tty2.service")
+// count by their base name.
+fn login_manager_from_unit(target: &str) -> Option<&'static str> {
+    let unit = target.rsplit('/').next()?.strip_suffix(".service")?;
+    let unit = unit.split('@').next()?;
+    LOGIN_MANAGERS.iter().find(|(name, _)| *name == unit).map(|(_, display)| *display)
+}
+
+// The greeter greetd runs, from the command in [default_session] of its config.toml.
+// Greeters that need a compositor run under one ("cage -s -- gtkgreet"), the greeter is then
+// whatever comes after the "--".
+fn greetd_greeter(config: &str) -> Option<String> {
+    let mut in_default_session = false;
+    let mut command = None;
+    for line in config.lines().map(str::trim) {
+        if line.starts_with('[') {
+            in_default_session = line == "[default_session]";
+        } else if in_default_session
+            && let Some((key, value)) = line.split_once('=')
+            && key.trim() == "command"
+        {
+            command = Some(value.trim().trim_matches(['"', '\'']).to_string());
         }
     }
+    let command = command?;
+    let words: Vec<&str> = command.split_whitespace().collect();
+    let program = match words.iter().position(|&word| word == "--") {
+        Some(separator) if words.first().is_some_and(|first| first.ends_with("cage")) => {
+            words.get(separator + 1)?
+        }
+        _ => words.first()?,
+    };
+    let program = program.rsplit('/').next()?;
+    (!program.is_empty()).then(|| program.to_string())
+}
 
-    "unknown".to_string()
+// Desktop details that come up in bug reports, e.g. "Wayland · fractional scaling · animations off".
+// Only Plasma and GNOME are probed, anything else gets an empty string (hidden).
+pub fn desktop_details() -> String {
+    let desktop = env::var("XDG_CURRENT_DESKTOP").unwrap_or_default().to_lowercase();
+    let is_desktop = |name: &str| desktop.split(':').any(|d| d == name);
+
+    let mut details: Vec<String> = Vec::new();
+    match env::var("XDG_SESSION_TYPE").as_deref() {
+        Ok("wayland") => details.push("Wayland".to_string()),
+        Ok("x11") => details.push("X11".to_string()),
+        _ => {}
+    }
+
+    let probed = if is_desktop("kde") || is_desktop("plasma") {
+        plasma_details()
+    } else if is_desktop("gnome") {
+        gnome_details()
+    } else {
+        return String::new();
+    };
+    details.extend(probed);
+
+    details.join(" · ")
+}
+
+// Plasma: scaling from kwinrc (Wayland) or kdeglobals (X11), compositing and animations
+fn plasma_details() -> Vec<String> {
+    let Ok(home) = env::var("HOME") else {
+        return vec![];
+    };
+    let kwinrc = fs::read_to_string(format!("{}/.config/kwinrc", home)).unwrap_or_default();
+    let kdeglobals =
+        fs::read_to_string(format!("{}/.config/kdeglobals", home)).unwrap_or_default();
+    plasma_details_from(&kwinrc, &kdeglobals)
+}
+
+fn plasma_details_from(kwinrc: &str, kdeglobals: &str) -> Vec<String> {
+    let mut details = Vec::new();
+
+    let scale = ini_value(kwinrc, "Xwayland", "Scale")
+        .or_else(|| ini_value(kdeglobals, "KScreen", "ScaleFactor"))
+        .and_then(|s| s.parse::<f64>().ok());
+    if let Some(scale) = scale
+        && scale.fract() != 0.0
+    {
+        details.push(format!("fractional scaling {}x", scale));
+    }
+
+    if ini_value(kwinrc, "Compositing", "Enabled") == Some("false") {
+        details.push("compositing off".to_string());
+    } else if let Some(backend) = ini_value(kwinrc, "Compositing", "Backend") {
+        details.push(backend.to_string());
+    }
+
+    let animation_factor = ini_value(kdeglobals, "KDE", "AnimationDurationFactor")
+        .and_then(|f| f.parse::<f64>().ok());
+    details.push(if animation_factor == Some(0.0) {
+        "animations off".to_string()
+    } else {
+        "animations on".to_string()
+    });
+
+    details
+}
+
+// GNOME: fractional scaling is a mutter experimental feature, animations is a plain toggle
+fn gnome_details() -> Vec<String> {
+    let gsettings = |schema: &str, key: &str| {
+        command_output_limited(
+            Command::new("gsettings").args(["get", schema, key]),
+            4096,
+            Duration::from_millis(500),
+        )
+        .map(|out| String::from_utf8_lossy(&out).trim().to_string())
+    };
+    let mut details = Vec::new();
+
+    if gsettings("org.gnome.mutter", "experimental-features")
+        .is_some_and(|features| features.contains("scale-monitor-framebuffer"))
+    {
+        details.push("fractional scaling".to_string());
+    }
+
+    match gsettings("org.gnome.desktop.interface", "enable-animations").as_deref() {
+        Some("false") => details.push("animations off".to_string()),
+        Some("true") => details.push("animations on".to_string()),
+        _ => {}
+    }
+
+    details
+}
+
+// Greeting for the top of the output, e.g. "Good evening, tui — Friday 21:42".
+// The date part is date_format when it's set.
+pub fn greeting(date_format: Option<&str>) -> String {
+    let name = username().unwrap_or_else(|| "friend".to_string());
+    let Some(time) = local_time() else {
+        return format!("Hello, {}", name);
+    };
+
+    let date = dateformat::format_date(&time, date_format.unwrap_or("%A %H:%M"));
+    format!("Good {}, {} — {}", part_of_day(time.hour), name, date)
+}
+
+fn part_of_day(hour: u32) -> &'static str {
+    match hour {
+        5..=11 => "morning",
+        12..=16 => "afternoon",
+        17..=21 => "evening",
+        _ => "night",
+    }
 }
 
 // Get the user's preferred editor from environment variables.
@@ -324,3 +1007,425 @@ pub fn editor() -> String {
         (None, None) => String::new()
     }
 }
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::helpers::ProcessScan;
+    use crate::testutil::TempDir;
+
+    #[test]
+    fn shell_version_banners() {
+        // What each shell prints for the args in SHELL_VERSION_ARGS (--version by default)
+        let banners = [
+            (
+                "bash",
+                "GNU bash, version 5.2.26(1)-release (x86_64-pc-linux-gnu)\n\
+                 Copyright (C) 2022 Free Software Foundation, Inc.\n\
+                 License GPLv3+: GNU GPL version 3 or later <http://gnu.org/licenses/gpl.html>\n",
+                "5.2.26",
+            ),
+            ("zsh", "zsh 5.9 (x86_64-pc-linux-gnu)\n", "5.9"),
+            ("fish", "fish, version 3.7.1\n", "3.7.1"),
+            ("nu", "0.95.0\n", "0.95.0"),
+            ("pwsh", "PowerShell 7.4.2\n", "7.4.2"),
+            ("elvish", "0.21.0+official\n", "0.21.0"),
+            ("ksh", "  version         sh (AT&T Research) 93u+m/1.0.8 2024-01-01\n", "1.0.8"),
+            ("mksh", "@(#)MIRBSD KSH R59 2020/10/31\n", "R59"),
+        ];
+        for (shell, banner, version) in banners {
+            assert_eq!(parse_shell_version(shell, banner).as_deref(), Some(version), "{}", shell);
+        }
+    }
+
+    #[test]
+    fn terminal_version_banners() {
+        let banners = [
+            ("kitty 0.35.2 created by Kovid Goyal\n", "0.35.2"),
+            ("alacritty 0.13.2 (bb8ea18e)\n", "0.13.2"),
+            ("foot version: 1.17.2 -pgo +ime +graphemes -assertions\n", "1.17.2"),
+            ("Ghostty 1.0.1\n\nVersion\n  - version: 1.0.1\n", "1.0.1"),
+            ("# GNOME Terminal 3.52.0 using VTE 0.76.0 +BIDI +GNUTLS\n", "3.52.0"),
+        ];
+        for (banner, version) in banners {
+            assert_eq!(parse_version(banner).as_deref(), Some(version), "{}", banner);
+        }
+        assert_eq!(parse_version("kitty: unknown option\n"), None);
+        assert_eq!(terminal_binary("Kitty"), Some("kitty"));
+        assert_eq!(terminal_binary("Xterm"), None);
+    }
+
+    #[test]
+    fn konsole_versions() {
+        assert_eq!(konsole_version("240202").as_deref(), Some("24.02.2"));
+        assert_eq!(konsole_version("231204\n").as_deref(), Some("23.12.4"));
+        assert_eq!(konsole_version("24.02"), None);
+    }
+
+    #[test]
+    fn version_below_a_banner_line() {
+        // pwsh with a login banner, the version shows up on line two
+        let banner = "PowerShell\nPowerShell 7.4.2\nhttps://aka.ms/powershell\n";
+        assert_eq!(parse_shell_version("pwsh", banner).as_deref(), Some("7.4.2"));
+        // Further down than three lines doesn't count
+        let banner = "Welcome\nto\nthe shell\nversion 1.2.3\n";
+        assert_eq!(parse_shell_version("xonsh", banner), None);
+    }
+
+    #[test]
+    fn no_version_in_the_output() {
+        assert_eq!(parse_shell_version("bash", ""), None);
+        assert_eq!(parse_shell_version("zsh", "zsh: bad option: --version\n"), None);
+        assert_eq!(parse_shell_version("mksh", "mksh\n"), None);
+    }
+
+    #[test]
+    fn wm_from_xdg_current_desktop() {
+        // Values real sessions set
+        let desktops = [
+            ("GNOME", Some("Mutter")),
+            ("ubuntu:GNOME", Some("Mutter")),
+            ("pop:GNOME", Some("Mutter")),
+            ("Budgie:GNOME", Some("Budgie WM")),
+            ("KDE", Some("KWin")),
+            ("X-Cinnamon", Some("Muffin")),
+            ("MATE", Some("Marco")),
+            ("Unity", Some("Compiz")),
+            ("Unity:Unity7:ubuntu", Some("Compiz")),
+            ("XFCE", Some("Xfwm4")),
+            ("Hyprland", Some("Hyprland")),
+            ("sway:wlroots", Some("Sway")),
+            ("niri", Some("Niri")),
+            // No fixed WM, or nothing known: left to the /proc scan
+            ("LXQt", None),
+            ("Pantheon", None),
+            ("COSMIC", None),
+            ("", None),
+        ];
+        for (desktop, wm) in desktops {
+            assert_eq!(wm_for_desktops(desktop), wm, "{}", desktop);
+        }
+    }
+
+    #[test]
+    fn wm_from_cmdline() {
+        let cmdlines: [(&[u8], _); 9] = [
+            (b"/usr/bin/sway\0", Some("Sway")),
+            (b"Hyprland\0", Some("Hyprland")),
+            (b"/usr/bin/kwin_wayland\0--wayland-fd\x007\0--socket\0wayland-0\0", Some("KWin")),
+            (b"/home/me/.cache/xmonad/xmonad-x86_64-linux\0", Some("XMonad")),
+            (b"/usr/bin/python3\0-u\0/usr/bin/qtile\0start\0", Some("Qtile")),
+            (b"/usr/bin/cosmic-comp\0", Some("COSMIC")),
+            (b"miracle-wm\0", Some("Miracle WM")),
+            (b"jay\0run\0", Some("Jay")),
+            (b"/usr/bin/gamescope\0-e\0--\0steam\0", Some("Gamescope")),
+        ];
+        for (cmdline, wm) in cmdlines {
+            assert_eq!(wm_for_cmdline(cmdline), wm, "{}", String::from_utf8_lossy(cmdline));
+        }
+    }
+
+    #[test]
+    fn wm_names_in_other_places_dont_count() {
+        let cmdlines: [&[u8]; 8] = [
+            // A WM's name in a directory, or the start of another program's name
+            b"/home/me/src/dwm/build/editor\0",
+            b"/usr/bin/ncageview\0",
+            b"/usr/bin/swaybg\0-i\0wall.png\0",
+            b"/usr/bin/swayidle\0-w\0",
+            b"niri-msg\0",
+            // As an argument, not the program
+            b"/usr/bin/vim\0/home/me/.config/sway/config\0",
+            b"/usr/bin/man\0dwm\0",
+            b"/usr/bin/less\0i3\0",
+        ];
+        for cmdline in cmdlines {
+            assert_eq!(wm_for_cmdline(cmdline), None, "{}", String::from_utf8_lossy(cmdline));
+        }
+        assert_eq!(wm_for_cmdline(b""), None);
+    }
+
+    #[test]
+    fn greeting_buckets() {
+        let buckets: Vec<&str> = [0, 4, 5, 11, 12, 16, 17, 21, 22, 23].map(part_of_day).to_vec();
+        let expected = [
+            "night", "night", "morning", "morning", "afternoon", "afternoon", "evening", "evening",
+            "night", "night",
+        ];
+        assert_eq!(buckets, expected);
+    }
+
+    #[test]
+    fn plasma_details_from_kwinrc_and_kdeglobals() {
+        let kwinrc = "[Compositing]\nBackend=OpenGL\n\n[Xwayland]\nScale=1.5\n";
+        let kdeglobals = "[KDE]\nAnimationDurationFactor=0\n";
+        assert_eq!(
+            plasma_details_from(kwinrc, kdeglobals),
+            ["fractional scaling 1.5x", "OpenGL", "animations off"]
+        );
+    }
+
+    #[test]
+    fn plasma_details_on_x11_and_defaults() {
+        // X11 keeps the scale in kdeglobals, whole scales aren't worth a mention
+        let kdeglobals = "[KScreen]\nScaleFactor=1.25\n[KDE]\nAnimationDurationFactor=0.5\n";
+        assert_eq!(
+            plasma_details_from("", kdeglobals),
+            ["fractional scaling 1.25x", "animations on"]
+        );
+        let kwinrc = "[Compositing]\nEnabled=false\nBackend=XRender\n[Xwayland]\nScale=2\n";
+        assert_eq!(plasma_details_from(kwinrc, ""), ["compositing off", "animations on"]);
+    }
+
+    #[test]
+    fn dash_is_not_asked_for_a_version() {
+        // dash has no version flag, it would treat --version as a script name
+        let args = SHELL_VERSION_ARGS.iter().find(|(name, _)| *name == "dash");
+        assert_eq!(args.map(|(_, args)| args.len()), Some(0));
+    }
+
+    // A fake /proc with one <pid>/cmdline per process, NUL separated like the real thing
+    fn fake_proc(processes: &[(u32, Vec<&str>)]) -> TempDir {
+        let proc = TempDir::new();
+        for (pid, args) in processes {
+            let cmdline: String = args.iter().map(|arg| format!("{}\0", arg)).collect();
+            proc.write(&format!("{}/cmdline", pid), &cmdline);
+        }
+        proc
+    }
+
+    #[test]
+    fn kwin_and_mutter_backends() {
+        let proc = fake_proc(&[
+            (1100, vec!["/usr/bin/kwin_wayland", "--wayland-fd", "7", "--xwayland"]),
+            (1200, vec!["/usr/bin/kwin_x11", "-session", "10d2e5"]),
+            (1300, vec!["/usr/bin/kwin_wayland", "--virtual", "--width", "1280"]),
+            (1400, vec!["/usr/bin/gnome-shell"]),
+            (1500, vec!["/usr/bin/gnome-shell", "--nested", "--x11"]),
+        ]);
+        proc.symlink("1100/fd/3", "/dev/null");
+        proc.symlink("1100/fd/21", "/dev/dri/card1");
+        proc.symlink("1200/fd/8", "/dev/dri/card0");
+        proc.symlink("1300/fd/8", "/dev/dri/card0");
+        proc.symlink("1400/fd/30", "/dev/dri/card2");
+        let mut scan = ProcessScan::new(proc.path());
+        let mut backend = |wm: &str, pid: u32, session_type: Option<&str>| {
+            let process = scan.find_map(|process| (process.pid == pid).then(|| process.clone()));
+            assert!(process.is_some());
+            wm_backend_in(wm, process.as_ref(), proc.path(), session_type.map(str::to_string))
+        };
+
+        // KWin says which it is in its name, whatever the session type says
+        assert_eq!(backend("KWin", 1100, Some("x11")).as_deref(), Some("wayland, card1"));
+        assert_eq!(backend("KWin", 1200, None).as_deref(), Some("x11, card0"));
+        // A virtual session doesn't drive a card even with one open
+        assert_eq!(backend("KWin", 1300, None).as_deref(), Some("wayland, virtual"));
+        // gnome-shell goes by the session type, or its own --x11
+        assert_eq!(backend("Mutter", 1400, Some("wayland")).as_deref(), Some("wayland, card2"));
+        assert_eq!(backend("Mutter", 1400, None), None);
+        assert_eq!(backend("Mutter", 1500, Some("wayland")).as_deref(), Some("x11"));
+        // Mutter's process out of sight, the session type is all there is
+        let mutter = wm_backend_in("Mutter", None, proc.path(), Some("wayland".into()));
+        assert_eq!(mutter.as_deref(), Some("wayland"));
+        assert_eq!(wm_backend_in("KWin", None, proc.path(), Some("wayland".into())), None);
+        // Other WMs have no backend detail
+        assert_eq!(backend("Hyprland", 1100, Some("wayland")), None);
+    }
+
+    #[test]
+    fn wm_matches_survive_cut_cmdlines() {
+        let long = "x".repeat(600);
+        let proc = fake_proc(&[
+            // Way past the read limit, the name up front is all that counts
+            (812, vec!["/usr/bin/Hyprland", "--config", &long]),
+            // Python WMs go by their script
+            (901, vec!["/usr/bin/python3", "/usr/bin/qtile", "start", "-b", "wayland", &long]),
+            // An editor with the sway config open, sway only turning up in an argument
+            (1500, vec!["nvim", "/home/tui/.config/sway/config"]),
+            // The cut lands in the middle of a "sway" that's only an argument anyway
+            (1600, vec!["/opt/app/electron", &long[..240], "--class=sway"]),
+            // Kernel threads have an empty cmdline
+            (2, vec![]),
+        ]);
+        let mut scan = ProcessScan::new(proc.path());
+        let mut wm = |pid: u32| {
+            let process = scan.find_map(|process| (process.pid == pid).then(|| process.clone()));
+            let process = process.unwrap();
+            // Nothing is kept past the limit
+            assert!(process.cmdline.len() <= 256);
+            wm_for_cmdline(&process.cmdline)
+        };
+        assert_eq!(wm(812), Some("Hyprland"));
+        assert_eq!(wm(901), Some("Qtile"));
+        assert_eq!(wm(1500), None);
+        assert_eq!(wm(1600), None);
+        assert_eq!(wm(2), None);
+    }
+
+    // A status file the way dpkg writes it, first stanza installed and no blank line at the end
+    const DPKG_STATUS: &str = "\
+Package: adduser
+Status: install ok installed
+Priority: important
+Version: 3.134
+
+Package: apt
+Status: install ok installed
+Version: 2.7.14
+
+Package: nano
+Status: deinstall ok config-files
+Version: 7.2-2
+
+Package: libc6
+Status: install ok unpacked
+Architecture: amd64
+Multi-Arch: same
+Version: 2.39-0ubuntu8
+
+Package: openssl
+Status: install reinstreq half-installed
+Version: 3.0.13-0ubuntu3
+
+Package: systemd
+Status: install ok half-configured
+Version: 255.4-1ubuntu8
+
+Package: man-db
+Status: install ok triggers-pending
+Version: 2.12.0-4build2
+
+Package: vim
+Status: purge ok not-installed
+
+Package: zsh
+Status: install ok installed
+Description: shell with lots of features
+ The status line below is part of the description:
+ Status: install ok installed
+Version: 5.9-6ubuntu2";
+
+    #[test]
+    fn dpkg_status_stanzas() {
+        // adduser, apt, libc6, systemd, man-db and zsh
+        assert_eq!(count_dpkg_installed(DPKG_STATUS.as_bytes()), 6);
+        // Only the first stanza
+        let first = DPKG_STATUS.split("\n\n").next().unwrap();
+        assert_eq!(count_dpkg_installed(first.as_bytes()), 1);
+        // Trailing newline, extra blank lines between stanzas
+        let spaced = DPKG_STATUS.replace("\n\n", "\n\n\n") + "\n";
+        assert_eq!(count_dpkg_installed(spaced.as_bytes()), 6);
+        // Removed and broken ones on their own
+        let removed = "Package: nano\nStatus: deinstall ok config-files\n\n\
+                       Package: vim\nStatus: purge ok not-installed\n";
+        assert_eq!(count_dpkg_installed(removed.as_bytes()), 0);
+        assert_eq!(count_dpkg_installed(b"Package: broken\nStatus: install ok\n"), 0);
+        assert_eq!(count_dpkg_installed(b""), 0);
+    }
+
+    // What steamos-session-select writes, for either mode
+    fn autologin(session: &str) -> Option<String> {
+        Some(format!("[Autologin]\nRelogin=true\nSession={}\nUser=deck\n", session))
+    }
+
+    #[test]
+    fn steamos_modes() {
+        let gaming = || autologin("gamescope-wayland.desktop");
+        let desktop = || autologin("plasma.desktop");
+        // The WM says it while there is one
+        assert_eq!(mode_from("SteamOS", "Gamescope", None), "Gaming Mode");
+        assert_eq!(mode_from("SteamOS", "KWin", gaming()), "Desktop Mode");
+        assert_eq!(mode_from("SteamOS", "Gamescope", desktop()), "Gaming Mode");
+        // Over ssh it's the session picked last
+        assert_eq!(mode_from("SteamOS", "unknown", gaming()), "Gaming Mode");
+        assert_eq!(mode_from("SteamOS", "", desktop()), "Desktop Mode");
+        assert_eq!(mode_from("SteamOS", "unknown", None), "unknown");
+        assert_eq!(mode_from("SteamOS", "unknown", Some("[General]\n".into())), "unknown");
+        // Bazzite and friends have the switcher too, other distros get no line
+        assert_eq!(mode_from("Bazzite", "KWin", desktop()), "Desktop Mode");
+        assert_eq!(mode_from("Arch Linux", "Gamescope", None), "");
+    }
+
+    // display-manager.service as systemctl enable leaves it, and the ways it can be off
+    #[test]
+    fn login_manager_from_the_unit_symlink() {
+        let cases = [
+            ("/usr/lib/systemd/system/sddm.service", Some("SDDM")),
+            ("/lib/systemd/system/gdm3.service", Some("GDM")),
+            ("/usr/lib/systemd/system/lightdm.service", Some("LightDM")),
+            ("../../../usr/lib/systemd/system/greetd.service", Some("greetd")),
+            ("/usr/lib/systemd/system/ly@tty2.service", Some("ly")),
+            ("lemurs.service", Some("lemurs")),
+            ("/usr/lib/systemd/system/xdm.service", None),
+            ("/usr/lib/systemd/system/sddm.socket", None),
+            ("/dev/null", None),
+        ];
+        for (target, manager) in cases {
+            let units = TempDir::new();
+            units.symlink("display-manager.service", target);
+            assert_eq!(unit_login_manager(units.path()), manager, "{}", target);
+        }
+        // startx and TTY logins have no unit at all, and a copied unit isn't a link
+        let units = TempDir::new();
+        assert_eq!(unit_login_manager(units.path()), None);
+        units.write("display-manager.service", "[Unit]\nDescription=Simple Desktop Display\n");
+        assert_eq!(unit_login_manager(units.path()), None);
+    }
+
+    #[test]
+    fn greeter_from_greetd_config() {
+        let config = |command: &str| {
+            format!(
+                "[terminal]\nvt = 1\n\n[default_session]\n# the greeter\ncommand = {}\n\
+                 user = \"greeter\"\n\n[initial_session]\ncommand = \"Hyprland\"\n",
+                command
+            )
+        };
+        let cases = [
+            ("\"tuigreet --time --cmd Hyprland\"", Some("tuigreet")),
+            ("'/usr/bin/regreet'", Some("regreet")),
+            ("\"cage -s -- gtkgreet\"", Some("gtkgreet")),
+            ("\"/usr/bin/cage -s -mlast -- /usr/bin/regreet\"", Some("regreet")),
+            // Only cage's "--" separates the greeter
+            ("\"agreety --cmd -- zsh\"", Some("agreety")),
+            ("\"cage -s --\"", None),
+            ("\"\"", None),
+        ];
+        for (command, greeter) in cases {
+            assert_eq!(greetd_greeter(&config(command)).as_deref(), greeter, "{}", command);
+        }
+        // No [default_session], or a command only under another table
+        assert_eq!(greetd_greeter("[terminal]\nvt = 1\n"), None);
+        assert_eq!(greetd_greeter("[initial_session]\ncommand = \"sway\"\n"), None);
+        assert_eq!(greetd_greeter(""), None);
+    }
+
+    #[test]
+    fn prompt_from_env_and_files() {
+        let no_env = |_: &str| None;
+        let home = TempDir::new();
+        assert_eq!(prompt_in(home.path(), &no_env), "");
+
+        // Config files alone
+        home.write(".config/starship.toml", "");
+        assert_eq!(prompt_in(home.path(), &no_env), "Starship");
+        home.write(".oh-my-zsh/oh-my-zsh.sh", "");
+        assert_eq!(prompt_in(home.path(), &no_env), "Starship | oh-my-zsh");
+
+        // Env vars, an empty one doesn't count and $ZSH has to point at oh-my-zsh
+        let home = TempDir::new();
+        let env = |name: &str| match name {
+            "POSH_THEME" => Some(String::new()),
+            "POSH_PID" => Some("4242".to_string()),
+            "ZSH" => Some("/usr/share/zsh".to_string()),
+            _ => None,
+        };
+        assert_eq!(prompt_in(home.path(), &env), "Oh My Posh");
+        let env = |name: &str| (name == "ZSH").then(|| "/home/me/.oh-my-zsh".to_string());
+        assert_eq!(prompt_in(home.path(), &env), "oh-my-zsh");
+
+        // fish_plugins, matched case-insensitively
+        home.write(".config/fish/fish_plugins", "jorgebucaran/fisher\nIlanCosman/tide@v6\n");
+        assert_eq!(prompt_in(home.path(), &no_env), "Tide | fisher");
+    }
+}
@@ -0,0 +1,307 @@
+// A few worker threads the slow modules run on, instead of a fresh thread per module per run
+// (and per --watch tick). Workers start as tasks come in, up to the budget (threads in the
+// config, else 2 to 4 depending on the CPUs we're allowed), and stay for the next task.
+//
+// A task that misses the fetch deadline keeps its worker busy, possibly forever (a hung
+// subprocess). Its waiter abandons it, which frees a place in the budget so the tasks behind
+// it don't wait on it too. The stuck worker quits when its task finally ends. An abandoned task
+// that hadn't started yet is dropped.
+// A panicking task comes back as its reason and the worker carries on. When no worker thread
+// can be started at all (RLIMIT_NPROC, a cgroup pids limit) the caller runs the task itself.
+
+use std::collections::VecDeque;
+use std::panic::{self, AssertUnwindSafe};
+use std::sync::atomic::{AtomicBool, Ordering};
+use std::sync::{Arc, Condvar, Mutex, OnceLock, mpsc};
+use std::thread;
+use std::time::{Duration, Instant};
+
+use crate::collect::panic_reason;
+
+type Job = Box<dyn FnOnce() + Send>;
+
+// threads from the config, 0 or unset is auto
+static THREADS: OnceLock<usize> = OnceLock::new();
+static POOL: OnceLock<Pool> = OnceLock::new();
+
+// Set the threads config, call before the first task is spawned
+pub fn set_threads(threads: usize) {
+    let _ = THREADS.set(threads);
+}
+
+// Auto: one worker per CPU we get (cgroup limits count), at least 2 so a slow subprocess
+// doesn't hold up everything else, at most 4 since the modules mostly wait on I/O
+fn budget() -> usize {
+    match THREADS.get() {
+        Some(&threads) if threads > 0 => threads,
+        _ => thread::available_parallelism().map_or(2, |n| n.get().clamp(2, 4)),
+    }
+}
+
+struct Pool {
+    state: Mutex<State>,
+    ready: Condvar,
+    budget: usize,
+}
+
+#[derive(Default)]
+struct State {
+    queue: VecDeque<(Job, Arc<Ticket>)>,
+    // Workers alive, idle ones included
+    workers: usize,
+    idle: usize,
+    // Workers running an abandoned task, they don't count against the budget
+    stuck: usize,
+}
+
+// A task's bookkeeping, only changed with the pool's state locked so finishing and
+// abandoning can't cross
+#[derive(Default)]
+struct Ticket {
+    finished: AtomicBool,
+    abandoned: AtomicBool,
+}
+
+fn pool() -> &'static Pool {
+    POOL.get_or_init(|| Pool::new(budget()))
+}
+
+// A worker never panics while holding the lock, but don't let one poisoning stop the rest
+fn lock(pool: &Pool) -> std::sync::MutexGuard<'_, State> {
+    pool.state.lock().unwrap_or_else(|poisoned| poisoned.into_inner())
+}
+
+// What a task came back with and how long it ran (time spent queued not included)
+pub struct Finished<T> {
+    pub result: Result<T, String>,
+    pub took: Duration,
+}
+
+// Why a task didn't come back
+pub enum Missed {
+    TimedOut,
+    // The worker went away without an answer
+    Lost,
+}
+
+// A task handed to the pool, wait on it for the result
+pub struct Task<T> {
+    receiver: mpsc::Receiver<Finished<T>>,
+    ticket: Arc<Ticket>,
+    pool: &'static Pool,
+}
+
+// Run a task on the pool
+pub fn spawn<T: Send + 'static>(task: impl FnOnce() -> T + Send + 'static) -> Task<T> {
+    pool().spawn(task)
+}
+
+impl Pool {
+    fn new(budget: usize) -> Self {
+        Self { state: Mutex::new(State::default()), ready: Condvar::new(), budget }
+    }
+
+    fn spawn<T: Send + 'static>(
+        &'static self,
+        task: impl FnOnce() -> T + Send + 'static,
+    ) -> Task<T> {
+        let (sender, receiver) = mpsc::channel();
+        let job: Job = Box::new(move || {
+            let started = Instant::now();
+            let result = panic::catch_unwind(AssertUnwindSafe(task))
+                .map_err(|payload| panic_reason(&*payload));
+            let _ = sender.send(Finished { result, took: started.elapsed() });
+        });
+        let ticket = Arc::new(Ticket::default());
+
+        let mut state = lock(self);
+        state.queue.push_back((job, ticket.clone()));
+        let started = start_worker_if_needed(self, &mut state);
+        // No thread to be had and no worker left to take it, a slower fetch beats a crash
+        let inline = if !started && state.workers == 0 { state.queue.pop_back() } else { None };
+        drop(state);
+        match inline {
+            Some((job, _)) => job(),
+            None => self.ready.notify_one(),
+        }
+        Task { receiver, ticket, pool: self }
+    }
+}
+
+// Start a worker when tasks are queued, none is idle and the budget allows another.
+// False when one was needed but the thread couldn't be started.
+fn start_worker_if_needed(pool: &'static Pool, state: &mut State) -> bool {
+    if state.queue.is_empty() || state.idle > 0 || state.workers - state.stuck >= pool.budget {
+        return true;
+    }
+    let spawned = thread::Builder::new()
+        .name("slowfetch-worker".into())
+        .spawn(move || work(pool));
+    // The new worker waits for the lock held here before it looks at the count
+    if spawned.is_ok() {
+        state.workers += 1;
+    }
+    spawned.is_ok()
+}
+
+fn work(pool: &'static Pool) {
+    let mut state = lock(pool);
+    loop {
+        let Some((job, ticket)) = state.queue.pop_front() else {
+            state.idle += 1;
+            state = pool.ready.wait(state).unwrap_or_else(|poisoned| poisoned.into_inner());
+            state.idle -= 1;
+            continue;
+        };
+        drop(state);
+        // The job catches its task's panics itself
+        job();
+        state = lock(pool);
+        ticket.finished.store(true, Ordering::Relaxed);
+        if ticket.abandoned.load(Ordering::Relaxed) {
+            state.stuck -= 1;
+            // A replacement took this worker's place meanwhile, leave if that went over budget
+            if state.workers - state.stuck > pool.budget {
+                state.workers -= 1;
+                return;
+            }
+        }
+    }
+}
+
+impl<T> Task<T> {
+    // Wait for the result, until `deadline` if there is one. A task that misses it is abandoned.
+    pub fn wait(self, deadline: Option<Instant>) -> Result<Finished<T>, Missed> {
+        let Some(deadline) = deadline else {
+            return self.receiver.recv().map_err(|_| Missed::Lost);
+        };
+        let remaining = deadline.saturating_duration_since(Instant::now());
+        match self.receiver.recv_timeout(remaining) {
+            Ok(finished) => Ok(finished),
+            Err(mpsc::RecvTimeoutError::Disconnected) => Err(Missed::Lost),
+            Err(mpsc::RecvTimeoutError::Timeout) => {
+                self.abandon();
+                Err(Missed::TimedOut)
+            }
+        }
+    }
+
+    // Stop counting a late task's worker against the budget, or drop it if it hasn't started
+    fn abandon(&self) {
+        let pool = self.pool;
+        let mut state = lock(pool);
+        let queued = state.queue.iter().position(|(_, ticket)| Arc::ptr_eq(ticket, &self.ticket));
+        if let Some(index) = queued {
+            state.queue.remove(index);
+        } else if !self.ticket.finished.load(Ordering::Relaxed) {
+            self.ticket.abandoned.store(true, Ordering::Relaxed);
+            state.stuck += 1;
+            // The freed place goes to whatever is queued behind it
+            start_worker_if_needed(pool, &mut state);
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    // Handed in one order and finishing in the opposite one, each comes back on its own task
+    #[test]
+    fn results_in_submit_order() {
+        let tasks: Vec<Task<usize>> = (0..12)
+            .map(|i| {
+                spawn(move || {
+                    thread::sleep(Duration::from_millis(12 - i as u64));
+                    i
+                })
+            })
+            .collect();
+        let deadline = Some(Instant::now() + Duration::from_secs(10));
+        let results: Vec<usize> = tasks
+            .into_iter()
+            .map(|task| task.wait(deadline).ok().unwrap().result.unwrap())
+            .collect();
+        assert_eq!(results, (0..12).collect::<Vec<_>>());
+    }
+
+    // A panic is that task's reason, the tasks around it and after it aren't affected
+    #[test]
+    fn panics_stay_with_their_task() {
+        let tasks: Vec<Task<u32>> = (0..6)
+            .map(|i| {
+                spawn(move || {
+                    if i % 3 == 1 {
+                        panic!("no drm for {}", i);
+                    }
+                    i * 10
+                })
+            })
+            .collect();
+        let results: Vec<Result<u32, String>> =
+            tasks.into_iter().map(|task| task.wait(None).ok().unwrap().result).collect();
+        let panicked = |i| Err(format!("panicked: no drm for {}", i));
+        assert_eq!(results, [Ok(0), panicked(1), Ok(20), Ok(30), panicked(4), Ok(50)]);
+        let after = spawn(|| "still working").wait(None).ok().unwrap();
+        assert_eq!(after.result.unwrap(), "still working");
+    }
+
+    // A task still running at the deadline is timed out and left behind, its worker doesn't
+    // hold up what comes next, and it finishes quietly once it's let go
+    #[test]
+    fn late_tasks_are_abandoned() {
+        let (release, stuck) = mpsc::channel::<()>();
+        let (started, has_started) = mpsc::channel();
+        let late = spawn(move || {
+            started.send(()).unwrap();
+            stuck.recv().ok();
+            "too late"
+        });
+        has_started.recv().unwrap();
+        let deadline = Some(Instant::now() + Duration::from_millis(20));
+        assert!(matches!(late.wait(deadline), Err(Missed::TimedOut)));
+
+        let deadline = Some(Instant::now() + Duration::from_secs(10));
+        let next: Vec<Task<u32>> = (0..8).map(|i| spawn(move || i)).collect();
+        for (i, task) in next.into_iter().enumerate() {
+            assert_eq!(task.wait(deadline).ok().unwrap().result.unwrap(), i as u32);
+        }
+        release.send(()).unwrap();
+
+        // Already past its deadline before it could finish
+        let task = spawn(|| thread::sleep(Duration::from_millis(200)));
+        assert!(matches!(task.wait(Some(Instant::now())), Err(Missed::TimedOut)));
+    }
+
+    // With the smallest auto budget, a task stuck in one worker doesn't starve the queue: the
+    // other worker gets through the rest before the deadline, and once the stuck one is
+    // abandoned its place goes to the next batch. A pool of its own, the other tests' tasks
+    // don't take its workers.
+    #[test]
+    fn a_stuck_task_doesnt_starve_the_queue() {
+        let pool: &'static Pool = Box::leak(Box::new(Pool::new(2)));
+        for _ in 0..2 {
+            let (release, stuck) = mpsc::channel::<()>();
+            let deadline = Some(Instant::now() + Duration::from_millis(300));
+            let hung = pool.spawn(move || stuck.recv().ok());
+            let tasks: Vec<Task<usize>> = (0..8)
+                .map(|i| {
+                    pool.spawn(move || {
+                        thread::sleep(Duration::from_millis(10));
+                        i
+                    })
+                })
+                .collect();
+            // Collected in order like a fetch does, so the rest only get what's left of the
+            // deadline after the stuck one
+            assert!(matches!(hung.wait(deadline), Err(Missed::TimedOut)));
+            for (i, task) in tasks.into_iter().enumerate() {
+                assert_eq!(task.wait(deadline).ok().unwrap().result.unwrap(), i);
+            }
+            let state = lock(pool);
+            assert!(state.workers - state.stuck <= 2);
+            drop(state);
+            release.send(()).unwrap();
+        }
+    }
+}
@@ -1,8 +1,59 @@
 // slowfetch rendering system
 
-use crate::colorcontrol::{color_border, color_key, color_title, color_value};
+use std::borrow::Cow;
+use std::collections::HashMap;
+use std::sync::OnceLock;
+
+use crate::colorcontrol::{RESET, color_border, color_key, color_title, color_value, palette};
+use crate::configloader::Margins;
+#[cfg(feature = "art")]
+use crate::modules::asciimodule::ArtSet;
 use crate::terminalsize::get_terminal_size;
 
+// Built without the art feature there's no art to pass, draw_layout always gets None
+// and ends up sections-only
+#[cfg(not(feature = "art"))]
+pub enum ArtSet {}
+
+#[cfg(not(feature = "art"))]
+impl ArtSet {
+    fn wide(&self) -> &[String] {
+        match *self {}
+    }
+
+    fn medium(&self) -> &[String] {
+        match *self {}
+    }
+
+    fn narrow(&self) -> &[String] {
+        match *self {}
+    }
+
+    fn smol(&self) -> Option<&[String]> {
+        match *self {}
+    }
+}
+
+// Which of the art's sizes a layout drew
+#[derive(Clone, Copy)]
+pub enum ArtSize {
+    Wide,
+    Medium,
+    Narrow,
+    Smol,
+}
+
+// Where a layout put the art: the size it picked and the row and column (in cells) of the art's
+// first line in the output. Art lines are all padded to one width, the rest are right under it.
+// --animate uses it to redraw just the art.
+#[derive(Clone, Copy)]
+#[cfg_attr(not(feature = "art"), allow(dead_code))]
+pub struct ArtPlacement {
+    pub size: ArtSize,
+    pub row: usize,
+    pub column: usize,
+}
+
 // Box drawing characters (as &str for easier concatenation)
 const BOX_TOP_LEFT: &str = "╭";
 const BOX_TOP_RIGHT: &str = "╮";
@@ -10,42 +61,267 @@ const BOX_BOTTOM_LEFT: &str = "╰";
 const BOX_BOTTOM_RIGHT: &str = "╯";
 const BOX_HORIZONTAL: &str = "─";
 const BOX_VERTICAL: &str = "│";
+// Junctions where unified_box's divider and section rules meet the outer border
+const BOX_TEE_DOWN: &str = "┬";
+const BOX_TEE_UP: &str = "┴";
+const BOX_TEE_RIGHT: &str = "├";
+const BOX_TEE_LEFT: &str = "┤";
+
+// unified_box from the config: art and sections share one border in side-by-side layouts
+static UNIFIED_BOX: OnceLock<bool> = OnceLock::new();
+
+// Turn the unified box on/off, call before drawing
+pub fn set_unified_box(enabled: bool) {
+    let _ = UNIFIED_BOX.set(enabled);
+}
+
+fn unified_box() -> bool {
+    *UNIFIED_BOX.get().unwrap_or(&false)
+}
+
+// Where we are in an escape sequence, walking a string char by char.
+// Understands CSI sequences (ESC [ ... final byte, colors are 'm') and OSC sequences
+// (ESC ] ... terminated by BEL or ST, used for hyperlinks), plus their single char C1 forms
+// (U+009B CSI, U+009D OSC, U+009C ST) that some tools print.
+#[derive(Clone, Copy)]
+enum EscapeState {
+    Text,
+    // Just saw ESC, the next char says what kind of sequence this is
+    Escape,
+    // ESC [ - runs until a final byte in 0x40..=0x7E
+    Csi,
+    // ESC ] - runs until BEL or ESC \
+    Osc,
+    // ESC inside an OSC, a following '\' completes the ST terminator
+    OscEscape,
+}
+
+impl EscapeState {
+    // The state after `c`, and whether `c` itself is visible text
+    fn step(self, c: char) -> (Self, bool) {
+        let next = match self {
+            // Found escape character (0x1b = ESC), start of ANSI sequence
+            EscapeState::Text if c == '\x1b' => EscapeState::Escape,
+            EscapeState::Text if c == '\u{9b}' => EscapeState::Csi,
+            EscapeState::Text if c == '\u{9d}' => EscapeState::Osc,
+            EscapeState::Text => return (EscapeState::Text, true),
+            EscapeState::Escape => match c {
+                '[' => EscapeState::Csi,
+                ']' => EscapeState::Osc,
+                // Two byte sequence like ESC \, nothing more to skip
+                _ => EscapeState::Text,
+            },
+            EscapeState::Csi if ('\x40'..='\x7e').contains(&c) => EscapeState::Text,
+            EscapeState::Csi => EscapeState::Csi,
+            // BEL and the C1 ST end the OSC on their own, ESC may be the start of ST
+            EscapeState::Osc | EscapeState::OscEscape if matches!(c, '\x07' | '\u{9c}') => {
+                EscapeState::Text
+            }
+            EscapeState::Osc | EscapeState::OscEscape if c == '\x1b' => EscapeState::OscEscape,
+            EscapeState::OscEscape if c == '\\' => EscapeState::Text,
+            EscapeState::Osc | EscapeState::OscEscape => EscapeState::Osc,
+        };
+        (next, false)
+    }
+}
 
 //Calculate the visible character width of a string, ignoring ANSI escape codes.
 //
 // ANSI codes (like color sequences) add bytes but don't take up visual space.
-// This function iterates through bytes for speed since ANSI sequences are ASCII.
-// For UTF-8 multi-byte characters, only the start byte is counted.
+// Everything else is measured in terminal columns with char_width, so CJK names take two
+// columns and combining accents none. Right-to-left text is measured the same way, reordering
+// it is the terminal's job.
 pub fn visible_len(text: &str) -> usize {
-    let mut visible_char_count = 0;
-    let mut inside_ansi_escape = false;
-    let bytes = text.as_bytes();
-    let mut byte_index = 0;
+    let mut visible_width = 0;
+    let mut state = EscapeState::Text;
 
-    while byte_index < bytes.len() {
-        let current_byte = bytes[byte_index];
+    for current_char in text.chars() {
+        let (next, visible) = state.step(current_char);
+        if visible {
+            visible_width += char_width(current_char);
+        }
+        state = next;
+    }
+    visible_width
+}
 
-        if current_byte == 0x1b {
-            // Found escape character (0x1b = ESC), start of ANSI sequence
-            inside_ansi_escape = true;
-        } else if inside_ansi_escape {
-            // Inside ANSI sequence, wait for 'm' which terminates color codes
-            if current_byte == b'm' {
-                inside_ansi_escape = false;
-            }
-        } else if current_byte < 0x80 {
-            // Standard ASCII character (0x00-0x7F) - counts as one visible char
-            visible_char_count += 1;
-        } else {
-            // UTF-8 multi-byte character: only count the start byte (0xC0-0xFF)
-            // Continuation bytes (0x80-0xBF) are skipped to avoid double-counting
-            if (current_byte & 0xC0) != 0x80 {
-                visible_char_count += 1;
+// Cut a (colored) string down to `width` columns, ending in "…" when anything was cut.
+// Escape codes are kept, colors are reset after the ellipsis.
+pub fn truncate_visible(text: &str, width: usize) -> String {
+    if visible_len(text) <= width {
+        return text.to_string();
+    }
+    let mut result = String::new();
+    let mut used = 0;
+    let mut state = EscapeState::Text;
+    for current_char in text.chars() {
+        let (next, visible) = state.step(current_char);
+        state = next;
+        if visible {
+            // One column stays free for the ellipsis
+            used += char_width(current_char);
+            if used >= width {
+                break;
             }
         }
-        byte_index += 1;
+        result.push(current_char);
+    }
+    result.push('…');
+    result.push_str(RESET);
+    result
+}
+
+// Text with all escape sequences (colors, hyperlinks) taken out
+pub fn strip_escapes(text: &str) -> String {
+    let mut state = EscapeState::Text;
+    text.chars()
+        .filter(|&c| {
+            let (next, visible) = state.step(c);
+            state = next;
+            visible
+        })
+        .collect()
+}
+
+// Combining marks and invisible formatting characters, drawn on top of (or between) their
+// neighbours. Sorted so char_width can binary search.
+const ZERO_WIDTH: &[(u32, u32)] = &[
+    (0x0300, 0x036F), // combining diacritics (e + U+0301 is é)
+    (0x0483, 0x0489), // Cyrillic
+    (0x0591, 0x05BD), // Hebrew points
+    (0x05BF, 0x05BF),
+    (0x05C1, 0x05C2),
+    (0x05C4, 0x05C5),
+    (0x05C7, 0x05C7),
+    (0x0610, 0x061A), // Arabic marks
+    (0x064B, 0x065F),
+    (0x0670, 0x0670),
+    (0x06D6, 0x06DC),
+    (0x06DF, 0x06E4),
+    (0x06E7, 0x06E8),
+    (0x06EA, 0x06ED),
+    (0x0E31, 0x0E31), // Thai vowels and tones
+    (0x0E34, 0x0E3A),
+    (0x0E47, 0x0E4E),
+    (0x1AB0, 0x1AFF), // more combining diacritics
+    (0x1DC0, 0x1DFF),
+    (0x200B, 0x200F), // zero width space/joiners, LRM/RLM
+    (0x202A, 0x202E), // bidi embeddings and overrides
+    (0x2060, 0x2064),
+    (0x2066, 0x206F), // bidi isolates
+    (0x20D0, 0x20FF), // combining marks for symbols
+    (0xFE00, 0xFE0F), // variation selectors
+    (0xFE20, 0xFE2F),
+    (0xFEFF, 0xFEFF), // BOM
+    (0xE0100, 0xE01EF),
+];
+
+// East Asian wide and fullwidth characters plus emoji, two columns each. Sorted like ZERO_WIDTH.
+// Nerd font glyphs live in the private use areas and stay one column.
+const DOUBLE_WIDTH: &[(u32, u32)] = &[
+    (0x1100, 0x115F), // Hangul Jamo
+    (0x231A, 0x231B),
+    (0x2329, 0x232A),
+    (0x23E9, 0x23EC),
+    (0x23F0, 0x23F0),
+    (0x23F3, 0x23F3),
+    (0x25FD, 0x25FE),
+    (0x2614, 0x2615),
+    (0x2648, 0x2653),
+    (0x267F, 0x267F),
+    (0x2693, 0x2693),
+    (0x26A1, 0x26A1),
+    (0x26AA, 0x26AB),
+    (0x26BD, 0x26BE),
+    (0x26C4, 0x26C5),
+    (0x26CE, 0x26CE),
+    (0x26D4, 0x26D4),
+    (0x26EA, 0x26EA),
+    (0x26F2, 0x26F3),
+    (0x26F5, 0x26F5),
+    (0x26FA, 0x26FA),
+    (0x26FD, 0x26FD),
+    (0x2705, 0x2705),
+    (0x270A, 0x270B),
+    (0x2728, 0x2728),
+    (0x274C, 0x274C),
+    (0x274E, 0x274E),
+    (0x2753, 0x2755),
+    (0x2757, 0x2757),
+    (0x2795, 0x2797),
+    (0x27B0, 0x27B0),
+    (0x27BF, 0x27BF),
+    (0x2B1B, 0x2B1C),
+    (0x2B50, 0x2B50),
+    (0x2B55, 0x2B55),
+    (0x2E80, 0x303E), // CJK radicals, punctuation
+    (0x3041, 0x33FF), // kana, CJK compatibility
+    (0x3400, 0x4DBF), // CJK extension A
+    (0x4E00, 0x9FFF), // CJK ideographs
+    (0xA000, 0xA4CF), // Yi
+    (0xA960, 0xA97F),
+    (0xAC00, 0xD7A3), // Hangul syllables
+    (0xF900, 0xFAFF),
+    (0xFE10, 0xFE19),
+    (0xFE30, 0xFE6F),
+    (0xFF00, 0xFF60), // fullwidth forms
+    (0xFFE0, 0xFFE6),
+    (0x16FE0, 0x16FE4),
+    (0x17000, 0x18CFF), // Tangut
+    (0x1B000, 0x1B2FF), // kana supplement
+    (0x1F004, 0x1F004),
+    (0x1F0CF, 0x1F0CF),
+    (0x1F18E, 0x1F18E),
+    (0x1F191, 0x1F19A),
+    (0x1F200, 0x1F251),
+    (0x1F300, 0x1F64F), // emoji
+    (0x1F680, 0x1F6FF),
+    (0x1F7E0, 0x1F7EB),
+    (0x1F900, 0x1F9FF),
+    (0x1FA70, 0x1FAFF),
+    (0x20000, 0x2FFFD), // CJK extensions B onwards
+    (0x30000, 0x3FFFD),
+];
+
+// Columns a character takes in the terminal: 0 for combining marks, 2 for wide characters,
+// 1 for everything else (a wcwidth for the characters names and values actually contain)
+pub fn char_width(c: char) -> usize {
+    let in_table = |table: &[(u32, u32)]| {
+        let code = c as u32;
+        table
+            .binary_search_by(|&(start, end)| {
+                if end < code {
+                    std::cmp::Ordering::Less
+                } else if start > code {
+                    std::cmp::Ordering::Greater
+                } else {
+                    std::cmp::Ordering::Equal
+                }
+            })
+            .is_ok()
+    };
+    // Everything below the first table entry is one column, which is most of it
+    if (c as u32) < 0x0300 {
+        1
+    } else if in_table(ZERO_WIDTH) {
+        0
+    } else if in_table(DOUBLE_WIDTH) {
+        2
+    } else {
+        1
+    }
+}
+
+// The longest start of `text` (no escape codes) that fits in `width` columns
+pub fn truncate_to_width(text: &str, width: usize) -> &str {
+    let mut used = 0;
+    for (index, c) in text.char_indices() {
+        used += char_width(c);
+        if used > width {
+            return &text[..index];
+        }
     }
-    visible_char_count
+    text
 }
 
 // A section of system info with a title and content lines (key, value pairs).
@@ -63,7 +339,52 @@ impl Section {
     }
 }
 
-// uild a bordered box around content lines.
+// Assemble a section's lines from the collected module results.
+// Modules go in the configured order first, anything not mentioned follows in default order.
+// Modules that produced nothing (no battery, no displays) aren't in `results` and are skipped,
+// and a name listed twice only shows up the first time.
+pub fn ordered_lines(
+    default_order: &[&str],
+    order: Option<&Vec<String>>,
+    mut results: HashMap<&str, Vec<(String, String)>>,
+) -> Vec<(String, String)> {
+    let configured = order.map(|names| names.iter().map(String::as_str));
+    let mut lines = Vec::new();
+
+    for name in configured.into_iter().flatten().chain(default_order.iter().copied()) {
+        // Unknown names were warned about when the config was loaded
+        if !default_order.contains(&name) {
+            continue;
+        }
+        if let Some(entries) = results.remove(name) {
+            lines.extend(entries);
+        }
+    }
+
+    lines
+}
+
+// Spaces padding is sliced from, so a row's padding doesn't allocate
+const SPACES: &str = "                                                                ";
+
+// Append `count` spaces
+fn push_spaces(output: &mut String, mut count: usize) {
+    while count > 0 {
+        let chunk = count.min(SPACES.len());
+        output.push_str(&SPACES[..chunk]);
+        count -= chunk;
+    }
+}
+
+// Append `piece` `count` times
+fn push_repeated(output: &mut String, piece: &str, count: usize) {
+    output.reserve(piece.len() * count);
+    for _ in 0..count {
+        output.push_str(piece);
+    }
+}
+
+// Build a bordered box around content lines.
 //
 // `lines` - Content lines to display inside the box
 // `title` - Optional title to display centered in the top border
@@ -71,7 +392,10 @@ impl Section {
 // `target_height` - Optional minimum height (adds vertical padding if needed)
 // `center_content` - If true, center content horizontally; otherwise left-align
 //
-// returns a vec of strings, each representing one row of the rendered box
+// returns a vec of strings, each representing one row of the rendered box.
+// A wrapper around push_box, the layouts write straight into their output instead.
+// Only image mode still wants the rows.
+#[cfg_attr(not(feature = "image"), allow(dead_code))]
 pub fn build_box(
     lines: &[String],
     title: Option<&str>,
@@ -79,6 +403,21 @@ pub fn build_box(
     target_height: Option<usize>,
     center_content: bool,
 ) -> Vec<String> {
+    let mut output = String::new();
+    push_box(&mut output, lines, title, target_width, target_height, center_content);
+    output.lines().map(str::to_string).collect()
+}
+
+// Append a bordered box to `output`, one '\n' terminated row at a time.
+// Same arguments as build_box.
+fn push_box(
+    output: &mut String,
+    lines: &[String],
+    title: Option<&str>,
+    target_width: Option<usize>,
+    target_height: Option<usize>,
+    center_content: bool,
+) {
     // --- step 1: Calculate dimensions ---
 
     // Pre-compute visible lengths for all lines (ignoring ANSI codes)
@@ -87,8 +426,8 @@ pub fn build_box(
     // Find the widest content line
     let content_width = line_visible_lengths.iter().copied().max().unwrap_or(0);
 
-    // Title length - use chars().count() for Unicode correctness
-    let title_char_count = title.map_or(0, |title_text| title_text.chars().count());
+    // Title width in columns, titles can be user provided (wide or accented characters)
+    let title_char_count = title.map_or(0, visible_len);
 
     // Box must be wide enough for both content AND title
     let minimum_width = content_width.max(title_char_count);
@@ -105,43 +444,56 @@ pub fn build_box(
     let top_padding_rows = total_vertical_padding / 2;
     let bottom_padding_rows = total_vertical_padding - top_padding_rows;
 
-    let mut result = Vec::with_capacity(box_total_height);
+    // Roughly: every row is the box width plus the escapes around its borders and content
+    let content_bytes: usize = lines.iter().map(String::len).sum();
+    output.reserve(box_total_height * (box_inner_width + 48) + content_bytes);
 
-    // --- stepo 3: Pre-compute reusable colored border pieces ---
+    // --- stepo 3: Reusable colored border pieces ---
+    // Corners and dashes next to each other share one color span
+    let border = &palette().border;
     let colored_vertical_border = color_border(BOX_VERTICAL);
-    let colored_horizontal_line = color_border(&BOX_HORIZONTAL.repeat(box_inner_width + 2));
-    let inner_spaces = " ".repeat(box_inner_width + 2);
-    let empty_padding_row = format!("{colored_vertical_border}{inner_spaces}{colored_vertical_border}");
+    let push_horizontal_border = |output: &mut String, left: &str, right: &str| {
+        output.push_str(border);
+        output.push_str(left);
+        push_repeated(output, BOX_HORIZONTAL, box_inner_width + 2);
+        output.push_str(right);
+        output.push_str(RESET);
+        output.push('\n');
+    };
+    let push_padding_row = |output: &mut String| {
+        output.push_str(&colored_vertical_border);
+        push_spaces(output, box_inner_width + 2);
+        output.push_str(&colored_vertical_border);
+        output.push('\n');
+    };
 
     // --- step 4: Build top border ---
     // Format: ╭──── Title ────╮  or  ╭────────────╮
-    let top_border = if let Some(title_text) = title {
+    if let Some(title_text) = title {
         // Calculate dashes on each side of the title
         let total_dash_count = box_inner_width.saturating_sub(title_char_count);
         let left_dash_count = total_dash_count / 2;
         let right_dash_count = total_dash_count - left_dash_count;
-        format!(
-            "{}{} {} {}{}",
-            color_border(BOX_TOP_LEFT),
-            color_border(&BOX_HORIZONTAL.repeat(left_dash_count)),
-            color_title(title_text),
-            color_border(&BOX_HORIZONTAL.repeat(right_dash_count)),
-            color_border(BOX_TOP_RIGHT)
-        )
+        output.push_str(border);
+        output.push_str(BOX_TOP_LEFT);
+        push_repeated(output, BOX_HORIZONTAL, left_dash_count);
+        output.push_str(RESET);
+        output.push(' ');
+        output.push_str(&color_title(title_text));
+        output.push(' ');
+        output.push_str(border);
+        push_repeated(output, BOX_HORIZONTAL, right_dash_count);
+        output.push_str(BOX_TOP_RIGHT);
+        output.push_str(RESET);
+        output.push('\n');
     } else {
         // No title - just a solid horizontal line
-        format!(
-            "{}{}{}",
-            color_border(BOX_TOP_LEFT),
-            colored_horizontal_line,
-            color_border(BOX_TOP_RIGHT)
-        )
-    };
-    result.push(top_border);
+        push_horizontal_border(output, BOX_TOP_LEFT, BOX_TOP_RIGHT);
+    }
 
     // --- step 5: Add top padding rows ---
     for _ in 0..top_padding_rows {
-        result.push(empty_padding_row.clone());
+        push_padding_row(output);
     }
 
     // ---step 6: Build content rows ---
@@ -158,59 +510,58 @@ pub fn build_box(
             (0, total_padding)
         };
 
-        let content_row = format!(
-            "{} {}{}{} {}",
-            colored_vertical_border,
-            " ".repeat(left_padding_spaces),
-            line_content,
-            " ".repeat(right_padding_spaces),
-            colored_vertical_border
-        );
-        result.push(content_row);
+        output.push_str(&colored_vertical_border);
+        output.push(' ');
+        push_spaces(output, left_padding_spaces);
+        output.push_str(line_content);
+        push_spaces(output, right_padding_spaces);
+        output.push(' ');
+        output.push_str(&colored_vertical_border);
+        output.push('\n');
     }
 
     // ---step 7: Add bottom padding rows ---
     for _ in 0..bottom_padding_rows {
-        result.push(empty_padding_row.clone());
+        push_padding_row(output);
     }
 
     // === PHASE 8: Build bottom border ===
-    let bottom_border = format!(
-        "{}{}{}",
-        color_border(BOX_BOTTOM_LEFT),
-        colored_horizontal_line,
-        color_border(BOX_BOTTOM_RIGHT)
-    );
-    result.push(bottom_border);
+    push_horizontal_border(output, BOX_BOTTOM_LEFT, BOX_BOTTOM_RIGHT);
+}
 
-    result
+// A section's key-value pairs with colors, one string per line
+fn format_lines(section: &Section) -> Vec<String> {
+    section
+        .lines
+        .iter()
+        .map(|(key, value)| {
+            if value.is_empty() {
+                // Key-only line with colon (e.g., "Display:")
+                format!("{}:", color_key(key))
+            } else if key.starts_with('├') || key.starts_with('╰') {
+                // Tree branch entries (no colon)
+                format!("{} {}", color_key(key), color_value(value))
+            } else {
+                format!("{}: {}", color_key(key), color_value(value))
+            }
+        })
+        .collect()
 }
 
 // Convert sections into formatted, boxed output lines.
 //
 // All boxes are given the same width for visual consistency.
+// A wrapper around push_sections for callers that want the rows.
 pub fn build_sections_lines(sections: &[Section], target_width: Option<usize>) -> Vec<String> {
+    let mut output = String::new();
+    push_sections(&mut output, sections, target_width);
+    output.lines().map(str::to_string).collect()
+}
+
+// Append the section boxes to `output`, same arguments as build_sections_lines
+fn push_sections(output: &mut String, sections: &[Section], target_width: Option<usize>) {
     // ---step 1: Format all key-value pairs with colors ---
-    let formatted_sections: Vec<Vec<String>> = sections
-        .iter()
-        .map(|section| {
-            section
-                .lines
-                .iter()
-                .map(|(key, value)| {
-                    if value.is_empty() {
-                        // Key-only line with colon (e.g., "Display:")
-                        format!("{}:", color_key(key))
-                    } else if key.starts_with('├') || key.starts_with('╰') {
-                        // Tree branch entries (no colon)
-                        format!("{} {}", color_key(key), color_value(value))
-                    } else {
-                        format!("{}: {}", color_key(key), color_value(value))
-                    }
-                })
-                .collect()
-        })
-        .collect();
+    let formatted_sections: Vec<Vec<String>> = sections.iter().map(format_lines).collect();
 
     // ---step 2: Calculate the maximum content width across all sections ---
     // Need to consider both titles and formatted content lines
@@ -219,7 +570,7 @@ pub fn build_sections_lines(sections: &[Section], target_width: Option<usize>) -
         .zip(formatted_sections.iter())
         .flat_map(|(section, formatted_lines)| {
             // Include title width and all content line widths
-            std::iter::once(section.title.chars().count())
+            std::iter::once(visible_len(&section.title))
                 .chain(formatted_lines.iter().map(|line| visible_len(line)))
         })
         .max()
@@ -228,20 +579,17 @@ pub fn build_sections_lines(sections: &[Section], target_width: Option<usize>) -
     // Use target width if larger, otherwise use calculated width
     let unified_box_width = target_width.unwrap_or(max_content_width).max(max_content_width);
 
-    // === STEP 3: Build boxes for each section and combine ===
-    let mut result = Vec::new();
-    for (section_index, section) in sections.iter().enumerate() {
-        let section_box = build_box(
-            &formatted_sections[section_index],
+    // === STEP 3: Build boxes for each section, one after the other ===
+    for (section, formatted_lines) in sections.iter().zip(&formatted_sections) {
+        push_box(
+            output,
+            formatted_lines,
             Some(&section.title),
             Some(unified_box_width),
             None,
             false, // Left-aligned content
         );
-        result.extend(section_box);
     }
-
-    result
 }
 
 // Calculate the maximum visible width of ASCII art lines.
@@ -250,49 +598,174 @@ fn art_width(art: &[String]) -> usize {
     art.iter().map(|line| visible_len(line)).max().unwrap_or(0)
 }
 
-// Render two boxes side-by-side (art on left, sections on right).
+// Render the art box and the sections boxes side-by-side (art on left, sections on right).
+// Both are built into their own buffer first, then their rows are interleaved into `output`.
 //
 // Handles cases where boxes have different heights by padding the shorter one.
-fn render_side_by_side(art_box: &[String], sections_box: &[String], output: &mut String) {
-    let total_row_count = art_box.len().max(sections_box.len());
+// Returns the row and column the art starts at.
+fn render_side_by_side(
+    art: &[String],
+    sections: &[Section],
+    output: &mut String,
+) -> (usize, usize) {
+    let mut sections_box = String::new();
+    push_sections(&mut sections_box, sections, None);
+    let sections_height = sections_box.lines().count();
+    let mut art_box = String::new();
+    push_box(&mut art_box, art, None, None, Some(sections_height), true);
 
-    // Pre-compute padding for when art_box runs out of lines
-    let art_box_visual_width = art_box.first().map(|first_line| visible_len(first_line)).unwrap_or(0);
-    let art_padding_spaces = " ".repeat(art_box_visual_width);
+    // Padding for when art_box runs out of lines
+    let art_box_visual_width = art_box.lines().next().map_or(0, visible_len);
+    output.reserve(art_box.len() + sections_box.len() + sections_height * 2);
 
     // Build each row: [art_line or padding] [space] [section_line]
-    for row_index in 0..total_row_count {
+    let mut art_rows = art_box.lines();
+    let mut section_rows = sections_box.lines();
+    loop {
+        let (art_row, section_row) = (art_rows.next(), section_rows.next());
+        if art_row.is_none() && section_row.is_none() {
+            break;
+        }
+
         // Left side: art box (or padding if we've run out of art lines)
-        if row_index < art_box.len() {
-            output.push_str(&art_box[row_index]);
-        } else {
-            output.push_str(&art_padding_spaces);
+        match art_row {
+            Some(row) => output.push_str(row),
+            None => push_spaces(output, art_box_visual_width),
         }
 
         // Gap between boxes
         output.push(' ');
 
         // Right side: sections box
-        if row_index < sections_box.len() {
-            output.push_str(&sections_box[row_index]);
+        if let Some(row) = section_row {
+            output.push_str(row);
         }
 
         output.push('\n');
     }
+
+    // push_box splits the extra height between above and below the art, "│ " is left of it
+    let art_box_height = sections_height.max(art.len() + 2);
+    (1 + (art_box_height - art.len() - 2) / 2, 2)
 }
 
-// Render two boxes stacked vertically (art on top, sections below)
-fn render_stacked(art_box: &[String], sections_box: &[String], output: &mut String) {
-    // Art box first (on top)
-    for line in art_box {
-        output.push_str(line);
-        output.push('\n');
+// One row on the sections side of a unified box
+enum InfoRow {
+    // Section title, centered
+    Header(String),
+    Line(String),
+    // Rule between two sections, meets the divider and the right border
+    Rule,
+}
+
+// unified_box: the art and the sections inside one border, split by a vertical divider.
+// ╭────┬─────╮
+// │ art│ Core│   section titles are headers inside the box,
+// │    ├─────┤   a rule from the divider to the right border separates the sections
+// ╰────┴─────╯   the art is centered both ways in its column
+// Returns the row and column the art starts at.
+fn render_unified(art: &[String], sections: &[Section], output: &mut String) -> (usize, usize) {
+    let mut rows = Vec::new();
+    for (index, section) in sections.iter().enumerate() {
+        if index > 0 {
+            rows.push(InfoRow::Rule);
+        }
+        rows.push(InfoRow::Header(section.title.clone()));
+        rows.extend(format_lines(section).into_iter().map(InfoRow::Line));
     }
-    // Sections box below
-    for line in sections_box {
-        output.push_str(line);
+    let info_width = rows
+        .iter()
+        .map(|row| match row {
+            InfoRow::Header(text) | InfoRow::Line(text) => visible_len(text),
+            InfoRow::Rule => 0,
+        })
+        .max()
+        .unwrap_or(0);
+    let art_width = art_width(art);
+    let height = rows.len().max(art.len());
+    let art_top = (height - art.len()) / 2;
+
+    let border = &palette().border;
+    let art_rule = BOX_HORIZONTAL.repeat(art_width + 2);
+    let info_rule = BOX_HORIZONTAL.repeat(info_width + 2);
+    let vertical = color_border(BOX_VERTICAL);
+    output.push_str(&format!(
+        "{border}{BOX_TOP_LEFT}{art_rule}{BOX_TEE_DOWN}{info_rule}{BOX_TOP_RIGHT}{RESET}\n"
+    ));
+
+    for row_index in 0..height {
+        // Art column, centered like build_box does
+        let art_line = row_index.checked_sub(art_top).and_then(|i| art.get(i));
+        let art_line = art_line.map_or("", String::as_str);
+        let art_padding = art_width - visible_len(art_line);
+        let art_left = art_padding / 2;
+        output.push_str(&vertical);
+        output.push(' ');
+        push_spaces(output, art_left);
+        output.push_str(art_line);
+        push_spaces(output, art_padding - art_left);
+        output.push(' ');
+
+        let (text, centered) = match rows.get(row_index) {
+            Some(InfoRow::Rule) => {
+                output.push_str(&format!(
+                    "{border}{BOX_TEE_RIGHT}{info_rule}{BOX_TEE_LEFT}{RESET}\n"
+                ));
+                continue;
+            }
+            Some(InfoRow::Header(title)) => (Cow::Owned(color_title(title)), true),
+            Some(InfoRow::Line(line)) => (Cow::Borrowed(line.as_str()), false),
+            None => (Cow::Borrowed(""), false),
+        };
+        let padding = info_width - visible_len(&text);
+        let left = if centered { padding / 2 } else { 0 };
+        output.push_str(&vertical);
+        output.push(' ');
+        push_spaces(output, left);
+        output.push_str(&text);
+        push_spaces(output, padding - left);
+        output.push(' ');
+        output.push_str(&vertical);
         output.push('\n');
     }
+
+    output.push_str(&format!(
+        "{border}{BOX_BOTTOM_LEFT}{art_rule}{BOX_TEE_UP}{info_rule}{BOX_BOTTOM_RIGHT}{RESET}\n"
+    ));
+    (1 + art_top, 2)
+}
+
+// Render the art box with the sections boxes under it, all `width` wide inside.
+// Returns the row and column the art starts at.
+fn render_stacked(
+    art: &[String],
+    sections: &[Section],
+    width: usize,
+    output: &mut String,
+) -> (usize, usize) {
+    push_box(output, art, None, Some(width), None, true);
+    push_sections(output, sections, Some(width));
+    (1, 2 + (width - art_width(art)) / 2)
+}
+
+// Indent every line by the left margin and add blank lines above and below.
+pub fn apply_margins(output: &str, margins: Margins) -> String {
+    let indent = " ".repeat(margins.left);
+    let mut result = String::with_capacity(output.len() + margins.top + margins.bottom);
+
+    for _ in 0..margins.top {
+        result.push('\n');
+    }
+    for line in output.lines() {
+        result.push_str(&indent);
+        result.push_str(line);
+        result.push('\n');
+    }
+    for _ in 0..margins.bottom {
+        result.push('\n');
+    }
+
+    result
 }
 
 // Draw ASCII art and system info sections with adaptive layout.
@@ -304,25 +777,74 @@ fn render_stacked(art_box: &[String], sections_box: &[String], output: &mut Stri
 // 4. Smol art stacked (if terminal is tall enough but not wide neough)
 // 5. Narrow art stacked (default stacked layout)
 // 6. Sections only (if terminal is too small for any art)
+//
+// With unified_box the side-by-side layouts draw art and sections in one border, stacked ones
+// keep their own boxes.
+//
+// Margins (and the optional header and footer lines) are taken out of the terminal size before picking
+// a layout, then applied to the result.
 pub fn draw_layout(
-    wide_art: &[String],
-    medium_art: &[String],
-    narrow_art: &[String],
+    art: Option<&ArtSet>,
+    sections: &[Section],
+    header: Option<&str>,
+    footer: Option<&str>,
+    margins: Margins,
+) -> String {
+    draw_layout_placed(art, sections, header, footer, margins).0
+}
+
+// draw_layout that also says where the art went, None when the layout has no art
+pub fn draw_layout_placed(
+    art: Option<&ArtSet>,
+    sections: &[Section],
+    header: Option<&str>,
+    footer: Option<&str>,
+    margins: Margins,
+) -> (String, Option<ArtPlacement>) {
+    let terminal_size = get_terminal_size()
+        .map(|(cols, rows)| (cols as usize, rows as usize))
+        .unwrap_or((80, 24)); // Fallback to standard 80x24 terminal
+    draw_layout_sized(art, sections, header, footer, margins, terminal_size)
+}
+
+// draw_layout_placed for a given terminal size (columns, rows), what the golden tests render with
+pub fn draw_layout_sized(
+    art: Option<&ArtSet>,
     sections: &[Section],
-    smol_art: Option<&[String]>,
+    header: Option<&str>,
+    footer: Option<&str>,
+    margins: Margins,
+    terminal_size: (usize, usize),
+) -> (String, Option<ArtPlacement>) {
+    layout(art, sections, header, footer, margins, terminal_size, unified_box())
+}
+
+// draw_layout_sized with unified_box on whatever the config said, for the golden tests
+#[cfg(all(test, feature = "art"))]
+pub fn draw_unified_sized(
+    art: Option<&ArtSet>,
+    sections: &[Section],
+    margins: Margins,
+    terminal_size: (usize, usize),
 ) -> String {
-    // ---step 1: Calculate all art widths ---
-    let wide_art_width = art_width(wide_art);
-    let medium_art_width = art_width(medium_art);
-    let narrow_art_width = art_width(narrow_art);
-    let smol_art_width = smol_art.map(art_width).unwrap_or(0);
+    layout(art, sections, None, None, margins, terminal_size, true).0
+}
 
-    // ---step 2: Calculate sections width ---
+fn layout(
+    art: Option<&ArtSet>,
+    sections: &[Section],
+    header: Option<&str>,
+    footer: Option<&str>,
+    margins: Margins,
+    (terminal_width, terminal_height): (usize, usize),
+    unified: bool,
+) -> (String, Option<ArtPlacement>) {
+    // ---step 1: Calculate sections width ---
     // Each line is "Key: Value", so width = key_len + 2 (": ") + value_len
     let sections_content_width = sections
         .iter()
         .flat_map(|section| {
-            std::iter::once(section.title.chars().count())
+            std::iter::once(visible_len(&section.title))
                 .chain(section.lines.iter().map(|(key, value)| {
                     visible_len(key) + 2 + visible_len(value)
                 }))
@@ -330,67 +852,443 @@ pub fn draw_layout(
         .max()
         .unwrap_or(0);
 
-    // ---step 3: Calculate total widths for side-by-side layouts ---
-    // Box width = content + 4 (2 for borders, 2 for internal margins)
-    // Side-by-side = art_box + 1 (gap) + sections_box
-    let sections_box_width = sections_content_width + 4;
-    let wide_side_by_side_width = wide_art_width + 4 + 1 + sections_box_width;
-    let smol_side_by_side_width = smol_art_width + 4 + 1 + sections_box_width;
-    let medium_side_by_side_width = medium_art_width + 4 + 1 + sections_box_width;
-
-    // ---step 4: Get terminal dimensions ---
-    let (terminal_width, terminal_height) = get_terminal_size()
-        .map(|(cols, rows)| (cols as usize, rows as usize))
-        .unwrap_or((80, 24)); // Fallback to standard 80x24 terminal
+    // ---step 2: Terminal dimensions, minus the space margins take up ---
+    let terminal_width = terminal_width.saturating_sub(margins.left);
+    let terminal_height = terminal_height.saturating_sub(margins.top + margins.bottom);
+    // The header (greeting) takes a line above everything, the footer one below
+    let terminal_height = terminal_height
+        .saturating_sub(header.map_or(0, |_| 1))
+        .saturating_sub(footer.map_or(0, |_| 1));
 
-    // ---step 5: Calculate heights for stacked layouts ---
+    // ---step 3: Sections box size ---
+    // Box width = content + 4 (2 for borders, 2 for internal margins)
     // Sections height = sum of (content lines + 2 borders) for each section
+    let sections_box_width = sections_content_width + 4;
     let sections_total_height: usize = sections
         .iter()
         .map(|section| section.lines.len() + 2)
         .sum();
-    let narrow_art_box_height = narrow_art.len() + 2;
 
-    // ---step 6: Select layout based on terminal size ---
+    // ---step 4: Fit checks ---
+    // Side-by-side = art_box + 1 (gap) + sections_box, stacked = art_box height + sections height.
+    // Art is rendered lazily, so rule out layouts that can't fit even with empty art first,
+    // that way a tiny terminal goes straight to sections-only without rendering anything.
+    let side_by_side_possible = terminal_width >= 4 + 1 + sections_box_width;
+    let stacked_possible = terminal_height >= sections_total_height + 2;
+    let fits_side_by_side =
+        |art: &[String]| terminal_width >= art_width(art) + 4 + 1 + sections_box_width;
+    let fits_stacked = |art: &[String]| terminal_height >= sections_total_height + art.len() + 2;
+
+    // ---step 5: Select layout based on terminal size ---
     let mut output = String::new();
+    if let Some(header) = header {
+        output.push_str(header);
+        output.push('\n');
+    }
+    let layout_start = output.len();
 
-    if terminal_width >= wide_side_by_side_width {
+    // The art's size and where it starts, relative to the layout
+    let mut placed: Option<(ArtSize, (usize, usize))> = None;
+    if let Some(art) = art
+        && side_by_side_possible
+        && fits_side_by_side(art.wide())
+    {
         // layout 1: Wide art side-by-side 
-        let sections_box = build_sections_lines(sections, None);
-        let art_box = build_box(wide_art, None, None, Some(sections_box.len()), true);
-        render_side_by_side(&art_box, &sections_box, &mut output);
-    } else if smol_art.is_some() && terminal_width >= smol_side_by_side_width {
+        let start = if unified {
+            render_unified(art.wide(), sections, &mut output)
+        } else {
+            render_side_by_side(art.wide(), sections, &mut output)
+        };
+        placed = Some((ArtSize::Wide, start));
+    } else if let Some(art) = art
+        && side_by_side_possible
+        && let Some(smol_art) = art.smol()
+        && fits_side_by_side(smol_art)
+    {
         // layout 2: Smol art side-by-side 
-        let smol_art_lines = smol_art.unwrap();
-        let sections_box = build_sections_lines(sections, None);
-        let art_box = build_box(smol_art_lines, None, None, Some(sections_box.len()), true);
-        render_side_by_side(&art_box, &sections_box, &mut output);
-    } else if terminal_width >= medium_side_by_side_width {
+        let start = if unified {
+            render_unified(smol_art, sections, &mut output)
+        } else {
+            render_side_by_side(smol_art, sections, &mut output)
+        };
+        placed = Some((ArtSize::Smol, start));
+    } else if let Some(art) = art
+        && side_by_side_possible
+        && fits_side_by_side(art.medium())
+    {
         // layuot 3: Medium art side-by-side
-        let sections_box = build_sections_lines(sections, None);
-        let art_box = build_box(medium_art, None, None, Some(sections_box.len()), true);
-        render_side_by_side(&art_box, &sections_box, &mut output);
-    } else if smol_art.is_some() && terminal_height >= sections_total_height + smol_art.unwrap().len() + 2 {
+        let start = if unified {
+            render_unified(art.medium(), sections, &mut output)
+        } else {
+            render_side_by_side(art.medium(), sections, &mut output)
+        };
+        placed = Some((ArtSize::Medium, start));
+    } else if let Some(art) = art
+        && stacked_possible
+        && let Some(smol_art) = art.smol()
+        && fits_stacked(smol_art)
+    {
         // layout 4: Smol art stacked 
-        let smol_art_lines = smol_art.unwrap();
-        let stacked_width = smol_art_width.max(sections_content_width);
-        let art_box = build_box(smol_art_lines, None, Some(stacked_width), None, true);
-        let sections_box = build_sections_lines(sections, Some(stacked_width));
-        render_stacked(&art_box, &sections_box, &mut output);
-    } else if terminal_height >= sections_total_height + narrow_art_box_height {
+        let stacked_width = art_width(smol_art).max(sections_content_width);
+        let start = render_stacked(smol_art, sections, stacked_width, &mut output);
+        placed = Some((ArtSize::Smol, start));
+    } else if let Some(art) = art
+        && stacked_possible
+        && fits_stacked(art.narrow())
+    {
         // layout 5: Narrow art stacked 
-        let stacked_width = narrow_art_width.max(sections_content_width);
-        let art_box = build_box(narrow_art, None, Some(stacked_width), None, true);
-        let sections_box = build_sections_lines(sections, Some(stacked_width));
-        render_stacked(&art_box, &sections_box, &mut output);
+        let stacked_width = art_width(art.narrow()).max(sections_content_width);
+        let start = render_stacked(art.narrow(), sections, stacked_width, &mut output);
+        placed = Some((ArtSize::Narrow, start));
+    } else {
+        // layout 6: Sections only (also whenever there's no art at all)
+        push_sections(&mut output, sections, None);
+    }
+
+    if let Some(footer) = footer {
+        let layout_width = output[layout_start..].lines().map(visible_len).max().unwrap_or(0);
+        output.push_str(&footer_line(footer, layout_width, terminal_width));
+        output.push('\n');
+    }
+
+    // Below the top margin and the header, right of the left margin
+    let placement = placed.map(|(size, (row, column))| ArtPlacement {
+        size,
+        row: margins.top + usize::from(header.is_some()) + row,
+        column: margins.left + column,
+    });
+    (apply_margins(&output, margins), placement)
+}
+
+// Width of the --badge box, borders included, whatever the terminal size
+const BADGE_WIDTH: usize = 60;
+
+// What the badge shows, by section line key
+const BADGE_KEYS: &[&str] = &["OS", "Kernel", "WM", "Shell"];
+
+// --badge: OS, kernel, WM and shell centered in one small box, for README screenshots and
+// exports. Always BADGE_WIDTH wide, longer lines are cut short with an ellipsis.
+pub fn draw_badge(sections: &[Section], margins: Margins) -> String {
+    let inner_width = BADGE_WIDTH - 4;
+    let lines: Vec<String> = BADGE_KEYS
+        .iter()
+        .filter_map(|wanted| {
+            sections.iter().flat_map(|section| &section.lines).find(|(key, _)| key == wanted)
+        })
+        .map(|(key, value)| {
+            let line = format!("{}: {}", color_key(key), color_value(value));
+            truncate_visible(&line, inner_width)
+        })
+        .collect();
+
+    let mut output = String::new();
+    push_box(&mut output, &lines, None, Some(inner_width), None, true);
+    apply_margins(&output, margins)
+}
+
+// The footer centered under a layout `layout_width` columns wide (art included), in the
+// border color. Wider than the layout it starts at the left edge, wider than the terminal
+// it's cut short with an ellipsis.
+pub fn footer_line(footer: &str, layout_width: usize, terminal_width: usize) -> String {
+    let footer = if visible_len(footer) > terminal_width {
+        format!("{}…", truncate_to_width(footer, terminal_width.saturating_sub(1)))
     } else {
-        // layout 6: Sections only
-        let sections_box = build_sections_lines(sections, None);
-        for line in &sections_box {
-            output.push_str(line);
-            output.push('\n');
+        footer.to_string()
+    };
+    // Centered in what's visible when the layout itself runs past the terminal edge
+    let padding = layout_width.min(terminal_width).saturating_sub(visible_len(&footer)) / 2;
+    format!("{}{}", " ".repeat(padding), color_border(&footer))
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    const ORDER: &[&str] = &["cpu", "gpu", "memory", "battery", "displays"];
+
+    // What the collectors came back with, one line per module that had something
+    fn results<'a>(ids: &[&'a str]) -> HashMap<&'a str, Vec<(String, String)>> {
+        ids.iter().map(|&id| (id, vec![(id.to_string(), format!("{} value", id))])).collect()
+    }
+
+    fn keys(lines: Vec<(String, String)>) -> Vec<String> {
+        lines.into_iter().map(|(key, _)| key).collect()
+    }
+
+    fn order(names: &[&str]) -> Vec<String> {
+        names.iter().map(|name| name.to_string()).collect()
+    }
+
+    #[test]
+    fn default_order_without_config() {
+        let lines = ordered_lines(ORDER, None, results(&["memory", "cpu", "gpu"]));
+        assert_eq!(keys(lines), ["cpu", "gpu", "memory"]);
+    }
+
+    #[test]
+    fn configured_names_first_then_the_rest() {
+        // Displays above memory, which used to be impossible for the dynamic entries
+        let configured = order(&["displays", "memory"]);
+        let all = results(&["cpu", "gpu", "memory", "battery", "displays"]);
+        let lines = ordered_lines(ORDER, Some(&configured), all);
+        assert_eq!(keys(lines), ["displays", "memory", "cpu", "gpu", "battery"]);
+    }
+
+    #[test]
+    fn modules_without_output_are_skipped() {
+        // No battery, no displays: their spots in the order just disappear
+        let configured = order(&["battery", "displays", "cpu"]);
+        let lines = ordered_lines(ORDER, Some(&configured), results(&["cpu", "memory"]));
+        assert_eq!(keys(lines), ["cpu", "memory"]);
+    }
+
+    #[test]
+    fn duplicate_and_unknown_names() {
+        let configured = order(&["memory", "cpuu", "memory", "cpu", "battery"]);
+        let lines = ordered_lines(ORDER, Some(&configured), results(&["cpu", "memory", "gpu"]));
+        assert_eq!(keys(lines), ["memory", "cpu", "gpu"]);
+    }
+
+    #[test]
+    fn a_module_can_have_several_lines() {
+        let displays = vec![
+            ("Displays".to_string(), String::new()),
+            ("├─".to_string(), "DP-1".to_string()),
+            ("╰─".to_string(), "HDMI-A-1".to_string()),
+        ];
+        let mut all = results(&["cpu"]);
+        all.insert("displays", displays);
+        let lines = ordered_lines(ORDER, Some(&order(&["displays"])), all);
+        assert_eq!(keys(lines), ["Displays", "├─", "╰─", "cpu"]);
+    }
+
+    // What helpers::hyperlink wraps a value in, with ST or BEL ending the OSC 8 sequences
+    fn link_st(text: &str, url: &str) -> String {
+        format!("\x1b]8;;{}\x1b\\{}\x1b]8;;\x1b\\", url, text)
+    }
+
+    fn link_bel(text: &str, url: &str) -> String {
+        format!("\x1b]8;;{}\x07{}\x1b]8;;\x07", url, text)
+    }
+
+    #[test]
+    fn hyperlinks_take_no_columns() {
+        let url = "https://archlinux.org/?q=a;b[m]";
+        assert_eq!(visible_len(&link_st("Arch Linux", url)), 10);
+        assert_eq!(visible_len(&link_bel("Arch Linux", url)), 10);
+        // Colored inside the link, and a link next to plain text
+        assert_eq!(visible_len(&link_st(&color_value("Hyprland"), url)), 8);
+        assert_eq!(visible_len(&format!("{} (Wayland)", link_bel("sway", url))), 14);
+    }
+
+    #[test]
+    fn hyperlinked_values_line_up_in_a_box() {
+        let os = link_st(&color_value("Arch Linux"), "https://a.org");
+        let lines = vec![
+            format!("{}: {}", color_key("OS"), os),
+            format!("{}: {}", color_key("WM"), link_bel("sway", "https://swaywm.org")),
+            format!("{}: {}", color_key("Kernel"), color_value("6.9.1")),
+        ];
+        let rows = build_box(&lines, Some("Core"), None, None, false);
+        let width = visible_len(&rows[0]);
+        // Sized by "OS: Arch Linux", the link escapes don't stretch the box
+        assert_eq!(width, 14 + 4);
+        for row in &rows {
+            assert_eq!(visible_len(row), width, "{:?}", row);
+        }
+        assert!(rows[1].contains("\x1b]8;;https://a.org\x1b\\"));
+        assert_eq!(strip_escapes(&rows[2]), "│ WM: sway       │");
+    }
+
+    // Each visible char with the SGR color it's drawn in, so outputs that only differ in
+    // where the color spans start and end compare equal
+    fn colored_cells(row: &str) -> Vec<(char, Option<String>)> {
+        let mut cells = Vec::new();
+        let mut color = None;
+        let mut chars = row.chars();
+        while let Some(c) = chars.next() {
+            if c != '\x1b' {
+                cells.push((c, color.clone()));
+                continue;
+            }
+            let sequence: String = chars.by_ref().take_while(|&c| c != 'm').collect();
+            color = match sequence.trim_start_matches('[') {
+                "39" | "0" | "" => None,
+                params => Some(params.to_string()),
+            };
+        }
+        cells
+    }
+
+    #[test]
+    fn merged_border_spans_draw_the_same_box() {
+        let section = Section::new(
+            "Core",
+            vec![("OS".into(), "Arch Linux".into()), ("Kernel".into(), "6.9.1".into())],
+        );
+        let lines = format_lines(&section);
+        let after = build_box(&lines, Some("Core"), Some(30), None, false);
+
+        // The box the way it was drawn before, every border piece in its own span
+        let vertical = color_border(BOX_VERTICAL);
+        let mut before = vec![format!(
+            "{}{} {} {}{}",
+            color_border(BOX_TOP_LEFT),
+            color_border(&BOX_HORIZONTAL.repeat(13)),
+            color_title("Core"),
+            color_border(&BOX_HORIZONTAL.repeat(13)),
+            color_border(BOX_TOP_RIGHT)
+        )];
+        for line in &lines {
+            let padding = " ".repeat(30 - visible_len(line));
+            before.push(format!("{vertical} {line}{padding} {vertical}"));
+        }
+        before.push(format!(
+            "{}{}{}",
+            color_border(BOX_BOTTOM_LEFT),
+            color_border(&BOX_HORIZONTAL.repeat(32)),
+            color_border(BOX_BOTTOM_RIGHT)
+        ));
+
+        assert_eq!(after.len(), before.len());
+        for (after, before) in after.iter().zip(&before) {
+            assert_eq!(colored_cells(after), colored_cells(before), "{:?}", after);
+            assert!(after.len() <= before.len(), "{:?} grew", after);
+        }
+        // The borders are where the spans got merged
+        assert!(after[0].len() < before[0].len());
+        assert!(after[3].len() < before[3].len());
+    }
+
+    #[test]
+    fn character_widths() {
+        for c in ['a', 'ж', 'é', '\u{f303}', '─'] {
+            assert_eq!(char_width(c), 1, "{:?}", c);
+        }
+        for c in ['東', 'あ', '한', 'Ａ', '🦀', '🚀', '⚡'] {
+            assert_eq!(char_width(c), 2, "{:?}", c);
+        }
+        // Combining acute, Cyrillic titlo, ZWJ, variation selector, RLM
+        for c in ['\u{301}', '\u{483}', '\u{200d}', '\u{fe0f}', '\u{200f}'] {
+            assert_eq!(char_width(c), 0, "{:?}", c);
+        }
+        assert_eq!(visible_len("Jose\u{301} 東京 🦀"), 4 + 1 + 4 + 1 + 2);
+    }
+
+    #[test]
+    fn truncation_keeps_escapes_and_wide_chars_whole() {
+        // Never more than the width, a wide char that doesn't fit goes entirely
+        assert_eq!(truncate_visible("東京都庁", 4), format!("東…{}", RESET));
+        assert_eq!(truncate_visible("東京都庁", 5), format!("東京…{}", RESET));
+        assert_eq!(truncate_to_width("東京", 3), "東");
+        assert_eq!(truncate_to_width("e\u{301}x", 1), "e\u{301}");
+        // The color codes come through in one piece
+        let red = "\x1b[38;5;196mabcdef\x1b[0m";
+        assert_eq!(truncate_visible(red, 3), format!("\x1b[38;5;196mab…{}", RESET));
+        let link = link_st("Arch Linux", "https://archlinux.org");
+        let cut = truncate_visible(&link, 5);
+        assert!(cut.starts_with("\x1b]8;;https://archlinux.org\x1b\\Arch"), "{:?}", cut);
+        assert_eq!(strip_escapes(&cut), "Arch…");
+        for width in 0..12 {
+            assert!(visible_len(&truncate_visible("ab東c🦀d", width)) <= width.max(1));
+        }
+    }
+
+    #[test]
+    fn non_latin_titles_line_up() {
+        let lines = vec![
+            format!("{}: {}", color_key("Host"), color_value("сервер-01")),
+            format!("{}: {}", color_key("User"), color_value("José\u{301}")),
+            format!("{}: {}", color_key("City"), color_value("東京 🗼")),
+        ];
+        for title in ["Сервер", "東京のマシン", "Jose\u{301}'s laptop", "שרת"] {
+            let rows = build_box(&lines, Some(title), None, None, false);
+            let width = visible_len(&rows[0]);
+            for row in &rows {
+                assert_eq!(visible_len(row), width, "{}: {:?}", title, strip_escapes(row));
+            }
+            assert!(strip_escapes(&rows[0]).contains(title));
+        }
+
+        // A wide title sizes the box just like an ASCII one of the same columns
+        let wide = build_box(&["ab".to_string()], Some("東京東京"), None, None, false);
+        let ascii = build_box(&["ab".to_string()], Some("ABCDEFGH"), None, None, false);
+        let wide: Vec<String> = wide.iter().map(|row| strip_escapes(row)).collect();
+        let ascii: Vec<String> = ascii.iter().map(|row| strip_escapes(row)).collect();
+        assert_eq!(wide[0], ascii[0].replace("ABCDEFGH", "東京東京"));
+        assert_eq!(wide[1..], ascii[1..]);
+    }
+
+    // Visible text of `chunks` walked one after another, the state carried across like a
+    // stream read in pieces
+    fn visible_in_chunks(chunks: &[&str]) -> String {
+        let mut state = EscapeState::Text;
+        let mut visible = String::new();
+        for c in chunks.iter().flat_map(|chunk| chunk.chars()) {
+            let (next, shown) = state.step(c);
+            state = next;
+            if shown {
+                visible.push(c);
+            }
+        }
+        visible
+    }
+
+    #[test]
+    fn sequences_split_across_chunks() {
+        let text = "\x1b[38;2;120;200;120mkitty\x1b[0m \x1b]8;;https://a.org\x1b\\link\x1b]8;;\x07";
+        // Every split point, including inside the CSI parameters and the ST
+        for (i, _) in text.char_indices() {
+            assert_eq!(visible_in_chunks(&[&text[..i], &text[i..]]), "kitty link", "at {}", i);
         }
+        assert_eq!(visible_in_chunks(&["\x1b", "[", "2", "K", "ok"]), "ok");
+    }
+
+    #[test]
+    fn escape_edge_cases() {
+        // OSC 8 ended by BEL and by ST, and by the C1 ST
+        assert_eq!(strip_escapes(&link_bel("bel", "https://a.org")), "bel");
+        assert_eq!(strip_escapes(&link_st("st", "https://a.org")), "st");
+        assert_eq!(strip_escapes("\x1b]8;;https://a.org\u{9c}c1\x1b]8;;\u{9c}"), "c1");
+        // Adjacent SGRs, cursor codes and the C1 CSI
+        assert_eq!(visible_len("\x1b[1m\x1b[31mab\x1b[2Kc\u{9b}0md"), 4);
+        // A lone ESC at the end takes nothing, and neither does one before a single char
+        assert_eq!(visible_len("abc\x1b"), 3);
+        assert_eq!(strip_escapes("a\x1bcb"), "ab");
+        assert_eq!(truncate_visible("abc\x1b", 3), "abc\x1b");
+    }
+
+    // Small xorshift so the random strings are the same every run
+    fn next(seed: &mut u64) -> usize {
+        *seed ^= *seed << 13;
+        *seed ^= *seed >> 7;
+        *seed ^= *seed << 17;
+        *seed as usize
     }
 
-    output
+    #[test]
+    fn random_escape_laden_strings() {
+        let text = ["a", "Arch", " ", "東京", "e\u{301}", "🦀", "[m", "]8;;", "\\"];
+        let escapes = [
+            "\x1b[0m", "\x1b[38;2;1;2;3m", "\x1b[2K", "\x1b[10;20H", "\x1b[?25l", "\u{9b}31m",
+            "\x1b]8;;https://a.org\x1b\\", "\x1b]8;;\x07", "\x1b]0;title\u{9c}", "\x1bc", "\x1b7",
+        ];
+        let mut seed = 0x5eed;
+        for _ in 0..500 {
+            // The reference: what the string is built from, minus the escapes
+            let (mut string, mut visible) = (String::new(), String::new());
+            for _ in 0..next(&mut seed) % 12 {
+                if next(&mut seed).is_multiple_of(2) {
+                    let piece = text[next(&mut seed) % text.len()];
+                    string.push_str(piece);
+                    visible.push_str(piece);
+                } else {
+                    string.push_str(escapes[next(&mut seed) % escapes.len()]);
+                }
+            }
+            assert_eq!(strip_escapes(&string), visible, "{:?}", string);
+            let width: usize = visible.chars().map(char_width).sum();
+            assert_eq!(visible_len(&string), width, "{:?}", string);
+        }
+    }
 }
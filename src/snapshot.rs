@@ -0,0 +1,270 @@
+// Snapshots for --snapshot and --diff.
+// A snapshot is the collected sections as plain text, one "Section<TAB>Name<TAB>Value" line
+// per entry, so two runs can be compared later (after a kernel update, a new rice...).
+
+use std::collections::HashMap;
+use std::fs;
+use std::path::Path;
+
+use crate::renderer::{Section, strip_escapes};
+
+// First line of a snapshot file, anything else isn't read
+const HEADER: &str = "# slowfetch snapshot";
+
+// Lines that change from one run to the next, --diff skips them unless diff_include lists them
+const VOLATILE_KEYS: &[&str] =
+    &["Uptime", "Load", "Memory", "Storage", "Battery", "UPS", "Session", "Activity", "Today"];
+
+// One compared value: section title, entry name, plain value
+pub type Entry = (String, String, String);
+
+// Flatten the sections into entries. Tree lines ("├─ Font: JetBrains Mono") are named after
+// their parent ("Terminal › Font"), keys that show up more than once (GPUs, displays) get
+// numbered so every entry has a name of its own.
+pub fn entries(sections: &[Section]) -> Vec<Entry> {
+    let mut entries: Vec<Entry> = Vec::new();
+    for section in sections {
+        let mut parent = String::new();
+        let mut seen: HashMap<String, usize> = HashMap::new();
+        for (key, value) in &section.lines {
+            let value = strip_escapes(value).trim().replace(['\t', '\n'], " ");
+            let (name, value) = if key.starts_with('├') || key.starts_with('╰') {
+                match value.split_once(": ") {
+                    Some((label, child)) => (format!("{} › {}", parent, label), child.to_string()),
+                    None => (format!("{} ›", parent), value),
+                }
+            } else {
+                parent = strip_escapes(key);
+                (parent.clone(), value)
+            };
+            let count = seen.entry(name.clone()).or_insert(0);
+            *count += 1;
+            let name = if *count == 1 { name } else { format!("{} #{}", name, count) };
+            entries.push((section.title.clone(), name, value));
+        }
+    }
+    entries
+}
+
+// Write the sections to a snapshot file
+pub fn write(sections: &[Section], path: &Path) -> Result<(), String> {
+    let mut content = format!("{}\n", HEADER);
+    for (title, name, value) in entries(sections) {
+        content.push_str(&format!("{}\t{}\t{}\n", title, name, value));
+    }
+    fs::write(path, content).map_err(|e| format!("Can't write snapshot {}: {}", path.display(), e))
+}
+
+// Sections back from a snapshot, for --simulate-data. Values come back plain, the colors and
+// bars' escape codes aren't in the file.
+pub fn sections(entries: &[Entry]) -> Vec<Section> {
+    let mut sections: Vec<Section> = Vec::new();
+    for (title, name, value) in entries {
+        if sections.last().is_none_or(|section| section.title != *title) {
+            sections.push(Section::new(title, Vec::new()));
+        }
+        let Some(section) = sections.last_mut() else {
+            continue;
+        };
+        // "GPU #2" was only numbered for the snapshot
+        let name = match name.rsplit_once(" #") {
+            Some((name, count)) if count.parse::<usize>().is_ok() => name,
+            _ => name,
+        };
+        let line = match name.split_once(" ›") {
+            // A tree line, branches are fixed up below
+            Some((_, "")) => ("╰─".to_string(), value.clone()),
+            Some((_, label)) => ("╰─".to_string(), format!("{}: {}", label.trim(), value)),
+            None => (name.to_string(), value.clone()),
+        };
+        section.lines.push(line);
+    }
+    // Every tree line but the last of its run branches on
+    for section in &mut sections {
+        for i in 1..section.lines.len() {
+            if section.lines[i].0 == "╰─" && section.lines[i - 1].0 == "╰─" {
+                section.lines[i - 1].0 = "├─".to_string();
+            }
+        }
+    }
+    sections
+}
+
+// Read a snapshot file back
+pub fn read(path: &Path) -> Result<Vec<Entry>, String> {
+    let content = fs::read_to_string(path)
+        .map_err(|e| format!("Can't read snapshot {}: {}", path.display(), e))?;
+    let mut lines = content.lines();
+    if lines.next() != Some(HEADER) {
+        return Err(format!("{} isn't a slowfetch snapshot", path.display()));
+    }
+    Ok(lines
+        .filter_map(|line| {
+            let mut fields = line.splitn(3, '\t');
+            let title = fields.next()?.to_string();
+            let name = fields.next()?.to_string();
+            Some((title, name, fields.next()?.to_string()))
+        })
+        .collect())
+}
+
+// The entries that changed between a snapshot and the current sections, as a "Changes"
+// section with "old → new" values. Entries that came or went show "–" on the missing side.
+// None when nothing changed.
+pub fn diff(old: &[Entry], sections: &[Section], include: &[String]) -> Option<Section> {
+    let new = entries(sections);
+    let compared = |name: &str| {
+        let key = name.split([' ', '›']).next().unwrap_or(name);
+        !VOLATILE_KEYS.contains(&key) || include.iter().any(|k| k.eq_ignore_ascii_case(key))
+    };
+    let find = |entries: &[Entry], title: &str, name: &str| {
+        entries
+            .iter()
+            .find(|(t, n, _)| t == title && n == name)
+            .map(|(_, _, value)| value.clone())
+    };
+
+    let mut lines = Vec::new();
+    for (title, name, value) in new.iter().filter(|(_, name, _)| compared(name)) {
+        match find(old, title, name) {
+            Some(old_value) if old_value == *value => {}
+            Some(old_value) => lines.push((name.clone(), format!("{} → {}", old_value, value))),
+            None => lines.push((name.clone(), format!("– → {}", value))),
+        }
+    }
+    for (title, name, value) in old.iter().filter(|(_, name, _)| compared(name)) {
+        if find(&new, title, name).is_none() {
+            lines.push((name.clone(), format!("{} → –", value)));
+        }
+    }
+    (!lines.is_empty()).then(|| Section::new("Changes", lines))
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::testutil::TempDir;
+
+    fn section(title: &str, lines: &[(&str, &str)]) -> Section {
+        let lines = lines.iter().map(|(key, value)| (key.to_string(), value.to_string())).collect();
+        Section::new(title, lines)
+    }
+
+    fn entry(title: &str, name: &str, value: &str) -> Entry {
+        (title.to_string(), name.to_string(), value.to_string())
+    }
+
+    // Two GPUs, a tree under Terminal, a colored value and a bar
+    fn sections() -> Vec<Section> {
+        vec![
+            section("Core", &[("OS", "Arch Linux"), ("Kernel", "6.11.2-arch1-1")]),
+            section(
+                "Hardware",
+                &[
+                    ("GPU", "AMD Radeon 780M"),
+                    ("GPU", "NVIDIA GeForce RTX 4060"),
+                    ("Memory", "\x1b[38;2;1;2;3m██░░\x1b[39m 8.1 GiB / 32 GiB"),
+                ],
+            ),
+            section(
+                "Userspace",
+                &[
+                    ("Terminal", "\x1b[1mkitty\x1b[0m 0.39"),
+                    ("├─", "Font: JetBrains Mono"),
+                    ("╰─", "Theme: Catppuccin"),
+                    ("Packages", "1450 (pacman)"),
+                ],
+            ),
+        ]
+    }
+
+    #[test]
+    fn snapshots_round_trip() {
+        let dir = TempDir::new();
+        let path = dir.join("before.snap");
+        write(&sections(), &path).unwrap();
+        let read_back = read(&path).unwrap();
+        assert_eq!(read_back, entries(&sections()));
+        assert!(read_back.contains(&entry("Hardware", "GPU #2", "NVIDIA GeForce RTX 4060")));
+        assert!(read_back.contains(&entry("Userspace", "Terminal › Font", "JetBrains Mono")));
+
+        // Back to sections, the same lines minus the escapes
+        let plain: Vec<Section> = sections()
+            .into_iter()
+            .map(|section| {
+                let lines =
+                    section.lines.into_iter().map(|(key, value)| (key, strip_escapes(&value)));
+                Section::new(&section.title, lines.collect())
+            })
+            .collect();
+        let rebuilt = super::sections(&read_back);
+        assert_eq!(rebuilt.len(), plain.len());
+        for (rebuilt, plain) in rebuilt.iter().zip(&plain) {
+            assert_eq!(rebuilt.title, plain.title);
+            assert_eq!(rebuilt.lines, plain.lines);
+        }
+        // Writing the rebuilt sections gives the same file
+        let again = dir.join("again.snap");
+        write(&rebuilt, &again).unwrap();
+        assert_eq!(fs::read_to_string(&again).unwrap(), fs::read_to_string(&path).unwrap());
+    }
+
+    #[test]
+    fn only_snapshots_are_read() {
+        let dir = TempDir::new();
+        let notes = dir.write("notes.txt", "Core\tOS\tArch Linux\n");
+        assert_eq!(read(&notes), Err(format!("{} isn't a slowfetch snapshot", notes.display())));
+        assert!(read(&dir.join("missing.snap")).unwrap_err().starts_with("Can't read snapshot"));
+        // Lines without all three fields are skipped
+        let short = dir.write("short.snap", format!("{}\nCore\tOS\nCore\tKernel\t6.12\n", HEADER));
+        assert_eq!(read(&short).unwrap(), [entry("Core", "Kernel", "6.12")]);
+    }
+
+    #[test]
+    fn diff_between_two_snapshots() {
+        let old = vec![
+            entry("Core", "Kernel", "6.11.2-arch1-1"),
+            entry("Core", "Uptime", "2 days, 3 hours"),
+            entry("Hardware", "GPU", "AMD Radeon 780M"),
+            entry("Hardware", "GPU #2", "NVIDIA GeForce RTX 3060"),
+            entry("Hardware", "Memory", "7.9 GiB / 32 GiB"),
+            entry("Userspace", "Terminal › Font", "Fira Code"),
+            entry("Userspace", "Packages", "1450 (pacman)"),
+            entry("Userspace", "Editor", "nvim"),
+        ];
+        let mut new = sections();
+        new[0].lines.push(("Uptime".into(), "5 minutes".into()));
+        new[1].lines.pop();
+        new[1].lines.push(("Memory".into(), "2.0 GiB / 32 GiB".into()));
+        new[0].lines[1].1 = "6.12.4-arch1-1".into();
+        new[2].lines[3].1 = "1456 (pacman)".into();
+
+        let changes = diff(&old, &new, &[]).unwrap();
+        assert_eq!(changes.title, "Changes");
+        let lines: Vec<(&str, &str)> =
+            changes.lines.iter().map(|(key, value)| (key.as_str(), value.as_str())).collect();
+        assert_eq!(
+            lines,
+            [
+                ("OS", "– → Arch Linux"),
+                ("Kernel", "6.11.2-arch1-1 → 6.12.4-arch1-1"),
+                ("GPU #2", "NVIDIA GeForce RTX 3060 → NVIDIA GeForce RTX 4060"),
+                ("Terminal", "– → kitty 0.39"),
+                ("Terminal › Font", "Fira Code → JetBrains Mono"),
+                ("Terminal › Theme", "– → Catppuccin"),
+                ("Packages", "1450 (pacman) → 1456 (pacman)"),
+                // Gone since the snapshot, after everything that's there now
+                ("Editor", "nvim → –"),
+            ]
+        );
+
+        // Volatile lines only when asked for
+        let changes = diff(&old, &new, &["uptime".to_string()]).unwrap();
+        let uptime = changes.lines.iter().find(|(key, _)| key == "Uptime");
+        assert_eq!(uptime.map(|(_, value)| value.as_str()), Some("2 days, 3 hours → 5 minutes"));
+        assert!(!changes.lines.iter().any(|(key, _)| key == "Memory"));
+
+        // Nothing changed, nothing to show
+        assert!(diff(&entries(&new), &new, &[]).is_none());
+    }
+}
@@ -0,0 +1,216 @@
+// Tiny template engine for per-module value formats.
+// Templates look like "{model} @ {ghz}GHz" or "{value|split:-|first}".
+// Placeholders are looked up in a per-module field list, filters run left to right.
+
+use std::collections::HashMap;
+
+// Named fields a module exposes to its template. "value" is always present.
+pub type Fields = Vec<(&'static str, String)>;
+
+// A placeholder value while filters are being applied.
+// split turns text into a list, first/last turn it back into text.
+enum Value {
+    Text(String),
+    List(Vec<String>, String),
+}
+
+impl Value {
+    fn map(self, f: impl Fn(&str) -> String) -> Value {
+        match self {
+            Value::Text(text) => Value::Text(f(&text)),
+            Value::List(items, sep) => Value::List(items.iter().map(|s| f(s)).collect(), sep),
+        }
+    }
+
+    fn into_string(self) -> String {
+        match self {
+            Value::Text(text) => text,
+            Value::List(items, sep) => items.join(&sep),
+        }
+    }
+}
+
+// Apply a single filter (e.g. "split:-", "first", "upper") to a value
+fn apply_filter(value: Value, filter: &str) -> Result<Value, String> {
+    let (name, arg) = match filter.split_once(':') {
+        Some((name, arg)) => (name.trim(), Some(arg)),
+        None => (filter.trim(), None),
+    };
+
+    match (name, arg) {
+        ("split", Some(sep)) if !sep.is_empty() => Ok(match value {
+            Value::Text(text) => {
+                Value::List(text.split(sep).map(str::to_string).collect(), sep.to_string())
+            }
+            list => list,
+        }),
+        ("split", _) => Err("split needs a separator, e.g. split:-".to_string()),
+        ("first", None) => Ok(match value {
+            Value::List(items, _) => Value::Text(items.into_iter().next().unwrap_or_default()),
+            text => text,
+        }),
+        ("last", None) => Ok(match value {
+            Value::List(items, _) => Value::Text(items.into_iter().last().unwrap_or_default()),
+            text => text,
+        }),
+        ("upper", None) => Ok(value.map(str::to_uppercase)),
+        ("lower", None) => Ok(value.map(str::to_lowercase)),
+        ("trim", None) => Ok(value.map(|s| s.trim().to_string())),
+        _ => Err(format!("unknown filter '{}'", filter)),
+    }
+}
+
+// Expand one placeholder body like "value|split:-|first"
+fn expand_placeholder(body: &str, fields: &Fields) -> Result<String, String> {
+    let mut parts = body.split('|');
+    let name = parts.next().unwrap_or("").trim();
+
+    let field = fields
+        .iter()
+        .find(|(key, _)| *key == name)
+        .map(|(_, value)| value.clone())
+        .ok_or_else(|| {
+            let known: Vec<&str> = fields.iter().map(|(key, _)| *key).collect();
+            format!("unknown field '{}' (available: {})", name, known.join(", "))
+        })?;
+
+    let mut value = Value::Text(field);
+    for filter in parts {
+        value = apply_filter(value, filter)?;
+    }
+    Ok(value.into_string())
+}
+
+// Render a template against a field list.
+// "{{" and "}}" are literal braces.
+pub fn render(template: &str, fields: &Fields) -> Result<String, String> {
+    let mut output = String::with_capacity(template.len());
+    let mut chars = template.chars().peekable();
+
+    while let Some(c) = chars.next() {
+        match c {
+            '{' if chars.peek() == Some(&'{') => {
+                chars.next();
+                output.push('{');
+            }
+            '}' if chars.peek() == Some(&'}') => {
+                chars.next();
+                output.push('}');
+            }
+            '{' => {
+                let mut body = String::new();
+                let mut closed = false;
+                for next in chars.by_ref() {
+                    if next == '}' {
+                        closed = true;
+                        break;
+                    }
+                    body.push(next);
+                }
+                if !closed {
+                    return Err("unclosed '{'".to_string());
+                }
+                output.push_str(&expand_placeholder(&body, fields)?);
+            }
+            '}' => return Err("unmatched '}'".to_string()),
+            _ => output.push(c),
+        }
+    }
+
+    Ok(output)
+}
+
+// Format a module value with its configured template, if there is one.
+// `fields` is only called when a template exists, so modules can compute extra fields lazily.
+// Unknown values are left alone, invalid templates warn and fall back to the default output.
+pub fn format_module(
+    formats: &HashMap<String, String>,
+    module: &str,
+    value: String,
+    fields: impl FnOnce(&str) -> Fields,
+) -> String {
+    let Some(template) = formats.get(module) else {
+        return value;
+    };
+    if value == "unknown" || value.is_empty() {
+        return value;
+    }
+
+    let mut all_fields: Fields = vec![("value", value.clone())];
+    all_fields.extend(fields(&value));
+
+    match render(template, &all_fields) {
+        Ok(formatted) => formatted,
+        Err(e) => {
+            eprintln!("Warning: Invalid format for {}: {}", module, e);
+            value
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn fields() -> Fields {
+        vec![
+            ("value", "6.12.10-arch1-1".to_string()),
+            ("model", "Ryzen 7 7840U".to_string()),
+            ("ghz", "5.1".to_string()),
+        ]
+    }
+
+    #[test]
+    fn fields_are_substituted() {
+        let rendered = render("{model} @ {ghz}GHz", &fields());
+        assert_eq!(rendered.unwrap(), "Ryzen 7 7840U @ 5.1GHz");
+        assert_eq!(render("no placeholders", &fields()).unwrap(), "no placeholders");
+    }
+
+    #[test]
+    fn filters_run_left_to_right() {
+        assert_eq!(render("{value|split:-|first}", &fields()).unwrap(), "6.12.10");
+        assert_eq!(render("{value|split:-|last|upper}", &fields()).unwrap(), "1");
+        assert_eq!(render("{model|split: |last|lower}", &fields()).unwrap(), "7840u");
+        // A list that's never picked from is joined back with its separator
+        assert_eq!(render("{value|split:.|upper}", &fields()).unwrap(), "6.12.10-ARCH1-1");
+    }
+
+    #[test]
+    fn unknown_fields_and_filters_are_errors() {
+        let error = render("{vendor}", &fields()).unwrap_err();
+        assert!(error.contains("unknown field 'vendor'"), "{}", error);
+        assert!(error.contains("value, model, ghz"), "{}", error);
+        assert!(render("{value|reverse}", &fields()).unwrap_err().contains("unknown filter"));
+        assert!(render("{value|split}", &fields()).is_err());
+        assert!(render("{value|first:x}", &fields()).is_err());
+    }
+
+    #[test]
+    fn unclosed_and_unmatched_braces_are_errors() {
+        assert_eq!(render("{model", &fields()).unwrap_err(), "unclosed '{'");
+        assert_eq!(render("{model} @ {ghz", &fields()).unwrap_err(), "unclosed '{'");
+        assert_eq!(render("model}", &fields()).unwrap_err(), "unmatched '}'");
+    }
+
+    #[test]
+    fn doubled_braces_are_literal() {
+        assert_eq!(render("{{model}}", &fields()).unwrap(), "{model}");
+        assert_eq!(render("{{{ghz}}}", &fields()).unwrap(), "{5.1}");
+        assert_eq!(render("}}{{", &fields()).unwrap(), "}{");
+    }
+
+    #[test]
+    fn format_module_falls_back_on_a_bad_template() {
+        let formats = HashMap::from([
+            ("kernel".to_string(), "{value|split:-|first}".to_string()),
+            ("cpu".to_string(), "{nope}".to_string()),
+        ]);
+        let kernel = format_module(&formats, "kernel", "6.12.10-arch1-1".into(), |_| vec![]);
+        assert_eq!(kernel, "6.12.10");
+        assert_eq!(format_module(&formats, "cpu", "Ryzen".into(), |_| vec![]), "Ryzen");
+        // No template, and unknown values, are left alone
+        assert_eq!(format_module(&formats, "gpu", "Radeon".into(), |_| vec![]), "Radeon");
+        assert_eq!(format_module(&formats, "kernel", "unknown".into(), |_| vec![]), "unknown");
+    }
+}
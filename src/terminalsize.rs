@@ -2,6 +2,7 @@
 // a lot of this code is from stack overflow.
 
 use std::os::unix::io::AsRawFd;
+use std::sync::OnceLock;
 
 //tells Rust to use c-compatible memory layout
 //need this because im interfacing with the kernel's ioctl syscall
@@ -13,56 +14,114 @@ struct Winsize {
     ws_ypixel: u16,
 }
 
-// TIOCGWINSZ constant for Linux
-const TIOCGWINSZ: u64 = 0x5413;
+// --simulate-width/--simulate-height, whichever is left out comes from the real terminal
+static SIMULATED: OnceLock<(Option<u16>, Option<u16>)> = OnceLock::new();
+
+pub fn set_simulated_size(cols: Option<u16>, rows: Option<u16>) {
+    let _ = SIMULATED.set((cols, rows));
+}
 
 // Get the terminal size as, columns and rows
 // Returns None if the terminal size cannot be determined.
 pub fn get_terminal_size() -> Option<(u16, u16)> {
-    use std::io::stdout;
-
-    unsafe {
-        //uhoh
-        let mut ws = std::mem::MaybeUninit::<Winsize>::zeroed();
-        let fd = stdout().as_raw_fd();
-
-        #[cfg(target_os = "linux")]
-        {
-            let result = libc_ioctl(fd, TIOCGWINSZ, ws.as_mut_ptr());
-            if result == 0 {
-                let ws = ws.assume_init();
-                if ws.ws_col > 0 && ws.ws_row > 0 {
-                    return Some((ws.ws_col, ws.ws_row));
-                }
-            }
+    let real = || {
+        terminal_winsize()
+            .map(|ws| (ws.ws_col, ws.ws_row))
+            // Fallback to environment variables
+            .or_else(get_size_from_env)
+    };
+    match SIMULATED.get().copied() {
+        Some((Some(cols), Some(rows))) => Some((cols, rows)),
+        // Half simulated, the other half falls back to 80x24 like the renderer does
+        Some((cols, rows)) if cols.is_some() || rows.is_some() => {
+            let (real_cols, real_rows) = real().unwrap_or((80, 24));
+            Some((cols.unwrap_or(real_cols), rows.unwrap_or(real_rows)))
         }
+        _ => real(),
     }
+}
 
-    // Fallback to environment variables
-    get_size_from_env()
+// Size of one cell in pixels (width, height). None when the terminal doesn't fill in its
+// pixel size, plenty leave ws_xpixel/ws_ypixel at 0.
+#[cfg(feature = "image")]
+pub fn get_cell_size() -> Option<(u16, u16)> {
+    let ws = terminal_winsize()?;
+    let size = (ws.ws_xpixel / ws.ws_col, ws.ws_ypixel / ws.ws_row);
+    (size.0 > 0 && size.1 > 0).then_some(size)
 }
 
-#[cfg(target_os = "linux")]
-unsafe fn libc_ioctl(fd: i32, request: u64, winsize: *mut Winsize) -> i32 {
-    // Direct syscall
-    let result: i64;
-    unsafe {
-        std::arch::asm!(
-            "syscall",
-            in("rax") 16, // SYS_ioctl
-            in("rdi") fd,
-            in("rsi") request,
-            in("rdx") winsize,
-            lateout("rax") result,
-            lateout("rcx") _,
-            lateout("r11") _,
-        );
+// Tries stdout, then stderr and stdin (so `slowfetch | tee fetch.txt` still sees the terminal),
+// then the controlling terminal itself.
+fn terminal_winsize() -> Option<Winsize> {
+    use std::io::{stderr, stdin, stdout};
+
+    let fds = [stdout().as_raw_fd(), stderr().as_raw_fd(), stdin().as_raw_fd()];
+    if let Some(ws) = fds.into_iter().find_map(winsize_of_fd) {
+        return Some(ws);
     }
-    result as i32
+
+    // All three redirected, ask /dev/tty directly (fails cleanly without a controlling terminal)
+    let tty = std::fs::File::open("/dev/tty").ok()?;
+    winsize_of_fd(tty.as_raw_fd())
+}
+
+// Ask the kernel for the window size of one fd
+fn winsize_of_fd(fd: i32) -> Option<Winsize> {
+    let mut ws = std::mem::MaybeUninit::<Winsize>::zeroed();
+
+    // Winsize matches struct winsize, and the kernel only writes into it
+    let result = unsafe { libc::ioctl(fd, libc::TIOCGWINSZ, ws.as_mut_ptr()) };
+    if result != 0 {
+        return None;
+    }
+
+    let ws = unsafe { ws.assume_init() };
+    (ws.ws_col > 0 && ws.ws_row > 0).then_some(ws)
 }
 
 fn get_size_from_env() -> Option<(u16, u16)> {
-    let cols = std::env::var("COLUMNS").ok()?.parse().ok()?;
-    let rows = std::env::var("LINES").ok()?.parse().ok()?;
+    size_from_vars(std::env::var("COLUMNS").ok(), std::env::var("LINES").ok())
+}
+
+// COLUMNS and LINES as a size, both have to be set and be numbers
+fn size_from_vars(columns: Option<String>, lines: Option<String>) -> Option<(u16, u16)> {
+    let cols = columns?.parse().ok()?;
+    let rows = lines?.parse().ok()?;
     Some((cols, rows))
 }
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::testutil::TempDir;
+
+    fn vars(columns: Option<&str>, lines: Option<&str>) -> Option<(u16, u16)> {
+        size_from_vars(columns.map(str::to_string), lines.map(str::to_string))
+    }
+
+    #[test]
+    fn env_size_needs_both_numbers() {
+        assert_eq!(vars(Some("200"), Some("50")), Some((200, 50)));
+        assert_eq!(vars(Some("200"), None), None);
+        assert_eq!(vars(None, Some("50")), None);
+        assert_eq!(vars(Some("wide"), Some("50")), None);
+        assert_eq!(vars(Some("-80"), Some("24")), None);
+        // Doesn't fit the u16 the kernel uses either
+        assert_eq!(vars(Some("70000"), Some("24")), None);
+    }
+
+    #[test]
+    fn winsize_matches_the_kernel_struct() {
+        assert_eq!(std::mem::size_of::<Winsize>(), std::mem::size_of::<libc::winsize>());
+        assert_eq!(std::mem::align_of::<Winsize>(), std::mem::align_of::<libc::winsize>());
+    }
+
+    #[test]
+    fn fds_that_arent_terminals_have_no_size() {
+        // What stdout is under `slowfetch > fetch.txt`, the lookup moves on to the next fd
+        let dir = TempDir::new();
+        let file = std::fs::File::open(dir.write("fetch.txt", "")).unwrap();
+        assert!(winsize_of_fd(file.as_raw_fd()).is_none());
+        assert!(winsize_of_fd(-1).is_none());
+    }
+}
@@ -0,0 +1,105 @@
+// Ask the terminal something and read its reply (background color, image acknowledgments).
+// The request goes out with a primary device attributes request (DA1) behind it. Every
+// terminal answers DA1, so once that reply shows up anything else it was going to say is in.
+// The raw mode guard is shared with --animate, which waits on key presses.
+
+use std::fs::{File, OpenOptions};
+use std::io::{IsTerminal, Read, Write};
+use std::mem::MaybeUninit;
+use std::os::unix::io::AsRawFd;
+use std::time::{Duration, Instant};
+
+// The terminal with echo and line buffering off, so replies and key presses don't land on
+// screen and reads return right away. Put back the way it was when dropped, on early returns
+// and panics too.
+pub struct RawMode {
+    tty: File,
+    original: libc::termios,
+}
+
+impl RawMode {
+    // None without a terminal on both stdin and stdout, don't put the terminal in raw mode under
+    // someone else's pipe. With `keys` Ctrl-C and friends come in as keys instead of signals,
+    // so a Ctrl-C goes through the caller (and this guard) rather than killing us mid-draw.
+    pub fn enter(keys: bool) -> Option<RawMode> {
+        if !std::io::stdin().is_terminal() || !std::io::stdout().is_terminal() {
+            return None;
+        }
+        let tty = OpenOptions::new().read(true).write(true).open("/dev/tty").ok()?;
+        let fd = tty.as_raw_fd();
+
+        let mut original = MaybeUninit::<libc::termios>::uninit();
+        if unsafe { libc::tcgetattr(fd, original.as_mut_ptr()) } != 0 {
+            return None;
+        }
+        let original = unsafe { original.assume_init() };
+        let mut raw = original;
+        raw.c_lflag &= !(libc::ICANON | libc::ECHO);
+        if keys {
+            raw.c_lflag &= !libc::ISIG;
+        }
+        raw.c_cc[libc::VMIN] = 0;
+        raw.c_cc[libc::VTIME] = 0;
+        if unsafe { libc::tcsetattr(fd, libc::TCSANOW, &raw) } != 0 {
+            return None;
+        }
+        Some(RawMode { tty, original })
+    }
+
+    pub fn tty(&mut self) -> &mut File {
+        &mut self.tty
+    }
+
+    // Wait up to `timeout` for something to read, true when it came
+    pub fn wait_for_input(&self, timeout: Duration) -> bool {
+        let fd = self.tty.as_raw_fd();
+        let mut poll_fd = libc::pollfd { fd, events: libc::POLLIN, revents: 0 };
+        unsafe { libc::poll(&mut poll_fd, 1, timeout.as_millis() as libc::c_int) > 0 }
+    }
+}
+
+impl Drop for RawMode {
+    fn drop(&mut self) {
+        unsafe {
+            libc::tcsetattr(self.tty.as_raw_fd(), libc::TCSANOW, &self.original);
+        }
+    }
+}
+
+// Send `request` and return everything the terminal sent back before its DA1 reply,
+// or None without a terminal to ask
+pub fn query(request: &[u8], timeout: Duration) -> Option<Vec<u8>> {
+    let mut raw = RawMode::enter(false)?;
+    let tty = raw.tty();
+    tty.write_all(request)
+        .and_then(|_| tty.write_all(b"\x1b[c"))
+        .and_then(|_| tty.flush())
+        .ok()?;
+    Some(read_reply(&mut raw, timeout))
+}
+
+// Read until the DA1 reply ("\x1b[?...c") shows up or time runs out
+fn read_reply(raw: &mut RawMode, timeout: Duration) -> Vec<u8> {
+    let deadline = Instant::now() + timeout;
+    let mut reply = Vec::new();
+    let mut buffer = [0u8; 256];
+
+    while reply.len() < 4096 {
+        let remaining = deadline.saturating_duration_since(Instant::now());
+        if remaining.is_zero() || !raw.wait_for_input(remaining) {
+            break;
+        }
+        match raw.tty().read(&mut buffer) {
+            Ok(0) | Err(_) => break,
+            Ok(read) => reply.extend_from_slice(&buffer[..read]),
+        }
+
+        // DA1 always comes last
+        if let Some(start) = memchr::memmem::find(&reply, b"\x1b[?")
+            && reply[start..].contains(&b'c')
+        {
+            break;
+        }
+    }
+    reply
+}
@@ -0,0 +1,126 @@
+// Test helpers: a throwaway directory to build fixture trees in (fake /sys, /proc, config and
+// cache dirs), so the readers can be pointed at it instead of the real system, and a count
+// of the allocations a piece of code makes.
+// Only built for cargo test.
+
+use std::alloc::{GlobalAlloc, Layout, System};
+use std::cell::Cell;
+use std::fs;
+use std::path::{Path, PathBuf};
+use std::sync::atomic::{AtomicUsize, Ordering};
+
+// Tests run in parallel, every TempDir gets its own number
+static NEXT: AtomicUsize = AtomicUsize::new(0);
+
+// A fresh directory under the system temp dir, removed with everything in it on drop
+pub struct TempDir(PathBuf);
+
+impl TempDir {
+    pub fn new() -> TempDir {
+        let number = NEXT.fetch_add(1, Ordering::Relaxed);
+        let name = format!("slowfetch-test-{}-{}", std::process::id(), number);
+        let path = std::env::temp_dir().join(name);
+        // Left over from a killed run with the same pid
+        let _ = fs::remove_dir_all(&path);
+        fs::create_dir_all(&path).unwrap();
+        TempDir(path)
+    }
+
+    pub fn path(&self) -> &Path {
+        &self.0
+    }
+
+    pub fn join(&self, relative: &str) -> PathBuf {
+        self.0.join(relative)
+    }
+
+    // Write a file, creating the directories above it, and return its path
+    pub fn write(&self, relative: &str, content: impl AsRef<[u8]>) -> PathBuf {
+        let path = self.join(relative);
+        if let Some(parent) = path.parent() {
+            fs::create_dir_all(parent).unwrap();
+        }
+        fs::write(&path, content).unwrap();
+        path
+    }
+
+    // A symlink at `relative` pointing at `target` (taken as is, relative targets stay relative)
+    pub fn symlink(&self, relative: &str, target: impl AsRef<Path>) -> PathBuf {
+        let path = self.join(relative);
+        if let Some(parent) = path.parent() {
+            fs::create_dir_all(parent).unwrap();
+        }
+        std::os::unix::fs::symlink(target, &path).unwrap();
+        path
+    }
+}
+
+impl Drop for TempDir {
+    fn drop(&mut self) {
+        let _ = fs::remove_dir_all(&self.0);
+    }
+}
+
+// The system allocator, counting allocations per thread so a test can measure its own code
+// while the others run in parallel
+struct CountingAlloc;
+
+thread_local! {
+    static ALLOCATIONS: Cell<usize> = const { Cell::new(0) };
+}
+
+fn count_allocation() {
+    // Not there any more while the thread shuts down
+    let _ = ALLOCATIONS.try_with(|count| count.set(count.get() + 1));
+}
+
+unsafe impl GlobalAlloc for CountingAlloc {
+    unsafe fn alloc(&self, layout: Layout) -> *mut u8 {
+        count_allocation();
+        unsafe { System.alloc(layout) }
+    }
+
+    unsafe fn dealloc(&self, ptr: *mut u8, layout: Layout) {
+        unsafe { System.dealloc(ptr, layout) }
+    }
+
+    unsafe fn realloc(&self, ptr: *mut u8, layout: Layout, new_size: usize) -> *mut u8 {
+        count_allocation();
+        unsafe { System.realloc(ptr, layout, new_size) }
+    }
+}
+
+#[global_allocator]
+static COUNTING_ALLOC: CountingAlloc = CountingAlloc;
+
+// Run `f` and count the allocations (and reallocations) it made on this thread
+pub fn allocations<T>(f: impl FnOnce() -> T) -> (T, usize) {
+    let before = ALLOCATIONS.with(Cell::get);
+    let result = f();
+    (result, ALLOCATIONS.with(Cell::get) - before)
+}
+
+#[test]
+fn allocations_are_counted() {
+    let (_, none) = allocations(|| 1 + 1);
+    assert_eq!(none, 0);
+    let (text, some) = allocations(|| "x".repeat(100));
+    assert_eq!(text.len(), 100);
+    assert_eq!(some, 1);
+}
+
+#[test]
+fn temp_dir_builds_a_tree_and_cleans_up() {
+    let dir = TempDir::new();
+    let other = TempDir::new();
+    assert_ne!(dir.path(), other.path());
+
+    let file = dir.write("sys/class/power_supply/BAT0/capacity", "64\n");
+    assert_eq!(fs::read_to_string(&file).unwrap(), "64\n");
+    let link = dir.symlink("run/current-system", "/nix/store/abc-nixos-system");
+    assert_eq!(fs::read_link(link).unwrap(), Path::new("/nix/store/abc-nixos-system"));
+
+    let path = dir.path().to_path_buf();
+    drop(dir);
+    assert!(!path.exists());
+}
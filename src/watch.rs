@@ -0,0 +1,210 @@
+// Watch mode for Slowfetch.
+// Redraws the layout in place every few seconds, only re-reading the dynamic modules.
+// Static values (OS, CPU, GPU names...) are collected once before the loop starts.
+
+use std::collections::HashMap;
+use std::io::Write;
+use std::sync::atomic::{AtomicBool, Ordering};
+use std::thread;
+use std::time::{Duration, Instant, SystemTime, UNIX_EPOCH};
+
+use crate::collect::{self, StaticInfo};
+use crate::configloader::Config;
+use crate::renderer::{Section, visible_len};
+
+// Set by the SIGINT handler, checked between sleeps
+static INTERRUPTED: AtomicBool = AtomicBool::new(false);
+
+extern "C" fn on_sigint(_: libc::c_int) {
+    INTERRUPTED.store(true, Ordering::SeqCst);
+}
+
+// Run until Ctrl-C. `draw` gets the sections, the greeting and whether this is the first frame
+// (image mode only transmits the image then), and prints the frame with the cursor already homed.
+pub fn run(
+    config: &Config,
+    statics: &StaticInfo,
+    first_sections: Vec<Section>,
+    interval: Duration,
+    mut draw: impl FnMut(&[Section], Option<&str>, bool),
+) {
+    unsafe {
+        libc::signal(libc::SIGINT, on_sigint as *const () as libc::sighandler_t);
+    }
+
+    // Hide the cursor and start from a clean screen
+    print!("\x1b[?25l\x1b[2J\x1b[H");
+
+    // Widest value seen per line, so boxes only ever grow and the layout doesn't jump around
+    let mut widths: HashMap<LineId, usize> = HashMap::new();
+    let mut sections = first_sections;
+    let mut first_frame = true;
+
+    while !INTERRUPTED.load(Ordering::SeqCst) {
+        pin_widths(&mut sections, &mut widths);
+        let greeting = collect::greeting(config);
+
+        print!("\x1b[H");
+        draw(&sections, greeting.as_deref(), first_frame);
+        let _ = std::io::stdout().flush();
+        first_frame = false;
+
+        if !sleep_unless_interrupted(until_next_tick(wall_clock(), interval)) {
+            break;
+        }
+        sections = collect::build_sections(config, statics, collect::collect_dynamic(config));
+    }
+
+    // Leave the last frame on screen and give the cursor back
+    println!("\x1b[?25h");
+    let _ = std::io::stdout().flush();
+}
+
+// Which line a width belongs to: the section title, the key of the entry the line is part of
+// and how many lines below that entry's first one it is. Tree lines ("├─", "╰─") all have the
+// same few keys, in every section, so they go by the entry above them.
+type LineId = (String, String, usize);
+
+// Pad every value to the widest one seen for its line
+fn pin_widths(sections: &mut [Section], widths: &mut HashMap<LineId, usize>) {
+    for section in sections {
+        let mut entry = String::new();
+        let mut below = 0;
+        for (key, value) in &mut section.lines {
+            if key.starts_with(['├', '╰']) {
+                below += 1;
+            } else {
+                entry.clone_from(key);
+                below = 0;
+            }
+            let len = visible_len(value);
+            let id = (section.title.clone(), entry.clone(), below);
+            let width = widths.entry(id).or_insert(len);
+            *width = (*width).max(len);
+            value.push_str(&" ".repeat(*width - len));
+        }
+    }
+}
+
+fn wall_clock() -> Duration {
+    SystemTime::now().duration_since(UNIX_EPOCH).unwrap_or_default()
+}
+
+// How long to sleep so the next frame lands on a whole second, `interval` after the second
+// this one was drawn in. Ticks don't drift by however long collecting took, and the seconds on
+// the uptime line step along with the clock.
+fn until_next_tick(now: Duration, interval: Duration) -> Duration {
+    let next_tick = Duration::from_secs(now.as_secs()) + interval;
+    next_tick.saturating_sub(now)
+}
+
+// Sleep in short steps so Ctrl-C doesn't wait out the whole interval.
+// Returns false if interrupted.
+fn sleep_unless_interrupted(interval: Duration) -> bool {
+    let deadline = Instant::now() + interval;
+    while Instant::now() < deadline {
+        if INTERRUPTED.load(Ordering::SeqCst) {
+            return false;
+        }
+        thread::sleep(Duration::from_millis(100).min(deadline - Instant::now()));
+    }
+    !INTERRUPTED.load(Ordering::SeqCst)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn section(title: &str, lines: &[(&str, &str)]) -> Section {
+        let lines = lines.iter().map(|(key, value)| (key.to_string(), value.to_string())).collect();
+        Section::new(title, lines)
+    }
+
+    fn values(sections: &[Section]) -> Vec<Vec<&str>> {
+        sections
+            .iter()
+            .map(|section| section.lines.iter().map(|(_, value)| value.as_str()).collect())
+            .collect()
+    }
+
+    #[test]
+    fn widths_only_grow() {
+        let mut widths = HashMap::new();
+        let mut first = [section("Hardware", &[("Memory", "15.2GB/32GB"), ("CPU", "Ryzen")])];
+        pin_widths(&mut first, &mut widths);
+        let mut second = [section("Hardware", &[("Memory", "9.8GB/32GB"), ("CPU", "Ryzen")])];
+        pin_widths(&mut second, &mut widths);
+        assert_eq!(values(&second), [["9.8GB/32GB ", "Ryzen"]]);
+    }
+
+    #[test]
+    fn tree_lines_keep_their_own_widths() {
+        let mut widths = HashMap::new();
+        let hardware = |battery: &'static str| {
+            section(
+                "Hardware",
+                &[
+                    ("Displays", ""),
+                    ("├─", "DP-1 2560x1440 @ 165Hz"),
+                    ("╰─", "eDP-1 1920x1200 @ 60Hz"),
+                    ("Battery", battery),
+                    ("╰─", "12.4W"),
+                ],
+            )
+        };
+        let userspace = || section("Userspace", &[("Font", "Iosevka"), ("╰─", "14pt")]);
+        let mut sections = [hardware("64%"), userspace()];
+        pin_widths(&mut sections, &mut widths);
+        // The battery's and the font's "╰─" aren't padded out to the display's
+        assert_eq!(
+            values(&sections),
+            [
+                vec!["", "DP-1 2560x1440 @ 165Hz", "eDP-1 1920x1200 @ 60Hz", "64%", "12.4W"],
+                vec!["Iosevka", "14pt"],
+            ]
+        );
+
+        let mut sections = [hardware("100%"), userspace()];
+        pin_widths(&mut sections, &mut widths);
+        assert_eq!(values(&sections)[0][3..], ["100%", "12.4W"]);
+        assert_eq!(values(&sections)[1], ["Iosevka", "14pt"]);
+    }
+
+    fn at(seconds: u64, millis: u64) -> Duration {
+        Duration::from_secs(seconds) + Duration::from_millis(millis)
+    }
+
+    #[test]
+    fn ticks_land_on_whole_seconds() {
+        let second = Duration::from_secs(1);
+        // Drawn right on a tick: a whole interval to wait
+        assert_eq!(until_next_tick(at(1000, 0), second), second);
+        assert_eq!(until_next_tick(at(1000, 0), Duration::from_secs(5)), Duration::from_secs(5));
+        // Just after one, or late in the second after a slow collect: only what's left
+        assert_eq!(until_next_tick(at(1000, 1), second), Duration::from_millis(999));
+        assert_eq!(until_next_tick(at(1000, 850), second), Duration::from_millis(150));
+        let nanos = Duration::from_secs(1000) + Duration::from_nanos(1);
+        assert_eq!(until_next_tick(nanos, second), Duration::from_nanos(999_999_999));
+        assert_eq!(until_next_tick(at(1000, 400), Duration::from_secs(2)), at(1, 600));
+        // The interval is longer than the time that's gone by in this second, still counted
+        // from its start
+        assert_eq!(until_next_tick(at(1000, 999), Duration::from_secs(60)), at(59, 1));
+        // Sub-second intervals that the second has already used up don't sleep at all
+        let half = Duration::from_millis(500);
+        assert_eq!(until_next_tick(at(1000, 200), half), Duration::from_millis(300));
+        assert_eq!(until_next_tick(at(1000, 700), half), Duration::ZERO);
+    }
+
+    #[test]
+    fn frames_keep_to_the_clock() {
+        // Collecting takes a varying bit of each second, the frames still start a second apart
+        let mut now = at(5000, 0);
+        let mut frames = Vec::new();
+        for took in [120, 480, 30, 910, 0] {
+            now += Duration::from_millis(took);
+            now += until_next_tick(now, Duration::from_secs(1));
+            frames.push(now);
+        }
+        assert_eq!(frames, [5001, 5002, 5003, 5004, 5005].map(Duration::from_secs));
+    }
+}
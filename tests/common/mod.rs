@@ -0,0 +1,60 @@
+// A throwaway home for runs of the real binary: its own HOME, config and cache dirs so nothing
+// on this machine leaks in, and a plain terminal.
+
+// Each test file uses some of it
+#![allow(dead_code)]
+
+use std::fs;
+use std::path::PathBuf;
+use std::process::{Command, Output};
+use std::sync::atomic::{AtomicUsize, Ordering};
+
+// Tests run in parallel, every home gets its own number
+static NEXT: AtomicUsize = AtomicUsize::new(0);
+
+// A home with `config` as its slowfetch config, removed on drop
+pub struct Home(PathBuf);
+
+impl Home {
+    pub fn new(config: &str) -> Home {
+        let number = NEXT.fetch_add(1, Ordering::Relaxed);
+        let name = format!("slowfetch-home-{}-{}", std::process::id(), number);
+        let path = std::env::temp_dir().join(name);
+        let _ = fs::remove_dir_all(&path);
+        fs::create_dir_all(path.join(".config/slowfetch")).unwrap();
+        fs::write(path.join(".config/slowfetch/config.toml"), config).unwrap();
+        Home(path)
+    }
+
+    pub fn path(&self) -> &std::path::Path {
+        &self.0
+    }
+
+    // slowfetch run with `args` in this home
+    pub fn output(&self, args: &[&str]) -> Output {
+        Command::new(env!("CARGO_BIN_EXE_slowfetch"))
+            .args(args)
+            .current_dir(&self.0)
+            .env("HOME", &self.0)
+            .env("XDG_CONFIG_HOME", self.0.join(".config"))
+            .env("XDG_CACHE_HOME", self.0.join(".cache"))
+            .env("TERM", "xterm-256color")
+            .env_remove("KITTY_WINDOW_ID")
+            .env_remove("SLOWFETCH_PROFILE")
+            .output()
+            .unwrap()
+    }
+
+    // Exit code and stderr of slowfetch run with `args`
+    pub fn run(&self, args: &[&str]) -> (i32, String) {
+        let output = self.output(args);
+        let stderr = String::from_utf8_lossy(&output.stderr).into_owned();
+        (output.status.code().unwrap(), stderr)
+    }
+}
+
+impl Drop for Home {
+    fn drop(&mut self) {
+        let _ = fs::remove_dir_all(&self.0);
+    }
+}
@@ -0,0 +1,78 @@
+// The exit codes listed under --help, one run of the real binary per failure class.
+// Every run gets its own Home so nothing on this machine leaks in.
+
+mod common;
+
+use common::Home;
+
+#[test]
+fn ok_is_zero() {
+    let (code, stderr) = Home::new("").run(&["--quiet"]);
+    assert_eq!(code, 0, "{}", stderr);
+    let (code, _) = Home::new("").run(&["--help"]);
+    assert_eq!(code, 0);
+    let (code, stderr) = Home::new("").run(&["--quiet", "--refresh", "images"]);
+    assert_eq!(code, 0, "{}", stderr);
+}
+
+#[test]
+fn errors_are_one() {
+    let home = Home::new("");
+    let (code, stderr) = home.run(&["--no-such-flag"]);
+    assert_eq!(code, 1, "{}", stderr);
+    assert!(stderr.contains("--no-such-flag"), "{}", stderr);
+    let (code, stderr) = home.run(&["--quiet", "--refresh", "everything"]);
+    assert_eq!(code, 1, "{}", stderr);
+    let (code, stderr) = home.run(&["--quiet", "--diff", "missing.snap"]);
+    assert_eq!(code, 1, "{}", stderr);
+    assert!(stderr.starts_with("Can't read snapshot"), "{}", stderr);
+}
+
+#[test]
+fn strict_config_problems_are_two() {
+    let home = Home::new("[display]\nno_such_key = true\n");
+    let (code, stderr) = home.run(&["--quiet", "--strict"]);
+    assert_eq!(code, 2, "{}", stderr);
+    assert!(stderr.contains("Config has 1 problem(s)"), "{}", stderr);
+    // Only a warning without --strict
+    let (code, stderr) = home.run(&["--quiet"]);
+    assert_eq!(code, 0, "{}", stderr);
+}
+
+#[test]
+fn unknown_modules_are_three() {
+    let home = Home::new("[order]\ncore = [\"os\", \"no_such_module\"]\n");
+    let (code, stderr) = home.run(&["--quiet"]);
+    assert_eq!(code, 3, "{}", stderr);
+    let home = Home::new("[modules]\nno_such_module = \"show\"\n");
+    let (code, stderr) = home.run(&["--quiet"]);
+    assert_eq!(code, 3, "{}", stderr);
+}
+
+// Not a Kitty terminal, so the art is shown instead
+#[cfg(feature = "image")]
+#[test]
+fn unsupported_images_are_four() {
+    let (code, stderr) = Home::new("[display]\nimage = true\n").run(&["--quiet"]);
+    assert_eq!(code, 4, "{}", stderr);
+}
+
+// Built without image mode at all: the same exit code, and the notice only without --quiet
+#[cfg(not(feature = "image"))]
+#[test]
+fn images_built_out_are_four() {
+    let home = Home::new("[display]\nimage = true\n");
+    let (code, stderr) = home.run(&["--quiet"]);
+    assert_eq!(code, 4, "{}", stderr);
+    assert!(stderr.is_empty(), "{}", stderr);
+    let (code, stderr) = home.run(&[]);
+    assert_eq!(code, 4, "{}", stderr);
+    assert!(stderr.contains("built without image support"), "{}", stderr);
+}
+
+// Nothing that spawns a thread comes back within a millisecond
+#[test]
+fn missed_deadlines_are_five() {
+    let (code, stderr) = Home::new("[display]\nmax_fetch_ms = 1\n").run(&["--quiet"]);
+    assert_eq!(code, 5, "{}", stderr);
+}
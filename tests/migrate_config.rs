@@ -0,0 +1,33 @@
+// --migrate-config against the real rename table, which is empty so far: a config comes
+// through untouched, with no backup made, and a missing config is an error.
+
+mod common;
+
+use std::fs;
+
+use common::Home;
+
+#[test]
+fn nothing_to_migrate() {
+    let config = "[display]\n# os_art = \"debian\"\nos_art = \"arch\"  # the logo\n\n\
+                  [order]\nhardware = [\"cpu\", \"storage\"]\n\n\
+                  [profile.work.colors]\nborder = \"#FF79C6\"\n";
+    let home = Home::new(config);
+    let output = home.output(&["--migrate-config"]);
+    let stdout = String::from_utf8_lossy(&output.stdout);
+    assert!(output.status.success(), "{}", String::from_utf8_lossy(&output.stderr));
+    assert!(stdout.ends_with("config.toml already uses the current names\n"), "{}", stdout);
+
+    let dir = home.path().join(".config/slowfetch");
+    assert_eq!(fs::read_to_string(dir.join("config.toml")).unwrap(), config);
+    assert!(!dir.join("config.toml.bak").exists());
+}
+
+#[test]
+fn no_config_to_migrate() {
+    let home = Home::new("");
+    fs::remove_file(home.path().join(".config/slowfetch/config.toml")).unwrap();
+    let (code, stderr) = home.run(&["--migrate-config"]);
+    assert_eq!(code, 1, "{}", stderr);
+    assert!(stderr.contains("There's no config file to migrate"), "{}", stderr);
+}
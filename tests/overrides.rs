@@ -0,0 +1,62 @@
+// terminal, terminal_font and nerd_font from the config win over detection, checked through
+// a --snapshot of a real run: the declared values come back as they were written, and the
+// bars are drawn with nerd font glyphs or without as nerd_font says.
+
+mod common;
+
+use std::fs;
+
+use common::Home;
+
+// Nerd font progress glyphs, filled and empty middles
+const NERD_BAR: &[char] = &['\u{ee03}', '\u{ee04}'];
+
+// (entry name, value) of every line of a run's snapshot
+fn snapshot(config: &str) -> Vec<(String, String)> {
+    let home = Home::new(config);
+    let path = home.path().join("run.snap");
+    let (code, stderr) = home.run(&["--quiet", "--snapshot", path.to_str().unwrap()]);
+    assert_eq!(code, 0, "{}", stderr);
+    let content = fs::read_to_string(path).unwrap();
+    content
+        .lines()
+        .skip(1)
+        .filter_map(|line| {
+            let mut fields = line.splitn(3, '\t').skip(1);
+            Some((fields.next()?.to_string(), fields.next()?.to_string()))
+        })
+        .collect()
+}
+
+fn value<'a>(entries: &'a [(String, String)], name: &str) -> Option<&'a str> {
+    entries.iter().find(|(entry, _)| entry == name).map(|(_, value)| value.as_str())
+}
+
+#[test]
+fn declared_terminal_and_font_win() {
+    let entries = snapshot(
+        "[display]\nterminal = \"Wezterm\"\nterminal_font = \"Berkeley Mono 13\"\n\
+         nerd_font = false\n",
+    );
+    assert_eq!(value(&entries, "Terminal"), Some("Wezterm"));
+    assert_eq!(value(&entries, "Terminal Font"), Some("Berkeley Mono 13"));
+}
+
+#[test]
+fn bars_follow_nerd_font() {
+    let memory = |config: &str| value(&snapshot(config), "Memory").unwrap().to_string();
+
+    let plain = memory("[display]\nnerd_font = false\n");
+    assert!(plain.starts_with('[') && !plain.contains(NERD_BAR), "{}", plain);
+    let nerd = memory("[display]\nnerd_font = true\n");
+    assert!(nerd.contains(NERD_BAR) && !nerd.starts_with('['), "{}", nerd);
+
+    // A declared font is what nerd_font = auto goes by
+    let declared = memory("[display]\nterminal_font = \"JetBrainsMono Nerd Font 12\"\n");
+    assert!(declared.contains(NERD_BAR), "{}", declared);
+    let declared = memory("[display]\nterminal_font = \"DejaVu Sans Mono 12\"\n");
+    assert!(declared.starts_with('['), "{}", declared);
+    // and nerd_font still beats it
+    let forced = "[display]\nterminal_font = \"JetBrainsMono Nerd Font 12\"\nnerd_font = false\n";
+    assert!(memory(forced).starts_with('['));
+}
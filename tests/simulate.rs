@@ -0,0 +1,97 @@
+// The layout goldens drawn again through the binary: tests/golden/sections.snap (the golden
+// sections, kept in step by golden.rs) fed to --simulate-data at each golden's terminal size
+// with --simulate-width/--simulate-height. The output, colors stripped, has to be the same file
+// byte for byte, so the simulated size and data go through the real drawing path.
+
+mod common;
+
+use std::fs;
+use std::path::PathBuf;
+
+use common::Home;
+
+const SIZES: &[(usize, usize)] = &[(200, 50), (120, 35), (90, 30), (60, 40), (40, 15)];
+
+fn golden_dir() -> PathBuf {
+    PathBuf::from(env!("CARGO_MANIFEST_DIR")).join("tests").join("golden")
+}
+
+// The golden sections drawn at `size` in `home`, escapes stripped
+fn simulate(home: &Home, size: (usize, usize)) -> String {
+    let (width, height) = (size.0.to_string(), size.1.to_string());
+    let snapshot = golden_dir().join("sections.snap");
+    let snapshot = snapshot.to_str().unwrap();
+    let output = home.output(&[
+        "--simulate-width",
+        &width,
+        "--simulate-height",
+        &height,
+        "--simulate-data",
+        snapshot,
+    ]);
+    let stderr = String::from_utf8_lossy(&output.stderr);
+    assert!(output.status.success(), "{}", stderr);
+    strip_escapes(&String::from_utf8_lossy(&output.stdout))
+}
+
+// CSI sequences out, the only kind slowfetch colors with
+fn strip_escapes(text: &str) -> String {
+    let mut output = String::new();
+    let mut chars = text.chars();
+    while let Some(c) = chars.next() {
+        if c != '\x1b' {
+            output.push(c);
+            continue;
+        }
+        if chars.next() == Some('[') {
+            for c in chars.by_ref() {
+                if ('@'..='~').contains(&c) {
+                    break;
+                }
+            }
+        }
+    }
+    output
+}
+
+// Every size of a golden case against the binary with `config`, all mismatches in one failure
+fn check(case: &str, config: &str) {
+    let home = Home::new(config);
+    let mut failures = Vec::new();
+    for &size in SIZES {
+        let path = golden_dir().join(format!("{}_{}x{}.txt", case, size.0, size.1));
+        let expected = fs::read_to_string(&path).unwrap();
+        let output = simulate(&home, size);
+        if output != expected {
+            failures.push(format!("{}:\n{}", path.display(), output));
+        }
+    }
+    assert!(failures.is_empty(), "simulated layout differs\n\n{}", failures.join("\n\n"));
+}
+
+// With os_art off the art is the default one, as in golden.rs
+#[cfg(feature = "art")]
+#[test]
+fn default_art() {
+    check("default_art", "[display]\nos_art = false\n");
+}
+
+#[cfg(feature = "art")]
+#[test]
+fn footer_under_art() {
+    check("footer_under_art", "[display]\nos_art = false\nfooter = \"~ dotfiles ~\"\n");
+}
+
+#[cfg(not(feature = "art"))]
+#[test]
+fn sections_only() {
+    check("sections_only", "");
+}
+
+#[cfg(not(feature = "art"))]
+#[test]
+fn footer_wider_than_the_layout() {
+    let footer = "https://github.com/tui/dotfiles/tree/main/.config/slowfetch \
+                  (the one with the 40-line CPU module override and a pinned Hyprland)";
+    check("footer_wider_than_the_layout", &format!("[display]\nfooter = \"{}\"\n", footer));
+}
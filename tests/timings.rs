@@ -0,0 +1,25 @@
+// --timings on stderr: the cached modules' hits and misses next to the module times, a first
+// run missing everything and the run after it hitting what the first one cached.
+
+mod common;
+
+use common::Home;
+
+// The "<module>: <status>" lines of a --timings run for the cached modules
+fn cache_lines(home: &Home) -> Vec<String> {
+    let (code, stderr) = home.run(&["--quiet", "--timings"]);
+    assert_eq!(code, 0, "{}", stderr);
+    stderr.lines().filter(|line| line.contains(": cache ")).map(str::to_string).collect()
+}
+
+#[test]
+fn cache_status_in_timings() {
+    let home = Home::new("");
+    let first = cache_lines(&home);
+    assert!(first.iter().any(|line| line == "os: cache miss"), "{:?}", first);
+    assert!(first.iter().all(|line| line.ends_with("cache miss")), "{:?}", first);
+
+    let second = cache_lines(&home);
+    assert!(second.iter().any(|line| line.starts_with("os: cache hit (age ")), "{:?}", second);
+    assert!(!second.iter().any(|line| line.ends_with("cache miss")), "{:?}", second);
+}